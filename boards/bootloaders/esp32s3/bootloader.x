@@ -0,0 +1,39 @@
+/* rustBoot is the esp32s3's second-stage bootloader i.e. the ROM loads its segments into RAM
+ * rather than running it from flash. Its IRAM and DRAM are the ESP-IDF bootloader's, which
+ * firmware can't load segments into (see `rustBoot_hal::esp::esp32s3::MEMORY_MAP`). It runs
+ * on the ROM's stack, above its DRAM. */
+MEMORY {
+    IRAM : ORIGIN = 0x403B0000, LENGTH = 0x18000
+    DRAM : ORIGIN = 0x3FCD8000, LENGTH = 0x11700
+}
+
+ENTRY(call_start_cpu0)
+
+SECTIONS {
+    .text : ALIGN(4)
+    {
+        *(.literal .text .literal.* .text.*)
+        *(.rwtext .rwtext.*)
+    } > IRAM
+
+    .rodata : ALIGN(4)
+    {
+        *(.rodata .rodata.*)
+    } > DRAM
+
+    .data : ALIGN(4)
+    {
+        *(.data .data.*)
+    } > DRAM
+
+    .bss (NOLOAD) : ALIGN(4)
+    {
+        _bss_start = .;
+        *(.bss .bss.* COMMON)
+        . = ALIGN(4);
+        _bss_end = .;
+    } > DRAM
+}
+
+/* the ROM's functions i.e. its spiflash and cache API, from esp-hal */
+INCLUDE rom-functions.x
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+use core::ptr::{addr_of_mut, write_volatile};
+
+use rustBoot_hal::esp::esp32s3::{disable_watchdogs, FlashWriterEraser};
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+
+extern "C" {
+    static mut _bss_start: u32;
+    static mut _bss_end: u32;
+}
+
+/// This is the second-stage bootloader's entry point i.e. the ROM jumps here (on its own stack)
+/// once it has loaded rustBoot into RAM. `.bss` isn't part of the image, so it's zeroed first.
+#[no_mangle]
+pub unsafe extern "C" fn call_start_cpu0() -> ! {
+    let mut word = addr_of_mut!(_bss_start);
+    while word < addr_of_mut!(_bss_end) {
+        write_volatile(word, 0);
+        word = word.add(1);
+    }
+    main()
+}
+
+fn main() -> ! {
+    disable_watchdogs();
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    updater.rustboot_start()
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
@@ -12,6 +12,17 @@ use crate::{
 // Assembly counterpart to this file.
 global_asm!(include_str!("entry.s"));
 
+/// The largest fit-image rustBoot loads.
+#[cfg(feature = "ddr")]
+pub const MAX_ITB_SIZE: usize = 64 * 1024 * 1024;
+
+/// A statically determined region of DRAM for the image-tree (or fit-image) blob i.e. serves as
+/// the fit-image's entry point. It's only usable once DRAM is initialized (see `ddr_init`) and
+/// isn't zeroed at reset (see `link.lds`).
+#[cfg(feature = "ddr")]
+#[link_section = ".bss.dram.itb"]
+pub static mut ITB_LOAD_ADDR: [u8; MAX_ITB_SIZE] = [0u8; MAX_ITB_SIZE];
+
 /// The Rust entry of the `kernel` binary.
 ///
 /// The function is called from the assembly `_start` function.
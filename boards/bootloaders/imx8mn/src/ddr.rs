@@ -0,0 +1,22 @@
+//! The EVK's DRAM timings and DDR PHY training firmware, see `ddr.md`.
+
+use rustBoot_hal::nxp::imx8mn::bsp::ddr::{
+    DramCfgParam, DramFspMsg, DramTiming, FwType, TrainingFirmware,
+};
+
+// NXP's timings, converted to rust i.e. the `DDRC_CFG`, `DDRPHY_CFG`, `FSP_MSG` and `DDRPHY_PIE`
+// consts
+include!("../ddr/ddr4_timing.rs");
+
+pub static DRAM_TIMING: DramTiming = DramTiming {
+    ddrc_cfg: DDRC_CFG,
+    ddrphy_cfg: DDRPHY_CFG,
+    fsp_msg: FSP_MSG,
+    ddrphy_pie: DDRPHY_PIE,
+    firmware: TrainingFirmware {
+        imem_1d: include_bytes!("../ddr/ddr4_imem_1d.bin"),
+        dmem_1d: include_bytes!("../ddr/ddr4_dmem_1d.bin"),
+        imem_2d: include_bytes!("../ddr/ddr4_imem_2d.bin"),
+        dmem_2d: include_bytes!("../ddr/ddr4_dmem_2d.bin"),
+    },
+};
@@ -3,6 +3,8 @@
 #![feature(format_args_nl)]
 
 mod boot;
+#[cfg(feature = "ddr")]
+mod ddr;
 
 use rustBoot_hal::info;
 use rustBoot_hal::nxp::imx8mn::arch::cpu_core::*;
@@ -16,6 +18,11 @@ use rustBoot_hal::nxp::imx8mn::bsp::{
     },
     global, mux,
 };
+#[cfg(feature = "ddr")]
+use rustBoot_hal::nxp::imx8mn::bsp::{
+    ddr::{ddr_init, dram_check},
+    memory_map::map::dram,
+};
 use rustBoot_hal::nxp::imx8mn::{
     memory,
     exception,
@@ -64,6 +71,22 @@ fn kernel_main() -> ! {
     }
     info!("Chars written: {}", console::console().chars_written());
 
+    // init DRAM
+    #[cfg(feature = "ddr")]
+    {
+        if let Err(e) = ddr_init(&ddr::DRAM_TIMING) {
+            panic!("DDR init failed: {:?}", e);
+        }
+        if let Err(e) = dram_check(dram::END_INCLUSIVE - dram::START + 1) {
+            panic!("DRAM check failed: {:?}", e);
+        }
+        info!(
+            "DRAM: {} MiB, fit-images are loaded to {:p}",
+            (dram::END_INCLUSIVE - dram::START + 1) >> 20,
+            unsafe { boot::ITB_LOAD_ADDR.as_ptr() }
+        );
+    }
+
     // init uSDHC
     match SDHC2.init_usdhc() {
         SdResult::SdOk => info!("uSDHC driver initialized..."),
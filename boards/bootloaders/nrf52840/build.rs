@@ -8,10 +8,49 @@ fn main() {
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
     File::create(out.join("memory.x"))
         .unwrap()
-        .write_all(include_bytes!("memory.x"))
+        .write_all(memory_x().as_bytes())
+        .unwrap();
+    // generated from the board's manifest, see `cargo <board> gen layout`
+    File::create(out.join("partitions.x"))
+        .unwrap()
+        .write_all(include_bytes!("partitions.x"))
+        .unwrap();
+    File::create(out.join("stage1.x"))
+        .unwrap()
+        .write_all(include_bytes!("stage1.x"))
         .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=partitions.x");
+    println!("cargo:rerun-if-changed=stage1.x");
+    println!("cargo:rerun-if-env-changed=RUSTBOOT_STAGE1_ORIGIN");
+    println!("cargo:rerun-if-env-changed=RUSTBOOT_STAGE1_LENGTH");
+}
+
+/// Returns rustBoot's `memory.x`. rustBoot built as stage1 (see `cargo nrf52840 build stage1`)
+/// runs from the stage1 slot xtask passes, instead of from the start of flash, and is checked
+/// against the stage1 slots (`stage1.x`) rather than the boot partition.
+fn memory_x() -> String {
+    let memory_x = include_str!("memory.x");
+    let (origin, length) = match (
+        env::var("RUSTBOOT_STAGE1_ORIGIN"),
+        env::var("RUSTBOOT_STAGE1_LENGTH"),
+    ) {
+        (Ok(origin), Ok(length)) => (origin, length),
+        _ => return memory_x.to_string(),
+    };
+    memory_x
+        .lines()
+        .map(|line| match line.trim_start() {
+            flash if flash.starts_with("FLASH") => format!(
+                "  FLASH    (rx)  : ORIGIN = {}, LENGTH = {}",
+                origin, length
+            ),
+            "INCLUDE partitions.x" => "INCLUDE stage1.x".to_string(),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
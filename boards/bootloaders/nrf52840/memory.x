@@ -31,4 +31,7 @@ MEMORY
        . = ALIGN(4);
      } > RAM2
    } INSERT AFTER .bss;
-*/
\ No newline at end of file
+*/
+
+/* checks that rustBoot ends below the boot partition, see `cargo nrf52840 gen layout` */
+INCLUDE partitions.x
@@ -0,0 +1,6 @@
+/* @generated by `cargo nrf52840 gen layout` from `boards/manifests/nrf52840.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_boot_partition = 0x2f000;
+ASSERT(LOADADDR(.data) + SIZEOF(.data) <= __rustboot_boot_partition,
+       "rustBoot runs into the boot partition, see boards/manifests/nrf52840.toml");
@@ -0,0 +1,57 @@
+//! rustBoot's diagnostics console (see `rustBoot_update::console`), on the nrf52840-mdk's UART
+//! i.e. its DAPLink's virtual COM port (`P0.20` TX, `P0.19` RX, 115200 baud).
+//!
+//! The console is entered if the user button (`P1.00`, i.e. the boot pin in the board's manifest)
+//! is held at reset or if firmware wrote `CONSOLE_MAGIC` to `GPREGRET` (which survives a soft
+//! reset) before resetting.
+
+use nrf52840_hal as hal;
+
+use hal::gpio::{p0, Level};
+use hal::pac::{Peripherals, UARTE0};
+use hal::prelude::*;
+use hal::uarte::{Baudrate, Parity, Pins, Uarte};
+use rustBoot_hal::nrf::nrf52840::FlashWriterEraser;
+use rustBoot_update::console::{Console, Serial, CONSOLE_MAGIC};
+use rustBoot_update::update::boot_pin::boot_pin_held;
+use rustBoot_update::update::update_flash::FlashUpdater;
+
+struct Uart(Uarte<UARTE0>);
+
+impl Serial for Uart {
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0u8; 1];
+        let _ = self.0.read(&mut byte);
+        byte[0]
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        // EasyDMA can only read RAM i.e. bytes in flash are sent through a buffer.
+        let mut buf = [0u8; 32];
+        for chunk in bytes.chunks(buf.len()) {
+            buf[..chunk.len()].copy_from_slice(chunk);
+            let _ = self.0.write(&buf[..chunk.len()]);
+        }
+    }
+}
+
+/// Runs the console if it was requested, returning once it's left.
+pub fn run_if_requested(updater: &FlashUpdater<FlashWriterEraser>) {
+    // the NVMC is owned by `FlashWriterEraser`, only the peripherals below are used here.
+    let p = unsafe { Peripherals::steal() };
+    let magic = p.POWER.gpregret.read().gpregret().bits() == CONSOLE_MAGIC;
+    if !magic && !boot_pin_held() {
+        return;
+    }
+    p.POWER.gpregret.write(|w| unsafe { w.gpregret().bits(0) });
+
+    let port0 = p0::Parts::new(p.P0);
+    let pins = Pins {
+        txd: port0.p0_20.into_push_pull_output(Level::High).degrade(),
+        rxd: port0.p0_19.into_floating_input().degrade(),
+        cts: None,
+        rts: None,
+    };
+    let uarte = Uarte::new(p.UARTE0, pins, Parity::EXCLUDED, Baudrate::BAUD115200);
+    Console::new(updater, Uart(uarte)).run();
+}
@@ -1,19 +1,51 @@
 #![no_std]
 #![no_main]
 
+#[cfg(feature = "console")]
+mod console;
+
 #[cfg(feature = "defmt")]
 use defmt_rtt as _; // global logger
-use rustBoot_hal::nrf::nrf52840::FlashWriterEraser;
+use rustBoot_hal::nrf::nrf52840::{uicr_state, FlashWriterEraser};
 use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
 
 use cortex_m_rt::entry;
 
 #[entry]
 fn main() -> ! {
+    check_uicr();
     let updater = FlashUpdater::new(FlashWriterEraser::new());
+    #[cfg(feature = "console")]
+    console::run_if_requested(&updater);
+    // without the console, rustBoot just stays put while the button is held (ex: to attach a probe)
+    #[cfg(all(feature = "boot-pin", not(feature = "console")))]
+    if rustBoot_update::update::boot_pin::boot_pin_held() {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
     updater.rustboot_start()
 }
 
+/// Checks that the UICR was provisioned (see `cargo nrf52840 provision-uicr`). A part that
+/// wasn't, or whose debug port isn't locked, is only reported in development builds but never
+/// booted by `production` builds.
+fn check_uicr() {
+    let uicr = uicr_state();
+    if uicr.is_provisioned() && (uicr.approtect || cfg!(not(feature = "production"))) {
+        return;
+    }
+    #[cfg(feature = "defmt")]
+    defmt::warn!(
+        "UICR isn't provisioned, bootloader addr: {}, nfc pins as gpio: {}, approtect: {}",
+        uicr.bootloader_addr,
+        uicr.nfc_pins_as_gpio,
+        uicr.approtect
+    );
+    #[cfg(feature = "production")]
+    panic!("refusing to boot an unprovisioned part");
+}
+
 #[panic_handler] // panicking behavior
 fn panic(_: &core::panic::PanicInfo) -> ! {
     loop {
@@ -0,0 +1,6 @@
+/* @generated by `cargo nrf52840 gen layout` from `boards/manifests/nrf52840.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_stage0_end = 0x8000;
+ASSERT(LOADADDR(.data) + SIZEOF(.data) <= __rustboot_stage0_end,
+       "stage0 runs into the stage1 slots, see boards/manifests/nrf52840.toml");
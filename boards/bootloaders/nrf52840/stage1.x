@@ -0,0 +1,11 @@
+/* @generated by `cargo nrf52840 gen layout` from `boards/manifests/nrf52840.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_stage1_a = 0x8000;
+__rustboot_stage1_b = 0x1b000;
+__rustboot_stage1_size = 0x13000;
+/* stage1 is linked right after the 256-byte rustBoot header */
+ASSERT(ORIGIN(FLASH) == __rustboot_stage1_a + 0x100 || ORIGIN(FLASH) == __rustboot_stage1_b + 0x100,
+       "stage1 isn't linked for a stage1 slot, see boards/manifests/nrf52840.toml");
+ASSERT(LOADADDR(.data) + SIZEOF(.data) <= ORIGIN(FLASH) - 0x100 + __rustboot_stage1_size,
+       "stage1 runs past its slot, see boards/manifests/nrf52840.toml");
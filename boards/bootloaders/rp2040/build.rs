@@ -9,7 +9,13 @@ fn main() {
         .unwrap()
         .write_all(include_bytes!("memory.x"))
         .unwrap();
+    // generated from the board's manifest, see `cargo <board> gen layout`
+    File::create(out.join("partitions.x"))
+        .unwrap()
+        .write_all(include_bytes!("partitions.x"))
+        .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=partitions.x");
 }
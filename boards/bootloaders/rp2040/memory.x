@@ -11,4 +11,7 @@ SECTIONS {
     {
         KEEP(*(.boot2));
     } > BOOT2
-} INSERT BEFORE .text;
\ No newline at end of file
+} INSERT BEFORE .text;
+
+/* checks that rustBoot ends below the boot partition, see `cargo rp2040 gen layout` */
+INCLUDE partitions.x
@@ -0,0 +1,6 @@
+/* @generated by `cargo rp2040 gen layout` from `boards/manifests/rp2040.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_boot_partition = 0x10020000;
+ASSERT(LOADADDR(.data) + SIZEOF(.data) <= __rustboot_boot_partition,
+       "rustBoot runs into the boot partition, see boards/manifests/rp2040.toml");
@@ -0,0 +1,22 @@
+MEMORY {
+    FLASH : ORIGIN = 0x10000000, LENGTH = 4096K
+    RAM   : ORIGIN = 0x20000000, LENGTH = 512K
+    SRAM4 : ORIGIN = 0x20080000, LENGTH = 4K
+    SRAM5 : ORIGIN = 0x20081000, LENGTH = 4K
+}
+
+SECTIONS {
+    /* the IMAGE_DEF block goes right after the vector table i.e. within the first 4K of flash,
+     * where the bootrom (and picotool) look for it */
+    .start_block : ALIGN(4)
+    {
+        __start_block_addr = .;
+        KEEP(*(.start_block));
+    } > FLASH
+} INSERT AFTER .vector_table;
+
+/* .text starts after the IMAGE_DEF block */
+_stext = ADDR(.start_block) + SIZEOF(.start_block);
+
+/* checks that rustBoot ends below the boot partition, see `cargo rp2350 gen layout` */
+INCLUDE partitions.x
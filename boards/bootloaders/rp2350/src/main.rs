@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use rustBoot_hal::pico::rp2350::FlashWriterEraser;
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+
+/// Marks rustBoot as a (secure, Arm) executable for the rp2350's bootrom, which boots the first
+/// image in flash. There's no `boot2`, the bootrom sets up the QSPI flash itself. With secure boot
+/// enabled, the bootrom also checks the signature `picotool seal` appends to rustBoot (see
+/// `cargo rp2350 sign rustBoot`), so rustBoot becomes the second, verified, stage.
+#[link_section = ".start_block"]
+#[used]
+pub static IMAGE_DEF: rp235x_hal::block::ImageDef = rp235x_hal::block::ImageDef::secure_exe();
+
+#[entry]
+fn main() -> ! {
+    check_otp();
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    updater.rustboot_start()
+}
+
+/// Checks that OTP was provisioned (see `cargo rp2350 provision-otp`) i.e. that the bootrom only
+/// boots a signed rustBoot. `production` builds never boot a part without secure boot or whose
+/// debug port isn't disabled, as anything could have taken rustBoot's place.
+fn check_otp() {
+    #[cfg(feature = "production")]
+    {
+        let otp = rustBoot_hal::pico::rp2350::otp_state();
+        if !otp.is_provisioned() || !otp.debug_disabled {
+            panic!("refusing to boot a part without secure boot");
+        }
+    }
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    #[cfg(feature = "panic-record")]
+    rustBoot_hal::panic_record::record_and_reset(_info);
+    #[cfg(not(feature = "panic-record"))]
+    loop {
+        cortex_m::asm::bkpt();
+    }
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Load the address of a symbol into a register, PC-relative.
+.macro ADR_REL register, symbol
+	adrp	\register, \symbol
+	add	\register, \register, #:lo12:\symbol
+.endm
+
+.equ _core_id_mask, 0b11
+
+.section .text._start
+
+//------------------------------------------------------------------------------
+// fn _start()
+//------------------------------------------------------------------------------
+_start:
+	// Only proceed on the boot core. Park it otherwise.
+	mrs	x1, MPIDR_EL1
+	and	x1, x1, _core_id_mask
+	cbnz	x1, .L_parking_loop
+
+	// The firmware passes the dtb's address in x0, hold on to it for stage2.
+	mov	x19, x0
+
+	// Zero out the bss.
+	ADR_REL	x0, __bss_start
+	ADR_REL x1, __bss_end_exclusive
+
+.L_bss_init_loop:
+	cmp	x0, x1
+	b.eq	.L_prepare_rust
+	stp	xzr, xzr, [x0], #16
+	b	.L_bss_init_loop
+
+	// Prepare the jump to Rust code.
+.L_prepare_rust:
+	ADR_REL	x0, __stack_end_exclusive
+	mov	sp, x0
+	mov	x0, x19
+
+	// Jump to Rust code.
+	b	_start_rust
+
+	// Infinitely wait for events (aka "park the core").
+.L_parking_loop:
+	wfe
+	b	.L_parking_loop
+
+.size	_start, . - _start
+.type	_start, function
+.global	_start
@@ -0,0 +1,72 @@
+//! rpi4's first stage i.e. the `kernel8.img` loaded by the Raspberry's firmware.
+//!
+//! Stage1 verifies the signed second stage (i.e. rustBoot, signed with `rbsigner mcu-image`)
+//! against the embedded public key, copies it to the address it's linked at and jumps to it.
+//! This extends verification one level earlier - rustBoot itself is no longer trusted blindly.
+//!
+//! Stage1 doesn't touch the SD card or any peripheral, the firmware loads both stages (see
+//! `config.txt` in the README). It runs with the MMU and caches off. If verification fails, the
+//! boot core is parked.
+
+#![no_std]
+#![no_main]
+
+use core::arch::{asm, global_asm};
+
+use rustBoot::chain::SignedImage;
+
+// Assembly counterpart to this file.
+global_asm!(include_str!("boot.s"));
+
+/// Where the firmware loads the signed second stage, must match the `initramfs` line in config.txt.
+const STAGE2_LOAD_ADDR: usize = 0x0200_0000;
+/// Size limit of the signed second stage (header included).
+const STAGE2_MAX_SIZE: usize = 0x0080_0000;
+/// The address the second stage is linked at i.e. the firmware's default kernel load address.
+const STAGE2_RUN_ADDR: usize = 0x8_0000;
+
+extern "C" {
+    static __stack_start: u8;
+}
+
+/// The Rust entry of the `stage1` binary, called from `_start` with the dtb's address.
+///
+/// # Safety
+///
+/// - Only the boot core must be running this function.
+#[no_mangle]
+pub unsafe extern "C" fn _start_rust(dtb: usize) -> ! {
+    let staged = core::slice::from_raw_parts(STAGE2_LOAD_ADDR as *const u8, STAGE2_MAX_SIZE);
+    let stage2 = match SignedImage::parse(staged) {
+        Ok(image) if image.verify().is_ok() => image.firmware(),
+        _ => park(),
+    };
+    // stage1 (and its stack, right below it) must not be overwritten by the copy.
+    if STAGE2_RUN_ADDR + stage2.len() > &__stack_start as *const u8 as usize {
+        park()
+    }
+
+    // copy a word at a time, all of memory is device memory (i.e. strictly aligned) until
+    // someone turns on the MMU.
+    let src = stage2.as_ptr() as *const u64;
+    let dst = STAGE2_RUN_ADDR as *mut u64;
+    for i in 0..(stage2.len() + 7) / 8 {
+        dst.add(i).write_volatile(src.add(i).read_volatile());
+    }
+    asm!("dsb sy", "ic iallu", "dsb sy", "isb");
+
+    let stage2: extern "C" fn(usize) -> ! = core::mem::transmute(STAGE2_RUN_ADDR);
+    stage2(dtb)
+}
+
+/// Parks the boot core.
+fn park() -> ! {
+    loop {
+        unsafe { asm!("wfe") }
+    }
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    park()
+}
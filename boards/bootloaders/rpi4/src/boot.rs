@@ -6,12 +6,23 @@
 
 use core::arch::global_asm;
 use cortex_a::{asm, registers::*};
+use rustBoot::fs::controller::{FatCache, MAX_FAT_SECTORS};
+use rustBoot::kernel::Arm64Image;
+use rustBoot_hal::rpi::rpi4::arch::cpu_core::clean_dcache_range;
 use tock_registers::interfaces::Writeable;
 use zeroize::Zeroize;
 
 // Assembly counterpart to this file.
 global_asm!(include_str!("boot.s"));
 
+/// CPTR_EL2's RES1 bits i.e. no traps.
+const CPTR_EL2_RES1: u64 = 0x33ff;
+
+extern "C" {
+    /// EL2's vector table, see `boot.s`.
+    static __el2_stub_vectors: u8;
+}
+
 /// Prepares the transition from EL2 to EL1.
 ///
 /// # Safety
@@ -29,6 +40,29 @@ unsafe fn el2_to_el1_transition(phys_boot_core_stack_end_exclusive_addr: u64) {
     // Set EL1 execution state to AArch64.
     HCR_EL2.write(HCR_EL2::RW::EL1IsAarch64);
 
+    // EL1 reads of MIDR_EL1 and MPIDR_EL1 return VPIDR_EL2 and VMPIDR_EL2, whose reset values are
+    // unknown. Mirror the real ones.
+    core::arch::asm!(
+        "mrs {tmp}, MIDR_EL1",
+        "msr VPIDR_EL2, {tmp}",
+        "mrs {tmp}, MPIDR_EL1",
+        "msr VMPIDR_EL2, {tmp}",
+        tmp = out(reg) _,
+    );
+
+    // Don't trap EL1's accesses to FP/SIMD, CP15 or the trace registers.
+    core::arch::asm!(
+        "msr CPTR_EL2, {cptr}",
+        "msr HSTR_EL2, xzr",
+        cptr = in(reg) CPTR_EL2_RES1,
+    );
+
+    // Install the EL2 stub, which enters the kernel in EL2 (see `boot_kernel`).
+    core::arch::asm!(
+        "msr VBAR_EL2, {vbar}",
+        vbar = in(reg) &__el2_stub_vectors as *const u8 as u64,
+    );
+
     // Set up a simulated exception return.
     //
     // First, fake a saved program status where all interrupts were masked and SP_EL1 was used as a
@@ -77,6 +111,15 @@ pub unsafe extern "C" fn _start_rust(phys_boot_core_stack_end_exclusive_addr: u6
 #[link_section = ".text._start_arguments"]
 pub static BOOT_CORE_ID: u64 = 0;
 
+/// The kernel image format this board boots, see `fit::relocate_kernel`.
+pub type KernelImage<'a> = Arm64Image<'a>;
+/// The standalone dtb, only used if the fit-image doesn't carry an fdt (see `fit::select_dtb`).
+pub const DTB_NAME: &str = "bcm2711-rpi-4-b.dtb";
+/// The standalone dtb's sha256 digest, as hex (i.e. `sha256sum`'s output).
+pub const DTB_DIGEST_NAME: &str = "bcm2711-rpi-4-b.dtb.sha256";
+/// The rootfs partitions of an A/B update's slots (see `cfgparser::RootfsSlot`) i.e. the SD card's 2nd and 3rd partitions.
+pub const ROOTFS_PARTITIONS: [&str; 2] = ["/dev/mmcblk0p2", "/dev/mmcblk0p3"];
+
 const MAX_INITRAMFS_SIZE: usize = 16066 * 4 * 512;
 const MAX_KERNEL_SIZE: usize = 14624 * 4 * 512;
 pub(crate) const MAX_DTB_SIZE: usize = 100 * 512;
@@ -93,6 +136,10 @@ pub struct KernelEntry(pub [u8; MAX_KERNEL_SIZE]);
 /// A statically determined region of memory for the device-tree blob i.e.
 /// serves as the dtb's entry point
 pub struct DtbEntry(pub [u8; MAX_DTB_SIZE]);
+#[repr(align(8))]
+/// A statically determined region of memory for the standalone dtb (i.e. `bcm2711-rpi-4-b.dtb`),
+/// which is read from the FAT partition when the fit-image doesn't carry an fdt.
+pub struct DtbFile(pub [u8; MAX_DTB_SIZE]);
 #[derive(Zeroize)]
 /// A statically determined region of memory for the image-tree (or fit-image) blob i.e.
 /// serves as the fit-image's entry point. 
@@ -126,6 +173,13 @@ impl DtbEntry {
     }
 }
 
+impl DtbFile {
+    /// Get a buffer for the standalone DTB.
+    pub const fn new() -> Self {
+        Self([0u8; MAX_DTB_SIZE])
+    }
+}
+
 impl InitRamfsEntry {
     /// Get an entry point to the `initramfs`.
     pub const fn new() -> Self {
@@ -136,21 +190,59 @@ impl InitRamfsEntry {
 pub static mut INITRAMFS_LOAD_ADDR: InitRamfsEntry = InitRamfsEntry::new();
 pub static mut KERNEL_LOAD_ADDR: KernelEntry = KernelEntry::new();
 pub static mut DTB_LOAD_ADDR: DtbEntry = DtbEntry::new();
+pub static mut DTB_FILE_ADDR: DtbFile = DtbFile::new();
 pub static mut ITB_LOAD_ADDR: ImageTreeEntry = ImageTreeEntry::new();
+/// The FAT32 volume's file allocation table, see `Controller::populate_fat_cache`.
+pub static mut FAT_CACHE: FatCache<MAX_FAT_SECTORS> = FatCache::new();
 
-type EntryPoint = unsafe extern "C" fn(dtb: usize, rsv0: usize, rsv1: usize, rsv2: usize);
+/// Cleans the kernel, initramfs and dtb out of the data caches i.e. before they're handed over to
+/// the kernel, with caching turned off.
+pub fn clean_boot_images() {
+    unsafe {
+        clean_dcache_range(KERNEL_LOAD_ADDR.0.as_ptr() as usize, MAX_KERNEL_SIZE);
+        clean_dcache_range(INITRAMFS_LOAD_ADDR.0.as_ptr() as usize, MAX_INITRAMFS_SIZE);
+        clean_dcache_range(DTB_LOAD_ADDR.0.as_ptr() as usize, MAX_DTB_SIZE);
+    }
+}
 
 #[no_mangle]
 #[inline(never)]
-/// Jump to kernel. 
-/// 
+/// Jump to kernel, in EL2.
+///
+/// rustBoot itself runs in EL1. The secondary cores are released (by the kernel, through the
+/// spin-table or PSCI) in EL2 and Linux expects the boot core to enter in the same EL. So, the
+/// jump goes through the EL2 stub, installed by `el2_to_el1_transition`. The kernel is entered
+/// with the MMU and caches off, `x0` holding the dtb's address and `x1 - x3` zeroed.
+///
 /// **note:** this method is better as it has a safe abstraction around the `unsafe jump`
 pub fn boot_kernel(kernel_entry: usize, dtb_addr: usize) -> ! {
     unsafe {
-        let f = core::mem::transmute::<usize, EntryPoint>(kernel_entry);
-        f(dtb_addr, 0, 0, 0);
+        core::arch::asm!(
+            "hvc #0",
+            in("x0") kernel_entry,
+            in("x1") dtb_addr,
+            in("x2") 0usize,
+            options(noreturn)
+        )
+    }
+}
+
+#[cfg(feature = "chainload")]
+#[inline(never)]
+/// Jump to a chainloaded next stage (see `chainload.rs`), in EL2.
+///
+/// Same as [`boot_kernel`], except that the next stage is entered with `x0` holding the dtb's
+/// address and `x1` the handoff's.
+pub fn chainload(entry: usize, dtb_addr: usize, handoff_addr: usize) -> ! {
+    unsafe {
+        core::arch::asm!(
+            "hvc #0",
+            in("x0") entry,
+            in("x1") dtb_addr,
+            in("x2") handoff_addr,
+            options(noreturn)
+        )
     }
-    halt()
 }
 
 pub fn halt() -> ! {
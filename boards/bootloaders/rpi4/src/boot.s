@@ -19,7 +19,12 @@
 .endm
 
 .equ _EL2, 0x8
-.equ _core_id_mask, 0b11
+// Aff0 and Aff1 i.e. the core's id is in Aff0 on the rpi4 (Cortex-A72) and in Aff1 on the
+// rpi5 (Cortex-A76, Aff0 is the thread id).
+.equ _core_id_mask, 0xffff
+// The spin-table's release addresses i.e. the dtb's `cpu-release-addr` for core n is
+// `_spin_table_base + 8 * n`, same as the firmware's armstub.
+.equ _spin_table_base, 0xd8
 
 //--------------------------------------------------------------------------------------------------
 // Public Code
@@ -38,12 +43,12 @@ _start:
 	cmp	x0, _EL2
 	b.ne	.L_parking_loop
 
-	// Only proceed on the boot core. Park it otherwise.
+	// Only proceed on the boot core. Hold it in the spin-table otherwise.
 	mrs	x1, MPIDR_EL1
 	and	x1, x1, _core_id_mask
 	ldr	x2, BOOT_CORE_ID      // provided by bsp/__board_name__/cpu.rs
 	cmp	x1, x2
-	b.ne	.L_parking_loop
+	b.ne	.L_spin_table
 
 	// If execution reaches here, it is the boot core.
 
@@ -71,6 +76,47 @@ _start:
 	wfe
 	b	.L_parking_loop
 
+	// Secondary cores normally stay in the firmware's armstub. If they're released to us instead,
+	// behave like the armstub - wait (in EL2) until the kernel writes an entry point to the core's
+	// release address, then jump to it.
+.L_spin_table:
+	and	x1, x1, #0b11
+	mov	x2, _spin_table_base
+	add	x2, x2, x1, lsl #3
+.L_spin_table_wait:
+	wfe
+	ldr	x4, [x2]
+	cbz	x4, .L_spin_table_wait
+	mov	x0, xzr
+	mov	x1, xzr
+	mov	x2, xzr
+	mov	x3, xzr
+	br	x4
+
 .size	_start, . - _start
 .type	_start, function
 .global	_start  // _start is a declared as a global
+
+//------------------------------------------------------------------------------
+// EL2 stub vectors
+//------------------------------------------------------------------------------
+// Installed (in VBAR_EL2) before rustBoot drops to EL1. rustBoot hands control back to EL2 with
+// `hvc #0` (x0: kernel entry, x1: dtb, x2: handoff or 0), so that the kernel is entered in EL2 - the
+// exception level the secondary cores are released in. Linux expects all cores to enter in the
+// same mode. A chainloaded stage (see `chainload.rs`) gets the handoff in x1, a kernel gets 0.
+.section .text._el2_stub_vectors
+.balign 0x800
+__el2_stub_vectors:
+.org 0x400
+	// Synchronous exception from a lower EL (AArch64) i.e. rustBoot's `hvc`.
+	msr	ELR_EL2, x0
+	mov	x0, #0x3c9    // EL2h, all interrupts masked
+	msr	SPSR_EL2, x0
+	mov	x0, x1
+	mov	x1, x2
+	mov	x2, xzr
+	mov	x3, xzr
+	eret
+.org 0x800
+
+.global	__el2_stub_vectors
@@ -0,0 +1,73 @@
+//! Chainloading a next-stage bootloader (ex: U-Boot or a newer rustBoot) i.e. a verified
+//! fit-image's loadable, see the `chainload` feature.
+//!
+//! The loadable is loaded to [`KERNEL_LOAD_ADDR`] (plus its `entry - load` offset, if it carries
+//! both) and entered in EL2 with `x0` holding the dtb's address and `x1` the handoff's (see
+//! `boot::chainload`). The handoff (see [`rustBoot::handoff`]) describes the verified fit-image,
+//! the dtb and the loadable, as they are in RAM.
+//!
+//! **note:** the loadable is copied to the 2MiB aligned [`KERNEL_LOAD_ADDR`] rather than its `load`
+//! address i.e. it must be position independent (U-Boot relocates itself, as does rustBoot).
+
+use rustBoot::dt::{Error, Loadable};
+use rustBoot::handoff::{Component, Handoff, Kind, HANDOFF_LEN};
+use rustBoot::{Result as RbResult, RustbootError};
+use rustBoot_hal::info;
+use rustBoot_hal::rpi::rpi4::arch::cpu_core::clean_dcache_range;
+
+use crate::boot::{DTB_LOAD_ADDR, ITB_LOAD_ADDR, KERNEL_LOAD_ADDR, MAX_DTB_SIZE};
+
+#[repr(align(8))]
+/// A statically determined region of memory for the handoff, passed to the next stage.
+pub struct HandoffEntry(pub [u8; HANDOFF_LEN]);
+
+static mut HANDOFF_ADDR: HandoffEntry = HandoffEntry([0u8; HANDOFF_LEN]);
+
+/// Loads the loadable to [`KERNEL_LOAD_ADDR`] and the dtb (as it was verified i.e. unpatched, the
+/// next stage patches its own) to [`DTB_LOAD_ADDR`], and writes the handoff. Returns the
+/// loadable's entry point.
+pub fn load_next_stage(itb_blob: &[u8], dtb_blob: &[u8], loadable: &Loadable) -> RbResult<usize> {
+    let base = unsafe { KERNEL_LOAD_ADDR.0.as_mut() };
+    let len = loadable.load_into(base).map_err(loadable_error)?;
+    let offset = loadable.entry_offset().map_err(loadable_error)?;
+    if offset >= len {
+        info!("loadable: entry {:#x} is past its end", offset);
+        return Err(RustbootError::InvalidImage);
+    }
+    info!(
+        "loadable {}: {:#x} bytes, compression: {:?}",
+        loadable.name, len, loadable.compression
+    );
+
+    if dtb_blob.len() > MAX_DTB_SIZE {
+        return Err(RustbootError::BufferTooSmall);
+    }
+    let dtb = unsafe { &mut DTB_LOAD_ADDR.0[..dtb_blob.len()] };
+    dtb.copy_from_slice(dtb_blob);
+
+    let mut handoff = Handoff::new();
+    handoff.push(Component::new(Kind::FitImage, itb_blob))?;
+    handoff.push(Component::new(Kind::Fdt, dtb))?;
+    handoff.push(Component::new(Kind::Loadable, &base[..len]))?;
+    unsafe { HANDOFF_ADDR.0 = handoff.to_bytes() };
+    Ok(base[offset..].as_ptr() as usize)
+}
+
+fn loadable_error(e: Error) -> RustbootError {
+    info!("loadable: {:?}", e);
+    RustbootError::InvalidImage
+}
+
+/// The handoff's address, passed to the next stage.
+pub fn handoff_addr() -> usize {
+    unsafe { HANDOFF_ADDR.0.as_ptr() as usize }
+}
+
+/// Cleans the handoff and the fit-image (which it describes) out of the data caches i.e. along
+/// with `boot::clean_boot_images`, before caching is turned off.
+pub fn clean_handoff() {
+    unsafe {
+        clean_dcache_range(HANDOFF_ADDR.0.as_ptr() as usize, HANDOFF_LEN);
+        clean_dcache_range(ITB_LOAD_ADDR.0.as_ptr() as usize, ITB_LOAD_ADDR.0.len());
+    }
+}
@@ -1,85 +1,107 @@
-use rustBoot::dt::{get_image_data, patch_chosen_node, Error, PropertyValue, Reader, Result};
+use rustBoot::cfgparser::RootfsSlot;
+use rustBoot::dt::{
+    get_config_bootargs, get_image_data, patch_chosen_node, patch_chosen_node_with_report,
+    select_bootargs, select_rootfs, Error, PropertyValue, Reader, Result, UpdateReport,
+};
 
 use rustBoot_hal::info;
 
-use crate::boot::{DTB_LOAD_ADDR, INITRAMFS_LOAD_ADDR, MAX_DTB_SIZE};
+use crate::boot::{DTB_LOAD_ADDR, INITRAMFS_LOAD_ADDR, MAX_DTB_SIZE, ROOTFS_PARTITIONS};
 
-pub fn patch_dtb<'a>(itb_blob: &'a [u8]) -> Result<(&'a mut [u8; MAX_DTB_SIZE], usize)> {
-    // Load rbconfig
-    info!("load rbconfig...");
-    let rbconfig = get_image_data(itb_blob, "rbconfig").unwrap();
+/// Refuse kernel command lines that aren't covered by the fit-image's signature i.e. a
+/// `cmdline.txt` on the SD card fails the boot (see `select_bootargs`).
+const SECURE_BOOTARGS: bool = cfg!(feature = "secure-bootargs");
+/// Report an update's outcome to the kernel i.e. add a `rustboot,update-status` property to the
+/// `chosen` node (see `UpdateReport`).
+const REPORT_UPDATE_STATUS: bool = cfg!(feature = "update-status");
 
-    let propval_list = get_propval_list(itb_blob, rbconfig)?;
+/// Patches a dtb (i.e. the fit-image's fdt or the standalone dtb, see `select_dtb`) with a kernel
+/// command line and the initrd's location.
+///
+/// The command line is the fit-image's default config's `bootargs` or, if it doesn't carry any,
+/// its `rbconfig` - both are signed. `cmdline` (i.e. the SD card's `cmdline.txt`) overrides them,
+/// unless the `secure-bootargs` feature is enabled.
+///
+/// A `rootfs` slot (i.e. an A/B update's rootfs, see `load_fit`) replaces the command line's
+/// `root=` with the slot's partition (see `boot::ROOTFS_PARTITIONS`).
+///
+/// With the `update-status` feature, `report` (i.e. the outcome of an update, if one was attempted)
+/// is patched in as well, so userspace can tell a booted update from a fallback to the active image.
+pub fn patch_dtb<'a>(
+    itb_blob: &'a [u8],
+    dtb_blob: &'a [u8],
+    cmdline: Option<&'a [u8]>,
+    report: Option<UpdateReport>,
+    rootfs: Option<RootfsSlot>,
+) -> Result<(&'a mut [u8; MAX_DTB_SIZE], usize)> {
+    let signed = match get_config_bootargs(itb_blob) {
+        Some(bootargs) => Some(bootargs),
+        None => {
+            // Load rbconfig
+            info!("load rbconfig...");
+            get_image_data(itb_blob, "rbconfig")
+                .map(parse_bootargs)
+                .transpose()?
+        }
+    };
+    let external = cmdline.map(parse_bootargs).transpose()?;
+    if external.is_some() {
+        info!("found an unsigned kernel cmdline i.e. `cmdline.txt`...");
+    }
+    let bootargs = select_bootargs(signed, external, SECURE_BOOTARGS)?;
+    let mut bootargs_buf = [0u8; 512];
+    let bootargs = match rootfs {
+        Some(slot) => {
+            let root = ROOTFS_PARTITIONS[slot.index()];
+            info!("booting rootfs {:?} i.e. {}", slot, root);
+            select_rootfs(bootargs, root, &mut bootargs_buf)?
+        }
+        None => bootargs,
+    };
+
+    let propval_list = get_propval_list(itb_blob, bootargs)?;
 
-    let dtb_blob = get_image_data(itb_blob, "fdt").unwrap();
     let reader = Reader::read(dtb_blob)?;
     info!("\x1b[5m\x1b[34mpatching dtb...\x1b[0m");
-    let res = patch_chosen_node(reader, dtb_blob, &propval_list, unsafe {
-        &mut DTB_LOAD_ADDR.0
-    });
-    Ok(res)
+    match report.filter(|_| REPORT_UPDATE_STATUS) {
+        Some(report) => {
+            info!("reporting update status: {:?}", report);
+            patch_chosen_node_with_report(reader, dtb_blob, &propval_list, report, unsafe {
+                &mut DTB_LOAD_ADDR.0
+            })
+        }
+        None => patch_chosen_node(reader, dtb_blob, &propval_list, unsafe {
+            &mut DTB_LOAD_ADDR.0
+        }),
+    }
+}
+
+/// Parses a kernel command line, given as `bootargs="..."` (i.e. the `rbconfig.txt` format).
+pub fn parse_bootargs(cmd_line: &[u8]) -> Result<&str> {
+    core::str::from_utf8(cmd_line)
+        .map_err(|val| Error::BadStrEncoding(val))?
+        .trim_end()
+        .strip_prefix("bootargs=\"")
+        .and_then(|cmd_line| cmd_line.strip_suffix("\""))
+        .ok_or(Error::BadValueStr)
 }
 
 pub fn get_propval_list<'a>(
     itb_blob: &'a [u8],
-    cmd_line: &'a [u8],
+    bootargs: &'a str,
 ) -> Result<[PropertyValue<'a>; 3]> {
-    let cmd_line = core::str::from_utf8(cmd_line)
-        .map_err(|val| Error::BadStrEncoding(val))?
-        .strip_suffix("\"")
-        .unwrap();
-    let cmd_line = cmd_line.strip_prefix("bootargs=\"");
-    // info!("cmd_line: {}", cmd_line.unwrap());
+    // info!("cmd_line: {}", bootargs);
     let initrd_start = unsafe { &INITRAMFS_LOAD_ADDR.0 as *const u8 as u32 };
-    let initrd_len = get_image_data(itb_blob, "ramdisk").unwrap().len();
+    let initrd_len = get_image_data(itb_blob, "ramdisk")
+        .ok_or(Error::MissingProperty)?
+        .len();
     let initrd_end = initrd_start + initrd_len as u32;
     // info!("initrd_start: {:?}", initrd_start.to_be_bytes());
     // info!("initrd_end: {:?}", initrd_end.to_be_bytes());
 
     Ok([
-        PropertyValue::String(cmd_line.unwrap()),
+        PropertyValue::String(bootargs),
         PropertyValue::U32(initrd_start.to_be_bytes()),
         PropertyValue::U32(initrd_end.to_be_bytes()),
     ])
 }
-
-// #[allow(dead_code)]
-// pub fn patch_dtb_1<'a, const N: usize, D, T>(
-//     itb_blob: &'a [u8],
-//     volume: &mut Volume,
-//     ctrlr: &mut Controller<D, T>,
-// ) -> Result<(&'a mut [u8; MAX_DTB_SIZE], usize)>
-// where
-//     D: BlockDevice,
-//     T: TimeSource,
-// {
-//     let mut num_read = 0;
-//     let mut rbconfig = [0; N];
-//     let root_dir = ctrlr.open_root_dir(&volume).unwrap();
-
-//     // Load rbconfig
-//     info!("\x1b[5m\x1b[34mloading rbconfig...\x1b[0m");
-//     let mut rbconfig_file = ctrlr
-//         .open_file_in_dir(volume, &root_dir, "RBCONFIG.TXT", Mode::ReadOnly)
-//         .unwrap();
-//     while !rbconfig_file.eof() {
-//         num_read = ctrlr
-//             .read_multi(&volume, &mut rbconfig_file, rbconfig.as_mut())
-//             .unwrap();
-//         info!(
-//             "loaded rbconfig: {:?} bytes",
-//             num_read,
-//         );
-//     }
-//     ctrlr.close_file(&volume, rbconfig_file).unwrap();
-//     ctrlr.close_dir(&volume, root_dir);
-
-//     let propval_list = get_propval_list(itb_blob, &rbconfig.as_ref()[..num_read])?;
-
-//     let dtb_blob = get_image_data(itb_blob, "fdt").unwrap();
-//     let reader = Reader::read(dtb_blob)?;
-//     let res = patch_chosen_node(reader, dtb_blob, &propval_list, unsafe {
-//         &mut DTB_LOAD_ADDR.0
-//     });
-//     Ok(res)
-// }
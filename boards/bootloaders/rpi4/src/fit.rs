@@ -1,130 +1,258 @@
 use rustBoot::dt::{
-    get_image_data, verify_fit, Concat, Reader, Result, FALLBACK_TO_ACTIVE_IMG, IS_PASSIVE_SELECTED,
+    check_fit_validity, get_config_fdt, get_image_compression, get_image_data, load_image,
+    verify_fit_with, Compression, Concat, Error, ImageDigests, Reader, Sha256FitDigester,
+    UpdateReport, FALLBACK_TO_ACTIVE_IMG, IS_PASSIVE_SELECTED,
 };
 use rustBoot::fs::{
     blockdevice::BlockDevice,
+    bootcount::BootAttempt,
+    chunks::is_chunk_index,
     controller::{Controller, Volume, VolumeType},
-    filesystem::{LongFileName, Mode, TimeSource},
+    filesystem::{Directory, LongFileName, Mode, TimeSource},
+    state::{set_config_value, UpdateState, STATE_HEADER_SIZE},
 };
 
 use rustBoot::{
-    cfgparser::{self, UpdateStatus},
+    cfgparser::{self, RootfsSlot, UpdateConfig, UpdateStatus},
+    version::{TimestampPolicy, ValidityPolicy, VersionPolicy},
     Result as RbResult, RustbootError,
 };
+use rustBoot_hal::rpi::rpi4::log::print::set_verbosity;
 use rustBoot_hal::{info, print};
+use sha2::{Digest, Sha256};
 
-use crate::boot::{DTB_LOAD_ADDR, INITRAMFS_LOAD_ADDR, ITB_LOAD_ADDR, KERNEL_LOAD_ADDR};
+use crate::boot::{
+    KernelImage, DTB_DIGEST_NAME, DTB_FILE_ADDR, DTB_LOAD_ADDR, DTB_NAME, FAT_CACHE,
+    INITRAMFS_LOAD_ADDR, ITB_LOAD_ADDR, KERNEL_LOAD_ADDR,
+};
 use crate::dtb::patch_dtb;
 
-/// Loads a fit-image. Returns a tuple contianing the image-tree blob and its version number
+/// Decides whether a passive fit-image's version may replace the active one's.
+const VERSION_POLICY: VersionPolicy = VersionPolicy::STRICT;
+/// Decides whether a fit-image's timestamp satisfies the version recorded in `updt.txt`.
+const TIMESTAMP_POLICY: TimestampPolicy = TimestampPolicy::Exact;
+/// Decides whether a fit-image with a validity window boots when the RTC is unset (the boards
+/// that share this file have no RTC i.e. such fit-images are refused).
+const VALIDITY_POLICY: ValidityPolicy = ValidityPolicy::Refuse;
+/// Count the boots of an update, reverting to the active fit-image once an update has been
+/// booted [`MAX_BOOT_ATTEMPTS`] times without being confirmed by userspace (see
+/// `rustBoot::fs::bootcount`).
+const BOOT_COUNT: bool = cfg!(feature = "boot-count");
+/// The boots an update gets before it must be confirmed, with the `boot-count` feature.
+const MAX_BOOT_ATTEMPTS: u8 = 3;
+
+/// Where the dtb handed to the kernel came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtbSource {
+    /// The fit-image's fdt, verified along with the rest of the fit-image.
+    Fit,
+    /// The FAT partition's standalone dtb, checked against its sha256 digest.
+    Standalone,
+}
+
+impl core::fmt::Display for DtbSource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DtbSource::Fit => write!(f, "fit-image fdt"),
+            DtbSource::Standalone => write!(f, "standalone dtb"),
+        }
+    }
+}
+
+/// Loads a fit-image. Returns a tuple contianing the image-tree blob, its version number, the
+/// image digests computed while the blob was being read (if the blob could be streamed) and the
+/// rootfs slot to boot it with (if `updt.txt` pairs images with rootfs slots, see
+/// `cfgparser::RootfsSlot`).
 ///
-/// **note:** this function expects a valid `updt.txt` file to be present in the FAT partition's root directory.
-/// If it doesnt find one or if it isn't a valid `updt.txt` config, it will panic.
-pub fn load_fit<'a, D, T>(volume: &mut Volume, ctrlr: &mut Controller<D, T>) -> (&'a [u8], u32)
+/// A chunk index (i.e. a `.cix` image) is reassembled from the chunk store, see
+/// `rustBoot::fs::chunks`.
+///
+/// **note:** this function expects a valid update state (`UPDT_A.TXT`/`UPDT_B.TXT`) or `updt.txt` file to
+/// be present in the FAT partition's root directory. If it doesnt find one, if it isn't a valid `updt.txt`
+/// config or if the fit-image can't be read, it fails i.e. the next boot source is tried.
+pub fn load_fit<'a, D, T>(
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+) -> RbResult<(&'a [u8], u32, Option<ImageDigests<32>>, Option<RootfsSlot>)>
 where
     D: BlockDevice,
     T: TimeSource,
 {
-    let mut fit_to_load = None;
-    let mut version_to_load = None;
+    let root_dir = ctrlr
+        .open_root_dir(&volume)
+        .map_err(fs_error("failed to open the root directory"))?;
+    let res = load_fit_from(volume, ctrlr, &root_dir);
+    ctrlr.close_dir(&volume, root_dir);
+    res
+}
+
+fn load_fit_from<'a, D, T>(
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+    root_dir: &Directory,
+) -> RbResult<(&'a [u8], u32, Option<ImageDigests<32>>, Option<RootfsSlot>)>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let fit_to_load;
+    let version_to_load;
     let updt_flag;
-    let mut updt_triggered = false;
+    let updt_triggered;
+    let rootfs_to_load;
 
     let active_img_name;
     let passive_img_name;
 
-    let root_dir = ctrlr.open_root_dir(&volume).unwrap();
-
-    // Load update config
+    // Load update config - prefer the A/B update state (see `rustBoot::fs::state`) and fall
+    // back to a plain `updt.txt` if neither copy is valid.
     let mut num_read = 0;
     let mut cfg = [0u8; 200];
-    let mut updt_cfg = ctrlr
-        .open_file_in_dir(volume, &root_dir, "UPDT.TXT", Mode::ReadOnly)
-        .unwrap();
-    while !updt_cfg.eof() {
-        num_read = ctrlr.read(&volume, &mut updt_cfg, &mut cfg).unwrap();
-    }
-    info!(
-        "loaded `updt.txt` cfg: {:?} bytes, starting at addr: {:p}",
-        num_read, &cfg,
-    );
-    ctrlr.close_file(&volume, updt_cfg).unwrap();
+    let mut state_buf = [0u8; 1024];
+    let state = ctrlr
+        .read_update_state(volume, root_dir, &mut state_buf)
+        .map_err(fs_error("failed to read the update state"))?;
+    let cfg_bytes = match &state {
+        Some(state) => {
+            info!(
+                "loaded update state: {}, seq: {:?}, {:?} bytes",
+                state.slot.file_name(),
+                state.record.seq,
+                state.record.config.len(),
+            );
+            state.record.config
+        }
+        None => {
+            let mut updt_cfg = ctrlr
+                .open_file_in_dir(volume, root_dir, "UPDT.TXT", Mode::ReadOnly)
+                .map_err(fs_error("failed to open `updt.txt`"))?;
+            while !updt_cfg.eof() {
+                match ctrlr.read(&volume, &mut updt_cfg, &mut cfg) {
+                    Ok(read) => num_read = read,
+                    Err(e) => {
+                        let _ = ctrlr.close_file(&volume, updt_cfg);
+                        return Err(fs_error("failed to read `updt.txt`")(e));
+                    }
+                }
+            }
+            info!(
+                "loaded `updt.txt` cfg: {:?} bytes, starting at addr: {:p}",
+                num_read, &cfg,
+            );
+            ctrlr
+                .close_file(&volume, updt_cfg)
+                .map_err(fs_error("failed to close `updt.txt`"))?;
+            &cfg[..num_read]
+        }
+    };
 
     // parse `updt.txt` cfg
-    if let Ok((_, (active_conf, passive_conf))) = cfgparser::parse_config(
-        core::str::from_utf8(&cfg).expect("an invalid update cfg was provided"),
-    ) {
-        // get active config name and version
-        let active_name = active_conf.image_name;
-        let active_version = active_conf.image_version;
-        // get passive config name, version and status
-        let passive_name = passive_conf.image_name;
-        let passive_version = passive_conf.image_version;
-        let passive_status = passive_conf.update_status;
+    let updt_txt = core::str::from_utf8(cfg_bytes).map_err(|_| {
+        info!("an invalid update cfg was provided");
+        RustbootError::InvalidValue
+    })?;
+    match cfgparser::parse_update_config(updt_txt) {
+        Ok(UpdateConfig {
+            active: active_conf,
+            passive: passive_conf,
+            verbosity,
+        }) => {
+            if let Some(verbosity) = verbosity {
+                set_verbosity(verbosity);
+            }
+            // get active config name and version
+            let active_name = active_conf.image_name;
+            let active_version = active_conf.image_version;
+            // get passive config name, version and status
+            let passive_name = passive_conf.image_name;
+            let passive_version = passive_conf.image_version;
+            let passive_status = passive_conf.update_status;
+            // an update that doesn't pair its image with a rootfs slot (i.e. a kernel-only update)
+            // keeps the active rootfs. A fallback to the active image is a fallback to its rootfs.
+            let active_rootfs = active_conf.rootfs;
+            let passive_rootfs = passive_conf.rootfs.or(active_rootfs);
 
-        // check whether the `update` has been marked as ready (on the next reboot).
-        updt_flag = match passive_conf.ready_for_update_flag {
-            true => match (passive_name, passive_version, passive_status) {
-                (None, _, _) => false,
-                (_, None, _) => false,
-                (_, _, None) => false,
-                (
-                    Some((_, ".itb")),
-                    _,
-                    Some(UpdateStatus::Updating) | Some(UpdateStatus::Success),
-                ) => true,
-                (Some((_, _)), _, Some(UpdateStatus::Testing)) => {
-                    info!("update was authenticated and run but was not marked as successful, falling back to currently active image");
-                    false
+            // check whether the `update` has been marked as ready (on the next reboot).
+            updt_flag = match passive_conf.ready_for_update_flag {
+                true => match (passive_name, passive_version, passive_status) {
+                    (None, _, _) => false,
+                    (_, None, _) => false,
+                    (_, _, None) => false,
+                    (
+                        Some((_, ".itb" | ".cix")),
+                        _,
+                        Some(UpdateStatus::Updating) | Some(UpdateStatus::Success),
+                    ) => true,
+                    (Some((_, _)), _, Some(UpdateStatus::Testing)) => {
+                        info!("update was authenticated and run but was not marked as successful, falling back to currently active image");
+                        false
+                    }
+                    (Some((_, _)), _, _) => false,
+                },
+                false => false,
+            };
+            // Check the update version. A valid update must have a version
+            // permitted by the version policy i.e. (by default) greater than the active version.
+            let version_check = match passive_version {
+                Some(ver) => VERSION_POLICY.permits(active_version, ver),
+                None => false,
+            };
+            // `&str` concatentation - image name + extension
+            // name + extn must be less than 50 bytes.
+            active_img_name = active_name.0.concat::<50>(active_name.1.as_bytes());
+            passive_img_name = if let Some(val) = passive_name {
+                val.0.concat::<50>(val.1.as_bytes())
+            } else {
+                active_img_name
+            };
+            let passive_selected =
+                updt_flag && version_check && unsafe { FALLBACK_TO_ACTIVE_IMG.get().is_none() };
+            // an update that's used up its boot attempts is reverted
+            let passive_selected = passive_selected
+                && passive_version.map_or(false, |ver| {
+                    count_boot_attempt(volume, ctrlr, root_dir, ver, state.as_ref(), updt_txt)
+                });
+            match passive_selected {
+                true => {
+                    // ok to unwrap, we already checked.
+                    version_to_load = passive_version;
+                    let _ = unsafe { IS_PASSIVE_SELECTED.get_or_init(|| true) };
+                    fit_to_load = passive_img_name.as_str_no_suffix().ok();
+                    rootfs_to_load = passive_rootfs;
+                    updt_triggered = true;
+                }
+                false => {
+                    version_to_load = Some(active_version);
+                    fit_to_load = active_img_name.as_str_no_suffix().ok();
+                    rootfs_to_load = active_rootfs;
+                    updt_triggered = false;
                 }
-                (Some((_, _)), _, _) => false,
-            },
-            false => false,
-        };
-        // Check the update version. A valid update must have a version
-        // greater than the active version.
-        let version_check = match passive_version {
-            Some(ver) => ver > active_version,
-            None => false,
-        };
-        // `&str` concatentation - image name + extension
-        // name + extn must be less than 50 bytes.
-        active_img_name = active_name.0.concat::<50>(active_name.1.as_bytes());
-        passive_img_name = if let Some(val) = passive_name {
-            val.0.concat::<50>(val.1.as_bytes())
-        } else {
-            active_img_name
-        };
-        match updt_flag && version_check && unsafe { FALLBACK_TO_ACTIVE_IMG.get().is_none() } {
-            true => {
-                // ok to unwrap, we already checked.
-                version_to_load = passive_version;
-                let _ = unsafe { IS_PASSIVE_SELECTED.get_or_init(|| true) };
-                fit_to_load = passive_img_name.as_str_no_suffix().ok();
-                updt_triggered = true;
-            }
-            false => {
-                version_to_load = Some(active_version);
-                fit_to_load = active_img_name.as_str_no_suffix().ok();
-                updt_triggered = false;
             }
         }
+        Err(e) => {
+            info!("invalid `updt.txt` cfg: {:?}", e);
+            return Err(RustbootError::InvalidValue);
+        }
+    };
+    let (fit_name, fit_version) = match (fit_to_load, version_to_load) {
+        (Some(fit_name), Some(fit_version)) => (fit_name, fit_version),
+        // this shouldnt be possible if `parse_config` succeeds
+        (_, _) => return Err(RustbootError::InvalidValue),
     };
     info!(
-        "fit_to_load: {}, version_to_load: {}",
-        fit_to_load.unwrap(),
-        version_to_load.unwrap()
+        "fit_to_load: {}, version_to_load: {}, rootfs_to_load: {:?}",
+        fit_name, fit_version, rootfs_to_load
     );
 
     let mut num_read = 0;
     info!("Listing \x1b[33mroot\x1b[0m directory:");
     ctrlr
-        .iterate_dir(&volume, &root_dir, |entry| {
+        .iterate_dir(&volume, root_dir, |entry| {
             if entry.size > 60000000 {
                 info!("     - \x1b[36mFound: {}\x1b[0m", entry.name)
             };
         })
-        .unwrap();
+        .map_err(fs_error("failed to list the root directory"))?;
 
     if updt_triggered {
         info!("update triggered...");
@@ -132,71 +260,326 @@ where
         info!("booting active image...")
     }
     // Load itb
-    match (fit_to_load, version_to_load) {
-        (Some(fit_name), Some(fit_version)) => {
-            let lfn = LongFileName::create_from_str(fit_name);
-            let sfn_bytes = match &volume.volume_type {
-                VolumeType::Fat(fat) => {
-                    match fat.get_sfn_bytes_from_lfn_name(ctrlr, &lfn, &root_dir) {
-                        Ok(val) => to_dotted_sfn(val),
-                        Err(e) => panic!("error: {:?}", e),
-                    }
+    let lfn = LongFileName::create_from_str(fit_name);
+    let sfn_bytes = match &volume.volume_type {
+        VolumeType::Fat(fat) => fat
+            .get_sfn_bytes_from_lfn_name(ctrlr, &lfn, root_dir)
+            .map(to_dotted_sfn)
+            .map_err(fs_error("fit-image not found"))?,
+    };
+    let sfn = core::str::from_utf8(&sfn_bytes).map_err(|_| RustbootError::InvalidValue)?;
+    // info!("\x1b[5m\x1b[34msfn bytes: {:?} \x1b[0m", &sfn_bytes);
+    info!("\x1b[5m\x1b[34mloading fit-image...{} \x1b[0m", sfn);
+
+    // hash image data as each cluster-run (or chunk) lands in memory
+    let mut digester = Sha256FitDigester::new();
+    if is_chunk_index(fit_name) {
+        // a chunked fit-image is reassembled from the chunk store, see `rustBoot::fs::chunks`
+        num_read = ctrlr
+            .read_chunked_with(
+                volume,
+                root_dir,
+                sfn,
+                unsafe { &mut ITB_LOAD_ADDR.0 },
+                unsafe { &FAT_CACHE },
+                |chunk| digester.update(chunk),
+            )
+            .map_err(fs_error("failed to reassemble the fit-image"))?;
+        info!(
+            "reassembled {}: {:?} bytes, version: {:?}, starting at addr: {:p}",
+            fit_name,
+            num_read,
+            fit_version,
+            unsafe { &mut ITB_LOAD_ADDR.0 },
+        );
+    } else {
+        let mut itb_file = ctrlr
+            .open_file_in_dir(volume, root_dir, sfn, Mode::ReadOnly)
+            .map_err(fs_error("failed to open the fit-image"))?;
+        while !itb_file.eof() {
+            let res = ctrlr.read_multi_with(
+                &volume,
+                &mut itb_file,
+                unsafe { &mut ITB_LOAD_ADDR.0 },
+                unsafe { &FAT_CACHE },
+                |chunk| digester.update(chunk),
+            );
+            num_read = match res {
+                Ok(read) => read,
+                Err(e) => {
+                    let _ = ctrlr.close_file(&volume, itb_file);
+                    return Err(fs_error("failed to read the fit-image")(e));
                 }
             };
-            let sfn = core::str::from_utf8(&sfn_bytes).unwrap();
-            // info!("\x1b[5m\x1b[34msfn bytes: {:?} \x1b[0m", &sfn_bytes);
-            info!("\x1b[5m\x1b[34mloading fit-image...{} \x1b[0m", sfn);
+            info!(
+                "loaded {}: {:?} bytes, version: {:?}, starting at addr: {:p}",
+                fit_name,
+                num_read,
+                fit_version,
+                unsafe { &mut ITB_LOAD_ADDR.0 },
+            );
+        }
+        ctrlr
+            .close_file(&volume, itb_file)
+            .map_err(fs_error("failed to close the fit-image"))?;
+    }
 
-            let mut itb_file = ctrlr
-                .open_file_in_dir(volume, &root_dir, sfn, Mode::ReadOnly)
-                .unwrap();
-            while !itb_file.eof() {
-                num_read = ctrlr
-                    .read_multi(&volume, &mut itb_file, unsafe { &mut ITB_LOAD_ADDR.0 })
-                    .unwrap();
-                info!(
-                    "loaded {}: {:?} bytes, version: {:?}, starting at addr: {:p}",
-                    fit_name,
-                    num_read,
-                    fit_version,
-                    unsafe { &mut ITB_LOAD_ADDR.0 },
-                );
-            }
+    let itb_blob = unsafe { &ITB_LOAD_ADDR.0.as_ref()[..num_read] };
+    let digests = match digester.finalize(itb_blob) {
+        Ok(digests) => Some(digests),
+        Err(e) => {
+            info!("streamed hashing failed: {:?}, re-hashing after load", e);
+            None
+        }
+    };
+    Ok((itb_blob, fit_version, digests, rootfs_to_load))
+}
 
-            ctrlr.close_file(&volume, itb_file).unwrap();
-            ctrlr.close_dir(&volume, root_dir);
+/// Counts a boot of the passive fit-image with `version` (with the `boot-count` feature, see
+/// [`BOOT_COUNT`]). Returns `false` if it's to be reverted i.e. if it's used up its attempts or
+/// if its boot-attempt counter can't be stored. An update that's used up its attempts is reverted
+/// for good, see [`revert_update`].
+fn count_boot_attempt<D, T>(
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+    root_dir: &Directory,
+    version: u32,
+    state: Option<&UpdateState<'_>>,
+    config: &str,
+) -> bool
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    if !BOOT_COUNT {
+        return true;
+    }
+    match ctrlr.count_boot_attempt(volume, root_dir, version, MAX_BOOT_ATTEMPTS) {
+        Ok(BootAttempt::Confirmed) => true,
+        Ok(BootAttempt::Trial(count)) => {
+            info!(
+                "booting an unconfirmed update, {} attempt(s) left",
+                count.attempts_left
+            );
+            true
+        }
+        Ok(BootAttempt::Exhausted) => {
+            info!("update wasn't confirmed in time, reverting to the active image");
+            revert_update(volume, ctrlr, root_dir, state, config);
+            false
+        }
+        Err(e) => {
+            info!(
+                "failed to count the update's boot: {:?}, booting the active image",
+                e
+            );
+            false
+        }
+    }
+}
 
-            (
-                unsafe { &ITB_LOAD_ADDR.0.as_ref()[..num_read] },
-                fit_version,
-            )
+/// Reverts an update for good i.e. writes the update state with the passive fit-image's
+/// `ready_for_update_flag` cleared. Like every write of the update state, it goes through
+/// `Controller::write_update_state` (i.e. to the inactive copy), so a torn write keeps `state`. A
+/// plain `updt.txt` (i.e. no `state`) is carried over to the A/B copies.
+///
+/// A failed write is only logged, the exhausted update is still reverted on every boot.
+fn revert_update<D, T>(
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+    root_dir: &Directory,
+    state: Option<&UpdateState<'_>>,
+    config: &str,
+) where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let mut reverted = [0u8; 512];
+    let mut scratch = [0u8; 512 + STATE_HEADER_SIZE];
+    let len = match set_config_value(
+        config,
+        "[passive]",
+        "ready_for_update_flag",
+        "false",
+        &mut reverted,
+    ) {
+        Ok(len) => len,
+        Err(e) => {
+            info!("failed to revert the update: {:?}", e);
+            return;
         }
-        (_, _) => {
-            // this shouldnt be possible if `parse_config` succeeds
-            unreachable!()
+    };
+    match ctrlr.write_update_state(volume, root_dir, state, &reverted[..len], &mut scratch) {
+        Ok(slot) => info!("update reverted, wrote {}", slot.file_name()),
+        Err(e) => info!("failed to write the update state: {:?}", e),
+    }
+}
+
+/// Logs a filesystem error (i.e. `what` failed) and maps it to a `RustbootError`, so the next
+/// boot source is tried rather than panicking.
+fn fs_error<E: core::fmt::Debug>(what: &'static str) -> impl FnOnce(E) -> RustbootError {
+    move |e| {
+        info!("{}: {:?}", what, e);
+        RustbootError::InvalidImage
+    }
+}
+
+/// Selects the dtb to boot with. The fit-image's fdt is preferred, as it's verified along with the
+/// rest of the fit-image. The standalone dtb is only loaded (and hash-checked, see [`load_dtb`]) if
+/// the fit-image's default config doesn't reference an fdt.
+pub fn select_dtb<'a, D, T>(
+    itb_blob: &'a [u8],
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+) -> RbResult<(&'a [u8], DtbSource)>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    match get_config_fdt(itb_blob) {
+        Some(fdt) => Ok((fdt, DtbSource::Fit)),
+        None => {
+            info!("fit-image has no fdt, loading the standalone dtb...");
+            load_dtb(volume, ctrlr).map(|dtb| (dtb, DtbSource::Standalone))
         }
     }
 }
 
+/// Loads the standalone dtb (i.e. [`DTB_NAME`]) and checks it against the sha256 digest in
+/// [`DTB_DIGEST_NAME`]. A missing, corrupt or unparseable dtb fails the boot source.
+///
+/// **note:** the digest isn't signed i.e. it catches corruption, not tampering.
+fn load_dtb<'a, D, T>(volume: &mut Volume, ctrlr: &mut Controller<D, T>) -> RbResult<&'a [u8]>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let root_dir = ctrlr
+        .open_root_dir(&volume)
+        .map_err(fs_error("failed to open the root directory"))?;
+    let res = load_dtb_from(volume, ctrlr, &root_dir);
+    ctrlr.close_dir(&volume, root_dir);
+    res
+}
+
+fn load_dtb_from<'a, D, T>(
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+    root_dir: &Directory,
+) -> RbResult<&'a [u8]>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let dtb_buf = unsafe { &mut DTB_FILE_ADDR.0 };
+    let len = read_file(volume, ctrlr, root_dir, DTB_NAME, dtb_buf)?;
+    let dtb_blob = &dtb_buf[..len];
+
+    let mut digest_buf = [0u8; 128];
+    let digest_len = read_file(volume, ctrlr, root_dir, DTB_DIGEST_NAME, &mut digest_buf)?;
+    let expected = parse_sha256_hex(&digest_buf[..digest_len]).ok_or_else(|| {
+        info!("`{}` isn't a sha256 digest", DTB_DIGEST_NAME);
+        RustbootError::InvalidValue
+    })?;
+    if Sha256::digest(dtb_blob).as_slice() != expected {
+        info!("standalone dtb doesn't match its sha256 digest");
+        return Err(RustbootError::IntegrityCheckFailed);
+    }
+    Reader::read(dtb_blob).map_err(|e| {
+        info!("invalid standalone dtb: {:?}", e);
+        RustbootError::InvalidValue
+    })?;
+    info!("loaded {}: {:?} bytes, sha256 ok", DTB_NAME, len);
+    Ok(dtb_blob)
+}
+
+/// Reads the file named `name` (a long file name) in `root_dir` into `buf`. Returns the number of
+/// bytes read. Fails if the file doesn't fit in `buf`.
+fn read_file<D, T>(
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+    root_dir: &Directory,
+    name: &str,
+    buf: &mut [u8],
+) -> RbResult<usize>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let lfn = LongFileName::create_from_str(name);
+    let sfn_bytes = match &volume.volume_type {
+        VolumeType::Fat(fat) => fat
+            .get_sfn_bytes_from_lfn_name(ctrlr, &lfn, root_dir)
+            .map(to_dotted_sfn)
+            .map_err(fs_error("file not found"))?,
+    };
+    let sfn = core::str::from_utf8(&sfn_bytes).map_err(|_| RustbootError::InvalidValue)?;
+    let mut file = ctrlr
+        .open_file_in_dir(volume, root_dir, sfn, Mode::ReadOnly)
+        .map_err(fs_error("failed to open file"))?;
+    let len = file.length() as usize;
+    let res = match buf.get_mut(..len) {
+        Some(buf) => ctrlr
+            .read(&volume, &mut file, buf)
+            .map_err(fs_error("failed to read file")),
+        None => {
+            info!("{}: {:?} bytes is too large", name, len);
+            Err(RustbootError::BufferTooSmall)
+        }
+    };
+    let _ = ctrlr.close_file(&volume, file);
+    res
+}
+
+/// Parses a sha256 digest from (at least) 64 hex characters i.e. the first field of `sha256sum`'s
+/// output.
+fn parse_sha256_hex(hex: &[u8]) -> Option<[u8; 32]> {
+    let mut digest = [0u8; 32];
+    for (byte, pair) in digest.iter_mut().zip(hex.get(..64)?.chunks(2)) {
+        let pair = core::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(digest)
+}
+
 /// Verifies a loaded fit-image's cryptographic digital signature, when supplied with a `fit version number`.
 ///
 /// The fit's version number is retrieved from rustBoot's `updt.txt` file i.e. this function also checks
-/// whether the `version-number` from `updt.txt` matches the fit-image's timestamp.
+/// whether the fit-image's timestamp satisfies the `version-number` from `updt.txt`, as per
+/// [`TIMESTAMP_POLICY`]. A fit-image's validity window (if any) is then checked against the RTC, as
+/// per [`VALIDITY_POLICY`].
+///
+/// Images' `load` addresses aren't checked, as they aren't honored i.e. images are relocated to
+/// statically determined buffers (see [`relocate_kernel`]) instead.
+///
+/// Image digests computed by [`load_fit`] are re-used instead of hashing the loaded blob a second time.
 ///
 /// **note:** rustBoot uses a global mutable static to load its fit-images.
-pub fn verify_authenticity(itb_version: u32) -> RbResult<bool> {
+pub fn verify_authenticity(itb_version: u32, digests: Option<&ImageDigests<32>>) -> RbResult<bool> {
     info!("\x1b[5m\x1b[31mauthenticating fit-image...\x1b[0m");
-    let header = Reader::get_header(unsafe { &ITB_LOAD_ADDR.0 }).unwrap();
+    let header =
+        Reader::get_header(unsafe { &ITB_LOAD_ADDR.0 }).map_err(|_| RustbootError::InvalidImage)?;
     let total_size = header.total_size;
-    let val = match verify_fit::<32, 64, 4>(
+    let val = match verify_fit_with::<32, 64, 4>(
         unsafe { &ITB_LOAD_ADDR.0[..total_size as usize] },
         itb_version,
+        digests,
+        TIMESTAMP_POLICY,
     ) {
         Ok(val) => {
             print!(
                 "######## \x1b[33mecdsa signature\x1b[0m checks out, \
                 \x1b[92mimage is authentic\x1b[0m ########\n"
             );
+            check_fit_validity(
+                unsafe { &ITB_LOAD_ADDR.0[..total_size as usize] },
+                rustBoot_hal::rtc_time(),
+                VALIDITY_POLICY,
+            )
+            .map_err(|e| {
+                info!("fit-image refused: {}", e);
+                e
+            })?;
             Ok(val)
         }
         Err(e) => {
@@ -210,81 +593,150 @@ pub fn verify_authenticity(itb_version: u32) -> RbResult<bool> {
 }
 
 /// Extracts and relocates the kernel image from a loaded fit-image to a
-/// (statically determined) location in bss.
-pub fn relocate_kernel(itb_blob: &[u8]) {
-    let kernel_entry = unsafe { KERNEL_LOAD_ADDR.0.as_mut() };
-    let kernel_data = get_image_data(itb_blob, "kernel");
-    match kernel_data {
-        Some(val) => {
-            let len = val.len();
-            assert!(len < unsafe { KERNEL_LOAD_ADDR.0.len() });
-            kernel_entry[..len].copy_from_slice(val);
-        }
-        None => {
-            panic!("itb has no kernel data")
-        }
+/// (statically determined) location in bss. A compressed (i.e. `gzip`) kernel is decompressed
+/// in the process.
+///
+/// The kernel's `Image` header (i.e. the board's [`KernelImage`]) is validated first and the
+/// kernel is placed at its `text_offset` (from the 2MiB aligned [`KERNEL_LOAD_ADDR`]). Returns the
+/// kernel's entry point.
+pub fn relocate_kernel(itb_blob: &[u8]) -> RbResult<usize> {
+    let kernel_base = unsafe { KERNEL_LOAD_ADDR.0.as_mut() };
+    let compression = get_image_compression(itb_blob, "kernel").map_err(|e| {
+        info!("kernel: {:?}", e);
+        RustbootError::InvalidKernelImage
+    })?;
+    if compression == Compression::None {
+        let kernel_data =
+            get_image_data(itb_blob, "kernel").ok_or(RustbootError::InvalidKernelImage)?;
+        let kernel = KernelImage::parse(kernel_data)?;
+        log_kernel(&kernel, compression);
+        let offset = kernel.load_into(kernel_base)?;
+        return Ok(kernel_base[offset..].as_ptr() as usize);
     }
+    // the kernel's header (i.e. its `text_offset`) is only known after decompression, so it's
+    // decompressed to the start of the buffer and then moved to `text_offset`.
+    let len = load_image(itb_blob, "kernel", kernel_base).map_err(|e| {
+        info!("kernel decompression failed: {:?}", e);
+        RustbootError::InvalidKernelImage
+    })?;
+    let kernel = KernelImage::parse(&kernel_base[..len])?;
+    log_kernel(&kernel, compression);
+    let (offset, image_size) = (kernel.text_offset(), kernel.image_size());
+    if offset + image_size > kernel_base.len() {
+        return Err(RustbootError::BufferTooSmall);
+    }
+    kernel_base.copy_within(..len, offset);
+    Ok(kernel_base[offset..].as_ptr() as usize)
+}
+
+fn log_kernel(kernel: &KernelImage, compression: Compression) {
+    info!(
+        "kernel: text_offset: {:#x}, image_size: {:#x}, efi-stub: {}, compression: {:?}",
+        kernel.text_offset(),
+        kernel.image_size(),
+        kernel.is_efi_stub(),
+        compression
+    );
 }
 #[allow(dead_code)]
 /// Extracts and relocates the flattened device tree from a loaded fit-image to a
 /// (statically determined) location in bss.
-pub fn relocate_fdt(itb_blob: &[u8]) {
+///
+/// Fails if the fit-image has no fdt or if it doesn't fit the dtb's buffer.
+pub fn relocate_fdt(itb_blob: &[u8]) -> RbResult<()> {
     let fdt_entry = unsafe { DTB_LOAD_ADDR.0.as_mut() };
-    let fdt_data = get_image_data(itb_blob, "fdt");
-    match fdt_data {
-        Some(val) => {
-            let len = val.len();
-            assert!(len < unsafe { DTB_LOAD_ADDR.0.len() });
-            fdt_entry[..len].copy_from_slice(val);
-        }
-        None => {
-            panic!("itb has no fdt data")
-        }
-    }
+    let fdt_data = get_image_data(itb_blob, "fdt").ok_or_else(|| {
+        info!("itb has no fdt data");
+        RustbootError::InvalidImage
+    })?;
+    fdt_entry
+        .get_mut(..fdt_data.len())
+        .ok_or(RustbootError::BufferTooSmall)?
+        .copy_from_slice(fdt_data);
+    Ok(())
 }
 /// Extracts and relocates the ramdisk/initrd from a loaded fit-image to a
-/// (statically determined) location in bss.
-pub fn relocate_ramdisk(itb_blob: &[u8]) {
+/// (statically determined) location in bss. A compressed (i.e. `gzip`) ramdisk is decompressed
+/// in the process.
+///
+/// Returns the ramdisk's (decompressed) size. Fails if the fit-image has no ramdisk or if it
+/// can't be loaded.
+pub fn relocate_ramdisk(itb_blob: &[u8]) -> RbResult<usize> {
     let initrd_entry = unsafe { INITRAMFS_LOAD_ADDR.0.as_mut() };
-    let initrd_data = get_image_data(itb_blob, "ramdisk");
-    match initrd_data {
-        Some(val) => {
-            let len = val.len();
-            assert!(len < unsafe { INITRAMFS_LOAD_ADDR.0.len() });
-            initrd_entry[..len].copy_from_slice(val);
+    let len = load_image(itb_blob, "ramdisk", initrd_entry).map_err(|e| match e {
+        Error::MissingNode | Error::MissingProperty => {
+            info!("itb has no ramdisk data");
+            RustbootError::InvalidImage
         }
-        None => {
-            panic!("itb has no ramdisk data")
+        e => {
+            info!("failed to load the ramdisk: {:?}", e);
+            RustbootError::InvalidValue
         }
-    }
+    })?;
+    info!("ramdisk: {:#x} bytes", len);
+    Ok(len)
 }
 
 /// Relocates the kernel and ramdisk from a loaded fit-image to a
-/// (statically determined) location in bss and extracts the device-tree blob from the fit-image, patches
-/// it with contents of `rbconfig.txt` (i.e. linux cmdline parameters) and finally relocates it to a
-/// (statically determined) location in bss.
+/// (statically determined) location in bss and patches the device-tree blob (see [`select_dtb`])
+/// with the linux cmdline parameters (pointed at the `rootfs` slot, if any), the update's `report`
+/// (see [`patch_dtb`]) and finally relocates it to a (statically determined) location in bss.
 ///
-/// **note:** This function can fail if `patching` fails.
+/// Returns the kernel's entry point.
 ///
-pub fn relocate_and_patch<'a>(itb_blob: &'a [u8]) -> Result<&'a [u8]> {
-    let _ = relocate_kernel(itb_blob);
-    info!("relocating kernel to addr: {:p}", unsafe {
-        &KERNEL_LOAD_ADDR.0
-    });
-    let _ = relocate_ramdisk(itb_blob);
+/// **note:** This function fails if the kernel isn't a valid `Image` (for the board's
+/// architecture), if the fit-image's ramdisk can't be relocated or if `patching` fails.
+///
+pub fn relocate_and_patch(
+    itb_blob: &[u8],
+    dtb_blob: &[u8],
+    cmdline: Option<&[u8]>,
+    report: Option<UpdateReport>,
+    rootfs: Option<RootfsSlot>,
+) -> RbResult<usize> {
+    let kernel_entry = relocate_kernel(itb_blob)?;
+    info!("relocating kernel to addr: {:#x}", kernel_entry);
+    relocate_ramdisk(itb_blob)?;
     info!("relocating initrd to addr: {:p}", unsafe {
         &INITRAMFS_LOAD_ADDR.0
     });
-    let res = patch_dtb(itb_blob);
+    let res = patch_dtb(itb_blob, dtb_blob, cmdline, report, rootfs);
     match res {
-        Ok((buf, len)) => {
+        Ok((buf, _len)) => {
             info!("relocating dtb to addr: {:p}\n", buf.as_slice());
-            Ok(&buf[..len])
+            Ok(kernel_entry)
+        }
+        Err(e) => {
+            info!("dtb patching failed: {:?}", e);
+            Err(RustbootError::InvalidValue)
         }
-        Err(e) => return Err(e),
     }
 }
 
+/// Loads the SD card's `cmdline.txt` (an unsigned kernel command line override, in the
+/// `rbconfig.txt` format) into `buf`, if there's one.
+pub fn load_cmdline<'a, D, T>(
+    volume: &mut Volume,
+    ctrlr: &mut Controller<D, T>,
+    buf: &'a mut [u8],
+) -> Option<&'a [u8]>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    let root_dir = ctrlr.open_root_dir(&volume).ok()?;
+    let cmdline = match ctrlr.open_file_in_dir(volume, &root_dir, "CMDLINE.TXT", Mode::ReadOnly) {
+        Ok(mut file) => {
+            let num_read = ctrlr.read(&volume, &mut file, buf);
+            let _ = ctrlr.close_file(&volume, file);
+            num_read.ok().map(|num_read| &buf[..num_read])
+        }
+        Err(_) => None,
+    };
+    ctrlr.close_dir(&volume, root_dir);
+    cmdline
+}
+
 /// Short file names do not include the `.`, separating the
 /// filename and file-extension.
 ///
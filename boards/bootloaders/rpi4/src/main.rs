@@ -4,18 +4,23 @@
 #![allow(warnings)]
 
 mod boot;
+#[cfg(feature = "chainload")]
+mod chainload;
 mod dtb;
 mod fit;
 mod log;
 
-use boot::{boot_kernel, DTB_LOAD_ADDR, ITB_LOAD_ADDR, KERNEL_LOAD_ADDR};
-use fit::{load_fit, relocate_and_patch, verify_authenticity};
+use boot::{boot_kernel, clean_boot_images, DTB_LOAD_ADDR, FAT_CACHE, ITB_LOAD_ADDR};
+use fit::{load_cmdline, load_fit, relocate_and_patch, select_dtb, verify_authenticity};
 
 use rustBoot::{
-    dt::FALLBACK_TO_ACTIVE_IMG,
-    fs::controller::{Controller, TestClock, VolumeIdx},
-    fs::filesystem::Directory,
-    RustbootError,
+    cfgparser::RootfsSlot,
+    dt::{get_config_loadable, UpdateReport, FALLBACK_TO_ACTIVE_IMG, IS_PASSIVE_SELECTED},
+    fs::blockdevice::{BlockDevice, Statistics as BlockStatistics},
+    fs::boot_source::{first_bootable, BootSource, DEFAULT_BOOT_ORDER},
+    fs::controller::Controller,
+    fs::filesystem::{Directory, TimeSource},
+    Recovery, Result as RbResult, RustbootError,
 };
 use rustBoot_hal::rpi::rpi4::bsp::{
     drivers::{common::interface::DriverManager, driver_manager::driver_manager},
@@ -23,16 +28,23 @@ use rustBoot_hal::rpi::rpi4::bsp::{
     global::EMMC_CONT,
 };
 use rustBoot_hal::rpi::rpi4::{
+    arch::time::SystemCounterClock,
     exception,
     log::{
         console,
         console::{Read, Statistics},
+        print,
+        print::Verbosity,
     },
     memory::{layout::interface::MMU, mmu::mmu, vmm},
 };
-use rustBoot_hal::{info, println};
+use rustBoot_hal::{debug, info, println};
 use zeroize::Zeroize;
 
+/// The order in which boot sources are tried - the primary partition, a recovery partition and
+/// then the network/usb sources (where the board supports them).
+const BOOT_ORDER: [BootSource; 4] = DEFAULT_BOOT_ORDER;
+
 /// Early init code.
 ///
 /// # Safety
@@ -64,6 +76,119 @@ fn init_logger() {
     };
 }
 
+/// What a boot source's verified fit-image boots.
+enum BootEntry {
+    /// a kernel, at its entry point
+    Kernel(usize),
+    /// a next-stage bootloader i.e. the fit-image's loadable, at its entry point (see `chainload`)
+    #[cfg(feature = "chainload")]
+    Chainload(usize),
+}
+
+/// Loads, verifies and relocates a fit-image from `source` (see [`load_fit`] and
+/// [`relocate_and_patch`]). Returns the kernel's entry point. A kernel that isn't a valid ARM64
+/// `Image` is never jumped to.
+///
+/// With the `chainload` feature, a fit-image whose default config carries a loadable boots it
+/// instead of its kernel (see `chainload::load_next_stage`).
+fn boot_source<D, T>(ctrlr: &mut Controller<D, T>, source: BootSource) -> RbResult<BootEntry>
+where
+    D: BlockDevice,
+    D::Error: core::fmt::Debug + Into<RustbootError>,
+    T: TimeSource,
+{
+    let volume_idx = match source.volume() {
+        Some(volume_idx) => volume_idx,
+        None => {
+            info!("{} boot is not supported on this board", source);
+            return Err(RustbootError::InvalidValue);
+        }
+    };
+    let mut volume = ctrlr.get_volume(volume_idx).map_err(|e| {
+        info!("failed to open fat32 volume/partition, {:?}", e);
+        RustbootError::from(e)
+    })?;
+    ctrlr
+        .populate_fat_cache(&volume, unsafe { &mut FAT_CACHE })
+        .map_err(|e| {
+            info!("error populating fat_cache, {:?}", e);
+            RustbootError::from(e)
+        })?;
+    info!("fat cache populated ...");
+
+    let mut cmdline_buf = [0u8; 512];
+    let cmdline = load_cmdline(&mut volume, ctrlr, &mut cmdline_buf);
+    let (itb_blob, version, digests, rootfs) = load_fit(&mut volume, ctrlr)?;
+    // the passive image's version, if an update is being attempted
+    let update = unsafe { IS_PASSIVE_SELECTED.get().map(|_| version) };
+    let report;
+    let res = match verify_authenticity(version, digests.as_ref()) {
+        Err(RustbootError::BadVersion)
+            if unsafe { *FALLBACK_TO_ACTIVE_IMG.get().unwrap_or(&false) } =>
+        {
+            // passive image version check failed
+            // falling back to active
+            // FALLBACK_TO_ACTIVE_IMG is set to true.
+            info!("### passive-image version check failed, falling back to active...###");
+            report = update.map(|version| UpdateReport::failed(RustbootError::BadVersion, version));
+            let _ = unsafe { &mut ITB_LOAD_ADDR.0.zeroize() };
+            let (itb_blob, version, digests, rootfs) = load_fit(&mut volume, ctrlr)?;
+            verify_authenticity(version, digests.as_ref()).map(|val| (val, itb_blob, rootfs))
+        }
+        res => {
+            report = update.map(UpdateReport::booted);
+            res.map(|val| (val, itb_blob, rootfs))
+        }
+    };
+    let res = match res {
+        Ok((true, itb_blob, rootfs)) => {
+            select_dtb(itb_blob, &mut volume, ctrlr).and_then(|(dtb_blob, dtb_source)| {
+                info!("using the {}", dtb_source);
+                boot_entry(itb_blob, dtb_blob, cmdline, report, rootfs)
+            })
+        }
+        Ok((false, _, _)) => Err(RustbootError::FwAuthFailed),
+        Err(e) => Err(e),
+    };
+    if res.is_err() {
+        // don't leave a rejected image lying around for the next source.
+        let _ = unsafe { &mut ITB_LOAD_ADDR.0.zeroize() };
+    }
+    res
+}
+
+/// Relocates the fit-image's kernel (see [`relocate_and_patch`]) or, with the `chainload` feature,
+/// its loadable (if it carries one).
+fn boot_entry(
+    itb_blob: &[u8],
+    dtb_blob: &[u8],
+    cmdline: Option<&[u8]>,
+    report: Option<UpdateReport>,
+    rootfs: Option<RootfsSlot>,
+) -> RbResult<BootEntry> {
+    let loadable = get_config_loadable(itb_blob).map_err(|e| {
+        info!("loadables: {:?}", e);
+        RustbootError::InvalidImage
+    })?;
+    match loadable {
+        #[cfg(feature = "chainload")]
+        Some(loadable) => {
+            chainload::load_next_stage(itb_blob, dtb_blob, &loadable).map(BootEntry::Chainload)
+        }
+        #[cfg(not(feature = "chainload"))]
+        Some(loadable) => {
+            info!(
+                "ignoring loadable {}, chainloading is disabled",
+                loadable.name
+            );
+            relocate_and_patch(itb_blob, dtb_blob, cmdline, report, rootfs).map(BootEntry::Kernel)
+        }
+        None => {
+            relocate_and_patch(itb_blob, dtb_blob, cmdline, report, rootfs).map(BootEntry::Kernel)
+        }
+    }
+}
+
 /// The main function running after the early init.
 ///
 /// active_fitimage=true,image_name=xx.itb,image_version=xxx
@@ -76,26 +201,29 @@ fn kernel_main() -> ! {
     );
     info!("Booting on: {}", global::board_name());
 
-    info!("MMU online. Special regions:");
-    vmm::virt_mem_layout().print_layout();
+    // the board's state, only at `Verbosity::Debug`
+    if print::enabled(Verbosity::Debug) {
+        debug!("MMU online. Special regions:");
+        vmm::virt_mem_layout().print_layout();
 
-    let (_, privilege_level) = exception::exception::current_privilege_level();
-    info!("Current privilege level: {}", privilege_level);
+        let (_, privilege_level) = exception::exception::current_privilege_level();
+        debug!("Current privilege level: {}", privilege_level);
 
-    info!("Exception handling state:");
-    exception::asynchronous::print_state();
+        debug!("Exception handling state:");
+        exception::asynchronous::print_state();
 
-    info!(
-        "Architectural timer resolution: {} ns",
-        time_manager().resolution().as_nanos()
-    );
+        debug!(
+            "Architectural timer resolution: {} ns",
+            time_manager().resolution().as_nanos()
+        );
 
-    info!("Drivers loaded:");
-    for (i, driver) in driver_manager().all_device_drivers().iter().enumerate() {
-        info!("      {}. {}", i + 1, driver.compatible());
-    }
+        debug!("Drivers loaded:");
+        for (i, driver) in driver_manager().all_device_drivers().iter().enumerate() {
+            debug!("      {}. {}", i + 1, driver.compatible());
+        }
 
-    info!("Chars written: {}", console::console().chars_written());
+        debug!("Chars written: {}", console::console().chars_written());
+    }
 
     // Discard any spurious received characters before going into echo mode.
     console::console().clear_rx();
@@ -103,72 +231,68 @@ fn kernel_main() -> ! {
     // initialize logger.
     // init_logger();
 
-    let mut ctrlr = Controller::new(&EMMC_CONT, TestClock);
-    let volume = ctrlr.get_volume(VolumeIdx(0));
-    match volume {
-        Ok(mut volume) => {
-            let _fat_cache = match ctrlr.populate_fat_cache(&volume) {
-                Ok(_val) => {
-                    info!("fat cache populated ...")
-                }
-                Err(e) => {
-                    panic!("error populating fat_cache, {:?}", e)
-                }
-            };
-            let (itb_blob, version) = load_fit(&mut volume, &mut ctrlr);
-            let res = verify_authenticity(version);
-
-            match res {
-                Ok(val) => match val {
-                    true => {
-                        let _ = relocate_and_patch(itb_blob); // relocate kernel, ramdisk and patch dtb
-                    }
-                    false => panic!("signature verification result: {}", val),
-                },
-                Err(e)
-                    if (e == RustbootError::BadVersion
-                        && unsafe { *FALLBACK_TO_ACTIVE_IMG.get().unwrap_or(&false) }) =>
-                {
-                    // passive image version check failed
-                    // falling back to active
-                    // FALLBACK_TO_ACTIVE_IMG is set to true.
-                    {
-                        info!("### passive-image version check failed, falling back to active...###");
-                        let _ = unsafe { &mut ITB_LOAD_ADDR.0.zeroize() };
-                        let (itb_blob, version) = load_fit(&mut volume, &mut ctrlr);
-                        let res = verify_authenticity(version);
-                        match res {
-                            Ok(val) => match val {
-                                true => {
-                                    let _ = relocate_and_patch(itb_blob); // relocate kernel, ramdisk and patch dtb
-                                }
-                                false => unreachable!("this should be unreachable"), 
-                            },
-                            // by definition, this shouldn't be possible. An active image must have been
-                            // successfully verified and booted at least once.
-                            Err(e) => unreachable!("active-image boot failed, {}", e),
-                        }
-                    }
+    let mut ctrlr = Controller::new(
+        &EMMC_CONT,
+        SystemCounterClock::new(SystemCounterClock::FAT_EPOCH),
+    );
+    let entry = match first_bootable(
+        &BOOT_ORDER,
+        |source| {
+            // a transient (i.e. hardware) error gets a second attempt, before moving on.
+            boot_source(&mut ctrlr, source).or_else(|e| match e.recovery() {
+                Recovery::Retry => {
+                    info!("boot source {} failed: {}, retrying", source, e);
+                    boot_source(&mut ctrlr, source)
                 }
-                Err(e) => panic!("error: image verification failed, {}", e),
-            }
+                _ => Err(e),
+            })
+        },
+        |source, e| match e.recovery() {
+            Recovery::Halt => panic!("boot source {} failed: {}, halting", source, e),
+            _ => info!("boot source {} failed: {}, trying the next one", source, e),
+        },
+    ) {
+        Some((source, entry)) => {
+            info!("booting from {}", source);
+            entry
         }
-        Err(e) => {
-            panic!("failed to open fat32 volume/partition, {:?}", e)
-        }
-    }
-
-    println!(
-        "\x1b[5m\x1b[34m*************** \
-            Starting kernel \
-            ***************\x1b[0m\n"
+        None => panic!("error: all boot sources exhausted"),
+    };
+    info!(
+        "EMMC: {} read retries, {} failed reads, {} re-inits, {} clock downshifts",
+        EMMC_CONT.read_retries(),
+        EMMC_CONT.read_failures(),
+        EMMC_CONT.reinits(),
+        EMMC_CONT.clock_downshifts()
     );
 
-    unsafe {
-        mmu().disable_mmu_and_caching();
-        boot_kernel(
-            { &mut KERNEL_LOAD_ADDR.0 }.as_ptr() as usize,
-            { &mut DTB_LOAD_ADDR.0 }.as_ptr() as usize,
-        )
+    let dtb_addr = unsafe { { &mut DTB_LOAD_ADDR.0 }.as_ptr() as usize };
+    match entry {
+        BootEntry::Kernel(kernel_entry) => {
+            println!(
+                "\x1b[5m\x1b[34m*************** \
+                    Starting kernel \
+                    ***************\x1b[0m\n"
+            );
+            clean_boot_images();
+            unsafe {
+                mmu().disable_mmu_and_caching();
+                boot_kernel(kernel_entry, dtb_addr)
+            }
+        }
+        #[cfg(feature = "chainload")]
+        BootEntry::Chainload(entry) => {
+            println!(
+                "\x1b[5m\x1b[34m*************** \
+                    Starting next stage \
+                    ***************\x1b[0m\n"
+            );
+            clean_boot_images();
+            chainload::clean_handoff();
+            unsafe {
+                mmu().disable_mmu_and_caching();
+                boot::chainload(entry, dtb_addr, chainload::handoff_addr())
+            }
+        }
     }
 }
@@ -3,3 +3,6 @@ MEMORY {
     CCMRAM (rwx) : ORIGIN = 0x10000000, LENGTH = 4K
     RAM (rwx) : ORIGIN = 0x20000000, LENGTH = 12K
 }
+
+/* checks that rustBoot ends below the boot partition, see `cargo stm32f334 gen layout` */
+INCLUDE partitions.x
@@ -0,0 +1,15 @@
+MEMORY
+{
+  /* NOTE 1 K = 1 KiBi = 1024 bytes */
+  /* rustBoot occupies sectors 0-4 i.e. everything up to the boot partition */
+  FLASH    (rx)  : ORIGIN = 0x08000000, LENGTH = 128K
+  /* SRAM1 and SRAM2 (the 64K CCM RAM at 0x10000000 isn't used) */
+  RAM      (rwx) : ORIGIN = 0x20000000, LENGTH = 128K
+}
+
+/* This is where the call stack will be allocated. */
+/* The stack is of the full descending type. */
+/* _stack_start = ORIGIN(RAM) + LENGTH(RAM); */
+
+/* checks that rustBoot ends below the boot partition, see `cargo stm32f407 gen layout` */
+INCLUDE partitions.x
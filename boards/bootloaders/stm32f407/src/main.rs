@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+// #[cfg(feature = "defmt")]
+// use defmt_rtt as _; // global logger
+// use panic_probe as _;
+
+use rustBoot_hal::stm::stm32f407::FlashWriterEraser;
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    updater.rustboot_start()
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    #[cfg(feature = "panic-record")]
+    rustBoot_hal::panic_record::record_and_reset(_info);
+    #[cfg(not(feature = "panic-record"))]
+    loop {
+        cortex_m::asm::bkpt();
+    }
+}
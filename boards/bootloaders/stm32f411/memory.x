@@ -32,4 +32,7 @@ MEMORY
        . = ALIGN(4);
      } > RAM2
    } INSERT AFTER .bss;
-*/
\ No newline at end of file
+*/
+
+/* checks that rustBoot ends below the boot partition, see `cargo stm32f411 gen layout` */
+INCLUDE partitions.x
@@ -15,7 +15,10 @@ fn main() -> ! {
 }
 
 #[panic_handler] // panicking behavior
-fn panic(_: &core::panic::PanicInfo) -> ! {
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    #[cfg(feature = "panic-record")]
+    rustBoot_hal::panic_record::record_and_reset(_info);
+    #[cfg(not(feature = "panic-record"))]
     loop {
         cortex_m::asm::bkpt();
     }
@@ -9,4 +9,7 @@ MEMORY
 /* This is where the call stack will be allocated. */
 /* The stack is of the full descending type. */
 /* NOTE Do NOT modify `_stack_start` unless you know what you are doing */
-/*_stack_start = ORIGIN(RAM) + LENGTH(RAM); */
\ No newline at end of file
+/*_stack_start = ORIGIN(RAM) + LENGTH(RAM); */
+
+/* checks that rustBoot ends below the boot partition, see `cargo stm32f746 gen layout` */
+INCLUDE partitions.x
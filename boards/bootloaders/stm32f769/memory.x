@@ -0,0 +1,17 @@
+/* For STM32F7{65,67,69,77,79} devices */
+MEMORY
+{
+  /* NOTE K = KiBi = 1024 bytes */
+  /* rustBoot occupies sectors 0-4 i.e. everything up to the boot partition */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 256K
+  /* DTCM, SRAM1 and SRAM2 */
+  RAM : ORIGIN = 0x20000000, LENGTH = 512K
+}
+
+/* This is where the call stack will be allocated. */
+/* The stack is of the full descending type. */
+/* NOTE Do NOT modify `_stack_start` unless you know what you are doing */
+/*_stack_start = ORIGIN(RAM) + LENGTH(RAM); */
+
+/* checks that rustBoot ends below the boot partition, see `cargo stm32f769 gen layout` */
+INCLUDE partitions.x
@@ -0,0 +1,6 @@
+/* @generated by `cargo stm32f769 gen layout` from `boards/manifests/stm32f769.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_boot_partition = 0x8040000;
+ASSERT(LOADADDR(.data) + SIZEOF(.data) <= __rustboot_boot_partition,
+       "rustBoot runs into the boot partition, see boards/manifests/stm32f769.toml");
@@ -0,0 +1,26 @@
+#![no_main]
+#![no_std]
+
+#[cfg(feature = "defmt")]
+use defmt_rtt as _; // global logger
+
+use cortex_m_rt::entry;
+
+use rustBoot_hal::stm::stm32f769::FlashWriterEraser;
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+
+#[entry]
+fn main() -> ! {
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    updater.rustboot_start()
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    #[cfg(feature = "panic-record")]
+    rustBoot_hal::panic_record::record_and_reset(_info);
+    #[cfg(not(feature = "panic-record"))]
+    loop {
+        cortex_m::asm::bkpt();
+    }
+}
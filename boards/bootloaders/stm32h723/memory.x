@@ -20,3 +20,6 @@ MEMORY
   /* DTCM  */
   RAM    : ORIGIN = 0x20000000, LENGTH = 128K
 }
+
+/* checks that rustBoot ends below the boot partition, see `cargo stm32h723 gen layout` */
+INCLUDE partitions.x
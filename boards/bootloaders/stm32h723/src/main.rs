@@ -11,12 +11,16 @@ use cortex_m_rt::entry;
 
 #[entry]
 fn main() -> ! {
-    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    // reads of the h7's flash trap double-bit ECC errors, see `rustBoot_hal::ecc`
+    let updater = FlashUpdater::new(FlashWriterEraser::new()).with_checked_reads();
     updater.rustboot_start()
 }
 
 #[panic_handler] // panicking behavior
-fn panic(_: &core::panic::PanicInfo) -> ! {
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    #[cfg(feature = "panic-record")]
+    rustBoot_hal::panic_record::record_and_reset(_info);
+    #[cfg(not(feature = "panic-record"))]
     loop {
         cortex_m::asm::bkpt();
     }
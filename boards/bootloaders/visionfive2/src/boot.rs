@@ -0,0 +1,146 @@
+//! Architectural boot code.
+
+use core::arch::global_asm;
+use rustBoot::fs::controller::{FatCache, MAX_FAT_SECTORS};
+use rustBoot::kernel::Riscv64Image;
+use rustBoot_hal::starfive::visionfive2::arch::cpu_core::{
+    disable_interrupts, sync_boot_images, wait_forever,
+};
+use zeroize::Zeroize;
+
+// Assembly counterpart to this file.
+global_asm!(include_str!("boot.s"));
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// The Rust entry of the `rustBoot` binary.
+///
+/// The function is called from the assembly `_start` function, with OpenSBI's arguments i.e. the
+/// boot hart's id and the firmware's dtb.
+///
+/// # Safety
+///
+/// - Only the boot hart must run this function.
+#[no_mangle]
+pub unsafe extern "C" fn _start_rust(hartid: usize, fw_dtb_addr: usize) -> ! {
+    crate::kernel_init(hartid, fw_dtb_addr)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The kernel image format this board boots, see `fit::relocate_kernel`.
+pub type KernelImage<'a> = Riscv64Image<'a>;
+/// The standalone dtb, only used if the fit-image doesn't carry an fdt (see `fit::select_dtb`).
+pub const DTB_NAME: &str = "jh7110-starfive-visionfive-2-v1.3b.dtb";
+/// The standalone dtb's sha256 digest, as hex (i.e. `sha256sum`'s output).
+pub const DTB_DIGEST_NAME: &str = "jh7110-starfive-visionfive-2-v1.3b.dtb.sha256";
+/// The rootfs partitions of an A/B update's slots (see `cfgparser::RootfsSlot`) i.e. the micro-SD card's (i.e. `sdio1`'s) 2nd and 3rd partitions.
+pub const ROOTFS_PARTITIONS: [&str; 2] = ["/dev/mmcblk1p2", "/dev/mmcblk1p3"];
+
+const MAX_INITRAMFS_SIZE: usize = 16066 * 4 * 512;
+const MAX_KERNEL_SIZE: usize = 14624 * 4 * 512;
+pub(crate) const MAX_DTB_SIZE: usize = 100 * 512;
+const MAX_ITB_SIZE: usize = 32000 * 4 * 512;
+
+/// A statically determined region of memory for the initial ramdisk i.e.
+/// serves as the ramdisk's entry point.
+pub struct InitRamfsEntry(pub [u8; MAX_INITRAMFS_SIZE]);
+#[repr(align(2097152))]
+/// A statically determined region of memory for the kernel i.e.
+/// serves as the kernel's entry point.
+pub struct KernelEntry(pub [u8; MAX_KERNEL_SIZE]);
+#[repr(align(8))]
+/// A statically determined region of memory for the device-tree blob i.e.
+/// serves as the dtb's entry point. RISC-V Linux only needs it 8-byte aligned.
+pub struct DtbEntry(pub [u8; MAX_DTB_SIZE]);
+#[repr(align(8))]
+/// A statically determined region of memory for the standalone dtb (i.e. [`DTB_NAME`]), which is
+/// read from the FAT partition when the fit-image doesn't carry an fdt.
+pub struct DtbFile(pub [u8; MAX_DTB_SIZE]);
+#[derive(Zeroize)]
+/// A statically determined region of memory for the image-tree (or fit-image) blob i.e.
+/// serves as the fit-image's entry point.
+pub struct ImageTreeEntry(pub [u8; MAX_ITB_SIZE]);
+
+impl ImageTreeEntry {
+    /// Get an entry point to the ITB.
+    pub const fn new() -> Self {
+        Self([0u8; MAX_ITB_SIZE])
+    }
+}
+
+impl KernelEntry {
+    /// Get a 2MB aligned base for the kernel. RISC-V kernels are placed at their `text_offset`
+    /// from a 2MB aligned base (4KiB aligned is the minimum, see `Riscv64Image`).
+    pub const fn new() -> Self {
+        Self([0u8; MAX_KERNEL_SIZE])
+    }
+}
+
+impl DtbEntry {
+    /// Get an entry point to the DTB.
+    pub const fn new() -> Self {
+        Self([0u8; MAX_DTB_SIZE])
+    }
+}
+
+impl DtbFile {
+    /// Get a buffer for the standalone DTB.
+    pub const fn new() -> Self {
+        Self([0u8; MAX_DTB_SIZE])
+    }
+}
+
+impl InitRamfsEntry {
+    /// Get an entry point to the `initramfs`.
+    pub const fn new() -> Self {
+        Self([0u8; MAX_INITRAMFS_SIZE])
+    }
+}
+
+pub static mut INITRAMFS_LOAD_ADDR: InitRamfsEntry = InitRamfsEntry::new();
+pub static mut KERNEL_LOAD_ADDR: KernelEntry = KernelEntry::new();
+pub static mut DTB_LOAD_ADDR: DtbEntry = DtbEntry::new();
+pub static mut DTB_FILE_ADDR: DtbFile = DtbFile::new();
+pub static mut ITB_LOAD_ADDR: ImageTreeEntry = ImageTreeEntry::new();
+/// The FAT32 volume's file allocation table, see `Controller::populate_fat_cache`.
+pub static mut FAT_CACHE: FatCache<MAX_FAT_SECTORS> = FatCache::new();
+
+/// Makes the kernel, initramfs and dtb visible to the kernel i.e. orders rustBoot's stores before
+/// the jump and synchronizes the instruction stream. The U74's caches are coherent, so there's
+/// nothing to clean.
+pub fn clean_boot_images() {
+    sync_boot_images();
+}
+
+#[no_mangle]
+#[inline(never)]
+/// Jump to kernel, per the RISC-V Linux boot protocol.
+///
+/// rustBoot and the kernel both run in S-mode, OpenSBI stays resident in M-mode. The kernel is
+/// entered with the MMU off (`satp` zeroed), interrupts masked, `a0` holding the boot hart's id
+/// and `a1` the dtb's address. The other harts are started by the kernel, through SBI's HSM
+/// extension.
+pub fn boot_kernel(kernel_entry: usize, hartid: usize, dtb_addr: usize) -> ! {
+    disable_interrupts();
+    unsafe {
+        core::arch::asm!(
+            "csrw sie, zero",
+            "csrw satp, zero",
+            "sfence.vma",
+            "jr {entry}",
+            entry = in(reg) kernel_entry,
+            in("a0") hartid,
+            in("a1") dtb_addr,
+            options(noreturn)
+        )
+    }
+}
+
+pub fn halt() -> ! {
+    wait_forever()
+}
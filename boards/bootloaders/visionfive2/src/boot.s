@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+.section .text._start
+
+//------------------------------------------------------------------------------
+// fn _start()
+//------------------------------------------------------------------------------
+// OpenSBI enters its payload in S-mode, with the MMU off, on a single hart - the others are held
+// by its HSM extension. a0 holds the boot hart's id and a1 the firmware's dtb.
+_start:
+	// Mask interrupts, OpenSBI may have left some enabled.
+	csrw	sie, zero
+	csrci	sstatus, 0x2
+
+	// Zero out the bss memory-range. a0 and a1 are preserved.
+	la	t0, __bss_start
+	la	t1, __bss_end_exclusive
+.L_bss_init_loop:
+	bgeu	t0, t1, .L_prepare_rust
+	sd	zero, (t0)
+	addi	t0, t0, 8
+	j	.L_bss_init_loop
+
+	// Prepare the jump to Rust code.
+.L_prepare_rust:
+	// Set the stack pointer.
+	la	sp, __boot_core_stack_end_exclusive
+
+	// Jump to Rust code i.e. `_start_rust(hartid, fdt)`.
+	tail	_start_rust
+
+.size	_start, . - _start
+.type	_start, function
+.global	_start
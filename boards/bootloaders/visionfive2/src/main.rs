@@ -0,0 +1,194 @@
+#![no_std]
+#![no_main]
+#![feature(format_args_nl, core_intrinsics, once_cell)]
+#![allow(warnings)]
+
+mod boot;
+// the fit-image and dtb handling is shared with the rpi4 i.e. it's architecture-neutral, the
+// board-specific bits (kernel format, dtb name, load buffers) come from `boot`.
+#[path = "../../rpi4/src/dtb.rs"]
+mod dtb;
+#[path = "../../rpi4/src/fit.rs"]
+mod fit;
+
+use boot::{boot_kernel, clean_boot_images, DTB_LOAD_ADDR, FAT_CACHE, ITB_LOAD_ADDR};
+use fit::{load_cmdline, load_fit, relocate_and_patch, select_dtb, verify_authenticity};
+
+use rustBoot::{
+    dt::{UpdateReport, FALLBACK_TO_ACTIVE_IMG, IS_PASSIVE_SELECTED},
+    fs::blockdevice::{BlockDevice, Statistics as BlockStatistics},
+    fs::boot_source::{first_bootable, BootSource, DEFAULT_BOOT_ORDER},
+    fs::controller::Controller,
+    fs::filesystem::{Directory, TimeSource},
+    Recovery, Result as RbResult, RustbootError,
+};
+use rustBoot_hal::starfive::visionfive2::{
+    arch::time::SystemCounterClock,
+    bsp::{drivers::dwmmc::DwMmc, global},
+    log::{
+        console,
+        console::{Read, Statistics},
+    },
+};
+use rustBoot_hal::{info, println};
+use zeroize::Zeroize;
+
+/// The order in which boot sources are tried - the primary partition, a recovery partition and
+/// then the network/usb sources (where the board supports them).
+const BOOT_ORDER: [BootSource; 4] = DEFAULT_BOOT_ORDER;
+
+/// Early init code.
+///
+/// # Safety
+///
+/// - Only a single hart must be active and running this function.
+unsafe fn kernel_init(hartid: usize, fw_dtb_addr: usize) -> ! {
+    // the SPL has already set up the UART's pins and clock.
+    global::UART.init();
+    // println! is usable from here on.
+
+    // Transition from unsafe to safe.
+    kernel_main(hartid, fw_dtb_addr)
+}
+
+/// Picks the card to boot from - the micro-SD card if there's one, else the eMMC module.
+fn boot_device() -> &'static DwMmc {
+    for sdio in [&global::SDIO1, &global::SDIO0] {
+        match sdio.init() {
+            Ok(()) => {
+                info!("{}: found a {:?} card", sdio.name(), sdio.card_type());
+                return sdio;
+            }
+            Err(e) => info!("{}: no usable card, {:?}", sdio.name(), e),
+        }
+    }
+    panic!("error: no SD card or eMMC found")
+}
+
+/// Loads, verifies and relocates a fit-image from `source` (see [`load_fit`] and
+/// [`relocate_and_patch`]). Returns the kernel's entry point. A kernel that isn't a valid RISC-V
+/// `Image` is never jumped to.
+fn boot_source<D, T>(ctrlr: &mut Controller<D, T>, source: BootSource) -> RbResult<usize>
+where
+    D: BlockDevice,
+    D::Error: core::fmt::Debug + Into<RustbootError>,
+    T: TimeSource,
+{
+    let volume_idx = match source.volume() {
+        Some(volume_idx) => volume_idx,
+        None => {
+            info!("{} boot is not supported on this board", source);
+            return Err(RustbootError::InvalidValue);
+        }
+    };
+    let mut volume = ctrlr.get_volume(volume_idx).map_err(|e| {
+        info!("failed to open fat32 volume/partition, {:?}", e);
+        RustbootError::from(e)
+    })?;
+    ctrlr
+        .populate_fat_cache(&volume, unsafe { &mut FAT_CACHE })
+        .map_err(|e| {
+            info!("error populating fat_cache, {:?}", e);
+            RustbootError::from(e)
+        })?;
+    info!("fat cache populated ...");
+
+    let mut cmdline_buf = [0u8; 512];
+    let cmdline = load_cmdline(&mut volume, ctrlr, &mut cmdline_buf);
+    let (itb_blob, version, digests, rootfs) = load_fit(&mut volume, ctrlr)?;
+    // the passive image's version, if an update is being attempted
+    let update = unsafe { IS_PASSIVE_SELECTED.get().map(|_| version) };
+    let report;
+    let res = match verify_authenticity(version, digests.as_ref()) {
+        Err(RustbootError::BadVersion)
+            if unsafe { *FALLBACK_TO_ACTIVE_IMG.get().unwrap_or(&false) } =>
+        {
+            // passive image version check failed
+            // falling back to active
+            // FALLBACK_TO_ACTIVE_IMG is set to true.
+            info!("### passive-image version check failed, falling back to active...###");
+            report = update.map(|version| UpdateReport::failed(RustbootError::BadVersion, version));
+            let _ = unsafe { &mut ITB_LOAD_ADDR.0.zeroize() };
+            let (itb_blob, version, digests, rootfs) = load_fit(&mut volume, ctrlr)?;
+            verify_authenticity(version, digests.as_ref()).map(|val| (val, itb_blob, rootfs))
+        }
+        res => {
+            report = update.map(UpdateReport::booted);
+            res.map(|val| (val, itb_blob, rootfs))
+        }
+    };
+    let res = match res {
+        Ok((true, itb_blob, rootfs)) => {
+            select_dtb(itb_blob, &mut volume, ctrlr).and_then(|(dtb_blob, dtb_source)| {
+                info!("using the {}", dtb_source);
+                relocate_and_patch(itb_blob, dtb_blob, cmdline, report, rootfs)
+            })
+        }
+        Ok((false, _, _)) => Err(RustbootError::FwAuthFailed),
+        Err(e) => Err(e),
+    };
+    if res.is_err() {
+        // don't leave a rejected image lying around for the next source.
+        let _ = unsafe { &mut ITB_LOAD_ADDR.0.zeroize() };
+    }
+    res
+}
+
+/// The main function running after the early init.
+fn kernel_main(hartid: usize, fw_dtb_addr: usize) -> ! {
+    info!(
+        "{} version {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+    info!("Booting on: {}", global::board_name());
+    info!("boot hart: {}, firmware dtb @ {:#x}", hartid, fw_dtb_addr);
+    info!("Chars written: {}", console::console().chars_written());
+
+    // Discard any spurious received characters.
+    console::console().clear_rx();
+
+    let sdio = boot_device();
+    let mut ctrlr = Controller::new(sdio, SystemCounterClock::new(SystemCounterClock::FAT_EPOCH));
+    let kernel_entry = match first_bootable(
+        &BOOT_ORDER,
+        |source| {
+            // a transient (i.e. hardware) error gets a second attempt, before moving on.
+            boot_source(&mut ctrlr, source).or_else(|e| match e.recovery() {
+                Recovery::Retry => {
+                    info!("boot source {} failed: {}, retrying", source, e);
+                    boot_source(&mut ctrlr, source)
+                }
+                _ => Err(e),
+            })
+        },
+        |source, e| match e.recovery() {
+            Recovery::Halt => panic!("boot source {} failed: {}, halting", source, e),
+            _ => info!("boot source {} failed: {}, trying the next one", source, e),
+        },
+    ) {
+        Some((source, kernel_entry)) => {
+            info!("booting from {}", source);
+            kernel_entry
+        }
+        None => panic!("error: all boot sources exhausted"),
+    };
+    info!(
+        "{}: {} read retries, {} failed reads, {} re-inits",
+        sdio.name(),
+        sdio.read_retries(),
+        sdio.read_failures(),
+        sdio.reinits()
+    );
+
+    println!(
+        "\x1b[5m\x1b[34m*************** \
+            Starting kernel \
+            ***************\x1b[0m\n"
+    );
+
+    clean_boot_images();
+    boot_kernel(kernel_entry, hartid, unsafe {
+        { &mut DTB_LOAD_ADDR.0 }.as_ptr() as usize
+    })
+}
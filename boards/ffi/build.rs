@@ -0,0 +1,15 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Regenerates the C header (i.e. `include/rustboot.h`) from the crate's `extern "C"` API, see
+/// `cbindgen.toml`.
+fn main() {
+    let crate_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    cbindgen::generate(&crate_dir)
+        .expect("failed to generate the C header")
+        .write_to_file(crate_dir.join("include/rustboot.h"));
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src/lib.rs");
+}
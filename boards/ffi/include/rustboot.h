@@ -0,0 +1,96 @@
+/* rustBoot's update API, for firmware written in C. See `boards/ffi`. */
+
+/* Generated by cbindgen from `boards/ffi/src/lib.rs`, do not edit by hand. */
+
+#ifndef RUSTBOOT_H
+#define RUSTBOOT_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * A partition's state, see `rustBoot::image::image::PartitionState`.
+ */
+enum RustbootState {
+  /**
+   * no update has been triggered (or the partition holds no image).
+   */
+  RUSTBOOT_STATE_NEW = 0,
+  /**
+   * the update partition holds an update, to be installed on the next boot.
+   */
+  RUSTBOOT_STATE_UPDATING = 1,
+  /**
+   * the boot image is a freshly installed update, it's rolled back on the next boot unless
+   * it's confirmed.
+   */
+  RUSTBOOT_STATE_TESTING = 2,
+  /**
+   * the boot image is confirmed.
+   */
+  RUSTBOOT_STATE_SUCCESS = 3,
+};
+typedef uint8_t RustbootState;
+
+/**
+ * The boot and update images, as seen by the running firmware.
+ */
+typedef struct RustbootBootReport {
+  /**
+   * the boot (i.e. running) image's version.
+   */
+  uint32_t boot_version;
+  RustbootState boot_state;
+  /**
+   * the update image's version, `0` if the update partition holds no image.
+   */
+  uint32_t update_version;
+  RustbootState update_state;
+  /**
+   * debug-access protection, as read from the device i.e. `0` (the debug port is open), `1`
+   * (enabled, ex: STM32 RDP level 1) or `2` (permanent, STM32 RDP level 2).
+   */
+  uint8_t debug_protection;
+  /**
+   * set if `time` holds the board's RTC time.
+   */
+  bool has_time;
+  /**
+   * the time, in seconds since the unix epoch.
+   */
+  uint64_t time;
+} RustbootBootReport;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Marks the update staged in the update partition for installation. rustBoot verifies and
+ * installs it on the next boot (i.e. after a reset), in the `testing` state.
+ */
+int32_t rustboot_update_trigger(void);
+
+/**
+ * Confirms the running image i.e. an update that's being tested is kept. Unless it's confirmed,
+ * an update is rolled back on the next boot.
+ */
+int32_t rustboot_update_success(void);
+
+/**
+ * Fills in `report` with the boot and update images' versions and states.
+ *
+ * # Safety
+ *
+ * `report` must be null or point to a (writable) `RustbootBootReport`.
+ */
+int32_t rustboot_get_boot_report(struct RustbootBootReport *report);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RUSTBOOT_H */
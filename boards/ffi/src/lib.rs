@@ -0,0 +1,162 @@
+//! C bindings to rustBoot's update API, for firmware written in C (or C++).
+//!
+//! The crate builds a static library (`librustboot.a`) for a board, see `cargo <board> build ffi`.
+//! Firmware links it and includes the header generated from this file (`include/rustboot.h`) to
+//!
+//! - trigger the update staged in the update partition i.e. [`rustboot_update_trigger`],
+//! - confirm a freshly installed update i.e. [`rustboot_update_success`] and
+//! - read the boot and update images' versions and states i.e. [`rustboot_get_boot_report`].
+//!
+//! ```c
+//! #include "rustboot.h"
+//!
+//! RustbootBootReport report;
+//! if (rustboot_get_boot_report(&report) == 0 && report.boot_state == RUSTBOOT_STATE_TESTING) {
+//!     rustboot_update_success();
+//! }
+//! ```
+//!
+//! Every function returns `0` on success or, on failure, a negative error i.e. `-1 - code` where
+//! `code` is rustBoot's error code (see `RustbootError::code`). The functions aren't reentrant,
+//! they mustn't be called from interrupt handlers.
+
+#![no_std]
+#![allow(non_snake_case)]
+
+use rustBoot::image::image::PartitionState;
+use rustBoot::RustbootError;
+use rustBoot_hal::{DebugProtection, FlashInterface};
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+
+#[cfg(feature = "nrf52840")]
+use rustBoot_hal::nrf::nrf52840::FlashWriterEraser;
+#[cfg(feature = "rp2040")]
+use rustBoot_hal::pico::rp2040::FlashWriterEraser;
+#[cfg(feature = "rp2350")]
+use rustBoot_hal::pico::rp2350::FlashWriterEraser;
+#[cfg(feature = "stm32f334")]
+use rustBoot_hal::stm::stm32f334::FlashWriterEraser;
+#[cfg(feature = "stm32f407")]
+use rustBoot_hal::stm::stm32f407::FlashWriterEraser;
+#[cfg(feature = "stm32f411")]
+use rustBoot_hal::stm::stm32f411::FlashWriterEraser;
+#[cfg(feature = "stm32f446")]
+use rustBoot_hal::stm::stm32f446::FlashWriterEraser;
+#[cfg(feature = "stm32f469")]
+use rustBoot_hal::stm::stm32f469::FlashWriterEraser;
+#[cfg(feature = "stm32f746")]
+use rustBoot_hal::stm::stm32f746::FlashWriterEraser;
+#[cfg(feature = "stm32f769")]
+use rustBoot_hal::stm::stm32f769::FlashWriterEraser;
+#[cfg(feature = "stm32h723")]
+use rustBoot_hal::stm::stm32h723::FlashWriterEraser;
+
+/// A partition's state, see `rustBoot::image::image::PartitionState`.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RustbootState {
+    /// no update has been triggered (or the partition holds no image).
+    New = 0,
+    /// the update partition holds an update, to be installed on the next boot.
+    Updating = 1,
+    /// the boot image is a freshly installed update, it's rolled back on the next boot unless
+    /// it's confirmed.
+    Testing = 2,
+    /// the boot image is confirmed.
+    Success = 3,
+}
+
+/// The boot and update images, as seen by the running firmware.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RustbootBootReport {
+    /// the boot (i.e. running) image's version.
+    pub boot_version: u32,
+    pub boot_state: RustbootState,
+    /// the update image's version, `0` if the update partition holds no image.
+    pub update_version: u32,
+    pub update_state: RustbootState,
+    /// debug-access protection, as read from the device i.e. `0` (the debug port is open), `1`
+    /// (enabled, ex: STM32 RDP level 1) or `2` (permanent, STM32 RDP level 2).
+    pub debug_protection: u8,
+    /// set if `time` holds the board's RTC time.
+    pub has_time: bool,
+    /// the time, in seconds since the unix epoch.
+    pub time: u64,
+}
+
+impl From<PartitionState> for RustbootState {
+    fn from(state: PartitionState) -> Self {
+        match state {
+            PartitionState::New => RustbootState::New,
+            PartitionState::Updating => RustbootState::Updating,
+            PartitionState::Testing => RustbootState::Testing,
+            PartitionState::Success => RustbootState::Success,
+        }
+    }
+}
+
+/// Maps a result to the C convention i.e. `0` or `-1 - code`.
+fn status(res: rustBoot::Result<()>) -> i32 {
+    match res {
+        Ok(()) => 0,
+        Err(e) => -1 - e.code() as i32,
+    }
+}
+
+/// Marks the update staged in the update partition for installation. rustBoot verifies and
+/// installs it on the next boot (i.e. after a reset), in the `testing` state.
+#[no_mangle]
+pub extern "C" fn rustboot_update_trigger() -> i32 {
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    status(updater.update_trigger())
+}
+
+/// Confirms the running image i.e. an update that's being tested is kept. Unless it's confirmed,
+/// an update is rolled back on the next boot.
+#[no_mangle]
+pub extern "C" fn rustboot_update_success() -> i32 {
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    status(updater.update_success())
+}
+
+/// Fills in `report` with the boot and update images' versions and states.
+///
+/// # Safety
+///
+/// `report` must be null or point to a (writable) `RustbootBootReport`.
+#[no_mangle]
+pub unsafe extern "C" fn rustboot_get_boot_report(report: *mut RustbootBootReport) -> i32 {
+    let report = match report.as_mut() {
+        Some(report) => report,
+        None => return status(Err(RustbootError::NullValue)),
+    };
+    let flash = FlashWriterEraser::new();
+    let debug_protection = match flash.hal_debug_protection() {
+        DebugProtection::Disabled => 0,
+        DebugProtection::Enabled => 1,
+        DebugProtection::Permanent => 2,
+    };
+    let updater = FlashUpdater::new(flash);
+    let boot = match updater.boot_image_info() {
+        Ok(info) => info,
+        Err(e) => return status(Err(e)),
+    };
+    let update = updater.update_image_info().ok();
+    let time = rustBoot_hal::rtc_time();
+    *report = RustbootBootReport {
+        boot_version: boot.version,
+        boot_state: boot.state.into(),
+        update_version: update.map_or(0, |info| info.version),
+        update_state: update.map_or(RustbootState::New, |info| info.state.into()),
+        debug_protection,
+        has_time: time.is_some(),
+        time: time.unwrap_or(0),
+    };
+    0
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    cortex_m::asm::udf()
+}
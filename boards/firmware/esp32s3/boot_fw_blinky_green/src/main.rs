@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+use esp_hal::delay::Delay;
+use esp_hal::gpio::{Level, Output};
+use esp_hal::prelude::*;
+
+use rustBoot_hal::esp::esp32s3::FlashWriterEraser;
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+
+// a green LED on GPIO4 (the devkit's own LED is an addressable RGB LED)
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let mut led = Output::new(peripherals.GPIO4, Level::Low);
+    let delay = Delay::new();
+
+    let mut count = 0u8;
+    while count < 5 {
+        led.set_high();
+        delay.delay_millis(1000); // 1 Sec
+        led.set_low();
+        delay.delay_millis(1000); // 1 Sec
+        count += 1;
+    }
+
+    let flash_writer = FlashWriterEraser::new();
+    let updater = FlashUpdater::new(flash_writer);
+
+    match updater.update_trigger() {
+        Ok(_v) => {}
+        Err(e) => panic!("couldnt trigger update: {}", e),
+    }
+
+    esp_hal::reset::software_reset();
+    loop {}
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+// End of file
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+use esp_hal::delay::Delay;
+use esp_hal::gpio::{Level, Output};
+use esp_hal::prelude::*;
+
+use rustBoot_hal::esp::esp32s3::FlashWriterEraser;
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+
+// a red LED on GPIO5 (the devkit's own LED is an addressable RGB LED)
+#[entry]
+fn main() -> ! {
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let mut led = Output::new(peripherals.GPIO5, Level::Low);
+    let delay = Delay::new();
+
+    let flash_writer = FlashWriterEraser::new();
+    let updater = FlashUpdater::new(flash_writer);
+
+    match updater.update_success() {
+        Ok(_v) => {}
+        Err(e) => panic!("couldnt trigger update: {}", e),
+    }
+
+    loop {
+        led.set_high();
+        delay.delay_millis(125); // 125 mSec
+        led.set_low();
+        delay.delay_millis(125); // 125 mSec
+    }
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+// End of file
@@ -0,0 +1,11 @@
+/* @generated by `cargo nrf52840 gen layout` from `boards/manifests/nrf52840.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_sector_size = 0x1000;
+__rustboot_partition_size = 0x28000;
+__rustboot_boot_partition = 0x2f000;
+__rustboot_update_partition = 0x58000;
+__rustboot_swap_partition = 0x57000;
+/* firmware is linked right after the 256-byte rustBoot header */
+__rustboot_fw_origin = 0x2f100;
+__rustboot_fw_max_len = 0x27f00;
@@ -0,0 +1,11 @@
+/* @generated by `cargo rp2040 gen layout` from `boards/manifests/rp2040.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_sector_size = 0x1000;
+__rustboot_partition_size = 0x20000;
+__rustboot_boot_partition = 0x10020000;
+__rustboot_update_partition = 0x10040000;
+__rustboot_swap_partition = 0x10060000;
+/* firmware is linked right after the 256-byte rustBoot header */
+__rustboot_fw_origin = 0x10020100;
+__rustboot_fw_max_len = 0x1ff00;
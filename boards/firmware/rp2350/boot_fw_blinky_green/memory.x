@@ -0,0 +1,4 @@
+MEMORY {
+    FLASH : ORIGIN = 0x10020100, LENGTH = 128K - 0x100
+    RAM   : ORIGIN = 0x20000000, LENGTH = 512K
+}
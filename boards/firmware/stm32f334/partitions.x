@@ -0,0 +1,11 @@
+/* @generated by `cargo stm32f334 gen layout` from `boards/manifests/stm32f334.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_sector_size = 0x1800;
+__rustboot_partition_size = 0x1800;
+__rustboot_boot_partition = 0x800b800;
+__rustboot_update_partition = 0x800d000;
+__rustboot_swap_partition = 0x800e800;
+/* firmware is linked right after the 256-byte rustBoot header */
+__rustboot_fw_origin = 0x800b900;
+__rustboot_fw_max_len = 0x1700;
@@ -0,0 +1,11 @@
+/* @generated by `cargo stm32f746 gen layout` from `boards/manifests/stm32f746.toml`. */
+/* Do not edit by hand. */
+
+__rustboot_sector_size = 0x40000;
+__rustboot_partition_size = 0x40000;
+__rustboot_boot_partition = 0x8040000;
+__rustboot_update_partition = 0x8080000;
+__rustboot_swap_partition = 0x80c0000;
+/* firmware is linked right after the 256-byte rustBoot header */
+__rustboot_fw_origin = 0x8040100;
+__rustboot_fw_max_len = 0x3ff00;
@@ -0,0 +1,44 @@
+#![no_main]
+#![no_std]
+
+#[cfg(feature = "defmt")]
+use defmt_rtt as _; // global logger
+
+use crate::mcu::{pac, prelude::*};
+use cortex_m_rt::entry;
+use stm32f7xx_hal as mcu;
+
+use rustBoot_hal::stm::stm32f769::FlashWriterEraser;
+use rustBoot_update::update::{update_flash::FlashUpdater, UpdateInterface};
+#[entry]
+fn main() -> ! {
+    let _p = pac::Peripherals::take().unwrap();
+
+    // the discovery board's green led (LD2)
+    let gpioj = _p.GPIOJ.split();
+    let mut led1 = gpioj.pj5.into_push_pull_output();
+    let mut count = 0;
+    while count < 6 {
+        led1.toggle();
+        cortex_m::asm::delay(8000000);
+        count = count + 1;
+    }
+
+    let flash1 = _p.FLASH;
+    let flash_writer = FlashWriterEraser { nvm: flash1 };
+    let updater = FlashUpdater::new(flash_writer);
+
+    match updater.update_trigger() {
+        Ok(_v) => {}
+        Err(e) => panic!("couldnt trigger update: {}", e),
+    }
+
+    stm32f7xx_hal::pac::SCB::sys_reset()
+}
+
+#[panic_handler] // panicking behavior
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        cortex_m::asm::bkpt();
+    }
+}
@@ -0,0 +1,12 @@
+/* For STM32F7{65,67,69,77,79} devices */
+MEMORY
+{
+  /* NOTE K = KiBi = 1024 bytes */
+  FLASH : ORIGIN = 0x08040100, LENGTH = 256K - 0x100
+  RAM : ORIGIN = 0x20000000, LENGTH = 512K
+}
+
+/* This is where the call stack will be allocated. */
+/* The stack is of the full descending type. */
+/* NOTE Do NOT modify `_stack_start` unless you know what you are doing */
+/*_stack_start = ORIGIN(RAM) + LENGTH(RAM); */
\ No newline at end of file
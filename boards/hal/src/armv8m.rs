@@ -0,0 +1,129 @@
+//! A hardened jump to firmware for Armv8-M Mainline (i.e. Cortex-M33) parts, see the `armv8m`
+//! feature. It's shared by all such boards (ex: the nrf9160) i.e. their `boot_from` checks the
+//! image's stack pointer and reset vector and hands over to [`boot`].
+//!
+//! Firmware expects to start from a (mostly) reset core, but rustBoot leaves state behind that a
+//! plain `VTOR`/`MSP` jump doesn't undo. [`boot`] puts the core back into its reset state first:
+//!
+//! - interrupts are masked, then every NVIC interrupt is disabled and un-pended. SysTick is
+//!   stopped and pending SysTick/PendSV exceptions are cleared.
+//! - the SCB's fault handler enables, system handler priorities and (sticky) fault status
+//!   registers are cleared.
+//! - the MPU is disabled and its regions cleared.
+//! - the FPU's lazy-stacking state is cleared and access to it is revoked (i.e. `CPACR` is reset),
+//!   firmware enables it from its reset handler.
+//! - the stack limit registers (`MSPLIM`/`PSPLIM`) are cleared, the main stack is sealed and the
+//!   core runs privileged, on the main stack (i.e. `CONTROL` is reset).
+//!
+//! # Stack sealing
+//!
+//! The top of the firmware's main stack holds the stack seal (i.e. `0xFEF5EDA5`, twice) and its
+//! main stack pointer starts right below it. An exception return that underflows the stack then
+//! faults on the seal rather than popping an attacker-controlled frame. This costs the firmware 8
+//! bytes of stack.
+//!
+//! # Limitations
+//!
+//! - caches aren't touched, as the Cortex-M33 has none (parts with a system cache must clean and
+//!   disable it before calling [`boot`]).
+//! - the `hide-bootloader` feature's MPU setup (see [`crate::mpu`]) is Armv6-M/Armv7-M only i.e.
+//!   it isn't supported along with this one.
+//! - firmware is booted in the security state rustBoot runs in. Booting non-secure firmware (i.e.
+//!   setting up the SAU and jumping with `BLXNS`) is up to the board.
+
+// the system control block
+const ICSR: u32 = 0xE000_ED04;
+const VTOR: u32 = 0xE000_ED08;
+const SHPR: u32 = 0xE000_ED18;
+const SHCSR: u32 = 0xE000_ED24;
+const CFSR: u32 = 0xE000_ED28;
+const HFSR: u32 = 0xE000_ED2C;
+const CPACR: u32 = 0xE000_ED88;
+const ICSR_PENDSTCLR: u32 = 1 << 25;
+const ICSR_PENDSVCLR: u32 = 1 << 27;
+// SysTick
+const SYST_CSR: u32 = 0xE000_E010;
+// the NVIC's interrupt clear-enable and clear-pending registers
+const NVIC_ICER: u32 = 0xE000_E180;
+const NVIC_ICPR: u32 = 0xE000_E280;
+const NVIC_REGS: u32 = 16;
+// the MPU
+const MPU_TYPE: u32 = 0xE000_ED90;
+const MPU_CTRL: u32 = 0xE000_ED94;
+const MPU_RNR: u32 = 0xE000_ED98;
+const MPU_RLAR: u32 = 0xE000_EDA0;
+// the FPU's context control register i.e. lazy-stacking state
+const FPCCR: u32 = 0xE000_EF34;
+const FPCCR_LSPACT: u32 = 1 << 0;
+
+/// The value that seals a stack, see the module docs.
+pub const STACK_SEAL: u32 = 0xFEF5_EDA5;
+
+/// Resets the core's state (see the module docs), seals the main stack at `sp` and jumps to
+/// firmware i.e. loads the sealed stack pointer and branches to `reset_vector`, with the vector
+/// table at `vtor`.
+///
+/// # Safety
+///
+/// `sp` and `reset_vector` must be the (checked) initial stack pointer and reset vector of a
+/// verified image, whose vector table is at `vtor`. `sp` must be 8-byte aligned. Nothing of
+/// rustBoot's can run afterwards.
+pub unsafe fn boot(vtor: u32, sp: u32, reset_vector: u32) -> ! {
+    cortex_m::interrupt::disable();
+    let write = |addr: u32, val: u32| core::ptr::write_volatile(addr as *mut u32, val);
+    let read = |addr: u32| core::ptr::read_volatile(addr as *const u32);
+
+    // interrupts and exceptions
+    for reg in 0..NVIC_REGS {
+        write(NVIC_ICER + reg * 4, 0xFFFF_FFFF);
+        write(NVIC_ICPR + reg * 4, 0xFFFF_FFFF);
+    }
+    write(SYST_CSR, 0);
+    write(ICSR, ICSR_PENDSTCLR | ICSR_PENDSVCLR);
+
+    // the SCB's fault handling, status registers are write-one-to-clear
+    write(SHCSR, 0);
+    (0..3).for_each(|reg| write(SHPR + reg * 4, 0));
+    write(CFSR, read(CFSR));
+    write(HFSR, read(HFSR));
+
+    // the MPU
+    write(MPU_CTRL, 0);
+    for region in 0..(read(MPU_TYPE) >> 8) & 0xFF {
+        write(MPU_RNR, region);
+        write(MPU_RLAR, 0);
+    }
+
+    // the FPU
+    write(FPCCR, read(FPCCR) & !FPCCR_LSPACT);
+    write(CPACR, 0);
+
+    // seal the stack
+    let sealed_sp = sp - 8;
+    write(sealed_sp, STACK_SEAL);
+    write(sealed_sp + 4, STACK_SEAL);
+
+    write(VTOR, vtor);
+    jump(sealed_sp, reset_vector)
+}
+
+/// Clears the stack limits and `CONTROL`, then jumps. Once the stack pointer is replaced,
+/// nothing can be pushed to (or popped off) the stack.
+#[inline(always)]
+unsafe fn jump(sp: u32, rv: u32) -> ! {
+    core::arch::asm!(
+        "msr msplim, {zero}",
+        "msr psplim, {zero}",
+        "msr control, {zero}",
+        "isb",
+        "msr msp, {sp}",
+        "dsb",
+        "isb",
+        "cpsie i",
+        "bx {rv}",
+        zero = in(reg) 0u32,
+        sp = in(reg) sp,
+        rv = in(reg) rv,
+        options(noreturn)
+    )
+}
@@ -0,0 +1,41 @@
+//! A boot pin i.e. a GPIO (ex: a button) that, held at reset, keeps rustBoot from booting firmware
+//! so the device can be recovered (ex: with its console), see [`crate::boot_pin_held`].
+//!
+//! The pin is configured as an input, pulled towards its inactive level (i.e. up, for an
+//! active-low button) and sampled every millisecond for [`BootPin::hold_ms`]. It's held if it's
+//! asserted in every sample, so a bouncing or briefly pressed button doesn't count.
+//!
+//! Milliseconds are counted in core clock cycles, at the clock the mcu comes out of reset with -
+//! the pin must be sampled before rustBoot (or the board) configures its clocks. The pin is left
+//! configured as an input and, on STM32 parts, its port's clock is left enabled.
+
+/// A boot pin, as configured in the board's manifest (i.e. its `[boot_pin]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootPin {
+    /// the GPIO port i.e. `0` for `P0` (nRF) or `GPIOA` (STM32), `1` for `P1` or `GPIOB` etc.
+    pub port: u8,
+    /// the pin's number, in its port.
+    pub pin: u8,
+    /// set if the pin reads low while it's held (ex: a button to ground).
+    pub active_low: bool,
+    /// how long the pin must be held, in milliseconds.
+    pub hold_ms: u32,
+}
+
+/// Samples `boot_pin` with `read_pin` (which configures the pin as an input with a pull-up, if
+/// asked to, or a pull-down and returns its level) until it's released or held for `hold_ms`.
+/// `clock_hz` is the core clock's frequency.
+pub(crate) fn sample(
+    boot_pin: &BootPin,
+    read_pin: fn(u8, u8, bool) -> bool,
+    clock_hz: u32,
+) -> bool {
+    let asserted =
+        || read_pin(boot_pin.port, boot_pin.pin, boot_pin.active_low) != boot_pin.active_low;
+    // the first read configures the pin, let its pull settle before sampling it
+    let _ = asserted();
+    (0..=boot_pin.hold_ms).all(|_| {
+        cortex_m::asm::delay(clock_hz / 1000);
+        asserted()
+    })
+}
@@ -0,0 +1,197 @@
+//! Why the mcu booted i.e. the last reset's cause, per the part's reset flags, and the register
+//! rustBoot records its boot reason in (see `rustBoot::bootreason` for its format), for firmware
+//! to read back.
+//!
+//! | part                  | reset flags | boot reason                                         |
+//! |-----------------------|-------------|-----------------------------------------------------|
+//! | stm32f4s, f7s, f334   | `RCC_CSR`   | `RTC_BKP4R` i.e. past [`crate::read_backup_regs`]'s |
+//! | stm32h723             | `RCC_RSR`   | `RTC_BKP4R`                                         |
+//! | nrf52840              | `RESETREAS` | `GPREGRET2`                                         |
+//!
+//! [`take_reset_cause`] clears the reset flags, so that the next reset's cause is its own i.e.
+//! firmware must go by the boot reason rather than the flags.
+//!
+//! # Limitations
+//!
+//! - the rp2040 and rp2350 have no spare scratch register (the bootrom reserves the watchdog's
+//!   other scratch registers), so nothing is recorded on them (or on the esp32s3).
+//! - backup registers don't survive a backup-domain reset and `GPREGRET2` doesn't survive a
+//!   power-on reset. rustBoot records a reason on every boot though.
+
+use rustBoot::bootreason::{BootReason, ResetCause};
+
+/// The backup register the boot reason is kept in, on stm32 parts.
+#[cfg(feature = "stm")]
+const STM_BKP_REG: usize = crate::BACKUP_REGS;
+
+/// Returns the last reset's cause and clears the mcu's reset flags. [`ResetCause::Unknown`] if
+/// the board has none.
+pub fn take_reset_cause() -> ResetCause {
+    #[cfg(feature = "nrf52840")]
+    return crate::nrf::nrf52840::take_reset_cause();
+
+    #[cfg(feature = "stm32f411")]
+    return crate::stm::stm32f411::take_reset_cause();
+
+    #[cfg(feature = "stm32f446")]
+    return crate::stm::stm32f446::take_reset_cause();
+
+    #[cfg(feature = "stm32f469")]
+    return crate::stm::stm32f469::take_reset_cause();
+
+    #[cfg(feature = "stm32h723")]
+    return crate::stm::stm32h723::take_reset_cause();
+
+    #[cfg(feature = "stm32f746")]
+    return crate::stm::stm32f746::take_reset_cause();
+
+    #[cfg(feature = "stm32f334")]
+    return crate::stm::stm32f334::take_reset_cause();
+
+    #[cfg(feature = "stm32f407")]
+    return crate::stm::stm32f407::take_reset_cause();
+
+    #[cfg(feature = "stm32f769")]
+    return crate::stm::stm32f769::take_reset_cause();
+
+    ResetCause::Unknown
+}
+
+/// Returns the recorded boot reason, if any.
+pub fn read() -> Option<BootReason> {
+    read_byte().and_then(BootReason::from_byte)
+}
+
+/// Records `reason`. Returns `false` if the board has nowhere to keep it.
+pub fn write(reason: &BootReason) -> bool {
+    write_byte(reason.to_byte())
+}
+
+/// Clears the recorded boot reason.
+pub fn clear() {
+    write_byte(0);
+}
+
+fn read_byte() -> Option<u8> {
+    #[cfg(feature = "nrf52840")]
+    return Some(crate::nrf::nrf52840::read_gpregret2());
+
+    #[cfg(feature = "stm32f411")]
+    return Some(
+        crate::stm::stm32f411::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    #[cfg(feature = "stm32f446")]
+    return Some(
+        crate::stm::stm32f446::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    #[cfg(feature = "stm32f469")]
+    return Some(
+        crate::stm::stm32f469::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    #[cfg(feature = "stm32h723")]
+    return Some(
+        crate::stm::stm32h723::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    #[cfg(feature = "stm32f746")]
+    return Some(
+        crate::stm::stm32f746::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    #[cfg(feature = "stm32f334")]
+    return Some(
+        crate::stm::stm32f334::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    #[cfg(feature = "stm32f407")]
+    return Some(
+        crate::stm::stm32f407::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    #[cfg(feature = "stm32f769")]
+    return Some(
+        crate::stm::stm32f769::read_backup_regs::<{ STM_BKP_REG + 1 }>()[STM_BKP_REG] as u8,
+    );
+
+    None
+}
+
+fn write_byte(byte: u8) -> bool {
+    #[cfg(feature = "nrf52840")]
+    {
+        crate::nrf::nrf52840::write_gpregret2(byte);
+        return true;
+    }
+
+    #[cfg(feature = "stm32f411")]
+    return write_stm(
+        crate::stm::stm32f411::read_backup_regs,
+        crate::stm::stm32f411::write_backup_regs,
+        byte,
+    );
+
+    #[cfg(feature = "stm32f446")]
+    return write_stm(
+        crate::stm::stm32f446::read_backup_regs,
+        crate::stm::stm32f446::write_backup_regs,
+        byte,
+    );
+
+    #[cfg(feature = "stm32f469")]
+    return write_stm(
+        crate::stm::stm32f469::read_backup_regs,
+        crate::stm::stm32f469::write_backup_regs,
+        byte,
+    );
+
+    #[cfg(feature = "stm32h723")]
+    return write_stm(
+        crate::stm::stm32h723::read_backup_regs,
+        crate::stm::stm32h723::write_backup_regs,
+        byte,
+    );
+
+    #[cfg(feature = "stm32f746")]
+    return write_stm(
+        crate::stm::stm32f746::read_backup_regs,
+        crate::stm::stm32f746::write_backup_regs,
+        byte,
+    );
+
+    #[cfg(feature = "stm32f334")]
+    return write_stm(
+        crate::stm::stm32f334::read_backup_regs,
+        crate::stm::stm32f334::write_backup_regs,
+        byte,
+    );
+
+    #[cfg(feature = "stm32f407")]
+    return write_stm(
+        crate::stm::stm32f407::read_backup_regs,
+        crate::stm::stm32f407::write_backup_regs,
+        byte,
+    );
+
+    #[cfg(feature = "stm32f769")]
+    return write_stm(
+        crate::stm::stm32f769::read_backup_regs,
+        crate::stm::stm32f769::write_backup_regs,
+        byte,
+    );
+
+    false
+}
+
+/// Writes `byte` to the boot reason's backup register, with `read` and `write` (a part's
+/// `read_backup_regs` and `write_backup_regs`). The registers before it (i.e. a panic record or a
+/// redirect request) are left as they are.
+#[cfg(feature = "stm")]
+fn write_stm(read: fn() -> [u32; STM_BKP_REG + 1], write: fn(&[u32]), byte: u8) -> bool {
+    let mut regs = read();
+    regs[STM_BKP_REG] = byte as u32;
+    write(&regs);
+    true
+}
@@ -0,0 +1,133 @@
+//! Reads that survive an uncorrectable ECC error.
+//!
+//! On parts with ECC-protected flash or RAM (ex: the stm32h7s' flash), reading a location with a
+//! double-bit error raises a bus fault (or, on aarch64, a synchronous external abort). Hashing a
+//! corrupted partition would then fault, and keep faulting on every reset.
+//!
+//! [`read`] copies memory with a dedicated routine, whose only load is known to the fault
+//! handler. A fault taken on that load is fixed up i.e. the routine returns early and [`read`]
+//! reports [`FlashError::ReadFailed`], which the updater turns into an integrity check failure.
+//! Faults anywhere else are handled as before.
+//!
+//! - **Cortex-M:** this module provides the `BusFault` handler (so the application mustn't define
+//! its own) and [`read`] enables the `BusFault` exception, which otherwise escalates to a
+//! `HardFault`. Only precise bus faults are fixed up, and [`read`] mustn't be called with
+//! interrupts masked (i.e. from a critical section), as a masked `BusFault` escalates too.
+//! - **aarch64:** the board's synchronous exception handler calls [`fixup`], asynchronous
+//! aborts (i.e. `SError`s) aren't fixed up.
+
+use core::arch::global_asm;
+
+use crate::FlashError;
+
+#[cfg(target_arch = "arm")]
+global_asm!(
+    ".section .text.__rustboot_ecc_read,\"ax\",%progbits",
+    ".global __rustboot_ecc_read",
+    ".type __rustboot_ecc_read,%function",
+    ".thumb_func",
+    // r0: src, r1: dst, r2: len. Returns 0, or 1 if a load faulted.
+    "__rustboot_ecc_read:",
+    "    cbz r2, 2f",
+    "1:",
+    "__rustboot_ecc_load:",
+    "    ldrb r3, [r0], #1",
+    "    strb r3, [r1], #1",
+    "    subs r2, r2, #1",
+    "    bne 1b",
+    "2:",
+    "    movs r0, #0",
+    "    bx lr",
+    "__rustboot_ecc_fixup:",
+    "    movs r0, #1",
+    "    bx lr",
+    "",
+    ".section .text.BusFault,\"ax\",%progbits",
+    ".global BusFault",
+    ".type BusFault,%function",
+    ".thumb_func",
+    "BusFault:",
+    // the stacked frame is on the main or the process stack, see `EXC_RETURN`
+    "    tst lr, #4",
+    "    ite eq",
+    "    mrseq r0, msp",
+    "    mrsne r0, psp",
+    // a precise fault taken by the guarded load returns to the fixup instead
+    "    ldr r1, [r0, #24]",
+    "    ldr r2, =__rustboot_ecc_load",
+    "    bic r2, r2, #1",
+    "    cmp r1, r2",
+    "    bne 3f",
+    "    ldr r2, =__rustboot_ecc_fixup",
+    "    bic r2, r2, #1",
+    "    str r2, [r0, #24]",
+    // clear `CFSR.BFSR` (write-one-to-clear)
+    "    ldr r1, =0xE000ED28",
+    "    ldr r2, =0xFF00",
+    "    str r2, [r1]",
+    "    dsb",
+    "    bx lr",
+    "3:",
+    "    b DefaultHandler",
+);
+
+#[cfg(target_arch = "aarch64")]
+global_asm!(
+    ".section .text.__rustboot_ecc_read,\"ax\",%progbits",
+    ".global __rustboot_ecc_read",
+    ".global __rustboot_ecc_load",
+    ".global __rustboot_ecc_fixup",
+    // x0: src, x1: dst, x2: len. Returns 0, or 1 if a load aborted.
+    "__rustboot_ecc_read:",
+    "    cbz x2, 2f",
+    "1:",
+    "__rustboot_ecc_load:",
+    "    ldrb w3, [x0], #1",
+    "    strb w3, [x1], #1",
+    "    subs x2, x2, #1",
+    "    b.ne 1b",
+    "2:",
+    "    mov x0, #0",
+    "    ret",
+    "__rustboot_ecc_fixup:",
+    "    mov x0, #1",
+    "    ret",
+);
+
+extern "C" {
+    fn __rustboot_ecc_read(src: *const u8, dst: *mut u8, len: usize) -> u32;
+    #[cfg(target_arch = "aarch64")]
+    fn __rustboot_ecc_load();
+    #[cfg(target_arch = "aarch64")]
+    fn __rustboot_ecc_fixup();
+}
+
+/// `SHCSR.BUSFAULTENA`
+#[cfg(target_arch = "arm")]
+const SHCSR_BUSFAULTENA: u32 = 1 << 17;
+
+/// Fills `data` with the bytes of memory at `addr`. Returns [`FlashError::ReadFailed`] if a read
+/// faulted (i.e. hit an uncorrectable ECC error), `data` is then only partially filled.
+pub fn read(addr: usize, data: &mut [u8]) -> Result<(), FlashError> {
+    #[cfg(target_arch = "arm")]
+    unsafe {
+        let scb = &*cortex_m::peripheral::SCB::PTR;
+        scb.shcsr.modify(|shcsr| shcsr | SHCSR_BUSFAULTENA);
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+    match unsafe { __rustboot_ecc_read(addr as *const u8, data.as_mut_ptr(), data.len()) } {
+        0 => Ok(()),
+        _ => Err(FlashError::ReadFailed),
+    }
+}
+
+/// Returns where to resume if `pc` (i.e. the address of the instruction that aborted) is the
+/// guarded load, else `None`.
+#[cfg(target_arch = "aarch64")]
+pub fn fixup(pc: usize) -> Option<usize> {
+    match pc == __rustboot_ecc_load as usize {
+        true => Some(__rustboot_ecc_fixup as usize),
+        false => None,
+    }
+}
@@ -0,0 +1,50 @@
+//! Entropy sources for rustBoot's fault and side-channel countermeasures (see the `entropy`
+//! feature and `rustBoot::crypto::entropy`).
+//!
+//! Parts with a hardware RNG provide an `Rng` (ex: `nrf::nrf52840::Rng`, or the `Rng` of the
+//! stm32f4s, f7s and the stm32h723). [`CycleJitter`] is a fallback for parts without one. Boards
+//! register a source before starting rustBoot, for example
+//!
+//! ```ignore
+//! static RNG: Rng = Rng;
+//! rustBoot::crypto::entropy::set_entropy_source(&RNG);
+//! ```
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m::peripheral::{DCB, DWT};
+use rustBoot::crypto::entropy::EntropySource;
+
+/// A fallback entropy source, for parts without a hardware RNG (Armv7-M and later i.e. parts
+/// with a cycle counter).
+///
+/// Each call mixes the cycle counter (i.e. `DWT_CYCCNT`) into a xorshift state. It's only as
+/// unpredictable as the time between calls (ex: flash wait states, interrupts, clock start-up),
+/// which is enough to randomize delays but not to derive keys.
+pub struct CycleJitter {
+    state: AtomicU32,
+}
+
+impl CycleJitter {
+    pub const fn new() -> Self {
+        CycleJitter {
+            state: AtomicU32::new(0x9E37_79B9),
+        }
+    }
+}
+
+impl EntropySource for CycleJitter {
+    fn next_u32(&self) -> u32 {
+        // the cycle counter is enabled on first use, `TRCENA` gates the DWT
+        unsafe {
+            (*DCB::PTR).demcr.modify(|demcr| demcr | (1 << 24));
+            (*DWT::PTR).ctrl.modify(|ctrl| ctrl | 1);
+        }
+        let mut state = self.state.load(Ordering::Relaxed) ^ DWT::cycle_count();
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        self.state.store(state, Ordering::Relaxed);
+        state
+    }
+}
@@ -0,0 +1,438 @@
+//! Flash  Read, Write and Erase opration for the `esp32s3`, along with booting ESP app images.
+//!
+//! rustBoot is the esp32s3's second-stage bootloader i.e. the ROM loads it (from flash offset
+//! `0x0`) into RAM and runs it. rustBoot then maps flash into the data cache, 1:1 at
+//! `FLASH_MAP_BASE` (see [`FlashWriterEraser::new`]), which is where the partitions' addresses in
+//! `boards/manifests/esp32s3.toml` point. The ESP partition table (i.e. the one `espflash` and
+//! ESP-IDF's tools read) is generated from the same manifest.
+//!
+//! Firmware are ESP app images (see `rustBoot::image::esp`), which are loaded and mapped rather
+//! than executed in place.
+
+/*
+    IMPORTANT NOTE ABOUT ESP32-S3 FLASH SPACE ADDRESSES:
+    The ROM's spiflash functions want flash offsets (i.e. addresses that start at `0x0000_0000`),
+    whereas flash is read back through the data cache at `0x3D00_0000` (FLASH_MAP_BASE). The
+    window is in the upper half of the cache's address space, so it doesn't clash with an app's
+    own IROM/DROM mappings (i.e. it's also available to firmware, to stage updates).
+*/
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::{DebugProtection, FlashError, FlashInterface};
+use esp32s3_constants::*;
+use rustBoot::constants::{BOOT_PARTITION_ADDRESS, FLASH_SIZE, PARTITION_SIZE};
+use rustBoot::image::esp::{app_image_start, EspImage, MemoryMap, MMU_PAGE_SIZE};
+
+#[rustfmt::skip]
+mod esp32s3_constants {
+    use core::ops::Range;
+
+    pub const FLASH_MAP_BASE            : usize = 0x3D00_0000;
+    pub const FLASH_SECTOR_SIZE         : usize = 4096;
+    // the flash cache's (virtual) buses. The ICache and DCache share a single MMU table
+    // i.e. `IROM.start + n` and `DROM.start + n` are mapped by the same entry.
+    pub const IROM                      : Range<usize> = 0x4200_0000..0x4400_0000;
+    pub const DROM                      : Range<usize> = 0x3C00_0000..0x3E00_0000;
+    pub const MMU_ACCESS_FLASH          : u32   = 0;
+    pub const MMU_PAGE_SIZE_KB          : u32   = 64;
+    // the RAM firmware may be loaded to i.e. SRAM0/1 minus rustBoot's own IRAM and DRAM, see
+    // `boards/bootloaders/esp32s3/memory.x`
+    pub const FW_IRAM                   : Range<usize> = 0x4037_0000..0x403B_0000;
+    pub const FW_DRAM                   : Range<usize> = 0x3FC8_8000..0x3FCC_0000;
+    pub const ESP32S3_CHIP_ID           : u16   = 9;
+    // `SPI_BOOT_CRYPT_CNT` i.e. flash encryption is enabled if an odd number of its bits are set
+    pub const EFUSE_RD_REPEAT_DATA1     : usize = 0x6000_7034;
+    pub const SPI_BOOT_CRYPT_CNT_SHIFT  : u32   = 18;
+    pub const SPI_BOOT_CRYPT_CNT_MASK   : u32   = 0x7;
+    // the caches' bus enables
+    pub const EXTMEM_DCACHE_CTRL1       : usize = 0x600C_4004;
+    pub const EXTMEM_ICACHE_CTRL1       : usize = 0x600C_4064;
+    pub const CACHE_SHUT_CORE0_BUS      : u32   = 1 << 0;
+    // the watchdogs the ROM leaves running (in flashboot mode) while the bootloader runs
+    pub const RTC_CNTL_WDTCONFIG0       : usize = 0x6000_8098;
+    pub const RTC_CNTL_WDTWPROTECT      : usize = 0x6000_80B0;
+    pub const RTC_CNTL_WDT_FLASHBOOT_EN : u32   = 1 << 12;
+    pub const TIMG0_WDTCONFIG0          : usize = 0x6001_F048;
+    pub const TIMG0_WDTWPROTECT         : usize = 0x6001_F064;
+    pub const TIMG_WDT_FLASHBOOT_EN     : u32   = 1 << 14;
+    pub const WDT_EN                    : u32   = 1 << 31;
+    pub const WDT_WKEY                  : u32   = 0x50D8_3AA1;
+}
+
+/// The esp32s3's memory map, as far as booting an app image goes.
+pub const MEMORY_MAP: MemoryMap<'static> = MemoryMap {
+    chip_id: ESP32S3_CHIP_ID,
+    mapped: &[IROM, DROM],
+    ram: &[FW_IRAM, FW_DRAM],
+};
+
+// ROM functions, see `esp32s3.rom.ld`
+extern "C" {
+    fn esp_rom_spiflash_unlock() -> i32;
+    fn esp_rom_spiflash_erase_sector(sector: u32) -> i32;
+    fn esp_rom_spiflash_write(dest: u32, src: *const u32, len: u32) -> i32;
+    fn esp_rom_spiflash_write_encrypted_enable();
+    fn esp_rom_spiflash_write_encrypted_disable();
+    fn esp_rom_spiflash_write_encrypted(dest: u32, src: *const u32, len: u32) -> i32;
+    fn Cache_Suspend_ICache() -> u32;
+    fn Cache_Resume_ICache(autoload: u32);
+    fn Cache_Suspend_DCache() -> u32;
+    fn Cache_Resume_DCache(autoload: u32);
+    fn Cache_Invalidate_Addr(addr: u32, size: u32) -> i32;
+    fn Cache_Invalidate_ICache_All() -> i32;
+    fn Cache_Invalidate_DCache_All() -> i32;
+    fn Cache_Ibus_MMU_Set(
+        ext_ram: u32,
+        vaddr: u32,
+        paddr: u32,
+        psize: u32,
+        num: u32,
+        fixed: u32,
+    ) -> i32;
+    fn Cache_Dbus_MMU_Set(
+        ext_ram: u32,
+        vaddr: u32,
+        paddr: u32,
+        psize: u32,
+        num: u32,
+        fixed: u32,
+    ) -> i32;
+}
+
+pub struct FlashWriterEraser {
+    encrypted: bool,
+}
+
+impl FlashWriterEraser {
+    /// Maps the whole flash into the data cache at `FLASH_MAP_BASE` i.e. where the partitions
+    /// are read from, and checks whether flash encryption is enabled.
+    pub fn new() -> Self {
+        let pages = (FLASH_SIZE / MMU_PAGE_SIZE) as u32;
+        unsafe {
+            Cache_Dbus_MMU_Set(
+                MMU_ACCESS_FLASH,
+                FLASH_MAP_BASE as u32,
+                0,
+                MMU_PAGE_SIZE_KB,
+                pages,
+                0,
+            );
+            let ctrl1 = read_volatile(EXTMEM_DCACHE_CTRL1 as *const u32);
+            write_volatile(
+                EXTMEM_DCACHE_CTRL1 as *mut u32,
+                ctrl1 & !CACHE_SHUT_CORE0_BUS,
+            );
+            Cache_Invalidate_DCache_All();
+        }
+        rom_unlock();
+        FlashWriterEraser {
+            encrypted: flash_encrypted(),
+        }
+    }
+
+    /// Programs `data` (a whole number of words) at flash `offset`, through an aligned buffer
+    /// as the ROM wants word-aligned data. Bytes that are `0xFF` leave flash as it is.
+    fn program(&self, offset: usize, data: &[u8]) -> Result<(), FlashError> {
+        let mut buf = [0u32; 8];
+        for (idx, chunk) in data.chunks(32).enumerate() {
+            for (word, bytes) in buf.iter_mut().zip(chunk.chunks(4)) {
+                let mut le = [0xFF; 4];
+                le[..bytes.len()].copy_from_slice(bytes);
+                *word = u32::from_le_bytes(le);
+            }
+            let dest = (offset + idx * 32) as u32;
+            let len = ((chunk.len() + 3) & !3) as u32;
+            if rom_write(self.encrypted, dest, &buf, len) != 0 {
+                return Err(FlashError::WriteFailed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-programs the sector at flash `sector` with `data` at `offset` into it i.e. the sector is
+    /// read (decrypted, through the cache), patched, erased and programmed in full.
+    ///
+    /// Encrypted flash can't be programmed twice, as the new ciphertext isn't a bit-wise subset of
+    /// the old one. Trailer updates (i.e. the swap's progress) therefore cost an erase each and
+    /// aren't power-fail safe, a reset between the erase and the write loses the sector.
+    fn rewrite_sector(&self, sector: usize, offset: usize, data: &[u8]) -> Result<(), FlashError> {
+        let mut buf = [0xFFu8; FLASH_SECTOR_SIZE];
+        if data.len() < FLASH_SECTOR_SIZE {
+            self.hal_flash_read(FLASH_MAP_BASE + sector, &mut buf)?;
+        }
+        buf[offset..offset + data.len()].copy_from_slice(data);
+        erase_sector(sector)?;
+        self.program(sector, &buf)
+    }
+}
+
+/// Returns true if flash encryption is enabled i.e. the ROM and the cache transparently
+/// decrypt flash, and writes must be encrypted.
+pub fn flash_encrypted() -> bool {
+    let data1 = unsafe { read_volatile(EFUSE_RD_REPEAT_DATA1 as *const u32) };
+    let crypt_cnt = (data1 >> SPI_BOOT_CRYPT_CNT_SHIFT) & SPI_BOOT_CRYPT_CNT_MASK;
+    crypt_cnt.count_ones() % 2 == 1
+}
+
+/// Erases the sector at flash `offset`.
+fn erase_sector(offset: usize) -> Result<(), FlashError> {
+    match rom_erase_sector((offset / FLASH_SECTOR_SIZE) as u32) {
+        0 => Ok(()),
+        _ => Err(FlashError::EraseFailed),
+    }
+}
+
+// The flash can't be read through the caches while it's programmed or erased, so the ROM's
+// flash functions are called with the caches suspended, from IRAM (i.e. `.rwtext`). This matters
+// to firmware staging an update, which otherwise runs from flash i.e. it must keep interrupts
+// disabled meanwhile, as their handlers run from flash too.
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+fn rom_unlock() {
+    unsafe {
+        let (icache, dcache) = (Cache_Suspend_ICache(), Cache_Suspend_DCache());
+        esp_rom_spiflash_unlock();
+        Cache_Resume_DCache(dcache);
+        Cache_Resume_ICache(icache);
+    }
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+fn rom_erase_sector(sector: u32) -> i32 {
+    unsafe {
+        let (icache, dcache) = (Cache_Suspend_ICache(), Cache_Suspend_DCache());
+        let res = esp_rom_spiflash_erase_sector(sector);
+        Cache_Resume_DCache(dcache);
+        Cache_Resume_ICache(icache);
+        res
+    }
+}
+
+#[inline(never)]
+#[link_section = ".rwtext"]
+fn rom_write(encrypted: bool, dest: u32, buf: &[u32; 8], len: u32) -> i32 {
+    unsafe {
+        let (icache, dcache) = (Cache_Suspend_ICache(), Cache_Suspend_DCache());
+        let res = match encrypted {
+            true => {
+                esp_rom_spiflash_write_encrypted_enable();
+                let res = esp_rom_spiflash_write_encrypted(dest, buf.as_ptr(), len);
+                esp_rom_spiflash_write_encrypted_disable();
+                res
+            }
+            false => esp_rom_spiflash_write(dest, buf.as_ptr(), len),
+        };
+        Cache_Resume_DCache(dcache);
+        Cache_Resume_ICache(icache);
+        res
+    }
+}
+
+/// Drops the data cache's lines for `addr..addr + len`, the cache still returns the old
+/// contents of flash that was just written or erased otherwise (ex: a swap would copy stale
+/// sectors).
+fn invalidate(addr: usize, len: usize) {
+    unsafe { Cache_Invalidate_Addr(addr as u32, len as u32) };
+}
+
+impl FlashInterface for FlashWriterEraser {
+    /// This method is to write data on flash.
+    ///
+    /// Without flash encryption, `data` is programmed as-is (padded to whole words with `0xFF`).
+    /// With it, every sector `data` overlaps is re-programmed in full (see `rewrite_sector`).
+    ///
+    /// Method arguments:
+    /// -   address: It holds the address of flash where data has to be written
+    /// -   data: the bytes to be written
+    ///
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        let offset = address - FLASH_MAP_BASE;
+        let res = match self.encrypted {
+            false => {
+                // the ROM programs whole words, `program` pads the first one with `0xFF`
+                let pad = offset % 4;
+                let mut head = [0xFFu8; 4];
+                let len = (4 - pad).min(data.len());
+                head[pad..pad + len].copy_from_slice(&data[..len]);
+                self.program(offset - pad, &head)
+                    .and_then(|_| self.program(offset + len, &data[len..]))
+            }
+            true => {
+                let mut written = 0;
+                let mut res = Ok(());
+                while written < data.len() && res.is_ok() {
+                    let addr = offset + written;
+                    let sector = addr - addr % FLASH_SECTOR_SIZE;
+                    let len = (FLASH_SECTOR_SIZE - (addr - sector)).min(data.len() - written);
+                    res = self.rewrite_sector(sector, addr - sector, &data[written..written + len]);
+                    written += len;
+                }
+                res
+            }
+        };
+        invalidate(
+            address - address % FLASH_SECTOR_SIZE,
+            data.len() + FLASH_SECTOR_SIZE,
+        );
+        res
+    }
+
+    /// This method is used to erase data on flash
+    ///
+    /// The ROM erases a 4K sector at a time, whatever be the length of bytes we pass to this
+    /// function will erase the whole sector, whichever the sector the address belong to. With
+    /// flash encryption, erased sectors are programmed with (encrypted) `0xFF`, so they read as
+    /// erased through the cache.
+    ///
+    /// Method arguments:
+    /// -   addr: Address where data has to be erased
+    /// -   len :  number of bytes to be erased
+    ///
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        let offset = addr - FLASH_MAP_BASE;
+        let starting_sector = offset - offset % FLASH_SECTOR_SIZE;
+        let mut res = Ok(());
+        for sector in (starting_sector..offset + len).step_by(FLASH_SECTOR_SIZE) {
+            res = match self.encrypted {
+                true => self.rewrite_sector(sector, 0, &[0xFF; FLASH_SECTOR_SIZE]),
+                false => erase_sector(sector),
+            };
+            if res.is_err() {
+                break;
+            }
+        }
+        invalidate(
+            FLASH_MAP_BASE + starting_sector,
+            offset + len - starting_sector,
+        );
+        res
+    }
+    /// The flash chip's block-protect bits aren't used, as application code can clear them just
+    /// as easily. rustBoot is protected by secure boot i.e. the ROM verifies it, once enabled.
+    fn hal_flash_protect(&self, _addr: usize, _len: usize) {}
+
+    /// JTAG is disabled by burning eFuses (i.e. `espefuse.py burn_efuse DIS_PAD_JTAG`), which
+    /// rustBoot doesn't read, so the debug port is reported as open.
+    fn hal_debug_protection(&self) -> DebugProtection {
+        DebugProtection::Disabled
+    }
+    /// See [`FlashWriterEraser::hal_debug_protection`], rustBoot doesn't burn eFuses.
+    fn hal_set_debug_protection(&self, _level: DebugProtection) {}
+
+    fn hal_init() {}
+    fn hal_flash_lock(&self) {}
+    fn hal_flash_unlock(&self) {}
+}
+
+/// Disables the watchdogs the ROM leaves running while it boots i.e. the RTC watchdog and
+/// timer group 0's, as a swap takes longer than their (flashboot) timeouts. Firmware sets up its
+/// own.
+pub fn disable_watchdogs() {
+    unsafe {
+        write_volatile(RTC_CNTL_WDTWPROTECT as *mut u32, WDT_WKEY);
+        let config = read_volatile(RTC_CNTL_WDTCONFIG0 as *const u32);
+        write_volatile(
+            RTC_CNTL_WDTCONFIG0 as *mut u32,
+            config & !(WDT_EN | RTC_CNTL_WDT_FLASHBOOT_EN),
+        );
+        write_volatile(RTC_CNTL_WDTWPROTECT as *mut u32, 0);
+
+        write_volatile(TIMG0_WDTWPROTECT as *mut u32, WDT_WKEY);
+        let config = read_volatile(TIMG0_WDTCONFIG0 as *const u32);
+        write_volatile(
+            TIMG0_WDTCONFIG0 as *mut u32,
+            config & !(WDT_EN | TIMG_WDT_FLASHBOOT_EN),
+        );
+        write_volatile(TIMG0_WDTWPROTECT as *mut u32, 0);
+    }
+}
+
+pub fn preboot() {}
+
+/// This method is used to boot the firmware from a particular address i.e. the ESP app image
+/// that follows the rustBoot header (see `rustBoot::image::esp::app_image_start`). Its RAM
+/// segments are copied, its IROM/DROM segments are mapped into the flash cache and the caches
+/// invalidated, before jumping to its entry point.
+///
+/// Method arguments:
+/// -   fw_base_address  : address of the firmware
+/// Returns:
+/// -  NONE
+pub fn boot_from(fw_base_address: usize) -> ! {
+    let start = app_image_start(fw_base_address);
+    let end = BOOT_PARTITION_ADDRESS + PARTITION_SIZE;
+    let bytes = unsafe { core::slice::from_raw_parts(start as *const u8, end - start) };
+    let image = match EspImage::parse(bytes) {
+        Ok(image) if image.check(start, &MEMORY_MAP).is_ok() => image,
+        _ => panic!("invalid app image"),
+    };
+
+    for segment in image
+        .segments()
+        .filter(|segment| !in_flash_cache(segment.load_addr))
+    {
+        // IRAM only takes word accesses, images are padded to whole words
+        let src = (start + segment.offset) as *const u32;
+        for idx in 0..(segment.len + 3) / 4 {
+            unsafe {
+                write_volatile(
+                    (segment.load_addr as *mut u32).add(idx),
+                    read_volatile(src.add(idx)),
+                )
+            };
+        }
+    }
+    for segment in image
+        .segments()
+        .filter(|segment| in_flash_cache(segment.load_addr))
+    {
+        let vaddr = segment.load_addr - segment.load_addr % MMU_PAGE_SIZE;
+        let paddr = start - FLASH_MAP_BASE + segment.offset;
+        let paddr = paddr - paddr % MMU_PAGE_SIZE;
+        let pages =
+            ((segment.load_addr + segment.len - vaddr + MMU_PAGE_SIZE - 1) / MMU_PAGE_SIZE) as u32;
+        unsafe {
+            match IROM.contains(&segment.load_addr) {
+                true => Cache_Ibus_MMU_Set(
+                    MMU_ACCESS_FLASH,
+                    vaddr as u32,
+                    paddr as u32,
+                    MMU_PAGE_SIZE_KB,
+                    pages,
+                    0,
+                ),
+                false => Cache_Dbus_MMU_Set(
+                    MMU_ACCESS_FLASH,
+                    vaddr as u32,
+                    paddr as u32,
+                    MMU_PAGE_SIZE_KB,
+                    pages,
+                    0,
+                ),
+            };
+        }
+    }
+    unsafe {
+        Cache_Invalidate_ICache_All();
+        Cache_Invalidate_DCache_All();
+        for ctrl1 in [EXTMEM_ICACHE_CTRL1, EXTMEM_DCACHE_CTRL1] {
+            let value = read_volatile(ctrl1 as *const u32);
+            write_volatile(ctrl1 as *mut u32, value & !CACHE_SHUT_CORE0_BUS);
+        }
+        let entry: extern "C" fn() -> ! = core::mem::transmute(image.entry());
+        entry()
+    }
+}
+
+/// Returns true if `addr` is in the flash cache's address space i.e. a segment loaded there is
+/// mapped rather than copied.
+fn in_flash_cache(addr: usize) -> bool {
+    IROM.contains(&addr) || DROM.contains(&addr)
+}
@@ -0,0 +1,2 @@
+#[cfg(feature = "esp32s3")]
+pub mod esp32s3;
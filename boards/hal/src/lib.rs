@@ -17,6 +17,29 @@ pub mod nxp;
 pub mod stm;
 #[cfg(feature = "pico")]
 pub mod pico;
+#[cfg(feature = "esp")]
+pub mod esp;
+#[cfg(feature = "starfive")]
+pub mod starfive;
+#[cfg(feature = "se")]
+pub mod se;
+#[cfg(feature = "hide-bootloader")]
+pub mod mpu;
+#[cfg(feature = "armv8m")]
+pub mod armv8m;
+#[cfg(feature = "panic-record")]
+pub mod panic_record;
+#[cfg(feature = "boot-reason")]
+pub mod boot_reason;
+#[cfg(feature = "nor-flash")]
+pub mod nor_flash;
+#[cfg(feature = "ramfunc")]
+pub mod ramfunc;
+#[cfg(feature = "ecc-trap")]
+pub mod ecc;
+#[cfg(feature = "entropy")]
+pub mod entropy;
+pub mod boot_pin;
 
 /// This is the trait that abstracts out the necessary hardware-specific flash operations
 /// such as
@@ -24,13 +47,271 @@ pub mod pico;
 /// - `writing to flash` - write an arbitrary blob of data to an arbitrary location in flash
 /// - `erasing a flash page` - erase a page of flash, given the address (i.e. first word) of the page
 /// to be erased and number of btyes to erase.
+/// - `verifying writes and erases` - (optional) read back what was just written or erased, to
+/// catch worn sectors that fail silently.
+/// - `write-protecting flash` - lock a region of flash (i.e. rustBoot's own code and its embedded
+/// public key) against writes and erases, before jumping to firmware.
+/// - `hiding flash` - (optional) make that region inaccessible to firmware altogether.
+/// - `installing companion images` - (optional) hand off a verified companion image, delivered
+/// along with an update, to its destination i.e. a radio or coprocessor.
 ///
 pub trait FlashInterface {
+    /// `true` if a programmed write unit can't be programmed again before it's erased (ex: the
+    /// stm32h7s' ECC-protected flash, whose ECC covers the whole unit). rustBoot then refuses to
+    /// re-write a unit it already wrote, rather than corrupt it. Defaults to `false`.
+    const PROGRAM_ONCE: bool = false;
     fn hal_init();
     fn hal_flash_unlock(&self);
     fn hal_flash_lock(&self);
-    fn hal_flash_write(&self, addr: usize, data: *const u8, len: usize);
-    fn hal_flash_erase(&self, addr: usize, len: usize);
+    /// Writes `data` to flash, starting at `addr`. Returns an error if the flash controller
+    /// reports one, see [`FlashInterface::hal_flash_verify`] for checking that a write actually
+    /// took.
+    fn hal_flash_write(&self, addr: usize, data: &[u8]) -> Result<(), FlashError>;
+    /// Erases every page (or sector) that `addr..addr + len` overlaps. Returns an error if the
+    /// flash controller reports one.
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError>;
+    /// Fills `data` with the bytes of flash at `addr`. Returns [`FlashError::ReadFailed`] if the
+    /// flash can't be read (ex: an uncorrectable ECC error, see `ecc`).
+    ///
+    /// The default impl reads memory-mapped flash. Boards with non-memory-mapped flash must
+    /// override this.
+    fn hal_flash_read(&self, addr: usize, data: &mut [u8]) -> Result<(), FlashError> {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = unsafe { core::ptr::read_volatile((addr + i) as *const u8) };
+        }
+        Ok(())
+    }
+    /// Makes `len` bytes of flash at `addr` read back fresh after an erase or a write i.e. drops
+    /// whatever caches, prefetch buffers or accelerators (ex: the stm32f7s' ART) may still hold
+    /// of the old contents, and orders the accesses with barriers.
+    ///
+    /// Boards call it once an erase or a write completes, and the default verify impls call it
+    /// before reading back. The default impl does nothing i.e. for parts that don't cache flash.
+    fn hal_post_erase_barrier(&self, addr: usize, len: usize) {}
+    /// Checks that the flash at `addr` holds `data` i.e. a write took.
+    fn hal_flash_verify(&self, addr: usize, data: &[u8]) -> Result<(), FlashError> {
+        self.hal_post_erase_barrier(addr, data.len());
+        let mut buf = [0u8; 32];
+        for (i, expected) in data.chunks(32).enumerate() {
+            let read = &mut buf[..expected.len()];
+            self.hal_flash_read(addr + i * 32, read).map_err(|_| FlashError::WriteFailed)?;
+            if read != expected {
+                return Err(FlashError::WriteFailed);
+            }
+        }
+        Ok(())
+    }
+    /// Checks that the `len` bytes of flash at `addr` are erased (i.e. read as `0xFF`).
+    fn hal_flash_verify_erased(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        self.hal_post_erase_barrier(addr, len);
+        let mut buf = [0u8; 32];
+        let mut pos = 0;
+        while pos < len {
+            let read = &mut buf[..(len - pos).min(32)];
+            self.hal_flash_read(addr + pos, read).map_err(|_| FlashError::EraseFailed)?;
+            if read.iter().any(|byte| *byte != 0xFF) {
+                return Err(FlashError::EraseFailed);
+            }
+            pos += read.len();
+        }
+        Ok(())
+    }
+    /// Write-protects `len` bytes of flash, starting at `addr`.
+    ///
+    /// Protection is applied at the granularity supported by the hardware - only sectors
+    /// (or protection groups) that lie entirely within the region are protected. It must hold
+    /// at least until the next reset. Boards that persist protection in option bytes should
+    /// only re-program them when they don't already match.
+    fn hal_flash_protect(&self, addr: usize, len: usize);
+    /// Makes `len` bytes of flash, starting at `addr`, inaccessible to firmware (i.e. no reads,
+    /// writes or instruction fetches) once it's booted. Only as much of the region as the
+    /// hardware can cover is hidden.
+    ///
+    /// With the `hide-bootloader` feature, the default impl sets up the Cortex-M MPU (see
+    /// [`mpu`]), which is enabled as firmware is booted. Without it, this is a no-op.
+    fn hal_hide_region(&self, addr: usize, len: usize) {
+        #[cfg(feature = "hide-bootloader")]
+        mpu::hide(addr, len);
+    }
+    /// Returns the device's current debug-access (SWD/JTAG) protection.
+    fn hal_debug_protection(&self) -> DebugProtection;
+    /// Raises the device's debug-access protection to `level`. Lowering it isn't supported, as
+    /// that erases the chip.
+    ///
+    /// On most parts, the new level only takes effect after the next reset.
+    fn hal_set_debug_protection(&self, level: DebugProtection);
+    /// Returns true if the board can install the companion image (e.g. radio or coprocessor
+    /// firmware) identified by `id`. Boards without companion images needn't implement this.
+    fn hal_has_companion(&self, id: u8) -> bool {
+        false
+    }
+    /// Installs a companion image's (already verified) `firmware` to its destination. Only
+    /// called for ids accepted by [`FlashInterface::hal_has_companion`].
+    ///
+    /// Installation may be interrupted by a reset and is then repeated, so it must be idempotent.
+    fn hal_install_companion(&self, id: u8, firmware: &[u8]) {}
+}
+
+/// Non-blocking flash operations i.e. an erase or write is started and then polled until it
+/// completes, rather than busy-waiting on the flash controller.
+///
+/// This is meant for applications that stage updates while running time-critical code (ex: a
+/// BLE stack), see [`NonBlocking`]. Only a single operation may be in flight at a time.
+///
+/// *Note: flash reads still stall while an operation is in progress, on most parts. Code that
+/// must run during an operation (ex: a radio's interrupt handlers) has to execute from RAM or
+/// from another flash bank, see `ramfunc`.*
+pub trait FlashInterfaceNb: FlashInterface {
+    /// The number of bytes programmed by [`FlashInterfaceNb::hal_start_write`] (at most 32).
+    const WRITE_SIZE: usize;
+    /// Starts erasing the page (or sector) containing `addr` and returns the address of the
+    /// next page. If `addr` isn't in (erasable) flash, nothing is started and `usize::MAX` is
+    /// returned.
+    fn hal_start_erase(&self, addr: usize) -> usize;
+    /// Starts programming `data` (i.e. `WRITE_SIZE` bytes) at `addr`, which must be aligned to
+    /// `WRITE_SIZE`.
+    fn hal_start_write(&self, addr: usize, data: &[u8]);
+    /// Returns `true` once the operation started last has completed. The flash controller is
+    /// then idle (and locked, where applicable).
+    fn hal_poll_complete(&self) -> bool;
+}
+
+/// Adapts a [`FlashInterfaceNb`] to [`FlashInterface`] - every erase and write is started and
+/// polled to completion, calling `wait` while the flash controller is busy.
+///
+/// Interrupts are serviced between polls (i.e. they aren't held off for a whole page erase), and
+/// `wait` can yield to the application (ex: to run a BLE stack's event loop).
+pub struct NonBlocking<I> {
+    iface: I,
+    wait: fn(),
+}
+
+impl<I: FlashInterfaceNb> NonBlocking<I> {
+    pub fn new(iface: I, wait: fn()) -> Self {
+        NonBlocking { iface, wait }
+    }
+
+    fn complete(&self) {
+        while !self.iface.hal_poll_complete() {
+            (self.wait)()
+        }
+    }
+}
+
+impl<I: FlashInterfaceNb> FlashInterface for NonBlocking<I> {
+    fn hal_init() {
+        I::hal_init()
+    }
+    fn hal_flash_unlock(&self) {
+        self.iface.hal_flash_unlock()
+    }
+    fn hal_flash_lock(&self) {
+        self.iface.hal_flash_lock()
+    }
+    /// Writes are split into `WRITE_SIZE` units. Bytes of a unit that lie outside
+    /// `addr..addr + data.len()` are re-written with their current value.
+    fn hal_flash_write(&self, addr: usize, data: &[u8]) -> Result<(), FlashError> {
+        let size = I::WRITE_SIZE;
+        let len = data.len();
+        let mut unit = [0u8; 32];
+        let mut unit_addr = addr - addr % size;
+        while unit_addr < addr + len {
+            self.iface.hal_flash_read(unit_addr, &mut unit[..size])?;
+            for (i, byte) in unit[..size].iter_mut().enumerate() {
+                let at = unit_addr + i;
+                if at >= addr && at < addr + len {
+                    *byte = data[at - addr];
+                }
+            }
+            self.iface.hal_start_write(unit_addr, &unit[..size]);
+            self.complete();
+            unit_addr += size;
+        }
+        self.iface.hal_post_erase_barrier(addr, len);
+        Ok(())
+    }
+    /// Erases every page (or sector) that `addr..addr + len` overlaps.
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        let mut page = addr;
+        while page < addr + len {
+            page = self.iface.hal_start_erase(page);
+            self.complete();
+        }
+        self.iface.hal_post_erase_barrier(addr, len);
+        Ok(())
+    }
+    fn hal_flash_read(&self, addr: usize, data: &mut [u8]) -> Result<(), FlashError> {
+        self.iface.hal_flash_read(addr, data)
+    }
+    fn hal_post_erase_barrier(&self, addr: usize, len: usize) {
+        self.iface.hal_post_erase_barrier(addr, len)
+    }
+    fn hal_flash_verify(&self, addr: usize, data: &[u8]) -> Result<(), FlashError> {
+        self.iface.hal_flash_verify(addr, data)
+    }
+    fn hal_flash_verify_erased(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        self.iface.hal_flash_verify_erased(addr, len)
+    }
+    fn hal_flash_protect(&self, addr: usize, len: usize) {
+        self.iface.hal_flash_protect(addr, len)
+    }
+    fn hal_hide_region(&self, addr: usize, len: usize) {
+        self.iface.hal_hide_region(addr, len)
+    }
+    fn hal_debug_protection(&self) -> DebugProtection {
+        self.iface.hal_debug_protection()
+    }
+    fn hal_set_debug_protection(&self, level: DebugProtection) {
+        self.iface.hal_set_debug_protection(level)
+    }
+    fn hal_has_companion(&self, id: u8) -> bool {
+        self.iface.hal_has_companion(id)
+    }
+    fn hal_install_companion(&self, id: u8, firmware: &[u8]) {
+        self.iface.hal_install_companion(id, firmware)
+    }
+}
+
+/// A failed flash operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashError {
+    /// a write failed, or the flash doesn't hold the written data (ex: a worn sector).
+    WriteFailed,
+    /// an erase failed, or the erased region isn't blank.
+    EraseFailed,
+    /// a read failed (ex: it hit an uncorrectable ECC error).
+    ReadFailed,
+}
+
+/// Debug-access protection levels, in increasing order of protection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugProtection {
+    /// the debug port is open.
+    Disabled,
+    /// the debug port can't access flash or RAM, this can only be undone with a full chip
+    /// erase (i.e. STM32 RDP level 1, nRF APPROTECT).
+    Enabled,
+    /// the debug port is disabled for good (i.e. STM32 RDP level 2).
+    Permanent,
+}
+
+/// Returns a bitmask of the sectors that lie entirely within `addr..addr + len`, where bit `n`
+/// corresponds to `sectors[n]` i.e. a `(start address, size)` tuple.
+pub(crate) fn protected_sectors(sectors: &[(usize, usize)], addr: usize, len: usize) -> u32 {
+    sectors
+        .iter()
+        .enumerate()
+        .filter(|(_, (start, size))| *start >= addr && start + size <= addr + len)
+        .fold(0, |mask, (idx, _)| mask | (1 << idx))
+}
+
+/// Returns the index and end address of the sector containing `addr`, where `sectors` holds
+/// `(start address, size)` tuples.
+pub(crate) fn sector_at(sectors: &[(usize, usize)], addr: usize) -> Option<(u8, usize)> {
+    sectors
+        .iter()
+        .position(|(start, size)| addr >= *start && addr < start + size)
+        .map(|idx| (idx as u8, sectors[idx].0 + sectors[idx].1))
 }
 
 // Arch-specific code
@@ -57,7 +338,312 @@ pub fn boot_from(fw_base_address: usize) -> ! {
     #[cfg(feature = "stm32f334")]
     crate::stm::stm32f334::boot_from(fw_base_address);
 
+    #[cfg(feature = "stm32f407")]
+    crate::stm::stm32f407::boot_from(fw_base_address);
+
+    #[cfg(feature = "stm32f769")]
+    crate::stm::stm32f769::boot_from(fw_base_address);
+
     #[cfg(feature = "rp2040")]
     crate::pico::rp2040::boot_from(fw_base_address);
+
+    #[cfg(feature = "rp2350")]
+    crate::pico::rp2350::boot_from(fw_base_address);
+
+    #[cfg(feature = "esp32s3")]
+    crate::esp::esp32s3::boot_from(fw_base_address);
     panic!(": unrecognized board")
 }
+
+/// The board's name i.e. the board id its images are signed for (see rbsigner's `--board`
+/// option). The bootloader refuses images signed for another board. `None` if the board is
+/// unrecognized.
+pub const BOARD: Option<&str> = board();
+
+const fn board() -> Option<&'static str> {
+    #[cfg(feature = "nrf52840")]
+    return Some("nrf52840");
+
+    #[cfg(feature = "stm32f411")]
+    return Some("stm32f411");
+
+    #[cfg(feature = "stm32f446")]
+    return Some("stm32f446");
+
+    #[cfg(feature = "stm32f469")]
+    return Some("stm32f469");
+
+    #[cfg(feature = "stm32h723")]
+    return Some("stm32h723");
+
+    #[cfg(feature = "stm32f746")]
+    return Some("stm32f746");
+
+    #[cfg(feature = "stm32f334")]
+    return Some("stm32f334");
+
+    #[cfg(feature = "stm32f407")]
+    return Some("stm32f407");
+
+    #[cfg(feature = "stm32f769")]
+    return Some("stm32f769");
+
+    #[cfg(feature = "rp2040")]
+    return Some("rp2040");
+
+    #[cfg(feature = "rp2350")]
+    return Some("rp2350");
+
+    #[cfg(feature = "esp32s3")]
+    return Some("esp32s3");
+
+    None
+}
+
+/// Returns the mcu's factory-programmed unique ID i.e. the device-unique root that image keys are
+/// derived from (see `rustBoot::crypto::kdf::UidRoot`). `None` if the board's ID isn't
+/// memory-mapped (i.e. the rp2040's, which is read from its external flash, and the rp2350's, which
+/// is kept in OTP).
+pub fn device_uid() -> Option<&'static [u8]> {
+    #[cfg(feature = "nrf52840")]
+    return Some(crate::nrf::nrf52840::device_uid());
+
+    #[cfg(feature = "stm32f411")]
+    return Some(crate::stm::stm32f411::device_uid());
+
+    #[cfg(feature = "stm32f446")]
+    return Some(crate::stm::stm32f446::device_uid());
+
+    #[cfg(feature = "stm32f469")]
+    return Some(crate::stm::stm32f469::device_uid());
+
+    #[cfg(feature = "stm32h723")]
+    return Some(crate::stm::stm32h723::device_uid());
+
+    #[cfg(feature = "stm32f746")]
+    return Some(crate::stm::stm32f746::device_uid());
+
+    #[cfg(feature = "stm32f334")]
+    return Some(crate::stm::stm32f334::device_uid());
+
+    #[cfg(feature = "stm32f407")]
+    return Some(crate::stm::stm32f407::device_uid());
+
+    #[cfg(feature = "stm32f769")]
+    return Some(crate::stm::stm32f769::device_uid());
+
+    None
+}
+
+/// Returns the time (in seconds since the unix epoch) per the board's RTC calendar i.e. for the
+/// boot report. `None` if the board has no calendar RTC (the nrf52840's RTCs are just counters) or
+/// if it was never set.
+pub fn rtc_time() -> Option<u64> {
+    #[cfg(feature = "stm32f411")]
+    return crate::stm::stm32f411::rtc_time();
+
+    #[cfg(feature = "stm32f446")]
+    return crate::stm::stm32f446::rtc_time();
+
+    #[cfg(feature = "stm32f469")]
+    return crate::stm::stm32f469::rtc_time();
+
+    #[cfg(feature = "stm32h723")]
+    return crate::stm::stm32h723::rtc_time();
+
+    #[cfg(feature = "stm32f746")]
+    return crate::stm::stm32f746::rtc_time();
+
+    #[cfg(feature = "stm32f334")]
+    return crate::stm::stm32f334::rtc_time();
+
+    #[cfg(feature = "stm32f407")]
+    return crate::stm::stm32f407::rtc_time();
+
+    #[cfg(feature = "stm32f769")]
+    return crate::stm::stm32f769::rtc_time();
+
+    None
+}
+
+/// Returns the mcu's RAM i.e. where a (sane) image's initial stack pointer points to. The
+/// stack pointer may also point right past it, as stacks grow down. `None` if the board is
+/// unrecognized or its images don't start with a vector table (i.e. the esp32s3's app images, see
+/// `rustBoot::image::esp`).
+pub fn ram_region() -> Option<core::ops::Range<usize>> {
+    #[cfg(feature = "nrf52840")]
+    return Some(crate::nrf::nrf52840::ram_region());
+
+    #[cfg(feature = "stm32f411")]
+    return Some(crate::stm::stm32f411::ram_region());
+
+    #[cfg(feature = "stm32f446")]
+    return Some(crate::stm::stm32f446::ram_region());
+
+    #[cfg(feature = "stm32f469")]
+    return Some(crate::stm::stm32f469::ram_region());
+
+    #[cfg(feature = "stm32h723")]
+    return Some(crate::stm::stm32h723::ram_region());
+
+    #[cfg(feature = "stm32f746")]
+    return Some(crate::stm::stm32f746::ram_region());
+
+    #[cfg(feature = "stm32f334")]
+    return Some(crate::stm::stm32f334::ram_region());
+
+    #[cfg(feature = "stm32f407")]
+    return Some(crate::stm::stm32f407::ram_region());
+
+    #[cfg(feature = "stm32f769")]
+    return Some(crate::stm::stm32f769::ram_region());
+
+    #[cfg(feature = "rp2040")]
+    return Some(crate::pico::rp2040::ram_region());
+
+    #[cfg(feature = "rp2350")]
+    return Some(crate::pico::rp2350::ram_region());
+
+    None
+}
+
+/// Returns true if `boot_pin` is held (see [`boot_pin`]) i.e. rustBoot should stay in its
+/// bootloader rather than boot firmware. Always `false` for boards without boot pin support (i.e.
+/// the rp2040, rp2350 and esp32s3).
+pub fn boot_pin_held(boot_pin: &boot_pin::BootPin) -> bool {
+    #[cfg(feature = "nrf52840")]
+    return crate::nrf::nrf52840::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32f411")]
+    return crate::stm::stm32f411::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32f446")]
+    return crate::stm::stm32f446::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32f469")]
+    return crate::stm::stm32f469::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32h723")]
+    return crate::stm::stm32h723::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32f746")]
+    return crate::stm::stm32f746::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32f334")]
+    return crate::stm::stm32f334::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32f407")]
+    return crate::stm::stm32f407::boot_pin_held(boot_pin);
+
+    #[cfg(feature = "stm32f769")]
+    return crate::stm::stm32f769::boot_pin_held(boot_pin);
+
+    false
+}
+
+/// The number of (32-bit) registers that survive a reset, that rustBoot uses i.e. to record a
+/// bootloader panic (see [`panic_record`]).
+pub const BACKUP_REGS: usize = 4;
+
+/// Reads the board's backup registers i.e. the RTC's backup registers on stm32 parts and the
+/// watchdog's scratch registers on the rp2040 and rp2350. `None` if the board has none (i.e. the
+/// nrf52840) or rustBoot doesn't use them (i.e. the esp32s3).
+pub fn read_backup_regs() -> Option<[u32; BACKUP_REGS]> {
+    #[cfg(feature = "stm32f411")]
+    return Some(crate::stm::stm32f411::read_backup_regs());
+
+    #[cfg(feature = "stm32f446")]
+    return Some(crate::stm::stm32f446::read_backup_regs());
+
+    #[cfg(feature = "stm32f469")]
+    return Some(crate::stm::stm32f469::read_backup_regs());
+
+    #[cfg(feature = "stm32h723")]
+    return Some(crate::stm::stm32h723::read_backup_regs());
+
+    #[cfg(feature = "stm32f746")]
+    return Some(crate::stm::stm32f746::read_backup_regs());
+
+    #[cfg(feature = "stm32f334")]
+    return Some(crate::stm::stm32f334::read_backup_regs());
+
+    #[cfg(feature = "stm32f407")]
+    return Some(crate::stm::stm32f407::read_backup_regs());
+
+    #[cfg(feature = "stm32f769")]
+    return Some(crate::stm::stm32f769::read_backup_regs());
+
+    #[cfg(feature = "rp2040")]
+    return Some(crate::pico::rp2040::read_backup_regs());
+
+    #[cfg(feature = "rp2350")]
+    return Some(crate::pico::rp2350::read_backup_regs());
+
+    None
+}
+
+/// Writes the board's backup registers, see [`read_backup_regs`]. Returns `false` if the board has
+/// none.
+pub fn write_backup_regs(regs: &[u32; BACKUP_REGS]) -> bool {
+    #[cfg(feature = "stm32f411")]
+    {
+        crate::stm::stm32f411::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "stm32f446")]
+    {
+        crate::stm::stm32f446::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "stm32f469")]
+    {
+        crate::stm::stm32f469::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "stm32h723")]
+    {
+        crate::stm::stm32h723::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "stm32f746")]
+    {
+        crate::stm::stm32f746::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "stm32f334")]
+    {
+        crate::stm::stm32f334::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "stm32f407")]
+    {
+        crate::stm::stm32f407::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "stm32f769")]
+    {
+        crate::stm::stm32f769::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "rp2040")]
+    {
+        crate::pico::rp2040::write_backup_regs(regs);
+        return true;
+    }
+
+    #[cfg(feature = "rp2350")]
+    {
+        crate::pico::rp2350::write_backup_regs(regs);
+        return true;
+    }
+
+    false
+}
@@ -0,0 +1,103 @@
+//! Hides rustBoot (i.e. its code and embedded keys) from firmware with the Cortex-M MPU, see the
+//! `hide-bootloader` feature.
+//!
+//! [`hide`] sets up MPU regions that deny all access (reads, writes and instruction fetches) to
+//! the bootloader's flash and [`boot`] enables the MPU as it jumps to firmware. The MPU is enabled
+//! from RAM, as rustBoot can no longer execute its own code once it's hidden. The default memory
+//! map still applies everywhere else (i.e. `PRIVDEFENA`).
+//!
+//! This works the same on Armv6-M (rp2040) and Armv7-M (nrf52840, stm32) parts, so the boards'
+//! [`FlashInterface::hal_hide_region`](crate::FlashInterface::hal_hide_region) use it as-is.
+//!
+//! # Limitations
+//!
+//! - the MPU only keeps *unprivileged* code out. Firmware running privileged (i.e. most bare-metal
+//!   firmware) can reconfigure or disable the MPU, and read the bootloader afterwards. Hiding the
+//!   bootloader for good needs TrustZone (i.e. rustBoot in secure flash, set up with the SAU on
+//!   Armv8-M parts), which none of rustBoot's boards have.
+//! - the MPU only polices the CPU - DMA isn't affected and neither is the debug port (see
+//!   [`DebugProtection`](crate::DebugProtection)).
+//! - MPU regions are power-of-two sized and aligned. Only as much of the bootloader as the
+//!   available regions cover (largest blocks first) is hidden.
+//! - flash aliases (ex: STM32 parts mapping the boot flash at `0x0`) aren't hidden.
+
+use cortex_m::peripheral::MPU;
+
+const CTRL_ENABLE: u32 = 1 << 0;
+const CTRL_PRIVDEFENA: u32 = 1 << 2;
+const RASR_ENABLE: u32 = 1 << 0;
+const RASR_SIZE_SHIFT: u32 = 1;
+// `C` i.e. normal, write-through memory (flash)
+const RASR_CACHEABLE: u32 = 1 << 17;
+// `AP = 0b000` i.e. no access, privileged or unprivileged
+const RASR_NO_ACCESS: u32 = 0b000 << 24;
+const RASR_XN: u32 = 1 << 28;
+/// The smallest region supported by both Armv6-M and Armv7-M MPUs, in bytes.
+const MIN_REGION_SIZE: usize = 256;
+
+/// Sets up MPU regions that deny all access to `addr..addr + len`, replacing any existing
+/// regions. The MPU is only enabled by [`boot`]. Returns the number of bytes hidden, from `addr`.
+pub fn hide(addr: usize, len: usize) -> usize {
+    let mpu = unsafe { &*MPU::PTR };
+    let regions = (mpu._type.read() >> 8) & 0xFF;
+    let (mut at, end) = (addr, addr + len);
+    for region in 0..regions {
+        unsafe { mpu.rnr.write(region) };
+        // the largest block that's aligned at `at` and fits
+        let mut size = match end.saturating_sub(at) {
+            0 => 0,
+            rest => 1 << (usize::BITS - 1 - rest.leading_zeros()),
+        };
+        if at != 0 {
+            size = size.min(1 << at.trailing_zeros());
+        }
+        if size < MIN_REGION_SIZE {
+            unsafe { mpu.rasr.write(0) };
+            continue;
+        }
+        let size_field = (size.trailing_zeros() - 1) << RASR_SIZE_SHIFT;
+        unsafe {
+            mpu.rbar.write(at as u32);
+            mpu.rasr
+                .write(RASR_XN | RASR_NO_ACCESS | RASR_CACHEABLE | size_field | RASR_ENABLE);
+        }
+        at += size;
+    }
+    at - addr
+}
+
+/// Enables the MPU (if [`hide`] set up any regions) and jumps to firmware i.e. loads `sp` into
+/// the main stack pointer and branches to `reset_vector`. The vector table must already point to
+/// the firmware's.
+///
+/// # Safety
+///
+/// `sp` and `reset_vector` must be the (checked) initial stack pointer and reset vector of a
+/// verified image. Nothing of rustBoot's can run afterwards.
+pub unsafe fn boot(sp: u32, reset_vector: u32) -> ! {
+    let mpu = &*MPU::PTR;
+    mpu.rnr.write(0);
+    let ctrl = match mpu.rasr.read() & RASR_ENABLE {
+        0 => 0,
+        _ => CTRL_ENABLE | CTRL_PRIVDEFENA,
+    };
+    enable_and_jump(&mpu.ctrl as *const _ as *mut u32, ctrl, sp, reset_vector)
+}
+
+/// Runs from RAM, as rustBoot's flash can't be executed once the MPU is enabled.
+#[inline(never)]
+#[link_section = ".data.rustBoot_mpu_boot"]
+unsafe extern "C" fn enable_and_jump(ctrl_reg: *mut u32, ctrl: u32, sp: u32, rv: u32) -> ! {
+    core::arch::asm!(
+        "str {ctrl}, [{ctrl_reg}]",
+        "dsb",
+        "isb",
+        "msr msp, {sp}",
+        "bx {rv}",
+        ctrl_reg = in(reg) ctrl_reg,
+        ctrl = in(reg) ctrl,
+        sp = in(reg) sp,
+        rv = in(reg) rv,
+        options(noreturn)
+    )
+}
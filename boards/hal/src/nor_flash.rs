@@ -0,0 +1,244 @@
+//! Adapters between rustBoot's [`FlashInterface`] and `embedded-storage`'s [`NorFlash`] i.e. the
+//! trait most community flash drivers implement.
+//!
+//! - [`NorFlashInterface`] turns a [`NorFlash`] driver (ex: a chip HAL's flash driver or an
+//! external SPI NOR flash driver) into a [`FlashInterface`], so a new board can be brought up
+//! without writing its own flash routines.
+//! - [`FlashStorage`] exposes a board's [`FlashInterface`] as [`NorFlash`] i.e. to libraries
+//! written against `embedded-storage` (ex: a key-value store kept in firmware's flash).
+//!
+//! [`FlashInterface`] takes absolute addresses, whereas [`NorFlash`] takes offsets from the start
+//! of the device. Both adapters translate between the two with a `base` address.
+
+use core::cell::RefCell;
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+
+use crate::{DebugProtection, FlashError, FlashInterface};
+
+/// The largest `READ_SIZE` and `WRITE_SIZE` a [`NorFlashInterface`] supports i.e. the size of the
+/// buffer unaligned reads and writes go through.
+pub const MAX_UNIT_SIZE: usize = 32;
+
+/// Adapts a [`NorFlash`] driver to [`FlashInterface`].
+///
+/// Reads and writes that aren't aligned to the driver's `READ_SIZE` or `WRITE_SIZE` are padded
+/// with the flash's current contents (i.e. a read-modify-write), erases are widened to whole
+/// `ERASE_SIZE` sectors.
+///
+/// `NorFlash` has no notion of write-protection or debug-access, so
+/// [`FlashInterface::hal_flash_protect`] and [`FlashInterface::hal_set_debug_protection`] are
+/// no-ops and the debug port always reads as [`DebugProtection::Disabled`]. Boards that rely on
+/// them (i.e. for rustBoot's own flash) must implement [`FlashInterface`] themselves.
+pub struct NorFlashInterface<F> {
+    flash: RefCell<F>,
+    base: usize,
+}
+
+impl<F: NorFlash> NorFlashInterface<F> {
+    /// `base` is the (absolute) address of the driver's offset `0` i.e. where its flash is
+    /// memory-mapped or, for non memory-mapped flash, where the partition layout places it.
+    pub fn new(flash: F, base: usize) -> Self {
+        NorFlashInterface {
+            flash: RefCell::new(flash),
+            base,
+        }
+    }
+
+    /// Returns the driver.
+    pub fn release(self) -> F {
+        self.flash.into_inner()
+    }
+
+    /// Translates `addr..addr + len` to an offset range of the driver's flash.
+    fn offsets(&self, addr: usize, len: usize) -> Option<(usize, usize)> {
+        let start = addr.checked_sub(self.base)?;
+        let end = start.checked_add(len)?;
+        match end <= self.flash.borrow().capacity() {
+            true => Some((start, end)),
+            false => None,
+        }
+    }
+}
+
+/// Returns the next chunk of `pos..end` i.e. `(unit_start, chunk_end)`, for a device that reads
+/// (or writes) `size` byte units. The chunk is either a run of whole units (`unit_start == pos`)
+/// or a part of a single unit, which has to go through a buffer.
+fn next_chunk(pos: usize, end: usize, size: usize) -> (usize, usize) {
+    let unit_start = pos - pos % size;
+    if unit_start == pos && end - pos >= size {
+        (pos, end - (end - pos) % size)
+    } else {
+        (unit_start, (unit_start + size).min(end))
+    }
+}
+
+impl<F: NorFlash> FlashInterface for NorFlashInterface<F> {
+    fn hal_init() {}
+    fn hal_flash_unlock(&self) {}
+    fn hal_flash_lock(&self) {}
+    fn hal_flash_write(&self, addr: usize, data: &[u8]) -> Result<(), FlashError> {
+        let size = F::WRITE_SIZE;
+        let (start, end) = self
+            .offsets(addr, data.len())
+            .ok_or(FlashError::WriteFailed)?;
+        if size > MAX_UNIT_SIZE {
+            return Err(FlashError::WriteFailed);
+        }
+        let mut flash = self.flash.borrow_mut();
+        let mut buf = [0u8; MAX_UNIT_SIZE];
+        let mut pos = start;
+        while pos < end {
+            let (unit_start, chunk_end) = next_chunk(pos, end, size);
+            let chunk = &data[pos - start..chunk_end - start];
+            if unit_start == pos && chunk.len() % size == 0 {
+                flash
+                    .write(pos as u32, chunk)
+                    .map_err(|_| FlashError::WriteFailed)?;
+            } else {
+                let unit = &mut buf[..size];
+                read_units(&mut *flash, unit_start, unit).map_err(|_| FlashError::WriteFailed)?;
+                unit[pos - unit_start..chunk_end - unit_start].copy_from_slice(chunk);
+                flash
+                    .write(unit_start as u32, unit)
+                    .map_err(|_| FlashError::WriteFailed)?;
+            }
+            pos = chunk_end;
+        }
+        Ok(())
+    }
+    /// Erases every `ERASE_SIZE` sector that `addr..addr + len` overlaps.
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        let size = F::ERASE_SIZE;
+        let (start, end) = self.offsets(addr, len).ok_or(FlashError::EraseFailed)?;
+        if len == 0 {
+            return Ok(());
+        }
+        let mut flash = self.flash.borrow_mut();
+        let from = start - start % size;
+        let to = match end % size {
+            0 => end,
+            rem => (end - rem + size).min(flash.capacity()),
+        };
+        flash
+            .erase(from as u32, to as u32)
+            .map_err(|_| FlashError::EraseFailed)
+    }
+    /// Reads through the driver i.e. the flash needn't be memory-mapped.
+    fn hal_flash_read(&self, addr: usize, data: &mut [u8]) -> Result<(), FlashError> {
+        let (start, _) = self
+            .offsets(addr, data.len())
+            .ok_or(FlashError::ReadFailed)?;
+        read_units(&mut *self.flash.borrow_mut(), start, data).map_err(|_| FlashError::ReadFailed)
+    }
+    fn hal_flash_protect(&self, addr: usize, len: usize) {}
+    fn hal_debug_protection(&self) -> DebugProtection {
+        DebugProtection::Disabled
+    }
+    fn hal_set_debug_protection(&self, level: DebugProtection) {}
+}
+
+/// Fills `data` with the flash at `offset`, regardless of the driver's `READ_SIZE`.
+fn read_units<F: ReadNorFlash>(
+    flash: &mut F,
+    offset: usize,
+    data: &mut [u8],
+) -> Result<(), NorFlashErrorKind> {
+    let size = F::READ_SIZE;
+    if size > MAX_UNIT_SIZE {
+        return Err(NorFlashErrorKind::NotAligned);
+    }
+    let end = offset + data.len();
+    let mut buf = [0u8; MAX_UNIT_SIZE];
+    let mut pos = offset;
+    while pos < end {
+        let (unit_start, chunk_end) = next_chunk(pos, end, size);
+        let chunk = &mut data[pos - offset..chunk_end - offset];
+        if unit_start == pos && chunk.len() % size == 0 {
+            flash
+                .read(pos as u32, chunk)
+                .map_err(|_| NorFlashErrorKind::Other)?;
+        } else {
+            let unit = &mut buf[..size];
+            flash
+                .read(unit_start as u32, unit)
+                .map_err(|_| NorFlashErrorKind::Other)?;
+            chunk.copy_from_slice(&unit[pos - unit_start..chunk_end - unit_start]);
+        }
+        pos = chunk_end;
+    }
+    Ok(())
+}
+
+/// Exposes a [`FlashInterface`] as [`NorFlash`], with `ERASE_SIZE` byte sectors.
+///
+/// The flash is unlocked for each write or erase, and locked again afterwards. Reads go through
+/// [`FlashInterface::hal_flash_read`]. Out-of-bounds or misaligned accesses are refused, before
+/// the flash is touched.
+pub struct FlashStorage<I, const ERASE_SIZE: usize> {
+    iface: I,
+    base: usize,
+    capacity: usize,
+}
+
+impl<I: FlashInterface, const ERASE_SIZE: usize> FlashStorage<I, ERASE_SIZE> {
+    /// `base` is the (absolute) address of the region to expose and `capacity` its size, which
+    /// must be a multiple of `ERASE_SIZE`.
+    pub fn new(iface: I, base: usize, capacity: usize) -> Self {
+        FlashStorage {
+            iface,
+            base,
+            capacity,
+        }
+    }
+
+    /// Returns the [`FlashInterface`].
+    pub fn release(self) -> I {
+        self.iface
+    }
+}
+
+impl<I: FlashInterface, const ERASE_SIZE: usize> ErrorType for FlashStorage<I, ERASE_SIZE> {
+    type Error = NorFlashErrorKind;
+}
+
+impl<I: FlashInterface, const ERASE_SIZE: usize> ReadNorFlash for FlashStorage<I, ERASE_SIZE> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        self.iface
+            .hal_flash_read(self.base + offset as usize, bytes)
+            .map_err(|_| NorFlashErrorKind::Other)
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<I: FlashInterface, const ERASE_SIZE: usize> NorFlash for FlashStorage<I, ERASE_SIZE> {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        self.iface.hal_flash_unlock();
+        let res = self
+            .iface
+            .hal_flash_erase(self.base + from as usize, (to - from) as usize);
+        self.iface.hal_flash_lock();
+        res.map_err(|_| NorFlashErrorKind::Other)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        self.iface.hal_flash_unlock();
+        let res = self
+            .iface
+            .hal_flash_write(self.base + offset as usize, bytes);
+        self.iface.hal_flash_lock();
+        res.map_err(|_| NorFlashErrorKind::Other)
+    }
+}
@@ -1,13 +1,14 @@
 //! NVMC (i.e. flash) driver for the nrf52840 board, written in pure-rust.
 
 use core::{
+    cell::Cell,
     ops::{Add, Sub},
     usize,
 };
 
 use nrf52840_hal as hal;
 
-use crate::FlashInterface;
+use crate::{DebugProtection, FlashError, FlashInterface, FlashInterfaceNb};
 use hal::pac::{Peripherals, NVMC};
 use nrf52840_constants::*;
 
@@ -19,23 +20,121 @@ mod nrf52840_constants {
     pub const RB_HDR_SIZE     : u32 = 0x100;
     pub const BASE_ADDR       : u32 = 0x2f000;
     pub const VTR_TABLE_SIZE  : u32 = 0x100;
+    // FICR `DEVICEID`
+    pub const UID_ADDR        : u32 = 0x1000_0060;
+    pub const UID_LEN         : usize = 8;
     pub const FW_RESET_VTR    : u32 = BASE_ADDR + RB_HDR_SIZE + VTR_TABLE_SIZE + 1;
+    // ACL i.e. access control lists, shares its base address with the NVMC.
+    pub const ACL_BASE        : u32 = 0x4001_E000;
+    pub const ACL0_ADDR       : u32 = ACL_BASE + 0x800;
+    pub const ACL0_SIZE       : u32 = ACL_BASE + 0x804;
+    pub const ACL0_PERM       : u32 = ACL_BASE + 0x808;
+    pub const ACL_PERM_WRITE_DISABLE : u32 = 1 << 1;
+    pub const UICR_APPROTECT  : u32 = 0x1000_1208;
+    // `APPROTECT` values that leave the access port open i.e. erased or `HwDisabled`
+    pub const APPROTECT_ERASED     : u32 = 0xFFFF_FFFF;
+    pub const APPROTECT_HWDISABLED : u32 = 0x0000_005A;
+    pub const APPROTECT_ENABLED    : u32 = 0x0000_0000;
+    // UICR `NRFFW[0]` (i.e. the bootloader's start address) and `NFCPINS`, see `uicr_state`
+    pub const UICR_NRFFW0     : u32 = 0x1000_1014;
+    pub const UICR_NFCPINS    : u32 = 0x1000_120C;
+    pub const NFCPINS_PROTECT : u32 = 1 << 0;
+    pub const BOOTLOADER_ADDR : u32 = 0x0;
+    // partial page erases (see `FlashInterfaceNb`), the NVMC and ACL share a base address.
+    pub const NVMC_ERASEPAGEPARTIAL    : u32 = ACL_BASE + 0x518;
+    pub const NVMC_ERASEPAGEPARTIALCFG : u32 = ACL_BASE + 0x51C;
+    pub const ERASEPAGEPARTIAL_MS      : u32 = 10;
+    // a page erase takes at most 85ms (i.e. `tERASEPAGE`), in partial erases
+    pub const PARTIAL_ERASES           : u32 = (85 + ERASEPAGEPARTIAL_MS - 1) / ERASEPAGEPARTIAL_MS;
+    // GPIO `P0`/`P1` i.e. the boot pin, see `boot_pin_held`. The core always runs at 64MHz.
+    pub const GPIO_P0         : u32 = 0x5000_0000;
+    pub const GPIO_P1         : u32 = 0x5000_0300;
+    pub const GPIO_IN         : u32 = 0x510;
+    pub const GPIO_PIN_CNF    : u32 = 0x700;
+    // `PIN_CNF` i.e. an input (with its buffer connected), pulled up or down
+    pub const PIN_CNF_PULLUP  : u32 = 0b11 << 2;
+    pub const PIN_CNF_PULLDOWN: u32 = 0b01 << 2;
+    pub const RESET_CLOCK_HZ  : u32 = 64_000_000;
+    // the RNG, see `Rng`. `CONFIG.DERCEN` enables its bias correction.
+    pub const RNG_TASKS_START : u32 = 0x4000_D000;
+    pub const RNG_TASKS_STOP  : u32 = 0x4000_D004;
+    pub const RNG_VALRDY      : u32 = 0x4000_D100;
+    pub const RNG_CONFIG      : u32 = 0x4000_D504;
+    pub const RNG_VALUE       : u32 = 0x4000_D508;
+    pub const CONFIG_DERCEN   : u32 = 1 << 0;
+    // `RESETREAS` (i.e. the reset flags) and `GPREGRET2` (i.e. the boot reason), see
+    // `take_reset_cause`
+    pub const POWER_RESETREAS : u32 = 0x4000_0400;
+    pub const POWER_GPREGRET2 : u32 = 0x4000_0520;
 }
 
 pub struct FlashWriterEraser {
     pub nvmc: NVMC,
+    /// the page being erased by non-blocking (partial) erases and the partial erases left
+    erase_page: Cell<u32>,
+    partial_erases: Cell<u32>,
 }
 
 impl FlashWriterEraser {
     pub fn new() -> Self {
         FlashWriterEraser {
             nvmc: Peripherals::take().unwrap().NVMC,
+            erase_page: Cell::new(0),
+            partial_erases: Cell::new(0),
         }
     }
+
+    fn start_partial_erase(&self) {
+        self.partial_erases.set(self.partial_erases.get() - 1);
+        unsafe {
+            core::ptr::write_volatile(NVMC_ERASEPAGEPARTIAL as *mut u32, self.erase_page.get())
+        }
+    }
+}
+
+/// Erases are split into partial erases of `ERASEPAGEPARTIAL_MS` each, so the CPU is only ever
+/// stalled for one partial erase (instead of a whole page erase) at a time.
+impl FlashInterfaceNb for FlashWriterEraser {
+    const WRITE_SIZE: usize = 4;
+
+    fn hal_start_erase(&self, addr: usize) -> usize {
+        let page = addr as u32 & !(FLASH_PAGE_SIZE - 1);
+        self.erase_page.set(page);
+        self.partial_erases.set(PARTIAL_ERASES);
+        // Enable erasing
+        self.nvmc.config.write(|w| w.wen().een());
+        unsafe {
+            core::ptr::write_volatile(NVMC_ERASEPAGEPARTIALCFG as *mut u32, ERASEPAGEPARTIAL_MS)
+        }
+        self.start_partial_erase();
+        (page + FLASH_PAGE_SIZE) as usize
+    }
+
+    fn hal_start_write(&self, addr: usize, data: &[u8]) {
+        let word = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]);
+        // Enable NVM writes
+        self.nvmc.config.write(|w| w.wen().wen());
+        while self.nvmc.readynext.read().readynext().is_busy() {}
+        unsafe { core::ptr::write_volatile(addr as *mut u32, word) }
+    }
+
+    fn hal_poll_complete(&self) -> bool {
+        if self.nvmc.ready.read().ready().is_busy() {
+            return false;
+        }
+        if self.partial_erases.get() > 0 {
+            self.start_partial_erase();
+            return false;
+        }
+        // set NVMC back to read-only
+        self.nvmc.config.write(|w| w.wen().ren());
+        true
+    }
 }
 
 impl FlashInterface for FlashWriterEraser {
-    fn hal_flash_write(&self, address: usize, data: *const u8, len: usize) {
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        let (data, len) = (data.as_ptr(), data.len());
         let address = address as u32;
         let len = len as u32;
 
@@ -89,9 +188,10 @@ impl FlashInterface for FlashWriterEraser {
                 idx += 1;
             }
         }
+        Ok(())
     }
 
-    fn hal_flash_erase(&self, addr: usize, len: usize) {
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
         let starting_page = addr as u32;
         let ending_page = (addr + len) as u32;
         // defmt::info!("starting_page={}, ending_page={}, len={}", starting_page, ending_page, len);
@@ -107,6 +207,53 @@ impl FlashInterface for FlashWriterEraser {
             // Wait until erasing is done
             while self.nvmc.ready.read().ready().is_busy() {}
         }
+        Ok(())
+    }
+
+    /// Write-protects a region of flash using ACL region 0.
+    ///
+    /// ACL regions are page (4KB) granular and cannot be changed until the next reset i.e.
+    /// they have to be set up on every boot, right before jumping to firmware.
+    fn hal_flash_protect(&self, addr: usize, len: usize) {
+        let start = (addr as u32 + FLASH_PAGE_SIZE - 1) & !(FLASH_PAGE_SIZE - 1);
+        let end = (addr + len) as u32 & !(FLASH_PAGE_SIZE - 1);
+        if end <= start {
+            return;
+        }
+        unsafe {
+            // an ACL region can only be configured once, after reset
+            if core::ptr::read_volatile(ACL0_SIZE as *const u32) != 0 {
+                return;
+            }
+            core::ptr::write_volatile(ACL0_ADDR as *mut u32, start);
+            core::ptr::write_volatile(ACL0_SIZE as *mut u32, end - start);
+            core::ptr::write_volatile(ACL0_PERM as *mut u32, ACL_PERM_WRITE_DISABLE);
+        }
+    }
+
+    /// Returns the state of the access port protection (`UICR.APPROTECT`).
+    ///
+    /// The nRF52840 has no equivalent to a permanent (i.e. STM32 RDP level 2) lock.
+    fn hal_debug_protection(&self) -> DebugProtection {
+        match unsafe { core::ptr::read_volatile(UICR_APPROTECT as *const u32) } {
+            APPROTECT_ERASED | APPROTECT_HWDISABLED => DebugProtection::Disabled,
+            _ => DebugProtection::Enabled,
+        }
+    }
+
+    /// Enables access port protection, by programming `UICR.APPROTECT`. It takes effect
+    /// after the next reset and can only be undone with an `ERASEALL`.
+    fn hal_set_debug_protection(&self, level: DebugProtection) {
+        if level <= self.hal_debug_protection() {
+            return;
+        }
+        // the NVMC doesn't report errors, the level is read back on the next boot
+        let _ = self.hal_flash_write(
+            UICR_APPROTECT as usize,
+            &APPROTECT_ENABLED.to_le_bytes(),
+        );
+        // set NVMC back to read-only
+        self.nvmc.config.write(|w| w.wen().ren());
     }
 
     fn hal_init() {}
@@ -116,6 +263,128 @@ impl FlashInterface for FlashWriterEraser {
 
 pub fn preboot() {}
 
+/// Returns the device's factory-programmed unique ID (i.e. FICR `DEVICEID`).
+pub fn device_uid() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(UID_ADDR as *const u8, UID_LEN) }
+}
+
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Returns true if `boot_pin` is held, see `rustBoot_hal::boot_pin_held`.
+pub fn boot_pin_held(boot_pin: &crate::boot_pin::BootPin) -> bool {
+    crate::boot_pin::sample(boot_pin, read_pin, RESET_CLOCK_HZ)
+}
+
+/// Reads a GPIO's level, after configuring it as an input with a pull-up (or a pull-down). `port`
+/// is `0` (i.e. `P0`) or `1` (`P1`).
+fn read_pin(port: u8, pin: u8, pull_up: bool) -> bool {
+    let gpio = match port {
+        0 => GPIO_P0,
+        _ => GPIO_P1,
+    };
+    let pin = pin as u32 & 0x1F;
+    let pull = match pull_up {
+        true => PIN_CNF_PULLUP,
+        false => PIN_CNF_PULLDOWN,
+    };
+    unsafe {
+        core::ptr::write_volatile((gpio + GPIO_PIN_CNF + 4 * pin) as *mut u32, pull);
+        core::ptr::read_volatile((gpio + GPIO_IN) as *const u32) & (1 << pin) != 0
+    }
+}
+
+/// Returns (and clears) the last reset's cause, see `rustBoot_hal::boot_reason`. `RESETREAS`
+/// flags none of its causes after a power-on (or brown-out) reset.
+#[cfg(feature = "boot-reason")]
+pub fn take_reset_cause() -> rustBoot::bootreason::ResetCause {
+    use rustBoot::bootreason::ResetCause;
+    // `DOG`, `LOCKUP`, `SREQ` and `RESETPIN`
+    const CAUSES: [(u32, ResetCause); 4] = [
+        (1 << 1, ResetCause::Watchdog),
+        (1 << 3, ResetCause::Lockup),
+        (1 << 2, ResetCause::Software),
+        (1 << 0, ResetCause::Pin),
+    ];
+    let flags = unsafe { core::ptr::read_volatile(POWER_RESETREAS as *const u32) };
+    // flags are cleared by writing 1s to them
+    unsafe { core::ptr::write_volatile(POWER_RESETREAS as *mut u32, flags) };
+    match flags {
+        0 => ResetCause::PowerOn,
+        _ => ResetCause::from_flags(flags, &CAUSES),
+    }
+}
+
+/// Reads `GPREGRET2`, which holds the boot reason (see `rustBoot_hal::boot_reason`). It's retained
+/// across resets, but for power-on and brown-out resets.
+#[cfg(feature = "boot-reason")]
+pub fn read_gpregret2() -> u8 {
+    unsafe { core::ptr::read_volatile(POWER_GPREGRET2 as *const u32) as u8 }
+}
+
+/// Writes `GPREGRET2`, see [`read_gpregret2`].
+#[cfg(feature = "boot-reason")]
+pub fn write_gpregret2(val: u8) {
+    unsafe { core::ptr::write_volatile(POWER_GPREGRET2 as *mut u32, val as u32) }
+}
+
+/// The RNG, see `rustBoot_hal::entropy`. Values are bias-corrected, a byte takes ~120us.
+#[cfg(feature = "entropy")]
+pub struct Rng;
+
+#[cfg(feature = "entropy")]
+impl rustBoot::crypto::entropy::EntropySource for Rng {
+    fn next_u32(&self) -> u32 {
+        let write =
+            |addr: u32, val: u32| unsafe { core::ptr::write_volatile(addr as *mut u32, val) };
+        let read = |addr: u32| unsafe { core::ptr::read_volatile(addr as *const u32) };
+        write(RNG_CONFIG, CONFIG_DERCEN);
+        write(RNG_TASKS_START, 1);
+        let mut value = 0;
+        for _ in 0..4 {
+            while read(RNG_VALRDY) == 0 {}
+            write(RNG_VALRDY, 0);
+            value = (value << 8) | (read(RNG_VALUE) & 0xFF);
+        }
+        write(RNG_TASKS_STOP, 1);
+        value
+    }
+}
+
+/// The UICR registers rustBoot relies on, as written by `cargo nrf52840 provision-uicr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UicrState {
+    /// `NRFFW[0]` i.e. the bootloader's start address, `None` if it's erased.
+    pub bootloader_addr: Option<u32>,
+    /// the NFC pins (P0.09/P0.10) are GPIOs i.e. `NFCPINS.PROTECT` is cleared.
+    pub nfc_pins_as_gpio: bool,
+    /// the access port is protected (`APPROTECT`), see `hal_debug_protection`.
+    pub approtect: bool,
+}
+
+impl UicrState {
+    /// Returns true if the UICR holds the bootloader's start address and frees the NFC pins.
+    /// `APPROTECT` is checked separately, as only production parts are locked.
+    pub fn is_provisioned(&self) -> bool {
+        self.bootloader_addr == Some(BOOTLOADER_ADDR) && self.nfc_pins_as_gpio
+    }
+}
+
+/// Reads the UICR's state.
+pub fn uicr_state() -> UicrState {
+    let read = |addr: u32| unsafe { core::ptr::read_volatile(addr as *const u32) };
+    UicrState {
+        bootloader_addr: match read(UICR_NRFFW0) {
+            0xFFFF_FFFF => None,
+            addr => Some(addr),
+        },
+        nfc_pins_as_gpio: read(UICR_NFCPINS) & NFCPINS_PROTECT == 0,
+        approtect: !matches!(read(UICR_APPROTECT), APPROTECT_ERASED | APPROTECT_HWDISABLED),
+    }
+}
+
 struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
 
 impl<const MIN: u32, const MAX: u32, const VAL: u32> RefinedUsize<MIN, MAX, VAL> {
@@ -144,6 +413,8 @@ pub fn boot_from(fw_base_address: usize) -> ! {
         cortex_m::asm::dsb();
         cortex_m::asm::isb();
         scb.vtor.write(base_img_addr);
+        #[cfg(feature = "hide-bootloader")]
+        crate::mpu::boot(stack_pointer, reset_vector);
         cortex_m::register::msp::write(stack_pointer);
         jump_vector()
     }
@@ -286,12 +286,59 @@ register_bitfields! {
         PLL_MAIN_DIV OFFSET(10) NUMBITS(2)[],
         RESRV2 OFFSET(22) NUMBITS(10)[],
     ],
+    /// DRAM PLL General Function Control Register
+    ///
+    /// The DRAM PLL is a fractional PLL i.e. its output enable is bit 13 (rather than 11, as for
+    /// the integer PLLs above).
+    DRAM_PLL_GEN_CTRL [
+        PLL_REF_CLK_SEL OFFSET(0) NUMBITS(2) [
+            Mhz24 = 0b00,
+            PadClk = 0b01,
+        ],
+        PLL_BYPASS OFFSET(4) NUMBITS(1)[],
+        PLL_RST_OVRD OFFSET(8) NUMBITS(1)[],
+        PLL_RST OFFSET(9) NUMBITS(1)[],
+        PLL_CLKE_OVRD OFFSET(12) NUMBITS(1)[],
+        PLL_CLKE OFFSET(13) NUMBITS(1)[],
+        PLL_LOCK_SEL OFFSET(29) NUMBITS(1)[],
+        PLL_LOCK OFFSET(31) NUMBITS(1)[],
+    ],
+    /// DRAM PLL Divide and Fraction Data Control 0 Register
+    DRAM_PLL_FDIV_CTRL0 [
+        /// Value of the post-divider
+        PLL_POST_DIV OFFSET(0) NUMBITS(3) [],
+        /// Value of the pre-divider
+        PLL_PRE_DIV OFFSET(4) NUMBITS(6)[],
+        /// Value of the main-divider
+        PLL_MAIN_DIV OFFSET(12) NUMBITS(10)[],
+    ],
+    /// DRAM PLL Divide and Fraction Data Control 1 Register
+    DRAM_PLL_FDIV_CTRL1 [
+        /// Value of the delta-sigma modulator i.e. the fractional part of the main-divider
+        PLL_DSM OFFSET(0) NUMBITS(16)[],
+    ],
 }
 
+/// The DRAM PLL's supported output frequencies (in MHz) and their main, pre and post dividers
+/// i.e. `24MHz * main / (pre * 2^post)`.
+const DRAM_PLL_RATES: [(u32, u32, u32, u32); 7] = [
+    (1000, 250, 3, 1),
+    (800, 200, 3, 1),
+    (750, 250, 8, 0),
+    (600, 300, 3, 2),
+    (400, 200, 3, 2),
+    (266, 266, 3, 3),
+    (167, 334, 3, 4),
+];
+
 register_structs! {
     #[allow(non_snake_case)]
     RegisterBlock {
         (0x00 => _reserved0),
+        (0x50 => DRAM_PLL_GEN_CTRL: ReadWrite<u32, DRAM_PLL_GEN_CTRL::Register>),
+        (0x54 => DRAM_PLL_FDIV_CTRL0: ReadWrite<u32, DRAM_PLL_FDIV_CTRL0::Register>),
+        (0x58 => DRAM_PLL_FDIV_CTRL1: ReadWrite<u32, DRAM_PLL_FDIV_CTRL1::Register>),
+        (0x5c => _reserved00),
         (0x84 => ARM_PLL_GEN_CTRL: ReadWrite<u32, ARM_GEN_CTRL::Register>),
         (0x88 => ARM_PLL_FDIV_CTRL: ReadWrite<u32, ARM_FDIV_CTRL::Register>),
         (0x8c => _reserved1),
@@ -437,6 +484,50 @@ impl CCMAnalog {
             _ => {}
         }
     }
+    /// Configures the DRAM PLL to `freq` (in MHz, see `DRAM_PLL_RATES`) i.e. the DDR PHY's DFI
+    /// clock. Returns false if `freq` isn't supported, the PLL is left untouched in that case.
+    pub fn dram_pll_configure(&self, freq: u32) -> bool {
+        let (main, pre, post) = match DRAM_PLL_RATES.iter().find(|rate| rate.0 == freq) {
+            Some(&(_, main, pre, post)) => (main, pre, post),
+            None => return false,
+        };
+        // Bypass clock and enable reset
+        self.registers
+            .DRAM_PLL_GEN_CTRL
+            .modify(DRAM_PLL_GEN_CTRL::PLL_BYPASS::SET);
+        self.registers
+            .DRAM_PLL_GEN_CTRL
+            .modify(DRAM_PLL_GEN_CTRL::PLL_RST::CLEAR);
+        // configure
+        self.registers.DRAM_PLL_FDIV_CTRL0.write(
+            DRAM_PLL_FDIV_CTRL0::PLL_MAIN_DIV.val(main)
+                + DRAM_PLL_FDIV_CTRL0::PLL_PRE_DIV.val(pre)
+                + DRAM_PLL_FDIV_CTRL0::PLL_POST_DIV.val(post),
+        );
+        self.registers
+            .DRAM_PLL_FDIV_CTRL1
+            .write(DRAM_PLL_FDIV_CTRL1::PLL_DSM.val(0));
+        // delay
+        timer_wait_micro(100);
+        // Disable reset
+        self.registers
+            .DRAM_PLL_GEN_CTRL
+            .modify(DRAM_PLL_GEN_CTRL::PLL_RST::SET);
+        // wait for pll lock
+        while !self
+            .registers
+            .DRAM_PLL_GEN_CTRL
+            .is_set(DRAM_PLL_GEN_CTRL::PLL_LOCK)
+        {}
+        // Clear bypass clock
+        self.registers
+            .DRAM_PLL_GEN_CTRL
+            .modify(DRAM_PLL_GEN_CTRL::PLL_BYPASS::CLEAR);
+        self.registers
+            .DRAM_PLL_GEN_CTRL
+            .modify(DRAM_PLL_GEN_CTRL::PLL_CLKE::SET);
+        true
+    }
     /// TODO: implementation not complete. Still needs to be tested
     /// Configure system Plls and set clock-gates, root-clocks for GIC, DRAM, NAND, WDG etc.
     pub fn clock_init(&self) {
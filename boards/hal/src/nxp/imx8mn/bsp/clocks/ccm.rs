@@ -26,6 +26,11 @@ pub const fn clk_root_source_sel(n: u32) -> u32 {
     (((n) & 0x7) << 24)
 }
 
+/// Pre-divider for each clock slice i.e. the source clock is divided by `n`, `n` ranges from 1 to 8
+pub const fn clk_root_pre_div(n: u32) -> u32 {
+    (((n - 1) & 0x7) << 16)
+}
+
 /// Clock Root Selects
 /// The table below details the clock root slices.
 ///
@@ -35,6 +40,7 @@ pub enum ClkRootIdx {
     ArmM7ClkRoot = 1,
     NandUsdhcBusClkRoot = 18,
     NocClkRoot = 26,
+    DramSelCfg = 48,
     CoreSelCfg = 49,
     DramAltClkRoot = 64,
     DramApbClkRoot = 65,
@@ -142,6 +148,7 @@ pub fn clock_set_target_val(idx: ClkRootIdx, val: u32) {
         ClkRootIdx::ArmA53ClkRoot => CCM_TARGET_ROOT_N_SET + (0x80 * 0),
         ClkRootIdx::NandUsdhcBusClkRoot => CCM_TARGET_ROOT_N_SET + (0x80 * 18),
         ClkRootIdx::NocClkRoot => CCM_TARGET_ROOT_N_SET + (0x80 * 26),
+        ClkRootIdx::DramSelCfg => CCM_TARGET_ROOT_N_SET + (0x80 * 48),
         ClkRootIdx::CoreSelCfg => CCM_TARGET_ROOT_N_SET + (0x80 * 49),
         ClkRootIdx::DramAltClkRoot => CCM_TARGET_ROOT_N_SET + (0x80 * 64),
         ClkRootIdx::DramApbClkRoot => CCM_TARGET_ROOT_N_SET + (0x80 * 65),
@@ -0,0 +1,33 @@
+//! The DDR controller and PHY's clocks. The PHY's DFI clock is the DRAM PLL's output, at a
+//! quarter of the data rate (ex: 600MHz for DDR4-2400).
+
+use super::super::global::ANALOG;
+use super::ccm::*;
+
+/// Sets the DRAM PLL (and switches the DRAM clock to it) for a data rate of `drate` MT/s. Returns
+/// false if there's no DRAM PLL setting for it.
+///
+/// *Note: the bypass rates (i.e. 400 and 100 MT/s, which are derived from the system PLLs instead)
+/// aren't supported.*
+pub fn enable_dram_clk(drate: u32) -> bool {
+    let freq = match drate {
+        4000 => 1000,
+        3200 => 800,
+        3000 => 750,
+        2400 => 600,
+        1600 => 400,
+        1066 => 266,
+        667 => 167,
+        _ => return false,
+    };
+    if !ANALOG.dram_pll_configure(freq) {
+        return false;
+    }
+    // dram_core_clk from the DRAM PLL, dram_apb_clk from SYSTEM_PLL1_800M / 5
+    clock_set_target_val(ClkRootIdx::DramSelCfg, clk_root_source_sel(0));
+    clock_set_target_val(
+        ClkRootIdx::DramApbClkRoot,
+        CLK_ROOT_ON | clk_root_source_sel(4) | clk_root_pre_div(5),
+    );
+    true
+}
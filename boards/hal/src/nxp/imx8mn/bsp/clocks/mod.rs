@@ -1,4 +1,5 @@
 mod ccm;
+pub mod dramclks;
 pub mod uartclks;
 pub mod usdhcclks;
 pub mod scntrclk;
@@ -0,0 +1,259 @@
+//! DDR initialization i.e. bringing up the DDR controller (uMCTL2) and training its PHY, so that
+//! DRAM (see `memory_map::map::dram`) can be used.
+//!
+//! The DRAM's timings and the PHY's training firmware are board (and DRAM part) specific. They
+//! aren't part of rustBoot, a board passes them as a [`DramTiming`]:
+//!
+//! - the register tables are NXP's DDR tool (or an NXP BSP's `ddr4_timing.c`) output. An entry
+//!   `{ 0x3d400000, 0x81040010 },` becomes `DramCfgParam { reg: 0x3d400000, val: 0x81040010 },`
+//! - the training firmware is NXP's (see the `firmware-imx` package) e.g. `ddr4_imem_1d_201810.bin`
+//!
+//! The sequence follows the reference manual's (and U-Boot's) i.e. reset the controller, program
+//! it, train the PHY at each frequency set-point, then hand the DFI interface over to the
+//! controller and wait for it to reach its normal operating mode.
+//!
+//! *Note: the trained PHY registers aren't saved i.e. DRAM retention (across low power modes)
+//! isn't supported. LPDDR4 (and its CDD adjustment) isn't either, the EVK has DDR4.*
+
+mod phy;
+
+use core::time::Duration;
+
+use super::memory_map::map::{
+    ddr::DDRC_START,
+    dram,
+    mmio::{GPC_START, SRC_START},
+};
+use crate::info;
+use crate::nxp::imx8mn::arch::timer::{time_manager, TimeManager};
+use crate::nxp::imx8mn::bsp::clocks::dramclks::enable_dram_clk;
+
+/// The DDR controller's reset control register, in the SRC.
+const SRC_DDRC_RCR: usize = SRC_START + 0x1000;
+/// The GPC's CPU mapping and power-up request registers i.e. the DDR power domain's.
+const GPC_PGC_CPU_MAPPING: usize = GPC_START + 0xec;
+const GPC_PU_PGC_SW_PUP_REQ: usize = GPC_START + 0xf8;
+const DDR1_SW_PUP_REQ: u32 = 1 << 5;
+
+/// The controller's registers.
+const DDRC_MSTR: usize = DDRC_START;
+const DDRC_STAT: usize = DDRC_START + 0x04;
+const DDRC_MSTR2: usize = DDRC_START + 0x28;
+const DDRC_PWRCTL: usize = DDRC_START + 0x30;
+const DDRC_RFSHCTL3: usize = DDRC_START + 0x60;
+const DDRC_DFIMISC: usize = DDRC_START + 0x1b0;
+const DDRC_DFISTAT: usize = DDRC_START + 0x1bc;
+const DDRC_DBG1: usize = DDRC_START + 0x304;
+const DDRC_SWCTL: usize = DDRC_START + 0x320;
+const DDRC_SWSTAT: usize = DDRC_START + 0x324;
+const DDRC_PCTRL_0: usize = DDRC_START + 0x490;
+
+/// How long the controller may take to acknowledge a step.
+const STEP_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A register write, as in NXP's timing files. `reg` is an address for the controller's
+/// registers and a PHY register (see `phy`) for the PHY's.
+#[derive(Debug, Clone, Copy)]
+pub struct DramCfgParam {
+    pub reg: u32,
+    pub val: u32,
+}
+
+/// The training firmware a frequency set-point is trained with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FwType {
+    Fw1dImage,
+    Fw2dImage,
+}
+
+/// A frequency set-point's training message block.
+pub struct DramFspMsg {
+    /// the set-point's data rate, in MT/s.
+    pub drate: u32,
+    pub fw_type: FwType,
+    pub fsp_cfg: &'static [DramCfgParam],
+}
+
+/// NXP's DDR PHY training firmware i.e. the 1D and 2D training's instruction and data memories.
+pub struct TrainingFirmware {
+    pub imem_1d: &'static [u8],
+    pub dmem_1d: &'static [u8],
+    pub imem_2d: &'static [u8],
+    pub dmem_2d: &'static [u8],
+}
+
+/// A board's DRAM timings, see the module's docs.
+pub struct DramTiming {
+    /// the controller's (uMCTL2) configuration.
+    pub ddrc_cfg: &'static [DramCfgParam],
+    /// the PHY's configuration.
+    pub ddrphy_cfg: &'static [DramCfgParam],
+    /// the frequency set-points, the first one is the one DRAM runs at.
+    pub fsp_msg: &'static [DramFspMsg],
+    /// the PHY Init Engine's image.
+    pub ddrphy_pie: &'static [DramCfgParam],
+    pub firmware: TrainingFirmware,
+}
+
+/// DDR initialization errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DdrError {
+    /// a set-point's data rate (in MT/s) isn't supported, see `dramclks::enable_dram_clk`.
+    UnsupportedRate(u32),
+    /// a training firmware image doesn't fit the PHY's memory.
+    Firmware,
+    /// the PHY's training failed, at a set-point's data rate.
+    TrainingFailed(u32),
+    /// the controller or the PHY didn't complete a step in time.
+    Timeout(&'static str),
+    /// DRAM didn't read back what was written to it, at an address (see [`dram_check`]).
+    BadAddress(usize),
+}
+
+fn reg32_write(addr: usize, val: u32) {
+    // #Safety
+    //
+    // `addr` is in the SRC's, GPC's or DDR controller's memory-map, assuming the offsets are set
+    // as per i.MX8MN reference manual (or NXP's timing files)
+    unsafe { core::ptr::write_volatile(addr as *mut u32, val) }
+}
+
+fn reg32_read(addr: usize) -> u32 {
+    unsafe { core::ptr::read_volatile(addr as *const u32) }
+}
+
+fn reg32_modify(addr: usize, clear: u32, set: u32) {
+    reg32_write(addr, (reg32_read(addr) & !clear) | set)
+}
+
+/// Polls `done` until it returns true. Returns [`DdrError::Timeout`] (for `step`) if it doesn't
+/// within `timeout`.
+fn poll(
+    timeout: Duration,
+    step: &'static str,
+    mut done: impl FnMut() -> bool,
+) -> Result<(), DdrError> {
+    let deadline = time_manager().uptime() + timeout;
+    while !done() {
+        if time_manager().uptime() > deadline {
+            return Err(DdrError::Timeout(step));
+        }
+    }
+    Ok(())
+}
+
+/// Sets SWCTL.sw_done and waits for the controller to acknowledge it i.e. applies the
+/// quasi-dynamic register writes since it was cleared.
+fn sw_done(step: &'static str) -> Result<(), DdrError> {
+    reg32_write(DDRC_SWCTL, 0x1);
+    poll(STEP_TIMEOUT, step, || reg32_read(DDRC_SWSTAT) & 0x1 == 0x1)
+}
+
+/// Initializes the DDR controller and trains its PHY, with a board's `timing`. DRAM can be used
+/// once this returns `Ok`.
+///
+/// Requires the system counter (see `driver_manager::start_system_counter`), for its timeouts.
+pub fn ddr_init(timing: &DramTiming) -> Result<(), DdrError> {
+    let initial_drate = timing
+        .fsp_msg
+        .first()
+        .map(|fsp| fsp.drate)
+        .ok_or(DdrError::UnsupportedRate(0))?;
+
+    // assert the controller's (and PHY's) resets
+    reg32_write(SRC_DDRC_RCR, 0x8F00_001F);
+    reg32_write(SRC_DDRC_RCR, 0x8F00_000F);
+
+    // power up the DDR domain
+    reg32_write(GPC_PGC_CPU_MAPPING, 0x0000_ffff);
+    reg32_modify(GPC_PU_PGC_SW_PUP_REQ, 0, DDR1_SW_PUP_REQ);
+
+    // start at the first set-point's frequency
+    if !enable_dram_clk(initial_drate) {
+        return Err(DdrError::UnsupportedRate(initial_drate));
+    }
+
+    // de-assert presetn and program the controller
+    reg32_write(SRC_DDRC_RCR, 0x8F00_0006);
+    for param in timing.ddrc_cfg {
+        reg32_write(param.reg as usize, param.val);
+    }
+
+    // de-assert core_ddrc_rstn and aresetn_n
+    reg32_write(SRC_DDRC_RCR, 0x8F00_0004);
+    reg32_write(SRC_DDRC_RCR, 0x8F00_0000);
+
+    // disable auto-refresh, self-refresh and powerdown during training
+    reg32_write(DDRC_DBG1, 0x0);
+    reg32_write(DDRC_RFSHCTL3, 0x1);
+    reg32_write(DDRC_PWRCTL, 0xa0);
+
+    // the initial boot frequency's set-point, only if frequency ratio switching is enabled
+    let mstr = reg32_read(DDRC_MSTR);
+    let target_freq = match mstr & (1 << 29) {
+        0 => 0,
+        _ => reg32_read(DDRC_MSTR2) & 0x3,
+    };
+
+    reg32_write(DDRC_SWCTL, 0x0);
+    reg32_modify(DDRC_DFIMISC, 0x1f << 8, target_freq << 8);
+    // dfi_init_complete_en = 0
+    reg32_modify(DDRC_DFIMISC, 0x1, 0);
+    sw_done("ddrc sw_done")?;
+
+    phy::ddr_cfg_phy(timing)?;
+    poll(STEP_TIMEOUT, "phy calibration", || {
+        phy::phy_read(phy::CAL_BUSY) & 0x1 == 0
+    })?;
+    info!("ddr: phy trained and calibrated");
+
+    // hand the DFI interface to the controller i.e. dfi_init_start
+    reg32_write(DDRC_SWCTL, 0x0);
+    reg32_modify(DDRC_DFIMISC, 0, 1 << 5);
+    sw_done("ddrc dfi_init_start")?;
+    poll(STEP_TIMEOUT, "dfi_init_complete", || {
+        reg32_read(DDRC_DFISTAT) & 0x1 == 0x1
+    })?;
+
+    reg32_write(DDRC_SWCTL, 0x0);
+    reg32_modify(DDRC_DFIMISC, 1 << 5, 0);
+    // dfi_init_complete_en = 1 and selfref_sw = 0
+    reg32_modify(DDRC_DFIMISC, 0, 0x1);
+    reg32_modify(DDRC_PWRCTL, 1 << 5, 0);
+    sw_done("ddrc dfi_init_complete_en")?;
+
+    // wait for the normal operating mode
+    poll(STEP_TIMEOUT, "ddrc normal mode", || {
+        reg32_read(DDRC_STAT) & 0x3 == 0x1
+    })?;
+
+    // re-enable auto-refresh and self-refresh, then enable the AXI port
+    reg32_write(DDRC_RFSHCTL3, 0x0);
+    reg32_modify(DDRC_PWRCTL, 0, 0x1);
+    reg32_write(DDRC_PCTRL_0, 0x1);
+    info!("ddr: initialized at {} MT/s", initial_drate);
+    Ok(())
+}
+
+/// A quick sanity check of the first `size` bytes of DRAM (after [`ddr_init`]) i.e. writes a word
+/// at every MiB and reads them back. Returns [`DdrError::BadAddress`] for the first one that
+/// doesn't match, which usually means a timing that doesn't fit the board's DRAM.
+///
+/// *Note: this overwrites DRAM's contents.*
+pub fn dram_check(size: usize) -> Result<(), DdrError> {
+    const STRIDE: usize = 1024 * 1024;
+    let end = dram::START + size.min(dram::END_INCLUSIVE - dram::START + 1);
+    let addrs = || (dram::START..end).step_by(STRIDE);
+    for addr in addrs() {
+        // #Safety
+        //
+        // the address is in DRAM, which is initialized
+        unsafe { core::ptr::write_volatile(addr as *mut u64, !(addr as u64)) }
+    }
+    for addr in addrs() {
+        if unsafe { core::ptr::read_volatile(addr as *const u64) } != !(addr as u64) {
+            return Err(DdrError::BadAddress(addr));
+        }
+    }
+    Ok(())
+}
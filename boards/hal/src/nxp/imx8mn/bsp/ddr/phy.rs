@@ -0,0 +1,139 @@
+//! DDR PHY (Synopsys DWC DDR PHY) training.
+//!
+//! The PHY is trained by its own microcontroller, running NXP's training firmware. For each
+//! frequency set-point, the firmware is loaded to the PHY's instruction (IMEM) and data (DMEM)
+//! memories, configured with the set-point's message block and run. It reports its progress
+//! through a mailbox.
+
+use core::time::Duration;
+
+use super::super::memory_map::map::ddr::DDRPHY_START;
+use super::{poll, DdrError, DramCfgParam, DramTiming, FwType};
+use crate::info;
+use crate::nxp::imx8mn::bsp::clocks::dramclks::enable_dram_clk;
+
+/// The PHY's instruction and data memories, as PHY registers.
+const IMEM_OFFSET: u32 = 0x5_0000;
+const DMEM_OFFSET: u32 = 0x5_4000;
+const IMEM_LEN: usize = 32 * 1024;
+const DMEM_LEN: usize = 16 * 1024;
+
+/// The microcontroller's registers.
+const MICRO_CONT_MUX_SEL: u32 = 0xd_0000;
+const UCT_SHADOW_REGS: u32 = 0xd_0004;
+const DCT_WRITE_PROT: u32 = 0xd_0031;
+const UCT_WRITE_ONLY_SHADOW: u32 = 0xd_0032;
+const UCT_DAT_WRITE_ONLY_SHADOW: u32 = 0xd_0034;
+const MICRO_RESET: u32 = 0xd_0099;
+/// The calibrator's status, bit 0 is set while it's calibrating.
+pub(super) const CAL_BUSY: u32 = 0x2_0097;
+
+/// The training firmware's mailbox messages.
+const MAIL_STREAMING: u32 = 0x08;
+const MAIL_TRAINING_PASSED: u32 = 0x07;
+const MAIL_TRAINING_FAILED: u32 = 0xff;
+
+/// How long a single training run (i.e. one frequency set-point) may take.
+const TRAINING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Writes to a PHY register. The PHY's registers are 16 bits wide, at 32 bit strides.
+pub(super) fn phy_write(reg: u32, val: u32) {
+    // #Safety
+    //
+    // the address is in the PHY's memory-map, assuming `reg` is a PHY register as per the
+    // i.MX8MN reference manual (or NXP's timing files)
+    unsafe { core::ptr::write_volatile((DDRPHY_START + 4 * reg as usize) as *mut u32, val) }
+}
+
+pub(super) fn phy_read(reg: u32) -> u32 {
+    unsafe { core::ptr::read_volatile((DDRPHY_START + 4 * reg as usize) as *const u32) }
+}
+
+fn phy_cfg(cfg: &[DramCfgParam]) {
+    for param in cfg {
+        phy_write(param.reg, param.val)
+    }
+}
+
+/// Copies a firmware image to one of the PHY's memories, a 16 bit word per register.
+fn load_image(offset: u32, image: &[u8], max_len: usize) -> Result<(), DdrError> {
+    if image.len() > max_len {
+        return Err(DdrError::Firmware);
+    }
+    for (idx, word) in image.chunks(2).enumerate() {
+        let lo = word[0] as u32;
+        let hi = word.get(1).copied().unwrap_or(0) as u32;
+        phy_write(offset + idx as u32, lo | hi << 8);
+    }
+    Ok(())
+}
+
+/// Reads the next mailbox message i.e. waits for the firmware to post one and acknowledges it.
+/// A streaming message (`long`) has 32 bits, the others 16.
+fn get_mail(long: bool) -> Result<u32, DdrError> {
+    poll(TRAINING_TIMEOUT, "phy mailbox", || {
+        phy_read(UCT_SHADOW_REGS) & 0x1 == 0
+    })?;
+    let mut mail = phy_read(UCT_WRITE_ONLY_SHADOW) & 0xffff;
+    if long {
+        mail |= phy_read(UCT_DAT_WRITE_ONLY_SHADOW) << 16;
+    }
+    phy_write(DCT_WRITE_PROT, 0x0);
+    poll(TRAINING_TIMEOUT, "phy mailbox ack", || {
+        phy_read(UCT_SHADOW_REGS) & 0x1 == 1
+    })?;
+    phy_write(DCT_WRITE_PROT, 0x1);
+    Ok(mail)
+}
+
+/// Waits for the training firmware to finish. Streaming messages (i.e. the firmware's debug
+/// output) are skipped.
+fn wait_training_complete(drate: u32) -> Result<(), DdrError> {
+    loop {
+        match get_mail(false)? {
+            MAIL_STREAMING => {
+                // a string index, followed by as many arguments as its lower half says
+                let string_index = get_mail(true)?;
+                for _ in 0..(string_index & 0xffff) {
+                    get_mail(true)?;
+                }
+            }
+            MAIL_TRAINING_PASSED => return Ok(()),
+            MAIL_TRAINING_FAILED => return Err(DdrError::TrainingFailed(drate)),
+            _ => {}
+        }
+    }
+}
+
+/// Configures the PHY and trains it at each of `timing`'s frequency set-points, then loads the
+/// PHY Init Engine (PIE) image.
+pub(super) fn ddr_cfg_phy(timing: &DramTiming) -> Result<(), DdrError> {
+    phy_cfg(timing.ddrphy_cfg);
+    for fsp in timing.fsp_msg {
+        info!("ddr: training at {} MT/s, {:?}", fsp.drate, fsp.fw_type);
+        // set the PHY's input clocks to the set-point's frequency
+        if !enable_dram_clk(fsp.drate) {
+            return Err(DdrError::UnsupportedRate(fsp.drate));
+        }
+        // load the training firmware, then the set-point's message block
+        let (imem, dmem) = match fsp.fw_type {
+            FwType::Fw1dImage => (timing.firmware.imem_1d, timing.firmware.dmem_1d),
+            FwType::Fw2dImage => (timing.firmware.imem_2d, timing.firmware.dmem_2d),
+        };
+        phy_write(MICRO_CONT_MUX_SEL, 0x0);
+        load_image(IMEM_OFFSET, imem, IMEM_LEN)?;
+        load_image(DMEM_OFFSET, dmem, DMEM_LEN)?;
+        phy_cfg(fsp.fsp_cfg);
+        // run the firmware
+        phy_write(MICRO_CONT_MUX_SEL, 0x1);
+        phy_write(MICRO_RESET, 0x9);
+        phy_write(MICRO_RESET, 0x1);
+        phy_write(MICRO_RESET, 0x0);
+        let res = wait_training_complete(fsp.drate);
+        // halt the microcontroller, whether or not training passed
+        phy_write(MICRO_RESET, 0x1);
+        res?;
+    }
+    phy_cfg(timing.ddrphy_pie);
+    Ok(())
+}
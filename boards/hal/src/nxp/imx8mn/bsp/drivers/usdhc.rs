@@ -11,6 +11,7 @@ use crate::nxp::imx8mn::bsp::drivers::usdhc::INT_STATUS::DEBE;
 use crate::nxp::imx8mn::bsp::global::GPIO2;
 use crate::{info, print, warn};
 use core::fmt::Debug;
+use rustBoot::{HalError, HalErrorKind, RustbootError};
 use tock_registers::{
     interfaces::{ReadWriteable, Readable, Writeable},
     register_bitfields, register_structs,
@@ -1256,6 +1257,29 @@ pub enum SdResult {
     None,
 }
 
+/// An SD error converts into a [`RustbootError::Hal`] error. Its `source` is the card's (R1)
+/// status for [`SdResult::SdCardState`] and the result's position in [`SdResult`] otherwise.
+impl From<SdResult> for RustbootError {
+    fn from(res: SdResult) -> Self {
+        let (kind, source) = match res {
+            SdResult::SdOk | SdResult::None => return RustbootError::Unreachable,
+            SdResult::SdError => (HalErrorKind::Io, 1),
+            SdResult::SdTimeout => (HalErrorKind::Timeout, 2),
+            SdResult::SdBusy => (HalErrorKind::Busy, 3),
+            SdResult::SdNoResp => (HalErrorKind::Timeout, 4),
+            SdResult::SdErrorReset => (HalErrorKind::NoDevice, 5),
+            SdResult::SdErrorClock => (HalErrorKind::NoDevice, 6),
+            SdResult::SdErrorVoltage => (HalErrorKind::NoDevice, 7),
+            SdResult::SdErrorAppCmd => (HalErrorKind::NoDevice, 8),
+            SdResult::SdCardAbsent => (HalErrorKind::NoDevice, 9),
+            SdResult::SdReadError => (HalErrorKind::Io, 10),
+            SdResult::SdMountFail => (HalErrorKind::NoDevice, 11),
+            SdResult::SdCardState(status) => (HalErrorKind::Io, status),
+        };
+        RustbootError::Hal(HalError::new(kind, source))
+    }
+}
+
 /// Enumerate the type of SD Card
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
 pub enum SdCardType {
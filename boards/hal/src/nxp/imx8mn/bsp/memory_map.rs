@@ -12,6 +12,8 @@ pub mod map {
     pub const SYSCNT_OFFSET :   usize = 0x006C_0000;
     pub const IOMUXC_OFFSET :   usize = 0x0033_0000;
     pub const ANALOG_OFFSET :   usize = 0x0036_0000;
+    pub const SRC_OFFSET    :   usize = 0x0039_0000;
+    pub const GPC_OFFSET    :   usize = 0x003A_0000;
 
     pub mod mmio {
         use super::*;
@@ -25,7 +27,21 @@ pub mod map {
         pub const SYSCNT_START:     usize = START + SYSCNT_OFFSET;
         pub const IOMUXC_START:     usize = START + IOMUXC_OFFSET;
         pub const CCM_ANALOG:       usize = START + ANALOG_OFFSET;
+        pub const SRC_START:        usize = START + SRC_OFFSET;
+        pub const GPC_START:        usize = START + GPC_OFFSET;
         pub const END_INCLUSIVE:    usize =         0x30FF_FFFF;
         
     }
+
+    /// The DDR controller (uMCTL2) and PHY, see `ddr`.
+    pub mod ddr {
+        pub const DDRPHY_START:     usize =         0x3C00_0000;
+        pub const DDRC_START:       usize =         0x3D40_0000;
+    }
+
+    /// DRAM i.e. the EVK's 2GiB of DDR4. Only usable once it's initialized (see `ddr::ddr_init`).
+    pub mod dram {
+        pub const START:            usize =         0x4000_0000;
+        pub const END_INCLUSIVE:    usize =         0xBFFF_FFFF;
+    }
 }
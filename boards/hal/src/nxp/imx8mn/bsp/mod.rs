@@ -1,5 +1,6 @@
 pub mod clocks;
 pub mod counter;
+pub mod ddr;
 pub mod drivers;
 pub mod global;
 pub mod memory_map;
@@ -71,6 +71,14 @@ unsafe extern "C" fn current_el0_serror(_e: &mut ExceptionContext) {
 
 #[no_mangle]
 unsafe extern "C" fn current_elx_synchronous(e: &mut ExceptionContext) {
+    // an abort taken by a guarded read (ex: an uncorrectable ECC error) fails the read instead,
+    // see `crate::ecc`.
+    if let Some(ESR_EL3::EC::Value::DataAbortCurrentEL) = e.exception_class() {
+        if let Some(fixup) = crate::ecc::fixup(e.elr_el3 as usize) {
+            e.elr_el3 = fixup as u64;
+            return;
+        }
+    }
     default_exception_handler(e);
 }
 
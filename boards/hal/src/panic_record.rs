@@ -0,0 +1,48 @@
+//! Records a bootloader panic in the board's backup registers (see [`crate::read_backup_regs`])
+//! before resetting, see the `panic-record` feature. A bootloader's panic handler hands over to
+//! [`record_and_reset`] i.e.
+//!
+//! ```ignore
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     rustBoot_hal::panic_record::record_and_reset(info)
+//! }
+//! ```
+//!
+//! The record (see `rustBoot::panicrecord`) holds the panic's file hash, line and time. It
+//! survives the reset, [`last_panic`] reads it back on the next boot.
+//!
+//! # Limitations
+//!
+//! - the nrf52840 has no backup registers, so nothing is recorded on it.
+//! - with a debugger attached, the core halts (i.e. `bkpt`) rather than resetting.
+
+use rustBoot::panicrecord::PanicRecord;
+
+/// Records `info`'s location, then resets the mcu.
+pub fn record_and_reset(info: &core::panic::PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let (file, line) = match info.location() {
+        Some(location) => (location.file(), location.line()),
+        None => ("", 0),
+    };
+    let timestamp = crate::rtc_time().unwrap_or(0) as u32;
+    crate::write_backup_regs(&PanicRecord::new(file, line, timestamp).to_words());
+
+    if cortex_m::peripheral::DCB::is_debugger_attached() {
+        loop {
+            cortex_m::asm::bkpt();
+        }
+    }
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// Returns the last recorded bootloader panic, if any.
+pub fn last_panic() -> Option<PanicRecord> {
+    crate::read_backup_regs().and_then(|regs| PanicRecord::from_words(&regs))
+}
+
+/// Clears the last recorded bootloader panic.
+pub fn clear() {
+    crate::write_backup_regs(&[0; crate::BACKUP_REGS]);
+}
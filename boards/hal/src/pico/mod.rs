@@ -1,2 +1,4 @@
 #[cfg(feature = "rp2040")]
-pub mod rp2040;
\ No newline at end of file
+pub mod rp2040;
+#[cfg(feature = "rp2350")]
+pub mod rp2350;
@@ -8,7 +8,7 @@ use core::{convert::TryInto, *};
 use cortex_m::asm;
 use rp2040_hal::rom_data;
 use rp2040_hal as hal;
-use crate::FlashInterface;
+use crate::{DebugProtection, FlashError, FlashInterface};
 use rp2040_constants::*;
 
 #[rustfmt::skip]
@@ -26,6 +26,9 @@ mod rp2040_constants {
     pub const FW_BASE_ADDR              : u32   = 0x1002_0000;
     pub const VTR_TABLE_SIZE            : u32   = 0x100;
     pub const FW_RESET_VTR              : u32   = FW_BASE_ADDR + RB_HDR_SIZE + 0xc1;
+    // the watchdog's scratch registers survive a (soft) reset. `SCRATCH4..7` are reserved by the bootrom.
+    pub const WATCHDOG_SCRATCH0         : u32   = 0x4005_800C;
+    pub const WATCHDOG_SCRATCH_REGS     : usize = 4;
 }
 
 pub struct FlashWriterEraser {}
@@ -45,14 +48,14 @@ impl FlashInterface for FlashWriterEraser {
     /// 
     /// Method arguments:
     /// -   address: It holds the address of flash where data has to be written
-    /// -   data: u8 pointer holding the holding data.
-    /// -   len :  number of bytes
+    /// -   data: the bytes to be written
     ///
     /// Returns:
     /// -  NONE
     #[inline(never)]
     #[link_section = ".data.ram_func"]
-    fn hal_flash_write(&self, address: usize, data: *const u8, len: usize) {
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        let (data, len) = (data.as_ptr(), data.len());
         asm::delay(8000);   // delay before writing data to flash
         if len <= 4 { 
             // for single byte or 4byte write
@@ -119,6 +122,7 @@ impl FlashInterface for FlashWriterEraser {
                 temp_page_buf = [0xff; FLASH_PAGE_SIZE];
             }
         }
+        Ok(())
     }
 
 
@@ -135,7 +139,7 @@ impl FlashInterface for FlashWriterEraser {
     /// -  NONE
     #[inline(never)]
     #[link_section = ".data.ram_func"]
-    fn hal_flash_erase(&self, addr: usize, len: usize) {
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
         asm::delay(8000);
         let addres = (addr - FLASH_XIP_BASE_ADDR) as u32;
         let starting_page = (addres ) as u32;
@@ -152,7 +156,18 @@ impl FlashInterface for FlashWriterEraser {
                 });
             }
         }
+        Ok(())
     }
+    /// The RP2040 has no on-chip flash, so there's nothing to write-protect. The external QSPI
+    /// flash chip's block-protect bits aren't used, as application code can clear them just as easily.
+    fn hal_flash_protect(&self, _addr: usize, _len: usize) {}
+
+    /// The RP2040 has no way to lock its debug port (SWD is always available).
+    fn hal_debug_protection(&self) -> DebugProtection {
+        DebugProtection::Disabled
+    }
+    fn hal_set_debug_protection(&self, _level: DebugProtection) {}
+
     fn hal_init() {}
     fn hal_flash_lock(&self) {}
     fn hal_flash_unlock(&self) {}
@@ -161,6 +176,29 @@ impl FlashInterface for FlashWriterEraser {
 
 pub fn preboot() {}
 
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Reads the watchdog's scratch registers, see `rustBoot_hal::read_backup_regs`.
+pub fn read_backup_regs<const N: usize>() -> [u32; N] {
+    let mut regs = [0u32; N];
+    for (idx, reg) in regs.iter_mut().take(WATCHDOG_SCRATCH_REGS).enumerate() {
+        let addr = WATCHDOG_SCRATCH0 + 4 * idx as u32;
+        *reg = unsafe { core::ptr::read_volatile(addr as *const u32) };
+    }
+    regs
+}
+
+/// Writes the watchdog's scratch registers, see `rustBoot_hal::write_backup_regs`.
+pub fn write_backup_regs(regs: &[u32]) {
+    for (idx, reg) in regs.iter().take(WATCHDOG_SCRATCH_REGS).enumerate() {
+        let addr = WATCHDOG_SCRATCH0 + 4 * idx as u32;
+        unsafe { core::ptr::write_volatile(addr as *mut u32, *reg) };
+    }
+}
+
 struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
 
 impl<const MIN: u32, const MAX: u32, const VAL: u32> RefinedUsize<MIN, MAX, VAL> {
@@ -202,6 +240,8 @@ pub fn boot_from(fw_base_address: usize) -> ! {
         let reset_vector = RefinedUsize::<0, 0, FW_RESET_VTR>::single_valued_int(
             *((fw_base_address + 4) as *const u32)).0;
         (*scb).vtor.write(address);
+        #[cfg(feature = "hide-bootloader")]
+        crate::mpu::boot(stack_pointer, reset_vector);
         cortex_m::asm::bootstrap(stack_pointer as *const u32, reset_vector as *const u32);
     }
 }
\ No newline at end of file
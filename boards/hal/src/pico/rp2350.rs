@@ -0,0 +1,292 @@
+//! Flash  Read, Write and Erase opration for the `rp2350` i.e. the Raspberry Pi Pico 2, along
+//! with the OTP state its secure boot relies on.
+//!
+//! The rp2350's bootrom verifies the (first) image in flash against a boot key, whose hash is
+//! programmed into OTP, when secure boot is enabled. rustBoot is that image i.e. it's signed with
+//! `picotool seal` (see `cargo rp2350 sign rustBoot`) and, once verified by the bootrom, verifies
+//! firmware in turn. Unlike the rp2040, there's no `boot2` i.e. the bootrom sets up the QSPI flash
+//! itself, but rustBoot must carry an `IMAGE_DEF` block within its first 4K.
+
+/*
+    IMPORTANT NOTE ABOUT RP2350 FLASH SPACE ADDRESSES:
+    As on the RP2040, the rp235x-hal::rom_data flash functions want addresses (i.e. flash offsets)
+    that start at `0x0000_0000`, whereas flash is read back at `0x1000_0000` (FLASH_XIP_BASE_ADDR).
+*/
+
+use crate::{DebugProtection, FlashError, FlashInterface};
+use core::ptr::read_volatile;
+use cortex_m::asm;
+use rp2350_constants::*;
+use rp235x_hal::rom_data;
+
+#[rustfmt::skip]
+mod rp2350_constants {
+    pub const FLASH_XIP_BASE_ADDR       : usize = 0x1000_0000;
+    pub const FLASH_BLOCK_SIZE          : usize = 65536;
+    pub const FLASH_SECTOR_SIZE         : usize = 4096;
+    pub const FLASH_PAGE_SIZE           : usize = 256;
+    pub const FLASH_SECTOR_ERASE_CMD    : u8    = 0x20;
+    // SRAM0-9 i.e. the 512K striped SRAM and the two 4K scratch banks
+    pub const STACK_LOW                 : u32   = 0x2000_0000;
+    pub const STACK_UP                  : u32   = 0x2008_2000;
+    pub const RB_HDR_SIZE               : u32   = 0x100;
+    pub const FW_BASE_ADDR              : u32   = 0x1002_0000;
+    // the firmware's reset handler follows its vector table i.e. cortex-m-rt's default 16 + 32
+    // vectors, as the example firmware doesn't enable rp235x-hal's `rt` feature
+    pub const FW_RESET_VTR              : u32   = FW_BASE_ADDR + RB_HDR_SIZE + 0xc1;
+    // the watchdog's scratch registers survive a (soft) reset. `SCRATCH4..7` are reserved by the bootrom.
+    pub const WATCHDOG_SCRATCH0         : u32   = 0x400D_800C;
+    pub const WATCHDOG_SCRATCH_REGS     : usize = 4;
+    // OTP, read raw (i.e. one 24-bit row per word) as the critical flags are stored redundantly
+    // rather than with ECC
+    pub const OTP_DATA_RAW_BASE         : u32   = 0x4013_4000;
+    // `CRIT1` is stored 8 times, a flag is set if it's set in at least 3 copies
+    pub const OTP_CRIT1_ROW             : u32   = 0x40;
+    pub const OTP_CRIT1_COPIES          : u32   = 8;
+    pub const OTP_CRIT1_VOTES           : u32   = 3;
+    pub const CRIT1_SECURE_BOOT_ENABLE  : u32   = 1 << 0;
+    pub const CRIT1_SECURE_DEBUG_DISABLE: u32   = 1 << 1;
+    pub const CRIT1_DEBUG_DISABLE       : u32   = 1 << 2;
+    // `BOOT_FLAGS1` is stored 3 times, a flag is set if it's set in at least 2 copies
+    pub const OTP_BOOT_FLAGS1_ROW       : u32   = 0x4B;
+    pub const OTP_BOOT_FLAGS1_COPIES    : u32   = 3;
+    pub const OTP_BOOT_FLAGS1_VOTES     : u32   = 2;
+    pub const BOOT_FLAGS1_KEY_VALID     : u32   = 0xF;
+    pub const BOOT_FLAGS1_KEY_INVALID   : u32   = 0xF << 8;
+}
+
+pub struct FlashWriterEraser {}
+
+impl FlashWriterEraser {
+    pub fn new() -> Self {
+        FlashWriterEraser {}
+    }
+}
+
+/// Programs (up to) a page of flash at `offset` i.e. a flash offset rather than an address. XIP
+/// is unavailable while the flash is programmed, so this runs from RAM with interrupts disabled.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+fn program_page(offset: usize, page: &[u8; FLASH_PAGE_SIZE]) {
+    cortex_m::interrupt::free(|_cs| unsafe {
+        rom_data::connect_internal_flash(); // Restore all QSPI controls to their default state and connects them to the flash
+        rom_data::flash_exit_xip(); // Initiates XIP exit sequence
+        rom_data::flash_range_program(offset as u32, page.as_ptr(), page.len());
+        rom_data::flash_flush_cache(); // Get the XIP working again
+        rom_data::flash_enter_cmd_xip(); // Start XIP back up
+    });
+}
+
+impl FlashInterface for FlashWriterEraser {
+    /// This method is to write data on flash.
+    ///
+    /// RP2350 uses an external QSPI Flash chip, which is programmed a page at a time by its bootrom
+    /// functions. Every page `data` overlaps is read, patched with `data` and programmed, so
+    /// writes may start and end anywhere.
+    ///
+    /// Method arguments:
+    /// -   address: It holds the address of flash where data has to be written
+    /// -   data: the bytes to be written
+    ///
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        asm::delay(8000); // delay before writing data to flash
+        let mut written = 0;
+        while written < data.len() {
+            let addr = address + written;
+            let page_start = addr - addr % FLASH_PAGE_SIZE;
+            let byte_num = addr - page_start;
+            let len = (FLASH_PAGE_SIZE - byte_num).min(data.len() - written);
+
+            // Caching the entire page the address belongs to, as flash_range_program() wants
+            // the count in multiples of 256 bytes
+            let mut page = [0u8; FLASH_PAGE_SIZE];
+            for (idx, byte) in page.iter_mut().enumerate() {
+                *byte = unsafe { read_volatile((page_start + idx) as *const u8) };
+            }
+            page[byte_num..byte_num + len].copy_from_slice(&data[written..written + len]);
+            program_page(page_start - FLASH_XIP_BASE_ADDR, &page);
+            written += len;
+        }
+        Ok(())
+    }
+
+    /// This method is used to erase data on flash
+    ///
+    /// In RP2350 only sector erase is available. whatever be the length of bytes we pass to this function will erase
+    /// the whole sector, whichever the sector the address belong to.
+    ///
+    /// Method arguments:
+    /// -   addr: Address where data has to be erased
+    /// -   len :  number of bytes to be erased
+    ///
+    /// Returns:
+    /// -  NONE
+    #[inline(never)]
+    #[link_section = ".data.ram_func"]
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        asm::delay(8000);
+        let offset = addr - FLASH_XIP_BASE_ADDR;
+        let starting_sector = offset - offset % FLASH_SECTOR_SIZE;
+        // flash is erased a 4K sector at a time
+        for sector in (starting_sector..offset + len).step_by(FLASH_SECTOR_SIZE) {
+            cortex_m::interrupt::free(|_cs| unsafe {
+                rom_data::connect_internal_flash();
+                rom_data::flash_exit_xip(); // Initiates XIP exit sequence
+                rom_data::flash_range_erase(
+                    sector as u32,
+                    FLASH_SECTOR_SIZE,
+                    FLASH_BLOCK_SIZE as u32,
+                    FLASH_SECTOR_ERASE_CMD,
+                );
+                rom_data::flash_flush_cache(); // Get the XIP working again
+                rom_data::flash_enter_cmd_xip(); // Start XIP back up
+            });
+        }
+        Ok(())
+    }
+    /// The RP2350 has no on-chip flash, so there's nothing to write-protect. rustBoot itself is
+    /// protected by secure boot i.e. the bootrom refuses a modified rustBoot, see [`otp_state`].
+    fn hal_flash_protect(&self, _addr: usize, _len: usize) {}
+
+    /// The debug port is disabled by OTP flags, which can't be cleared i.e. it's disabled for good.
+    fn hal_debug_protection(&self) -> DebugProtection {
+        match otp_state().debug_disabled {
+            true => DebugProtection::Permanent,
+            false => DebugProtection::Disabled,
+        }
+    }
+    /// OTP is programmed along with the boot key, by `cargo rp2350 provision-otp`, rather than by
+    /// rustBoot.
+    fn hal_set_debug_protection(&self, _level: DebugProtection) {}
+
+    fn hal_init() {}
+    fn hal_flash_lock(&self) {}
+    fn hal_flash_unlock(&self) {}
+}
+
+/// The OTP flags rustBoot relies on, as programmed by `cargo rp2350 provision-otp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtpState {
+    /// the bootrom only boots images signed with a boot key i.e. `CRIT1.SECURE_BOOT_ENABLE`.
+    pub secure_boot: bool,
+    /// the boot keys that are valid (and weren't invalidated), as a bitmask of `BOOTKEY0..3`.
+    pub boot_keys: u8,
+    /// the (secure) debug port is disabled i.e. `CRIT1.DEBUG_DISABLE` or
+    /// `CRIT1.SECURE_DEBUG_DISABLE`.
+    pub debug_disabled: bool,
+}
+
+impl OtpState {
+    /// Returns true if the bootrom verifies rustBoot i.e. secure boot is enabled, with at least
+    /// one valid boot key. The debug port is checked separately, as only production parts are
+    /// locked.
+    pub fn is_provisioned(&self) -> bool {
+        self.secure_boot && self.boot_keys != 0
+    }
+}
+
+/// Reads the OTP's state.
+pub fn otp_state() -> OtpState {
+    let crit1 = otp_flags(OTP_CRIT1_ROW, OTP_CRIT1_COPIES, OTP_CRIT1_VOTES);
+    let boot_flags1 = otp_flags(
+        OTP_BOOT_FLAGS1_ROW,
+        OTP_BOOT_FLAGS1_COPIES,
+        OTP_BOOT_FLAGS1_VOTES,
+    );
+    let valid = boot_flags1 & BOOT_FLAGS1_KEY_VALID;
+    let invalid = (boot_flags1 & BOOT_FLAGS1_KEY_INVALID) >> 8;
+    OtpState {
+        secure_boot: crit1 & CRIT1_SECURE_BOOT_ENABLE != 0,
+        boot_keys: (valid & !invalid) as u8,
+        debug_disabled: crit1 & (CRIT1_DEBUG_DISABLE | CRIT1_SECURE_DEBUG_DISABLE) != 0,
+    }
+}
+
+/// Reads a redundantly stored OTP row i.e. `copies` consecutive rows from `row`. A flag is set if
+/// it's set in at least `votes` of them.
+fn otp_flags(row: u32, copies: u32, votes: u32) -> u32 {
+    let rows = (row..row + copies)
+        .map(|row| unsafe { read_volatile((OTP_DATA_RAW_BASE + 4 * row) as *const u32) });
+    let mut counts = [0u32; 24];
+    for value in rows {
+        for (bit, count) in counts.iter_mut().enumerate() {
+            *count += (value >> bit) & 1;
+        }
+    }
+    counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count >= votes)
+        .fold(0, |flags, (bit, _)| flags | (1 << bit))
+}
+
+pub fn preboot() {}
+
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Reads the watchdog's scratch registers, see `rustBoot_hal::read_backup_regs`.
+pub fn read_backup_regs<const N: usize>() -> [u32; N] {
+    let mut regs = [0u32; N];
+    for (idx, reg) in regs.iter_mut().take(WATCHDOG_SCRATCH_REGS).enumerate() {
+        let addr = WATCHDOG_SCRATCH0 + 4 * idx as u32;
+        *reg = unsafe { core::ptr::read_volatile(addr as *const u32) };
+    }
+    regs
+}
+
+/// Writes the watchdog's scratch registers, see `rustBoot_hal::write_backup_regs`.
+pub fn write_backup_regs(regs: &[u32]) {
+    for (idx, reg) in regs.iter().take(WATCHDOG_SCRATCH_REGS).enumerate() {
+        let addr = WATCHDOG_SCRATCH0 + 4 * idx as u32;
+        unsafe { core::ptr::write_volatile(addr as *mut u32, *reg) };
+    }
+}
+
+struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
+
+impl<const MIN: u32, const MAX: u32, const VAL: u32> RefinedUsize<MIN, MAX, VAL> {
+    /// This method is used to check the address bound of stack pointer
+    ///
+    /// Method arguments:
+    /// -   i : starting address of stack
+    /// Returns:
+    /// -  It returns u32 address of stack pointer
+    pub fn bounded_int(i: u32) -> Self {
+        assert!(i >= MIN && i <= MAX);
+        RefinedUsize(i)
+    }
+    /// This method is used to check the address of reset pointer
+    ///
+    /// Method arguments:
+    /// -   i : starting address of reset
+    /// Returns:
+    /// -  It returns u32 address of reset pointer
+    pub fn single_valued_int(i: u32) -> Self {
+        assert!(i == VAL);
+        RefinedUsize(i)
+    }
+}
+
+/// This method is used to boot the firmware from a particular address, with the Armv8-M
+/// hardened jump (see `rustBoot_hal::armv8m`)
+///
+/// Method arguments:
+/// -   fw_base_address  : address of the firmware
+/// Returns:
+/// -  NONE
+#[rustfmt::skip]
+pub fn boot_from(fw_base_address: usize) -> ! {
+    let address = fw_base_address as u32;
+    unsafe {
+        let stack_pointer = RefinedUsize::<STACK_LOW, STACK_UP, 0>::bounded_int(
+            *(fw_base_address as *const u32)).0;
+        let reset_vector = RefinedUsize::<0, 0, FW_RESET_VTR>::single_valued_int(
+            *((fw_base_address + 4) as *const u32)).0;
+        crate::armv8m::boot(address, stack_pointer, reset_vector)
+    }
+}
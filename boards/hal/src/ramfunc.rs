@@ -0,0 +1,87 @@
+//! Running code from RAM, while the flash it would otherwise run from is being erased or
+//! programmed.
+//!
+//! On single-bank parts (i.e. the stm32f411, stm32f446 and stm32f407), any read of the bank
+//! that's busy - an instruction fetch, a literal load or an exception's vector fetch - stalls the
+//! CPU until the operation completes. A sector erase takes seconds, so firmware that stages an
+//! update (ex: with [`crate::NonBlocking`]) stops responding and may trip its watchdog meanwhile.
+//!
+//! - **RAM functions:** a function marked `#[inline(never)]` and
+//! `#[link_section = ".data.ram_func"]` is placed in `.data` i.e. it's copied to RAM (by
+//! `cortex-m-rt`'s `.data` initialization, before `main`) along with the statics. So, no linker
+//! script changes are needed. A RAM function mustn't call into flash while the flash is busy, not
+//! even `core` helpers that don't get inlined (the boards' erase routines are written in
+//! assembly for this reason).
+//! - **Vector table:** [`relocate_vectors`] copies the vector table to RAM and points `VTOR` at
+//! the copy, so that exceptions don't fetch their vectors from flash. Handlers that must run
+//! during an erase (ex: a watchdog feed or a radio's interrupt) are installed with
+//! [`RamVectors::set_handler`], and must be RAM functions themselves. Other exceptions are taken
+//! once the operation completes.
+
+use core::ptr::addr_of_mut;
+use cortex_m::peripheral::SCB;
+
+/// The number of vectors the RAM vector table holds i.e. the 16 system exceptions and up to 112
+/// interrupts.
+pub const NUM_VECTORS: usize = 128;
+
+/// `VTOR` requires the table to be aligned to its size, rounded up to a power of two.
+#[repr(C, align(512))]
+struct VectorTable([usize; NUM_VECTORS]);
+
+static mut RAM_VECTORS: VectorTable = VectorTable([0; NUM_VECTORS]);
+
+/// The vector table in RAM, while it's active. Dropping it points `VTOR` back at the previous
+/// vector table.
+pub struct RamVectors {
+    prev: u32,
+}
+
+/// Copies the active vector table (i.e. the one `VTOR` points at) to RAM and switches to the copy.
+/// Returns `None` if the RAM vector table is already active.
+///
+/// [`NUM_VECTORS`] entries are copied. On parts with fewer interrupts, the entries past the last
+/// one are never used.
+pub fn relocate_vectors() -> Option<RamVectors> {
+    cortex_m::interrupt::free(|_| unsafe {
+        let scb = &*SCB::PTR;
+        let prev = scb.vtor.read();
+        let table = addr_of_mut!(RAM_VECTORS.0) as *mut usize;
+        if prev as usize == table as usize {
+            return None;
+        }
+        core::ptr::copy_nonoverlapping(prev as *const usize, table, NUM_VECTORS);
+        cortex_m::asm::dsb();
+        scb.vtor.write(table as u32);
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+        Some(RamVectors { prev })
+    })
+}
+
+impl RamVectors {
+    /// Points the vector of exception number `exception` (i.e. `16 + n` for interrupt `n`) at
+    /// `handler`, which should be a RAM function.
+    ///
+    /// # Panics
+    ///
+    /// If `exception` is `0` (i.e. the initial stack pointer) or isn't below [`NUM_VECTORS`].
+    pub fn set_handler(&mut self, exception: usize, handler: unsafe extern "C" fn()) {
+        assert!(exception > 0 && exception < NUM_VECTORS);
+        unsafe {
+            let vector = (addr_of_mut!(RAM_VECTORS.0) as *mut usize).add(exception);
+            core::ptr::write_volatile(vector, handler as usize);
+        }
+        cortex_m::asm::dsb();
+    }
+}
+
+impl Drop for RamVectors {
+    fn drop(&mut self) {
+        unsafe {
+            (*SCB::PTR).vtor.write(self.prev);
+        }
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+}
@@ -1,2 +1,6 @@
-#[cfg(feature = "rpi4")]
+#[cfg(any(feature = "rpi4", feature = "rpi5"))]
 pub mod rpi4;
+/// The rpi5 (BCM2712) shares the rpi4's drivers, its BSP i.e. memory map, clocks and pin
+/// configuration is selected with the `rpi5` feature.
+#[cfg(feature = "rpi5")]
+pub use rpi4 as rpi5;
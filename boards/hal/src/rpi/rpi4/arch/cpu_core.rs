@@ -18,3 +18,21 @@ pub fn wait_forever() -> ! {
         asm::wfe()
     }
 }
+
+/// Cleans the data cache (to the point of coherency) for `len` bytes, starting at `start`.
+///
+/// Anything handed over to the next stage (i.e. the kernel, its dtb and ramdisk) must be cleaned
+/// before caching is turned off. The next stage reads memory directly and would otherwise miss
+/// lines that are still dirty in the caches.
+pub fn clean_dcache_range(start: usize, len: usize) {
+    let ctr: u64;
+    unsafe { core::arch::asm!("mrs {}, ctr_el0", out(reg) ctr) };
+    // CTR_EL0.DminLine is log2 of the number of words in the smallest data cache line.
+    let line = 4usize << ((ctr >> 16) & 0xf);
+    let mut addr = start & !(line - 1);
+    while addr < start + len {
+        unsafe { core::arch::asm!("dc cvac, {}", in(reg) addr) };
+        addr += line;
+    }
+    unsafe { core::arch::asm!("dsb sy") };
+}
@@ -5,6 +5,7 @@
 use crate::warn;
 use core::time::Duration;
 use cortex_a::{asm::barrier, registers::*};
+use rustBoot::fs::{TimeSource, Timestamp};
 use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 
 //--------------------------------------------------------------------------------------------------
@@ -64,6 +65,28 @@ pub fn time_manager() -> &'static impl TimeManager {
     &TIME_MANAGER
 }
 
+/// A [`TimeSource`] for the FAT layer (i.e. file timestamps), backed by the system counter. The
+/// board has no RTC, so time starts at `epoch` (seconds since 1970) on every boot.
+pub struct SystemCounterClock {
+    epoch: u64,
+}
+
+impl SystemCounterClock {
+    /// The FAT epoch i.e. 1980-Jan-01, the earliest time a FAT timestamp can hold.
+    pub const FAT_EPOCH: u64 = 315_532_800;
+
+    /// Create a clock that starts at `epoch` (seconds since 1970) when the device is powered on.
+    pub const fn new(epoch: u64) -> Self {
+        Self { epoch }
+    }
+}
+
+impl TimeSource for SystemCounterClock {
+    fn get_timestamp(&self) -> Timestamp {
+        Timestamp::from_unix_secs(self.epoch + time_manager().uptime().as_secs())
+    }
+}
+
 //------------------------------------------------------------------------------
 // OS Interface Code
 //------------------------------------------------------------------------------
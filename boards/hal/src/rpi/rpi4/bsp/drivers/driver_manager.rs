@@ -14,17 +14,29 @@ use crate::rpi::rpi4::bsp::global::{EMMC_CONT, GPIO, PL011_UART};
 
 /// Device Driver Manager type.
 struct BSPDriverManager {
-    device_drivers: [&'static (dyn DeviceDriver + Sync); 2],
+    device_drivers: [&'static (dyn DeviceDriver + Sync); NUM_DRIVERS],
 }
 
+#[cfg(not(feature = "rpi5"))]
+const NUM_DRIVERS: usize = 2;
+// the rpi5's debug UART has dedicated pins, GPIO isn't needed.
+#[cfg(feature = "rpi5")]
+const NUM_DRIVERS: usize = 1;
+
 //--------------------------------------------------------------------------------------------------
 // Global instances
 //--------------------------------------------------------------------------------------------------
 
+#[cfg(not(feature = "rpi5"))]
 static BSP_DRIVER_MANAGER: BSPDriverManager = BSPDriverManager {
     device_drivers: [&GPIO, &PL011_UART],
 };
 
+#[cfg(feature = "rpi5")]
+static BSP_DRIVER_MANAGER: BSPDriverManager = BSPDriverManager {
+    device_drivers: [&PL011_UART],
+};
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
@@ -45,6 +57,7 @@ impl DriverManager for BSPDriverManager {
 
     fn post_device_driver_init(&self) {
         // Configure PL011Uart's output pins.
+        #[cfg(not(feature = "rpi5"))]
         GPIO.map_pl011_uart();
         // initialize EMMC controller (i.e. sd card driver).
         // Note: emmc HW is to be initialized only after we fully initialize the uart instance,
@@ -17,7 +17,10 @@ use tock_registers::{
     LocalRegisterCopy,
 };
 
-use rustBoot::fs::blockdevice::{Block, BlockCount, BlockDevice, BlockIdx};
+use rustBoot::fs::blockdevice::{
+    Block, BlockCount, BlockDevice, BlockIdx, Statistics as BlockStatistics,
+};
+use rustBoot::{HalError, HalErrorKind, RustbootError};
 
 // --------------------------------------------------------------------
 // PRIVATE INTERNAL SD HOST REGISTER STRUCTURES AS PER BCM2835 MANUAL
@@ -814,7 +817,11 @@ mod rpi4_constants {
     --------------------------------------------------------------------------*/
     pub const FREQ_SETUP  : usize = 400_000; // 400 Khz
     pub const FREQ_NORMAL : usize = 25_000_000; // 25 Mhz
+    pub const FREQ_FALLBACK : usize = 25_000_000; // 25 Mhz, the clock a card is downshifted to after repeated read failures
+    #[cfg(not(feature = "rpi5"))]
     pub const BASE_CLOCK  : usize = 50_000_000; // 50Mhz
+    #[cfg(feature = "rpi5")]
+    pub const BASE_CLOCK  : usize = 200_000_000; // 200Mhz, BCM2712's sdio1 i.e. `clk_emmc2`
 
     /*--------------------------------------------------------------------------
     						  CMD 41 BIT SELECTIONS							    
@@ -827,6 +834,12 @@ mod rpi4_constants {
     //(ACMD41_HCS|ACMD41_SDXC_POWER|ACMD41_VOLTAGE|ACMD41_S18R)
     pub const ACMD41_ARG_HC     : usize = ACMD41_HCS | ACMD41_SDXC_POWER | ACMD41_VOLTAGE;
     pub const ACMD41_ARG_SC     : usize = ACMD41_VOLTAGE; //(ACMD41_VOLTAGE|ACMD41_S18R)
+
+    /*--------------------------------------------------------------------------
+    						  READ RETRIES							    
+    --------------------------------------------------------------------------*/
+    pub const READ_RETRIES          : u32 = 3; // retries per failed read, each one re-initializes the card
+    pub const CLOCK_DOWNSHIFT_AFTER : u32 = 2; // failed retries after which the card is re-initialized at `FREQ_FALLBACK`
 }
 
 /*--------------------------------------------------------------------------
@@ -872,9 +885,34 @@ pub enum SdResult {
     EMMC_READ_ERROR,
     EMMC_MOUNT_FAIL,
     EMMC_CARD_STATE(u32),
+    EMMC_UNSUPPORTED,   // Operation not supported by the driver
     NONE,
 }
 
+/// An SD error converts into a [`RustbootError::Hal`] error. Its `source` is the card's (R1)
+/// status for [`SdResult::EMMC_CARD_STATE`] and the result's position in [`SdResult`] otherwise.
+impl From<SdResult> for RustbootError {
+    fn from(res: SdResult) -> Self {
+        let (kind, source) = match res {
+            SdResult::EMMC_OK | SdResult::NONE => return RustbootError::Unreachable,
+            SdResult::EMMC_ERROR => (HalErrorKind::Io, 1),
+            SdResult::EMMC_TIMEOUT => (HalErrorKind::Timeout, 2),
+            SdResult::EMMC_BUSY => (HalErrorKind::Busy, 3),
+            SdResult::EMMC_NO_RESP => (HalErrorKind::Timeout, 4),
+            SdResult::EMMC_ERROR_RESET => (HalErrorKind::NoDevice, 5),
+            SdResult::EMMC_ERROR_CLOCK => (HalErrorKind::NoDevice, 6),
+            SdResult::EMMC_ERROR_VOLTAGE => (HalErrorKind::NoDevice, 7),
+            SdResult::EMMC_ERROR_APP_CMD => (HalErrorKind::NoDevice, 8),
+            SdResult::EMMC_CARD_ABSENT => (HalErrorKind::NoDevice, 9),
+            SdResult::EMMC_READ_ERROR => (HalErrorKind::Io, 10),
+            SdResult::EMMC_MOUNT_FAIL => (HalErrorKind::NoDevice, 11),
+            SdResult::EMMC_CARD_STATE(status) => (HalErrorKind::Io, status),
+            SdResult::EMMC_UNSUPPORTED => (HalErrorKind::Unsupported, 12),
+        };
+        RustbootError::Hal(HalError::new(kind, source))
+    }
+}
+
 /*--------------------------------------------------------------------------
                     PUBLIC ENUMERATION OF SD CARD TYPE
 --------------------------------------------------------------------------*/
@@ -1456,6 +1494,27 @@ impl<'a> SdDescriptor<'a> {
 //--------------------------------------------------------------------------
 static mut EMMC_CARD: SdDescriptor = SdDescriptor::new();
 
+/// The clock the card is run at, once initialized. Lowered to `FREQ_FALLBACK` if reads keep failing.
+static mut EMMC_CLOCK: u32 = FREQ_NORMAL as u32;
+
+//--------------------------------------------------------------------------
+//                        EMMC READ STATISTICS
+//--------------------------------------------------------------------------
+#[derive(Clone, Copy)]
+struct EmmcStats {
+    read_retries: usize,
+    read_failures: usize,
+    reinits: usize,
+    clock_downshifts: usize,
+}
+
+static mut EMMC_STATS: EmmcStats = EmmcStats {
+    read_retries: 0,
+    read_failures: 0,
+    reinits: 0,
+    clock_downshifts: 0,
+};
+
 pub const R1_ERRORS_MASK: u32 = 0xfff9c004;
 pub const ST_APP_CMD: u32 = 0x00000020;
 pub const DTO: u32 = 14; // data timeout exponent (guesswork)
@@ -1471,6 +1530,25 @@ pub const DTO: u32 = 14; // data timeout exponent (guesswork)
 use crate::rpi::rpi4::arch::time::*;
 use core::time::Duration;
 
+/// BCM2712's sdio1 has a block of config registers, after the SDHCI ones. `SD_PIN_SEL` selects
+/// where card-detect comes from - route it to the card-detect pin, so that the controller's
+/// `CARD_INSERTED` status reflects the SD card slot.
+#[cfg(feature = "rpi5")]
+fn emmc_route_card_detect() {
+    use crate::rpi::rpi4::bsp::memory_map::map::mmio::EMMC_CFG_START;
+
+    const SDIO_CFG_SD_PIN_SEL: usize = 0x44;
+    const SD_PIN_SEL_MASK: u32 = 0x3;
+    const SD_PIN_SEL_CARD: u32 = 0x2;
+
+    let reg = (EMMC_CFG_START + SDIO_CFG_SD_PIN_SEL) as *mut u32;
+    // Safety: the address is sdio1's (remapped) config block, see `memory_map`.
+    unsafe {
+        let val = reg.read_volatile();
+        reg.write_volatile((val & !SD_PIN_SEL_MASK) | SD_PIN_SEL_CARD);
+    }
+}
+
 /// Waits for the `delay` specified number of microseconds
 fn timer_wait_micro(delay: u64) {
     time_manager().wait_for(Duration::from_micros(delay));
@@ -1495,20 +1573,7 @@ pub struct EMMCController {
 impl BlockDevice for &EMMCController {
     type Error = SdResult;
     /// Read one or more blocks, starting at the given block index.
-    fn read(
-        &self,
-        blocks: &mut [Block],
-        start_block_idx: BlockIdx,
-        reason: &str,
-    ) -> Result<(), Self::Error> {
-        match reason {
-            "read_multi" | "read" | "read_mbr" | "read_bpb" | "read_info_sector" | "read_fat"
-            | "next_cluster" | "read_dir" | "fat_read" => {}
-            _ => {
-                info!("invalid read operation");
-                return Err(SdResult::NONE);
-            }
-        }
+    fn read(&self, blocks: &mut [Block], start_block_idx: BlockIdx) -> Result<(), Self::Error> {
         let num_blocks = blocks.len();
         let len = num_blocks * Block::LEN;
         let ptr = (&mut blocks[0].contents).as_mut_ptr();
@@ -1526,20 +1591,57 @@ impl BlockDevice for &EMMCController {
             // - This is still safe as it satifies all (of from_raw_parts_mut) usage conditions.
             buff = core::slice::from_raw_parts_mut(ptr, len);
         }
-        let res =
-            &EMMC_CONT.emmc_transfer_blocks(start_block_idx.0, num_blocks as u32, &mut buff, false);
+        // marginal cards (or a card that was re-seated) can fail a transfer with a CRC error or a
+        // timeout. Re-initialize the card and retry, a bounded number of times.
+        let mut res =
+            EMMC_CONT.emmc_transfer_blocks(start_block_idx.0, num_blocks as u32, &mut buff, false);
+        let mut retries = 0;
+        while res != SdResult::EMMC_OK && retries < READ_RETRIES {
+            retries += 1;
+            unsafe { EMMC_STATS.read_retries += 1 };
+            warn!(
+                "EMMC: read of {} block(s) at {} failed: {:?}, retrying ({}/{})",
+                num_blocks, start_block_idx.0, res, retries, READ_RETRIES
+            );
+            res = match EMMC_CONT.emmc_reinit_card(retries) {
+                SdResult::EMMC_OK => EMMC_CONT.emmc_transfer_blocks(
+                    start_block_idx.0,
+                    num_blocks as u32,
+                    &mut buff,
+                    false,
+                ),
+                err => err,
+            };
+        }
         match res {
             SdResult::EMMC_OK => Ok(()),
-            _ => Err(*res),
+            _ => {
+                unsafe { EMMC_STATS.read_failures += 1 };
+                Err(res)
+            }
         }
     }
     /// Write one or more blocks, starting at the given block index.
-    fn write(&self, _blocks: &[Block], _start_block_idx: BlockIdx) -> Result<(), Self::Error> {
-        unimplemented!()
+    ///
+    /// Blocks are written one at a time (i.e. a write is never retried as a whole), rustBoot only
+    /// writes small state files.
+    fn write(&self, blocks: &[Block], start_block_idx: BlockIdx) -> Result<(), Self::Error> {
+        let mut buff = [0u8; Block::LEN];
+        for (idx, block) in blocks.iter().enumerate() {
+            buff.copy_from_slice(&block.contents);
+            match EMMC_CONT.emmc_transfer_blocks(start_block_idx.0 + idx as u32, 1, &mut buff, true)
+            {
+                SdResult::EMMC_OK => {}
+                res => return Err(res),
+            }
+        }
+        Ok(())
     }
     /// Determine how many blocks this device can hold.
+    ///
+    /// **note:** not supported (yet).
     fn num_blocks(&self) -> Result<BlockCount, Self::Error> {
-        unimplemented!()
+        Err(SdResult::EMMC_UNSUPPORTED)
     }
 }
 
@@ -2264,6 +2366,9 @@ impl EMMCController {
         let mut td = 0; // Zero time difference
         let mut start_time = 0; // Zero start time
 
+        #[cfg(feature = "rpi5")]
+        emmc_route_card_detect();
+
         self.registers.EMMC_CONTROL1.write(CONTROL1::SRST_HC.val(1)); // Reset the complete host circuit
         timer_wait_micro(10); // Wait 10 microseconds
 
@@ -2738,8 +2843,8 @@ impl EMMCController {
             return self.emmc_debug_response(resp);
         }
 
-        // At this point, set the clock to full speed
-        resp = self.emmc_set_clock2(FREQ_NORMAL as u32);
+        // At this point, set the clock to full speed (or `FREQ_FALLBACK`, if reads kept failing)
+        resp = self.emmc_set_clock2(unsafe { EMMC_CLOCK });
         if (resp != SdResult::EMMC_OK) {
             return self.emmc_debug_response(resp);
         }
@@ -2824,6 +2929,49 @@ impl EMMCController {
 
         return SdResult::EMMC_OK;
     }
+
+    /// Re-initializes the card to recover from a failed read, `attempt` being the retry it's
+    /// called for. Once `CLOCK_DOWNSHIFT_AFTER` retries have failed, the card is brought up at
+    /// `FREQ_FALLBACK` instead.
+    ///
+    /// **note:** the card is run in default-speed mode (there's no voltage switch), so there's no
+    /// tuning to redo - a full re-initialization is as close to re-tuning as it gets.
+    ///
+    /// RETURN:
+    /// - EMMC_OK indicates the card was successfully re-initialized.
+    /// - !EMMC_OK if card initialize failed with code identifying error.
+    pub fn emmc_reinit_card(&self, attempt: u32) -> SdResult {
+        unsafe {
+            if attempt > CLOCK_DOWNSHIFT_AFTER && EMMC_CLOCK > FREQ_FALLBACK as u32 {
+                warn!(
+                    "EMMC: repeated read failures, downshifting clock from {}Hz to {}Hz",
+                    EMMC_CLOCK, FREQ_FALLBACK
+                );
+                EMMC_CLOCK = FREQ_FALLBACK as u32;
+                EMMC_STATS.clock_downshifts += 1;
+            }
+            EMMC_STATS.reinits += 1;
+        }
+        self.emmc_init_card()
+    }
+}
+
+impl BlockStatistics for EMMCController {
+    fn read_retries(&self) -> usize {
+        unsafe { EMMC_STATS.read_retries }
+    }
+
+    fn read_failures(&self) -> usize {
+        unsafe { EMMC_STATS.read_failures }
+    }
+
+    fn reinits(&self) -> usize {
+        unsafe { EMMC_STATS.reinits }
+    }
+
+    fn clock_downshifts(&self) -> usize {
+        unsafe { EMMC_STATS.clock_downshifts }
+    }
 }
 
 impl Debug for SCR::BUS_WIDTH::Value {
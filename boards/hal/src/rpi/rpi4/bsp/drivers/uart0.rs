@@ -218,6 +218,8 @@ impl PL011UartInner {
     ///
     /// - **integer divisor:** 2
     /// - **fractional divisor:** 0xB
+    ///
+    /// The rpi5's debug UART is clocked at 9.216MHz i.e. the divisor is exactly 5.
     pub fn init(&mut self) {
         // Execution can arrive here while there are still characters queued in the TX FIFO and
         // actively being sent out by the UART hardware. If the UART is turned off in this case,
@@ -242,8 +244,16 @@ impl PL011UartInner {
         // contents of IBRD or FBRD, a LCR_H write must always be performed at the end.
         //
         // Set the baud rate, 8N1 and FIFO enabled.
-        self.registers.IBRD.write(IBRD::BAUD_DIVINT.val(2));
-        self.registers.FBRD.write(FBRD::BAUD_DIVFRAC.val(0xB));
+        #[cfg(not(feature = "rpi5"))]
+        {
+            self.registers.IBRD.write(IBRD::BAUD_DIVINT.val(2));
+            self.registers.FBRD.write(FBRD::BAUD_DIVFRAC.val(0xB));
+        }
+        #[cfg(feature = "rpi5")]
+        {
+            self.registers.IBRD.write(IBRD::BAUD_DIVINT.val(5));
+            self.registers.FBRD.write(FBRD::BAUD_DIVFRAC.val(0));
+        }
         self.registers
             .LCR_H
             .write(LCR_H::WLEN::EightBit + LCR_H::FEN::FifosEnabled);
@@ -24,7 +24,12 @@ pub static EMMC_CONT: EMMCController =
 
 /// Board identification.
 pub fn board_name() -> &'static str {
+    #[cfg(not(feature = "rpi5"))]
     {
         "Raspberry Pi 4"
     }
+    #[cfg(feature = "rpi5")]
+    {
+        "Raspberry Pi 5"
+    }
 }
@@ -9,9 +9,13 @@
 //--------------------------------------------------------------------------------------------------
 
 /// The board's physical memory map.
+///
+/// The kernel's address space spans the largest rpi4's (8GiB) LPDDR4, so that RAM above the first
+/// 4GiB is reachable. On boards with less RAM, its upper part isn't backed by RAM.
+#[cfg(not(feature = "rpi5"))]
 #[rustfmt::skip]
 pub mod map {
-    pub const END_INCLUSIVE: usize = 0xFFFF_FFFF;
+    pub const END_INCLUSIVE: usize = dram::END_INCLUSIVE;
 
     pub const GPIO_OFFSET:   usize = 0x0020_0000;
     pub const UART_OFFSET:   usize = 0x0020_1000;
@@ -27,4 +31,54 @@ pub mod map {
         pub const END_INCLUSIVE:    usize =         0xFF84_FFFF;
         
     }
+
+    /// RAM, in two parts: below the low peripherals' window (`0xFC00_0000 - 0xFFFF_FFFF`) and
+    /// above 4GiB, on 8GiB boards.
+    pub mod dram {
+        pub const START:             usize =         0x0000_0000;
+        pub const LOW_END_INCLUSIVE: usize =         0xFBFF_FFFF;
+        pub const HIGH_START:        usize =       0x1_0000_0000;
+        pub const END_INCLUSIVE:     usize =       0x1_FFFF_FFFF;
+    }
+}
+
+/// The rpi5's memory map.
+///
+/// BCM2712's peripherals live above 4GiB (i.e. at `0x10_xxxx_xxxx`), outside the kernel's 4GiB
+/// address space. The few peripherals rustBoot uses are remapped to the top of the address space
+/// (see `memory::vmm`), so `mmio` holds their *virtual* addresses and `phys` holds the physical
+/// ones. The debug UART and the SD card's controller have dedicated pins, there's no pin-muxing
+/// involved.
+#[cfg(feature = "rpi5")]
+#[rustfmt::skip]
+pub mod map {
+    pub const END_INCLUSIVE: usize = 0xFFFF_FFFF;
+
+    /// Physical addresses of the 64KiB pages holding the remapped peripherals.
+    pub mod phys {
+        /// Debug UART (`uart10`), a PL011.
+        pub const UART_PAGE:    usize = 0x10_7D00_0000;
+        /// SD card's controller (`sdio1`), an SDHCI-compliant controller.
+        pub const EMMC_PAGE:    usize = 0x10_00FF_0000;
+    }
+
+    pub const UART_OFFSET:      usize = 0x0000_1000;
+    pub const EMMC_OFFSET:      usize = 0x0001_F000;
+    /// sdio1's config registers i.e. card-detect routing, follow its SDHCI registers.
+    pub const EMMC_CFG_OFFSET:  usize = 0x0001_F400;
+    /// BCM2712's GPIO block isn't remapped, the 40-pin header is on the RP1 (behind PCIe).
+    pub const GPIO_OFFSET:      usize = 0x0002_0000;
+
+    pub mod mmio {
+        use super::*;
+
+        pub const START:            usize =         0xFE00_0000;
+        pub const UART_PAGE:        usize = START;
+        pub const EMMC_PAGE:        usize = START + 0x0001_0000;
+        pub const GPIO_START:       usize = START + GPIO_OFFSET;
+        pub const PL011_UART_START: usize = START + UART_OFFSET;
+        pub const EMMC_START:       usize = START + EMMC_OFFSET;
+        pub const EMMC_CFG_START:   usize = START + EMMC_CFG_OFFSET;
+        pub const END_INCLUSIVE:    usize =         0xFE01_FFFF;
+    }
 }
@@ -57,7 +57,8 @@ pub trait Statistics {
 /// In case of a panic, the panic handler uses this function to take a last shot at printing
 /// something before the system is halted.
 ///
-/// We try to init panic-versions of the GPIO and the UART. The panic versions are not protected
+/// We try to init panic-versions of the GPIO (not on the rpi5, its debug UART has dedicated pins)
+/// and the UART. The panic versions are not protected
 /// with synchronization primitives, which increases chances that we get to print something, even
 /// when the kernel's default GPIO or UART instances happen to be locked at the time of the panic.
 ///
@@ -65,10 +66,10 @@ pub trait Statistics {
 ///
 /// - Use only for printing during a panic.
 pub unsafe fn panic_console_out() -> impl fmt::Write {
-    let mut panic_gpio = PanicGPIO::new(memory_map::map::mmio::GPIO_START);
     let mut panic_uart = PanicUart::new(memory_map::map::mmio::PL011_UART_START);
 
-    panic_gpio.map_pl011_uart();
+    #[cfg(not(feature = "rpi5"))]
+    PanicGPIO::new(memory_map::map::mmio::GPIO_START).map_pl011_uart();
     panic_uart.init();
     panic_uart
 }
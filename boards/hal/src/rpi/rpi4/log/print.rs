@@ -3,17 +3,74 @@
 // Copyright (c) 2018-2021 Andre Richter <andre.o.richter@gmail.com>
 
 //! Printing.
+//!
+//! Output is filtered by the console's [`Verbosity`] i.e. [`warn!`](crate::warn) prints from
+//! `Error` up, [`info!`](crate::info) and [`println!`](crate::println) from `Normal` up and
+//! [`debug!`](crate::debug) only at `Debug`. A build's verbosity is capped by the `console-*`
+//! features (`Normal`, without any), see [`MAX_VERBOSITY`]. A board may lower it at runtime,
+//! ex: from its config file.
 
 use super::{console, console::Write};
 use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+pub use rustBoot::cfgparser::Verbosity;
+
+/// The most verbose a build's console gets i.e. the `console-quiet`, `console-error` or
+/// `console-debug` feature (the quietest one, if several are enabled) or `Normal`.
+pub const MAX_VERBOSITY: Verbosity = if cfg!(feature = "console-quiet") {
+    Verbosity::Quiet
+} else if cfg!(feature = "console-error") {
+    Verbosity::Error
+} else if cfg!(feature = "console-debug") {
+    Verbosity::Debug
+} else {
+    Verbosity::Normal
+};
+
+/// The console's verbosity, `Normal` (or less, see [`MAX_VERBOSITY`]) until a board sets it.
+///
+/// Only loaded and stored, which are ordinary loads and stores on `AArch64` i.e. safe with the
+/// MMU and caching off (see `panic_wait`).
+static VERBOSITY: AtomicU8 = AtomicU8::new(match MAX_VERBOSITY {
+    Verbosity::Debug => Verbosity::Normal as u8,
+    verbosity => verbosity as u8,
+});
 
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
 
+/// Sets the console's verbosity, capped at [`MAX_VERBOSITY`].
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity.min(MAX_VERBOSITY) as u8, Ordering::Relaxed);
+}
+
+/// Returns the console's verbosity.
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Error,
+        2 => Verbosity::Normal,
+        _ => Verbosity::Debug,
+    }
+}
+
+/// Returns true if output at `level` is printed.
+pub fn enabled(level: Verbosity) -> bool {
+    level != Verbosity::Quiet && level <= verbosity()
+}
+
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
-    console::console().write_fmt(args).unwrap();
+    _print_at(Verbosity::Normal, args)
+}
+
+#[doc(hidden)]
+pub fn _print_at(level: Verbosity, args: fmt::Arguments) {
+    if enabled(level) {
+        console::console().write_fmt(args).unwrap();
+    }
 }
 
 /// Prints without a newline.
@@ -45,12 +102,15 @@ macro_rules! info {
         let timestamp = time_manager().uptime();
         let timestamp_subsec_us = timestamp.subsec_micros();
 
-        $crate::rpi::rpi4::log::print::_print(format_args_nl!(
-            concat!("[  {:>3}.{:03}{:03}] ", $string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000
-        ));
+        $crate::rpi::rpi4::log::print::_print_at(
+            $crate::rpi::rpi4::log::print::Verbosity::Normal,
+            format_args_nl!(
+                concat!("[  {:>3}.{:03}{:03}] ", $string),
+                timestamp.as_secs(),
+                timestamp_subsec_us / 1_000,
+                timestamp_subsec_us % 1_000
+            ),
+        );
     });
     ($format_string:expr, $($arg:tt)*) => ({
         #[allow(unused_imports)]
@@ -59,13 +119,16 @@ macro_rules! info {
         let timestamp = time_manager().uptime();
         let timestamp_subsec_us = timestamp.subsec_micros();
 
-        $crate::rpi::rpi4::log::print::_print(format_args_nl!(
-            concat!("[  {:>3}.{:03}{:03}] ", $format_string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000,
-            $($arg)*
-        ));
+        $crate::rpi::rpi4::log::print::_print_at(
+            $crate::rpi::rpi4::log::print::Verbosity::Normal,
+            format_args_nl!(
+                concat!("[  {:>3}.{:03}{:03}] ", $format_string),
+                timestamp.as_secs(),
+                timestamp_subsec_us / 1_000,
+                timestamp_subsec_us % 1_000,
+                $($arg)*
+            ),
+        );
     })
 }
 
@@ -79,12 +142,55 @@ macro_rules! warn {
         let timestamp = time_manager().uptime();
         let timestamp_subsec_us = timestamp.subsec_micros();
 
-        $crate::rpi::rpi4::log::print::_print(format_args_nl!(
-            concat!("[W {:>3}.{:03}{:03}] ", $string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000
-        ));
+        $crate::rpi::rpi4::log::print::_print_at(
+            $crate::rpi::rpi4::log::print::Verbosity::Error,
+            format_args_nl!(
+                concat!("[W {:>3}.{:03}{:03}] ", $string),
+                timestamp.as_secs(),
+                timestamp_subsec_us / 1_000,
+                timestamp_subsec_us % 1_000
+            ),
+        );
+    });
+    ($format_string:expr, $($arg:tt)*) => ({
+        #[allow(unused_imports)]
+        use $crate::rpi::rpi4::arch::time::*;
+
+        let timestamp = time_manager().uptime();
+        let timestamp_subsec_us = timestamp.subsec_micros();
+
+        $crate::rpi::rpi4::log::print::_print_at(
+            $crate::rpi::rpi4::log::print::Verbosity::Error,
+            format_args_nl!(
+                concat!("[W {:>3}.{:03}{:03}] ", $format_string),
+                timestamp.as_secs(),
+                timestamp_subsec_us / 1_000,
+                timestamp_subsec_us % 1_000,
+                $($arg)*
+            ),
+        );
+    })
+}
+
+/// Prints a debug message (i.e. only at `Verbosity::Debug`), with a newline.
+#[macro_export]
+macro_rules! debug {
+    ($string:expr) => ({
+        #[allow(unused_imports)]
+        use $crate::rpi::rpi4::arch::time::*;
+
+        let timestamp = time_manager().uptime();
+        let timestamp_subsec_us = timestamp.subsec_micros();
+
+        $crate::rpi::rpi4::log::print::_print_at(
+            $crate::rpi::rpi4::log::print::Verbosity::Debug,
+            format_args_nl!(
+                concat!("[D {:>3}.{:03}{:03}] ", $string),
+                timestamp.as_secs(),
+                timestamp_subsec_us / 1_000,
+                timestamp_subsec_us % 1_000
+            ),
+        );
     });
     ($format_string:expr, $($arg:tt)*) => ({
         #[allow(unused_imports)]
@@ -93,12 +199,15 @@ macro_rules! warn {
         let timestamp = time_manager().uptime();
         let timestamp_subsec_us = timestamp.subsec_micros();
 
-        $crate::rpi::rpi4::log::print::_print(format_args_nl!(
-            concat!("[W {:>3}.{:03}{:03}] ", $format_string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000,
-            $($arg)*
-        ));
+        $crate::rpi::rpi4::log::print::_print_at(
+            $crate::rpi::rpi4::log::print::Verbosity::Debug,
+            format_args_nl!(
+                concat!("[D {:>3}.{:03}{:03}] ", $format_string),
+                timestamp.as_secs(),
+                timestamp_subsec_us / 1_000,
+                timestamp_subsec_us % 1_000,
+                $($arg)*
+            ),
+        );
     })
 }
@@ -19,6 +19,9 @@ use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 /// Memory Management Unit type.
 struct MemoryManagementUnit;
 
+/// `ID_AA64MMFR0_EL1.PARange` (and `TCR_EL1.IPS`) for a 42-bit physical address space.
+const PA_RANGE_42_BITS: u64 = 0b0011;
+
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
 //--------------------------------------------------------------------------------------------------
@@ -65,12 +68,18 @@ impl MemoryManagementUnit {
     }
 
     /// Configure various settings of stage 1 of the EL1 translation regime.
+    ///
+    /// The intermediate physical address size is the core's (i.e. `ID_AA64MMFR0_EL1.PARange`),
+    /// capped at 42 bits.
     fn configure_translation_control(&self) {
         let t0sz = (64 - super::vmm::KernelAddrSpace::SIZE_SHIFT) as u64;
+        let ips = ID_AA64MMFR0_EL1
+            .read(ID_AA64MMFR0_EL1::PARange)
+            .min(PA_RANGE_42_BITS);
 
         TCR_EL1.write(
             TCR_EL1::TBI0::Used
-                + TCR_EL1::IPS::Bits_40
+                + TCR_EL1::IPS.val(ips)
                 + TCR_EL1::TG0::KiB_64
                 + TCR_EL1::SH0::Inner
                 + TCR_EL1::ORGN0::WriteBack_ReadAlloc_WriteAlloc_Cacheable
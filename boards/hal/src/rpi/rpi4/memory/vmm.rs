@@ -16,38 +16,69 @@ use core::ops::RangeInclusive;
 /// The kernel's address space defined by this BSP.
 pub type KernelAddrSpace = AddressSpace<{ memory_map::map::END_INCLUSIVE + 1 }>;
 
+#[cfg(not(feature = "rpi5"))]
 const NUM_MEM_RANGES: usize = 2;
+#[cfg(feature = "rpi5")]
+const NUM_MEM_RANGES: usize = 3;
 
 /// The virtual memory layout.
 ///
 /// The layout must contain only special ranges, aka anything that is _not_ normal cacheable DRAM.
 /// It is agnostic of the paging granularity that the architecture's MMU will use.
+#[cfg(not(feature = "rpi5"))]
 pub static LAYOUT: KernelVirtualLayout<NUM_MEM_RANGES> = KernelVirtualLayout::new(
     memory_map::map::END_INCLUSIVE,
     [
-        TranslationDescriptor {
-            name: "Kernel code and RO data",
-            virtual_range: code_range_inclusive,
-            physical_range_translation: Translation::Identity,
-            attribute_fields: AttributeFields {
-                mem_attributes: MemAttributes::CacheableDRAM,
-                acc_perms: AccessPermissions::ReadOnly,
-                execute_never: false,
-            },
-        },
+        CODE_AND_RO_DATA,
         TranslationDescriptor {
             name: "Device MMIO",
             virtual_range: mmio_range_inclusive,
             physical_range_translation: Translation::Identity,
-            attribute_fields: AttributeFields {
-                mem_attributes: MemAttributes::Device,
-                acc_perms: AccessPermissions::ReadWrite,
-                execute_never: true,
-            },
+            attribute_fields: DEVICE_MMIO,
+        },
+    ],
+);
+
+/// The virtual memory layout.
+///
+/// On the rpi5, the peripherals' (64KiB) pages are remapped below 4GiB, see `memory_map`.
+#[cfg(feature = "rpi5")]
+pub static LAYOUT: KernelVirtualLayout<NUM_MEM_RANGES> = KernelVirtualLayout::new(
+    memory_map::map::END_INCLUSIVE,
+    [
+        CODE_AND_RO_DATA,
+        TranslationDescriptor {
+            name: "Device MMIO (debug UART)",
+            virtual_range: uart_range_inclusive,
+            physical_range_translation: Translation::Offset(memory_map::map::phys::UART_PAGE),
+            attribute_fields: DEVICE_MMIO,
+        },
+        TranslationDescriptor {
+            name: "Device MMIO (SD card)",
+            virtual_range: emmc_range_inclusive,
+            physical_range_translation: Translation::Offset(memory_map::map::phys::EMMC_PAGE),
+            attribute_fields: DEVICE_MMIO,
         },
     ],
 );
 
+const CODE_AND_RO_DATA: TranslationDescriptor = TranslationDescriptor {
+    name: "Kernel code and RO data",
+    virtual_range: code_range_inclusive,
+    physical_range_translation: Translation::Identity,
+    attribute_fields: AttributeFields {
+        mem_attributes: MemAttributes::CacheableDRAM,
+        acc_perms: AccessPermissions::ReadOnly,
+        execute_never: false,
+    },
+};
+
+const DEVICE_MMIO: AttributeFields = AttributeFields {
+    mem_attributes: MemAttributes::Device,
+    acc_perms: AccessPermissions::ReadWrite,
+    execute_never: true,
+};
+
 //--------------------------------------------------------------------------------------------------
 // Private Code
 //--------------------------------------------------------------------------------------------------
@@ -70,6 +101,22 @@ fn mmio_range_inclusive() -> RangeInclusive<usize> {
     )
 }
 
+#[cfg(feature = "rpi5")]
+fn uart_range_inclusive() -> RangeInclusive<usize> {
+    RangeInclusive::new(
+        memory_map::map::mmio::UART_PAGE,
+        memory_map::map::mmio::UART_PAGE + 0xFFFF,
+    )
+}
+
+#[cfg(feature = "rpi5")]
+fn emmc_range_inclusive() -> RangeInclusive<usize> {
+    RangeInclusive::new(
+        memory_map::map::mmio::EMMC_PAGE,
+        memory_map::map::mmio::EMMC_PAGE + 0xFFFF,
+    )
+}
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
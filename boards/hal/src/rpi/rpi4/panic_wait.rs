@@ -5,12 +5,16 @@
 //! A panic handler that infinitely waits.
 
 use crate::rpi::rpi4::arch::cpu_core;
-use crate::rpi::rpi4::log::console;
+use crate::rpi::rpi4::log::{console, print};
 use core::{fmt, panic::PanicInfo};
 
 fn _panic_print(args: fmt::Arguments) {
     use fmt::Write;
 
+    // a quiet console (see `print::Verbosity`) stays quiet, even on a panic
+    if !print::enabled(print::Verbosity::Error) {
+        return;
+    }
     unsafe { console::panic_console_out().write_fmt(args).unwrap() };
 }
 
@@ -0,0 +1,265 @@
+//! Microchip ATECC608 secure element driver (over I2C), written in pure-rust.
+//!
+//! The ATECC608 holds the public key that images are signed against in one of its data slots
+//! i.e. outside of MCU flash. Depending on how it's set up, it either hands the key over to
+//! rustBoot (the default) or verifies signatures itself, against the key in its slot (see
+//! [`Atecc608::with_on_chip_verify`]), in which case the key never leaves the chip.
+//!
+//! The key slot must be provisioned and locked beforehand (ex: on the production line). Boards
+//! register the driver as rustBoot's secure element, before verifying any image:
+//!
+//! ```ignore
+//! let se = cortex_m::singleton!(: Atecc608<I2C, Delay> = Atecc608::new(i2c, delay, 9)).unwrap();
+//! set_secure_element(se);
+//! ```
+
+use core::cell::RefCell;
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::i2c::{Read, Write};
+use rustBoot::crypto::secure_element::SecureElement;
+use rustBoot::{Result, RustbootError};
+
+use atecc608_constants::*;
+
+#[rustfmt::skip]
+mod atecc608_constants {
+    // the default (7-bit) I2C address
+    pub const I2C_ADDRESS     : u8 = 0x60;
+    // word addresses i.e. the first byte of every write
+    pub const WORD_COMMAND    : u8 = 0x03;
+    pub const WORD_IDLE       : u8 = 0x02;
+    // opcodes
+    pub const OP_READ         : u8 = 0x02;
+    pub const OP_NONCE        : u8 = 0x16;
+    pub const OP_VERIFY       : u8 = 0x45;
+    // `Read` zone i.e. a 32-byte read of the data zone
+    pub const ZONE_DATA       : u8 = 0x02;
+    pub const READ_32         : u8 = 0x80;
+    // `Nonce` pass-through mode i.e. the digest is loaded into TempKey as-is
+    pub const NONCE_PASSTHROUGH : u8 = 0x03;
+    // `Verify` stored mode i.e. against the public key in a slot
+    pub const VERIFY_STORED   : u8 = 0x00;
+    // status codes
+    pub const STATUS_OK       : u8 = 0x00;
+    pub const STATUS_MISCOMPARE : u8 = 0x01;
+    // the status read after a wake
+    pub const WAKE_STATUS     : [u8; 4] = [0x04, 0x11, 0x33, 0x43];
+    // tWHI i.e. the time the device takes to wake up
+    pub const WAKE_DELAY_US   : u32 = 1500;
+    // the device doesn't acknowledge reads while it's busy, its longest command (`Verify`)
+    // takes at most 60ms.
+    pub const POLL_DELAY_US   : u32 = 1000;
+    pub const POLLS           : u32 = 100;
+    // a packet's count, opcode, param1 and param2 (i.e. the bytes preceding its data) and CRC
+    pub const PACKET_HDR_LEN  : usize = 5;
+    pub const CRC_LEN         : usize = 2;
+}
+
+/// An ATECC608 on an I2C bus, holding the public key in `slot`.
+pub struct Atecc608<I2C, D> {
+    bus: RefCell<(I2C, D)>,
+    address: u8,
+    slot: u8,
+    on_chip_verify: bool,
+}
+
+impl<I2C, D, E> Atecc608<I2C, D>
+where
+    I2C: Read<Error = E> + Write<Error = E>,
+    D: DelayUs<u32>,
+{
+    /// An ATECC608 at its default I2C address, holding the public key in `slot` (i.e. a P256 key
+    /// slot, `8..=15`). `delay` times its wake-up and commands.
+    ///
+    /// *Note: the bus must run at 100kHz (or less), for the device to wake up.*
+    pub fn new(i2c: I2C, delay: D, slot: u8) -> Self {
+        Atecc608 {
+            bus: RefCell::new((i2c, delay)),
+            address: I2C_ADDRESS,
+            slot,
+            on_chip_verify: false,
+        }
+    }
+
+    /// Talks to the ATECC608 at (7-bit) `address`, rather than at the default address.
+    pub fn with_address(mut self, address: u8) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Verifies signatures on the ATECC608, against the key in its slot, rather than reading the
+    /// key and verifying them in software.
+    pub fn with_on_chip_verify(mut self) -> Self {
+        self.on_chip_verify = true;
+        self
+    }
+
+    /// Releases the I2C bus and delay.
+    pub fn free(self) -> (I2C, D) {
+        self.bus.into_inner()
+    }
+
+    /// Wakes the device up, runs `f` and then idles the device. Idling (rather than putting it
+    /// to sleep) keeps TempKey i.e. a nonce loaded by `f` is kept until the device is woken up
+    /// again.
+    fn session<T>(&self, f: impl FnOnce(&mut I2C, &mut D) -> Result<T>) -> Result<T> {
+        let mut bus = self.bus.borrow_mut();
+        let (i2c, delay) = &mut *bus;
+        // holding SDA low for at least 60us (i.e. a write to address 0 at 100kHz) wakes the
+        // device, the write itself isn't acknowledged.
+        let _ = i2c.write(0x00, &[0x00]);
+        delay.delay_us(WAKE_DELAY_US);
+        let mut status = [0u8; 4];
+        i2c.read(self.address, &mut status).map_err(se_error)?;
+        if status != WAKE_STATUS {
+            return Err(RustbootError::SecureElementError);
+        }
+        let res = f(i2c, delay);
+        let _ = i2c.write(self.address, &[WORD_IDLE]);
+        res
+    }
+
+    /// Sends a command and reads its response into `resp`, returning the response's data.
+    /// A status response (i.e. a single byte) other than `STATUS_OK` is returned as an error.
+    fn execute<'a>(
+        &self,
+        i2c: &mut I2C,
+        delay: &mut D,
+        (opcode, param1, param2): (u8, u8, u16),
+        data: &[u8],
+        resp: &'a mut [u8],
+    ) -> Result<&'a [u8]> {
+        let mut packet = [0u8; 1 + PACKET_HDR_LEN + 64 + CRC_LEN];
+        let count = PACKET_HDR_LEN + data.len() + CRC_LEN;
+        if data.len() > 64 {
+            return Err(RustbootError::BufferTooSmall);
+        }
+        packet[0] = WORD_COMMAND;
+        packet[1] = count as u8;
+        packet[2] = opcode;
+        packet[3] = param1;
+        packet[4..6].copy_from_slice(&param2.to_le_bytes());
+        packet[6..6 + data.len()].copy_from_slice(data);
+        let crc = crc16(&packet[1..count - 1]);
+        packet[count - 1..count + 1].copy_from_slice(&crc);
+        i2c.write(self.address, &packet[..count + 1])
+            .map_err(se_error)?;
+
+        let mut polls = 0;
+        loop {
+            delay.delay_us(POLL_DELAY_US);
+            match i2c.read(self.address, resp) {
+                Ok(()) => break,
+                Err(_) if polls < POLLS => polls += 1,
+                Err(e) => return Err(se_error(e)),
+            }
+        }
+        let count = resp[0] as usize;
+        if count < 1 + CRC_LEN + 1 || count > resp.len() {
+            return Err(RustbootError::SecureElementError);
+        }
+        if crc16(&resp[..count - CRC_LEN]) != resp[count - CRC_LEN..count] {
+            return Err(RustbootError::SecureElementError);
+        }
+        match &resp[1..count - CRC_LEN] {
+            [STATUS_OK] => Ok(&resp[1..2]),
+            [_] => Err(RustbootError::SecureElementError),
+            data => Ok(data),
+        }
+    }
+
+    /// Reads `buf.len()` bytes (i.e. 4 or 32) of the key slot, at `block` and (4-byte) word
+    /// `offset`.
+    fn read_slot(
+        &self,
+        i2c: &mut I2C,
+        delay: &mut D,
+        block: u16,
+        offset: u16,
+        buf: &mut [u8],
+    ) -> Result<()> {
+        let zone = match buf.len() {
+            32 => ZONE_DATA | READ_32,
+            4 => ZONE_DATA,
+            _ => return Err(RustbootError::InvalidValue),
+        };
+        let address = (self.slot as u16) << 3 | block << 8 | offset;
+        let mut resp = [0u8; 1 + 32 + CRC_LEN];
+        let len = 1 + buf.len() + CRC_LEN;
+        let data = self.execute(i2c, delay, (OP_READ, zone, address), &[], &mut resp[..len])?;
+        if data.len() != buf.len() {
+            return Err(RustbootError::SecureElementError);
+        }
+        buf.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+impl<I2C, D, E> SecureElement for Atecc608<I2C, D>
+where
+    I2C: Read<Error = E> + Write<Error = E>,
+    D: DelayUs<u32>,
+{
+    fn public_key(&self) -> Result<[u8; 64]> {
+        // a P256 key slot holds the key as `pad(4) || X || pad(4) || Y` i.e. 72 bytes, the
+        // last 8 of which are read a word at a time.
+        let mut slot = [0u8; 72];
+        self.session(|i2c, delay| {
+            let (blocks, words) = slot.split_at_mut(64);
+            self.read_slot(i2c, delay, 0, 0, &mut blocks[..32])?;
+            self.read_slot(i2c, delay, 1, 0, &mut blocks[32..])?;
+            self.read_slot(i2c, delay, 2, 0, &mut words[..4])?;
+            self.read_slot(i2c, delay, 2, 1, &mut words[4..])
+        })?;
+        let mut key = [0u8; 64];
+        key[..32].copy_from_slice(&slot[4..36]);
+        key[32..].copy_from_slice(&slot[40..72]);
+        Ok(key)
+    }
+
+    fn verifies_on_chip(&self) -> bool {
+        self.on_chip_verify
+    }
+
+    fn verify(&self, digest: &[u8; 32], signature: &[u8]) -> Result<bool> {
+        if signature.len() != 64 {
+            return Err(RustbootError::BadSignature);
+        }
+        self.session(|i2c, delay| {
+            let mut resp = [0u8; 1 + 1 + CRC_LEN];
+            let nonce = (OP_NONCE, NONCE_PASSTHROUGH, 0);
+            self.execute(i2c, delay, nonce, digest, &mut resp)?;
+            let verify = (OP_VERIFY, VERIFY_STORED, self.slot as u16);
+            let res = self
+                .execute(i2c, delay, verify, signature, &mut resp)
+                .map(|_| ());
+            match res {
+                Ok(()) => Ok(true),
+                // a miscompare i.e. a bad signature, rather than a failed command
+                Err(_) if resp[1] == STATUS_MISCOMPARE => Ok(false),
+                Err(e) => Err(e),
+            }
+        })
+    }
+}
+
+fn se_error<E>(_: E) -> RustbootError {
+    RustbootError::SecureElementError
+}
+
+/// The ATECC608's CRC-16 i.e. polynomial `0x8005`, fed LSB-first and stored little-endian.
+fn crc16(data: &[u8]) -> [u8; 2] {
+    let mut crc = 0u16;
+    for byte in data {
+        for bit in 0..8 {
+            let data_bit = (byte >> bit) & 1;
+            let crc_bit = (crc >> 15) as u8;
+            crc <<= 1;
+            if data_bit != crc_bit {
+                crc ^= 0x8005;
+            }
+        }
+    }
+    crc.to_le_bytes()
+}
@@ -0,0 +1,2 @@
+#[cfg(feature = "atecc608")]
+pub mod atecc608;
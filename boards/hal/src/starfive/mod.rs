@@ -0,0 +1,2 @@
+#[cfg(feature = "visionfive2")]
+pub mod visionfive2;
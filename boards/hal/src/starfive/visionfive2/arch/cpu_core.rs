@@ -0,0 +1,25 @@
+//! Architectural processor code.
+
+/// Pause execution on the hart.
+#[inline(always)]
+pub fn wait_forever() -> ! {
+    loop {
+        unsafe { core::arch::asm!("wfi") }
+    }
+}
+
+/// Masks supervisor interrupts i.e. clears `sstatus.SIE`. The kernel must be entered with
+/// interrupts disabled.
+#[inline(always)]
+pub fn disable_interrupts() {
+    unsafe { core::arch::asm!("csrci sstatus, 0x2") }
+}
+
+/// Makes the boot images' stores visible to instruction fetches i.e. a `fence` (for the harts the
+/// kernel starts) and a `fence.i` (for this hart).
+///
+/// The U74's caches are coherent, so nothing has to be cleaned out of them before the jump.
+#[inline(always)]
+pub fn sync_boot_images() {
+    unsafe { core::arch::asm!("fence rw, rw", "fence.i") }
+}
@@ -0,0 +1,2 @@
+pub mod cpu_core;
+pub mod time;
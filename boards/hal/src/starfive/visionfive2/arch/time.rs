@@ -0,0 +1,93 @@
+//! Architectural timer primitives i.e. the `time` CSR, which reads the CLINT's `mtime`.
+
+use core::time::Duration;
+use rustBoot::fs::{TimeSource, Timestamp};
+
+const NS_PER_S: u64 = 1_000_000_000;
+
+/// The `time` CSR's frequency i.e. the dtb's `timebase-frequency`.
+pub const TIMEBASE_FREQUENCY: u64 = 4_000_000;
+
+/// Timekeeping interfaces.
+pub trait TimeManager {
+    /// The timer's resolution.
+    fn resolution(&self) -> Duration;
+
+    /// The uptime since power-on of the device.
+    ///
+    /// This includes time consumed by firmware and bootloaders.
+    fn uptime(&self) -> Duration;
+
+    /// Get the current value of the system counter
+    fn get_sys_tick_count(&self) -> u64;
+
+    /// Wait for a given duration.
+    fn wait_for(&self, duration: Duration);
+}
+
+/// The RISC-V `time` CSR.
+struct TimeCsr;
+
+static TIME_MANAGER: TimeCsr = TimeCsr;
+
+impl TimeCsr {
+    #[inline(always)]
+    fn read_time(&self) -> u64 {
+        let time: u64;
+        unsafe { core::arch::asm!("rdtime {}", out(reg) time) };
+        time
+    }
+}
+
+/// Return a reference to the time manager.
+pub fn time_manager() -> &'static impl TimeManager {
+    &TIME_MANAGER
+}
+
+/// A [`TimeSource`] for the FAT layer (i.e. file timestamps), backed by the system counter. The
+/// board has no RTC, so time starts at `epoch` (seconds since 1970) on every boot.
+pub struct SystemCounterClock {
+    epoch: u64,
+}
+
+impl SystemCounterClock {
+    /// The FAT epoch i.e. 1980-Jan-01, the earliest time a FAT timestamp can hold.
+    pub const FAT_EPOCH: u64 = 315_532_800;
+
+    /// Create a clock that starts at `epoch` (seconds since 1970) when the device is powered on.
+    pub const fn new(epoch: u64) -> Self {
+        Self { epoch }
+    }
+}
+
+impl TimeSource for SystemCounterClock {
+    fn get_timestamp(&self) -> Timestamp {
+        Timestamp::from_unix_secs(self.epoch + time_manager().uptime().as_secs())
+    }
+}
+
+impl TimeManager for TimeCsr {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(NS_PER_S / TIMEBASE_FREQUENCY)
+    }
+
+    fn uptime(&self) -> Duration {
+        let ticks = self.read_time();
+        Duration::new(
+            ticks / TIMEBASE_FREQUENCY,
+            ((ticks % TIMEBASE_FREQUENCY) * NS_PER_S / TIMEBASE_FREQUENCY) as u32,
+        )
+    }
+
+    fn get_sys_tick_count(&self) -> u64 {
+        self.read_time()
+    }
+
+    fn wait_for(&self, duration: Duration) {
+        let ticks = (duration.as_nanos() * TIMEBASE_FREQUENCY as u128 / NS_PER_S as u128) as u64;
+        let start = self.read_time();
+        while self.read_time().wrapping_sub(start) < ticks {
+            core::hint::spin_loop()
+        }
+    }
+}
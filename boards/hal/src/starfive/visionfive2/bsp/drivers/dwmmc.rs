@@ -0,0 +1,678 @@
+//! Driver - Synopsys DesignWare Mobile Storage Host controller i.e. the JH7110's SDIO0 (eMMC) and
+//! SDIO1 (micro-SD).
+//!
+//! Reads only, in PIO mode (i.e. no IDMAC) at default speed (25MHz) with a 4-bit bus. SD (v1, SDSC
+//! and SDHC/SDXC) and MMC (i.e. eMMC) cards are supported. The controllers' clocks and pins are
+//! left set up by the SPL, which loads OpenSBI (and rustBoot) from one of them.
+//!
+//! # Resources
+//!
+//! - DesignWare Cores Mobile Storage Host Databook (i.e. Linux's `drivers/mmc/host/dw_mmc.c`)
+//! - SD Physical Layer Simplified Specification, v8.00
+
+use super::common::MMIODerefWrapper;
+use crate::starfive::visionfive2::arch::time::{time_manager, TimeManager};
+use crate::starfive::visionfive2::bsp::memory_map::SDIO_CIU_CLOCK;
+use crate::{info, warn};
+use core::cell::Cell;
+use core::time::Duration;
+use tock_registers::{
+    interfaces::{ReadWriteable, Readable, Writeable},
+    register_bitfields, register_structs,
+    registers::{ReadOnly, ReadWrite},
+};
+
+use rustBoot::fs::blockdevice::{
+    Block, BlockCount, BlockDevice, BlockIdx, Statistics as BlockStatistics,
+};
+use rustBoot::{HalError, HalErrorKind, RustbootError};
+
+register_bitfields! {
+    u32,
+
+    /// Control register.
+    CTRL [
+        CONTROLLER_RESET OFFSET(0) NUMBITS(1) [],
+        FIFO_RESET OFFSET(1) NUMBITS(1) [],
+        DMA_RESET OFFSET(2) NUMBITS(1) [],
+        /// Global interrupt enable i.e. interrupts reach the PLIC. The driver polls `RINTSTS`.
+        INT_ENABLE OFFSET(4) NUMBITS(1) [],
+        USE_IDMAC OFFSET(25) NUMBITS(1) []
+    ],
+
+    /// Clock enable register.
+    CLKENA [
+        CCLK_ENABLE OFFSET(0) NUMBITS(1) [],
+        CCLK_LOW_POWER OFFSET(16) NUMBITS(1) []
+    ],
+
+    /// Command register.
+    CMD [
+        INDEX OFFSET(0) NUMBITS(6) [],
+        RESPONSE_EXPECT OFFSET(6) NUMBITS(1) [],
+        RESPONSE_LONG OFFSET(7) NUMBITS(1) [],
+        CHECK_RESPONSE_CRC OFFSET(8) NUMBITS(1) [],
+        DATA_EXPECTED OFFSET(9) NUMBITS(1) [],
+        /// Send a `STOP_TRANSMISSION` once a multi-block read completes.
+        SEND_AUTO_STOP OFFSET(12) NUMBITS(1) [],
+        WAIT_PRVDATA_COMPLETE OFFSET(13) NUMBITS(1) [],
+        /// Send 80 clocks before the command i.e. for `GO_IDLE_STATE`.
+        SEND_INITIALIZATION OFFSET(15) NUMBITS(1) [],
+        /// Load `CLKDIV`, `CLKSRC` and `CLKENA` into the card clock domain, no command is sent.
+        UPDATE_CLOCK_REGISTERS_ONLY OFFSET(21) NUMBITS(1) [],
+        USE_HOLD_REG OFFSET(29) NUMBITS(1) [],
+        /// Set to hand the command to the controller, cleared once it's taken.
+        START_CMD OFFSET(31) NUMBITS(1) []
+    ],
+
+    /// Raw interrupt status register.
+    RINTSTS [
+        RESPONSE_ERROR OFFSET(1) NUMBITS(1) [],
+        COMMAND_DONE OFFSET(2) NUMBITS(1) [],
+        DATA_TRANSFER_OVER OFFSET(3) NUMBITS(1) [],
+        RX_DATA_REQUEST OFFSET(5) NUMBITS(1) [],
+        RESPONSE_CRC_ERROR OFFSET(6) NUMBITS(1) [],
+        DATA_CRC_ERROR OFFSET(7) NUMBITS(1) [],
+        RESPONSE_TIMEOUT OFFSET(8) NUMBITS(1) [],
+        DATA_READ_TIMEOUT OFFSET(9) NUMBITS(1) [],
+        HOST_TIMEOUT OFFSET(10) NUMBITS(1) [],
+        FIFO_UNDERRUN_OVERRUN OFFSET(11) NUMBITS(1) [],
+        HARDWARE_LOCKED_WRITE OFFSET(12) NUMBITS(1) [],
+        START_BIT_ERROR OFFSET(13) NUMBITS(1) [],
+        AUTO_COMMAND_DONE OFFSET(14) NUMBITS(1) [],
+        END_BIT_ERROR OFFSET(15) NUMBITS(1) []
+    ],
+
+    /// Status register.
+    STATUS [
+        FIFO_EMPTY OFFSET(2) NUMBITS(1) [],
+        /// The card is busy i.e. it's holding DAT0 low.
+        DATA_BUSY OFFSET(9) NUMBITS(1) [],
+        FIFO_COUNT OFFSET(17) NUMBITS(13) []
+    ],
+
+    /// FIFO threshold watermark register.
+    FIFOTH [
+        TX_WMARK OFFSET(0) NUMBITS(12) [],
+        /// Reads back as the FIFO's depth, minus one, after a reset.
+        RX_WMARK OFFSET(16) NUMBITS(12) []
+    ],
+
+    /// Hardware configuration register.
+    HCON [
+        H_DATA_WIDTH OFFSET(7) NUMBITS(3) [
+            Bits16 = 0,
+            Bits32 = 1,
+            Bits64 = 2
+        ]
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    pub RegisterBlock {
+        (0x00 => CTRL: ReadWrite<u32, CTRL::Register>),
+        (0x04 => PWREN: ReadWrite<u32>),
+        (0x08 => CLKDIV: ReadWrite<u32>),
+        (0x0c => CLKSRC: ReadWrite<u32>),
+        (0x10 => CLKENA: ReadWrite<u32, CLKENA::Register>),
+        (0x14 => TMOUT: ReadWrite<u32>),
+        (0x18 => CTYPE: ReadWrite<u32>),
+        (0x1c => BLKSIZ: ReadWrite<u32>),
+        (0x20 => BYTCNT: ReadWrite<u32>),
+        (0x24 => INTMASK: ReadWrite<u32>),
+        (0x28 => CMDARG: ReadWrite<u32>),
+        (0x2c => CMD: ReadWrite<u32, CMD::Register>),
+        (0x30 => RESP: [ReadOnly<u32>; 4]),
+        (0x40 => MINTSTS: ReadOnly<u32>),
+        (0x44 => RINTSTS: ReadWrite<u32, RINTSTS::Register>),
+        (0x48 => STATUS: ReadOnly<u32, STATUS::Register>),
+        (0x4c => FIFOTH: ReadWrite<u32, FIFOTH::Register>),
+        (0x50 => _reserved0),
+        (0x6c => VERID: ReadOnly<u32>),
+        (0x70 => HCON: ReadOnly<u32, HCON::Register>),
+        (0x74 => _reserved1),
+        (0x80 => BMOD: ReadWrite<u32>),
+        (0x84 => @END),
+    }
+}
+
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// The card clock during identification.
+const FREQ_SETUP: u32 = 400_000;
+/// The card clock once the card is selected i.e. default speed.
+const FREQ_NORMAL: u32 = 25_000_000;
+/// Retries per failed read, each one re-initializes the card.
+const READ_RETRIES: u32 = 3;
+/// How long a command (or one block of a transfer) may take.
+const CMD_TIMEOUT: Duration = Duration::from_millis(500);
+/// How long a card may take to power up i.e. to clear its OCR's busy bit.
+const POWER_UP_TIMEOUT: Duration = Duration::from_secs(1);
+/// The FIFO moved from `0x100` to `0x200` in v2.40a of the controller.
+const VERID_240A: u32 = 0x240a;
+
+/// `SEND_IF_COND`'s argument i.e. 2.7-3.6V and a check pattern.
+const IF_COND_ARG: u32 = 0x1aa;
+/// OCR bits: 2.7-3.6V.
+const OCR_VOLTAGE_WINDOW: u32 = 0x00ff_8000;
+/// OCR bit: SD high capacity (i.e. block addressing) or MMC sector mode.
+const OCR_HCS: u32 = 1 << 30;
+/// OCR bit: set once the card has powered up.
+const OCR_POWERED_UP: u32 = 1 << 31;
+/// MMC `SWITCH` argument, writes `EXT_CSD[183]` (`BUS_WIDTH`) = 1 i.e. 4 bits.
+const MMC_SWITCH_BUS_WIDTH_4: u32 = 0x03b7_0100;
+/// R1 error bits.
+const R1_ERRORS_MASK: u32 = 0xfff9_c004;
+
+/// An SD/MMC error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmcError {
+    /// The card didn't respond to a command.
+    NoResponse,
+    /// The controller didn't take a command or a transfer didn't complete in time.
+    Timeout,
+    /// A response or data error. Holds the controller's `RINTSTS` or the card's (R1) status.
+    Transfer(u32),
+    /// There's no (usable) card i.e. it didn't power up or isn't an SD/MMC card.
+    NoCard,
+    /// The operation isn't supported by the driver.
+    Unsupported,
+}
+
+/// An SD/MMC error converts into a [`RustbootError::Hal`] error. Its `source` is the controller's
+/// (or card's) status for [`MmcError::Transfer`] and the error's position in [`MmcError`]
+/// otherwise.
+impl From<MmcError> for RustbootError {
+    fn from(e: MmcError) -> Self {
+        let (kind, source) = match e {
+            MmcError::NoResponse => (HalErrorKind::Timeout, 0),
+            MmcError::Timeout => (HalErrorKind::Timeout, 1),
+            MmcError::Transfer(status) => (HalErrorKind::Io, status),
+            MmcError::NoCard => (HalErrorKind::NoDevice, 3),
+            MmcError::Unsupported => (HalErrorKind::Unsupported, 4),
+        };
+        RustbootError::Hal(HalError::new(kind, source))
+    }
+}
+
+/// The kind of card in the slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardType {
+    Unknown,
+    /// An (e)MMC card.
+    Mmc,
+    /// An SD v1.x card i.e. byte addressed.
+    SdV1,
+    /// An SD v2 card, either byte addressed (SDSC) or block addressed (SDHC/SDXC).
+    SdV2,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Card {
+    kind: CardType,
+    rca: u32,
+    /// Block (rather than byte) addressing.
+    high_capacity: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MmcStats {
+    read_retries: usize,
+    read_failures: usize,
+    reinits: usize,
+}
+
+/// The response a command expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Response {
+    None,
+    /// 48 bits, with a CRC.
+    Short,
+    /// 48 bits, without a valid CRC (i.e. R3, the OCR).
+    ShortNoCrc,
+    /// 136 bits (i.e. R2, the CID or CSD).
+    Long,
+}
+
+/// Representation of a DesignWare SD/MMC controller.
+pub struct DwMmc {
+    registers: Registers,
+    name: &'static str,
+    card: Cell<Card>,
+    stats: Cell<MmcStats>,
+}
+
+// Safety: rustBoot runs on a single hart, with interrupts disabled i.e. the cells are never
+// accessed concurrently.
+unsafe impl Sync for DwMmc {}
+
+impl BlockDevice for &DwMmc {
+    type Error = MmcError;
+
+    /// Read one or more blocks, starting at the given block index. A failed read re-initializes
+    /// the card and is retried, a bounded number of times.
+    fn read(&self, blocks: &mut [Block], start_block_idx: BlockIdx) -> Result<(), Self::Error> {
+        let mut res = self.read_blocks(blocks, start_block_idx.0);
+        let mut retries = 0;
+        while let Err(e) = res {
+            if retries == READ_RETRIES {
+                self.update_stats(|stats| stats.read_failures += 1);
+                return Err(e);
+            }
+            retries += 1;
+            self.update_stats(|stats| stats.read_retries += 1);
+            warn!(
+                "{}: read of {} block(s) at {} failed: {:?}, retrying ({}/{})",
+                self.name,
+                blocks.len(),
+                start_block_idx.0,
+                e,
+                retries,
+                READ_RETRIES
+            );
+            self.update_stats(|stats| stats.reinits += 1);
+            res = self
+                .init()
+                .and_then(|_| self.read_blocks(blocks, start_block_idx.0));
+        }
+        Ok(())
+    }
+
+    /// Write one or more blocks, starting at the given block index.
+    ///
+    /// **note:** writes aren't supported (yet).
+    fn write(&self, _blocks: &[Block], _start_block_idx: BlockIdx) -> Result<(), Self::Error> {
+        Err(MmcError::Unsupported)
+    }
+
+    /// Determine how many blocks this device can hold.
+    ///
+    /// **note:** not supported (yet).
+    fn num_blocks(&self) -> Result<BlockCount, Self::Error> {
+        Err(MmcError::Unsupported)
+    }
+}
+
+impl BlockStatistics for DwMmc {
+    fn read_retries(&self) -> usize {
+        self.stats.get().read_retries
+    }
+
+    fn read_failures(&self) -> usize {
+        self.stats.get().read_failures
+    }
+
+    fn reinits(&self) -> usize {
+        self.stats.get().reinits
+    }
+}
+
+impl DwMmc {
+    /// Create an instance.
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide a correct MMIO start address.
+    pub const unsafe fn new(mmio_start_addr: usize, name: &'static str) -> Self {
+        Self {
+            registers: Registers::new(mmio_start_addr),
+            name,
+            card: Cell::new(Card {
+                kind: CardType::Unknown,
+                rca: 0,
+                high_capacity: false,
+            }),
+            stats: Cell::new(MmcStats {
+                read_retries: 0,
+                read_failures: 0,
+                reinits: 0,
+            }),
+        }
+    }
+
+    /// The controller's name i.e. `sdio0` or `sdio1`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The initialized card's type.
+    pub fn card_type(&self) -> CardType {
+        self.card.get().kind
+    }
+
+    /// Resets the controller and initializes the card i.e. identifies it, selects it and
+    /// switches it to a 4-bit bus at default speed.
+    pub fn init(&self) -> Result<(), MmcError> {
+        self.card.set(Card {
+            kind: CardType::Unknown,
+            rca: 0,
+            high_capacity: false,
+        });
+        self.reset_controller()?;
+        self.set_clock(FREQ_SETUP)?;
+        self.registers.CTYPE.set(0);
+        self.identify_card()?;
+        self.set_clock(FREQ_NORMAL)?;
+        self.set_bus_width_4()?;
+        let card = self.card.get();
+        if !card.high_capacity {
+            // SET_BLOCKLEN
+            self.send_cmd(16, Block::LEN_U32, Response::Short)?;
+        }
+        info!(
+            "{}: {:?} card, rca: {:#x}, high capacity: {}",
+            self.name, card.kind, card.rca, card.high_capacity
+        );
+        Ok(())
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut MmcStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+
+    /// Waits (for up to `timeout`) for `cond` to hold.
+    fn wait_for(&self, timeout: Duration, cond: impl Fn() -> bool) -> Result<(), MmcError> {
+        let start = time_manager().uptime();
+        while !cond() {
+            if time_manager().uptime() - start > timeout {
+                return Err(MmcError::Timeout);
+            }
+        }
+        Ok(())
+    }
+
+    fn reset_controller(&self) -> Result<(), MmcError> {
+        let regs = &self.registers;
+        regs.CTRL
+            .write(CTRL::CONTROLLER_RESET::SET + CTRL::FIFO_RESET::SET + CTRL::DMA_RESET::SET);
+        self.wait_for(CMD_TIMEOUT, || {
+            !regs.CTRL.is_set(CTRL::CONTROLLER_RESET)
+                && !regs.CTRL.is_set(CTRL::FIFO_RESET)
+                && !regs.CTRL.is_set(CTRL::DMA_RESET)
+        })?;
+        regs.PWREN.set(1);
+        regs.INTMASK.set(0);
+        regs.RINTSTS.set(u32::MAX);
+        regs.TMOUT.set(u32::MAX);
+        regs.BMOD.set(0);
+        regs.CTRL
+            .modify(CTRL::INT_ENABLE::CLEAR + CTRL::USE_IDMAC::CLEAR);
+        // i.e. half the FIFO
+        let depth = regs.FIFOTH.read(FIFOTH::RX_WMARK) + 1;
+        regs.FIFOTH
+            .write(FIFOTH::RX_WMARK.val(depth / 2 - 1) + FIFOTH::TX_WMARK.val(depth / 2));
+        Ok(())
+    }
+
+    /// Sets the card clock to (at most) `freq`.
+    fn set_clock(&self, freq: u32) -> Result<(), MmcError> {
+        let regs = &self.registers;
+        // cclk = ciu / (2 * CLKDIV), 0 bypasses the divider.
+        let div = match freq >= SDIO_CIU_CLOCK {
+            true => 0,
+            false => (SDIO_CIU_CLOCK + 2 * freq - 1) / (2 * freq),
+        };
+        regs.CLKENA.set(0);
+        self.update_clock()?;
+        regs.CLKDIV.set(div);
+        regs.CLKSRC.set(0);
+        self.update_clock()?;
+        regs.CLKENA.write(CLKENA::CCLK_ENABLE::SET);
+        self.update_clock()
+    }
+
+    fn update_clock(&self) -> Result<(), MmcError> {
+        let regs = &self.registers;
+        regs.CMD.write(
+            CMD::START_CMD::SET
+                + CMD::UPDATE_CLOCK_REGISTERS_ONLY::SET
+                + CMD::WAIT_PRVDATA_COMPLETE::SET
+                + CMD::USE_HOLD_REG::SET,
+        );
+        self.wait_for(CMD_TIMEOUT, || !regs.CMD.is_set(CMD::START_CMD))
+    }
+
+    /// Sends a command and returns its response, `RESP0` holds a short response.
+    fn send_cmd(&self, index: u32, arg: u32, resp: Response) -> Result<[u32; 4], MmcError> {
+        self.send_cmd_with(index, arg, resp, CMD::INDEX.val(0))
+    }
+
+    fn send_cmd_with(
+        &self,
+        index: u32,
+        arg: u32,
+        resp: Response,
+        flags: tock_registers::fields::FieldValue<u32, CMD::Register>,
+    ) -> Result<[u32; 4], MmcError> {
+        let regs = &self.registers;
+        self.wait_for(CMD_TIMEOUT, || !regs.STATUS.is_set(STATUS::DATA_BUSY))?;
+        regs.RINTSTS.set(u32::MAX);
+        regs.CMDARG.set(arg);
+        let resp_flags = match resp {
+            Response::None => CMD::RESPONSE_EXPECT::CLEAR,
+            Response::Short => CMD::RESPONSE_EXPECT::SET + CMD::CHECK_RESPONSE_CRC::SET,
+            Response::ShortNoCrc => CMD::RESPONSE_EXPECT::SET,
+            Response::Long => {
+                CMD::RESPONSE_EXPECT::SET + CMD::RESPONSE_LONG::SET + CMD::CHECK_RESPONSE_CRC::SET
+            }
+        };
+        let init = match index {
+            0 => CMD::SEND_INITIALIZATION::SET,
+            _ => CMD::SEND_INITIALIZATION::CLEAR,
+        };
+        regs.CMD.write(
+            CMD::START_CMD::SET
+                + CMD::USE_HOLD_REG::SET
+                + CMD::WAIT_PRVDATA_COMPLETE::SET
+                + CMD::INDEX.val(index)
+                + resp_flags
+                + init
+                + flags,
+        );
+        self.wait_for(CMD_TIMEOUT, || !regs.CMD.is_set(CMD::START_CMD))?;
+        self.wait_for(CMD_TIMEOUT, || regs.RINTSTS.is_set(RINTSTS::COMMAND_DONE))?;
+
+        let status = regs.RINTSTS.extract();
+        regs.RINTSTS.set(u32::MAX);
+        if status.is_set(RINTSTS::RESPONSE_TIMEOUT) {
+            return Err(MmcError::NoResponse);
+        }
+        let errors = RINTSTS::RESPONSE_ERROR::SET + RINTSTS::RESPONSE_CRC_ERROR::SET;
+        if status.get() & errors.value != 0 && resp != Response::ShortNoCrc {
+            return Err(MmcError::Transfer(status.get()));
+        }
+        let mut res = [0u32; 4];
+        for (word, reg) in res.iter_mut().zip(regs.RESP.iter()) {
+            *word = reg.get();
+        }
+        match resp {
+            // R1 i.e. the card's status.
+            Response::Short if index != 3 && index != 8 && res[0] & R1_ERRORS_MASK != 0 => {
+                Err(MmcError::Transfer(res[0]))
+            }
+            _ => Ok(res),
+        }
+    }
+
+    /// Sends an application specific (i.e. SD) command.
+    fn send_app_cmd(&self, index: u32, arg: u32, resp: Response) -> Result<[u32; 4], MmcError> {
+        let rca = self.card.get().rca;
+        self.send_cmd(55, rca << 16, Response::Short)?;
+        self.send_cmd(index, arg, resp)
+    }
+
+    /// Identifies the card and moves it to the transfer state i.e. assigns it an RCA and selects
+    /// it. SD cards are tried first, then MMC.
+    fn identify_card(&self) -> Result<(), MmcError> {
+        // GO_IDLE_STATE
+        self.send_cmd(0, 0, Response::None)?;
+        // SEND_IF_COND, only v2 SD cards respond
+        let kind = match self.send_cmd(8, IF_COND_ARG, Response::Short) {
+            Ok(resp) if resp[0] & 0xfff == IF_COND_ARG => CardType::SdV2,
+            Ok(_) => return Err(MmcError::NoCard),
+            Err(MmcError::NoResponse) => CardType::SdV1,
+            Err(e) => return Err(e),
+        };
+        let hcs = match kind {
+            CardType::SdV2 => OCR_HCS,
+            _ => 0,
+        };
+        // SD_SEND_OP_COND, MMC cards don't respond to APP_CMD
+        let (kind, ocr) = match self
+            .power_up(|| self.send_app_cmd(41, hcs | OCR_VOLTAGE_WINDOW, Response::ShortNoCrc))
+        {
+            Ok(ocr) => (kind, ocr),
+            Err(MmcError::NoResponse) => {
+                self.send_cmd(0, 0, Response::None)?;
+                // SEND_OP_COND
+                let ocr = self.power_up(|| {
+                    self.send_cmd(1, OCR_HCS | OCR_VOLTAGE_WINDOW, Response::ShortNoCrc)
+                })?;
+                (CardType::Mmc, ocr)
+            }
+            Err(e) => return Err(e),
+        };
+        // ALL_SEND_CID
+        self.send_cmd(2, 0, Response::Long)?;
+        // SEND_RELATIVE_ADDR i.e. SD cards publish an RCA, MMC cards are assigned one
+        let rca = match kind {
+            CardType::Mmc => {
+                self.send_cmd(3, 1 << 16, Response::Short)?;
+                1
+            }
+            _ => self.send_cmd(3, 0, Response::Short)?[0] >> 16,
+        };
+        self.card.set(Card {
+            kind,
+            rca,
+            high_capacity: ocr & OCR_HCS != 0,
+        });
+        // SELECT_CARD
+        self.send_cmd(7, rca << 16, Response::Short)?;
+        Ok(())
+    }
+
+    /// Repeats an `SEND_OP_COND` until the card reports that it's powered up. Returns its OCR.
+    fn power_up(&self, op_cond: impl Fn() -> Result<[u32; 4], MmcError>) -> Result<u32, MmcError> {
+        let start = time_manager().uptime();
+        loop {
+            let ocr = op_cond()?[0];
+            if ocr & OCR_POWERED_UP != 0 {
+                return Ok(ocr);
+            }
+            if time_manager().uptime() - start > POWER_UP_TIMEOUT {
+                return Err(MmcError::NoCard);
+            }
+            time_manager().wait_for(Duration::from_millis(10));
+        }
+    }
+
+    fn set_bus_width_4(&self) -> Result<(), MmcError> {
+        match self.card.get().kind {
+            // SWITCH, the card signals busy while it switches
+            CardType::Mmc => self.send_cmd(6, MMC_SWITCH_BUS_WIDTH_4, Response::Short)?,
+            // SET_BUS_WIDTH
+            _ => self.send_app_cmd(6, 2, Response::Short)?,
+        };
+        self.registers.CTYPE.set(1);
+        Ok(())
+    }
+
+    /// Reads `blocks.len()` blocks, starting at block `start`, through the FIFO.
+    fn read_blocks(&self, blocks: &mut [Block], start: u32) -> Result<(), MmcError> {
+        let regs = &self.registers;
+        let card = self.card.get();
+        if card.kind == CardType::Unknown {
+            return Err(MmcError::NoCard);
+        }
+        let count = blocks.len() as u32;
+        let arg = match card.high_capacity {
+            true => start,
+            false => start * Block::LEN_U32,
+        };
+        regs.CTRL.modify(CTRL::FIFO_RESET::SET);
+        self.wait_for(CMD_TIMEOUT, || !regs.CTRL.is_set(CTRL::FIFO_RESET))?;
+        regs.BLKSIZ.set(Block::LEN_U32);
+        regs.BYTCNT.set(count * Block::LEN_U32);
+        // READ_SINGLE_BLOCK or READ_MULTIPLE_BLOCK
+        let (index, stop) = match count {
+            1 => (17, CMD::SEND_AUTO_STOP::CLEAR),
+            _ => (18, CMD::SEND_AUTO_STOP::SET),
+        };
+        self.send_cmd_with(index, arg, Response::Short, CMD::DATA_EXPECTED::SET + stop)?;
+
+        let fifo = self.fifo_addr();
+        let wide = regs.HCON.matches_all(HCON::H_DATA_WIDTH::Bits64);
+        let bytes = unsafe {
+            // Safety: a `Block` is a `[u8; 512]`.
+            core::slice::from_raw_parts_mut(
+                blocks.as_mut_ptr() as *mut u8,
+                blocks.len() * Block::LEN,
+            )
+        };
+        let errors = RINTSTS::DATA_CRC_ERROR::SET
+            + RINTSTS::DATA_READ_TIMEOUT::SET
+            + RINTSTS::HOST_TIMEOUT::SET
+            + RINTSTS::FIFO_UNDERRUN_OVERRUN::SET
+            + RINTSTS::START_BIT_ERROR::SET
+            + RINTSTS::END_BIT_ERROR::SET;
+        let mut pos = 0;
+        let mut last = time_manager().uptime();
+        loop {
+            let status = regs.RINTSTS.extract();
+            if status.get() & errors.value != 0 {
+                regs.RINTSTS.set(u32::MAX);
+                return Err(MmcError::Transfer(status.get()));
+            }
+            let available = regs.STATUS.read(STATUS::FIFO_COUNT) as usize;
+            if available > 0 {
+                pos = self.drain_fifo(fifo, wide, available, &mut bytes[pos..]) + pos;
+                regs.RINTSTS.write(RINTSTS::RX_DATA_REQUEST::SET);
+                last = time_manager().uptime();
+            } else if status.is_set(RINTSTS::DATA_TRANSFER_OVER) {
+                break;
+            } else if time_manager().uptime() - last > CMD_TIMEOUT {
+                return Err(MmcError::Timeout);
+            }
+        }
+        regs.RINTSTS.write(RINTSTS::DATA_TRANSFER_OVER::SET);
+        if count > 1 {
+            self.wait_for(CMD_TIMEOUT, || {
+                regs.RINTSTS.is_set(RINTSTS::AUTO_COMMAND_DONE)
+            })?;
+            regs.RINTSTS.write(RINTSTS::AUTO_COMMAND_DONE::SET);
+        }
+        match pos == bytes.len() {
+            true => Ok(()),
+            false => Err(MmcError::Transfer(regs.RINTSTS.get())),
+        }
+    }
+
+    /// Reads `words` FIFO words into `buf`. Returns the number of bytes read.
+    fn drain_fifo(&self, fifo: usize, wide: bool, words: usize, buf: &mut [u8]) -> usize {
+        let width = if wide { 8 } else { 4 };
+        let mut pos = 0;
+        for _ in 0..words {
+            let word = match wide {
+                true => unsafe { (fifo as *const u64).read_volatile() },
+                false => unsafe { (fifo as *const u32).read_volatile() as u64 },
+            };
+            let len = width.min(buf.len() - pos);
+            buf[pos..pos + len].copy_from_slice(&word.to_le_bytes()[..len]);
+            pos += len;
+        }
+        pos
+    }
+
+    /// The data FIFO's address.
+    fn fifo_addr(&self) -> usize {
+        let base = &*self.registers as *const RegisterBlock as usize;
+        match self.registers.VERID.get() & 0xffff >= VERID_240A {
+            true => base + 0x200,
+            false => base + 0x100,
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod common;
+pub mod dwmmc;
+pub mod uart0;
@@ -0,0 +1,209 @@
+//! 8250/16550 UART driver i.e. the JH7110's DesignWare APB UART.
+//!
+//! Its registers are 32 bits wide, at a 4 byte stride (the dtb's `reg-shift = <2>`).
+
+use super::common::MMIODerefWrapper;
+use crate::starfive::visionfive2::bsp::memory_map::UART_CLOCK;
+use crate::starfive::visionfive2::log::console;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use tock_registers::{
+    interfaces::{Readable, Writeable},
+    register_bitfields, register_structs,
+    registers::ReadWrite,
+};
+
+const BAUD_RATE: u32 = 115_200;
+
+register_bitfields! {
+    u32,
+
+    /// Line Control Register.
+    LCR [
+        /// Divisor latch access i.e. `RBR_THR` and `IER` address the baud rate divisor.
+        DLAB OFFSET(7) NUMBITS(1) [],
+        /// Word length.
+        WLS OFFSET(0) NUMBITS(2) [
+            EightBit = 0b11
+        ]
+    ],
+
+    /// FIFO Control Register (write-only, shares its offset with `IIR`).
+    FCR [
+        RCVR_RESET OFFSET(1) NUMBITS(1) [],
+        XMIT_RESET OFFSET(2) NUMBITS(1) [],
+        FIFO_ENABLE OFFSET(0) NUMBITS(1) []
+    ],
+
+    /// Line Status Register.
+    LSR [
+        /// Transmitter empty i.e. the FIFO and shift register are empty.
+        TEMT OFFSET(6) NUMBITS(1) [],
+        /// Transmit holding register empty.
+        THRE OFFSET(5) NUMBITS(1) [],
+        /// Data ready.
+        DR OFFSET(0) NUMBITS(1) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    pub RegisterBlock {
+        (0x00 => RBR_THR: ReadWrite<u32>),
+        (0x04 => IER: ReadWrite<u32>),
+        (0x08 => FCR: ReadWrite<u32, FCR::Register>),
+        (0x0c => LCR: ReadWrite<u32, LCR::Register>),
+        (0x10 => MCR: ReadWrite<u32>),
+        (0x14 => LSR: ReadWrite<u32, LSR::Register>),
+        (0x18 => @END),
+    }
+}
+
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// The UART, without any locking i.e. for the panic handler.
+pub struct PanicUart {
+    registers: Registers,
+}
+
+/// The console UART.
+pub struct Uart {
+    inner: PanicUart,
+    chars_written: AtomicUsize,
+    chars_read: AtomicUsize,
+}
+
+impl PanicUart {
+    /// Create an instance.
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide a correct MMIO start address.
+    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        Self {
+            registers: Registers::new(mmio_start_addr),
+        }
+    }
+
+    /// Sets up the UART for 115200 8N1, with its FIFOs enabled and interrupts off.
+    pub fn init(&self) {
+        self.flush();
+        let divisor = (UART_CLOCK + 8 * BAUD_RATE) / (16 * BAUD_RATE);
+        self.registers.IER.set(0);
+        self.registers.LCR.write(LCR::DLAB::SET);
+        self.registers.RBR_THR.set(divisor & 0xff);
+        self.registers.IER.set(divisor >> 8);
+        self.registers.LCR.write(LCR::WLS::EightBit);
+        self.registers
+            .FCR
+            .write(FCR::FIFO_ENABLE::SET + FCR::RCVR_RESET::SET + FCR::XMIT_RESET::SET);
+        self.registers.MCR.set(0);
+    }
+
+    fn put_char(&self, c: char) {
+        while !self.registers.LSR.is_set(LSR::THRE) {
+            core::hint::spin_loop()
+        }
+        self.registers.RBR_THR.set(c as u32);
+    }
+
+    fn flush(&self) {
+        while !self.registers.LSR.is_set(LSR::TEMT) {
+            core::hint::spin_loop()
+        }
+    }
+
+    fn read_char(&self) -> Option<char> {
+        match self.registers.LSR.is_set(LSR::DR) {
+            true => Some((self.registers.RBR_THR.get() as u8) as char),
+            false => None,
+        }
+    }
+}
+
+impl fmt::Write for PanicUart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c == '\n' {
+                self.put_char('\r')
+            }
+            self.put_char(c);
+        }
+        Ok(())
+    }
+}
+
+impl Uart {
+    /// Create an instance.
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide a correct MMIO start address.
+    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        Self {
+            inner: PanicUart::new(mmio_start_addr),
+            chars_written: AtomicUsize::new(0),
+            chars_read: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets up the UART, see [`PanicUart::init`].
+    pub fn init(&self) {
+        self.inner.init()
+    }
+}
+
+impl console::Write for Uart {
+    fn write_char(&self, c: char) {
+        self.inner.put_char(c);
+        self.chars_written.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn write_fmt(&self, args: fmt::Arguments) -> fmt::Result {
+        /// Counts the characters written.
+        struct Writer<'a>(&'a Uart);
+
+        impl fmt::Write for Writer<'_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                for c in s.chars() {
+                    if c == '\n' {
+                        console::Write::write_char(self.0, '\r')
+                    }
+                    console::Write::write_char(self.0, c);
+                }
+                Ok(())
+            }
+        }
+
+        fmt::Write::write_fmt(&mut Writer(self), args)
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+impl console::Read for Uart {
+    fn read_char(&self) -> char {
+        loop {
+            if let Some(c) = self.inner.read_char() {
+                self.chars_read.fetch_add(1, Ordering::Relaxed);
+                return if c == '\r' { '\n' } else { c };
+            }
+        }
+    }
+
+    fn clear_rx(&self) {
+        while self.inner.read_char().is_some() {}
+    }
+}
+
+impl console::Statistics for Uart {
+    fn chars_written(&self) -> usize {
+        self.chars_written.load(Ordering::Relaxed)
+    }
+
+    fn chars_read(&self) -> usize {
+        self.chars_read.load(Ordering::Relaxed)
+    }
+}
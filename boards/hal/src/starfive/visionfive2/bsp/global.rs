@@ -0,0 +1,15 @@
+//! BSP Processor code. Global peripherals file for the VisionFive 2.
+
+use super::drivers::{dwmmc::DwMmc, uart0::Uart};
+use super::memory_map;
+
+pub static UART: Uart = unsafe { Uart::new(memory_map::map::mmio::UART0_START) };
+/// The eMMC module's controller.
+pub static SDIO0: DwMmc = unsafe { DwMmc::new(memory_map::map::mmio::SDIO0_START, "sdio0") };
+/// The micro-SD card slot's controller.
+pub static SDIO1: DwMmc = unsafe { DwMmc::new(memory_map::map::mmio::SDIO1_START, "sdio1") };
+
+/// Board identification.
+pub fn board_name() -> &'static str {
+    "StarFive VisionFive 2"
+}
@@ -0,0 +1,31 @@
+//! BSP Memory Map.
+
+/// The board's physical memory map.
+#[rustfmt::skip]
+pub mod map {
+
+    pub const UART0_OFFSET  :   usize = 0x0000_0000;
+    pub const SDIO0_OFFSET  :   usize = 0x0601_0000;
+    pub const SDIO1_OFFSET  :   usize = 0x0602_0000;
+
+    pub mod mmio {
+        use super::*;
+
+        pub const START:            usize =         0x1000_0000;
+        /// The debug UART (i.e. the 40-pin header's pins 8 and 10).
+        pub const UART0_START:      usize = START + UART0_OFFSET;
+        /// `mmc0` i.e. the eMMC module's socket.
+        pub const SDIO0_START:      usize = START + SDIO0_OFFSET;
+        /// `mmc1` i.e. the micro-SD card slot.
+        pub const SDIO1_START:      usize = START + SDIO1_OFFSET;
+        pub const END_INCLUSIVE:    usize =         0x3FFF_FFFF;
+    }
+
+    /// DRAM starts here. OpenSBI keeps its first 512KiB (see `layout.ld`).
+    pub const DRAM_START:           usize =         0x4000_0000;
+}
+
+/// The SDIO controllers' `ciu` (card interface unit) clock, as set up by the SPL.
+pub const SDIO_CIU_CLOCK: u32 = 50_000_000;
+/// The UART's clock.
+pub const UART_CLOCK: u32 = 24_000_000;
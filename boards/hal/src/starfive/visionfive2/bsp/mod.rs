@@ -0,0 +1,3 @@
+pub mod drivers;
+pub mod global;
+pub mod memory_map;
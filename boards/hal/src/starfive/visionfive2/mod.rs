@@ -0,0 +1,14 @@
+//! The StarFive VisionFive 2 (JH7110, RV64GC) i.e. a RISC-V 64 Linux-class board.
+//!
+//! rustBoot runs as OpenSBI's payload in S-mode, with the MMU off (`satp` = 0). It's entered on
+//! the boot hart, with its hart id in `a0` and the firmware's dtb in `a1`. The other harts are
+//! held by OpenSBI (i.e. its HSM extension) until the kernel starts them.
+//!
+//! Memory attributes are fixed by the JH7110's PMAs i.e. DRAM is cached and the peripherals
+//! aren't, so no page tables are needed.
+
+pub mod arch;
+pub mod bsp;
+pub mod log;
+
+mod panic_wait;
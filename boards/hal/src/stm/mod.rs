@@ -15,3 +15,392 @@ pub mod stm32f746;
 
 #[cfg(feature = "stm32f334")]
 pub mod stm32f334;
+
+#[cfg(feature = "stm32f407")]
+pub mod stm32f407;
+
+#[cfg(feature = "stm32f769")]
+pub mod stm32f769;
+
+#[cfg(feature = "boot-reason")]
+use rustBoot::bootreason::ResetCause;
+
+/// Reads a GPIO's level on STM32 parts, after enabling its port's clock and configuring it as an
+/// input with a pull-up (or a pull-down), see [`crate::boot_pin`]. Ports are `GPIO_STRIDE` bytes
+/// apart, from `GPIOA` at `gpioa`, and `GPIOA`'s clock is enabled by bit `en_bit` of `rcc_enr`
+/// (i.e. the bus's clock enable register), with the following ports' bits after it.
+pub(crate) fn read_gpio(
+    rcc_enr: u32,
+    en_bit: u32,
+    gpioa: u32,
+    port: u8,
+    pin: u8,
+    pull_up: bool,
+) -> bool {
+    const GPIO_STRIDE: u32 = 0x400;
+    const GPIO_PUPDR: u32 = 0x0C;
+    const GPIO_IDR: u32 = 0x10;
+    let (port, pin) = (port as u32, pin as u32 & 0xF);
+    let gpio = gpioa + port * GPIO_STRIDE;
+    let pull = match pull_up {
+        true => 0b01,
+        false => 0b10,
+    };
+    let modify = |addr: u32, mask: u32, val: u32| unsafe {
+        let reg = core::ptr::read_volatile(addr as *const u32);
+        core::ptr::write_volatile(addr as *mut u32, (reg & !mask) | val);
+    };
+    modify(rcc_enr, 0, 1 << (en_bit + port));
+    // `MODER` i.e. input mode is `0b00`
+    modify(gpio, 0b11 << (pin * 2), 0);
+    modify(gpio + GPIO_PUPDR, 0b11 << (pin * 2), pull << (pin * 2));
+    unsafe { core::ptr::read_volatile((gpio + GPIO_IDR) as *const u32) & (1 << pin) != 0 }
+}
+
+/// Reads the RTC's calendar on STM32 parts (their RTCs share a register layout), as seconds since
+/// the unix epoch. `None` if the calendar was never initialized i.e. since the last backup-domain
+/// reset.
+pub(crate) fn read_rtc(rtc: u32) -> Option<u64> {
+    const RTC_TR: u32 = 0x00;
+    const RTC_DR: u32 = 0x04;
+    const RTC_CR: u32 = 0x08;
+    const RTC_ISR: u32 = 0x0C;
+    const ISR_INITS: u32 = 1 << 4;
+    const CR_FMT: u32 = 1 << 6;
+    const TR_PM: u32 = 1 << 22;
+    let read = |offset: u32| unsafe { core::ptr::read_volatile((rtc + offset) as *const u32) };
+    if read(RTC_ISR) & ISR_INITS == 0 {
+        return None;
+    }
+    // reading `TR` locks the (shadow) calendar until `DR` is read, so both are from the same second
+    let (tr, dr) = (read(RTC_TR), read(RTC_DR));
+    let bcd = |reg: u32, shift: u32, tens_mask: u32| -> u64 {
+        (((reg >> (shift + 4)) & tens_mask) * 10 + ((reg >> shift) & 0xF)) as u64
+    };
+    let mut hours = bcd(tr, 16, 0x3);
+    if read(RTC_CR) & CR_FMT != 0 {
+        // 12-hour format
+        hours = hours % 12 + if tr & TR_PM != 0 { 12 } else { 0 };
+    }
+    let (year, month, day) = (2000 + bcd(dr, 16, 0xF), bcd(dr, 8, 0x1), bcd(dr, 0, 0x3));
+    if !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+    // days-from-civil, see http://howardhinnant.github.io/date_algorithms.html
+    let year = year - (month <= 2) as u64;
+    let (era, yoe) = (year / 400, year % 400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let days = era * 146_097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719_468;
+    Some(days * 86_400 + hours * 3600 + bcd(tr, 8, 0x7) * 60 + bcd(tr, 0, 0x7))
+}
+
+/// The offset of the RTC's first backup register (i.e. `RTC_BKP0R`) on STM32 parts. Backup
+/// registers survive a reset, but not a backup-domain reset.
+const RTC_BKP0R: u32 = 0x50;
+
+/// Reads the RTC's first `N` backup registers on STM32 parts.
+pub(crate) fn read_backup_regs<const N: usize>(rtc: u32) -> [u32; N] {
+    let mut regs = [0u32; N];
+    for (idx, reg) in regs.iter_mut().enumerate() {
+        let addr = rtc + RTC_BKP0R + 4 * idx as u32;
+        *reg = unsafe { core::ptr::read_volatile(addr as *const u32) };
+    }
+    regs
+}
+
+/// Writes the RTC's first `regs.len()` backup registers on STM32 parts, after enabling writes to
+/// the backup domain i.e. `DBP` in `pwr_cr` (the power controller's clock must be enabled).
+pub(crate) fn write_backup_regs(rtc: u32, pwr_cr: u32, regs: &[u32]) {
+    const PWR_CR_DBP: u32 = 1 << 8;
+    unsafe {
+        let reg = core::ptr::read_volatile(pwr_cr as *const u32);
+        core::ptr::write_volatile(pwr_cr as *mut u32, reg | PWR_CR_DBP);
+        for (idx, reg) in regs.iter().enumerate() {
+            let addr = rtc + RTC_BKP0R + 4 * idx as u32;
+            core::ptr::write_volatile(addr as *mut u32, *reg);
+        }
+    }
+}
+
+/// The reset flags in `RCC_CSR` on the f4s and f7s (see `take_reset_cause`), in order of
+/// precedence i.e. a power-on reset also sets the brown-out flag and every reset sets the pin flag.
+#[cfg(feature = "boot-reason")]
+pub(crate) const RCC_CSR_CAUSES: [(u32, ResetCause); 5] = [
+    // `IWDGRSTF` and `WWDGRSTF`
+    (1 << 29 | 1 << 30, ResetCause::Watchdog),
+    (1 << 28, ResetCause::Software),
+    (1 << 27, ResetCause::PowerOn),
+    (1 << 25, ResetCause::BrownOut),
+    (1 << 26, ResetCause::Pin),
+];
+
+/// `RMVF` in `RCC_CSR` i.e. clears the reset flags.
+#[cfg(feature = "boot-reason")]
+pub(crate) const RCC_CSR_RMVF: u32 = 1 << 24;
+
+/// Returns the last reset's cause per the reset flags in `csr` (see
+/// `rustBoot::bootreason::ResetCause::from_flags` for `causes`), then clears them by setting
+/// `rmvf`, see `rustBoot_hal::boot_reason`.
+#[cfg(feature = "boot-reason")]
+pub(crate) fn take_reset_cause(csr: u32, rmvf: u32, causes: &[(u32, ResetCause)]) -> ResetCause {
+    unsafe {
+        let flags = core::ptr::read_volatile(csr as *const u32);
+        core::ptr::write_volatile(csr as *mut u32, flags | rmvf);
+        ResetCause::from_flags(flags, causes)
+    }
+}
+
+/// The RNG of the stm32f4s, f7s and the stm32h723 (they share a register layout), see
+/// [`crate::entropy`]. Boards get theirs from their part's `rng()`.
+///
+/// The RNG runs off a 48MHz clock i.e. the main PLL's `PLL48CLK` on the f4s and f7s, which the
+/// board must configure. Until the RNG is clocked (or if it reports a clock or seed error), values
+/// come from [`CycleJitter`](crate::entropy::CycleJitter).
+#[cfg(feature = "entropy")]
+pub struct Rng {
+    /// `RCC_AHB2ENR` i.e. the RNG's clock enable (bit 6)
+    rcc_ahb2enr: u32,
+    rng: u32,
+    /// `RCC_CR`, if the RNG's kernel clock is the HSI48 (bit 12 turns it on)
+    rcc_cr_hsi48: Option<u32>,
+    fallback: crate::entropy::CycleJitter,
+}
+
+#[cfg(feature = "entropy")]
+impl Rng {
+    pub(crate) const fn at(rcc_ahb2enr: u32, rng: u32, rcc_cr_hsi48: Option<u32>) -> Self {
+        Rng {
+            rcc_ahb2enr,
+            rng,
+            rcc_cr_hsi48,
+            fallback: crate::entropy::CycleJitter::new(),
+        }
+    }
+
+    /// Reads a 32-bit random number, `None` if none is ready in time.
+    fn read(&self) -> Option<u32> {
+        const RCC_RNG_EN: u32 = 1 << 6;
+        const RCC_CR_HSI48_ON: u32 = 1 << 12;
+        const RNG_SR: u32 = 0x04;
+        const RNG_DR: u32 = 0x08;
+        const CR_RNGEN: u32 = 1 << 2;
+        const SR_DRDY: u32 = 1 << 0;
+        const SR_CECS: u32 = 1 << 1;
+        const SR_SECS: u32 = 1 << 2;
+        // a value takes ~40 RNG clock cycles
+        const POLLS: u32 = 10_000;
+        let set = |addr: u32, bits: u32| unsafe {
+            let reg = core::ptr::read_volatile(addr as *const u32);
+            core::ptr::write_volatile(addr as *mut u32, reg | bits);
+        };
+        if let Some(rcc_cr) = self.rcc_cr_hsi48 {
+            set(rcc_cr, RCC_CR_HSI48_ON);
+        }
+        set(self.rcc_ahb2enr, RCC_RNG_EN);
+        set(self.rng, CR_RNGEN);
+        let read =
+            |offset: u32| unsafe { core::ptr::read_volatile((self.rng + offset) as *const u32) };
+        for _ in 0..POLLS {
+            let sr = read(RNG_SR);
+            if sr & (SR_CECS | SR_SECS) != 0 {
+                return None;
+            }
+            if sr & SR_DRDY != 0 {
+                return Some(read(RNG_DR));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(feature = "entropy")]
+impl rustBoot::crypto::entropy::EntropySource for Rng {
+    fn next_u32(&self) -> u32 {
+        self.read().unwrap_or_else(|| self.fallback.next_u32())
+    }
+}
+
+/// Makes `len` bytes of flash at `addr` read back fresh on the Cortex-M7 parts (i.e. the stm32f7s
+/// and the stm32h723), after an erase or a write. Flash is cacheable, so the L1 data cache may
+/// still hold lines of the old contents (and the instruction cache, of old code).
+///
+/// The data cache lines covering the range are cleaned and invalidated (flash is write-through,
+/// so there's nothing to clean in practice), the instruction cache is invalidated and barriers
+/// complete the maintenance before flash is read again.
+#[cfg(any(feature = "stm32f746", feature = "stm32f769", feature = "stm32h723"))]
+pub(crate) fn m7_flash_barrier(addr: usize, len: usize) {
+    const SCB_CCR: u32 = 0xE000_ED14;
+    const CCR_DC: u32 = 1 << 16;
+    const CCR_IC: u32 = 1 << 17;
+    const SCB_ICIALLU: u32 = 0xE000_EF50;
+    const SCB_DCCIMVAC: u32 = 0xE000_EF70;
+    const CACHE_LINE_SIZE: usize = 32;
+    cortex_m::asm::dsb();
+    let ccr = unsafe { core::ptr::read_volatile(SCB_CCR as *const u32) };
+    if ccr & CCR_DC != 0 {
+        let mut line = addr & !(CACHE_LINE_SIZE - 1);
+        while line < addr + len {
+            unsafe { core::ptr::write_volatile(SCB_DCCIMVAC as *mut u32, line as u32) };
+            line += CACHE_LINE_SIZE;
+        }
+    }
+    if ccr & CCR_IC != 0 {
+        unsafe { core::ptr::write_volatile(SCB_ICIALLU as *mut u32, 0) };
+    }
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+/// Resets the stm32f7s' ART accelerator i.e. its cache of flash (on the ITCM interface), which
+/// isn't kept coherent with erases and writes. ART can only be reset while it's disabled, it's
+/// re-enabled afterwards.
+#[cfg(any(feature = "stm32f746", feature = "stm32f769"))]
+pub(crate) fn f7_art_reset() {
+    const FLASH_ACR: u32 = 0x4002_3C00;
+    const ACR_ARTEN: u32 = 1 << 9;
+    const ACR_ARTRST: u32 = 1 << 11;
+    unsafe {
+        let acr = core::ptr::read_volatile(FLASH_ACR as *const u32);
+        if acr & ACR_ARTEN == 0 {
+            return;
+        }
+        core::ptr::write_volatile(FLASH_ACR as *mut u32, acr & !ACR_ARTEN);
+        core::ptr::write_volatile(FLASH_ACR as *mut u32, (acr & !ACR_ARTEN) | ACR_ARTRST);
+        core::ptr::write_volatile(FLASH_ACR as *mut u32, acr & !ACR_ARTEN);
+        core::ptr::write_volatile(FLASH_ACR as *mut u32, acr);
+    }
+}
+
+/// Runs flash erases and writes from the HSI on the stm32h723 and the stm32f7s i.e. parts that
+/// usually run at several hundred MHz, where a (large sector's) erase can brown out a marginal
+/// supply. See the `flash-clock` feature.
+///
+/// Before an erase or a write, [`FlashClock::slow_down`] checks that the core voltage (VOS) is
+/// ready and that the supply isn't below the PVD's threshold (if the board enabled the PVD), then
+/// switches SYSCLK to the HSI. The returned guard switches it back to its previous source, which
+/// is left running (i.e. the PLL stays locked), when it's dropped. Flash wait states aren't
+/// touched, those set for the faster clock are enough for the HSI.
+///
+/// *Note: peripherals clocked off SYSCLK (ex: a UART's baud rate) run slower during an erase or
+/// a write. The non-blocking operations (see [`crate::FlashInterfaceNb`]) aren't covered.*
+#[cfg(feature = "flash-clock")]
+pub(crate) struct FlashClock {
+    /// `RCC_CR` and its `HSIRDY` flag (`HSION` is bit 0)
+    pub rcc_cr: u32,
+    pub hsirdy: u32,
+    /// `RCC_CFGR` and the width of its `SW` field (`SWS` follows it)
+    pub rcc_cfgr: u32,
+    pub sw_bits: u32,
+    /// `PWR_CR1`, `PWR_CSR1` and the latter's `PVDO` and `VOSRDY` (or `ACTVOSRDY`) flags
+    pub pwr_cr1: u32,
+    pub pwr_csr1: u32,
+    pub pvdo: u32,
+    pub vosrdy: u32,
+    /// the power controller's clock enable, where it's gated i.e. `RCC_APB1ENR` and `PWREN`
+    pub pwr_en: Option<(u32, u32)>,
+}
+
+/// Switches SYSCLK back to its previous source, see [`FlashClock`].
+#[cfg(feature = "flash-clock")]
+pub(crate) struct SlowClock<'a> {
+    clock: &'a FlashClock,
+    sw: u32,
+}
+
+#[cfg(feature = "flash-clock")]
+impl FlashClock {
+    /// Checks the supply and switches SYSCLK to the HSI. `None` if the supply isn't ready i.e.
+    /// the erase or write shouldn't be started.
+    pub(crate) fn slow_down(&self) -> Option<SlowClock<'_>> {
+        const PWR_CR1_PVDE: u32 = 1 << 4;
+        const RCC_CR_HSION: u32 = 1 << 0;
+        const SW_HSI: u32 = 0;
+        // VOS settles in a few µs, when it's changed at all
+        const POLLS: u32 = 100_000;
+        let read = |addr: u32| unsafe { core::ptr::read_volatile(addr as *const u32) };
+        let modify = |addr: u32, mask: u32, val: u32| unsafe {
+            core::ptr::write_volatile(addr as *mut u32, (read(addr) & !mask) | val);
+        };
+        if let Some((enr, en)) = self.pwr_en {
+            modify(enr, 0, en);
+        }
+        if !(0..POLLS).any(|_| read(self.pwr_csr1) & self.vosrdy != 0) {
+            return None;
+        }
+        if read(self.pwr_cr1) & PWR_CR1_PVDE != 0 && read(self.pwr_csr1) & self.pvdo != 0 {
+            return None;
+        }
+        modify(self.rcc_cr, 0, RCC_CR_HSION);
+        while read(self.rcc_cr) & self.hsirdy == 0 {}
+        let sw_mask = (1 << self.sw_bits) - 1;
+        let sw = read(self.rcc_cfgr) & sw_mask;
+        self.switch(SW_HSI);
+        Some(SlowClock { clock: self, sw })
+    }
+
+    /// Selects `sw` as SYSCLK's source and waits for the switch.
+    fn switch(&self, sw: u32) {
+        let sw_mask = (1 << self.sw_bits) - 1;
+        unsafe {
+            let cfgr = core::ptr::read_volatile(self.rcc_cfgr as *const u32);
+            core::ptr::write_volatile(self.rcc_cfgr as *mut u32, (cfgr & !sw_mask) | sw);
+            while (core::ptr::read_volatile(self.rcc_cfgr as *const u32) >> self.sw_bits) & sw_mask
+                != sw
+            {}
+        }
+    }
+}
+
+#[cfg(feature = "flash-clock")]
+impl Drop for SlowClock<'_> {
+    fn drop(&mut self) {
+        self.clock.switch(self.sw)
+    }
+}
+
+/// `FLASH_SR` and `FLASH_CR` on the stm32f4s.
+#[cfg(feature = "ramfunc")]
+const F4_FLASH_SR: u32 = 0x4002_3C0C;
+#[cfg(feature = "ramfunc")]
+const F4_FLASH_CR: u32 = 0x4002_3C10;
+
+/// Erases sector `snb` of an stm32f4's flash and waits for the erase to complete. The flash must
+/// be unlocked.
+///
+/// Reading the bank that's being erased stalls the CPU (for up to a few seconds, for a 128KiB
+/// sector). So, this is a RAM function and it's written in assembly i.e. nothing is fetched from
+/// flash until `FLASH_SR.BSY` clears, see [`crate::ramfunc`]. Interrupts stay enabled - handlers
+/// installed in a RAM vector table keep running during the erase.
+#[cfg(feature = "ramfunc")]
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+pub(crate) unsafe fn f4_erase_sector(snb: u8) {
+    const CR_PG: u32 = 1 << 0;
+    const CR_SER: u32 = 1 << 1;
+    const CR_SNB_MASK: u32 = 0b1_1111 << 3;
+    // `PSIZE` is cleared i.e. x8 parallelism
+    const CR_PSIZE_MASK: u32 = 0b11 << 8;
+    const CR_STRT: u32 = 1 << 16;
+    const SR_BSY: u32 = 1 << 16;
+    core::arch::asm!(
+        "ldr {cr}, [{cr_reg}]",
+        "bic {cr}, {cr}, {clear}",
+        "orr {cr}, {cr}, {set}",
+        "str {cr}, [{cr_reg}]",
+        "orr {cr}, {cr}, {strt}",
+        "str {cr}, [{cr_reg}]",
+        "2:",
+        "ldr {sr}, [{sr_reg}]",
+        "tst {sr}, {bsy}",
+        "bne 2b",
+        cr = out(reg) _,
+        sr = out(reg) _,
+        cr_reg = in(reg) F4_FLASH_CR,
+        sr_reg = in(reg) F4_FLASH_SR,
+        clear = in(reg) CR_PG | CR_SNB_MASK | CR_PSIZE_MASK,
+        set = in(reg) CR_SER | ((snb as u32) << 3),
+        strt = in(reg) CR_STRT,
+        bsy = in(reg) SR_BSY,
+        options(nostack)
+    );
+}
@@ -2,11 +2,13 @@
 #![no_main]
 
 use stm32f3xx_hal as hal;
-use core::ptr::write_volatile;
+use core::ptr::{read_volatile, write_volatile};
 use cortex_m::asm;
 use hal::pac::{Peripherals, FLASH};
-use crate::FlashInterface;
+use crate::{protected_sectors, DebugProtection, FlashError, FlashInterface};
 use stm32f334r8_constants::*;
+#[cfg(feature = "boot-reason")]
+use rustBoot::bootreason::ResetCause;
 #[rustfmt::skip]
 mod stm32f334r8_constants {
 
@@ -16,11 +18,48 @@ mod stm32f334r8_constants {
     pub const RB_HDR_SIZE     : u32 = 0x100;
     pub const BASE_ADDR       : u32 = 0x0800B800;   //  pagetor 5 starting flag
     pub const VTR_TABLE_SIZE  : u32 = 0x100;
+    // the 96-bit unique device ID
+    pub const UID_ADDR        : u32 = 0x1FFF_F7AC;
+    pub const UID_LEN         : usize = 12;
     pub const FW_RESET_VTR    : u32 = BASE_ADDR + RB_HDR_SIZE + VTR_TABLE_SIZE + 0x89;
     pub const UNLOCKKEY1      : u32 = 0x45670123;
     pub const UNLOCKKEY2      : u32 = 0xCDEF89AB;
-
-
+    pub const FLASH_OPTKEYR   : u32 = 0x4002_2008;
+    pub const FLASH_CR        : u32 = 0x4002_2010;
+    pub const FLASH_OBR       : u32 = 0x4002_201C;
+    pub const FLASH_WRPR      : u32 = 0x4002_2020;
+    pub const OBR_RDPRT_SHIFT : u32 = 1;
+    pub const CR_OPTPG        : u32 = 1 << 4;
+    pub const CR_OPTER        : u32 = 1 << 5;
+    pub const CR_STRT         : u32 = 1 << 6;
+    pub const CR_OBL_LAUNCH   : u32 = 1 << 13;
+    // option bytes i.e. RDP, USER, DATA0, DATA1, WRP0..WRP3 (one half-word each)
+    pub const OPTION_BYTES    : u32 = 0x1FFF_F800;
+    pub const OPTION_BYTES_LEN: usize = 8;
+    pub const OB_RDP_IDX      : usize = 0;
+    pub const OB_WRP0_IDX     : usize = 4;
+    pub const RDP_LEVEL_1     : u16 = 0x55;
+    pub const RDP_LEVEL_2     : u16 = 0xCC;
+    // (start address, size) of the 2-page groups covered by `WRP0` and `WRP1`
+    pub const FLASH_WRP_GROUPS: [(usize, usize); 16] = [
+        (0x0800_0000, 0x1000), (0x0800_1000, 0x1000), (0x0800_2000, 0x1000), (0x0800_3000, 0x1000),
+        (0x0800_4000, 0x1000), (0x0800_5000, 0x1000), (0x0800_6000, 0x1000), (0x0800_7000, 0x1000),
+        (0x0800_8000, 0x1000), (0x0800_9000, 0x1000), (0x0800_A000, 0x1000), (0x0800_B000, 0x1000),
+        (0x0800_C000, 0x1000), (0x0800_D000, 0x1000), (0x0800_E000, 0x1000), (0x0800_F000, 0x1000),
+    ];
+    // the boot pin's GPIO port and its clock, see `boot_pin_held`. The core runs off the HSI
+    // (i.e. at 8MHz) out of reset.
+    pub const RCC_GPIO_ENR    : u32 = 0x4002_1014;
+    pub const RCC_GPIOA_EN_BIT: u32 = 17;
+    pub const GPIOA_BASE      : u32 = 0x4800_0000;
+    pub const RESET_CLOCK_HZ  : u32 = 8_000_000;
+    pub const RTC_BASE        : u32 = 0x4000_2800;
+    // the power controller (i.e. backup-domain write access) and its clock
+    pub const PWR_CR          : u32 = 0x4000_7000;
+    pub const RCC_APB1ENR     : u32 = 0x4002_101C;
+    pub const RCC_PWR_EN      : u32 = 1 << 28;
+    // the reset flags, see `take_reset_cause`
+    pub const RCC_CSR         : u32 = 0x4002_1024;
 }
 pub struct FlashWriterEraser {    
     pub nvm: FLASH,
@@ -34,6 +73,46 @@ impl FlashWriterEraser {
     }
 }
 
+impl FlashWriterEraser {
+    /// Erases and re-programs the option bytes, and then reloads them. Reloading the option bytes
+    /// resets the device.
+    fn program_option_bytes(&self, option_bytes: &[u16; OPTION_BYTES_LEN]) -> ! {
+        self.hal_flash_unlock();
+        unsafe {
+            // unlock the option bytes
+            write_volatile(FLASH_OPTKEYR as *mut u32, UNLOCKKEY1);
+            write_volatile(FLASH_OPTKEYR as *mut u32, UNLOCKKEY2);
+            // erase
+            let cr = read_volatile(FLASH_CR as *const u32);
+            write_volatile(FLASH_CR as *mut u32, cr | CR_OPTER);
+            write_volatile(FLASH_CR as *mut u32, cr | CR_OPTER | CR_STRT);
+            while self.nvm.sr.read().bsy().bit_is_set() {}
+            // program
+            write_volatile(FLASH_CR as *mut u32, cr | CR_OPTPG);
+            for (idx, val) in option_bytes.iter().enumerate() {
+                write_volatile((OPTION_BYTES as *mut u16).add(idx), *val);
+                while self.nvm.sr.read().bsy().bit_is_set() {}
+            }
+            write_volatile(FLASH_CR as *mut u32, cr);
+            // reload option bytes, this resets the device
+            write_volatile(FLASH_CR as *mut u32, cr | CR_OBL_LAUNCH);
+        }
+        loop {
+            asm::nop();
+        }
+    }
+}
+
+/// Reads the current option bytes. Only the low byte of each half-word is returned, as the
+/// complement is computed by the hardware when programming them.
+fn read_option_bytes() -> [u16; OPTION_BYTES_LEN] {
+    let mut option_bytes = [0u16; OPTION_BYTES_LEN];
+    for (idx, val) in option_bytes.iter_mut().enumerate() {
+        *val = unsafe { read_volatile((OPTION_BYTES as *const u16).add(idx)) } & 0xff;
+    }
+    option_bytes
+}
+
 impl FlashInterface for FlashWriterEraser {
 
     /// This method is used to erase data on flash
@@ -47,7 +126,7 @@ impl FlashInterface for FlashWriterEraser {
     ///
     /// Returns:
     /// -  NONE
-    fn hal_flash_erase(&self, addr: usize, len: usize) {
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
         let mut flag: bool = true;
         let mut address = (addr & 0x0800_F800) as u32; // Finding base address of the page from the given address 
         let remaing_bytes  = len%FLASH_PAGE_SIZE as usize;
@@ -92,6 +171,7 @@ impl FlashInterface for FlashWriterEraser {
             num_pages = num_pages - 1;
         }
       
+        Ok(())
     }
 
 
@@ -99,13 +179,13 @@ impl FlashInterface for FlashWriterEraser {
     ///
     /// Method arguments:
     /// -   address: It holds the address of flash where data has to be written
-    /// -   data: u8 pointer holding the holding data.
-    /// -   len :  number of bytes
+    /// -   data: the bytes to be written
     ///
     /// Returns:
     /// -  NONE
     /// 
-    fn hal_flash_write(&self, address: usize, data: *const u8, len: usize) {
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        let (data, len) = (data.as_ptr(), data.len());
 
         let address = address as u32;
         let mut len = len as u32;
@@ -167,7 +247,7 @@ impl FlashInterface for FlashWriterEraser {
                         asm::delay(1);              //One clock cycle delay of main clock 
                     }
                     buffer[offset] = data1;
-                    self.hal_flash_erase(dst_addr as usize,1);
+                    self.hal_flash_erase(dst_addr as usize,1)?;
                     for half_words in 0..1024
                     { 
                         while self.nvm.sr.read().bsy().bit_is_set() {}
@@ -241,6 +321,7 @@ impl FlashInterface for FlashWriterEraser {
             .pg().clear_bit()
         });
         self.hal_flash_lock();
+        Ok(())
     }
 
     /// This method is used to unlock the flash
@@ -268,11 +349,142 @@ impl FlashInterface for FlashWriterEraser {
         self.nvm.cr.modify(|_, w| w.lock().set_bit());
     }
 
+    /// This method is used to write-protect a region of flash
+    ///
+    /// Each `WRP` option bit protects a group of 2 pages (4KB), so only groups that lie entirely
+    /// within the region are protected. Option bytes can only be erased as a whole - the ones
+    /// we don't change are saved and re-programmed as is. The new option bytes are loaded with
+    /// `OBL_LAUNCH`, which resets the device i.e. this method does not return if the option
+    /// bytes were programmed.
+    ///
+    /// Method arguments:
+    /// -   addr: start address of the region to be protected
+    /// -   len : number of bytes to be protected
+    /// Returns:
+    /// -  NONE
+    ///
+    fn hal_flash_protect(&self, addr: usize, len: usize) {
+        let groups = protected_sectors(&FLASH_WRP_GROUPS, addr, len);
+        let wrpr = unsafe { read_volatile(FLASH_WRPR as *const u32) };
+        // a cleared `WRP` bit means the group is already write-protected
+        let unprotected = wrpr & groups;
+        if unprotected == 0 {
+            return;
+        }
+        let wrp = wrpr & !unprotected;
+        let mut option_bytes = read_option_bytes();
+        option_bytes[OB_WRP0_IDX] = (wrp & 0xff) as u16;
+        option_bytes[OB_WRP0_IDX + 1] = ((wrp >> 8) & 0xff) as u16;
+        self.program_option_bytes(&option_bytes)
+    }
+
+    /// This method returns the current read-out protection (RDP) level
+    ///
+    /// Method arguments:
+    /// -   NONE
+    /// Returns:
+    /// -  the RDP level, as a `DebugProtection` level
+    ///
+    fn hal_debug_protection(&self) -> DebugProtection {
+        let obr = unsafe { read_volatile(FLASH_OBR as *const u32) };
+        match (obr >> OBR_RDPRT_SHIFT) & 0b11 {
+            0b00 => DebugProtection::Disabled,
+            0b01 => DebugProtection::Enabled,
+            _ => DebugProtection::Permanent,
+        }
+    }
+
+    /// This method is used to raise the read-out protection (RDP) level
+    ///
+    /// Like `hal_flash_protect`, this resets the device if the option bytes were programmed.
+    ///
+    /// **note:** RDP level 2 is irreversible, it also locks the option bytes i.e. write-protection
+    /// must be set up before this.
+    ///
+    /// Method arguments:
+    /// -   level: the `DebugProtection` level to set
+    /// Returns:
+    /// -  NONE
+    ///
+    fn hal_set_debug_protection(&self, level: DebugProtection) {
+        if level <= self.hal_debug_protection() {
+            return;
+        }
+        let mut option_bytes = read_option_bytes();
+        option_bytes[OB_RDP_IDX] = match level {
+            DebugProtection::Disabled => return,
+            DebugProtection::Enabled => RDP_LEVEL_1,
+            DebugProtection::Permanent => RDP_LEVEL_2,
+        };
+        self.program_option_bytes(&option_bytes)
+    }
+
     fn hal_init(){}
 
 }
 
 pub fn preboot() {}
+/// Returns the device's factory-programmed unique ID (i.e. the 96-bit unique device ID).
+pub fn device_uid() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(UID_ADDR as *const u8, UID_LEN) }
+}
+
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Returns true if `boot_pin` is held, see `rustBoot_hal::boot_pin_held`.
+pub fn boot_pin_held(boot_pin: &crate::boot_pin::BootPin) -> bool {
+    crate::boot_pin::sample(boot_pin, read_pin, RESET_CLOCK_HZ)
+}
+
+fn read_pin(port: u8, pin: u8, pull_up: bool) -> bool {
+    super::read_gpio(
+        RCC_GPIO_ENR,
+        RCC_GPIOA_EN_BIT,
+        GPIOA_BASE,
+        port,
+        pin,
+        pull_up,
+    )
+}
+
+/// Returns the RTC's calendar time, see `rustBoot_hal::rtc_time`.
+pub fn rtc_time() -> Option<u64> {
+    super::read_rtc(RTC_BASE)
+}
+
+/// Reads the RTC's backup registers, see `rustBoot_hal::read_backup_regs`.
+pub fn read_backup_regs<const N: usize>() -> [u32; N] {
+    super::read_backup_regs(RTC_BASE)
+}
+
+/// Writes the RTC's backup registers, see `rustBoot_hal::write_backup_regs`.
+pub fn write_backup_regs(regs: &[u32]) {
+    unsafe {
+        let reg = core::ptr::read_volatile(RCC_APB1ENR as *const u32);
+        core::ptr::write_volatile(RCC_APB1ENR as *mut u32, reg | RCC_PWR_EN);
+    }
+    super::write_backup_regs(RTC_BASE, PWR_CR, regs)
+}
+
+/// The reset flags in `RCC_CSR`, see `super::RCC_CSR_CAUSES`. The f334 has no brown-out flag, its
+/// bit 25 flags an option byte loader reset.
+#[cfg(feature = "boot-reason")]
+const RCC_CSR_CAUSES: [(u32, ResetCause); 4] = [
+    (1 << 29 | 1 << 30, ResetCause::Watchdog),
+    (1 << 28, ResetCause::Software),
+    (1 << 27, ResetCause::PowerOn),
+    (1 << 26, ResetCause::Pin),
+];
+
+/// Returns (and clears) the last reset's cause, see `rustBoot_hal::boot_reason`.
+#[cfg(feature = "boot-reason")]
+pub fn take_reset_cause() -> ResetCause {
+    super::take_reset_cause(RCC_CSR, super::RCC_CSR_RMVF, &RCC_CSR_CAUSES)
+}
+
 struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
 
 impl<const MIN: u32, const MAX: u32, const VAL: u32> RefinedUsize<MIN, MAX, VAL> {
@@ -315,6 +527,8 @@ pub fn boot_from(fw_base_address: usize) -> ! {
         *((fw_base_address + 4) as *const u32)).0;
        let jump_vector = core::mem::transmute::<usize, extern "C" fn() -> !>(rv as usize);
        (*scb).vtor.write(address);
+       #[cfg(feature = "hide-bootloader")]
+       crate::mpu::boot(sp, rv);
        cortex_m::register::msp::write(sp);
        jump_vector();
     
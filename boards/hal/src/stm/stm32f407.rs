@@ -0,0 +1,453 @@
+//! Flash  Read, Write and Erase opration for `stm32f407vg` i.e. the STM32F4DISCOVERY.
+
+use stm32f4xx_hal as hal;
+
+use crate::{
+    protected_sectors, sector_at, DebugProtection, FlashError, FlashInterface, FlashInterfaceNb,
+};
+use core::ptr::{read_volatile, write_volatile};
+use hal::pac::{Peripherals, FLASH};
+use stm32f407vg_constants::*;
+#[rustfmt::skip]
+mod stm32f407vg_constants {
+    pub const FLASH_PAGE_SIZE : u32 = 131072;   // 1 sector size = 128KB
+    pub const STACK_LOW       : u32 = 0x2000_0000;
+    pub const STACK_UP        : u32 = 0x2002_0000;
+    pub const RB_HDR_SIZE     : u32 = 0x100;
+    pub const BASE_ADDR       : u32 = 0x08020000;   //  sector 5 starting address
+    // the firmware's reset handler follows its vector table i.e. 16 + 82 vectors
+    pub const VTR_TABLE_SIZE  : u32 = 0x100;
+    // the 96-bit unique device ID
+    pub const UID_ADDR        : u32 = 0x1FFF_7A10;
+    pub const UID_LEN         : usize = 12;
+    pub const FW_RESET_VTR    : u32 = BASE_ADDR + RB_HDR_SIZE + VTR_TABLE_SIZE + 0x89;
+    pub const UNLOCKKEY1      : u32 = 0x45670123;
+    pub const UNLOCKKEY2      : u32 = 0xCDEF89AB;
+    pub const PSIZE_X8        : u8  = 0b00;
+    pub const PSIZE_X16       : u8  = 0b01;
+    pub const PSIZE_X32       : u8  = 0b10;
+    pub const PSIZE_X64       : u8  = 0b11;
+    pub const FLASH_OPTKEYR   : u32 = 0x4002_3C08;
+    pub const FLASH_OPTCR     : u32 = 0x4002_3C14;
+    pub const OPTKEY1         : u32 = 0x0819_2A3B;
+    pub const OPTKEY2         : u32 = 0x4C5D_6E7F;
+    pub const OPTCR_OPTLOCK   : u32 = 1 << 0;
+    pub const OPTCR_OPTSTRT   : u32 = 1 << 1;
+    pub const OPTCR_NWRP_SHIFT: u32 = 16;
+    pub const OPTCR_RDP_SHIFT : u32 = 8;
+    pub const RDP_LEVEL_0     : u32 = 0xAA;
+    pub const RDP_LEVEL_1     : u32 = 0x55;
+    pub const RDP_LEVEL_2     : u32 = 0xCC;
+    // (start address, size) of the sectors covered by `nWRP`
+    pub const FLASH_SECTORS   : [(usize, usize); 12] = [
+        (0x0800_0000, 0x4000),
+        (0x0800_4000, 0x4000),
+        (0x0800_8000, 0x4000),
+        (0x0800_C000, 0x4000),
+        (0x0801_0000, 0x10000),
+        (0x0802_0000, 0x20000),
+        (0x0804_0000, 0x20000),
+        (0x0806_0000, 0x20000),
+        (0x0808_0000, 0x20000),
+        (0x080A_0000, 0x20000),
+        (0x080C_0000, 0x20000),
+        (0x080E_0000, 0x20000),
+    ];
+    // the boot pin's GPIO port and its clock, see `boot_pin_held`. The core runs off the HSI
+    // (i.e. at 16MHz) out of reset.
+    pub const RCC_GPIO_ENR    : u32 = 0x4002_3830;
+    pub const RCC_GPIOA_EN_BIT: u32 = 0;
+    pub const GPIOA_BASE      : u32 = 0x4002_0000;
+    pub const RESET_CLOCK_HZ  : u32 = 16_000_000;
+    pub const RTC_BASE        : u32 = 0x4000_2800;
+    // the power controller (i.e. backup-domain write access) and its clock
+    pub const PWR_CR          : u32 = 0x4000_7000;
+    pub const RCC_APB1ENR     : u32 = 0x4002_3840;
+    pub const RCC_PWR_EN      : u32 = 1 << 28;
+    // the reset flags, see `take_reset_cause`
+    pub const RCC_CSR         : u32 = 0x4002_3874;
+    // the RNG and its clock, see `rng`
+    pub const RCC_AHB2ENR     : u32 = 0x4002_3834;
+    pub const RNG_BASE        : u32 = 0x5006_0800;
+}
+
+pub struct FlashWriterEraser {
+    pub nvm: FLASH,
+}
+
+impl FlashWriterEraser {
+    pub fn new() -> Self {
+        FlashWriterEraser {
+            nvm: Peripherals::take().unwrap().FLASH,
+        }
+    }
+
+    /// Programs the option control register i.e. the option bytes
+    fn program_optcr(&self, optcr: u32) {
+        let optcr = optcr & !(OPTCR_OPTLOCK | OPTCR_OPTSTRT);
+        unsafe {
+            // unlock the option bytes
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY1);
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY2);
+            while self.nvm.sr.read().bsy().bit() {}
+            write_volatile(FLASH_OPTCR as *mut u32, optcr);
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTSTRT);
+            while self.nvm.sr.read().bsy().bit() {}
+            // lock the option bytes
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTLOCK);
+        }
+    }
+}
+
+impl FlashInterface for FlashWriterEraser {
+    /// This method is to write data on flash
+    ///
+    /// Method arguments:
+    /// -   address: It holds the address of flash where data has to be written
+    /// -   data: the bytes to be written
+    ///
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        let (data, len) = (data.as_ptr(), data.len());
+        let address = address as u32;
+        let len = len as u32;
+        let mut idx = 0u32;
+        let mut src = data as *mut u32;
+        let mut dst = address as *mut u32;
+        //Unlock the FLASH
+        self.hal_flash_unlock();
+        while idx < len {
+            let data_ptr = (data as *const u32) as u32;
+            //checking if the len is more than 4 bytes to compute a 4 byte write on flash
+            if (len - idx > 3) {
+                // Enable FLASH Page writes
+                self.nvm.cr.modify(|_, w| unsafe {
+                    w.psize()
+                        .bits(PSIZE_X32)
+                        // no sector erase
+                        .ser()
+                        .clear_bit()
+                        // programming
+                        .pg()
+                        .set_bit()
+                });
+                while self.nvm.sr.read().bsy().bit() {}
+                unsafe {
+                    // *dst = data; // 4-byte write
+                    write_volatile(dst, *src);
+                };
+
+                src = ((src as u32) + 4) as *mut u32; // increment pointer by 4
+                dst = ((dst as u32) + 4) as *mut u32; // increment pointer by 4
+                idx += 4;
+            } else {
+                // else do a single byte write i.e. 1-byte write
+                let mut val = 0u32;
+                let val_bytes = ((&mut val) as *mut u32) as *mut u8;
+                let offset = (address + idx) - (((address + idx) >> 2) << 2); // offset from nearest word aligned address
+                dst = ((dst as u32) - offset) as *mut u32; // subtract offset from dst addr
+                unsafe {
+                    val = *dst; // assign current val at dst to val
+                                // store data byte at idx to `val`. `val_bytes` is a byte-pointer to val.
+                    *val_bytes.add(offset as usize) = *data.add(idx as usize);
+                }
+                // Enable FLASH Page writes
+                self.nvm.cr.modify(|_, w| unsafe {
+                    w.psize()
+                        .bits(PSIZE_X32)
+                        // no sector erase
+                        .ser()
+                        .clear_bit()
+                        // programming
+                        .pg()
+                        .set_bit()
+                });
+                while self.nvm.sr.read().bsy().bit() {}
+                unsafe {
+                    *dst = val; // Technically this is a 1-byte write ONLY
+                                // but only full 32-bit words can be written to Flash using the NVMC interface
+                };
+                src = ((src as u32) + 1) as *mut u32; // increment pointer by 1
+                dst = ((dst as u32) + 1) as *mut u32; // increment pointer by 1
+                idx += 1;
+            }
+        }
+        //Lock the FLASH
+        self.hal_flash_lock();
+        Ok(())
+    }
+
+    /// This method is used to erase data on flash
+    ///
+    /// In STM32F407 only sector erase is available. whatever be the length of bytes we pass to this function will erase
+    /// the whole sector, whichever the sector the address belong to.
+    ///
+    /// Method arguments:
+    /// -   addr: Address where data has to be erased
+    /// -   len :  number of bytes to be erased
+    ///
+    /// Returns:
+    /// -  NONE
+
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        if let Some((sec, _)) = sector_at(&FLASH_SECTORS, addr) {
+            self.hal_flash_unlock();
+            // Erase the sector and wait until it's done, from RAM i.e. without reading the bank
+            unsafe { super::f4_erase_sector(sec) };
+            //Lock the FLASH
+            self.hal_flash_lock();
+        }
+        Ok(())
+    }
+    /// This method is used to lock the flash
+    ///
+    /// Once the flash is locked no operation on flash can be perfomed.
+    /// Method arguments:
+    /// -   NONE
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_lock(&self) {
+        self.nvm.cr.modify(|_, w| w.lock().set_bit());
+    }
+    /// This method is used to unlock the flash
+    ///
+    /// Flash has to be unlocked to do any operation on it.
+    /// Method arguments:
+    /// -   NONE
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_unlock(&self) {
+        self.nvm.keyr.write(|w| unsafe { w.key().bits(UNLOCKKEY1) });
+        self.nvm.keyr.write(|w| unsafe { w.key().bits(UNLOCKKEY2) });
+    }
+
+    /// This method is used to write-protect a region of flash
+    ///
+    /// Sectors are protected by clearing their `nWRP` bits in the option bytes. Option bytes are
+    /// non-volatile, so they're only programmed if a sector isn't already protected.
+    ///
+    /// Method arguments:
+    /// -   addr: start address of the region to be protected
+    /// -   len : number of bytes to be protected
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_protect(&self, addr: usize, len: usize) {
+        let sectors = protected_sectors(&FLASH_SECTORS, addr, len);
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        // a cleared `nWRP` bit means the sector is already write-protected
+        let unprotected = (optcr >> OPTCR_NWRP_SHIFT) & sectors;
+        if unprotected == 0 {
+            return;
+        }
+        self.program_optcr(optcr & !(unprotected << OPTCR_NWRP_SHIFT));
+    }
+
+    /// This method returns the current read-out protection (RDP) level
+    ///
+    /// Method arguments:
+    /// -   NONE
+    /// Returns:
+    /// -  the RDP level, as a `DebugProtection` level
+    fn hal_debug_protection(&self) -> DebugProtection {
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        match (optcr >> OPTCR_RDP_SHIFT) & 0xff {
+            RDP_LEVEL_0 => DebugProtection::Disabled,
+            RDP_LEVEL_2 => DebugProtection::Permanent,
+            _ => DebugProtection::Enabled,
+        }
+    }
+
+    /// This method is used to raise the read-out protection (RDP) level
+    ///
+    /// **note:** RDP level 2 is irreversible, it also locks the option bytes i.e. write-protection
+    /// must be set up before this.
+    ///
+    /// Method arguments:
+    /// -   level: the `DebugProtection` level to set
+    /// Returns:
+    /// -  NONE
+    fn hal_set_debug_protection(&self, level: DebugProtection) {
+        if level <= self.hal_debug_protection() {
+            return;
+        }
+        let rdp = match level {
+            DebugProtection::Disabled => return,
+            DebugProtection::Enabled => RDP_LEVEL_1,
+            DebugProtection::Permanent => RDP_LEVEL_2,
+        };
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        self.program_optcr((optcr & !(0xff << OPTCR_RDP_SHIFT)) | (rdp << OPTCR_RDP_SHIFT));
+    }
+
+    fn hal_init() {}
+}
+
+/// Sectors are erased (and words programmed) by the flash controller in the background,
+/// [`FlashInterfaceNb::hal_poll_complete`] checks `FLASH_SR.BSY`. Only the sectors in
+/// `FLASH_SECTORS` can be erased.
+impl FlashInterfaceNb for FlashWriterEraser {
+    const WRITE_SIZE: usize = 4;
+
+    fn hal_start_erase(&self, addr: usize) -> usize {
+        let (sec, end) = match sector_at(&FLASH_SECTORS, addr) {
+            Some(sector) => sector,
+            None => return usize::MAX,
+        };
+        while self.nvm.sr.read().bsy().bit() {}
+        self.hal_flash_unlock();
+        #[rustfmt::skip]
+        self.nvm.cr.modify(|_, w| unsafe {
+            w
+                // start
+                .strt().set_bit()
+                .psize().bits(PSIZE_X8)
+                // sector number
+                .snb().bits(sec)
+                // sectore erase
+                .ser().set_bit()
+                // no programming
+                .pg().clear_bit()
+        });
+        end
+    }
+
+    fn hal_start_write(&self, addr: usize, data: &[u8]) {
+        while self.nvm.sr.read().bsy().bit() {}
+        self.hal_flash_unlock();
+        // Enable FLASH Page writes
+        self.nvm.cr.modify(|_, w| unsafe {
+            w.psize()
+                .bits(PSIZE_X32)
+                // no sector erase
+                .ser()
+                .clear_bit()
+                // programming
+                .pg()
+                .set_bit()
+        });
+        let word = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]);
+        unsafe { write_volatile(addr as *mut u32, word) };
+    }
+
+    fn hal_poll_complete(&self) -> bool {
+        if self.nvm.sr.read().bsy().bit() {
+            return false;
+        }
+        self.nvm
+            .cr
+            .modify(|_, w| w.ser().clear_bit().pg().clear_bit());
+        self.hal_flash_lock();
+        true
+    }
+}
+
+pub fn preboot() {}
+
+/// Returns the device's factory-programmed unique ID (i.e. the 96-bit unique device ID).
+pub fn device_uid() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(UID_ADDR as *const u8, UID_LEN) }
+}
+
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Returns true if `boot_pin` is held, see `rustBoot_hal::boot_pin_held`.
+pub fn boot_pin_held(boot_pin: &crate::boot_pin::BootPin) -> bool {
+    crate::boot_pin::sample(boot_pin, read_pin, RESET_CLOCK_HZ)
+}
+
+fn read_pin(port: u8, pin: u8, pull_up: bool) -> bool {
+    super::read_gpio(
+        RCC_GPIO_ENR,
+        RCC_GPIOA_EN_BIT,
+        GPIOA_BASE,
+        port,
+        pin,
+        pull_up,
+    )
+}
+
+/// Returns the RNG, see `rustBoot_hal::entropy`.
+#[cfg(feature = "entropy")]
+pub const fn rng() -> super::Rng {
+    super::Rng::at(RCC_AHB2ENR, RNG_BASE, None)
+}
+
+/// Returns the RTC's calendar time, see `rustBoot_hal::rtc_time`.
+pub fn rtc_time() -> Option<u64> {
+    super::read_rtc(RTC_BASE)
+}
+
+/// Reads the RTC's backup registers, see `rustBoot_hal::read_backup_regs`.
+pub fn read_backup_regs<const N: usize>() -> [u32; N] {
+    super::read_backup_regs(RTC_BASE)
+}
+
+/// Writes the RTC's backup registers, see `rustBoot_hal::write_backup_regs`.
+pub fn write_backup_regs(regs: &[u32]) {
+    unsafe {
+        let reg = core::ptr::read_volatile(RCC_APB1ENR as *const u32);
+        core::ptr::write_volatile(RCC_APB1ENR as *mut u32, reg | RCC_PWR_EN);
+    }
+    super::write_backup_regs(RTC_BASE, PWR_CR, regs)
+}
+
+/// Returns (and clears) the last reset's cause, see `rustBoot_hal::boot_reason`.
+#[cfg(feature = "boot-reason")]
+pub fn take_reset_cause() -> rustBoot::bootreason::ResetCause {
+    super::take_reset_cause(RCC_CSR, super::RCC_CSR_RMVF, &super::RCC_CSR_CAUSES)
+}
+
+struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
+
+impl<const MIN: u32, const MAX: u32, const VAL: u32> RefinedUsize<MIN, MAX, VAL> {
+    /// This method is used to check the address bound of stack pointer
+    ///
+    /// Method arguments:
+    /// -   i : starting address of stack  
+    /// Returns:
+    /// -  It returns u32 address of stack pointer
+    pub fn bounded_int(i: u32) -> Self {
+        assert!(i >= MIN && i <= MAX);
+        RefinedUsize(i)
+    }
+    /// This method is used to check the address of reset pointer
+    ///
+    /// Method arguments:
+    /// -   i : starting address of reset  
+    /// Returns:
+    /// -  It returns u32 address of reset pointer
+    pub fn single_valued_int(i: u32) -> Self {
+        assert!(i == VAL);
+        RefinedUsize(i)
+    }
+}
+
+/// This method is used to boot the firmware from a particular address
+    ///
+    /// Method arguments:
+    /// -   fw_base_address  : address of the firmware
+    /// Returns:
+    /// -  NONE
+#[rustfmt::skip]
+pub fn boot_from(fw_base_address: usize) -> ! {
+       let address = fw_base_address as u32;
+       let scb = hal::pac::SCB::ptr();
+       unsafe {
+       let sp = RefinedUsize::<STACK_LOW, STACK_UP, 0>::bounded_int(
+        *(fw_base_address as *const u32)).0;
+       let rv = RefinedUsize::<0, 0, FW_RESET_VTR>::single_valued_int(
+        *((fw_base_address + 4) as *const u32)).0;
+       let jump_vector = core::mem::transmute::<usize, extern "C" fn() -> !>(rv as usize);
+       (*scb).vtor.write(address);
+       #[cfg(feature = "hide-bootloader")]
+       crate::mpu::boot(sp, rv);
+       cortex_m::register::msp::write(sp);
+       jump_vector();
+    
+       }
+       loop{}
+}
@@ -1,7 +1,9 @@
 use stm32f4xx_hal as hal;
 
-use crate::FlashInterface;
-use core::ptr::write_volatile;
+use crate::{
+    protected_sectors, sector_at, DebugProtection, FlashError, FlashInterface, FlashInterfaceNb,
+};
+use core::ptr::{read_volatile, write_volatile};
 use hal::pac::{Peripherals, FLASH};
 use stm32f446re_constants::*;
 #[rustfmt::skip]
@@ -12,6 +14,9 @@ mod stm32f446re_constants {
     pub const RB_HDR_SIZE     : u32 = 0x100;
     pub const BASE_ADDR       : u32 = 0x08020000;   //  sector 5 starting address
     pub const VTR_TABLE_SIZE  : u32 = 0x100;
+    // the 96-bit unique device ID
+    pub const UID_ADDR        : u32 = 0x1FFF_7A10;
+    pub const UID_LEN         : usize = 12;
     pub const FW_RESET_VTR    : u32 = BASE_ADDR + RB_HDR_SIZE + VTR_TABLE_SIZE + 0xA9;
     pub const UNLOCKKEY1      : u32 = 0x45670123;
     pub const UNLOCKKEY2      : u32 = 0xCDEF89AB;
@@ -19,6 +24,44 @@ mod stm32f446re_constants {
     pub const PSIZE_X16       : u8  = 0b01;
     pub const PSIZE_X32       : u8  = 0b10;
     pub const PSIZE_X64       : u8  = 0b11;
+    pub const FLASH_OPTKEYR   : u32 = 0x4002_3C08;
+    pub const FLASH_OPTCR     : u32 = 0x4002_3C14;
+    pub const OPTKEY1         : u32 = 0x0819_2A3B;
+    pub const OPTKEY2         : u32 = 0x4C5D_6E7F;
+    pub const OPTCR_OPTLOCK   : u32 = 1 << 0;
+    pub const OPTCR_OPTSTRT   : u32 = 1 << 1;
+    pub const OPTCR_NWRP_SHIFT: u32 = 16;
+    pub const OPTCR_RDP_SHIFT : u32 = 8;
+    pub const RDP_LEVEL_0     : u32 = 0xAA;
+    pub const RDP_LEVEL_1     : u32 = 0x55;
+    pub const RDP_LEVEL_2     : u32 = 0xCC;
+    // (start address, size) of the sectors covered by `nWRP`
+    pub const FLASH_SECTORS   : [(usize, usize); 8] = [
+        (0x0800_0000, 0x4000),
+        (0x0800_4000, 0x4000),
+        (0x0800_8000, 0x4000),
+        (0x0800_C000, 0x4000),
+        (0x0801_0000, 0x10000),
+        (0x0802_0000, 0x20000),
+        (0x0804_0000, 0x20000),
+        (0x0806_0000, 0x20000),
+    ];
+    // the boot pin's GPIO port and its clock, see `boot_pin_held`. The core runs off the HSI
+    // (i.e. at 16MHz) out of reset.
+    pub const RCC_GPIO_ENR    : u32 = 0x4002_3830;
+    pub const RCC_GPIOA_EN_BIT: u32 = 0;
+    pub const GPIOA_BASE      : u32 = 0x4002_0000;
+    pub const RESET_CLOCK_HZ  : u32 = 16_000_000;
+    pub const RTC_BASE        : u32 = 0x4000_2800;
+    // the power controller (i.e. backup-domain write access) and its clock
+    pub const PWR_CR          : u32 = 0x4000_7000;
+    pub const RCC_APB1ENR     : u32 = 0x4002_3840;
+    pub const RCC_PWR_EN      : u32 = 1 << 28;
+    // the reset flags, see `take_reset_cause`
+    pub const RCC_CSR         : u32 = 0x4002_3874;
+    // the RNG and its clock, see `rng`
+    pub const RCC_AHB2ENR     : u32 = 0x4002_3834;
+    pub const RNG_BASE        : u32 = 0x5006_0800;
 }
 
 pub struct FlashWriterEraser {
@@ -31,6 +74,22 @@ impl FlashWriterEraser {
             nvm: Peripherals::take().unwrap().FLASH,
         }
     }
+
+    /// Programs the option control register i.e. the option bytes
+    fn program_optcr(&self, optcr: u32) {
+        let optcr = optcr & !(OPTCR_OPTLOCK | OPTCR_OPTSTRT);
+        unsafe {
+            // unlock the option bytes
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY1);
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY2);
+            while self.nvm.sr.read().bsy().bit() {}
+            write_volatile(FLASH_OPTCR as *mut u32, optcr);
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTSTRT);
+            while self.nvm.sr.read().bsy().bit() {}
+            // lock the option bytes
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTLOCK);
+        }
+    }
 }
 
 impl FlashInterface for FlashWriterEraser {
@@ -56,7 +115,7 @@ impl FlashInterface for FlashWriterEraser {
     ///
     /// Returns:
     /// -  NONE
-    fn hal_flash_erase(&self, addr: usize, len: usize) {
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
         let mut sec: u8 = 0;
         let mut flag: bool = true;
         let address = addr as u32;
@@ -74,25 +133,12 @@ impl FlashInterface for FlashWriterEraser {
 
         if flag {
             self.hal_flash_unlock();
-            // Erase page starting at addr
-            #[rustfmt::skip]
-            self.nvm.cr.modify(|_, w| unsafe {
-                w
-                    // start
-                    .strt().set_bit()
-                    .psize().bits(PSIZE_X8)
-                    // sector number
-                    .snb().bits(sec)
-                    // sectore erase
-                    .ser().set_bit()
-                    // no programming
-                    .pg().clear_bit()
-            });
-            // Wait until erasing is done
-            while self.nvm.sr.read().bsy().bit() {}
+            // Erase the sector and wait until it's done, from RAM i.e. without reading the bank
+            unsafe { super::f4_erase_sector(sec) };
             //Lock the FLASH
             self.hal_flash_lock();
         }
+        Ok(())
     }
 
     /// This method is used to unlock the flash
@@ -107,18 +153,76 @@ impl FlashInterface for FlashWriterEraser {
         self.nvm.keyr.write(|w| unsafe { w.key().bits(UNLOCKKEY2) });
     }
 
+    /// This method is used to write-protect a region of flash
+    ///
+    /// Sectors are protected by clearing their `nWRP` bits in the option bytes. Option bytes are
+    /// non-volatile, so they're only programmed if a sector isn't already protected.
+    ///
+    /// Method arguments:
+    /// -   addr: start address of the region to be protected
+    /// -   len : number of bytes to be protected
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_protect(&self, addr: usize, len: usize) {
+        let sectors = protected_sectors(&FLASH_SECTORS, addr, len);
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        // a cleared `nWRP` bit means the sector is already write-protected
+        let unprotected = (optcr >> OPTCR_NWRP_SHIFT) & sectors;
+        if unprotected == 0 {
+            return;
+        }
+        self.program_optcr(optcr & !(unprotected << OPTCR_NWRP_SHIFT));
+    }
+
+    /// This method returns the current read-out protection (RDP) level
+    ///
+    /// Method arguments:
+    /// -   NONE
+    /// Returns:
+    /// -  the RDP level, as a `DebugProtection` level
+    fn hal_debug_protection(&self) -> DebugProtection {
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        match (optcr >> OPTCR_RDP_SHIFT) & 0xff {
+            RDP_LEVEL_0 => DebugProtection::Disabled,
+            RDP_LEVEL_2 => DebugProtection::Permanent,
+            _ => DebugProtection::Enabled,
+        }
+    }
+
+    /// This method is used to raise the read-out protection (RDP) level
+    ///
+    /// **note:** RDP level 2 is irreversible, it also locks the option bytes i.e. write-protection
+    /// must be set up before this.
+    ///
+    /// Method arguments:
+    /// -   level: the `DebugProtection` level to set
+    /// Returns:
+    /// -  NONE
+    fn hal_set_debug_protection(&self, level: DebugProtection) {
+        if level <= self.hal_debug_protection() {
+            return;
+        }
+        let rdp = match level {
+            DebugProtection::Disabled => return,
+            DebugProtection::Enabled => RDP_LEVEL_1,
+            DebugProtection::Permanent => RDP_LEVEL_2,
+        };
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        self.program_optcr((optcr & !(0xff << OPTCR_RDP_SHIFT)) | (rdp << OPTCR_RDP_SHIFT));
+    }
+
     fn hal_init() {}
 
     /// This method is to write data on flash
     ///
     /// Method arguments:
     /// -   address: It holds the address of flash where data has to be written
-    /// -   data: u8 pointer holding the holding data.
-    /// -   len :  number of bytes
+    /// -   data: the bytes to be written
     ///
     /// Returns:
     /// -  NONE
-    fn hal_flash_write(&self, address: usize, data: *const u8, len: usize) {
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        let (data, len) = (data.as_ptr(), data.len());
         let address = address as u32;
         let len = len as u32;
         let mut idx = 0u32;
@@ -184,7 +288,124 @@ impl FlashInterface for FlashWriterEraser {
         }
         //Lock the FLASH
         self.hal_flash_lock();
+        Ok(())
+    }
+}
+
+/// Sectors are erased (and words programmed) by the flash controller in the background,
+/// [`FlashInterfaceNb::hal_poll_complete`] checks `FLASH_SR.BSY`. Only the sectors in
+/// `FLASH_SECTORS` can be erased.
+impl FlashInterfaceNb for FlashWriterEraser {
+    const WRITE_SIZE: usize = 4;
+
+    fn hal_start_erase(&self, addr: usize) -> usize {
+        let (sec, end) = match sector_at(&FLASH_SECTORS, addr) {
+            Some(sector) => sector,
+            None => return usize::MAX,
+        };
+        while self.nvm.sr.read().bsy().bit() {}
+        self.hal_flash_unlock();
+        #[rustfmt::skip]
+        self.nvm.cr.modify(|_, w| unsafe {
+            w
+                // start
+                .strt().set_bit()
+                .psize().bits(PSIZE_X8)
+                // sector number
+                .snb().bits(sec)
+                // sectore erase
+                .ser().set_bit()
+                // no programming
+                .pg().clear_bit()
+        });
+        end
+    }
+
+    fn hal_start_write(&self, addr: usize, data: &[u8]) {
+        while self.nvm.sr.read().bsy().bit() {}
+        self.hal_flash_unlock();
+        // Enable FLASH Page writes
+        self.nvm.cr.modify(|_, w| unsafe {
+            w.psize()
+                .bits(PSIZE_X32)
+                // no sector erase
+                .ser()
+                .clear_bit()
+                // programming
+                .pg()
+                .set_bit()
+        });
+        let word = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]);
+        unsafe { write_volatile(addr as *mut u32, word) };
+    }
+
+    fn hal_poll_complete(&self) -> bool {
+        if self.nvm.sr.read().bsy().bit() {
+            return false;
+        }
+        self.nvm
+            .cr
+            .modify(|_, w| w.ser().clear_bit().pg().clear_bit());
+        self.hal_flash_lock();
+        true
+    }
+}
+
+/// Returns the device's factory-programmed unique ID (i.e. the 96-bit unique device ID).
+pub fn device_uid() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(UID_ADDR as *const u8, UID_LEN) }
+}
+
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Returns true if `boot_pin` is held, see `rustBoot_hal::boot_pin_held`.
+pub fn boot_pin_held(boot_pin: &crate::boot_pin::BootPin) -> bool {
+    crate::boot_pin::sample(boot_pin, read_pin, RESET_CLOCK_HZ)
+}
+
+fn read_pin(port: u8, pin: u8, pull_up: bool) -> bool {
+    super::read_gpio(
+        RCC_GPIO_ENR,
+        RCC_GPIOA_EN_BIT,
+        GPIOA_BASE,
+        port,
+        pin,
+        pull_up,
+    )
+}
+
+/// Returns the RNG, see `rustBoot_hal::entropy`.
+#[cfg(feature = "entropy")]
+pub const fn rng() -> super::Rng {
+    super::Rng::at(RCC_AHB2ENR, RNG_BASE, None)
+}
+
+/// Returns the RTC's calendar time, see `rustBoot_hal::rtc_time`.
+pub fn rtc_time() -> Option<u64> {
+    super::read_rtc(RTC_BASE)
+}
+
+/// Reads the RTC's backup registers, see `rustBoot_hal::read_backup_regs`.
+pub fn read_backup_regs<const N: usize>() -> [u32; N] {
+    super::read_backup_regs(RTC_BASE)
+}
+
+/// Writes the RTC's backup registers, see `rustBoot_hal::write_backup_regs`.
+pub fn write_backup_regs(regs: &[u32]) {
+    unsafe {
+        let reg = core::ptr::read_volatile(RCC_APB1ENR as *const u32);
+        core::ptr::write_volatile(RCC_APB1ENR as *mut u32, reg | RCC_PWR_EN);
     }
+    super::write_backup_regs(RTC_BASE, PWR_CR, regs)
+}
+
+/// Returns (and clears) the last reset's cause, see `rustBoot_hal::boot_reason`.
+#[cfg(feature = "boot-reason")]
+pub fn take_reset_cause() -> rustBoot::bootreason::ResetCause {
+    super::take_reset_cause(RCC_CSR, super::RCC_CSR_RMVF, &super::RCC_CSR_CAUSES)
 }
 
 struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
@@ -230,6 +451,8 @@ pub fn boot_from(fw_base_address: usize) -> ! {
         *((fw_base_address + 4) as *const u32)).0;
        let jump_vector = core::mem::transmute::<usize, extern "C" fn() -> !>(rv as usize);
        (*scb).vtor.write(address);
+       #[cfg(feature = "hide-bootloader")]
+       crate::mpu::boot(sp, rv);
        cortex_m::register::msp::write(sp);
        jump_vector();
     
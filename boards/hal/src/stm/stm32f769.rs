@@ -0,0 +1,468 @@
+//! Flash  Read, Write and Erase opration for `stm32f769ni` i.e. the STM32F769I-DISCO.
+
+use stm32f7xx_hal as hal;
+
+use crate::{
+    protected_sectors, sector_at, DebugProtection, FlashError, FlashInterface, FlashInterfaceNb,
+};
+use core::ptr::{read_volatile, write_volatile};
+use core::slice::from_raw_parts;
+
+use hal::pac::{Peripherals, FLASH};
+use stm32f769ni_constants::*;
+
+#[rustfmt::skip]
+mod stm32f769ni_constants {
+    pub const FLASH_PAGE_SIZE : u32 = 0x40000;   // 1 sector size = 256KB
+    pub const STACK_LOW       : u32 = 0x2000_0000;
+    pub const STACK_UP        : u32 = 0x2008_0000;
+    pub const RB_HDR_SIZE     : u32 = 0x100;
+    pub const BASE_ADDR       : u32 = 0x08040000;   //  sector 5 starting address
+    pub const VTR_TABLE_SIZE  : u32 = 0x100;
+    // the firmware's reset handler follows its vector table i.e. 16 + 110 vectors
+    // the 96-bit unique device ID
+    pub const UID_ADDR        : u32 = 0x1FF0_F420;
+    pub const UID_LEN         : usize = 12;
+    pub const FW_RESET_VTR    : u32 = BASE_ADDR + RB_HDR_SIZE + VTR_TABLE_SIZE + 0xF9;
+    pub const UNLOCKKEY1      : u32 = 0x45670123;
+    pub const UNLOCKKEY2      : u32 = 0xCDEF89AB;
+    pub const FLASH_OPTKEYR   : u32 = 0x4002_3C08;
+    pub const FLASH_OPTCR     : u32 = 0x4002_3C14;
+    pub const OPTKEY1         : u32 = 0x0819_2A3B;
+    pub const OPTKEY2         : u32 = 0x4C5D_6E7F;
+    pub const OPTCR_OPTLOCK   : u32 = 1 << 0;
+    pub const OPTCR_OPTSTRT   : u32 = 1 << 1;
+    pub const OPTCR_NWRP_SHIFT: u32 = 16;
+    pub const OPTCR_RDP_SHIFT : u32 = 8;
+    pub const RDP_LEVEL_0     : u32 = 0xAA;
+    pub const RDP_LEVEL_1     : u32 = 0x55;
+    pub const RDP_LEVEL_2     : u32 = 0xCC;
+    // (start address, size) of the sectors covered by `nWRP`, with the flash in its (default)
+    // single-bank mode
+    pub const FLASH_SECTORS   : [(usize, usize); 12] = [
+        (0x0800_0000, 0x8000),
+        (0x0800_8000, 0x8000),
+        (0x0801_0000, 0x8000),
+        (0x0801_8000, 0x8000),
+        (0x0802_0000, 0x20000),
+        (0x0804_0000, 0x40000),
+        (0x0808_0000, 0x40000),
+        (0x080C_0000, 0x40000),
+        (0x0810_0000, 0x40000),
+        (0x0814_0000, 0x40000),
+        (0x0818_0000, 0x40000),
+        (0x081C_0000, 0x40000),
+    ];
+    // the boot pin's GPIO port and its clock, see `boot_pin_held`. The core runs off the HSI
+    // (i.e. at 16MHz) out of reset.
+    pub const RCC_GPIO_ENR    : u32 = 0x4002_3830;
+    pub const RCC_GPIOA_EN_BIT: u32 = 0;
+    pub const GPIOA_BASE      : u32 = 0x4002_0000;
+    pub const RESET_CLOCK_HZ  : u32 = 16_000_000;
+    pub const RTC_BASE        : u32 = 0x4000_2800;
+    // the power controller (i.e. backup-domain write access) and its clock
+    pub const PWR_CR          : u32 = 0x4000_7000;
+    pub const RCC_APB1ENR     : u32 = 0x4002_3840;
+    pub const RCC_PWR_EN      : u32 = 1 << 28;
+    // the reset flags, see `take_reset_cause`
+    pub const RCC_CSR         : u32 = 0x4002_3874;
+    // the RNG and its clock, see `rng`
+    pub const RCC_AHB2ENR     : u32 = 0x4002_3834;
+    pub const RNG_BASE        : u32 = 0x5006_0800;
+}
+
+/// Constrained FLASH peripheral
+pub struct FlashWriterEraser {
+    pub nvm: FLASH,
+}
+
+impl FlashWriterEraser {
+    pub fn new() -> Self {
+        FlashWriterEraser {
+            nvm: Peripherals::take().unwrap().FLASH,
+        }
+    }
+
+    /// Programs the option control register i.e. the option bytes
+    fn program_optcr(&self, optcr: u32) {
+        let optcr = optcr & !(OPTCR_OPTLOCK | OPTCR_OPTSTRT);
+        unsafe {
+            // unlock the option bytes
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY1);
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY2);
+            while self.nvm.sr.read().bsy().bit() {}
+            write_volatile(FLASH_OPTCR as *mut u32, optcr);
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTSTRT);
+            while self.nvm.sr.read().bsy().bit() {}
+            // lock the option bytes
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTLOCK);
+        }
+    }
+}
+
+impl FlashInterface for FlashWriterEraser {
+    /// Write data at the specified address
+    ///
+    /// Arguments:
+    /// -   address: It holds the address of flash where data has to be written
+    /// -   data: the bytes to be written
+    ///
+    /// Return:
+    /// -  NONE
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        let (data, len) = (data.as_ptr(), data.len());
+        let mut data1 = unsafe { from_raw_parts((data as *mut u8), len) };
+
+        // Ensure no effective write, erase or option byte change operation is ongoing
+        while self.nvm.sr.read().bsy().bit() {}
+
+        // Unlock the FLASH_CR register.
+        self.hal_flash_unlock();
+
+        let addr = address as *mut u32;
+
+        // Set parallelism to write in 8 bit chunks, and enable programming.
+        self.nvm
+            .cr
+            .write(|w| w.lock().unlocked().psize().psize8().pg().program());
+
+        for idx in 0..(len + 1) {
+            if idx == len {
+                let mut offset = idx - 1;
+
+                let word: u8 = (data1[offset]);
+
+                let write_address = ((address as u32) + offset as u32) as *mut u8;
+
+                unsafe { core::ptr::write_volatile(write_address, word) };
+                cortex_m::asm::delay(4);
+            } else {
+                let offset = idx;
+
+                let word: u8 = (data1[offset]);
+
+                let write_address = ((address as u32) + offset as u32) as *mut u8;
+
+                unsafe { core::ptr::write_volatile(write_address, word) };
+                cortex_m::asm::dmb();
+                cortex_m::asm::delay(4);
+            }
+
+            let sr = self.nvm.sr.read();
+        }
+        // Cleanup by clearing the PG bit
+        self.nvm.cr.modify(|_, w| w.pg().clear_bit());
+        // Lock the FLASH_CR register
+        self.hal_flash_lock();
+        self.hal_post_erase_barrier(address, len);
+        Ok(())
+    }
+
+    /// Erase the sector of a given address
+    ///
+    /// Arguments:
+    /// -   addr: Address where data has to be erased
+    /// -   len :  number of bytes to be erased
+    ///
+    /// Return:
+    /// -  NONE
+
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        if let Some((sec, _)) = sector_at(&FLASH_SECTORS, addr) {
+            self.hal_flash_unlock();
+
+            cortex_m::asm::delay(8000000);
+            #[rustfmt::skip]
+            self.nvm.cr.modify(|_, w| unsafe {
+                w
+                    // start
+                    .strt().set_bit()
+                    .psize().psize8()
+                    // sector number
+                    .snb().bits(sec)
+                    // sectore erase
+                    .ser().set_bit()
+                    // no programming
+                    .pg().clear_bit()
+            });
+
+            self.nvm.cr.modify(|_, w| w.strt().start());
+            cortex_m::asm::delay(8000000);
+            // Wait until erasing is done
+            while self.nvm.sr.read().bsy().bit_is_set() {}
+            let sr = self.nvm.sr.read();
+            if sr.wrperr().bit_is_set() {
+                self.nvm.sr.modify(|_, w| w.wrperr().clear_bit());
+            }
+            self.nvm.cr.modify(|_, w| w.ser().clear_bit());
+            //Lock the FLASH
+            self.hal_flash_lock();
+            let (start, size) = FLASH_SECTORS[sec as usize];
+            self.hal_post_erase_barrier(start, size);
+        }
+        Ok(())
+    }
+
+    /// Locks the flash memory.
+    ///
+    /// Once the flash is locked no operation on flash can be perfomed.
+    ///
+    /// Arguments:
+    /// -  NONE
+    ///
+    /// Return:
+    /// -  NONE
+    fn hal_flash_lock(&self) {
+        self.nvm.cr.modify(|_, w| w.lock().set_bit());
+    }
+
+    /// Unlocks the flash memory.
+    ///
+    /// Flash has to be unlocked to do any operation on it.
+    ///
+    /// Arguments:
+    /// -   NONE
+    ///
+    /// Return:
+    /// -  NONE
+    fn hal_flash_unlock(&self) {
+        self.nvm.keyr.write(|w| unsafe { w.key().bits(UNLOCKKEY1) });
+        self.nvm.keyr.write(|w| unsafe { w.key().bits(UNLOCKKEY2) });
+    }
+
+    /// This method is used to write-protect a region of flash
+    ///
+    /// Sectors are protected by clearing their `nWRP` bits in the option bytes. Option bytes are
+    /// non-volatile, so they're only programmed if a sector isn't already protected.
+    ///
+    /// Method arguments:
+    /// -   addr: start address of the region to be protected
+    /// -   len : number of bytes to be protected
+    /// Returns:
+    /// -  NONE
+    fn hal_flash_protect(&self, addr: usize, len: usize) {
+        let sectors = protected_sectors(&FLASH_SECTORS, addr, len);
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        // a cleared `nWRP` bit means the sector is already write-protected
+        let unprotected = (optcr >> OPTCR_NWRP_SHIFT) & sectors;
+        if unprotected == 0 {
+            return;
+        }
+        self.program_optcr(optcr & !(unprotected << OPTCR_NWRP_SHIFT));
+    }
+
+    /// This method returns the current read-out protection (RDP) level
+    ///
+    /// Method arguments:
+    /// -   NONE
+    /// Returns:
+    /// -  the RDP level, as a `DebugProtection` level
+    fn hal_debug_protection(&self) -> DebugProtection {
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        match (optcr >> OPTCR_RDP_SHIFT) & 0xff {
+            RDP_LEVEL_0 => DebugProtection::Disabled,
+            RDP_LEVEL_2 => DebugProtection::Permanent,
+            _ => DebugProtection::Enabled,
+        }
+    }
+
+    /// This method is used to raise the read-out protection (RDP) level
+    ///
+    /// **note:** RDP level 2 is irreversible, it also locks the option bytes i.e. write-protection
+    /// must be set up before this.
+    ///
+    /// Method arguments:
+    /// -   level: the `DebugProtection` level to set
+    /// Returns:
+    /// -  NONE
+    fn hal_set_debug_protection(&self, level: DebugProtection) {
+        if level <= self.hal_debug_protection() {
+            return;
+        }
+        let rdp = match level {
+            DebugProtection::Disabled => return,
+            DebugProtection::Enabled => RDP_LEVEL_1,
+            DebugProtection::Permanent => RDP_LEVEL_2,
+        };
+        let optcr = unsafe { read_volatile(FLASH_OPTCR as *const u32) };
+        self.program_optcr((optcr & !(0xff << OPTCR_RDP_SHIFT)) | (rdp << OPTCR_RDP_SHIFT));
+    }
+
+    /// Drops the ART accelerator's and the L1 caches' copies of the flash at `addr` i.e. after an
+    /// erase or a write.
+    ///
+    /// Arguments:
+    /// -   addr: start address of the erased or written region
+    /// -   len : number of bytes erased or written
+    ///
+    /// Return:
+    /// -  NONE
+    fn hal_post_erase_barrier(&self, addr: usize, len: usize) {
+        super::f7_art_reset();
+        super::m7_flash_barrier(addr, len);
+    }
+
+    fn hal_init() {}
+}
+
+/// Sectors are erased (and bytes programmed) by the flash controller in the background,
+/// [`FlashInterfaceNb::hal_poll_complete`] checks `FLASH_SR.BSY`. Only the sectors in
+/// `FLASH_SECTORS` can be erased.
+impl FlashInterfaceNb for FlashWriterEraser {
+    const WRITE_SIZE: usize = 1;
+
+    fn hal_start_erase(&self, addr: usize) -> usize {
+        let (sec, end) = match sector_at(&FLASH_SECTORS, addr) {
+            Some(sector) => sector,
+            None => return usize::MAX,
+        };
+        while self.nvm.sr.read().bsy().bit() {}
+        self.hal_flash_unlock();
+        #[rustfmt::skip]
+        self.nvm.cr.modify(|_, w| unsafe {
+            w
+                // start
+                .strt().set_bit()
+                .psize().psize8()
+                // sector number
+                .snb().bits(sec)
+                // sectore erase
+                .ser().set_bit()
+                // no programming
+                .pg().clear_bit()
+        });
+        end
+    }
+
+    fn hal_start_write(&self, addr: usize, data: &[u8]) {
+        while self.nvm.sr.read().bsy().bit() {}
+        self.hal_flash_unlock();
+        // Set parallelism to write in 8 bit chunks, and enable programming.
+        self.nvm
+            .cr
+            .write(|w| w.lock().unlocked().psize().psize8().pg().program());
+        unsafe { write_volatile(addr as *mut u8, data[0]) };
+        cortex_m::asm::dmb();
+    }
+
+    fn hal_poll_complete(&self) -> bool {
+        if self.nvm.sr.read().bsy().bit() {
+            return false;
+        }
+        self.nvm
+            .cr
+            .modify(|_, w| w.ser().clear_bit().pg().clear_bit());
+        self.hal_flash_lock();
+        true
+    }
+}
+
+pub fn preboot() {}
+
+/// Returns the device's factory-programmed unique ID (i.e. the 96-bit unique device ID).
+pub fn device_uid() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(UID_ADDR as *const u8, UID_LEN) }
+}
+
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Returns true if `boot_pin` is held, see `rustBoot_hal::boot_pin_held`.
+pub fn boot_pin_held(boot_pin: &crate::boot_pin::BootPin) -> bool {
+    crate::boot_pin::sample(boot_pin, read_pin, RESET_CLOCK_HZ)
+}
+
+fn read_pin(port: u8, pin: u8, pull_up: bool) -> bool {
+    super::read_gpio(
+        RCC_GPIO_ENR,
+        RCC_GPIOA_EN_BIT,
+        GPIOA_BASE,
+        port,
+        pin,
+        pull_up,
+    )
+}
+
+/// Returns the RNG, see `rustBoot_hal::entropy`.
+#[cfg(feature = "entropy")]
+pub const fn rng() -> super::Rng {
+    super::Rng::at(RCC_AHB2ENR, RNG_BASE, None)
+}
+
+/// Returns the RTC's calendar time, see `rustBoot_hal::rtc_time`.
+pub fn rtc_time() -> Option<u64> {
+    super::read_rtc(RTC_BASE)
+}
+
+/// Reads the RTC's backup registers, see `rustBoot_hal::read_backup_regs`.
+pub fn read_backup_regs<const N: usize>() -> [u32; N] {
+    super::read_backup_regs(RTC_BASE)
+}
+
+/// Writes the RTC's backup registers, see `rustBoot_hal::write_backup_regs`.
+pub fn write_backup_regs(regs: &[u32]) {
+    unsafe {
+        let reg = core::ptr::read_volatile(RCC_APB1ENR as *const u32);
+        core::ptr::write_volatile(RCC_APB1ENR as *mut u32, reg | RCC_PWR_EN);
+    }
+    super::write_backup_regs(RTC_BASE, PWR_CR, regs)
+}
+
+/// Returns (and clears) the last reset's cause, see `rustBoot_hal::boot_reason`.
+#[cfg(feature = "boot-reason")]
+pub fn take_reset_cause() -> rustBoot::bootreason::ResetCause {
+    super::take_reset_cause(RCC_CSR, super::RCC_CSR_RMVF, &super::RCC_CSR_CAUSES)
+}
+
+struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
+
+impl<const MIN: u32, const MAX: u32, const VAL: u32> RefinedUsize<MIN, MAX, VAL> {
+    /// This method is used to check the address bound of stack pointer
+    ///
+    /// Method arguments:
+    /// -   i : starting address of stack  
+    /// Returns:
+    /// -  It returns u32 address of stack pointer
+    pub fn bounded_int(i: u32) -> Self {
+        assert!(i >= MIN && i <= MAX);
+        RefinedUsize(i)
+    }
+    /// This method is used to check the address of reset pointer
+    ///
+    /// Method arguments:
+    /// -   i : starting address of reset  
+    /// Returns:
+    /// -  It returns u32 address of reset pointer
+    pub fn single_valued_int(i: u32) -> Self {
+        assert!(i == VAL);
+        RefinedUsize(i)
+    }
+}
+
+/// This method is used to boot the firmware from a particular address
+    ///
+    /// Method arguments:
+    /// -   fw_base_address  : address of the firmware
+    /// Returns:
+    /// -  NONE
+#[rustfmt::skip]
+pub fn boot_from(fw_base_address: usize) -> ! {
+       let address = fw_base_address as u32;
+       let scb = hal::pac::SCB::ptr();
+       unsafe {
+       let sp = RefinedUsize::<STACK_LOW, STACK_UP, 0>::bounded_int(
+        *(fw_base_address as *const u32)).0;
+       let rv = RefinedUsize::<0, 0, FW_RESET_VTR>::single_valued_int(
+        *((fw_base_address + 4) as *const u32)).0;
+       let jump_vector = core::mem::transmute::<usize, extern "C" fn() -> !>(rv as usize);
+       (*scb).vtor.write(address);
+       #[cfg(feature = "hide-bootloader")]
+       crate::mpu::boot(sp, rv);
+       cortex_m::register::msp::write(sp);
+       jump_vector();
+    
+       }
+       loop{}
+}
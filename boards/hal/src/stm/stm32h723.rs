@@ -2,12 +2,18 @@
 
 use core::convert::TryInto;
 use core::slice::from_raw_parts;
-use core::{ops::Add, ptr::write_volatile};
+use core::{
+    ops::Add,
+    ptr::{read_volatile, write_volatile},
+};
 
 use hal::{pac, pac::FLASH};
 use stm32h7xx_hal as hal;
 
-use crate::FlashInterface;
+#[cfg(feature = "boot-reason")]
+use rustBoot::bootreason::ResetCause;
+
+use crate::{protected_sectors, DebugProtection, FlashError, FlashInterface};
 use stm32h723zg_constants::*;
 
 #[rustfmt::skip]
@@ -27,6 +33,9 @@ mod stm32h723zg_constants {
     pub const RB_HDR_SIZE     : u32 = 0x100;
     pub const BASE_ADDR       : u32 = 0x08020000;
     pub const VTR_TABLE_SIZE  : u32 = 0x100;
+    // the 96-bit unique device ID
+    pub const UID_ADDR        : u32 = 0x1FF1_E800;
+    pub const UID_LEN         : usize = 12;
     pub const FW_RESET_VTR    : u32 = BASE_ADDR + RB_HDR_SIZE + VTR_TABLE_SIZE + 0x19D;
 
     pub const UNLOCKKEY1  : u32 = 0x45670123;
@@ -34,8 +43,76 @@ mod stm32h723zg_constants {
     pub const PSIZE_X8    : u8 = 0b00;
     pub const PSIZE_X32   : u8 = 0b10;
     pub const KB          : u32 = 1024;
+
+    pub const FLASH_OPTKEYR     : u32 = 0x5200_2008;
+    pub const FLASH_OPTCR       : u32 = 0x5200_2018;
+    pub const FLASH_OPTSR_CUR   : u32 = 0x5200_201C;
+    pub const FLASH_OPTSR_PRG   : u32 = 0x5200_2020;
+    pub const FLASH_WPSN_CUR1R  : u32 = 0x5200_2038;
+    pub const FLASH_WPSN_PRG1R  : u32 = 0x5200_203C;
+    pub const OPTKEY1           : u32 = 0x0819_2A3B;
+    pub const OPTKEY2           : u32 = 0x4C5D_6E7F;
+    pub const OPTCR_OPTLOCK     : u32 = 1 << 0;
+    pub const OPTCR_OPTSTART    : u32 = 1 << 1;
+    pub const OPTSR_OPT_BUSY    : u32 = 1 << 0;
+    pub const OPTSR_RDP_SHIFT   : u32 = 8;
+    pub const RDP_LEVEL_0       : u32 = 0xAA;
+    pub const RDP_LEVEL_1       : u32 = 0x55;
+    pub const RDP_LEVEL_2       : u32 = 0xCC;
+    // (start address, size) of the sectors covered by `WRPSN`
+    pub const FLASH_SECTORS     : [(usize, usize); 8] = [
+        (0x0800_0000, 0x20000),
+        (0x0802_0000, 0x20000),
+        (0x0804_0000, 0x20000),
+        (0x0806_0000, 0x20000),
+        (0x0808_0000, 0x20000),
+        (0x080A_0000, 0x20000),
+        (0x080C_0000, 0x20000),
+        (0x080E_0000, 0x20000),
+    ];
+    // the boot pin's GPIO port and its clock, see `boot_pin_held`. The core runs off the HSI
+    // (i.e. at 64MHz) out of reset.
+    pub const RCC_GPIO_ENR    : u32 = 0x5802_44E0;
+    pub const RCC_GPIOA_EN_BIT: u32 = 0;
+    pub const GPIOA_BASE      : u32 = 0x5802_0000;
+    pub const RESET_CLOCK_HZ  : u32 = 64_000_000;
+    pub const RTC_BASE        : u32 = 0x5800_4000;
+    pub const RCC_APB4ENR     : u32 = 0x5802_44F4;
+    pub const RCC_RTCAPB_EN   : u32 = 1 << 16;
+    // the power controller i.e. backup-domain write access
+    pub const PWR_CR1         : u32 = 0x5802_4800;
+    // the reset flags, see `take_reset_cause`
+    pub const RCC_RSR         : u32 = 0x5802_44D0;
+    pub const RSR_RMVF        : u32 = 1 << 16;
+    // ECC error flags, see `hal_flash_read`
+    pub const FLASH_SR1       : u32 = 0x5200_2010;
+    pub const FLASH_CCR1      : u32 = 0x5200_2014;
+    pub const SR_SNECCERR     : u32 = 1 << 25;
+    pub const SR_DBECCERR     : u32 = 1 << 26;
+    // the RNG and its clock, see `rng`
+    pub const RCC_AHB2ENR     : u32 = 0x5802_44DC;
+    pub const RNG_BASE        : u32 = 0x4802_1800;
+    pub const RCC_CR          : u32 = 0x5802_4400;
+    // SYSCLK's source and the supply's status, see `FLASH_CLOCK`
+    pub const RCC_CFGR        : u32 = 0x5802_4410;
+    pub const PWR_CSR1        : u32 = 0x5802_4804;
 }
 
+/// SYSCLK runs off the HSI (i.e. at 64MHz) during erases and writes, see `stm::FlashClock`.
+#[cfg(feature = "flash-clock")]
+static FLASH_CLOCK: super::FlashClock = super::FlashClock {
+    rcc_cr: RCC_CR,
+    hsirdy: 1 << 2,
+    rcc_cfgr: RCC_CFGR,
+    sw_bits: 3,
+    pwr_cr1: PWR_CR1,
+    pwr_csr1: PWR_CSR1,
+    pvdo: 1 << 4,
+    // `ACTVOSRDY`
+    vosrdy: 1 << 13,
+    pwr_en: None,
+};
+
 /// Constrained FLASH peripheral
 pub struct FlashWriterEraser {
     pub nvm: FLASH,
@@ -47,19 +124,40 @@ impl FlashWriterEraser {
             nvm: pac::Peripherals::take().unwrap().FLASH,
         }
     }
+
+    /// Writes `val` to the option byte program register `reg` and starts option byte programming
+    fn program_option_bytes(&self, reg: u32, val: u32) {
+        unsafe {
+            // unlock the option bytes
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY1);
+            write_volatile(FLASH_OPTKEYR as *mut u32, OPTKEY2);
+            while read_volatile(FLASH_OPTSR_CUR as *const u32) & OPTSR_OPT_BUSY != 0 {}
+            write_volatile(reg as *mut u32, val);
+            let optcr = read_volatile(FLASH_OPTCR as *const u32);
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTSTART);
+            while read_volatile(FLASH_OPTSR_CUR as *const u32) & OPTSR_OPT_BUSY != 0 {}
+            // lock the option bytes
+            let optcr = read_volatile(FLASH_OPTCR as *const u32);
+            write_volatile(FLASH_OPTCR as *mut u32, optcr | OPTCR_OPTLOCK);
+        }
+    }
 }
 
 impl FlashInterface for FlashWriterEraser {
+    const PROGRAM_ONCE: bool = true;
+
     /// Write data at the specified address
     ///
     /// Arguments:
     /// -   address: It holds the address of flash where data has to be written
-    /// -   data: u8 pointer holding the holding data.
-    /// -   len :  number of bytes
+    /// -   data: the bytes to be written
     ///
     /// Return:
     /// -  NONE
-    fn hal_flash_write(&self, addr: usize, data: *const u8, len: usize) {
+    fn hal_flash_write(&self, addr: usize, data: &[u8]) -> Result<(), FlashError> {
+        #[cfg(feature = "flash-clock")]
+        let _clock = FLASH_CLOCK.slow_down().ok_or(FlashError::WriteFailed)?;
+        let (data, len) = (data.as_ptr(), data.len());
         let mut i = 0u32;
         let mut ii = 0u32;
 
@@ -141,11 +239,11 @@ impl FlashInterface for FlashWriterEraser {
                 // ensures that the sector is always erased before each write.
                 if stm32h7_boot_flag_page(addr as u32) {
                     self.hal_flash_lock();
-                    self.hal_flash_erase((STM32H7_PART_BOOT_FLAGS_PAGE_ADDRESS as usize), 1);
+                    self.hal_flash_erase((STM32H7_PART_BOOT_FLAGS_PAGE_ADDRESS as usize), 1)?;
                     self.hal_flash_unlock();
                 } else if stm32h7_update_flag_page(addr as u32) {
                     self.hal_flash_lock();
-                    self.hal_flash_erase((STM32H7_PART_UPDATE_FLAGS_PAGE_ADDRESS as usize), 1);
+                    self.hal_flash_erase((STM32H7_PART_UPDATE_FLAGS_PAGE_ADDRESS as usize), 1)?;
                     self.hal_flash_unlock();
                 }
 
@@ -192,6 +290,8 @@ impl FlashInterface for FlashWriterEraser {
             // Lock the FLASH_CR register
             self.hal_flash_lock();
         }
+        self.hal_post_erase_barrier(addr, len);
+        Ok(())
     }
 
     /// Erase the sector of a given address
@@ -202,7 +302,9 @@ impl FlashInterface for FlashWriterEraser {
     ///
     /// Return:
     /// -  NONE
-    fn hal_flash_erase(&self, addr: usize, len: usize) {
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {
+        #[cfg(feature = "flash-clock")]
+        let _clock = FLASH_CLOCK.slow_down().ok_or(FlashError::EraseFailed)?;
         let mut sec: u8 = 0;
         let mut flag: bool = true;
         let address = addr as u32;
@@ -245,7 +347,10 @@ impl FlashInterface for FlashWriterEraser {
 
             //Unlock the FLASH_CR register
             self.hal_flash_lock();
+            let (start, size) = FLASH_SECTORS[sec as usize];
+            self.hal_post_erase_barrier(start, size);
         }
+        Ok(())
     }
 
     /// Locks the flash memory.
@@ -284,10 +389,186 @@ impl FlashInterface for FlashWriterEraser {
             .write(|w| unsafe { w.bits(FLASH_KEY2) });
     }
 
+    /// Write-protect a region of flash
+    ///
+    /// Sectors are protected by clearing their `WRPSn` bits in the option bytes. Option bytes are
+    /// non-volatile, so they're only programmed if a sector isn't already protected.
+    ///
+    /// Arguments:
+    /// -   addr: start address of the region to be protected
+    /// -   len : number of bytes to be protected
+    ///
+    /// Return:
+    /// -   NONE
+    fn hal_flash_protect(&self, addr: usize, len: usize) {
+        let sectors = protected_sectors(&FLASH_SECTORS, addr, len);
+        let wpsn = unsafe { read_volatile(FLASH_WPSN_CUR1R as *const u32) };
+        // a cleared `WRPSn` bit means the sector is already write-protected
+        let unprotected = wpsn & sectors;
+        if unprotected == 0 {
+            return;
+        }
+        self.program_option_bytes(FLASH_WPSN_PRG1R, wpsn & !unprotected);
+    }
+
+    /// Returns the current read-out protection (RDP) level
+    ///
+    /// Arguments:
+    /// -   NONE
+    ///
+    /// Return:
+    /// -   the RDP level, as a `DebugProtection` level
+    fn hal_debug_protection(&self) -> DebugProtection {
+        let optsr = unsafe { read_volatile(FLASH_OPTSR_CUR as *const u32) };
+        match (optsr >> OPTSR_RDP_SHIFT) & 0xff {
+            RDP_LEVEL_0 => DebugProtection::Disabled,
+            RDP_LEVEL_2 => DebugProtection::Permanent,
+            _ => DebugProtection::Enabled,
+        }
+    }
+
+    /// Raise the read-out protection (RDP) level
+    ///
+    /// **note:** RDP level 2 is irreversible, it also locks the option bytes i.e. write-protection
+    /// must be set up before this.
+    ///
+    /// Arguments:
+    /// -   level: the `DebugProtection` level to set
+    ///
+    /// Return:
+    /// -   NONE
+    fn hal_set_debug_protection(&self, level: DebugProtection) {
+        if level <= self.hal_debug_protection() {
+            return;
+        }
+        let rdp = match level {
+            DebugProtection::Disabled => return,
+            DebugProtection::Enabled => RDP_LEVEL_1,
+            DebugProtection::Permanent => RDP_LEVEL_2,
+        };
+        let optsr = unsafe { read_volatile(FLASH_OPTSR_CUR as *const u32) };
+        self.program_option_bytes(
+            FLASH_OPTSR_PRG,
+            (optsr & !(0xff << OPTSR_RDP_SHIFT)) | (rdp << OPTSR_RDP_SHIFT),
+        );
+    }
+
+    /// Reads flash at the specified address
+    ///
+    /// Flash is read with `ecc::read` i.e. a double-bit ECC error (which raises a bus fault)
+    /// fails the read, instead of faulting.
+    ///
+    /// Arguments:
+    /// -   addr: address of flash to be read
+    /// -   data: buffer the bytes are read into
+    ///
+    /// Return:
+    /// -   `FlashError::ReadFailed` on an uncorrectable ECC error
+    fn hal_flash_read(&self, addr: usize, data: &mut [u8]) -> Result<(), FlashError> {
+        // the flags are cleared through `FLASH_CCR1`, at the same bit positions. Stale flags
+        // (i.e. from earlier reads) are cleared first.
+        let clear = || unsafe { write_volatile(FLASH_CCR1 as *mut u32, SR_SNECCERR | SR_DBECCERR) };
+        clear();
+        let res = crate::ecc::read(addr, data);
+        let sr = unsafe { read_volatile(FLASH_SR1 as *const u32) };
+        clear();
+        match sr & SR_DBECCERR {
+            0 => res,
+            _ => Err(FlashError::ReadFailed),
+        }
+    }
+
+    /// Drops the L1 caches' copies of the flash at `addr` i.e. after an erase or a write.
+    ///
+    /// Arguments:
+    /// -   addr: start address of the erased or written region
+    /// -   len : number of bytes erased or written
+    ///
+    /// Return:
+    /// -  NONE
+    fn hal_post_erase_barrier(&self, addr: usize, len: usize) {
+        super::m7_flash_barrier(addr, len);
+    }
+
     /// Hal initialization.
     fn hal_init() {}
 }
 
+/// Returns the device's factory-programmed unique ID (i.e. the 96-bit unique device ID).
+pub fn device_uid() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(UID_ADDR as *const u8, UID_LEN) }
+}
+
+/// Returns the RAM an image's initial stack pointer may point into, see `rustBoot_hal::ram_region`.
+pub fn ram_region() -> core::ops::Range<usize> {
+    STACK_LOW as usize..STACK_UP as usize
+}
+
+/// Returns true if `boot_pin` is held, see `rustBoot_hal::boot_pin_held`.
+pub fn boot_pin_held(boot_pin: &crate::boot_pin::BootPin) -> bool {
+    crate::boot_pin::sample(boot_pin, read_pin, RESET_CLOCK_HZ)
+}
+
+fn read_pin(port: u8, pin: u8, pull_up: bool) -> bool {
+    super::read_gpio(
+        RCC_GPIO_ENR,
+        RCC_GPIOA_EN_BIT,
+        GPIOA_BASE,
+        port,
+        pin,
+        pull_up,
+    )
+}
+
+/// Returns the RNG, see `rustBoot_hal::entropy`.
+#[cfg(feature = "entropy")]
+pub const fn rng() -> super::Rng {
+    super::Rng::at(RCC_AHB2ENR, RNG_BASE, Some(RCC_CR))
+}
+
+/// Returns the RTC's calendar time, see `rustBoot_hal::rtc_time`.
+pub fn rtc_time() -> Option<u64> {
+    enable_rtc_apb();
+    super::read_rtc(RTC_BASE)
+}
+
+/// Reads the RTC's backup registers, see `rustBoot_hal::read_backup_regs`.
+pub fn read_backup_regs<const N: usize>() -> [u32; N] {
+    enable_rtc_apb();
+    super::read_backup_regs(RTC_BASE)
+}
+
+/// Writes the RTC's backup registers, see `rustBoot_hal::write_backup_regs`.
+pub fn write_backup_regs(regs: &[u32]) {
+    enable_rtc_apb();
+    super::write_backup_regs(RTC_BASE, PWR_CR1, regs)
+}
+
+/// The reset flags in `RCC_RSR`, in order of precedence (see `super::RCC_CSR_CAUSES`).
+#[cfg(feature = "boot-reason")]
+const RCC_RSR_CAUSES: [(u32, ResetCause); 5] = [
+    // `IWDG1RSTF` and `WWDG1RSTF`
+    (1 << 26 | 1 << 28, ResetCause::Watchdog),
+    (1 << 24, ResetCause::Software),
+    (1 << 23, ResetCause::PowerOn),
+    (1 << 21, ResetCause::BrownOut),
+    (1 << 22, ResetCause::Pin),
+];
+
+/// Returns (and clears) the last reset's cause, see `rustBoot_hal::boot_reason`.
+#[cfg(feature = "boot-reason")]
+pub fn take_reset_cause() -> ResetCause {
+    super::take_reset_cause(RCC_RSR, RSR_RMVF, &RCC_RSR_CAUSES)
+}
+
+/// The RTC's register interface is clocked separately on the h7.
+fn enable_rtc_apb() {
+    unsafe {
+        let reg = core::ptr::read_volatile(RCC_APB4ENR as *const u32);
+        core::ptr::write_volatile(RCC_APB4ENR as *mut u32, reg | RCC_RTCAPB_EN);
+    }
+}
+
 struct RefinedUsize<const MIN: u32, const MAX: u32, const VAL: u32>(u32);
 
 impl<const MIN: u32, const MAX: u32, const VAL: u32> RefinedUsize<MIN, MAX, VAL> {
@@ -339,6 +620,8 @@ pub fn boot_from(fw_base_address: usize) -> ! {
             *((fw_base_address + 4) as *const u32)).0;
         let jump_vector = core::mem::transmute::<usize, extern "C" fn() -> !>(rv as usize);
         (*scb).vtor.write(address);
+        #[cfg(feature = "hide-bootloader")]
+        crate::mpu::boot(sp, rv);
         cortex_m::register::msp::write(sp);
         jump_vector();
        }
@@ -0,0 +1,36 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const BOARDS: [&str; 11] = [
+    "nrf52840",
+    "stm32f411",
+    "stm32f446",
+    "stm32f469",
+    "stm32h723",
+    "stm32f746",
+    "stm32f334",
+    "stm32f407",
+    "stm32f769",
+    "rp2040",
+    "rp2350",
+];
+
+/// The instrumented firmware is both the boot and the update image i.e. it's linked with the
+/// board's boot firmware `memory.x`, to run from the boot partition.
+fn main() {
+    let board = BOARDS
+        .iter()
+        .find(|board| env::var_os(format!("CARGO_FEATURE_{}", board.to_uppercase())).is_some())
+        .expect("no board feature enabled, see `cargo xtask hil <board>`");
+    let memory_x = PathBuf::from("../firmware")
+        .join(board)
+        .join("boot_fw_blinky_green/memory.x");
+
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy(&memory_x, out.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", memory_x.display());
+}
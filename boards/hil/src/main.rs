@@ -0,0 +1,126 @@
+//! Instrumented firmware for the hardware-in-the-loop test of the update flow, see `cargo xtask
+//! hil <board>`.
+//!
+//! It's both the boot and the update image (the host signs it with different versions) and, on
+//! every boot, reports what it booted and what it did over defmt/RTT:
+//!
+//! - `hil: booted v<version> <state>` - the boot image's version (as rustBoot reads it) and the
+//!   boot partition's state.
+//! - `hil: confirmed` or `hil: left unconfirmed` - an image booted for `testing` (i.e. freshly
+//!   swapped in) confirms itself, unless told not to. An unconfirmed image is rolled back on the
+//!   next boot.
+//! - `hil: triggered v<version>` - a newer update is staged (i.e. flashed by the host), it's
+//!   triggered i.e. swapped in on the next boot.
+//! - `hil: idle` - otherwise.
+//!
+//! What an image does is up to its [`HIL_COMMAND`] vendor TLV, so that the host can sign the same
+//! firmware (and `probe-run` decode its logs with the same defmt table) for every step. The
+//! firmware ends with a breakpoint, so that `probe-run` exits, and panics on errors.
+
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _; // global logger
+
+use cortex_m_rt::entry;
+use rustBoot::image::image::PartitionState;
+use rustBoot_update::update::{info::ImageInfo, update_flash::FlashUpdater, UpdateInterface};
+
+#[cfg(feature = "nrf52840")]
+use rustBoot_hal::nrf::nrf52840::FlashWriterEraser;
+#[cfg(feature = "rp2040")]
+use rustBoot_hal::pico::rp2040::FlashWriterEraser;
+#[cfg(feature = "rp2350")]
+use rustBoot_hal::pico::rp2350::FlashWriterEraser;
+#[cfg(feature = "stm32f334")]
+use rustBoot_hal::stm::stm32f334::FlashWriterEraser;
+#[cfg(feature = "stm32f407")]
+use rustBoot_hal::stm::stm32f407::FlashWriterEraser;
+#[cfg(feature = "stm32f411")]
+use rustBoot_hal::stm::stm32f411::FlashWriterEraser;
+#[cfg(feature = "stm32f446")]
+use rustBoot_hal::stm::stm32f446::FlashWriterEraser;
+#[cfg(feature = "stm32f469")]
+use rustBoot_hal::stm::stm32f469::FlashWriterEraser;
+#[cfg(feature = "stm32f746")]
+use rustBoot_hal::stm::stm32f746::FlashWriterEraser;
+#[cfg(feature = "stm32f769")]
+use rustBoot_hal::stm::stm32f769::FlashWriterEraser;
+#[cfg(feature = "stm32h723")]
+use rustBoot_hal::stm::stm32h723::FlashWriterEraser;
+
+/// The vendor TLV that tells an image what to do, a byte of [`CONFIRM`] and [`TRIGGER`] flags.
+/// An image without it does neither.
+const HIL_COMMAND: u16 = 0x8F00;
+/// Confirm the image, when it's booted for `testing`.
+const CONFIRM: u8 = 1 << 0;
+/// Trigger a staged update, if it's newer than the image.
+const TRIGGER: u8 = 1 << 1;
+
+#[entry]
+fn main() -> ! {
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    let boot = match updater.boot_image_info() {
+        Ok(boot) => boot,
+        Err(e) => defmt::panic!("can't read the boot image, {}", defmt::Debug2Format(&e)),
+    };
+    defmt::info!(
+        "hil: booted v{=u32} {=str}",
+        boot.version,
+        state_name(boot.state)
+    );
+
+    let command = command(&boot);
+    match boot.state {
+        PartitionState::Testing if command & CONFIRM != 0 => {
+            check(updater.update_success());
+            defmt::info!("hil: confirmed");
+        }
+        PartitionState::Testing => defmt::info!("hil: left unconfirmed"),
+        // a swap puts the update partition back in `New` i.e. the image it swapped out isn't
+        // re-triggered, as long as it's older
+        _ => match updater.update_image_info() {
+            Ok(update)
+                if command & TRIGGER != 0
+                    && update.state == PartitionState::New
+                    && update.version > boot.version =>
+            {
+                check(updater.update_trigger());
+                defmt::info!("hil: triggered v{=u32}", update.version);
+            }
+            _ => defmt::info!("hil: idle"),
+        },
+    }
+    loop {
+        cortex_m::asm::bkpt();
+    }
+}
+
+/// Returns the image's [`HIL_COMMAND`] flags.
+fn command(image: &ImageInfo) -> u8 {
+    let mut tlvs = image.vendor_tlvs;
+    tlvs.find(|tlv| tlv.typ == HIL_COMMAND)
+        .and_then(|tlv| tlv.value.first().copied())
+        .unwrap_or(0)
+}
+
+fn state_name(state: PartitionState) -> &'static str {
+    match state {
+        PartitionState::New => "new",
+        PartitionState::Updating => "updating",
+        PartitionState::Testing => "testing",
+        PartitionState::Success => "success",
+    }
+}
+
+fn check(res: rustBoot::Result<()>) {
+    if let Err(e) = res {
+        defmt::panic!("{}", defmt::Debug2Format(&e));
+    }
+}
+
+#[panic_handler] // panicking behavior
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    defmt::error!("{}", defmt::Display2Format(info));
+    cortex_m::asm::udf()
+}
@@ -0,0 +1,265 @@
+//! A flash driver self-test, see `cargo xtask selftest <board>`.
+//!
+//! It runs in place of rustBoot (i.e. from the bootloader's flash) and exercises the board's
+//! `FlashInterface` impl, reading back every write and erase:
+//!
+//! - `erase` - erases the update and swap partitions, they must read as blank.
+//! - `unaligned writes` - writes of partial words, at every offset within a word and from
+//!   unaligned buffers. The bytes around a write must stay erased.
+//! - `sector boundary` - a write straddling two sectors. Erasing the upper sector must leave the
+//!   lower one intact.
+//! - `partition edges` - writes to the first and last bytes of the update and swap partitions.
+//!   The flash right outside a partition must be left untouched.
+//!
+//! Results are reported over defmt/RTT. The self-test ends with a breakpoint if every case
+//! passes and panics otherwise, so that `probe-run` exits with an error.
+//!
+//! *Note: the update and swap partitions are erased i.e. a staged update is lost. The boot
+//! partition isn't touched.*
+
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _; // global logger
+
+use cortex_m_rt::entry;
+use rustBoot::constants::{
+    PARTITION_SIZE, SECTOR_SIZE, SWAP_PARTITION_ADDRESS, UPDATE_PARTITION_ADDRESS,
+};
+use rustBoot_hal::{FlashError, FlashInterface};
+
+#[cfg(feature = "nrf52840")]
+use rustBoot_hal::nrf::nrf52840::FlashWriterEraser;
+#[cfg(feature = "rp2040")]
+use rustBoot_hal::pico::rp2040::FlashWriterEraser;
+#[cfg(feature = "rp2350")]
+use rustBoot_hal::pico::rp2350::FlashWriterEraser;
+#[cfg(feature = "stm32f334")]
+use rustBoot_hal::stm::stm32f334::FlashWriterEraser;
+#[cfg(feature = "stm32f407")]
+use rustBoot_hal::stm::stm32f407::FlashWriterEraser;
+#[cfg(feature = "stm32f411")]
+use rustBoot_hal::stm::stm32f411::FlashWriterEraser;
+#[cfg(feature = "stm32f446")]
+use rustBoot_hal::stm::stm32f446::FlashWriterEraser;
+#[cfg(feature = "stm32f469")]
+use rustBoot_hal::stm::stm32f469::FlashWriterEraser;
+#[cfg(feature = "stm32f746")]
+use rustBoot_hal::stm::stm32f746::FlashWriterEraser;
+#[cfg(feature = "stm32f769")]
+use rustBoot_hal::stm::stm32f769::FlashWriterEraser;
+#[cfg(feature = "stm32h723")]
+use rustBoot_hal::stm::stm32h723::FlashWriterEraser;
+
+#[cfg(feature = "rp2040")]
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+#[cfg(feature = "rp2350")]
+#[link_section = ".start_block"]
+#[used]
+pub static IMAGE_DEF: rp235x_hal::block::ImageDef = rp235x_hal::block::ImageDef::secure_exe();
+
+/// Every write goes to its own (erased) slot. Slots are larger than any board's flash word (i.e.
+/// the h7's 32 bytes), so that no word is programmed twice.
+const SLOT: usize = 64;
+
+/// Write lengths, covering partial words on either side of a (4-byte) word.
+const LENGTHS: [usize; 8] = [1, 2, 3, 4, 5, 7, 13, 33];
+
+/// The number of bytes outside a partition that are checked to be untouched.
+const GUARD: usize = 16;
+
+type Case = (&'static str, fn(&FlashWriterEraser) -> Result<(), Failure>);
+
+const CASES: [Case; 4] = [
+    ("erase", erase),
+    ("unaligned writes", unaligned_writes),
+    ("sector boundary", sector_boundary),
+    ("partition edges", partition_edges),
+];
+
+enum Failure {
+    /// the driver reported an error.
+    Driver(FlashError),
+    /// the flash doesn't hold what was written (or erased).
+    Mismatch {
+        addr: usize,
+        expected: u8,
+        actual: u8,
+    },
+}
+
+impl defmt::Format for Failure {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Failure::Driver(e) => defmt::write!(f, "driver error, {}", defmt::Debug2Format(e)),
+            Failure::Mismatch {
+                addr,
+                expected,
+                actual,
+            } => defmt::write!(
+                f,
+                "{=usize:#x} reads {=u8:#x}, expected {=u8:#x}",
+                addr,
+                actual,
+                expected
+            ),
+        }
+    }
+}
+
+impl From<FlashError> for Failure {
+    fn from(e: FlashError) -> Self {
+        Failure::Driver(e)
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    let flash = FlashWriterEraser::new();
+    defmt::info!(
+        "flash self-test, update partition @ {=usize:#x}, swap partition @ {=usize:#x}",
+        UPDATE_PARTITION_ADDRESS,
+        SWAP_PARTITION_ADDRESS
+    );
+    let mut failed = 0;
+    for (name, case) in CASES.iter() {
+        match case(&flash) {
+            Ok(()) => defmt::info!("{=str}: ok", name),
+            Err(e) => {
+                failed += 1;
+                defmt::error!("{=str}: {}", name, e);
+            }
+        }
+    }
+    if failed > 0 {
+        defmt::panic!("{} of {} cases failed", failed, CASES.len());
+    }
+    defmt::info!("all {} cases passed", CASES.len());
+    loop {
+        cortex_m::asm::bkpt();
+    }
+}
+
+fn erase(flash: &FlashWriterEraser) -> Result<(), Failure> {
+    flash.hal_flash_erase(UPDATE_PARTITION_ADDRESS, PARTITION_SIZE)?;
+    flash.hal_flash_erase(SWAP_PARTITION_ADDRESS, SECTOR_SIZE)?;
+    expect(flash, UPDATE_PARTITION_ADDRESS, PARTITION_SIZE, |_| 0xFF)?;
+    expect(flash, SWAP_PARTITION_ADDRESS, SECTOR_SIZE, |_| 0xFF)
+}
+
+fn unaligned_writes(flash: &FlashWriterEraser) -> Result<(), Failure> {
+    let base = UPDATE_PARTITION_ADDRESS;
+    flash.hal_flash_erase(base, LENGTHS.len() * 4 * SLOT)?;
+    let src = pattern::<{ SLOT + 4 }>();
+    for (i, len) in LENGTHS.iter().enumerate() {
+        for offset in 0..4 {
+            let slot = base + (i * 4 + offset) * SLOT;
+            // the source buffer's alignment differs from the destination's
+            let data = &src[(offset + 1) % 4..][..*len];
+            flash.hal_flash_write(slot + offset, data)?;
+            expect(flash, slot, SLOT, |idx| match idx.checked_sub(offset) {
+                Some(idx) if idx < *len => data[idx],
+                _ => 0xFF,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn sector_boundary(flash: &FlashWriterEraser) -> Result<(), Failure> {
+    let boundary = if PARTITION_SIZE > SECTOR_SIZE {
+        UPDATE_PARTITION_ADDRESS + SECTOR_SIZE
+    } else if SWAP_PARTITION_ADDRESS == UPDATE_PARTITION_ADDRESS + PARTITION_SIZE {
+        SWAP_PARTITION_ADDRESS
+    } else {
+        defmt::warn!("no sector boundary between the update and swap partitions, skipped");
+        return Ok(());
+    };
+    let (addr, below) = (boundary - 19, 19);
+    let data = &pattern::<45>()[..];
+    flash.hal_flash_erase(addr, data.len())?;
+    flash.hal_flash_write(addr, data)?;
+    expect(flash, addr, data.len(), |idx| data[idx])?;
+
+    flash.hal_flash_erase(boundary, 1)?;
+    expect(flash, addr, data.len(), |idx| match idx < below {
+        true => data[idx],
+        false => 0xFF,
+    })
+}
+
+fn partition_edges(flash: &FlashWriterEraser) -> Result<(), Failure> {
+    let data = &pattern::<7>()[..];
+    // the swap partition may end where flash does i.e. there's nothing after it to check
+    let partitions = [
+        (UPDATE_PARTITION_ADDRESS, PARTITION_SIZE, true),
+        (SWAP_PARTITION_ADDRESS, SECTOR_SIZE, false),
+    ];
+    for (start, size, check_after) in partitions.iter() {
+        let end = start + size;
+        let mut before = [0u8; GUARD];
+        let mut after = [0xFFu8; GUARD];
+        flash.hal_flash_read(start - GUARD, &mut before)?;
+        if *check_after {
+            flash.hal_flash_read(end, &mut after)?;
+        }
+
+        flash.hal_flash_erase(*start, 1)?;
+        flash.hal_flash_erase(end - 1, 1)?;
+        flash.hal_flash_write(*start, data)?;
+        flash.hal_flash_write(end - data.len(), data)?;
+        expect(flash, *start, data.len(), |idx| data[idx])?;
+        expect(flash, end - data.len(), data.len(), |idx| data[idx])?;
+
+        expect(flash, start - GUARD, GUARD, |idx| before[idx])?;
+        if *check_after {
+            expect(flash, end, GUARD, |idx| after[idx])?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the `len` bytes of flash at `addr` hold `expected(offset)`.
+fn expect(
+    flash: &FlashWriterEraser,
+    addr: usize,
+    len: usize,
+    expected: impl Fn(usize) -> u8,
+) -> Result<(), Failure> {
+    let mut buf = [0u8; 32];
+    let mut pos = 0;
+    while pos < len {
+        let read = &mut buf[..(len - pos).min(32)];
+        flash.hal_flash_read(addr + pos, read)?;
+        for (i, actual) in read.iter().enumerate() {
+            let expected = expected(pos + i);
+            if *actual != expected {
+                return Err(Failure::Mismatch {
+                    addr: addr + pos + i,
+                    expected,
+                    actual: *actual,
+                });
+            }
+        }
+        pos += read.len();
+    }
+    Ok(())
+}
+
+/// Test data that never reads as erased (i.e. `0xFF`), so that a short write is caught.
+fn pattern<const N: usize>() -> [u8; N] {
+    let mut data = [0u8; N];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = 0x5A ^ (i as u8 & 0x7F);
+    }
+    data
+}
+
+#[panic_handler] // panicking behavior
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    defmt::error!("{}", defmt::Display2Format(info));
+    cortex_m::asm::udf()
+}
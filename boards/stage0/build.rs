@@ -0,0 +1,26 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const BOARDS: [&str; 1] = ["nrf52840"];
+
+/// stage0 runs from the start of flash i.e. it's linked with the board's bootloader `memory.x`,
+/// and checked against the stage1 slots (`stage0.x`, in place of `partitions.x`).
+fn main() {
+    let board = BOARDS
+        .iter()
+        .find(|board| env::var_os(format!("CARGO_FEATURE_{}", board.to_uppercase())).is_some())
+        .expect("no board feature enabled, see `cargo <board> build stage0`");
+    let board_dir = PathBuf::from("../bootloaders").join(board);
+    let memory_x = board_dir.join("memory.x");
+    let stage0_x = board_dir.join("stage0.x");
+
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy(&memory_x, out.join("memory.x")).unwrap();
+    fs::copy(&stage0_x, out.join("partitions.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", memory_x.display());
+    println!("cargo:rerun-if-changed={}", stage0_x.display());
+}
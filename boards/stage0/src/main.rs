@@ -0,0 +1,53 @@
+//! stage0 of a two-stage bootloader i.e. boards whose manifest has a `[stage0]`, see `cargo
+//! <board> build stage0`.
+//!
+//! stage0 is flashed once, at the start of flash, and never updated. On every boot, it verifies
+//! the images in both stage1 slots (i.e. rustBoot built with `two-stage`, see
+//! `rustBoot_update::update::stage1`) and boots the newest one that checks out i.e. its digest,
+//! its signature (against the embedded public key) and its vector table, which must point into
+//! its slot. If neither does, stage0 halts.
+//!
+//! Everything else (updates, rollbacks, recovery, logging) is left to stage1, so stage0 has no
+//! flash driver and no logger.
+
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use rustBoot::chain::{newest_image, SignedImage};
+use rustBoot::constants::{IMAGE_HEADER_SIZE, STAGE1_A_ADDRESS, STAGE1_B_ADDRESS, STAGE1_SIZE};
+use rustBoot::image::vectors::check_vector_table;
+use rustBoot::Result;
+
+const SLOTS: [usize; 2] = [STAGE1_A_ADDRESS, STAGE1_B_ADDRESS];
+
+#[entry]
+fn main() -> ! {
+    let slots =
+        SLOTS.map(|addr| unsafe { core::slice::from_raw_parts(addr as *const u8, STAGE1_SIZE) });
+    match newest_image(&slots, check_slot) {
+        Some((idx, _)) => {
+            rustBoot_hal::preboot();
+            rustBoot_hal::boot_from(SLOTS[idx] + IMAGE_HEADER_SIZE)
+        }
+        None => halt(),
+    }
+}
+
+/// stage1 is linked to run from its slot, right after its header.
+fn check_slot(idx: usize, image: &SignedImage) -> Result<()> {
+    let fw_base = SLOTS[idx] + IMAGE_HEADER_SIZE;
+    let ram = rustBoot_hal::ram_region().unwrap_or(0..usize::MAX);
+    check_vector_table(image.firmware(), ram, fw_base..SLOTS[idx] + STAGE1_SIZE)
+}
+
+fn halt() -> ! {
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    halt()
+}
@@ -0,0 +1,253 @@
+//! A minimal interactive shell over a serial line (ex: UART), for manufacturing and field
+//! diagnostics - see the `console` feature.
+//!
+//! Bootloaders enter the console, rather than booting right away, when it's requested i.e. while
+//! a button is held or if firmware left [`CONSOLE_MAGIC`] in a retained register (ex: the
+//! nrf52840's `GPREGRET`) before it reset. rustBoot boots as usual once the console is left.
+//!
+//! | command    | does                                                                   |
+//! |------------|------------------------------------------------------------------------|
+//! | `help`     | lists the commands                                                     |
+//! | `state`    | prints the boot and update partitions' states                          |
+//! | `images`   | prints the boot and update images' versions and digests                |
+//! | `report`   | prints the device's security state i.e. its boot report, so far        |
+//! | `rollback` | reverts to the image in the update partition (for good), once left     |
+//! | `erase`    | erases the update partition, unless an update is pending               |
+//! | `boot`     | leaves the console                                                     |
+//!
+//! *Note: the console reverts and erases images on request, it must not be enterable on
+//! devices that are exposed to untrusted users.*
+
+use core::fmt::{self, Write};
+
+use rustBoot::constants::*;
+use rustBoot::fs::Timestamp;
+use rustBoot::image::image::*;
+use rustBoot::progress::Progress;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::FlashInterface;
+
+use crate::hal::hal::hal_rtc_time;
+use crate::update::swap::{SectorSwap, SwapPolicy};
+use crate::update::update_flash::FlashUpdater;
+
+/// The value firmware leaves in a retained register, to enter the console on the next boot.
+pub const CONSOLE_MAGIC: u8 = 0x5C;
+
+/// The longest command line, in bytes. Longer lines are truncated.
+const LINE_LEN: usize = 32;
+
+/// A serial line i.e. the console's connection to a terminal.
+pub trait Serial {
+    /// Blocks until a byte is received.
+    fn read_byte(&mut self) -> u8;
+    /// Blocks until `bytes` are sent.
+    fn write_bytes(&mut self, bytes: &[u8]);
+}
+
+/// The console, performing diagnostics on `updater`'s partitions.
+pub struct Console<'a, S, Interface, Policy = SectorSwap, Hook = ()> {
+    updater: &'a FlashUpdater<Interface, Policy, Hook>,
+    serial: S,
+}
+
+impl<'a, S, Interface, Policy, Hook> Console<'a, S, Interface, Policy, Hook>
+where
+    S: Serial,
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    pub fn new(updater: &'a FlashUpdater<Interface, Policy, Hook>, serial: S) -> Self {
+        Console { updater, serial }
+    }
+
+    /// Runs commands until the console is left (i.e. `boot`), returning the serial line.
+    pub fn run(mut self) -> S {
+        self.line(format_args!("rustBoot console, `help` lists the commands"));
+        let mut buf = [0u8; LINE_LEN];
+        loop {
+            self.serial.write_bytes(b"> ");
+            let len = self.read_line(&mut buf);
+            match core::str::from_utf8(&buf[..len]).unwrap_or("").trim() {
+                "" => {}
+                "help" => self.help(),
+                "state" => self.state(),
+                "images" => self.images(),
+                "report" => self.report(),
+                "rollback" => self.rollback(),
+                "erase" => self.erase(),
+                "boot" => return self.serial,
+                cmd => self.line(format_args!("unknown command `{}`", cmd)),
+            }
+        }
+    }
+
+    /// Reads a line into `buf`, echoing it. Returns its length.
+    fn read_line(&mut self, buf: &mut [u8]) -> usize {
+        let mut len = 0;
+        loop {
+            match self.serial.read_byte() {
+                // i.e. the `\n` of a `\r\n`
+                b'\n' if len == 0 => {}
+                b'\r' | b'\n' => {
+                    self.serial.write_bytes(b"\r\n");
+                    return len;
+                }
+                // backspace or delete
+                0x08 | 0x7f if len > 0 => {
+                    len -= 1;
+                    self.serial.write_bytes(b"\x08 \x08");
+                }
+                byte @ 0x20..=0x7e if len < buf.len() => {
+                    buf[len] = byte;
+                    len += 1;
+                    self.serial.write_bytes(&[byte]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn line(&mut self, args: fmt::Arguments) {
+        let _ = SerialWriter(&mut self.serial).write_fmt(args);
+        self.serial.write_bytes(b"\r\n");
+    }
+
+    fn help(&mut self) {
+        self.line(format_args!("state     partition states"));
+        self.line(format_args!("images    image versions and digests"));
+        self.line(format_args!("report    boot report"));
+        self.line(format_args!(
+            "rollback  revert to the update partition's image"
+        ));
+        self.line(format_args!("erase     erase the update partition"));
+        self.line(format_args!("boot      leave the console and boot"));
+    }
+
+    fn state(&mut self) {
+        let boot = match PartDescriptor::open_partition(Boot, self.updater) {
+            Ok(ImageType::BootInNewState(_)) => "new",
+            Ok(ImageType::BootInTestingState(_)) => "testing",
+            Ok(ImageType::BootInSuccessState(_)) => "success",
+            _ => "invalid",
+        };
+        let update = match PartDescriptor::open_partition(Update, self.updater) {
+            Ok(ImageType::UpdateInNewState(_)) => "new",
+            Ok(ImageType::UpdateInUpdatingState(_)) => "updating",
+            _ => "invalid",
+        };
+        self.line(format_args!("boot      {}", boot));
+        self.line(format_args!("update    {}", update));
+    }
+
+    fn images(&mut self) {
+        let images = [
+            ("boot", self.updater.boot_image_info()),
+            ("update", self.updater.update_image_info()),
+        ];
+        for (name, info) in images {
+            match info {
+                Ok(info) => self.line(format_args!(
+                    "{:<9} version {}, sha256 {}",
+                    name,
+                    info.version,
+                    Hex(info.digest)
+                )),
+                Err(_) => self.line(format_args!("{:<9} no image", name)),
+            }
+        }
+    }
+
+    fn report(&mut self) {
+        let protection = self.updater.iface().hal_debug_protection();
+        self.line(format_args!("debug protection  {:?}", protection));
+        match hal_rtc_time() {
+            Some(time) => self.line(format_args!(
+                "time              {}",
+                Timestamp::from_unix_secs(time)
+            )),
+            None => self.line(format_args!("time              unknown (no rtc)")),
+        }
+        // see `rustBoot::panicrecord::file_hash` for the file that panicked
+        #[cfg(feature = "panic-record")]
+        match crate::hal::hal::hal_last_panic() {
+            Some(rec) if rec.timestamp != 0 => self.line(format_args!(
+                "last panic        file {:#010x}, line {}, at {}",
+                rec.file_hash,
+                rec.line,
+                Timestamp::from_unix_secs(rec.timestamp as u64)
+            )),
+            Some(rec) => self.line(format_args!(
+                "last panic        file {:#010x}, line {}",
+                rec.file_hash, rec.line
+            )),
+            None => self.line(format_args!("last panic        none recorded")),
+        }
+        if cfg!(feature = "dev-unsigned") {
+            self.line(format_args!("dev-unsigned      unsigned images are booted"));
+        }
+    }
+
+    /// Moves the boot image to `testing` and marks it as a trial i.e. rustBoot reverts to the
+    /// update partition's image (once it's authenticated) and confirms it.
+    fn rollback(&mut self) {
+        if self.updater.update_image_info().is_err() {
+            return self.line(format_args!("no image to roll back to"));
+        }
+        let res = match PartDescriptor::open_partition(Boot, self.updater) {
+            Ok(ImageType::BootInNewState(img)) => self.mark_trial(img.into_testing_state()),
+            Ok(ImageType::BootInSuccessState(img)) => self.mark_trial(img.into_testing_state()),
+            Ok(ImageType::BootInTestingState(img)) => self.mark_trial(img),
+            _ => Err(RustbootError::InvalidState),
+        };
+        match res {
+            Ok(()) => self.line(format_args!("rolling back on `boot`")),
+            Err(e) => self.line(format_args!("rollback failed: {}", e)),
+        }
+    }
+
+    fn mark_trial(&self, img: RustbootImage<'_, Boot, StateTesting>) -> Result<()> {
+        let part = img.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+        part.set_state(self.updater, img.get_state()?)?;
+        if !part.is_trial()? {
+            part.set_trial(self.updater)?;
+        }
+        Ok(())
+    }
+
+    fn erase(&mut self) {
+        if let Ok(ImageType::UpdateInUpdatingState(_)) =
+            PartDescriptor::open_partition(Update, self.updater)
+        {
+            return self.line(format_args!("an update is pending, not erasing"));
+        }
+        match self
+            .updater
+            .iface()
+            .hal_flash_erase(UPDATE_PARTITION_ADDRESS, PARTITION_SIZE)
+        {
+            Ok(()) => self.line(format_args!("erased")),
+            Err(e) => self.line(format_args!("erase failed: {:?}", e)),
+        }
+    }
+}
+
+/// Formats the console's output onto its serial line.
+struct SerialWriter<'s, S>(&'s mut S);
+
+impl<S: Serial> Write for SerialWriter<'_, S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Formats bytes as (lowercase) hex.
+struct Hex<'b>(&'b [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
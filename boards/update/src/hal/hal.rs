@@ -1,4 +1,8 @@
-use rustBoot_hal::{boot_from, preboot};
+use rustBoot_hal::boot_pin::BootPin;
+use rustBoot_hal::{
+    boot_from, boot_pin_held, preboot, ram_region, read_backup_regs, rtc_time, write_backup_regs,
+    BACKUP_REGS,
+};
 
 // Arch-specific code
 pub fn hal_preboot() {
@@ -7,3 +11,38 @@ pub fn hal_preboot() {
 pub fn hal_boot_from(addr: usize) -> ! {
     boot_from(addr)
 }
+pub fn hal_ram_region() -> Option<core::ops::Range<usize>> {
+    ram_region()
+}
+pub fn hal_boot_pin_held(boot_pin: &BootPin) -> bool {
+    boot_pin_held(boot_pin)
+}
+pub fn hal_rtc_time() -> Option<u64> {
+    rtc_time()
+}
+pub fn hal_read_backup_regs() -> Option<[u32; BACKUP_REGS]> {
+    read_backup_regs()
+}
+pub fn hal_write_backup_regs(regs: &[u32; BACKUP_REGS]) -> bool {
+    write_backup_regs(regs)
+}
+#[cfg(feature = "panic-record")]
+pub fn hal_last_panic() -> Option<rustBoot::panicrecord::PanicRecord> {
+    rustBoot_hal::panic_record::last_panic()
+}
+#[cfg(feature = "boot-reason")]
+pub fn hal_take_reset_cause() -> rustBoot::bootreason::ResetCause {
+    rustBoot_hal::boot_reason::take_reset_cause()
+}
+#[cfg(feature = "boot-reason")]
+pub fn hal_read_boot_reason() -> Option<rustBoot::bootreason::BootReason> {
+    rustBoot_hal::boot_reason::read()
+}
+#[cfg(feature = "boot-reason")]
+pub fn hal_write_boot_reason(reason: &rustBoot::bootreason::BootReason) -> bool {
+    rustBoot_hal::boot_reason::write(reason)
+}
+#[cfg(feature = "boot-reason")]
+pub fn hal_clear_boot_reason() {
+    rustBoot_hal::boot_reason::clear()
+}
@@ -2,5 +2,27 @@
 #![allow(warnings)]
 #![feature(once_cell)]
 
+#[cfg(all(feature = "dev-unsigned", feature = "production"))]
+compile_error!("`dev-unsigned` boots unsigned images, it can't be combined with `production`");
+#[cfg(all(feature = "boot-redirect", feature = "esp32s3"))]
+compile_error!("`boot-redirect` boots a vector table, it isn't supported on ESP boards");
+#[cfg(all(feature = "two-stage", feature = "esp32s3"))]
+compile_error!("`two-stage` runs rustBoot from flash, it isn't supported on ESP boards");
+
+#[cfg(feature = "console")]
+pub mod console;
 pub mod hal;
+pub mod smp;
+#[cfg_attr(
+    feature = "panic-free",
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::todo,
+        clippy::unimplemented,
+        clippy::unreachable,
+        clippy::indexing_slicing
+    )
+)]
 pub mod update;
@@ -0,0 +1,288 @@
+//! The image-management group.
+//!
+//! Slot `0` is the boot partition i.e. the running image, slot `1` the update partition. An
+//! image is uploaded to slot `1`, marked for test (the bootloader then swaps it in and boots
+//! it in the `testing` state) and finally confirmed from the new image. An unconfirmed image is
+//! rolled back on the next reset.
+
+use rustBoot::constants::*;
+use rustBoot::image::image::*;
+
+use super::*;
+use crate::update::UpdateInterface;
+
+const IMG_MGMT_ID_STATE: u8 = 0;
+const IMG_MGMT_ID_UPLOAD: u8 = 1;
+const IMG_MGMT_ID_ERASE: u8 = 5;
+
+/// An image, as reported by the `image state` command.
+struct Slot {
+    slot: u32,
+    version: u32,
+    hash: &'static [u8],
+    pending: bool,
+    confirmed: bool,
+    active: bool,
+}
+
+impl<'a, Interface, Policy, Hook> SmpServer<'a, Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    pub(super) fn image_mgmt(
+        &mut self,
+        op: u8,
+        id: u8,
+        payload: &[u8],
+        rsp: &mut [u8],
+    ) -> MgmtResult<usize> {
+        match (op, id) {
+            (OP_READ, IMG_MGMT_ID_STATE) => self.image_state(rsp),
+            (OP_WRITE, IMG_MGMT_ID_STATE) => self.image_state_write(payload, rsp),
+            (OP_WRITE, IMG_MGMT_ID_UPLOAD) => self.image_upload(payload, rsp),
+            (OP_WRITE, IMG_MGMT_ID_ERASE) => self.image_erase(payload, rsp),
+            _ => Err(MgmtErr::NotSup),
+        }
+    }
+
+    fn boot_slot(&self) -> Option<Slot> {
+        let info = self.updater.boot_image_info().ok()?;
+        // a `new` boot image hasn't gone through an update i.e. there's nothing to confirm.
+        let confirmed = match info.state {
+            PartitionState::New | PartitionState::Success => true,
+            _ => false,
+        };
+        Some(Slot {
+            slot: 0,
+            version: info.version,
+            hash: info.digest,
+            pending: false,
+            confirmed,
+            active: true,
+        })
+    }
+
+    fn update_slot(&self) -> Option<Slot> {
+        // a partially uploaded image isn't reported
+        if self.upload.is_some() {
+            return None;
+        }
+        let info = self.updater.update_image_info().ok()?;
+        Some(Slot {
+            slot: 1,
+            version: info.version,
+            hash: info.digest,
+            pending: info.state == PartitionState::Updating,
+            confirmed: false,
+            active: false,
+        })
+    }
+
+    /// `image state` read i.e. lists both slots.
+    fn image_state(&self, rsp: &mut [u8]) -> MgmtResult<usize> {
+        let slots = [self.boot_slot(), self.update_slot()];
+        let mut enc = RspEncoder::new(Cursor::new(rsp));
+        enc.map(2)?
+            .str("images")?
+            .array(slots.iter().flatten().count() as u64)?;
+        for slot in slots.iter().flatten() {
+            let mut version = [0u8; 10];
+            enc.map(8)?
+                .str("slot")?
+                .u32(slot.slot)?
+                .str("version")?
+                .str(decimal(slot.version, &mut version))?
+                .str("hash")?
+                .bytes(slot.hash)?
+                .str("bootable")?
+                .bool(true)?
+                .str("pending")?
+                .bool(slot.pending)?
+                .str("confirmed")?
+                .bool(slot.confirmed)?
+                .str("active")?
+                .bool(slot.active)?
+                .str("permanent")?
+                .bool(false)?;
+        }
+        enc.str("splitStatus")?.u32(0)?;
+        Ok(enc.writer().position())
+    }
+
+    /// `image state` write i.e. test or confirm an image.
+    ///
+    /// - `hash` of slot `1`, `confirm: false` - marks the update for a trial boot (see
+    ///   [`UpdateInterface::update_test`]).
+    /// - `confirm: true` and no `hash` (or the hash of slot `0`) - confirms the running image.
+    ///
+    /// Permanent updates (i.e. `confirm: true` for slot `1`) are not supported - rustBoot always
+    /// boots an update in the `testing` state.
+    fn image_state_write(&mut self, payload: &[u8], rsp: &mut [u8]) -> MgmtResult<usize> {
+        let mut hash = None;
+        let mut confirm = false;
+        decode_map(payload, |key, d| {
+            match key {
+                "hash" => hash = Some(d.bytes()?),
+                "confirm" => confirm = d.bool()?,
+                _ => d.skip()?,
+            }
+            Ok(())
+        })?;
+        let boot = self.boot_slot();
+        let update = self.update_slot();
+        let is_boot = boot.as_ref().map_or(false, |slot| Some(slot.hash) == hash);
+        let is_update = update
+            .as_ref()
+            .map_or(false, |slot| Some(slot.hash) == hash);
+        if confirm && (hash.is_none() || is_boot) {
+            if !boot.as_ref().map_or(false, |slot| slot.confirmed) {
+                self.updater
+                    .update_success()
+                    .map_err(|_| MgmtErr::BadState)?;
+            }
+        } else if is_update {
+            if confirm {
+                return Err(MgmtErr::NotSup);
+            }
+            self.updater.update_test().map_err(|_| MgmtErr::BadState)?;
+        } else if hash.is_some() {
+            return Err(MgmtErr::NoEnt);
+        } else {
+            return Err(MgmtErr::Inval);
+        }
+        self.image_state(rsp)
+    }
+
+    /// `image upload` i.e. writes a chunk of an image to the update partition.
+    ///
+    /// The first chunk (`off: 0`) carries the image's total `len`. Sectors are erased as the
+    /// upload progresses. A chunk that doesn't continue the upload is answered with the
+    /// expected offset, so the client can resume from there.
+    fn image_upload(&mut self, payload: &[u8], rsp: &mut [u8]) -> MgmtResult<usize> {
+        let mut image = 0;
+        let mut len = None;
+        let mut off = None;
+        let mut data = None;
+        decode_map(payload, |key, d| {
+            match key {
+                "image" => image = d.u32()?,
+                "len" => len = Some(d.u32()? as usize),
+                "off" => off = Some(d.u32()? as usize),
+                "data" => data = Some(d.bytes()?),
+                _ => d.skip()?,
+            }
+            Ok(())
+        })?;
+        if image != 0 {
+            return Err(MgmtErr::NotSup);
+        }
+        let (off, data) = match (off, data) {
+            (Some(off), Some(data)) => (off, data),
+            _ => return Err(MgmtErr::Inval),
+        };
+
+        if off == 0 {
+            let len = len.ok_or(MgmtErr::Inval)?;
+            if len > PARTITION_SIZE - TRAILER_SECTORS * SECTOR_SIZE {
+                return Err(MgmtErr::NoMem);
+            }
+            if len < IMAGE_HEADER_SIZE || image_header_magic(data) == Some(false) {
+                return Err(MgmtErr::Inval);
+            }
+            if self.update_slot().map_or(false, |slot| slot.pending) {
+                return Err(MgmtErr::BadState);
+            }
+            // erasing the trailer (i.e. the sector it ends with) resets the partition's state
+            self.updater
+                .iface()
+                .hal_flash_erase(UPDATE_TRAILER_ADDRESS - SECTOR_SIZE, SECTOR_SIZE)?;
+            self.upload = Some(Upload {
+                len,
+                off: 0,
+                erased: 0,
+            });
+        }
+
+        let upload = self.upload.as_mut().ok_or(MgmtErr::BadState)?;
+        if off == upload.off {
+            let end = off + data.len();
+            if end > upload.len {
+                return Err(MgmtErr::Inval);
+            }
+            let iface = self.updater.iface();
+            while upload.erased < end {
+                iface.hal_flash_erase(UPDATE_PARTITION_ADDRESS + upload.erased, SECTOR_SIZE)?;
+                upload.erased += SECTOR_SIZE;
+            }
+            // chunks needn't end on a whole unit of flash, see `FlashUpdater::write`
+            self.updater
+                .write(UPDATE_PARTITION_ADDRESS + off, data)
+                .map_err(|_| MgmtErr::Unknown)?;
+            upload.off = end;
+        }
+        let next = upload.off;
+        if next == upload.len {
+            self.upload = None;
+        }
+
+        let mut enc = RspEncoder::new(Cursor::new(rsp));
+        enc.map(2)?
+            .str("rc")?
+            .u32(0)?
+            .str("off")?
+            .u32(next as u32)?;
+        Ok(enc.writer().position())
+    }
+
+    /// `image erase` i.e. erases the update partition. Erasing a pending update is refused.
+    fn image_erase(&mut self, payload: &[u8], rsp: &mut [u8]) -> MgmtResult<usize> {
+        let mut slot = 1;
+        if !payload.is_empty() {
+            decode_map(payload, |key, d| {
+                match key {
+                    "slot" => slot = d.u32()?,
+                    _ => d.skip()?,
+                }
+                Ok(())
+            })?;
+        }
+        if slot != 1 {
+            return Err(MgmtErr::Inval);
+        }
+        if self.update_slot().map_or(false, |slot| slot.pending) {
+            return Err(MgmtErr::BadState);
+        }
+        self.updater
+            .iface()
+            .hal_flash_erase(UPDATE_PARTITION_ADDRESS, PARTITION_SIZE)?;
+        self.upload = None;
+
+        let mut enc = RspEncoder::new(Cursor::new(rsp));
+        enc.map(1)?.str("rc")?.u32(0)?;
+        Ok(enc.writer().position())
+    }
+}
+
+/// Checks for rustBoot's magic at the start of `bytes`. Returns `None` if there are fewer
+/// than 4 bytes.
+fn image_header_magic(bytes: &[u8]) -> Option<bool> {
+    bytes
+        .get(..4)
+        .map(|magic| magic == (RUSTBOOT_MAGIC as u32).to_le_bytes())
+}
+
+/// Formats `val` as a decimal string, using `buf` for storage.
+fn decimal(mut val: u32, buf: &mut [u8; 10]) -> &str {
+    let mut pos = buf.len();
+    loop {
+        pos -= 1;
+        buf[pos] = b'0' + (val % 10) as u8;
+        val /= 10;
+        if val == 0 {
+            break;
+        }
+    }
+    core::str::from_utf8(&buf[pos..]).unwrap()
+}
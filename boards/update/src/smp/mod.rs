@@ -0,0 +1,228 @@
+//! A server for the SMP (Simple Management Protocol) i.e. the protocol spoken by `mcumgr` and
+//! compatible tools (mcumgr CLI, nRF Connect, etc.).
+//!
+//! Only the image-management group is implemented - list (image state), upload, test, confirm
+//! and erase. The server is transport-agnostic. Application firmware receives SMP packets over
+//! its transport of choice (BLE, UART, USB etc.), hands them to [`SmpServer::process`] and sends
+//! back the response.
+//!
+//! *Note: SMP over a serial line wraps packets in a base64 + CRC16 framing. Un-framing
+//! (and framing responses) is the transport's job, [`SmpServer`] deals with bare SMP packets.*
+//!
+//! Requests for any other group are answered with [`MgmtErr::NotSup`]. Applications that want
+//! to handle other groups (ex: `os` group's reset) can check [`SmpHeader::group`] before
+//! passing a request on.
+
+mod image;
+
+use minicbor::data::Type;
+use minicbor::decode::{self, Decoder};
+use minicbor::encode::{self, write::Cursor, Encoder};
+
+use crate::update::swap::{SectorSwap, SwapPolicy};
+use crate::update::update_flash::FlashUpdater;
+use rustBoot::progress::Progress;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::{FlashError, FlashInterface};
+
+/// Size of an SMP header.
+pub const SMP_HEADER_SIZE: usize = 8;
+
+/// Read request
+pub const OP_READ: u8 = 0;
+/// Read response
+pub const OP_READ_RSP: u8 = 1;
+/// Write request
+pub const OP_WRITE: u8 = 2;
+/// Write response
+pub const OP_WRITE_RSP: u8 = 3;
+
+/// The image-management group.
+pub const GROUP_IMAGE: u16 = 1;
+
+/// The 8-byte header preceding an SMP packet's CBOR payload. Multi-byte fields are big-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmpHeader {
+    /// operation - the lower 3 bits hold the op-code, bits `[4:3]` the protocol version.
+    pub op: u8,
+    pub flags: u8,
+    /// length of the payload following the header.
+    pub len: u16,
+    pub group: u16,
+    pub seq: u8,
+    /// command id, within the group.
+    pub id: u8,
+}
+
+impl SmpHeader {
+    /// Parses the header at the start of `packet`.
+    pub fn parse(packet: &[u8]) -> Result<Self> {
+        match packet.get(..SMP_HEADER_SIZE) {
+            Some(hdr) => Ok(SmpHeader {
+                op: hdr[0],
+                flags: hdr[1],
+                len: u16::from_be_bytes([hdr[2], hdr[3]]),
+                group: u16::from_be_bytes([hdr[4], hdr[5]]),
+                seq: hdr[6],
+                id: hdr[7],
+            }),
+            None => Err(RustbootError::InvalidValue),
+        }
+    }
+
+    /// Returns the op-code, without the protocol version.
+    pub fn op_code(&self) -> u8 {
+        self.op & 0x07
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        let len = self.len.to_be_bytes();
+        let group = self.group.to_be_bytes();
+        buf[..SMP_HEADER_SIZE].copy_from_slice(&[
+            self.op, self.flags, len[0], len[1], group[0], group[1], self.seq, self.id,
+        ]);
+    }
+}
+
+/// SMP management error codes i.e. the `rc` value of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum MgmtErr {
+    Ok = 0,
+    /// unknown error, ex: a flash write or erase failed.
+    Unknown = 1,
+    /// out of memory, ex: the response doesn't fit or an image is larger than the update partition.
+    NoMem = 2,
+    /// malformed or invalid request.
+    Inval = 3,
+    Timeout = 4,
+    /// no such image.
+    NoEnt = 5,
+    /// the request isn't permitted in the current state, ex: an update is pending.
+    BadState = 6,
+    MsgSize = 7,
+    /// unsupported group or command.
+    NotSup = 8,
+    Corrupt = 9,
+    Busy = 10,
+}
+
+impl<E> From<encode::Error<E>> for MgmtErr {
+    fn from(_: encode::Error<E>) -> Self {
+        MgmtErr::NoMem
+    }
+}
+
+impl From<decode::Error> for MgmtErr {
+    fn from(_: decode::Error) -> Self {
+        MgmtErr::Inval
+    }
+}
+
+impl From<FlashError> for MgmtErr {
+    fn from(_: FlashError) -> Self {
+        MgmtErr::Unknown
+    }
+}
+
+type MgmtResult<T> = core::result::Result<T, MgmtErr>;
+
+/// CBOR encoder for a response payload.
+type RspEncoder<'r> = Encoder<Cursor<&'r mut [u8]>>;
+
+/// An image upload in progress.
+#[derive(Debug, Clone, Copy)]
+struct Upload {
+    /// total image size (header included).
+    len: usize,
+    /// next expected offset.
+    off: usize,
+    /// number of bytes (from the start of the update partition) erased so far.
+    erased: usize,
+}
+
+/// An SMP server, performing image-management requests on the update partition.
+#[derive(Debug)]
+pub struct SmpServer<'a, Interface, Policy = SectorSwap, Hook = ()> {
+    updater: &'a FlashUpdater<Interface, Policy, Hook>,
+    upload: Option<Upload>,
+}
+
+impl<'a, Interface, Policy, Hook> SmpServer<'a, Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    pub fn new(updater: &'a FlashUpdater<Interface, Policy, Hook>) -> Self {
+        SmpServer {
+            updater,
+            upload: None,
+        }
+    }
+
+    /// Processes a single SMP request `packet` and writes the response packet to `rsp`.
+    /// Returns the length of the response.
+    ///
+    /// Requests that cannot be served are answered with an error response (i.e. a non-zero
+    /// `rc`). An error is only returned if `packet` is not an SMP request or `rsp` is too small
+    /// to hold even an error response.
+    pub fn process(&mut self, packet: &[u8], rsp: &mut [u8]) -> Result<usize> {
+        let hdr = SmpHeader::parse(packet)?;
+        let payload = packet
+            .get(SMP_HEADER_SIZE..SMP_HEADER_SIZE + hdr.len as usize)
+            .ok_or(RustbootError::InvalidValue)?;
+        let rsp_op = match hdr.op_code() {
+            OP_READ => OP_READ_RSP,
+            OP_WRITE => OP_WRITE_RSP,
+            _ => return Err(RustbootError::InvalidValue),
+        };
+        let rsp_payload = rsp
+            .get_mut(SMP_HEADER_SIZE..)
+            .ok_or(RustbootError::BufferTooSmall)?;
+
+        let result = match hdr.group {
+            GROUP_IMAGE => self.image_mgmt(hdr.op_code(), hdr.id, payload, rsp_payload),
+            _ => Err(MgmtErr::NotSup),
+        };
+        let len = match result {
+            Ok(len) => len,
+            Err(rc) => {
+                let mut enc = Encoder::new(Cursor::new(&mut *rsp_payload));
+                enc.map(1)
+                    .and_then(|e| e.str("rc"))
+                    .and_then(|e| e.u32(rc as u32))
+                    .map_err(|_| RustbootError::BufferTooSmall)?;
+                enc.writer().position()
+            }
+        };
+        SmpHeader {
+            op: (hdr.op & !0x07) | rsp_op,
+            len: len as u16,
+            ..hdr
+        }
+        .write(rsp);
+        Ok(SMP_HEADER_SIZE + len)
+    }
+}
+
+/// Calls `f` with each key of the CBOR map in `payload`, `f` must consume the value.
+fn decode_map<'b>(
+    payload: &'b [u8],
+    mut f: impl FnMut(&'b str, &mut Decoder<'b>) -> MgmtResult<()>,
+) -> MgmtResult<()> {
+    let mut d = Decoder::new(payload);
+    let len = d.map()?;
+    let mut entries = 0;
+    loop {
+        match len {
+            Some(len) if entries == len => break,
+            None if d.datatype()? == Type::Break => break,
+            _ => {}
+        }
+        let key = d.str()?;
+        f(key, &mut d)?;
+        entries += 1;
+    }
+    Ok(())
+}
@@ -0,0 +1,25 @@
+//! The board's boot pin (see the `boot-pin` feature) i.e. a GPIO (ex: a button) that, held at
+//! reset, keeps rustBoot from booting firmware. It's configured in the board's manifest (i.e. its
+//! `[boot_pin]`), see `rustBoot_hal::boot_pin`.
+//!
+//! What rustBoot does instead is up to the board's bootloader (ex: the nrf52840's enters its
+//! console).
+
+use rustBoot::constants::{BOOT_PIN, BOOT_PIN_ACTIVE_LOW, BOOT_PIN_HOLD_MS, BOOT_PIN_PORT};
+use rustBoot_hal::boot_pin::BootPin;
+
+use crate::hal::hal::hal_boot_pin_held;
+
+/// The board's boot pin, as configured in its manifest.
+pub const BOARD_BOOT_PIN: BootPin = BootPin {
+    port: BOOT_PIN_PORT,
+    pin: BOOT_PIN,
+    active_low: BOOT_PIN_ACTIVE_LOW,
+    hold_ms: BOOT_PIN_HOLD_MS,
+};
+
+/// Returns true if the boot pin is held. This must be checked right after reset i.e. before the
+/// board's clocks are configured.
+pub fn boot_pin_held() -> bool {
+    hal_boot_pin_held(&BOARD_BOOT_PIN)
+}
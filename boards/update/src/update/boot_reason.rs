@@ -0,0 +1,48 @@
+//! The boot reason (see the `boot-reason` feature) i.e. why the mcu booted (the last reset's cause)
+//! and whether rustBoot installed an update or rolled one back on the way. rustBoot records it on
+//! every boot, for firmware to act on (ex: report a watchdog reset, or run a self-test on an
+//! update's first boot) and clear:
+//!
+//! ```ignore
+//! use rustBoot_update::update::boot_reason::{boot_reason, clear_boot_reason, ResetCause};
+//!
+//! if let Some(reason) = boot_reason() {
+//!     if reason.reset == ResetCause::Watchdog {
+//!         report_watchdog_reset();
+//!     }
+//!     clear_boot_reason();
+//! }
+//! ```
+//!
+//! It's also in rustBoot's boot report. Where it's kept is up to the board (see
+//! `rustBoot_hal::boot_reason`), the mcu's reset flags are cleared once rustBoot has read them.
+//!
+//! *Note: boards with nowhere to keep it (i.e. the rp2040, rp2350 and esp32s3) have no boot
+//! reason.*
+
+pub use rustBoot::bootreason::{BootAction, BootReason, ResetCause};
+
+use crate::hal::hal::{
+    hal_clear_boot_reason, hal_read_boot_reason, hal_take_reset_cause, hal_write_boot_reason,
+};
+
+/// Returns the boot reason rustBoot recorded, unless firmware cleared it.
+pub fn boot_reason() -> Option<BootReason> {
+    hal_read_boot_reason()
+}
+
+/// Clears the boot reason, once firmware has acted on it.
+pub fn clear_boot_reason() {
+    hal_clear_boot_reason()
+}
+
+/// Records why the mcu booted i.e. the last reset's cause and what rustBoot did (`action`), for
+/// firmware. Returns the reason, for the boot report.
+pub(crate) fn record_boot_reason(action: BootAction) -> BootReason {
+    let reason = BootReason {
+        reset: hal_take_reset_cause(),
+        action,
+    };
+    hal_write_boot_reason(&reason);
+    reason
+}
@@ -0,0 +1,143 @@
+//! Compressed updates (see `rustBoot::lz4`) i.e. an update that's decompressed into the boot
+//! partition, rather than swapped in.
+//!
+//! The update partition isn't written to, so an interrupted installation is resumed by
+//! decompressing the update again. Each boot sector's progress is saved in the update partition's
+//! sector flags (see [`SectorFlag`]) i.e. sectors that were written are skipped. A write or erase
+//! that fails aborts the installation, which is retried on the next boot.
+//!
+//! The boot image isn't backed up, so a compressed update can't be rolled back. It's confirmed as
+//! soon as it's installed, even if it was staged with `update_test`.
+//!
+//! *Note: the update is decompressed into the boot partition, as MCU images are booted in place.
+//! Decompressing into RAM (i.e. a RAM-load boot) isn't supported.*
+
+use rustBoot::constants::*;
+use rustBoot::flashapi::FlashApi;
+use rustBoot::image::image::*;
+use rustBoot::progress::{Phase, Progress};
+use rustBoot::{Result, RustbootError};
+
+use super::swap::{sector_flag, sectors, set_sector_flag};
+
+/// Returns `true` if the installation has already started i.e. it was interrupted and is to be
+/// resumed, in which case the update isn't verified again.
+pub(crate) fn started(updt: &PartDescriptor<Update>) -> bool {
+    sector_flag(updt, 0) != SectorFlag::New
+}
+
+/// Decompresses the update (its header, and any padding before it, included) into the boot
+/// partition, resuming from the update partition's sector flags. Returns the number of boot
+/// sectors written.
+///
+/// `progress` is called with [`Phase::Swap`] as sectors are written.
+pub(crate) fn install(
+    updater: impl FlashApi,
+    boot: &PartDescriptor<Boot>,
+    updt: &PartDescriptor<Update>,
+    progress: &impl Progress,
+) -> Result<usize> {
+    let mut out = BootWriter {
+        updater,
+        boot,
+        updt,
+        progress,
+        sectors: sectors(updt.image_end()),
+        buf: [0u8; FLASHBUFFER_SIZE],
+        len: 0,
+        pos: 0,
+    };
+    // the header, and any padding that precedes it, are copied as they are
+    let mut header = [0u8; IMAGE_HEADER_SIZE];
+    let (mut offset, end) = (0, updt.hdr_offset + IMAGE_HEADER_SIZE);
+    while offset < end {
+        let chunk = header
+            .get_mut(..(end - offset).min(IMAGE_HEADER_SIZE))
+            .ok_or(RustbootError::Unreachable)?;
+        updater.flash_read(updt, offset, chunk)?;
+        out.push(chunk)?;
+        offset += chunk.len();
+    }
+    updt.decompress(updater, |firmware| out.push(firmware))?;
+    out.finish()?;
+    Ok(out.sectors)
+}
+
+/// Writes the decompressed image into the boot partition, one [`FLASHBUFFER_SIZE`] buffer at a
+/// time.
+struct BootWriter<'a, U: FlashApi, H: Progress> {
+    updater: U,
+    boot: &'a PartDescriptor<Boot>,
+    updt: &'a PartDescriptor<Update>,
+    progress: &'a H,
+    sectors: usize,
+    buf: [u8; FLASHBUFFER_SIZE],
+    /// the number of bytes in `buf`
+    len: usize,
+    /// where `buf` is written in the boot partition
+    pos: usize,
+}
+
+impl<U: FlashApi, H: Progress> BootWriter<'_, U, H> {
+    fn push(&mut self, mut data: &[u8]) -> Result<()> {
+        while !data.is_empty() {
+            let len = (FLASHBUFFER_SIZE - self.len).min(data.len());
+            let (src, rest) = data.split_at(len);
+            self.buf
+                .get_mut(self.len..self.len + len)
+                .ok_or(RustbootError::Unreachable)?
+                .copy_from_slice(src);
+            self.len += len;
+            data = rest;
+            if self.len == FLASHBUFFER_SIZE {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the buffer (its unused bytes are left erased), unless its sector was written before
+    /// the installation was interrupted. A sector is erased before its first buffer is written.
+    fn flush(&mut self) -> Result<()> {
+        let sector = self.pos / SECTOR_SIZE;
+        if sector_flag(self.updt, sector) != SectorFlag::Updated {
+            if self.pos % SECTOR_SIZE == 0 {
+                self.updater.flash_erase(self.boot, self.pos, SECTOR_SIZE)?;
+            }
+            self.buf
+                .get_mut(self.len..)
+                .ok_or(RustbootError::Unreachable)?
+                .fill(0xFF);
+            self.updater.flash_write(self.boot, self.pos, &self.buf)?;
+        }
+        self.pos += FLASHBUFFER_SIZE;
+        self.len = 0;
+        if self.pos % SECTOR_SIZE == 0 {
+            self.written(sector)?;
+        }
+        Ok(())
+    }
+
+    /// Writes what's left of the image and marks its last sector as written.
+    fn finish(&mut self) -> Result<()> {
+        if self.len > 0 {
+            self.flush()?;
+        }
+        match self.pos % SECTOR_SIZE {
+            0 => Ok(()),
+            _ => self.written(self.pos / SECTOR_SIZE),
+        }
+    }
+
+    /// Marks a sector as written i.e. its flag moves straight to `Updated`.
+    fn written(&self, sector: usize) -> Result<()> {
+        let mut flag = sector_flag(self.updt, sector);
+        while let Some(next) = flag.next() {
+            set_sector_flag(self.updater, self.updt, sector, next)?;
+            flag = next;
+        }
+        self.progress
+            .on_progress(Phase::Swap, sector + 1, self.sectors);
+        Ok(())
+    }
+}
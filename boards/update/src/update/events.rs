@@ -0,0 +1,64 @@
+//! The persistent boot/update event log (see the `event-log` feature and `rustBoot::eventlog`),
+//! in the sector the board's manifest reserves for it (i.e. `EVENT_LOG_ADDRESS`).
+//!
+//! rustBoot logs updates being triggered, installed, confirmed and rolled back as well as images
+//! that fail verification. Firmware reads (and clears) the log through its [`FlashUpdater`], so
+//! intermittent failures in the field can be diagnosed after the fact.
+
+use rustBoot::constants::*;
+use rustBoot::eventlog::{self, Event, Record, RECORD_LEN};
+use rustBoot::progress::Progress;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::FlashInterface;
+
+use super::swap::SwapPolicy;
+use super::update_flash::{flash_error, FlashUpdater};
+
+/// The number of (newest) records kept when the log is full.
+const KEEP: usize = 16;
+
+fn event_log() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(EVENT_LOG_ADDRESS as *const u8, SECTOR_SIZE) }
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Returns the logged events, oldest first.
+    pub fn events(&self) -> impl Iterator<Item = Record> {
+        eventlog::records(event_log())
+    }
+
+    /// Clears the event log.
+    pub fn clear_events(&self) -> Result<()> {
+        self.iface()
+            .hal_flash_erase(EVENT_LOG_ADDRESS, SECTOR_SIZE)
+            .map_err(flash_error)
+    }
+
+    /// Appends `event` to the log. A full log is erased, keeping its newest records.
+    pub(crate) fn append_event(&self, event: Event, code: u8, version: u32) -> Result<()> {
+        let log = event_log();
+        let record = Record {
+            event,
+            code,
+            seq: eventlog::next_seq(log),
+            version,
+        };
+        let mut used = eventlog::used(log);
+        if (used + 1) * RECORD_LEN > SECTOR_SIZE {
+            let mut kept = [0u8; KEEP * RECORD_LEN];
+            let last = log
+                .get((used - KEEP) * RECORD_LEN..used * RECORD_LEN)
+                .ok_or(RustbootError::Unreachable)?;
+            kept.copy_from_slice(last);
+            self.clear_events()?;
+            self.write(EVENT_LOG_ADDRESS, &kept)?;
+            used = KEEP;
+        }
+        self.write(EVENT_LOG_ADDRESS + used * RECORD_LEN, &record.to_bytes())
+    }
+}
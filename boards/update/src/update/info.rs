@@ -0,0 +1,114 @@
+//! Read-only queries of the boot and update images, for application firmware (ex: to display
+//! the running and pending firmware versions).
+//!
+//! Headers and trailers are parsed in place i.e. flash is neither unlocked nor written. Only
+//! images with a rustBoot header are reported.
+
+use core::convert::TryInto;
+
+use rustBoot::chain::find_header;
+use rustBoot::constants::*;
+#[cfg(feature = "state-journal")]
+use rustBoot::image::image::StateRecord;
+use rustBoot::image::image::{PartitionState, VendorTlvs};
+use rustBoot::parser::{parse_header_tlv, vendor_tlvs, Tags};
+use rustBoot::progress::Progress;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::FlashInterface;
+
+use super::swap::SwapPolicy;
+use super::update_flash::FlashUpdater;
+
+/// An image's metadata, as found in its header and its partition's trailer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub version: u32,
+    /// the SHA-256 digest of the image (i.e. its header's `Digest256`).
+    pub digest: &'static [u8],
+    /// the partition's state. A partition without a trailer is `New`.
+    pub state: PartitionState,
+    /// the image's vendor (i.e. OEM) TLVs, authenticated along with the image.
+    pub vendor_tlvs: VendorTlvs<'static>,
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Returns the boot (i.e. running) image's metadata.
+    pub fn boot_image_info(&self) -> Result<ImageInfo> {
+        image_info(
+            BOOT_PARTITION_ADDRESS,
+            BOOT_TRAILER_ADDRESS,
+            BOOT_TRAILER_LEN,
+        )
+    }
+
+    /// Returns the update image's metadata. Its state is `Updating` once an update is pending.
+    pub fn update_image_info(&self) -> Result<ImageInfo> {
+        image_info(
+            UPDATE_PARTITION_ADDRESS,
+            UPDATE_TRAILER_ADDRESS,
+            UPDATE_TRAILER_LEN,
+        )
+    }
+}
+
+fn image_info(addr: usize, trailer: usize, trailer_len: usize) -> Result<ImageInfo> {
+    let header = image_header(addr).ok_or(RustbootError::InvalidImage)?;
+    Ok(ImageInfo {
+        version: image_version(addr)?,
+        digest: parse_header_tlv(header, Tags::Digest256)?,
+        state: partition_state(trailer, trailer_len)?,
+        vendor_tlvs: vendor_tlvs(header)?,
+    })
+}
+
+/// Returns the version of the partition at `addr`'s image.
+pub(crate) fn image_version(addr: usize) -> Result<u32> {
+    let header = image_header(addr).ok_or(RustbootError::InvalidImage)?;
+    let version = parse_header_tlv(header, Tags::Version)?;
+    Ok(u32::from_le_bytes(
+        version
+            .try_into()
+            .map_err(|_| RustbootError::InvalidValue)?,
+    ))
+}
+
+/// Returns the rustBoot header of the partition at `addr` (i.e. past any padding, see
+/// [`HEADER_SEARCH_WINDOW`]), if there's one.
+fn image_header(addr: usize) -> Option<&'static [u8]> {
+    let window =
+        unsafe { core::slice::from_raw_parts(addr as *const u8, HEADER_SEARCH_WINDOW + 4) };
+    let offset = find_header(window, HEADER_SEARCH_WINDOW, HEADER_SEARCH_ALIGN)?;
+    Some(unsafe { core::slice::from_raw_parts((addr + offset) as *const u8, IMAGE_HEADER_SIZE) })
+}
+
+/// Reads the state of the partition whose trailer (of `len` bytes) ends at `trailer`. With the
+/// `state-journal` feature, it's the newest record in the journal i.e. the trailer's first (and
+/// lowest) `STATE_SLOTS` slots.
+fn partition_state(trailer: usize, len: usize) -> Result<PartitionState> {
+    let magic = unsafe { core::ptr::read((trailer - MAGIC_TRAIL_LEN) as *const [u8; 4]) };
+    if magic != (RUSTBOOT_MAGIC_TRAIL as u32).to_le_bytes() {
+        return Ok(PartitionState::New);
+    }
+    #[cfg(feature = "state-journal")]
+    {
+        let journal = trailer - len;
+        let slots = (0..STATE_SLOTS).map(|idx| unsafe {
+            core::slice::from_raw_parts(
+                (journal + idx * STATE_SLOT_LEN) as *const u8,
+                STATE_SLOT_LEN,
+            )
+        });
+        return Ok(StateRecord::newest(slots).map_or(PartitionState::New, |record| record.state));
+    }
+    #[cfg(not(feature = "state-journal"))]
+    {
+        let _ = len;
+        let state = unsafe { *((trailer - MAGIC_TRAIL_LEN - PART_STATUS_LEN) as *const u8) };
+        PartitionState::from_byte(state)
+    }
+}
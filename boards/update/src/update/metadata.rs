@@ -0,0 +1,78 @@
+//! The boot and update partitions' metadata sectors (see the `metadata-sector` feature) i.e. the
+//! sectors the board's manifest reserves for the partitions' trailers (`boot_metadata` and
+//! `update_metadata`).
+//!
+//! rustBoot builds without the feature keep the trailers at the end of the partitions. A board
+//! that moves to metadata sectors keeps its state i.e. on boot, a trailer found at the end of a
+//! partition is copied to the partition's (blank) metadata sector, see
+//! [`FlashUpdater::migrate_trailers`]. The old trailer is left as is (it may share its sector with
+//! the image), it's erased along with the partition's last sector by the next swap.
+
+use rustBoot::constants::*;
+use rustBoot::progress::Progress;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::FlashInterface;
+
+use super::swap::SwapPolicy;
+use super::update_flash::{flash_error, FlashUpdater};
+
+/// Returns `true` if the trailer ending at `trailer` holds the trailer magic.
+fn has_trailer_magic(trailer: usize) -> bool {
+    let magic = unsafe { core::ptr::read((trailer - MAGIC_TRAIL_LEN) as *const [u8; 4]) };
+    magic == (RUSTBOOT_MAGIC_TRAIL as u32).to_le_bytes()
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Copies the trailers left at the end of the boot and update partitions (i.e. by a rustBoot
+    /// without metadata sectors) to their metadata sectors. A partition whose metadata sector
+    /// already holds a trailer isn't migrated again.
+    ///
+    /// The trailer magic is written last, a migration that's interrupted is redone on the next
+    /// boot.
+    pub(crate) fn migrate_trailers(&self) -> Result<()> {
+        let trailers = [
+            (
+                BOOT_PARTITION_ADDRESS + PARTITION_SIZE,
+                BOOT_TRAILER_ADDRESS,
+                BOOT_TRAILER_LEN,
+            ),
+            (
+                UPDATE_PARTITION_ADDRESS + PARTITION_SIZE,
+                UPDATE_TRAILER_ADDRESS,
+                UPDATE_TRAILER_LEN,
+            ),
+        ];
+        for (old, trailer, len) in trailers {
+            if has_trailer_magic(trailer) || !has_trailer_magic(old) {
+                continue;
+            }
+            let mut buf = [0u8; UPDATE_TRAILER_LEN];
+            let buf = buf.get_mut(..len).ok_or(RustbootError::Unreachable)?;
+            self.iface()
+                .hal_flash_read(old - len, buf)
+                .map_err(flash_error)?;
+            self.erase_metadata(trailer - SECTOR_SIZE)?;
+            // flash is written in ascending order, the magic ends the trailer
+            self.write(trailer - len, buf)?;
+        }
+        Ok(())
+    }
+
+    /// Erases the boot and update partitions' trailers i.e. resets both partitions to `New`, once
+    /// a swap is done. The boot partition's trailer is erased first.
+    pub(crate) fn reset_trailers(&self) -> Result<()> {
+        self.erase_metadata(BOOT_METADATA_ADDRESS)?;
+        self.erase_metadata(UPDATE_METADATA_ADDRESS)
+    }
+
+    fn erase_metadata(&self, addr: usize) -> Result<()> {
+        self.iface()
+            .hal_flash_erase(addr, SECTOR_SIZE)
+            .map_err(flash_error)
+    }
+}
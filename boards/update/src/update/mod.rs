@@ -1,3 +1,26 @@
+#[cfg(feature = "boot-pin")]
+pub mod boot_pin;
+#[cfg(feature = "boot-reason")]
+pub mod boot_reason;
+#[cfg(feature = "compression")]
+pub mod decompress;
+#[cfg(feature = "event-log")]
+pub mod events;
+pub mod info;
+#[cfg(feature = "metadata-sector")]
+pub mod metadata;
+#[cfg(feature = "provisioning")]
+pub mod provision;
+#[cfg(feature = "boot-redirect")]
+pub mod redirect;
+#[cfg(feature = "release")]
+pub mod release;
+pub mod report;
+pub mod retire;
+#[cfg(feature = "two-stage")]
+pub mod stage1;
+pub mod staging;
+pub mod swap;
 pub mod update_flash;
 
 use rustBoot::flashapi::FlashApi;
@@ -5,6 +28,19 @@ use rustBoot::Result;
 
 pub trait UpdateInterface: FlashApi {
     fn rustboot_start(self) -> !;
+    /// Marks the update for installation. rustBoot swaps it in on the next boot and boots it
+    /// in the `testing` state i.e. unless the update calls [`UpdateInterface::update_success`],
+    /// the following boot swaps the images back. The restored image is itself tested, so
+    /// rustBoot keeps swapping until either image confirms itself.
+    ///
+    /// An update that's still being staged (see [`staging`]) isn't triggered.
     fn update_trigger(self) -> Result<()>;
+    /// Marks the update for a single trial boot (i.e. MCUboot's `test`). rustBoot swaps it in
+    /// and boots it once - unless the update calls [`UpdateInterface::update_success`], the
+    /// very next boot reverts to the previous image, for good i.e. the restored image is
+    /// confirmed. As with `update_trigger`, a staged update must be finalized first.
+    fn update_test(self) -> Result<()>;
+    /// Confirms the running image i.e. an update that's being tested is kept. The image it
+    /// replaced is retired, see [`retire`].
     fn update_success(self) -> Result<()>;
 }
@@ -0,0 +1,69 @@
+//! First-boot provisioning (see the `provisioning` feature) i.e. a routine the board's bootloader
+//! runs once, before the first application is booted (ex: to generate device keys, write a serial
+//! number or lock option bytes).
+//!
+//! Whether it ran is recorded in the flag word at the start of the sector the board's manifest
+//! reserves for it (i.e. its `provision`). The flag is written once the routine returns, in a
+//! single write (i.e. a whole unit of flash), so a provisioning that's interrupted (or fails) is
+//! run again on the next boot. The routine should then be idempotent.
+//!
+//! The board's bootloader runs it before starting rustBoot, for example
+//!
+//! ```ignore
+//! let updater = FlashUpdater::new(flash_writer);
+//! if updater.provision_once(&FactoryProvisioning).is_err() {
+//!     panic!("provisioning failed");
+//! }
+//! updater.rustboot_start()
+//! ```
+
+use rustBoot::constants::*;
+use rustBoot::progress::Progress;
+use rustBoot::Result;
+use rustBoot_hal::FlashInterface;
+
+use super::swap::SwapPolicy;
+use super::update_flash::{flash_error, FlashUpdater};
+
+/// The flag word of a provisioned device.
+const PROVISIONED: u32 = 0x4E56_5250; // PRVN
+
+/// A first-boot provisioning routine, implemented by the board.
+pub trait Provision {
+    /// Provisions the device. An error leaves it unprovisioned i.e. it's retried on the next
+    /// boot.
+    fn provision(&self) -> Result<()>;
+}
+
+fn flag() -> u32 {
+    unsafe { core::ptr::read_volatile(PROVISION_FLAG_ADDRESS as *const u32) }
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Returns `true` once the device has been provisioned.
+    pub fn is_provisioned(&self) -> bool {
+        flag() == PROVISIONED
+    }
+
+    /// Runs `routine`, unless the device has already been provisioned, and marks the device as
+    /// provisioned once it succeeds. Returns `true` if the routine was run.
+    pub fn provision_once(&self, routine: &impl Provision) -> Result<bool> {
+        match flag() {
+            PROVISIONED => return Ok(false),
+            0xFFFF_FFFF => {}
+            // a flag that was only partially written
+            _ => self
+                .iface()
+                .hal_flash_erase(PROVISION_FLAG_ADDRESS, SECTOR_SIZE)
+                .map_err(flash_error)?,
+        }
+        routine.provision()?;
+        self.write(PROVISION_FLAG_ADDRESS, &PROVISIONED.to_le_bytes())?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,127 @@
+//! Boot redirection (see the `boot-redirect` feature) i.e. firmware asks rustBoot to boot a signed
+//! image at another address once, ex: diagnostic firmware shipped alongside the application. The
+//! request is kept in the board's backup registers, see `rustBoot::redirect` for its format.
+//!
+//! ```ignore
+//! rustBoot_update::update::redirect::request_redirect(DIAG_IMAGE_ADDRESS, VersionPolicy::STRICT)?;
+//! cortex_m::peripheral::SCB::sys_reset();
+//! ```
+//!
+//! rustBoot takes (i.e. reads and clears) a pending request on every boot. Once it's done with
+//! updates and rollbacks, it checks the requested image as it checks the boot partition's (its
+//! digest, signature, board id and vector table) and boots it in its place. Its version is held to
+//! the updater's `VersionPolicy` against the boot partition's image (see
+//! `rustBoot::redirect::check_image`) i.e. a redirect can't boot an old image past anti-rollback.
+//! An image that doesn't check out is logged and the boot partition's image is booted, as usual.
+//!
+//! The image must lie outside the bootloader and the partitions (see [`PARTITIONS`]) i.e. it
+//! doesn't disturb the boot and update partitions, and it's linked to run right after its header.
+//! It's booted as is i.e. it has no trailer or state, and can't be updated through rustBoot.
+//!
+//! *Note: boards without backup registers (i.e. the nrf52840) can't request a redirect.*
+
+use core::convert::TryInto;
+
+use rustBoot::chain::SignedImage;
+use rustBoot::constants::*;
+use rustBoot::eventlog::Event;
+use rustBoot::image::vectors::check_vector_table;
+use rustBoot::parser::vendor_tlvs;
+use rustBoot::progress::Progress;
+use rustBoot::redirect::{check_image, check_target, BootRedirect, REQUEST_WORDS};
+use rustBoot::version::VersionPolicy;
+use rustBoot::{HalError, HalErrorKind, Result, RustbootError};
+use rustBoot_hal::{FlashInterface, BACKUP_REGS};
+
+use super::info::image_version;
+use super::swap::SwapPolicy;
+use super::update_flash::{FlashUpdater, BOARD_ID};
+use crate::hal::hal::*;
+
+/// A request must fit the backup registers.
+const _: () = assert!(REQUEST_WORDS <= BACKUP_REGS);
+
+/// Asks rustBoot to boot the image at `address` (i.e. its header) on the next boot, once. The
+/// image is checked as rustBoot checks it (with `policy`, i.e. rustBoot's version policy), so a
+/// request it would refuse isn't made.
+///
+/// Returns a [`HalErrorKind::Unsupported`] error if the board has no backup registers.
+pub fn request_redirect(address: usize, policy: VersionPolicy) -> Result<()> {
+    check_redirect(address, &policy)?;
+    let mut regs = [0u32; BACKUP_REGS];
+    regs.get_mut(..REQUEST_WORDS)
+        .ok_or(RustbootError::Unreachable)?
+        .copy_from_slice(
+            &BootRedirect {
+                address: address as u32,
+            }
+            .to_words(),
+        );
+    match hal_write_backup_regs(&regs) {
+        true => Ok(()),
+        false => Err(HalError::new(HalErrorKind::Unsupported, 0).into()),
+    }
+}
+
+/// Returns the pending request, if any, and clears it. Backup registers that don't hold a
+/// request (ex: a panic record) are left as they are.
+pub(crate) fn take_redirect() -> Option<BootRedirect> {
+    let regs = hal_read_backup_regs()?;
+    let request = BootRedirect::from_words(regs.get(..REQUEST_WORDS)?.try_into().ok()?)?;
+    hal_write_backup_regs(&[0; BACKUP_REGS]);
+    Some(request)
+}
+
+/// The flash a redirect's image may lie in i.e. above the bootloader, outside the partitions.
+fn flash() -> core::ops::Range<usize> {
+    BOOT_PARTITION_ADDRESS..BOOTLOADER_ADDRESS + FLASH_SIZE
+}
+
+/// Checks the image at `address`, its version against the boot partition's with `policy`.
+/// Returns it, along with its firmware's address.
+fn check_redirect(address: usize, policy: &VersionPolicy) -> Result<(SignedImage<'static>, usize)> {
+    check_target(address, IMAGE_HEADER_SIZE, flash(), &PARTITIONS)?;
+    let header = unsafe { core::slice::from_raw_parts(address as *const u8, IMAGE_HEADER_SIZE) };
+    let fw_size = header
+        .get(4..8)
+        .and_then(|size| size.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(RustbootError::InvalidImage)? as usize;
+    let len = IMAGE_HEADER_SIZE.saturating_add(fw_size);
+    check_target(address, len, flash(), &PARTITIONS)?;
+    let image =
+        SignedImage::parse(unsafe { core::slice::from_raw_parts(address as *const u8, len) })?;
+
+    if let Some(board_id) = BOARD_ID {
+        match vendor_tlvs(image.header())?.board_id()? {
+            Some(id) if id != board_id => return Err(RustbootError::BoardMismatch),
+            _ => {}
+        }
+    }
+    check_image(&image, image_version(BOOT_PARTITION_ADDRESS)?, policy)?;
+    let fw_base = address + IMAGE_HEADER_SIZE;
+    let ram = hal_ram_region().unwrap_or(0..usize::MAX);
+    check_vector_table(image.firmware(), ram, fw_base..address + len)?;
+    Ok((image, fw_base))
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Boots the image `request` selected, if it checks out. Returns otherwise i.e. the boot
+    /// partition's image is booted.
+    pub(crate) fn boot_redirect(&self, request: &BootRedirect) {
+        match check_redirect(request.address as usize, &self.version_policy) {
+            Ok((image, fw_base)) => {
+                let version = image.get_firmware_version().unwrap_or(0);
+                self.log_event(Event::Redirected, None, version);
+                hal_preboot();
+                hal_boot_from(fw_base)
+            }
+            Err(e) => self.log_event(Event::VerifyFailed, Some(e), 0),
+        }
+    }
+}
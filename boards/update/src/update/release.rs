@@ -0,0 +1,51 @@
+//! Checking an offered update against a fleet's release manifest, before it's staged (see
+//! [`rustBoot::release`] and [`super::staging`]).
+//!
+//! ```ignore
+//! let digest = updater.authorize_update(&release_manifest, BOARD_ID, offer.version)?;
+//! // stage the update, as offered, then check it against the manifest's digest
+//! updater.finalize_update(&digest)?;
+//! updater.update_trigger()?;
+//! ```
+
+use rustBoot::constants::SHA256_DIGEST_SIZE;
+use rustBoot::progress::Progress;
+use rustBoot::release::ReleaseManifest;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::FlashInterface;
+
+use super::swap::SwapPolicy;
+use super::update_flash::FlashUpdater;
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Checks that `release_manifest` (a signed release manifest) authorizes `version` of the
+    /// firmware for this board (i.e. `board_id`) and that the updater's version policy lets it
+    /// replace the running image. Returns the digest the staged update must match i.e. to be
+    /// passed to [`FlashUpdater::finalize_update`]. Returns
+    ///
+    /// - [`RustbootError::InvalidImage`] or [`RustbootError::FwAuthFailed`] if the manifest is
+    ///   malformed or its signature doesn't check out.
+    /// - [`RustbootError::FwAuthFailed`] if the manifest doesn't list the update.
+    /// - [`RustbootError::BadVersion`] if the update can't replace the running image.
+    pub fn authorize_update(
+        &self,
+        release_manifest: &[u8],
+        board_id: &[u8],
+        version: u32,
+    ) -> Result<[u8; SHA256_DIGEST_SIZE]> {
+        let release = ReleaseManifest::parse(release_manifest)?;
+        let entry = release
+            .find(board_id, version)
+            .ok_or(RustbootError::FwAuthFailed)?;
+        let installed = self.boot_image_info()?.version;
+        if !self.version_policy.permits(installed, version) {
+            return Err(RustbootError::BadVersion);
+        }
+        Ok(*entry.digest)
+    }
+}
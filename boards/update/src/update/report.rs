@@ -0,0 +1,46 @@
+//! A summary of the device's security state, as observed (or changed) by rustBoot on its way to
+//! booting firmware.
+
+use rustBoot::bootreason::BootReason;
+use rustBoot_hal::DebugProtection;
+
+/// The boot report for the current boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootReport {
+    /// debug-access protection, as read back from the device.
+    pub debug_protection: DebugProtection,
+    /// set if debug-access protection was programmed during this boot i.e. the first boot of a
+    /// `production` build. On most parts, it only takes effect after the next reset.
+    pub debug_protection_programmed: bool,
+    /// set if an image was accepted without a valid signature i.e. by a `dev-unsigned` build.
+    /// Such a device must never be deployed.
+    pub unsigned_image: bool,
+    /// the time (in seconds since the unix epoch) rustBoot booted at, per the board's RTC. `None`
+    /// if the board has no calendar RTC or if it was never set.
+    pub time: Option<u64>,
+    /// why the mcu booted and what rustBoot did on the way, as recorded for firmware (see
+    /// `boot_reason`). `None` without the `boot-reason` feature.
+    pub boot_reason: Option<BootReason>,
+}
+
+static mut BOOT_REPORT: Option<BootReport> = None;
+static mut UNSIGNED_IMAGE: bool = false;
+
+pub(crate) fn set_boot_report(report: BootReport) {
+    unsafe { BOOT_REPORT = Some(report) }
+}
+
+/// Records that an image was accepted without a valid signature, see `dev-unsigned`.
+pub(crate) fn flag_unsigned_image() {
+    unsafe { UNSIGNED_IMAGE = true }
+}
+
+pub(crate) fn unsigned_image() -> bool {
+    unsafe { UNSIGNED_IMAGE }
+}
+
+/// Returns the report for the current boot, once rustBoot is done checking the device i.e.
+/// right before it jumps to firmware.
+pub fn boot_report() -> Option<BootReport> {
+    unsafe { BOOT_REPORT }
+}
@@ -0,0 +1,113 @@
+//! Retiring the update partition's image i.e. the image an update replaced, once the update is
+//! confirmed.
+//!
+//! A swap leaves the previous image in the update partition, so an update that isn't confirmed
+//! can be rolled back. Once the update confirms itself (see `update_success`), that image is
+//! marked as retired in the update partition's trailer (see [`Retired`]) and is then erased
+//!
+//! - by rustBoot, once the confirmed image has booted a number of times, see
+//!   [`FlashUpdater::with_retired_erase`].
+//! - or by the application, one sector at a time (ex: while it's idle), see
+//!   [`FlashUpdater::erase_retired_update`].
+//!
+//! Sectors are erased in order, the trailer's sector (i.e. the retired mark) last, so an erase
+//! that's interrupted is resumed. Staging an update erases the trailer, which clears the mark i.e.
+//! a staged update is never erased.
+//!
+//! *Note: a partition that's been erased holds no image to fall back to i.e. a boot image that
+//! fails verification can't be replaced by an emergency update. Compressed updates (see
+//! `decompress`) aren't retired, the update partition holds the installed update.*
+
+use rustBoot::constants::*;
+use rustBoot::image::image::Retired;
+use rustBoot::progress::Progress;
+use rustBoot::Result;
+use rustBoot_hal::FlashInterface;
+
+use super::swap::SwapPolicy;
+use super::update_flash::{flash_error, FlashUpdater};
+
+/// The retired byte's offset in the update partition's trailer i.e. it follows the trial byte.
+const RETIRED_OFFSET: usize = 3 + (PARTITION_SIZE / SECTOR_SIZE + 1) / 2;
+const RETIRED_ADDRESS: usize = UPDATE_TRAILER_ADDRESS - (MAGIC_TRAIL_LEN + RETIRED_OFFSET);
+
+/// The update partition's sectors that precede its trailer's sector.
+const IMAGE_SECTORS: usize = PARTITION_SIZE / SECTOR_SIZE - TRAILER_SECTORS;
+
+fn has_trailer_magic() -> bool {
+    let magic =
+        unsafe { core::ptr::read((UPDATE_TRAILER_ADDRESS - MAGIC_TRAIL_LEN) as *const [u8; 4]) };
+    magic == (RUSTBOOT_MAGIC_TRAIL as u32).to_le_bytes()
+}
+
+/// Returns the update partition's retired byte, `None` if its image isn't retired.
+fn retired() -> Option<Retired> {
+    match has_trailer_magic() {
+        true => Retired::from_byte(unsafe { *(RETIRED_ADDRESS as *const u8) }),
+        false => None,
+    }
+}
+
+fn is_erased(addr: usize) -> bool {
+    let sector = unsafe { core::slice::from_raw_parts(addr as *const u8, SECTOR_SIZE) };
+    sector.iter().all(|byte| *byte == 0xFF)
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Erases the next sector of a retired image, returning `true` once the update partition
+    /// holds no retired image (i.e. it's been erased, or there was nothing to erase). At most one
+    /// sector is erased per call, so it can be called from an application's idle loop.
+    pub fn erase_retired_update(&self) -> Result<bool> {
+        if retired().is_none() {
+            return Ok(true);
+        }
+        let next = (0..IMAGE_SECTORS)
+            .map(|sector| UPDATE_PARTITION_ADDRESS + sector * SECTOR_SIZE)
+            .find(|addr| !is_erased(*addr));
+        let addr = next.unwrap_or(UPDATE_TRAILER_ADDRESS - SECTOR_SIZE);
+        self.iface()
+            .hal_flash_erase(addr, SECTOR_SIZE)
+            .map_err(flash_error)?;
+        Ok(next.is_none())
+    }
+
+    /// Marks the update partition's image as retired, once the update that replaced it is
+    /// confirmed. A partition without an image isn't marked.
+    pub(crate) fn retire_update(&self) -> Result<()> {
+        let magic = unsafe { core::ptr::read(UPDATE_PARTITION_ADDRESS as *const [u8; 4]) };
+        if magic != (RUSTBOOT_MAGIC as u32).to_le_bytes() || retired().is_some() {
+            return Ok(());
+        }
+        self.write(RETIRED_ADDRESS, &[Retired::MARKED.as_byte()])?;
+        if !has_trailer_magic() {
+            self.write(
+                UPDATE_TRAILER_ADDRESS - MAGIC_TRAIL_LEN,
+                &(RUSTBOOT_MAGIC_TRAIL as u32).to_le_bytes(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Counts a boot of the confirmed image and, once the boots set with
+    /// [`FlashUpdater::with_retired_erase`] are reached, erases the retired image.
+    pub(crate) fn count_retired_boot(&self) -> Result<()> {
+        let (after, retired) = match (self.retired_erase, retired()) {
+            (Some(after), Some(retired)) => (after, retired.counted()),
+            _ => return Ok(()),
+        };
+        if retired.boots() < after {
+            return self.write(RETIRED_ADDRESS, &[retired.as_byte()]);
+        }
+        for _ in 0..=IMAGE_SECTORS {
+            if self.erase_retired_update()? {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+//! rustBoot as stage1 of a two-stage bootloader (see the `two-stage` feature) i.e. an immutable
+//! stage0 (see `boards/stage0`) at the start of flash boots the newest valid stage1 out of two
+//! slots (the manifest's `[stage0]`), so rustBoot itself can be updated.
+//!
+//! A stage1 update is a signed image, linked to run from the slot it's installed to (see `cargo
+//! <board> sign stage1`). Firmware stages it in the update partition, as it would a firmware
+//! update, and installs it to the slot stage0 didn't boot
+//!
+//! ```ignore
+//! // each chunk, then
+//! updater.finalize_update(&expected_digest)?;
+//! updater.install_stage1()?;
+//! cortex_m::peripheral::SCB::sys_reset();
+//! ```
+//!
+//! The new stage1 must have a higher version than the one that's running. stage0 verifies both
+//! slots on every boot, so an interrupted install (or a stage1 that doesn't check out) leaves the
+//! running stage1 in charge.
+//!
+//! *Note: stage1 only write-protects (and, with `hide-bootloader`, hides) stage0, the stage1 slots
+//! are left writable so that firmware can install a stage1 update. A stage1 slot is only ever
+//! booted once stage0 has verified it.*
+
+use rustBoot::chain::{newest_image, SignedImage};
+use rustBoot::constants::*;
+use rustBoot::image::vectors::check_vector_table;
+use rustBoot::progress::Progress;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::FlashInterface;
+
+use super::swap::SwapPolicy;
+use super::update_flash::{flash_error, FlashUpdater};
+use crate::hal::hal::*;
+
+/// The stage1 slots, as `(address, size)`.
+pub const STAGE1_SLOTS: [(usize, usize); 2] = [
+    (STAGE1_A_ADDRESS, STAGE1_SIZE),
+    (STAGE1_B_ADDRESS, STAGE1_SIZE),
+];
+
+fn slot(idx: usize) -> Result<&'static [u8]> {
+    let (addr, size) = STAGE1_SLOTS.get(idx).ok_or(RustbootError::Unreachable)?;
+    Ok(unsafe { core::slice::from_raw_parts(*addr as *const u8, *size) })
+}
+
+/// Checks that a stage1 image in slot `idx` is linked to run from it, as stage0 does.
+fn check_slot(idx: usize, image: &SignedImage) -> Result<()> {
+    let (addr, size) = STAGE1_SLOTS.get(idx).ok_or(RustbootError::Unreachable)?;
+    let ram = hal_ram_region().unwrap_or(0..usize::MAX);
+    check_vector_table(image.firmware(), ram, addr + IMAGE_HEADER_SIZE..addr + size)
+}
+
+/// Returns the slot (i.e. its index in [`STAGE1_SLOTS`]) and version of the stage1 stage0 boots
+/// i.e. the newest valid one, if any.
+pub fn booted_stage1() -> Option<(usize, u32)> {
+    let slots = [slot(0).ok()?, slot(1).ok()?];
+    let (idx, image) = newest_image(&slots, check_slot)?;
+    Some((idx, image.get_firmware_version().ok()?))
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Installs the stage1 update staged in the update partition (see `staging`) to the slot
+    /// stage0 didn't boot, and erases it from the update partition. Returns the slot's index in
+    /// [`STAGE1_SLOTS`], stage0 boots it from the next reset. Returns
+    ///
+    /// - [`RustbootError::InvalidImage`] if the staged image isn't linked to run from that slot.
+    /// - [`RustbootError::InvalidFirmwareSize`] if it doesn't fit the slot.
+    /// - [`RustbootError::BadVersion`] if it isn't newer than the booted stage1.
+    /// - the staged image's verification error, if it doesn't check out.
+    pub fn install_stage1(&self) -> Result<usize> {
+        let staged = unsafe {
+            core::slice::from_raw_parts(UPDATE_PARTITION_ADDRESS as *const u8, PARTITION_SIZE)
+        };
+        let image = SignedImage::parse(staged)?;
+        image.verify()?;
+        let (idx, version) = match booted_stage1() {
+            Some((booted, version)) => (booted ^ 1, version),
+            None => (0, 0),
+        };
+        if image.get_firmware_version()? <= version {
+            return Err(RustbootError::BadVersion);
+        }
+        let (addr, size) = *STAGE1_SLOTS.get(idx).ok_or(RustbootError::Unreachable)?;
+        let len = IMAGE_HEADER_SIZE + image.firmware().len();
+        if len > size {
+            return Err(RustbootError::InvalidFirmwareSize);
+        }
+        check_slot(idx, &image)?;
+
+        // the header goes last, so an interrupted install leaves no image in the slot
+        self.iface()
+            .hal_flash_erase(addr, size)
+            .map_err(flash_error)?;
+        self.write(addr + IMAGE_HEADER_SIZE, image.firmware())?;
+        self.write(addr, image.header())?;
+        let installed = slot(idx)?.get(..len).ok_or(RustbootError::Unreachable)?;
+        SignedImage::parse(installed)?.verify()?;
+
+        // so the staged stage1 can't be triggered as a firmware update
+        self.iface()
+            .hal_flash_erase(UPDATE_PARTITION_ADDRESS, SECTOR_SIZE)
+            .map_err(flash_error)?;
+        Ok(idx)
+    }
+}
+
+/// The region stage1 write-protects (and hides) before booting firmware i.e. stage0, see the
+/// module's docs.
+pub(crate) fn protected_region() -> (usize, usize) {
+    (BOOTLOADER_ADDRESS, STAGE0_SIZE)
+}
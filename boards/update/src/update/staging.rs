@@ -0,0 +1,149 @@
+//! Staging an update i.e. writing an image received over the air (or any other transport) to the
+//! update partition, one chunk at a time.
+//!
+//! An OTA agent hands each chunk to [`FlashUpdater::write_update_chunk`], along with its offset in
+//! the image, and then checks the staged image against the digest it was given for it with
+//! [`FlashUpdater::finalize_update`]. The updater
+//!
+//! - only accepts chunks in order i.e. each chunk must start where the last one ended. A chunk at
+//!   offset `0` (re)starts staging.
+//! - erases sectors ahead of the writes and widens writes to whole units of flash, so chunks can
+//!   be of any size.
+//! - refuses to overwrite a pending update and to trigger an update that's still being staged.
+//!
+//! The transport is up to the agent (ex: a socket, a UART or a scripting runtime's byte buffer),
+//! for example
+//!
+//! ```ignore
+//! let mut updater = FlashUpdater::new(flash_writer);
+//! let mut offset = 0;
+//! while let Some(chunk) = transport.next_chunk(&mut buf)? {
+//!     updater.write_update_chunk(offset, chunk)?;
+//!     offset += chunk.len();
+//! }
+//! updater.finalize_update(&transport.expected_digest())?;
+//! updater.update_trigger()?;
+//! ```
+//!
+//! Applications should stage updates this way, rather than write the update partition through
+//! [`FlashApi`](rustBoot::flashapi::FlashApi).
+
+use rustBoot::chain::find_header;
+use rustBoot::constants::*;
+use rustBoot::image::image::PartitionState;
+use rustBoot::progress::Progress;
+use rustBoot::{Result, RustbootError};
+use rustBoot_hal::FlashInterface;
+use sha2::{Digest, Sha256};
+
+use super::swap::SwapPolicy;
+use super::update_flash::{flash_error, FlashUpdater};
+
+/// The most bytes that can be staged, the partition's last sector may hold its trailer.
+const MAX_STAGED: usize = PARTITION_SIZE - TRAILER_SECTORS * SECTOR_SIZE;
+
+/// An update being staged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Staging {
+    /// the number of bytes staged i.e. the offset of the next chunk.
+    next: usize,
+    /// the number of bytes erased, from the start of the update partition.
+    erased: usize,
+}
+
+fn staged(len: usize) -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(UPDATE_PARTITION_ADDRESS as *const u8, len) }
+}
+
+/// Returns the offset of the staged image's header (i.e. past any padding, see
+/// [`HEADER_SEARCH_WINDOW`]), if there's one.
+fn header_offset(bytes: &[u8]) -> Option<usize> {
+    find_header(bytes, HEADER_SEARCH_WINDOW, HEADER_SEARCH_ALIGN)
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Writes a chunk of an update to the update partition, at `offset` from the start of the
+    /// image. Returns
+    ///
+    /// - [`RustbootError::InvalidState`] if no update is being staged (i.e. the first chunk must
+    ///   be at offset `0`) or if the update partition holds a pending update.
+    /// - [`RustbootError::InvalidValue`] if the chunk doesn't start where the last one ended.
+    /// - [`RustbootError::InvalidImage`] if the image doesn't start with a rustBoot header (past
+    ///   any padding, see [`HEADER_SEARCH_WINDOW`]).
+    /// - [`RustbootError::InvalidFirmwareSize`] if the chunk doesn't fit the partition.
+    pub fn write_update_chunk(&mut self, offset: usize, data: &[u8]) -> Result<()> {
+        if offset == 0 {
+            // the first chunk may not span the header's window
+            if data.len() >= HEADER_SEARCH_WINDOW + 4 && header_offset(data).is_none() {
+                return Err(RustbootError::InvalidImage);
+            }
+            let pending = self
+                .update_image_info()
+                .map_or(false, |info| info.state == PartitionState::Updating);
+            if pending {
+                return Err(RustbootError::InvalidState);
+            }
+            // erasing the trailer (i.e. the sector it ends with) resets the partition's state
+            self.iface()
+                .hal_flash_erase(UPDATE_TRAILER_ADDRESS - SECTOR_SIZE, SECTOR_SIZE)
+                .map_err(flash_error)?;
+            self.staging = Some(Staging { next: 0, erased: 0 });
+        }
+        let mut staging = self.staging.ok_or(RustbootError::InvalidState)?;
+        if offset != staging.next {
+            return Err(RustbootError::InvalidValue);
+        }
+        let end = offset + data.len();
+        if end > MAX_STAGED {
+            return Err(RustbootError::InvalidFirmwareSize);
+        }
+        while staging.erased < end {
+            self.iface()
+                .hal_flash_erase(UPDATE_PARTITION_ADDRESS + staging.erased, SECTOR_SIZE)
+                .map_err(flash_error)?;
+            staging.erased += SECTOR_SIZE;
+            self.staging = Some(staging);
+        }
+        self.write(UPDATE_PARTITION_ADDRESS + offset, data)?;
+        staging.next = end;
+        self.staging = Some(staging);
+        Ok(())
+    }
+
+    /// Ends staging, checking the staged update against `expected_digest` i.e. the SHA-256 digest
+    /// of the bytes written. Returns
+    ///
+    /// - [`RustbootError::InvalidState`] if no update is being staged.
+    /// - [`RustbootError::BadHashValue`] if the digest doesn't match. The staged update's first
+    ///   sector is erased, so it can't be triggered.
+    /// - [`RustbootError::InvalidImage`] if no (or only part of a) rustBoot header was staged.
+    ///
+    /// Either way, the next update must be staged from offset `0`. A finalized update is
+    /// installed once it's triggered, see [`UpdateInterface`](super::UpdateInterface).
+    pub fn finalize_update(&mut self, expected_digest: &[u8; SHA256_DIGEST_SIZE]) -> Result<()> {
+        let staging = self.staging.take().ok_or(RustbootError::InvalidState)?;
+        let staged = staged(staging.next);
+        if Sha256::digest(staged).as_slice() != expected_digest {
+            self.iface()
+                .hal_flash_erase(UPDATE_PARTITION_ADDRESS, SECTOR_SIZE)
+                .map_err(flash_error)?;
+            return Err(RustbootError::BadHashValue);
+        }
+        let header = header_offset(staged);
+        if header.map_or(true, |offset| staged.len() < offset + IMAGE_HEADER_SIZE) {
+            return Err(RustbootError::InvalidImage);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` while an update is being staged i.e. it's been written to but not
+    /// finalized.
+    pub fn is_staging(&self) -> bool {
+        self.staging.is_some()
+    }
+}
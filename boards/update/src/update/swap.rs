@@ -0,0 +1,229 @@
+//! Swap policies i.e. how an update is exchanged with the image in the boot partition.
+//!
+//! Both policies are interruptible - each sector's progress is saved in the update partition's
+//! sector flags (see [`SectorFlag`]) and an interrupted swap is resumed on the next boot. Verifying
+//! an update before it is swapped in and resetting the partitions' trailers afterwards is up to the
+//! updater, and is the same for both policies.
+//!
+//! - [`SectorSwap`] (the default) exchanges each sector through the swap partition. The swap
+//!   partition's (single) sector is erased once for every sector of the image.
+//! - [`MoveSwap`] moves the update up by one sector within the update partition and then backs up
+//!   each boot sector in the slot freed up below it. It doesn't use the swap partition, at the cost
+//!   of one more sector in the update partition i.e. images must be at least 2 sectors smaller than
+//!   a partition (the last sector holds the trailer) or, with the `metadata-sector` feature, 1
+//!   sector smaller.
+//!
+//! A sector copy whose write or erase fails (see [`FlashUpdater::with_verified_writes`](super::update_flash::FlashUpdater::with_verified_writes))
+//! is retried. If it keeps failing, the swap is aborted with the sector's flag unchanged i.e. the
+//! copy is retried on the next boot.
+//!
+//! A board picks its policy with [`FlashUpdater::with_swap_policy`](super::update_flash::FlashUpdater::with_swap_policy).
+
+use rustBoot::constants::*;
+use rustBoot::flashapi::FlashApi;
+use rustBoot::image::image::*;
+use rustBoot::progress::{Phase, Progress};
+use rustBoot::{Result, RustbootError};
+
+/// How the boot and update images are exchanged.
+pub trait SwapPolicy: Copy {
+    /// Checks that images of `total_size` bytes (i.e. the larger of the 2 images, header
+    /// included) can be exchanged.
+    fn fits(&self, total_size: usize) -> bool;
+
+    /// Returns `true` if a swap has already started i.e. it was interrupted and is to be
+    /// resumed, in which case the update isn't verified again.
+    fn started(&self, updt: &PartDescriptor<Update>, total_size: usize) -> bool;
+
+    /// Exchanges the first `total_size` bytes of the boot and update partitions, resuming from
+    /// the update partition's sector flags. Returns the number of sectors exchanged.
+    ///
+    /// `progress` is called with [`Phase::Swap`] as sectors are exchanged.
+    fn swap(
+        &self,
+        updater: impl FlashApi,
+        boot: &PartDescriptor<Boot>,
+        updt: &PartDescriptor<Update>,
+        swap: &PartDescriptor<Swap>,
+        total_size: usize,
+        progress: &impl Progress,
+    ) -> Result<usize>;
+}
+
+/// Exchanges each sector through the swap partition i.e. update -> swap, boot -> update and
+/// swap -> boot.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SectorSwap;
+
+impl SwapPolicy for SectorSwap {
+    fn fits(&self, total_size: usize) -> bool {
+        total_size <= PARTITION_SIZE
+    }
+
+    fn started(&self, updt: &PartDescriptor<Update>, _total_size: usize) -> bool {
+        sector_flag(updt, 0) != SectorFlag::New
+    }
+
+    fn swap(
+        &self,
+        updater: impl FlashApi,
+        boot: &PartDescriptor<Boot>,
+        updt: &PartDescriptor<Update>,
+        swap: &PartDescriptor<Swap>,
+        total_size: usize,
+        progress: &impl Progress,
+    ) -> Result<usize> {
+        let sectors = sectors(total_size);
+        for sector in 0..sectors {
+            let mut flag = sector_flag(updt, sector);
+            while let Some(next) = flag.next() {
+                match flag {
+                    SectorFlag::New => {
+                        copy_sector(updater, (updt, sector), (swap, 0), image_len(updt, sector))?
+                    }
+                    SectorFlag::Swapping => copy_sector(
+                        updater,
+                        (boot, sector),
+                        (updt, sector),
+                        image_len(boot, sector),
+                    )?,
+                    SectorFlag::Backup => {
+                        copy_sector(updater, (swap, 0), (boot, sector), SECTOR_SIZE)?
+                    }
+                    SectorFlag::Updated => return Err(RustbootError::InvalidSectFlag),
+                };
+                flag = next;
+                set_sector_flag(updater, updt, sector, flag)?;
+            }
+            progress.on_progress(Phase::Swap, sector + 1, sectors);
+        }
+        updater.flash_erase(swap, 0, SECTOR_SIZE)?;
+        Ok(sectors)
+    }
+}
+
+/// Moves the update up by one sector within the update partition (starting with its last
+/// sector) and then, for each sector, backs up the boot sector in the update partition and copies
+/// the moved update sector into boot.
+///
+/// The sector flags record a sector's progress as
+/// - `Swapping` - the update's sector has been moved up.
+/// - `Backup` - the boot sector has been backed up in the update partition.
+/// - `Updated` - the update's sector has been copied into boot.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MoveSwap;
+
+impl SwapPolicy for MoveSwap {
+    fn fits(&self, total_size: usize) -> bool {
+        (sectors(total_size) + 1 + TRAILER_SECTORS) * SECTOR_SIZE <= PARTITION_SIZE
+    }
+
+    fn started(&self, updt: &PartDescriptor<Update>, total_size: usize) -> bool {
+        // the last sector is moved first and the first sector is moved last.
+        sector_flag(updt, 0) != SectorFlag::New
+            || sector_flag(updt, sectors(total_size) - 1) != SectorFlag::New
+    }
+
+    fn swap(
+        &self,
+        updater: impl FlashApi,
+        boot: &PartDescriptor<Boot>,
+        updt: &PartDescriptor<Update>,
+        _swap: &PartDescriptor<Swap>,
+        total_size: usize,
+        progress: &impl Progress,
+    ) -> Result<usize> {
+        let sectors = sectors(total_size);
+        for sector in (0..sectors).rev() {
+            if sector_flag(updt, sector) == SectorFlag::New {
+                copy_sector(updater, (updt, sector), (updt, sector + 1), SECTOR_SIZE)?;
+                set_sector_flag(updater, updt, sector, SectorFlag::Swapping)?;
+            }
+            // moving the update counts as the first half of the swap
+            progress.on_progress(Phase::Swap, sectors - sector, 2 * sectors);
+        }
+        for sector in 0..sectors {
+            if sector_flag(updt, sector) == SectorFlag::Swapping {
+                copy_sector(updater, (boot, sector), (updt, sector), SECTOR_SIZE)?;
+                set_sector_flag(updater, updt, sector, SectorFlag::Backup)?;
+            }
+            if sector_flag(updt, sector) == SectorFlag::Backup {
+                copy_sector(updater, (updt, sector + 1), (boot, sector), SECTOR_SIZE)?;
+                set_sector_flag(updater, updt, sector, SectorFlag::Updated)?;
+            }
+            progress.on_progress(Phase::Swap, sectors + sector + 1, 2 * sectors);
+        }
+        Ok(sectors)
+    }
+}
+
+/// The number of sectors spanned by `size` bytes.
+pub(super) fn sectors(size: usize) -> usize {
+    (size + SECTOR_SIZE - 1) / SECTOR_SIZE
+}
+
+/// A sector's flag, a sector without a valid flag is `New`.
+pub(super) fn sector_flag(updt: &PartDescriptor<Update>, sector: usize) -> SectorFlag {
+    updt.get_flags(sector).unwrap_or(SectorFlag::New)
+}
+
+/// Stores a sector's flag. If the partition's last sector holds the trailer (see
+/// [`TRAILER_SECTORS`]), its flag isn't stored.
+pub(super) fn set_sector_flag(
+    updater: impl FlashApi,
+    updt: &PartDescriptor<Update>,
+    sector: usize,
+    flag: SectorFlag,
+) -> Result<()> {
+    if ((sector + 1 + TRAILER_SECTORS) * SECTOR_SIZE) <= PARTITION_SIZE {
+        updt.set_flags(updater, sector, flag)?;
+    }
+    Ok(())
+}
+
+/// The number of bytes of `part`'s sector that hold its image, there's no need to copy the rest.
+fn image_len<Part: ValidPart>(part: &PartDescriptor<Part>, sector: usize) -> usize {
+    (part.image_end() + FLASHBUFFER_SIZE).saturating_sub(sector * SECTOR_SIZE)
+}
+
+/// The number of times a failed sector copy is retried, before the swap is aborted.
+const COPY_RETRIES: usize = 2;
+
+/// Erases the `dst` sector and copies the first `len` bytes of the `src` sector into it. A copy
+/// that fails is retried up to [`COPY_RETRIES`] times.
+fn copy_sector<Src: ValidPart, Dst: ValidPart>(
+    updater: impl FlashApi,
+    src: (&PartDescriptor<Src>, usize),
+    dst: (&PartDescriptor<Dst>, usize),
+    len: usize,
+) -> Result<()> {
+    let mut retries = 0;
+    loop {
+        match try_copy_sector(updater, src, dst, len) {
+            Err(RustbootError::FlashWriteFailed | RustbootError::FlashEraseFailed)
+                if retries < COPY_RETRIES =>
+            {
+                retries += 1
+            }
+            res => return res,
+        }
+    }
+}
+
+fn try_copy_sector<Src: ValidPart, Dst: ValidPart>(
+    updater: impl FlashApi,
+    (src, src_sector): (&PartDescriptor<Src>, usize),
+    (dst, dst_sector): (&PartDescriptor<Dst>, usize),
+    len: usize,
+) -> Result<()> {
+    let (src_offset, dst_offset) = (src_sector * SECTOR_SIZE, dst_sector * SECTOR_SIZE);
+    updater.flash_erase(dst, dst_offset, SECTOR_SIZE)?;
+    let mut buf = [0u8; FLASHBUFFER_SIZE];
+    let mut pos = 0usize;
+    while pos < len.min(SECTOR_SIZE) {
+        updater.flash_read(src, src_offset + pos, &mut buf)?;
+        updater.flash_write(dst, dst_offset + pos, &buf)?;
+        pos += FLASHBUFFER_SIZE;
+    }
+    Ok(())
+}
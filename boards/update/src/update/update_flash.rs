@@ -1,15 +1,46 @@
 use core::marker::PhantomData;
 
 use crate::hal::hal::*;
+use rustBoot::bootreason::{BootAction, BootReason};
 use rustBoot::constants::*;
 use rustBoot::crypto::signatures::HDR_IMG_TYPE_AUTH;
+use rustBoot::eventlog::Event;
+use rustBoot::image::companion::CompanionImages;
+#[cfg(feature = "esp32s3")]
+use rustBoot::image::esp::{app_image_start, EspImage};
 use rustBoot::image::image::*;
+#[cfg(not(feature = "esp32s3"))]
+use rustBoot::image::vectors::check_vector_table;
 use rustBoot::parser::*;
+use rustBoot::progress::{Phase, Progress};
+use rustBoot::rbconstants::HDR_BOARD_ID_LEN;
+use rustBoot::version::VersionPolicy;
 use rustBoot::{Result, RustbootError};
 
+use super::report::{set_boot_report, unsigned_image, BootReport};
+use super::staging::Staging;
+use super::swap::{SectorSwap, SwapPolicy};
 use super::UpdateInterface;
 use rustBoot::flashapi::FlashApi;
-use rustBoot_hal::FlashInterface;
+#[cfg(feature = "esp32s3")]
+use rustBoot_hal::esp::esp32s3::MEMORY_MAP;
+use rustBoot_hal::{DebugProtection, FlashError, FlashInterface, FlashInterfaceNb, NonBlocking};
+
+/// Debug-access protection enforced by `production` builds.
+#[cfg(all(feature = "production", not(feature = "production-permanent")))]
+const REQUIRED_DEBUG_PROTECTION: DebugProtection = DebugProtection::Enabled;
+#[cfg(feature = "production-permanent")]
+const REQUIRED_DEBUG_PROTECTION: DebugProtection = DebugProtection::Permanent;
+
+/// This board's id i.e. images signed for another board are refused, see
+/// [`RustbootImage::check_board`].
+pub(crate) const BOARD_ID: Option<[u8; HDR_BOARD_ID_LEN]> = match rustBoot_hal::BOARD {
+    Some(board) => Some(board_id(board)),
+    None => None,
+};
+
+/// The buffer images are read through with [`FlashUpdater::with_checked_reads`].
+const READ_CHUNK: usize = 256;
 
 struct RefinedUsize<const MIN: usize, const MAX: usize, const VAL: usize>(usize);
 
@@ -25,8 +56,17 @@ impl<const MIN: usize, const MAX: usize, const VAL: usize> RefinedUsize<MIN, MAX
 }
 
 #[derive(Debug, Clone, Copy)]
-pub struct FlashUpdater<Interface> {
+pub struct FlashUpdater<Interface, Policy = SectorSwap, Hook = ()> {
     iface: Interface,
+    pub(crate) version_policy: VersionPolicy,
+    swap_policy: Policy,
+    progress: Hook,
+    verify_writes: bool,
+    checked_reads: bool,
+    /// the boots after which a retired image is erased, see [`super::retire`]
+    pub(crate) retired_erase: Option<u8>,
+    /// the update being staged, see [`super::staging`]
+    pub(crate) staging: Option<Staging>,
 }
 
 impl<Interface> FlashUpdater<Interface>
@@ -34,37 +74,241 @@ where
     Interface: FlashInterface,
 {
     pub fn new(iface: Interface) -> Self {
-        FlashUpdater { iface }
+        FlashUpdater {
+            iface,
+            version_policy: VersionPolicy::default(),
+            swap_policy: SectorSwap,
+            progress: (),
+            verify_writes: false,
+            checked_reads: false,
+            retired_erase: None,
+            staging: None,
+        }
+    }
+}
+
+impl<Interface> FlashUpdater<NonBlocking<Interface>>
+where
+    Interface: FlashInterfaceNb,
+{
+    /// Same as [`FlashUpdater::new`], except that flash is erased and written with non-blocking
+    /// operations and `wait` is called while the flash controller is busy. Applications that stage
+    /// updates alongside time-critical code (ex: a BLE stack) should use this, see [`NonBlocking`].
+    pub fn new_non_blocking(iface: Interface, wait: fn()) -> Self {
+        FlashUpdater::new(NonBlocking::new(iface, wait))
+    }
+}
+
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
+where
+    Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
+{
+    /// Sets the policy used to decide whether an update's version may replace the
+    /// installed one. Defaults to [`VersionPolicy::STRICT`] i.e. downgrades are rejected.
+    pub fn with_version_policy(mut self, policy: VersionPolicy) -> Self {
+        self.version_policy = policy;
+        self
+    }
+
+    /// Sets how an update is exchanged with the boot image. Defaults to [`SectorSwap`] i.e.
+    /// through the swap partition, see [`super::swap`].
+    pub fn with_swap_policy<P: SwapPolicy>(self, policy: P) -> FlashUpdater<Interface, P, Hook> {
+        FlashUpdater {
+            iface: self.iface,
+            version_policy: self.version_policy,
+            swap_policy: policy,
+            progress: self.progress,
+            verify_writes: self.verify_writes,
+            checked_reads: self.checked_reads,
+            retired_erase: self.retired_erase,
+            staging: self.staging,
+        }
+    }
+
+    /// Sets a hook that is called as updates are verified and swapped (ex: to blink an LED or
+    /// feed a watchdog). See [`Progress`].
+    pub fn with_progress<H: Progress>(self, hook: H) -> FlashUpdater<Interface, Policy, H> {
+        FlashUpdater {
+            iface: self.iface,
+            version_policy: self.version_policy,
+            swap_policy: self.swap_policy,
+            progress: hook,
+            verify_writes: self.verify_writes,
+            checked_reads: self.checked_reads,
+            retired_erase: self.retired_erase,
+            staging: self.staging,
+        }
+    }
+
+    /// Reads back every write and erase (see [`FlashInterface::hal_flash_verify`]), so a write that
+    /// didn't take (ex: a worn sector) is reported as [`RustbootError::FlashWriteFailed`] rather
+    /// than going unnoticed. Swaps retry a sector copy that fails, before giving up. Off by
+    /// default, as it costs a read of every byte written or erased.
+    pub fn with_verified_writes(mut self) -> Self {
+        self.verify_writes = true;
+        self
+    }
+
+    /// Reads images through [`FlashInterface::hal_flash_read`] while they're verified, rather than
+    /// in place. On boards whose reads survive uncorrectable ECC errors (see `rustBoot_hal::ecc`),
+    /// a corrupted update then fails its integrity check (and the installed image is booted),
+    /// instead of faulting on every reset. Off by default, as images are copied through a buffer
+    /// and progress isn't reported.
+    pub fn with_checked_reads(mut self) -> Self {
+        self.checked_reads = true;
+        self
+    }
+
+    /// Erases the update partition's retired image (i.e. the image a confirmed update replaced,
+    /// see [`super::retire`]) once the confirmed image has booted `boots` times, between `1` and
+    /// [`Retired::MAX_BOOTS`]. Off by default i.e. the retired image is kept, unless the
+    /// application erases it.
+    pub fn with_retired_erase(mut self, boots: u8) -> Self {
+        self.retired_erase = Some(boots.clamp(1, Retired::MAX_BOOTS));
+        self
+    }
+
+    pub(crate) fn iface(&self) -> &Interface {
+        &self.iface
+    }
+
+    /// Writes `data` at `addr` and, if enabled, reads it back.
+    ///
+    /// The flash is programmed in whole units (i.e. the board's `WRITE_SIZE`). A write that
+    /// starts or ends within a unit is widened to it, the unit's other bytes are re-written with
+    /// what the flash already holds. Drivers never see a partial unit, so the last bytes of a
+    /// write aren't dropped. A unit that already holds the data isn't re-written.
+    ///
+    /// Returns [`RustbootError::FlashWriteFailed`] if a widened unit was already programmed and
+    /// the board's flash can't program it again (see `FlashInterface::PROGRAM_ONCE`) i.e. the unit
+    /// is left as it is.
+    pub(crate) fn write(&self, addr: usize, data: &[u8]) -> Result<()> {
+        let end = addr + data.len();
+        let mut pos = addr;
+        while pos < end {
+            let unit = pos - pos % WRITE_SIZE;
+            if pos == unit && end - pos >= WRITE_SIZE {
+                let len = (end - pos) - (end - pos) % WRITE_SIZE;
+                let src = data
+                    .get(pos - addr..pos - addr + len)
+                    .ok_or(RustbootError::Unreachable)?;
+                self.program(pos, src)?;
+                pos += len;
+            } else {
+                let mut held = [0u8; WRITE_SIZE];
+                self.iface
+                    .hal_flash_read(unit, &mut held)
+                    .map_err(flash_error)?;
+                let mut buf = held;
+                let len = (unit + WRITE_SIZE).min(end) - pos;
+                let src = data
+                    .get(pos - addr..pos - addr + len)
+                    .ok_or(RustbootError::Unreachable)?;
+                buf.get_mut(pos - unit..pos - unit + len)
+                    .ok_or(RustbootError::Unreachable)?
+                    .copy_from_slice(src);
+                if buf != held {
+                    if Interface::PROGRAM_ONCE && held.iter().any(|byte| *byte != 0xff) {
+                        return Err(RustbootError::FlashWriteFailed);
+                    }
+                    self.program(unit, &buf)?;
+                }
+                pos += len;
+            }
+        }
+        Ok(())
+    }
+
+    /// Programs whole units of flash and, if enabled, reads them back.
+    fn program(&self, addr: usize, data: &[u8]) -> Result<()> {
+        self.iface
+            .hal_flash_write(addr, data)
+            .map_err(flash_error)?;
+        if self.verify_writes {
+            self.iface
+                .hal_flash_verify(addr, data)
+                .map_err(flash_error)?;
+        }
+        Ok(())
     }
 }
-impl<Interface> FlashApi for &FlashUpdater<Interface>
+
+/// Stops the boot, on an error rustBoot can't recover from (ex: all boot options are exhausted)
+/// i.e. panics, so the board's panic handler runs. `panic-free` builds halt instead.
+#[track_caller]
+fn fatal(msg: &'static str) -> ! {
+    #[cfg(not(feature = "panic-free"))]
+    panic!("{}", msg);
+    #[cfg(feature = "panic-free")]
+    {
+        #[cfg(feature = "defmt")]
+        defmt::error!("{=str}", msg);
+        let _ = msg;
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+pub(crate) fn flash_error(e: FlashError) -> RustbootError {
+    match e {
+        FlashError::WriteFailed => RustbootError::FlashWriteFailed,
+        FlashError::EraseFailed => RustbootError::FlashEraseFailed,
+        // i.e. the image being read can't be trusted
+        FlashError::ReadFailed => RustbootError::IntegrityCheckFailed,
+    }
+}
+impl<Interface, Policy, Hook> FlashApi for &FlashUpdater<Interface, Policy, Hook>
 where
     Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
 {
     fn flash_write<Part: ValidPart>(
         self,
         part: &PartDescriptor<Part>,
         offset: usize,
-        data: *const u8,
-        len: usize,
-    ) {
-        let addr = part.hdr.unwrap() as usize + offset;
-        self.iface.hal_flash_write(addr, data, len)
+        data: &[u8],
+    ) -> Result<()> {
+        let addr = part.hdr.ok_or(RustbootError::FieldNotSet)? as usize + offset;
+        self.write(addr, data)
     }
-    fn flash_erase<Part: ValidPart>(self, part: &PartDescriptor<Part>, offset: usize, len: usize) {
-        let addr = part.hdr.unwrap() as usize + offset;
-        self.iface.hal_flash_erase(addr, len);
+    fn flash_erase<Part: ValidPart>(
+        self,
+        part: &PartDescriptor<Part>,
+        offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        let addr = part.hdr.ok_or(RustbootError::FieldNotSet)? as usize + offset;
+        self.iface.hal_flash_erase(addr, len).map_err(flash_error)?;
+        if self.verify_writes {
+            self.iface
+                .hal_flash_verify_erased(addr, len)
+                .map_err(flash_error)?;
+        }
+        Ok(())
     }
 
     fn flash_trailer_write<Part: ValidPart + Swappable>(
         self,
         part: &PartDescriptor<Part>,
         offset: usize,
-        data: *const u8,
-        len: usize,
-    ) {
-        let addr = part.trailer.unwrap() as usize - (4 + offset);
-        self.iface.hal_flash_write(addr, data, len)
+        data: &[u8],
+    ) -> Result<()> {
+        let addr = part.trailer.ok_or(RustbootError::FieldNotSet)? as usize - (4 + offset);
+        self.write(addr, data)
+    }
+
+    fn flash_read<Part: ValidPart>(
+        self,
+        part: &PartDescriptor<Part>,
+        offset: usize,
+        data: &mut [u8],
+    ) -> Result<()> {
+        let addr = part.hdr.ok_or(RustbootError::FieldNotSet)? as usize + offset;
+        self.iface.hal_flash_read(addr, data).map_err(flash_error)
     }
 
     fn flash_init() {}
@@ -72,37 +316,189 @@ where
     fn flash_lock() {}
 }
 
-impl<Interface> FlashUpdater<Interface>
+impl<Interface, Policy, Hook> FlashUpdater<Interface, Policy, Hook>
 where
     Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
 {
-    fn copy_sector<SrcPart: ValidPart, DstPart: ValidPart>(
-        &self,
-        src_part: &PartDescriptor<SrcPart>,
-        dst_part: &PartDescriptor<DstPart>,
-        sector: usize,
-    ) -> Result<usize> {
-        let mut pos = 0usize;
-        let mut src_sector_offset = sector * SECTOR_SIZE;
-        let mut dst_sector_offset = sector * SECTOR_SIZE;
-
-        if (src_part.part.part_id() == PartId::PartSwap) {
-            src_sector_offset = 0;
+    /// Checks the device's debug-access protection and records it in the boot report.
+    ///
+    /// `production` builds also raise it to the required level (i.e. on first boot). This must
+    /// happen after the bootloader is write-protected, as the strictest levels lock the option bytes.
+    /// `boot_reason` is the boot reason recorded for firmware, if any.
+    fn check_debug_protection(&self, boot_reason: Option<BootReason>) {
+        let mut programmed = false;
+        #[cfg(feature = "production")]
+        {
+            if self.iface.hal_debug_protection() < REQUIRED_DEBUG_PROTECTION {
+                self.iface
+                    .hal_set_debug_protection(REQUIRED_DEBUG_PROTECTION);
+                programmed = true;
+            }
+        }
+        set_boot_report(BootReport {
+            debug_protection: self.iface.hal_debug_protection(),
+            debug_protection_programmed: programmed,
+            unsigned_image: unsigned_image(),
+            time: hal_rtc_time(),
+            boot_reason,
+        });
+    }
+
+    /// Moves the update partition to `updating` i.e. marks its image for installation. Returns
+    /// the image's version, unless it was already marked.
+    fn mark_updating(&self) -> Result<Option<u32>> {
+        match PartDescriptor::open_partition(Update, self)? {
+            ImageType::UpdateInNewState(img) => {
+                let new_img = img.into_updating_state();
+                match new_img.part_desc.get() {
+                    Some(part) => part.set_state(self, new_img.get_state()?)?,
+                    None => return Err(RustbootError::__Nonexhaustive),
+                };
+                Ok(Some(new_img.get_firmware_version().unwrap_or(0)))
+            }
+            ImageType::UpdateInUpdatingState(_) => Ok(None), // the update has been triggered
+            _ => Err(RustbootError::Unreachable),
         }
-        if (dst_part.part.part_id() == PartId::PartSwap) {
-            dst_sector_offset = 0;
+    }
+
+    /// Checks an image's integrity, see [`RustbootImage::verify_integrity`]. With
+    /// [`FlashUpdater::with_checked_reads`], the image is read through the [`FlashInterface`].
+    fn verify_integrity<Part, State>(&self, img: &mut RustbootImage<Part, State>) -> Result<bool>
+    where
+        Part: ValidPart + Swappable,
+        State: TypeState,
+    {
+        match self.checked_reads {
+            true => img.verify_integrity_chunked::<SHA256_DIGEST_SIZE, READ_CHUNK>(self),
+            false => img.verify_integrity_with_progress::<SHA256_DIGEST_SIZE>(&self.progress),
+        }
+    }
+
+    /// Checks an image's signature, see [`RustbootImage::verify_authenticity`].
+    ///
+    /// `dev-unsigned` builds also accept an image whose signature is missing or doesn't check out,
+    /// as long as it's otherwise intact i.e. its integrity was checked beforehand and its CRC and
+    /// header are valid. This is flagged in the boot report.
+    ///
+    /// An image signed for another board (see [`BOARD_ID`]) is refused, `dev-unsigned` or not.
+    fn verify_signature<Part, State>(&self, img: &mut RustbootImage<Part, State>) -> Result<bool>
+    where
+        Part: ValidPart + Swappable,
+        State: TypeState,
+    {
+        if let Some(board_id) = BOARD_ID {
+            img.check_board(&board_id)?;
         }
-        self.flash_erase(dst_part, dst_sector_offset, SECTOR_SIZE);
-        while (pos < SECTOR_SIZE) {
-            if (src_sector_offset + pos < (src_part.fw_size + IMAGE_HEADER_SIZE + FLASHBUFFER_SIZE))
-            {
-                let data =
-                    ((src_part.hdr.unwrap() as usize) + src_sector_offset + pos) as *const u8;
-                self.flash_write(dst_part, dst_sector_offset + pos, data, FLASHBUFFER_SIZE);
+        let res = match self.checked_reads {
+            true => img.verify_authenticity_chunked::<HDR_IMG_TYPE_AUTH, READ_CHUNK>(self),
+            false => img.verify_authenticity_with_progress::<HDR_IMG_TYPE_AUTH>(&self.progress),
+        };
+        #[cfg(feature = "dev-unsigned")]
+        if let Err(
+            RustbootError::FwAuthFailed
+            | RustbootError::BadSignature
+            | RustbootError::ECCError
+            | RustbootError::TLVNotFound,
+        ) = res
+        {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("!!! dev-unsigned: booting an image WITHOUT a valid signature !!!");
+            super::report::flag_unsigned_image();
+            return Ok(true);
+        }
+        res
+    }
+
+    /// Checks the boot image before it's booted. If it's corrupted (or its signature doesn't check
+    /// out), the update partition's image is swapped in instead i.e. an emergency update.
+    fn check_boot_image(&self, mut boot: ImageType) {
+        match boot {
+            ImageType::BootInNewState(ref mut img) => {
+                if (self.verify_integrity(img).is_err() || self.verify_signature(img).is_err()) {
+                    let version = img.get_firmware_version().unwrap_or(0);
+                    self.log_event(Event::VerifyFailed, None, version);
+                    match self.rustboot_update(true) {
+                        Err(e) => {
+                            self.log_event(Event::UpdateFailed, Some(e), 0);
+                            // #[cfg(feature = "defmt")]
+                            fatal("all boot options exhausted")
+                        } // all boot options exhausted
+                        Ok(ref mut img) => {
+                            // Emergency update successful, try to re-authenticate boot image.
+                            if (self.verify_integrity(img).is_err()
+                                || self.verify_signature(img).is_err())
+                            {
+                                fatal("something went wrong after the emergency update")
+                                // something went wrong after the emergency update
+                            }
+                        }
+                    }
+                }
+            }
+            ImageType::BootInSuccessState(ref mut img) => {
+                if (self.verify_integrity(img).is_err() || self.verify_signature(img).is_err()) {
+                    let version = img.get_firmware_version().unwrap_or(0);
+                    self.log_event(Event::VerifyFailed, None, version);
+                    match self.rustboot_update(true) {
+                        Err(e) => {
+                            self.log_event(Event::UpdateFailed, Some(e), 0);
+                            // #[cfg(feature = "defmt")]
+                            fatal("all boot options exhausted")
+                        } // all boot options exhausted
+                        Ok(ref mut img) => {
+                            // Emergency update successful, try to re-authenticate boot image.
+                            if (self.verify_integrity(img).is_err()
+                                || self.verify_signature(img).is_err())
+                            {
+                                fatal("something went wrong after the emergency update")
+                                // something went wrong after the emergency update
+                            }
+                        }
+                    }
+                }
             }
-            pos += FLASHBUFFER_SIZE;
+            _ => fatal("reached an unreachable state"),
         }
-        Ok(pos)
+    }
+
+    /// Checks the boot image's vector table (i.e. at `fw_base`) before it's booted (see
+    /// [`rustBoot::image::vectors`]) i.e. refuses to jump to a stack pointer outside RAM or a reset
+    /// vector outside the boot partition. The RAM check is skipped for boards that don't report
+    /// their RAM.
+    #[cfg(not(feature = "esp32s3"))]
+    fn check_vector_table(&self, fw_base: usize) {
+        let vectors = unsafe { core::slice::from_raw_parts(fw_base as *const u8, 8) };
+        let ram = hal_ram_region().unwrap_or(0..usize::MAX);
+        let firmware = fw_base..BOOT_PARTITION_ADDRESS + PARTITION_SIZE;
+        if let Err(e) = check_vector_table(vectors, ram, firmware) {
+            self.log_event(Event::VerifyFailed, Some(e), 0);
+            fatal("invalid vector table")
+        }
+    }
+
+    /// ESP images are app images rather than vector tables i.e. checks that the boot image's
+    /// segments can be loaded and mapped, see [`rustBoot::image::esp`].
+    #[cfg(feature = "esp32s3")]
+    fn check_vector_table(&self, fw_base: usize) {
+        let start = app_image_start(fw_base);
+        let len = BOOT_PARTITION_ADDRESS + PARTITION_SIZE - start;
+        let image = unsafe { core::slice::from_raw_parts(start as *const u8, len) };
+        let res = EspImage::parse(image).and_then(|image| image.check(start, &MEMORY_MAP));
+        if let Err(e) = res {
+            self.log_event(Event::VerifyFailed, Some(e), 0);
+            fatal("invalid app image")
+        }
+    }
+
+    /// Logs `event` (see [`super::events`]), if the `event-log` feature is enabled. Logging is
+    /// best-effort i.e. an event that can't be logged doesn't fail the update.
+    pub(crate) fn log_event(&self, event: Event, err: Option<RustbootError>, version: u32) {
+        #[cfg(feature = "event-log")]
+        let _ = self.append_event(event, err.map_or(0, |e| e.code()), version);
+        #[cfg(not(feature = "event-log"))]
+        let _ = (event, err, version);
     }
 
     fn rustboot_update<'a>(&self, rollback: bool) -> Result<RustbootImage<'a, Boot, StateTesting>> {
@@ -111,55 +507,77 @@ where
         let swap = PartDescriptor::open_partition(Swap, self)?;
 
         let mut new_boot_img = None;
+        let mut trial = false;
+        let mut decompressed = false;
 
         match (updt, swap) {
             (ImageType::UpdateInUpdatingState(mut updt), ImageType::NoStateSwap(swap)) => {
                 /* use largest size for the swap */
                 let mut total_size = 0usize;
-                let mut sector = 0usize;
-                let mut flag = SectFlags::None;
                 {
                     // This scope is to satisfy the borrow checker
-                    let updt_part = updt.part_desc.get().unwrap();
+                    let updt_part = updt.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
                     let boot_part = match boot {
                         // Explicitly check all possible Boot states
                         ImageType::BootInNewState(ref boot) => {
-                            let boot_fw_size = boot.part_desc.get().unwrap().fw_size; // can be unwrapped as it was checked during init.
-                            let update_fw_size = updt_part.fw_size;
-                            total_size = boot_fw_size + IMAGE_HEADER_SIZE;
-                            if ((update_fw_size + IMAGE_HEADER_SIZE) > total_size) {
-                                total_size = update_fw_size + IMAGE_HEADER_SIZE;
-                            }
+                            let boot_end = boot
+                                .part_desc
+                                .get()
+                                .ok_or(RustbootError::FieldNotSet)?
+                                .image_end();
+                            total_size = boot_end.max(updt_part.image_end());
                             boot.part_desc.get()
                         }
                         ImageType::BootInSuccessState(ref boot) => {
-                            let boot_fw_size = boot.part_desc.get().unwrap().fw_size; // can be unwrapped as it was checked during init.
-                            let update_fw_size = updt_part.fw_size;
-                            total_size = boot_fw_size + IMAGE_HEADER_SIZE;
-                            if ((update_fw_size + IMAGE_HEADER_SIZE) > total_size) {
-                                total_size = update_fw_size + IMAGE_HEADER_SIZE;
-                            }
+                            let boot_end = boot
+                                .part_desc
+                                .get()
+                                .ok_or(RustbootError::FieldNotSet)?
+                                .image_end();
+                            total_size = boot_end.max(updt_part.image_end());
                             boot.part_desc.get()
                         }
                         // in case of a rollback
                         ImageType::BootInTestingState(ref boot) => {
-                            let boot_fw_size = boot.part_desc.get().unwrap().fw_size; // can be unwrapped as it was checked during init.
-                            let update_fw_size = updt_part.fw_size;
-                            total_size = boot_fw_size + IMAGE_HEADER_SIZE;
-                            if ((update_fw_size + IMAGE_HEADER_SIZE) > total_size) {
-                                total_size = update_fw_size + IMAGE_HEADER_SIZE;
-                            }
+                            let boot_end = boot
+                                .part_desc
+                                .get()
+                                .ok_or(RustbootError::FieldNotSet)?
+                                .image_end();
+                            total_size = boot_end.max(updt_part.image_end());
                             boot.part_desc.get()
                         }
                         _ => {
                             return Err(RustbootError::InvalidState);
                         }
                     };
+                    trial = match rollback {
+                        // an unconfirmed trial boot is being reverted
+                        true => boot_part.map_or(Ok(false), |part| part.is_trial())?,
+                        // the update was staged with `update_test`
+                        false => updt_part.is_trial()?,
+                    };
                     if total_size <= IMAGE_HEADER_SIZE {
                         return Err(RustbootError::InvalidImage);
                     }
-                    // Check the first sector to detect an interrupted update.
-                    if updt_part.get_flags(0).is_err() || updt_part.get_flags(0)?.has_new_flag() {
+                    // a compressed update is decompressed into boot rather than swapped in, see
+                    // `super::decompress`
+                    #[cfg(feature = "compression")]
+                    let compressed = updt_part.compressed_len()?.is_some();
+                    #[cfg(not(feature = "compression"))]
+                    let compressed = false;
+                    if !compressed && !self.swap_policy.fits(total_size) {
+                        return Err(RustbootError::InvalidFirmwareSize);
+                    }
+                    // Check the sector flags to detect an interrupted update.
+                    let companions = CompanionImages::in_update_partition(updt_part.image_end());
+                    let mut install_companions = false;
+                    let started = match compressed {
+                        #[cfg(feature = "compression")]
+                        true => super::decompress::started(updt_part),
+                        _ => self.swap_policy.started(updt_part, total_size),
+                    };
+                    if !started {
                         let update_type = updt.get_image_type()?;
                         // In the event that this is a new update, perform the required checks on the update
                         // before starting the swap.
@@ -168,25 +586,51 @@ where
                         {
                             return Err(RustbootError::ECCError);
                         }
-                        if (!updt_part.hdr_ok
-                            || updt.verify_integrity::<SHA256_DIGEST_SIZE>().is_err()
-                            || updt.verify_authenticity::<HDR_IMG_TYPE_AUTH>().is_err())
-                        {
-                            panic!("firmware authentication failed");
+                        let verified = match updt_part.hdr_ok {
+                            true => self
+                                .verify_integrity(&mut updt)
+                                .and_then(|_| self.verify_signature(&mut updt)),
+                            false => Err(RustbootError::InvalidImage),
+                        };
+                        if let Err(e) = verified {
+                            let version = updt.get_firmware_version().unwrap_or(0);
+                            self.log_event(Event::VerifyFailed, Some(e), version);
+                            // a corrupted update is refused, see `rustboot_start`
+                            if e == RustbootError::IntegrityCheckFailed {
+                                return Err(e);
+                            }
+                            fatal("firmware authentication failed");
+                        }
+                        // Companion images staged along with the update must all be authentic (and
+                        // supported by the board) before any image is activated.
+                        for companion in companions.clone() {
+                            match companion {
+                                Ok(img)
+                                    if self.iface.hal_has_companion(img.id())
+                                        && img.verify().is_ok() => {}
+                                _ => fatal("companion image authentication failed"),
+                            }
                         }
+                        install_companions = !rollback;
                     }
-                    // disallow downgrades
+                    // disallow downgrades, unless permitted by the version policy
                     match boot {
                         ImageType::BootInNewState(ref boot) => {
                             if (!rollback
-                                && (updt.get_firmware_version()? <= boot.get_firmware_version()?))
+                                && !self.version_policy.permits(
+                                    boot.get_firmware_version()?,
+                                    updt.get_firmware_version()?,
+                                ))
                             {
                                 return Err(RustbootError::FwAuthFailed);
                             }
                         }
                         ImageType::BootInSuccessState(ref boot) => {
                             if (!rollback
-                                && (updt.get_firmware_version()? <= boot.get_firmware_version()?))
+                                && !self.version_policy.permits(
+                                    boot.get_firmware_version()?,
+                                    updt.get_firmware_version()?,
+                                ))
                             {
                                 return Err(RustbootError::FwAuthFailed);
                             }
@@ -198,53 +642,56 @@ where
                             return Err(RustbootError::InvalidState);
                         }
                     }
+                    // Hand companion images off to the HAL before the swap, which erases them.
+                    // Companion images are not rolled back.
+                    if install_companions {
+                        for companion in companions {
+                            let img = companion?;
+                            self.iface.hal_install_companion(img.id(), img.firmware());
+                        }
+                    }
 
                     /* Interruptible swap
                      * The status is saved in the sector flags of the update partition.
                      * If something goes wrong, the operation will be resumed upon reboot.
                      */
-                    let boot_part = boot_part.unwrap();
-                    let updt_part = updt.part_desc.get().unwrap();
-                    let swap_part = swap.part_desc.get().unwrap();
-                    while ((sector * SECTOR_SIZE) < total_size) {
-                        if updt_part.get_flags(sector).is_err()
-                            || updt_part.get_flags(sector)?.has_new_flag()
-                        {
-                            flag = flag.set_swapping_flag();
-                            self.copy_sector(updt_part, swap_part, sector);
-                            if (((sector + 1) * SECTOR_SIZE) < PARTITION_SIZE) {
-                                updt_part.set_flags(self, sector, flag)?;
-                            }
-                        }
-                        if flag.has_swapping_flag() {
-                            flag = flag.set_backup_flag();
-                            self.copy_sector(boot_part, updt_part, sector);
-                            if (((sector + 1) * SECTOR_SIZE) < PARTITION_SIZE) {
-                                updt_part.set_flags(self, sector, flag)?;
-                            }
-                        }
-                        if flag.has_backup_flag() {
-                            flag = flag.set_updated_flag();
-                            self.copy_sector(swap_part, boot_part, sector);
-                            if (((sector + 1) * SECTOR_SIZE) < PARTITION_SIZE) {
-                                updt_part.set_flags(self, sector, flag)?;
-                            }
+                    let boot_part = boot_part.ok_or(RustbootError::FieldNotSet)?;
+                    let updt_part = updt.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+                    let swap_part = swap.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+                    let mut sector = match compressed {
+                        #[cfg(feature = "compression")]
+                        true => {
+                            super::decompress::install(self, boot_part, updt_part, &self.progress)?
                         }
-                        sector += 1;
-                    }
-
+                        _ => self.swap_policy.swap(
+                            self,
+                            boot_part,
+                            updt_part,
+                            swap_part,
+                            total_size,
+                            &self.progress,
+                        )?,
+                    };
+                    decompressed = compressed;
                     while ((sector * SECTOR_SIZE) < PARTITION_SIZE) {
-                        self.flash_erase(boot_part, sector * SECTOR_SIZE, SECTOR_SIZE);
-                        self.flash_erase(updt_part, sector * SECTOR_SIZE, SECTOR_SIZE);
+                        self.flash_erase(boot_part, sector * SECTOR_SIZE, SECTOR_SIZE)?;
+                        self.flash_erase(updt_part, sector * SECTOR_SIZE, SECTOR_SIZE)?;
                         sector += 1;
+                        self.progress.on_progress(
+                            Phase::Erase,
+                            sector,
+                            PARTITION_SIZE / SECTOR_SIZE,
+                        );
                     }
-                    self.flash_erase(swap_part, 0, SECTOR_SIZE);
+                    // trailers kept in metadata sectors aren't erased along with the partitions
+                    #[cfg(feature = "metadata-sector")]
+                    self.reset_trailers()?;
                 }
                 // Re-open the `Boot` partition after swap.
                 // Note: A successful swap moves the image in the update partition to the boot partition.
                 // TODO: As we're using singletons (i.e. BOOT, UPDT), swap the following `rustBoot header` fields -
                 //       size, sha_hash, signature_ok, sha_ok, hdr_ok.
-                let boot = PartDescriptor::open_partition(Boot, self).unwrap();
+                let boot = PartDescriptor::open_partition(Boot, self)?;
                 // the only valid state for the boot partition after a swap is `newState` as all state
                 // info is erased post the swap.
                 let new_img = match boot {
@@ -257,157 +704,227 @@ where
                 new_img
                     .part_desc
                     .get()
-                    .unwrap()
-                    .set_state(self, new_img.get_state());
+                    .ok_or(RustbootError::FieldNotSet)?
+                    .set_state(self, new_img.get_state()?)?;
+                match (trial, rollback) {
+                    // a reverted trial is final i.e. the restored image is confirmed, rather than
+                    // tested (and possibly reverted) again. So is a decompressed update, as the
+                    // image it replaced wasn't backed up.
+                    _ if decompressed => {
+                        new_img
+                            .part_desc
+                            .get()
+                            .ok_or(RustbootError::FieldNotSet)?
+                            .set_state(self, &StateSuccess)?;
+                    }
+                    (true, true) => {
+                        new_img
+                            .part_desc
+                            .get()
+                            .ok_or(RustbootError::FieldNotSet)?
+                            .set_state(self, &StateSuccess)?;
+                    }
+                    (true, false) => new_img
+                        .part_desc
+                        .get()
+                        .ok_or(RustbootError::FieldNotSet)?
+                        .set_trial(self)?,
+                    (false, _) => {}
+                }
+                let event = match rollback {
+                    true => Event::RollbackPerformed,
+                    false => Event::UpdateInstalled,
+                };
+                let version = new_img.get_firmware_version().unwrap_or(0);
+                self.log_event(event, None, version);
                 new_boot_img = Some(new_img);
             }
             _ => return Err(RustbootError::InvalidState),
         }
-        Ok(new_boot_img.unwrap())
+        new_boot_img.ok_or(RustbootError::Unreachable)
     }
 }
 
-impl<Interface> UpdateInterface for &FlashUpdater<Interface>
+impl<Interface, Policy, Hook> UpdateInterface for &FlashUpdater<Interface, Policy, Hook>
 where
     Interface: FlashInterface,
+    Policy: SwapPolicy,
+    Hook: Progress,
 {
     fn rustboot_start(self) -> ! {
-        let mut boot = PartDescriptor::open_partition(Boot, self).unwrap();
-        let updt = PartDescriptor::open_partition(Update, self).unwrap();
+        // a boot-redirect request is only ever used once, it's taken even if this boot fails
+        #[cfg(feature = "boot-redirect")]
+        let redirect = super::redirect::take_redirect();
+        #[cfg(feature = "metadata-sector")]
+        if self.migrate_trailers().is_err() {
+            fatal("trailer migration failed.")
+        }
+        let boot = PartDescriptor::open_partition(Boot, self)
+            .unwrap_or_else(|_| fatal("the boot partition can't be opened"));
+        // the update partition may hold no image (ex: a retired image that's been erased)
+        let updt = PartDescriptor::open_partition(Update, self);
 
         // Check the BOOT partition for state - if it is still in TESTING, trigger rollback.
-        if let ImageType::BootInTestingState(_v) = boot {
-            self.update_trigger();
+        let action = if let ImageType::BootInTestingState(_v) = boot {
+            let _ = self.mark_updating();
             match self.rustboot_update(true) {
-                Ok(_v) => {}
-                Err(_e) => {
-                    panic!("rollback failed.")
+                Ok(_v) => BootAction::RolledBack,
+                Err(e) => {
+                    self.log_event(Event::UpdateFailed, Some(e), 0);
+                    fatal("rollback failed.")
                 }
             }
         // Check the UPDATE partition for state - if it is marked as UPDATING, trigger update.
-        } else if let ImageType::UpdateInUpdatingState(_v) = updt {
+        } else if let Ok(ImageType::UpdateInUpdatingState(_v)) = updt {
             match self.rustboot_update(false) {
-                Ok(_v) => {}
-                Err(_e) => {
-                    panic!("update-swap failed.")
-                }
-            }
-        } else {
-            match boot {
-                ImageType::BootInNewState(ref mut img) => {
-                    if (img.verify_integrity::<SHA256_DIGEST_SIZE>().is_err()
-                        || img.verify_authenticity::<HDR_IMG_TYPE_AUTH>().is_err())
-                    {
-                        match self.rustboot_update(true) {
-                            Err(_v) => {
-                                // #[cfg(feature = "defmt")]
-                                panic!("all boot options exhausted")
-                            } // all boot options exhausted
-                            Ok(ref mut img) => {
-                                // Emergency update successful, try to re-authenticate boot image.
-                                if (img.verify_integrity::<SHA256_DIGEST_SIZE>().is_err()
-                                    || img.verify_authenticity::<HDR_IMG_TYPE_AUTH>().is_err())
-                                {
-                                    panic!("something went wrong after the emergency update")
-                                    // something went wrong after the emergency update
-                                }
-                            }
-                        }
-                    }
+                Ok(_v) => BootAction::UpdatePending,
+                // a corrupted update (ex: one that can't be read back, see
+                // `FlashUpdater::with_checked_reads`) is refused i.e. the installed image is booted.
+                Err(RustbootError::IntegrityCheckFailed) => {
+                    self.check_boot_image(boot);
+                    BootAction::None
                 }
-                ImageType::BootInSuccessState(ref mut img) => {
-                    if (img.verify_integrity::<SHA256_DIGEST_SIZE>().is_err()
-                        || img.verify_authenticity::<HDR_IMG_TYPE_AUTH>().is_err())
-                    {
-                        match self.rustboot_update(true) {
-                            Err(_v) => {
-                                // #[cfg(feature = "defmt")]
-                                panic!("all boot options exhausted")
-                            } // all boot options exhausted
-                            Ok(ref mut img) => {
-                                // Emergency update successful, try to re-authenticate boot image.
-                                if (img.verify_integrity::<SHA256_DIGEST_SIZE>().is_err()
-                                    || img.verify_authenticity::<HDR_IMG_TYPE_AUTH>().is_err())
-                                {
-                                    panic!("something went wrong after the emergency update")
-                                    // something went wrong after the emergency update
-                                }
-                            }
-                        }
-                    }
+                Err(e) => {
+                    self.log_event(Event::UpdateFailed, Some(e), 0);
+                    fatal("update-swap failed.")
                 }
-                _ => unreachable!(),
             }
+        } else {
+            self.check_boot_image(boot);
+            // a failed erase is retried on the next boot
+            let _ = self.count_retired_boot();
+            BootAction::None
+        };
+
+        // We're done writing to flash - write-protect rustBoot (including its embedded public key),
+        // so firmware can't erase or overwrite the bootloader. As stage1, only stage0 is (see
+        // `stage1`).
+        #[cfg(not(feature = "two-stage"))]
+        let (protected, protected_len) = (BOOTLOADER_ADDRESS, BOOTLOADER_SIZE);
+        #[cfg(feature = "two-stage")]
+        let (protected, protected_len) = super::stage1::protected_region();
+        self.iface.hal_flash_protect(protected, protected_len);
+        // and, if enabled, hide it from firmware altogether (see `rustBoot_hal::mpu`).
+        #[cfg(feature = "hide-bootloader")]
+        self.iface.hal_hide_region(protected, protected_len);
+        #[cfg(feature = "boot-reason")]
+        let boot_reason = Some(super::boot_reason::record_boot_reason(action));
+        #[cfg(not(feature = "boot-reason"))]
+        let boot_reason = {
+            let _ = action;
+            None
+        };
+        self.check_debug_protection(boot_reason);
+        #[cfg(feature = "boot-redirect")]
+        if let Some(request) = redirect {
+            self.boot_redirect(&request);
         }
 
         // After an update or rollback re-open the `boot` partition.
         // Note: Swapping moves the image in the update partition to the boot partition.
         // TODO: As we're using singletons (i.e. BOOT, UPDT), swap the following `rustBoot header` fields -
         //       size, sha_hash, signature_ok, sha_ok, hdr_ok.
-        let boot = PartDescriptor::open_partition(Boot, self).unwrap();
+        let boot = PartDescriptor::open_partition(Boot, self)
+            .unwrap_or_else(|_| fatal("the boot partition can't be opened"));
         match boot {
             ImageType::BootInNewState(img) => {
-                let boot_part = img.part_desc.get().unwrap();
-                let base_img_addr = RefinedUsize::<0, 0, BOOT_FWBASE>::single_valued_int(
-                    boot_part.fw_base as usize,
-                )
+                let boot_part = img
+                    .part_desc
+                    .get()
+                    .unwrap_or_else(|| fatal("reached an unreachable state"));
+                let base_img_addr = RefinedUsize::<
+                    BOOT_FWBASE,
+                    { BOOT_FWBASE + HEADER_SEARCH_WINDOW },
+                    0,
+                >::bounded_int(boot_part.fw_base as usize)
                 .0;
+                self.check_vector_table(base_img_addr);
                 hal_preboot();
                 hal_boot_from(base_img_addr)
             }
             ImageType::BootInSuccessState(img) => {
-                let boot_part = img.part_desc.get().unwrap();
-                let base_img_addr = RefinedUsize::<0, 0, BOOT_FWBASE>::single_valued_int(
-                    boot_part.fw_base as usize,
-                )
+                let boot_part = img
+                    .part_desc
+                    .get()
+                    .unwrap_or_else(|| fatal("reached an unreachable state"));
+                let base_img_addr = RefinedUsize::<
+                    BOOT_FWBASE,
+                    { BOOT_FWBASE + HEADER_SEARCH_WINDOW },
+                    0,
+                >::bounded_int(boot_part.fw_base as usize)
                 .0;
+                self.check_vector_table(base_img_addr);
                 hal_preboot();
                 hal_boot_from(base_img_addr)
             }
             // If an update is successful, this is the state of the boot partition.
             ImageType::BootInTestingState(img) => {
-                let boot_part = img.part_desc.get().unwrap();
-                let base_img_addr = RefinedUsize::<0, 0, BOOT_FWBASE>::single_valued_int(
-                    boot_part.fw_base as usize,
-                )
+                let boot_part = img
+                    .part_desc
+                    .get()
+                    .unwrap_or_else(|| fatal("reached an unreachable state"));
+                let base_img_addr = RefinedUsize::<
+                    BOOT_FWBASE,
+                    { BOOT_FWBASE + HEADER_SEARCH_WINDOW },
+                    0,
+                >::bounded_int(boot_part.fw_base as usize)
                 .0;
+                self.check_vector_table(base_img_addr);
                 hal_preboot();
                 hal_boot_from(base_img_addr)
             }
-            _ => panic!("reached an unreachable state"),
+            _ => fatal("reached an unreachable state"),
         }
     }
 
     fn update_trigger(self) -> Result<()> {
-        let updt = PartDescriptor::open_partition(Update, self).unwrap();
+        // an update that's being staged is incomplete (or unchecked), see `finalize_update`
+        if self.is_staging() {
+            return Err(RustbootError::InvalidState);
+        }
         Self::flash_unlock();
-        match updt {
-            ImageType::UpdateInNewState(img) => {
-                let new_img = img.into_updating_state();
-                let part_desc = new_img.part_desc.get();
-                match part_desc {
-                    Some(part) => part.set_state(self, new_img.get_state()),
-                    None => return Err(RustbootError::__Nonexhaustive),
-                };
-            }
-            ImageType::UpdateInUpdatingState(img) => {} // do nothing as update has been triggered
+        if let Some(version) = self.mark_updating()? {
+            self.log_event(Event::UpdateTriggered, None, version);
+        }
+        Self::flash_lock();
+        Ok(())
+    }
+
+    fn update_test(self) -> Result<()> {
+        if self.is_staging() {
+            return Err(RustbootError::InvalidState);
+        }
+        Self::flash_unlock();
+        let staged = self.mark_updating()?;
+        match PartDescriptor::open_partition(Update, self)? {
+            ImageType::UpdateInUpdatingState(img) => match img.part_desc.get() {
+                Some(part) if !part.is_trial()? => part.set_trial(self)?,
+                Some(_) => {} // already marked for a trial boot
+                None => return Err(RustbootError::__Nonexhaustive),
+            },
             _ => return Err(RustbootError::Unreachable),
         }
+        if let Some(version) = staged {
+            self.log_event(Event::UpdateTested, None, version);
+        }
         Self::flash_lock();
         Ok(())
     }
 
     fn update_success(self) -> Result<()> {
-        let boot = PartDescriptor::open_partition(Boot, self).unwrap();
+        let boot = PartDescriptor::open_partition(Boot, self)?;
         Self::flash_unlock();
         match boot {
             ImageType::BootInTestingState(img) => {
                 let new_img = img.into_success_state();
                 let part_desc = new_img.part_desc.get();
                 match part_desc {
-                    Some(part) => part.set_state(self, new_img.get_state()),
+                    Some(part) => part.set_state(self, new_img.get_state()?)?,
                     None => return Err(RustbootError::__Nonexhaustive),
                 };
+                let version = new_img.get_firmware_version().unwrap_or(0);
+                self.log_event(Event::UpdateConfirmed, None, version);
+                self.retire_update()?;
             }
             ImageType::BootInSuccessState(img) => {} // do nothing as we've successfully updated & booted
             _ => return Err(RustbootError::Unreachable),
@@ -0,0 +1,342 @@
+//! Factory images i.e. the bootloader and the signed boot and update images combined into a
+//! single file, so a board can be programmed in one step.
+//!
+//! ```text
+//! bootloader -> +-----------------+
+//!               | rustBoot        |
+//!               | (gap)           |
+//! boot ->       +-----------------+
+//!               | signed bootfw   |
+//!               | (gap)           |
+//! update ->     +-----------------+
+//!               | signed updtfw   |
+//!               +-----------------+
+//! ```
+//!
+//! Factory images are written as Intel HEX (only the programmed ranges) or as a raw binary
+//! starting at the bootloader's address, with gaps filled with `0xFF` i.e. erased flash.
+
+use crate::curve::{RbSignerError, Result};
+use core::convert::TryInto;
+use serde::Deserialize;
+
+/// The partitions of a board manifest (i.e. `boards/manifests/<board>.toml`) needed to place
+/// images. Other tables are ignored.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    partitions: Partitions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Partitions {
+    /// start of flash i.e. the bootloader, which runs up to the boot partition
+    pub bootloader: u32,
+    pub size: u32,
+    pub boot: u32,
+    pub update: u32,
+}
+
+impl Partitions {
+    /// Parses a board manifest's `[partitions]` table.
+    pub fn from_manifest(manifest: &str) -> Result<Self> {
+        toml::from_str::<Manifest>(manifest)
+            .map(|m| m.partitions)
+            .map_err(|_| RbSignerError::InvalidManifest)
+    }
+}
+
+/// Contiguous data at a flash address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub addr: u32,
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    fn end(&self) -> u64 {
+        self.addr as u64 + self.data.len() as u64
+    }
+}
+
+/// A factory image's segments, sorted by address.
+#[derive(Debug)]
+pub struct FactoryImage {
+    segments: Vec<Segment>,
+}
+
+/// Places the bootloader, the signed boot image and the signed update image, as per the board's
+/// partitions.
+///
+/// `bootloader` is either an ELF (its loadable segments are placed at their physical addresses)
+/// or a raw binary, placed at the start of flash. It must end before the boot partition and the
+/// signed images must fit in their partitions.
+pub fn assemble(
+    partitions: &Partitions,
+    bootloader: &[u8],
+    boot_image: &[u8],
+    update_image: &[u8],
+) -> Result<FactoryImage> {
+    let mut segments = match bootloader.starts_with(ELF_MAGIC) {
+        true => elf_segments(bootloader)?,
+        false => vec![Segment {
+            addr: partitions.bootloader,
+            data: bootloader.to_vec(),
+        }],
+    };
+    for segment in segments.iter() {
+        if segment.addr < partitions.bootloader || segment.end() > partitions.boot as u64 {
+            return Err(RbSignerError::ImageTooLarge(segment.data.len()));
+        }
+    }
+    for (addr, image) in [
+        (partitions.boot, boot_image),
+        (partitions.update, update_image),
+    ] {
+        if image.len() > partitions.size as usize {
+            return Err(RbSignerError::ImageTooLarge(image.len()));
+        }
+        segments.push(Segment {
+            addr,
+            data: image.to_vec(),
+        });
+    }
+    segments.sort_by_key(|s| s.addr);
+    if segments.windows(2).any(|w| w[0].end() > w[1].addr as u64) {
+        return Err(RbSignerError::OverlappingImages);
+    }
+    Ok(FactoryImage { segments })
+}
+
+impl FactoryImage {
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// A raw binary from the first segment's address to the end of the last, gaps are filled
+    /// with `0xFF`.
+    pub fn to_bin(&self) -> Vec<u8> {
+        let base = match self.segments.first() {
+            Some(segment) => segment.addr,
+            None => return Vec::new(),
+        };
+        let end = self.segments.last().unwrap().end();
+        let mut bin = vec![0xFF; (end - base as u64) as usize];
+        for segment in self.segments.iter() {
+            let offset = (segment.addr - base) as usize;
+            bin[offset..offset + segment.data.len()].copy_from_slice(&segment.data);
+        }
+        bin
+    }
+
+    /// Intel HEX i.e. 16-byte data records, with an extended linear address record whenever the
+    /// upper 16 bits of the address change.
+    pub fn to_ihex(&self) -> String {
+        let mut hex = String::new();
+        let mut upper = None;
+        for segment in self.segments.iter() {
+            let (mut addr, mut data) = (segment.addr, &segment.data[..]);
+            while !data.is_empty() {
+                // a record can't cross a 64K boundary
+                let len = IHEX_RECORD_LEN
+                    .min(0x1_0000 - (addr & 0xFFFF) as usize)
+                    .min(data.len());
+                if upper != Some(addr >> 16) {
+                    upper = Some(addr >> 16);
+                    let upper = (addr >> 16) as u16;
+                    hex += &ihex_record(0, IHEX_EXTENDED_LINEAR_ADDRESS, &upper.to_be_bytes());
+                }
+                hex += &ihex_record(addr as u16, IHEX_DATA, &data[..len]);
+                addr += len as u32;
+                data = &data[len..];
+            }
+        }
+        hex += &ihex_record(0, IHEX_END_OF_FILE, &[]);
+        hex
+    }
+}
+
+const IHEX_RECORD_LEN: usize = 16;
+const IHEX_DATA: u8 = 0x00;
+const IHEX_END_OF_FILE: u8 = 0x01;
+const IHEX_EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+
+/// `:LLAAAATT<data>CC`, the checksum is the two's complement of the sum of the record's bytes.
+fn ihex_record(addr: u16, record_type: u8, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&addr.to_be_bytes());
+    bytes.push(record_type);
+    bytes.extend_from_slice(data);
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, b| sum.wrapping_add(*b))
+        .wrapping_neg();
+    bytes.push(checksum);
+    let mut record = String::from(":");
+    for b in bytes {
+        record += &format!("{:02X}", b);
+    }
+    record + "\n"
+}
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const PT_LOAD: u32 = 1;
+
+/// Returns the loadable segments of a 32-bit little-endian ELF (i.e. an mcu bootloader), at
+/// their physical (load) addresses. Segments without file data (i.e. `.bss`) are skipped.
+fn elf_segments(elf: &[u8]) -> Result<Vec<Segment>> {
+    let u16_at = |offset: usize| -> Result<u16> {
+        elf.get(offset..offset + 2)
+            .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(RbSignerError::InvalidElf)
+    };
+    let u32_at = |offset: usize| -> Result<u32> {
+        elf.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(RbSignerError::InvalidElf)
+    };
+    if elf.get(4) != Some(&ELFCLASS32) || elf.get(5) != Some(&ELFDATA2LSB) {
+        return Err(RbSignerError::InvalidElf);
+    }
+    let (phoff, phentsize, phnum) = (u32_at(28)? as usize, u16_at(42)? as usize, u16_at(44)?);
+
+    let mut segments = Vec::new();
+    for i in 0..phnum as usize {
+        let ph = phoff + i * phentsize;
+        let (p_type, p_offset, p_paddr, p_filesz) = (
+            u32_at(ph)?,
+            u32_at(ph + 4)?,
+            u32_at(ph + 12)?,
+            u32_at(ph + 16)?,
+        );
+        if p_type != PT_LOAD || p_filesz == 0 {
+            continue;
+        }
+        let data = elf
+            .get(p_offset as usize..p_offset as usize + p_filesz as usize)
+            .ok_or(RbSignerError::InvalidElf)?;
+        segments.push(Segment {
+            addr: p_paddr,
+            data: data.to_vec(),
+        });
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARTITIONS: Partitions = Partitions {
+        bootloader: 0x0800_0000,
+        size: 0x2_0000,
+        boot: 0x0802_0000,
+        update: 0x0804_0000,
+    };
+
+    #[test]
+    fn manifest_partitions() {
+        let manifest = r#"
+            [board]
+            name = "stm32f411"
+
+            [partitions]
+            bootloader = 0x08000000
+            size = 0x20000
+            boot = 0x08020000
+            update = 0x08040000
+            swap = 0x08060000
+        "#;
+        assert_eq!(Partitions::from_manifest(manifest).unwrap(), PARTITIONS);
+        assert!(matches!(
+            Partitions::from_manifest("[board]\nname = \"x\""),
+            Err(RbSignerError::InvalidManifest)
+        ));
+    }
+
+    #[test]
+    fn raw_bin_with_gaps() {
+        let image = assemble(&PARTITIONS, &[0xaa; 0x100], &[0xbb; 0x10], &[0xcc; 0x10]).unwrap();
+        let bin = image.to_bin();
+        assert_eq!(bin.len(), 0x4_0010);
+        assert_eq!(bin[..0x100], [0xaa; 0x100]);
+        assert!(bin[0x100..0x2_0000].iter().all(|b| *b == 0xff));
+        assert_eq!(bin[0x2_0000..0x2_0010], [0xbb; 0x10]);
+        assert_eq!(bin[0x4_0000..], [0xcc; 0x10]);
+    }
+
+    #[test]
+    fn images_must_fit() {
+        // the bootloader runs into the boot partition
+        assert!(matches!(
+            assemble(&PARTITIONS, &vec![0; 0x2_0001], &[], &[]),
+            Err(RbSignerError::ImageTooLarge(0x2_0001))
+        ));
+        // the update image is larger than its partition
+        assert!(matches!(
+            assemble(&PARTITIONS, &[], &[], &vec![0; 0x2_0001]),
+            Err(RbSignerError::ImageTooLarge(0x2_0001))
+        ));
+    }
+
+    #[test]
+    fn intel_hex() {
+        let image = FactoryImage {
+            segments: vec![Segment {
+                addr: 0x0800_fff8,
+                data: (0..0x14).collect(),
+            }],
+        };
+        assert_eq!(
+            image.to_ihex(),
+            ":020000040800F2\n\
+             :08FFF8000001020304050607E5\n\
+             :020000040801F1\n\
+             :0C00000008090A0B0C0D0E0F1011121352\n\
+             :00000001FF\n"
+        );
+    }
+
+    /// A 32-bit little-endian ELF with a `.text` segment at 0x08000000 and a `.bss` segment
+    fn elf() -> Vec<u8> {
+        let mut elf = vec![0u8; 0x54 + 0x20 * 2];
+        elf[..6].copy_from_slice(&[0x7f, b'E', b'L', b'F', ELFCLASS32, ELFDATA2LSB]);
+        elf[28..32].copy_from_slice(&0x34u32.to_le_bytes());
+        elf[42..44].copy_from_slice(&0x20u16.to_le_bytes());
+        elf[44..46].copy_from_slice(&2u16.to_le_bytes());
+        let text = 0x94u32;
+        for (ph, (paddr, filesz)) in [(0x0800_0000u32, 8u32), (0x2000_0000, 0)]
+            .iter()
+            .enumerate()
+        {
+            let ph = 0x34 + ph * 0x20;
+            elf[ph..ph + 4].copy_from_slice(&PT_LOAD.to_le_bytes());
+            elf[ph + 4..ph + 8].copy_from_slice(&text.to_le_bytes());
+            elf[ph + 12..ph + 16].copy_from_slice(&paddr.to_le_bytes());
+            elf[ph + 16..ph + 20].copy_from_slice(&filesz.to_le_bytes());
+        }
+        elf.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        elf
+    }
+
+    #[test]
+    fn elf_bootloader() {
+        let image = assemble(&PARTITIONS, &elf(), &[0xbb; 4], &[0xcc; 4]).unwrap();
+        assert_eq!(
+            image.segments()[0],
+            Segment {
+                addr: 0x0800_0000,
+                data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            }
+        );
+        assert_eq!(image.segments().len(), 3);
+
+        let mut truncated = elf();
+        truncated.truncate(0x98);
+        assert!(matches!(
+            assemble(&PARTITIONS, &truncated, &[], &[]),
+            Err(RbSignerError::InvalidElf)
+        ));
+    }
+}
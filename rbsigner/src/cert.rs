@@ -0,0 +1,162 @@
+//! Signing certificates i.e. the root key's certification of another signing key (ex: one that
+//! signs daily builds), see `rustBoot::cert`.
+//!
+//! The root key certifies a signing key once, then images are signed with the signing key and
+//! carry its certificate
+//!
+//! ```text
+//! rbsigner cert <signing-key.der> nistp256 <root-key.der> <production|development> <not before> <not after>
+//! rbsigner mcu-image <image.bin> nistp256 <signing-key.der> <version> --cert <signing-key.cert>
+//! ```
+
+use crate::curve::*;
+use p256::ecdsa::signature::{digest::Digest, DigestSigner};
+use rustBoot::cert::{CertRole, SigningCert, CERT_MAGIC};
+use sha2::Sha256;
+
+/// Parses a certificate's role, as given on the command line.
+pub fn parse_role(role: &str) -> Option<CertRole> {
+    match role {
+        "production" => Some(CertRole::Production),
+        "development" => Some(CertRole::Development),
+        _ => None,
+    }
+}
+
+/// Returns a certificate for `key`, signed with `root`. The validity is in seconds since the unix
+/// epoch, checked against the timestamps of the images `key` signs.
+pub fn sign_cert(
+    role: CertRole,
+    validity: (u64, u64),
+    key: &SigningKeyType,
+    root: SigningKeyType,
+) -> Result<Vec<u8>> {
+    if validity.0 > validity.1 {
+        return Err(RbSignerError::InvalidCert);
+    }
+    let mut cert = Vec::new();
+    cert.extend_from_slice(&CERT_MAGIC.to_le_bytes());
+    cert.extend_from_slice(&role.as_u32().to_le_bytes());
+    cert.extend_from_slice(&validity.0.to_le_bytes());
+    cert.extend_from_slice(&validity.1.to_le_bytes());
+    cert.extend_from_slice(&public_key(key)?);
+    match root {
+        #[cfg(feature = "nistp256")]
+        SigningKeyType::NistP256(sk) => {
+            let signature = sk
+                .try_sign_digest(Sha256::new().chain(&cert))
+                .map_err(RbSignerError::SignatureError)?;
+            cert.extend_from_slice(signature.as_ref());
+            Ok(cert)
+        }
+        _ => Err(RbSignerError::InvalidKeyType),
+    }
+}
+
+/// Checks that `cert` is a certificate for `key` i.e. that images signed with `key` may carry it.
+pub fn check_cert(cert: &[u8], key: &SigningKeyType) -> Result<()> {
+    let cert = SigningCert::parse(cert).map_err(|_| RbSignerError::InvalidCert)?;
+    match cert.public_key() == public_key(key)? {
+        true => Ok(()),
+        false => Err(RbSignerError::InvalidCert),
+    }
+}
+
+/// A signing key's public key i.e. an untagged, uncompressed point.
+fn public_key(key: &SigningKeyType) -> Result<Vec<u8>> {
+    match key {
+        #[cfg(feature = "nistp256")]
+        SigningKeyType::NistP256(sk) => {
+            Ok(sk.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec())
+        }
+        _ => Err(RbSignerError::InvalidKeyType),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbsigner::sign::mcu_image_header;
+    use rustBoot::chain::SignedImage;
+    use rustBoot::parser::VendorTlv;
+    use rustBoot::rbconstants::{HDR_SIGNING_CERT, SIGNING_CERT_SIZE};
+    use rustBoot::RustbootError;
+
+    /// The signing key matching rustBoot's embedded (root) public key.
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+    const DEV_KEY: [u8; 32] = [0x22; 32];
+
+    fn key(bytes: &[u8; 32]) -> SigningKeyType {
+        import_signing_key(CurveType::NistP256, bytes).unwrap()
+    }
+
+    /// Signs `fw` with `sk`, the way `mcu-image --cert` does.
+    fn certified_image(fw: &[u8], cert: &[u8], timestamp: i64, sk: &SigningKeyType) -> Vec<u8> {
+        let fw = [fw, cert].concat();
+        let size = (SIGNING_CERT_SIZE as u16).to_le_bytes();
+        let tlvs = [VendorTlv {
+            typ: HDR_SIGNING_CERT,
+            value: &size,
+        }];
+        let header = mcu_image_header(
+            &fw[..],
+            fw.len() as u32,
+            [1, 0, 0, 0],
+            timestamp,
+            1,
+            &tlvs,
+            sk,
+        )
+        .unwrap();
+        [&header[..], &fw[..]].concat()
+    }
+
+    #[test]
+    fn certified_images() {
+        let dev = key(&DEV_KEY);
+        let cert = sign_cert(CertRole::Development, (1000, 2000), &dev, key(&SK_BYTES)).unwrap();
+        assert_eq!(cert.len(), SIGNING_CERT_SIZE);
+        check_cert(&cert, &dev).unwrap();
+        assert!(matches!(
+            check_cert(&cert, &key(&SK_BYTES)),
+            Err(RbSignerError::InvalidCert)
+        ));
+
+        let image = certified_image(&[0x55; 300], &cert, 1500, &dev);
+        SignedImage::parse(&image).unwrap().verify().unwrap();
+        // signed after the certificate expired
+        let image = certified_image(&[0x55; 300], &cert, 2001, &dev);
+        assert_eq!(
+            SignedImage::parse(&image).unwrap().verify(),
+            Err(RustbootError::CertRefused)
+        );
+        // a key can't certify itself
+        let cert = sign_cert(CertRole::Production, (1000, 2000), &dev, key(&DEV_KEY)).unwrap();
+        let image = certified_image(&[0x55; 300], &cert, 1500, &dev);
+        assert_eq!(
+            SignedImage::parse(&image).unwrap().verify(),
+            Err(RustbootError::FwAuthFailed)
+        );
+    }
+
+    #[test]
+    fn invalid_certs() {
+        let res = sign_cert(
+            CertRole::Production,
+            (2000, 1000),
+            &key(&DEV_KEY),
+            key(&SK_BYTES),
+        );
+        assert!(matches!(res, Err(RbSignerError::InvalidCert)));
+        assert!(matches!(
+            check_cert(&[0u8; SIGNING_CERT_SIZE], &key(&DEV_KEY)),
+            Err(RbSignerError::InvalidCert)
+        ));
+        assert_eq!(parse_role("development"), Some(CertRole::Development));
+        assert_eq!(parse_role("root"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use rustBoot::fs::chunks::{ChunkEntry, IndexHeader};
+use sha2::{Digest, Sha256};
+
+use std::ops::Range;
+
+/// Chunks are at least `MIN_CHUNK` bytes, unless they end the image.
+const MIN_CHUNK: usize = 16 * 1024;
+/// Chunks are at most `MAX_CHUNK` bytes.
+const MAX_CHUNK: usize = 256 * 1024;
+/// Cuts are made where the rolling hash's top 16 bits are clear i.e. chunks average ~64KiB.
+const CUT_MASK: u64 = 0xffff << 48;
+
+/// A chunked image i.e. its chunk index and its chunks.
+pub struct ChunkedImage<'a> {
+    /// the chunk index, as it's stored on the device.
+    pub index: Vec<u8>,
+    /// the chunks and their index entries, in image order.
+    pub chunks: Vec<(ChunkEntry, &'a [u8])>,
+}
+
+/// Splits an image (ex: a signed fit-image) into content-defined chunks and returns its chunk
+/// index, see `rustBoot::fs::chunks`.
+///
+/// Chunk boundaries depend on the (preceding 64 bytes of) content only, so a change in one part
+/// of an image only changes the chunks around it - the rest are shared with the previous image.
+pub fn chunk_image(image: &[u8]) -> ChunkedImage<'_> {
+    let chunks = cut_points(image)
+        .into_iter()
+        .map(|range| {
+            let data = &image[range];
+            let entry = ChunkEntry {
+                digest: Sha256::digest(data).into(),
+                len: data.len() as u32,
+            };
+            (entry, data)
+        })
+        .collect::<Vec<_>>();
+    let header = IndexHeader {
+        count: chunks.len() as u32,
+        size: image.len() as u32,
+    };
+    let mut index = header.to_bytes().to_vec();
+    chunks
+        .iter()
+        .for_each(|(entry, _)| index.extend_from_slice(&entry.to_bytes()));
+    ChunkedImage { index, chunks }
+}
+
+/// Returns the chunks' boundaries, found with a (gear-based) rolling hash.
+fn cut_points(image: &[u8]) -> Vec<Range<usize>> {
+    let gear = gear_table();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < image.len() {
+        let data = &image[start..image.len().min(start + MAX_CHUNK)];
+        let mut hash = 0u64;
+        let len = (MIN_CHUNK..data.len())
+            .find(|&idx| {
+                hash = (hash << 1).wrapping_add(gear[data[idx] as usize]);
+                hash & CUT_MASK == 0
+            })
+            .map_or(data.len(), |idx| idx + 1);
+        chunks.push(start..start + len);
+        start += len;
+    }
+    chunks
+}
+
+/// The rolling hash's table i.e. 256 pseudo-random values (splitmix64, with a fixed seed).
+fn gear_table() -> [u64; 256] {
+    let mut state = 0x7275_7374_426f_6f74u64; // "rustBoot"
+    let mut table = [0u64; 256];
+    table.iter_mut().for_each(|val| {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        *val = z ^ (z >> 31);
+    });
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `len` pseudo-random bytes.
+    fn image(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunk_image_test() {
+        let img = image(2 * 1024 * 1024, 1);
+        let chunked = chunk_image(&img);
+        let header = IndexHeader::from_bytes(&chunked.index).unwrap();
+        assert_eq!(header.size as usize, img.len());
+        assert_eq!(header.count as usize, chunked.chunks.len());
+        assert_eq!(chunked.index.len(), 16 + 36 * chunked.chunks.len());
+        let (last, rest) = chunked.chunks.split_last().unwrap();
+        assert!(rest
+            .iter()
+            .all(|(_, data)| (MIN_CHUNK..=MAX_CHUNK).contains(&data.len())));
+        assert!(last.1.len() <= MAX_CHUNK);
+        let reassembled = chunked
+            .chunks
+            .iter()
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect::<Vec<_>>();
+        assert_eq!(reassembled, img);
+    }
+
+    #[test]
+    fn chunks_are_content_defined() {
+        let img = image(2 * 1024 * 1024, 2);
+        // insert a few bytes near the start, only the first chunks change
+        let mut updated = img.clone();
+        updated.splice(1000..1000, [0xaa; 100]);
+        let digests = |img: &[u8]| {
+            chunk_image(img)
+                .chunks
+                .iter()
+                .map(|(entry, _)| entry.digest)
+                .collect::<Vec<_>>()
+        };
+        let (old, new) = (digests(&img), digests(&updated));
+        let shared = new.iter().filter(|digest| old.contains(digest)).count();
+        assert!(shared >= old.len() - 2, "{} of {}", shared, old.len());
+    }
+}
@@ -0,0 +1,124 @@
+use rustBoot::lz4::WINDOW;
+
+use std::convert::TryInto;
+
+/// The smallest match, matches' lengths are stored less this.
+const MIN_MATCH: usize = 4;
+/// A block ends with at least `LAST_LITERALS` literals...
+const LAST_LITERALS: usize = 5;
+/// ...and its last match starts at least `MF_LIMIT` bytes before its end, as LZ4 decoders expect.
+const MF_LIMIT: usize = 12;
+/// Matches are found with a hash table of the last position of each 4-byte sequence's hash.
+const HASH_BITS: u32 = 12;
+
+/// Compresses firmware into a raw LZ4 block, for rustBoot to decompress when it installs the
+/// update (see `rustBoot::lz4`).
+///
+/// Matches reach at most [`WINDOW`] bytes back, so the block can be decompressed with a
+/// [`WINDOW`]-byte buffer. The compressor is greedy i.e. it takes the first match it finds.
+pub fn lz4_compress(data: &[u8]) -> Vec<u8> {
+    let mut block = Vec::with_capacity(data.len() / 2);
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let (mut anchor, mut pos) = (0, 0);
+    while pos + MF_LIMIT < data.len() {
+        let seq = u32::from_le_bytes(data[pos..pos + MIN_MATCH].try_into().unwrap());
+        let hash = (seq.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[hash], pos);
+        if candidate != usize::MAX
+            && pos - candidate <= WINDOW
+            && data[candidate..candidate + MIN_MATCH] == data[pos..pos + MIN_MATCH]
+        {
+            let mut len = MIN_MATCH;
+            while pos + len < data.len() - LAST_LITERALS && data[candidate + len] == data[pos + len]
+            {
+                len += 1;
+            }
+            sequence(&mut block, &data[anchor..pos], Some((pos - candidate, len)));
+            pos += len;
+            anchor = pos;
+        } else {
+            pos += 1;
+        }
+    }
+    sequence(&mut block, &data[anchor..], None);
+    block
+}
+
+/// Appends a sequence i.e. a literal run and a match (`(offset, length)`), the block's last
+/// sequence has no match.
+fn sequence(block: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_len = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    block.push(((literals.len().min(15) as u8) << 4) | match_len.min(15) as u8);
+    if literals.len() >= 15 {
+        length(block, literals.len() - 15);
+    }
+    block.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        block.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_len >= 15 {
+            length(block, match_len - 15);
+        }
+    }
+}
+
+/// Appends the rest of a length that doesn't fit its token's nibble.
+fn length(block: &mut Vec<u8>, mut len: usize) {
+    while len >= 0xff {
+        block.push(0xff);
+        len -= 0xff;
+    }
+    block.push(len as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustBoot::lz4::decompress;
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let block = lz4_compress(data);
+        let mut input = block.iter();
+        let mut window = [0u8; WINDOW];
+        let mut out = Vec::new();
+        decompress(
+            || Ok(*input.next().unwrap()),
+            block.len(),
+            data.len(),
+            &mut window,
+            |bytes| {
+                out.extend_from_slice(bytes);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(out, data);
+        block
+    }
+
+    #[test]
+    fn compressed_firmware_round_trips() {
+        for len in [0, 1, 12, 13, 17, 100] {
+            round_trip(&vec![0xaa; len]);
+        }
+        // pseudo-random bytes don't compress
+        let mut state = 1u64;
+        let random = (0..64 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        assert!(round_trip(&random).len() > random.len());
+        // firmware-like i.e. repeated (but not identical) runs, with erased padding
+        let mut firmware = Vec::new();
+        for idx in 0..20_000u32 {
+            firmware.extend_from_slice(&[0x00, 0xbf, 0x4f, 0xf0]);
+            firmware.extend_from_slice(&(idx % 37).to_le_bytes());
+        }
+        firmware.extend_from_slice(&[0xff; 10_000]);
+        firmware.extend_from_slice(&random[..5000]);
+        assert!(round_trip(&firmware).len() < firmware.len() / 4);
+    }
+}
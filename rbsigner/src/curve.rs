@@ -71,6 +71,47 @@ pub enum RbSignerError {
     KeyError(SigningError),
     /// An invalid key type was provided
     InvalidKeyType,
+    /// The firmware couldn't be read or is shorter than its declared size
+    ReadError,
+    /// The SUIT envelope doesn't fit in an image header, contains the envelope's size
+    EnvelopeTooLarge(usize),
+    /// The image (or CSF) doesn't fit in the space available for it, contains its size
+    ImageTooLarge(usize),
+    /// Not an i.MX HAB image i.e. no IVT or an unexpected layout
+    InvalidImxImage,
+    /// The board manifest is missing its partitions or isn't valid toml
+    InvalidManifest,
+    /// Not a 32-bit little-endian ELF or a truncated one
+    InvalidElf,
+    /// The factory image's segments overlap
+    OverlappingImages,
+    /// A vendor TLV's type isn't a vendor type (i.e. `0x8000..=0xfffe`), contains the type
+    InvalidVendorTlv(u16),
+    /// The vendor TLVs don't fit in the image header, contains their size
+    VendorTlvsTooLarge(usize),
+    /// The release isn't valid toml, lists an invalid board id or an image without a rustBoot
+    /// header
+    InvalidRelease,
+    /// The signing certificate is malformed or doesn't certify the signing key
+    InvalidCert,
+    /// The signing server's config isn't valid toml, has an invalid token digest or a policy
+    /// lists an unknown key or client
+    InvalidServeConfig,
+    /// The key file isn't in a supported format (see rbsigner's `keyfile`) or is malformed
+    InvalidKeyFile,
+    /// The key isn't a nistp256 key, contains its curve or algorithm
+    UnsupportedKey(&'static str),
+    /// The encrypted key couldn't be decrypted i.e. a wrong passphrase or an unsupported cipher
+    KeyDecryptionFailed,
+    /// The SUIT envelope (or its manifest) couldn't be CBOR-encoded
+    EncodingError,
     #[doc(hidden)]
     __Nonexhaustive,
 }
+
+#[cfg(feature = "std")]
+impl<E> From<minicbor::encode::Error<E>> for RbSignerError {
+    fn from(_: minicbor::encode::Error<E>) -> Self {
+        RbSignerError::EncodingError
+    }
+}
@@ -0,0 +1,220 @@
+use crate::curve::*;
+use field::*;
+
+/// i.MX8M HAB (High Assurance Boot) image layout.
+///
+/// ```text
+/// self ->  +-----------------+
+///          | IVT             |
+///          | boot data       |
+/// entry -> +-----------------+
+///          | rustBoot        |
+///          | (padded)        |
+/// csf ->   +-----------------+
+///          | CSF             |
+///          +-----------------+
+/// ```
+///
+/// The ROM loads the whole image (i.e. `boot data.size` bytes) to `self`, authenticates everything
+/// up to the CSF (using the CSF's commands and signatures) and jumps to `entry`.
+mod field {
+
+    use core::ops::Range;
+
+    pub type Field = Range<usize>;
+
+    // Image Vector Table
+    pub const IVT_HEADER: Field = 0..4;
+    pub const IVT_ENTRY: Field = 4..8;
+    pub const IVT_DCD: Field = 12..16;
+    pub const IVT_BOOT_DATA: Field = 16..20;
+    pub const IVT_SELF: Field = 20..24;
+    pub const IVT_CSF: Field = 24..28;
+
+    // Boot data, follows the IVT
+    pub const BOOT_DATA_START: Field = 32..36;
+    pub const BOOT_DATA_SIZE: Field = 36..40;
+    pub const BOOT_DATA_PLUGIN: Field = 40..44;
+}
+
+/// IVT header i.e. tag `0xD1`, length `0x0020` (big-endian) and version `0x41`.
+const IVT_HEADER_VALUE: [u8; 4] = [0xD1, 0x00, 0x20, 0x41];
+/// Size of the IVT and boot data, padded. The application follows.
+pub const HAB_HEADER_SIZE: usize = 0x40;
+/// The authenticated part of the image (header and application) is padded to this alignment.
+const HAB_ALIGN: usize = 0x1000;
+/// Space reserved for the CSF (Command Sequence File) generated by NXP's CST.
+pub const CSF_SIZE: usize = 0x2000;
+/// End of the i.MX8M Nano's OCRAM i.e. the image must fit below this address.
+const OCRAM_END: u32 = 0x0098_0000;
+
+/// Addresses of a HAB image, as loaded by the ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HabLayout {
+    /// where the image (i.e. its IVT) is loaded.
+    pub load_addr: u32,
+    /// the application's entry point.
+    pub entry: u32,
+    /// where the CSF is loaded.
+    pub csf_addr: u32,
+    /// length of the image, without the CSF i.e. the length of the authenticated block.
+    pub auth_len: usize,
+}
+
+/// Returns a HAB image i.e. the application (`app_blob`) prefixed with an IVT and boot data,
+/// followed by space for the CSF. `entry` is the application's load (and entry) address i.e. the
+/// address rustBoot is linked at.
+///
+/// The CSF is filled with zeroes, see [`csf_template`] and [`insert_csf`].
+pub fn hab_image(app_blob: Vec<u8>, entry: u32) -> Result<(Vec<u8>, HabLayout)> {
+    let load_addr = entry - HAB_HEADER_SIZE as u32;
+    let auth_len = align(HAB_HEADER_SIZE + app_blob.len(), HAB_ALIGN);
+    let image_len = auth_len + CSF_SIZE;
+    if load_addr as usize + image_len > OCRAM_END as usize {
+        return Err(RbSignerError::ImageTooLarge(image_len));
+    }
+    let layout = HabLayout {
+        load_addr,
+        entry,
+        csf_addr: load_addr + auth_len as u32,
+        auth_len,
+    };
+
+    let mut image = vec![0u8; HAB_HEADER_SIZE];
+    image[IVT_HEADER].copy_from_slice(&IVT_HEADER_VALUE);
+    image[IVT_ENTRY].copy_from_slice(&entry.to_le_bytes());
+    image[IVT_DCD].copy_from_slice(&0u32.to_le_bytes());
+    image[IVT_BOOT_DATA].copy_from_slice(&(load_addr + BOOT_DATA_START.start as u32).to_le_bytes());
+    image[IVT_SELF].copy_from_slice(&load_addr.to_le_bytes());
+    image[IVT_CSF].copy_from_slice(&layout.csf_addr.to_le_bytes());
+    image[BOOT_DATA_START].copy_from_slice(&load_addr.to_le_bytes());
+    image[BOOT_DATA_SIZE].copy_from_slice(&(image_len as u32).to_le_bytes());
+    image[BOOT_DATA_PLUGIN].copy_from_slice(&0u32.to_le_bytes());
+    image.extend_from_slice(&app_blob);
+    image.resize(image_len, 0);
+    Ok((image, layout))
+}
+
+/// Returns a CSF description for NXP's CST (Code Signing Tool), authenticating the image in
+/// `image_file`. The key and certificate paths are placeholders, for the device's PKI.
+///
+/// `cst -i <csf-description> -o <csf-binary>` produces the CSF to insert with [`insert_csf`].
+pub fn csf_template(layout: &HabLayout, image_file: &str) -> String {
+    format!(
+        r#"[Header]
+    Version = 4.3
+    Hash Algorithm = sha256
+    Engine = CAAM
+    Engine Configuration = 0
+    Certificate Format = X509
+    Signature Format = CMS
+
+[Install SRK]
+    # SRK table, generated by CST's srktool
+    File = "crts/SRK_1_2_3_4_table.bin"
+    Source index = 0
+
+[Install CSFK]
+    File = "crts/CSF1_1_sha256_2048_65537_v3_usr_crt.pem"
+
+[Authenticate CSF]
+
+[Unlock]
+    Engine = CAAM
+    Features = MID
+
+[Install Key]
+    Verification index = 0
+    Target index = 2
+    File = "crts/IMG1_1_sha256_2048_65537_v3_usr_crt.pem"
+
+[Authenticate Data]
+    Verification index = 2
+    # IVT, boot data and rustBoot
+    Blocks = {:#x} 0x0 {:#x} "{}"
+"#,
+        layout.load_addr, layout.auth_len, image_file
+    )
+}
+
+/// Inserts a (vendor-signed) CSF into a HAB image produced by [`hab_image`].
+pub fn insert_csf(mut image: Vec<u8>, csf: &[u8]) -> Result<Vec<u8>> {
+    if image.len() < HAB_HEADER_SIZE || image[IVT_HEADER] != IVT_HEADER_VALUE {
+        return Err(RbSignerError::InvalidImxImage);
+    }
+    let load_addr = u32::from_le_bytes(image[IVT_SELF].try_into().unwrap());
+    let csf_addr = u32::from_le_bytes(image[IVT_CSF].try_into().unwrap());
+    let csf_offset = csf_addr.wrapping_sub(load_addr) as usize;
+    if csf_offset + CSF_SIZE != image.len() {
+        return Err(RbSignerError::InvalidImxImage);
+    }
+    if csf.len() > CSF_SIZE {
+        return Err(RbSignerError::ImageTooLarge(csf.len()));
+    }
+    image[csf_offset..csf_offset + csf.len()].copy_from_slice(csf);
+    Ok(image)
+}
+
+fn align(len: usize, align: usize) -> usize {
+    len.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY: u32 = 0x912000;
+
+    #[test]
+    fn ivt_and_boot_data() {
+        let (image, layout) = hab_image(vec![0xaa; 0x1500], ENTRY).unwrap();
+        assert_eq!(layout.load_addr, 0x911fc0);
+        assert_eq!(layout.auth_len, 0x2000);
+        assert_eq!(layout.csf_addr, 0x913fc0);
+        assert_eq!(image.len(), 0x2000 + CSF_SIZE);
+
+        assert_eq!(image[IVT_HEADER], [0xd1, 0x00, 0x20, 0x41]);
+        assert_eq!(image[IVT_ENTRY], ENTRY.to_le_bytes());
+        assert_eq!(image[IVT_BOOT_DATA], 0x911fe0u32.to_le_bytes());
+        assert_eq!(image[IVT_SELF], 0x911fc0u32.to_le_bytes());
+        assert_eq!(image[IVT_CSF], 0x913fc0u32.to_le_bytes());
+        assert_eq!(image[BOOT_DATA_START], 0x911fc0u32.to_le_bytes());
+        assert_eq!(image[BOOT_DATA_SIZE], 0x4000u32.to_le_bytes());
+        // the application starts at its entry point
+        assert_eq!(
+            image[HAB_HEADER_SIZE..HAB_HEADER_SIZE + 0x1500],
+            [0xaa; 0x1500]
+        );
+    }
+
+    #[test]
+    fn image_too_large() {
+        assert!(matches!(
+            hab_image(vec![0xaa; 0x70000], ENTRY),
+            Err(RbSignerError::ImageTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn csf_insertion() {
+        let (image, layout) = hab_image(vec![0xaa; 0x100], ENTRY).unwrap();
+        let csf_template = csf_template(&layout, "rustBoot_hab.bin");
+        assert!(csf_template.contains(r#"Blocks = 0x911fc0 0x0 0x1000 "rustBoot_hab.bin""#));
+
+        let signed = insert_csf(image.clone(), &[0x55; 0x400]).unwrap();
+        assert_eq!(
+            signed[layout.auth_len..layout.auth_len + 0x400],
+            [0x55; 0x400]
+        );
+        assert_eq!(signed[..layout.auth_len], image[..layout.auth_len]);
+
+        assert!(matches!(
+            insert_csf(image.clone(), &[0x55; CSF_SIZE + 1]),
+            Err(RbSignerError::ImageTooLarge(_))
+        ));
+        assert!(matches!(
+            insert_csf(vec![0xaa; 0x100], &[0x55; 0x400]),
+            Err(RbSignerError::InvalidImxImage)
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+//! rbsigner's signing core, as a library.
+//!
+//! The core doesn't allocate - the firmware is hashed as it's read (see [`sign::Read`]) and the
+//! image header is assembled in a fixed-size buffer. Without the `std` feature (on by default) it
+//! is `no_std`, so it can be embedded into other host tools or into provisioning firmware.
+//!
+//! The `rbsigner` tool (which also signs fit-images and SUIT envelopes) requires `std`.
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+pub mod curve;
+pub mod header;
+pub mod sign;
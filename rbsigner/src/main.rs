@@ -1,41 +1,85 @@
-mod curve;
+mod assemble;
+mod cert;
+mod chunker;
+mod compress;
 mod fitsigner;
+mod habimage;
+mod keyfile;
 mod mcusigner;
+mod profile;
+mod release;
+mod serve;
+mod suitsigner;
 
-use curve::SigningKeyType;
-use curve::{import_signing_key, CurveType};
+use assemble::{assemble, Partitions};
+use cert::{check_cert, parse_role, sign_cert};
+use chunker::chunk_image;
+use compress::lz4_compress;
 use fitsigner::sign_fit;
+use habimage::{csf_template, hab_image, insert_csf};
+use keyfile::load_signing_key;
 use mcusigner::sign_mcu_image;
+use profile::TargetProfile;
+use rbsigner::curve;
+use rbsigner::curve::SigningKeyType;
+use release::{sign_release, Release};
 use rustBoot::dt::Reader;
+use rustBoot::fs::chunks::CHUNK_DIR;
+use rustBoot::parser::{board_id, VendorTlv};
+use rustBoot::rbconstants::{
+    COMPRESSION_LZ4, HDR_BOARD_ID, HDR_COMPRESSION, HDR_IMG_TYPE_APP, HDR_SIGNING_CERT,
+    HDR_VENDOR_TYPE_MIN, HDR_VERSION_STRING, HDR_VERSION_STRING_MAX_LEN, IMAGE_HEADER_SIZE,
+    SIGNING_CERT_SIZE,
+};
+use serve::serve;
+use suitsigner::sign_suit_image;
 
 use std::env;
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::path::Path;
 
 fn main() {
     // let _ = log_init();
 
     let args = env::args().collect::<Vec<_>>();
     let args = args.iter().map(|s| &**s).collect::<Vec<_>>();
+    // mcu-images take any number of `--custom-tlv <type>:<hex value>` options and an optional
+    // `--board <board>`, `--cert <cert>`, `--compress lz4` and `--version-string <version>`,
+    // mcu/suit-images an optional `--target <board>`
+    let (args, options) = split_options(&args);
+    let custom_tlvs = options.custom_tlvs;
+    let board = options.board;
+    let cert = options.cert;
+    let compress = options.compress;
+    let version_string = options.version_string;
+    let profile = options.target.map(target_profile);
 
-    let mut key_file = Vec::new();
-    let mut kf = fs::File::open(args[4]).expect("Need path to key_file as argument");
-    kf.read_to_end(&mut key_file).unwrap();
-    let sk: SigningKeyType;
-
-    match args[3] {
-        "nistp256" => {
-            let signing_key = &key_file.as_slice()[0x40..];
-            if signing_key.len() != 32 {
-                panic!("invalid nistp256 key: length is not 32 bytes")
-            }
-            sk = import_signing_key(CurveType::NistP256, signing_key).unwrap();
+    // i.MX HAB images are signed with NXP's CST and the device's keys, not with a rustBoot key.
+    match args[1] {
+        "imx-image" => return imx_image(&args),
+        "imx-csf" => return imx_csf(&args),
+        // combines already signed images, no key required
+        "assemble" => return factory_image(&args),
+        // splits an already signed fit-image into chunks, no key required
+        "chunk-index" => return chunk_index(&args),
+        // signs digests on behalf of clients, with the keys listed in its config
+        "serve" => {
+            return serve(Path::new(
+                args.get(2).expect("Need path to the server's config"),
+            ))
         }
+        _ => {}
+    }
+
+    // a raw, SEC1 or (encrypted) PKCS#8 key, see `keyfile`
+    let sk: SigningKeyType = match args[3] {
+        "nistp256" => load_signing_key(args[4]),
         _ => {
             unimplemented!()
         }
-    }
+    };
 
     let mut image_blob = Vec::new();
     match args[1] {
@@ -101,6 +145,11 @@ fn main() {
             #[rustfmt::skip]
             let input_image_args = String::from(args[2].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]);
             let output_image = input_image_args + "_v" + &image_version_args + "_signed";
+            // optional image id, companion images (radio/coprocessor firmware) use an id other than the app's
+            let image_id = match args.get(6) {
+                Some(id) => parse_image_id(id),
+                None => HDR_IMG_TYPE_APP as u8,
+            };
 
             println!("\nImage type:       mcu-image");
             println!("Curve type:       {}", args[3]);
@@ -108,7 +157,28 @@ fn main() {
             println!("Input image:      {}.bin", String::from(args[2].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]));
             #[rustfmt::skip]
             println!("Public key:       {}.der", String::from(args[4].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]));
-            println!("Image version:    {}", args[5]);
+            match version_string {
+                Some(version_string) => {
+                    println!("Image version:    {} ({})", args[5], version_string)
+                }
+                None => println!("Image version:    {}", args[5]),
+            }
+            println!("Image id:         {:#04x}", image_id);
+            for (typ, value) in &custom_tlvs {
+                println!("Custom TLV:       {:#06x} ({} bytes)", typ, value.len());
+            }
+            if let Some(board) = board {
+                println!("Board:            {}", board);
+            }
+            if let Some(cert) = cert {
+                println!("Certificate:      {}", cert);
+            }
+            if compress {
+                println!("Compression:      lz4");
+            }
+            if let Some(profile) = &profile {
+                println!("Write size:       {} bytes", profile.write_size);
+            }
             println!("Output image:     {}.bin", output_image);
 
             //firmware version
@@ -118,8 +188,59 @@ fn main() {
             let mut mcu_image =
                 fs::File::open(args[2]).expect("Need path to mcu_image binary as argument");
             mcu_image.read_to_end(&mut image_blob).unwrap();
+            // the signing certificate is appended to the firmware and announced by its TLV
+            let cert_tlv = cert.map(|cert| {
+                let cert = fs::read(cert).expect("Need path to the signing certificate");
+                if let Err(e) = check_cert(&cert, &sk) {
+                    panic!("error: {:?}", e)
+                }
+                image_blob.extend_from_slice(&cert);
+                (SIGNING_CERT_SIZE as u16).to_le_bytes()
+            });
 
-            let mcu_image = sign_mcu_image(image_blob, args[2], sk, version);
+            // a compressed image is signed as it's installed i.e. decompressed, its compression TLV
+            // records the compressed firmware's length
+            let compressed = compress.then(|| lz4_compress(&image_blob));
+            let compression_tlv = compressed.as_ref().map(|compressed| {
+                let mut value = vec![COMPRESSION_LZ4, 0x00];
+                value.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+                println!(
+                    "Compressed size:  {} of {} bytes",
+                    compressed.len(),
+                    image_blob.len()
+                );
+                value
+            });
+
+            // the board-ID TLV binds the image to a board, rustBoot refuses it on any other board
+            let board_tlv = board.map(board_id);
+            let vendor_tlvs = custom_tlvs
+                .iter()
+                .map(|(typ, value)| VendorTlv { typ: *typ, value })
+                .chain(board_tlv.iter().map(|id| VendorTlv {
+                    typ: HDR_BOARD_ID,
+                    value: id,
+                }))
+                .chain(cert_tlv.iter().map(|size| VendorTlv {
+                    typ: HDR_SIGNING_CERT,
+                    value: size,
+                }))
+                .chain(compression_tlv.iter().map(|value| VendorTlv {
+                    typ: HDR_COMPRESSION,
+                    value,
+                }))
+                .chain(version_string.iter().map(|version| VendorTlv {
+                    typ: HDR_VERSION_STRING,
+                    value: version.as_bytes(),
+                }))
+                .collect::<Vec<_>>();
+            let mcu_image =
+                sign_mcu_image(image_blob, args[2], sk, version, image_id, &vendor_tlvs)
+                    .map(|image| match compressed {
+                        Some(compressed) => [&image[..IMAGE_HEADER_SIZE], &compressed].concat(),
+                        None => image,
+                    })
+                    .and_then(|image| pad_image(image, &profile));
             match mcu_image {
                 Ok(val) => {
                     let file = File::create(
@@ -139,10 +260,385 @@ fn main() {
                 Err(_e) => {}
             }
         }
+        "suit-image" => {
+            let image_version_args = String::from(args[5]);
+            #[rustfmt::skip]
+            let input_image_args = String::from(args[2].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]);
+            let output_image = input_image_args + "_v" + &image_version_args + "_suit_signed";
+
+            println!("\nImage type:       suit-image");
+            println!("Curve type:       {}", args[3]);
+            #[rustfmt::skip]
+            println!("Input image:      {}.bin", String::from(args[2].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]));
+            #[rustfmt::skip]
+            println!("Public key:       {}.der", String::from(args[4].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]));
+            println!("Sequence number:  {}", args[5]);
+            if let Some(profile) = &profile {
+                println!("Write size:       {} bytes", profile.write_size);
+            }
+            println!("Output image:     {}.bin", output_image);
+
+            // the manifest's sequence number is the firmware version
+            let sequence_number: u32 = args[5].parse().unwrap();
+
+            let mut mcu_image =
+                fs::File::open(args[2]).expect("Need path to mcu_image binary as argument");
+            mcu_image.read_to_end(&mut image_blob).unwrap();
+
+            match sign_suit_image(image_blob, sk, sequence_number)
+                .and_then(|image| pad_image(image, &profile))
+            {
+                Ok(val) => {
+                    let file = File::create(
+                        "../boards/sign_images/signed_images/{output_image}.bin"
+                            .replace("{output_image}", &output_image),
+                    );
+                    match file {
+                        Ok(mut file) => {
+                            let bytes_written = file.write(val.as_slice());
+                            if let Ok(val) = bytes_written {
+                                println!("Output image successfully created with {} bytes.\n", val);
+                            }
+                        }
+                        Err(e) => panic!("error: {:?}", e),
+                    }
+                }
+                Err(e) => panic!("error: {:?}", e),
+            }
+        }
+        "release-manifest" => release_manifest(&args, sk),
+        "cert" => signing_cert(&args, sk),
         _ => {}
     }
 }
 
+/// `release-manifest <release.toml> <curve> <key.der>` - signs a release manifest listing the
+/// images (and their target boards) of a release, see [`release`]. The manifest is written next
+/// to the release's file, as `<release>.rbm`.
+fn release_manifest(args: &[&str], sk: SigningKeyType) {
+    let path = Path::new(args[2]);
+    let release = fs::read_to_string(path).expect("Need path to the release (toml) as argument");
+    let release = match Release::from_toml(&release) {
+        Ok(release) => release,
+        Err(e) => panic!("error: {:?}", e),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let output = path.with_extension("rbm");
+
+    println!("\nImage type:       release-manifest");
+    println!("Curve type:       {}", args[3]);
+    println!("Sequence number:  {}", release.sequence);
+    let images = release
+        .images
+        .iter()
+        .map(|image| {
+            let path = dir.join(&image.image);
+            let bytes =
+                fs::read(&path).unwrap_or_else(|_| panic!("no signed image at {}", path.display()));
+            println!("Image:            {} ({})", path.display(), image.board);
+            (image.board.as_str(), bytes)
+        })
+        .collect::<Vec<_>>();
+    let images = images
+        .iter()
+        .map(|(board, bytes)| (*board, bytes.as_slice()))
+        .collect::<Vec<_>>();
+    match sign_release(release.sequence, &images, sk) {
+        Ok(manifest) => {
+            fs::write(&output, manifest).unwrap();
+            println!("Output manifest:  {}\n", output.display());
+        }
+        Err(e) => panic!("error: {:?}", e),
+    }
+}
+
+/// `cert <signing-key.der> <curve> <root-key.der> <role> <not before> <not after>` - certifies a
+/// signing key with the root key, the certificate is written next to the signing key.
+fn signing_cert(args: &[&str], root: SigningKeyType) {
+    let key = load_signing_key(args[2]);
+    let role = args
+        .get(5)
+        .and_then(|role| parse_role(role))
+        .expect("a certificate's role is either `production` or `development`");
+    let validity = match (args.get(6), args.get(7)) {
+        (Some(not_before), Some(not_after)) => (
+            not_before.parse().expect("invalid `not before` timestamp"),
+            not_after.parse().expect("invalid `not after` timestamp"),
+        ),
+        _ => panic!("Need the certificate's validity i.e. `<not before> <not after>` as arguments"),
+    };
+    let output = Path::new(args[2]).with_extension("cert");
+
+    println!("\nImage type:       signing-certificate");
+    println!("Curve type:       {}", args[3]);
+    println!("Signing key:      {}", args[2]);
+    println!("Role:             {:?}", role);
+    println!("Validity:         {} - {}", validity.0, validity.1);
+    match sign_cert(role, validity, &key, root) {
+        Ok(cert) => {
+            fs::write(&output, cert).unwrap();
+            println!("Output cert:      {}\n", output.display());
+        }
+        Err(e) => panic!("error: {:?}", e),
+    }
+}
+
+/// `imx-image <rustBoot.bin> <entry>` - wraps rustBoot in a HAB image and writes a CSF
+/// description for NXP's CST, next to it.
+fn imx_image(args: &[&str]) {
+    let entry = u32::from_str_radix(args[3].trim_start_matches("0x"), 16)
+        .expect("Need rustBoot's entry point (hex) as argument");
+    #[rustfmt::skip]
+    let input_image_args = String::from(args[2].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]);
+    let output_image = input_image_args + "_hab";
+    let out_dir = match args[2].rsplit_once('/') {
+        Some((dir, _)) => dir,
+        None => ".",
+    };
+
+    println!("\nImage type:       imx-image (HAB)");
+    println!("Input image:      {}", args[2]);
+    println!("Entry point:      {:#x}", entry);
+    println!("Output image:     {}.bin", output_image);
+
+    let mut app_blob = Vec::new();
+    let mut app = fs::File::open(args[2]).expect("Need path to rustBoot binary as argument");
+    app.read_to_end(&mut app_blob).unwrap();
+
+    match hab_image(app_blob, entry) {
+        Ok((image, layout)) => {
+            fs::write(format!("{out_dir}/{output_image}.bin"), &image).unwrap();
+            let csf = csf_template(&layout, &format!("{output_image}.bin"));
+            fs::write(format!("{out_dir}/{output_image}.csf"), csf).unwrap();
+            println!("CSF address:      {:#x}", layout.csf_addr);
+            println!(
+                "\nSign with `cst -i {output_image}.csf -o csf.bin`, then insert the CSF with `imx-csf`.\n"
+            );
+        }
+        Err(e) => panic!("error: {:?}", e),
+    }
+}
+
+/// `imx-csf <image_hab.bin> <csf.bin>` - inserts a CSF generated by NXP's CST into a HAB image.
+fn imx_csf(args: &[&str]) {
+    let image = fs::read(args[2]).expect("Need path to the HAB image as argument");
+    let csf = fs::read(args[3]).expect("Need path to the CSF binary as argument");
+    let output_image = args[2].trim_end_matches(".bin").to_owned() + "_signed.bin";
+    match insert_csf(image, &csf) {
+        Ok(image) => {
+            fs::write(&output_image, &image).unwrap();
+            println!("Output image successfully created: {}\n", output_image);
+        }
+        Err(e) => panic!("error: {:?}", e),
+    }
+}
+
+/// `assemble <manifest.toml> <bootloader> <bootfw_signed.bin> <updtfw_signed.bin> <output>` -
+/// combines the bootloader (an ELF or a binary) and the signed images into a factory image.
+/// The output is Intel HEX if its name ends with `.hex`, a raw binary otherwise.
+fn factory_image(args: &[&str]) {
+    let manifest =
+        fs::read_to_string(args[2]).expect("Need path to the board manifest as argument");
+    let bootloader =
+        fs::read(args[3]).expect("Need path to the bootloader (ELF or binary) as argument");
+    let boot_image = fs::read(args[4]).expect("Need path to the signed boot image as argument");
+    let update_image = fs::read(args[5]).expect("Need path to the signed update image as argument");
+    let output_image = args
+        .get(6)
+        .expect("Need path to the output image as argument");
+
+    let partitions = match Partitions::from_manifest(&manifest) {
+        Ok(partitions) => partitions,
+        Err(e) => panic!("error: {:?}", e),
+    };
+    println!("\nImage type:       factory-image");
+    println!(
+        "Bootloader:       {} @ {:#x}",
+        args[3], partitions.bootloader
+    );
+    println!("Boot image:       {} @ {:#x}", args[4], partitions.boot);
+    println!("Update image:     {} @ {:#x}", args[5], partitions.update);
+    println!("Output image:     {}", output_image);
+
+    match assemble(&partitions, &bootloader, &boot_image, &update_image) {
+        Ok(image) => {
+            match output_image.ends_with(".hex") {
+                true => fs::write(output_image, image.to_ihex()).unwrap(),
+                false => fs::write(output_image, image.to_bin()).unwrap(),
+            }
+            for segment in image.segments() {
+                println!(
+                    "  {:#010x}..{:#010x}",
+                    segment.addr,
+                    segment.addr as usize + segment.data.len()
+                );
+            }
+            println!("Output image successfully created: {}\n", output_image);
+        }
+        Err(e) => panic!("error: {:?}", e),
+    }
+}
+
+/// `chunk-index <signed.itb> <store>` - splits a signed fit-image into chunks, for differential
+/// updates (see `rustBoot::fs::chunks`). Writes the chunk index (`<name>.cix`) to `store` and adds
+/// the chunks that aren't in the store yet to `store/CHUNKS`. Only those need to be downloaded by
+/// a device that has the store's other images.
+fn chunk_index(args: &[&str]) {
+    let image = fs::read(args[2]).expect("Need path to the signed fit-image as argument");
+    let store = args
+        .get(3)
+        .expect("Need path to the chunk store as argument");
+    #[rustfmt::skip]
+    let output_index = String::from(args[2].rsplit_terminator(&['/', '.'][..]).collect::<Vec<_>>()[1]) + ".cix";
+
+    let chunked = chunk_image(&image);
+    let chunk_dir = format!("{store}/{CHUNK_DIR}");
+    fs::create_dir_all(&chunk_dir).unwrap();
+    let (mut new_chunks, mut new_bytes) = (0, 0);
+    for (entry, data) in &chunked.chunks {
+        let name = entry.file_name();
+        let path = format!("{chunk_dir}/{}", core::str::from_utf8(&name).unwrap());
+        if fs::metadata(&path).is_err() {
+            fs::write(&path, data).unwrap();
+            new_chunks += 1;
+            new_bytes += data.len();
+        }
+    }
+    fs::write(format!("{store}/{output_index}"), &chunked.index).unwrap();
+
+    println!("\nImage type:       chunk-index");
+    println!("Input image:      {} ({} bytes)", args[2], image.len());
+    println!("Chunks:           {}", chunked.chunks.len());
+    println!("New chunks:       {} ({} bytes)", new_chunks, new_bytes);
+    println!("Output index:     {}/{}\n", store, output_index);
+}
+
+/// Parses an image id, given as a decimal or `0x`-prefixed hex value.
+fn parse_image_id(arg: &str) -> u8 {
+    let id = match arg.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => arg.parse(),
+    }
+    .expect("image id must be a value between 0x01 and 0xfe");
+    assert!(
+        id != 0x00 && id != 0xFF,
+        "image id must be a value between 0x01 and 0xfe"
+    );
+    id
+}
+
+/// Options, given along with the positional arguments.
+#[derive(Default)]
+struct Options<'a> {
+    /// `--custom-tlv <type>:<hex value>`, any number of times
+    custom_tlvs: Vec<(u16, Vec<u8>)>,
+    /// `--target <board>` i.e. the board the signed image is padded for, see [`TargetProfile`]
+    target: Option<&'a str>,
+    /// `--board <board>` i.e. the board (or board variant) the signed image is bound to, see
+    /// `rustBoot::parser::board_id`
+    board: Option<&'a str>,
+    /// `--cert <cert>` i.e. the signing key's certificate, see `rustBoot::cert`
+    cert: Option<&'a str>,
+    /// `--compress lz4` i.e. the firmware is stored compressed, see `rustBoot::lz4`
+    compress: bool,
+    /// `--version-string <version>` i.e. the image's human-readable version (ex: from `git
+    /// describe`), see `rustBoot::parser::VendorTlvs::version_string`
+    version_string: Option<&'a str>,
+}
+
+/// Splits options from the positional arguments.
+fn split_options<'a>(args: &[&'a str]) -> (Vec<&'a str>, Options<'a>) {
+    let (mut positional, mut options) = (Vec::new(), Options::default());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--custom-tlv" => options.custom_tlvs.push(parse_custom_tlv(
+                args.next()
+                    .expect("--custom-tlv needs a `type:hexvalue` argument"),
+            )),
+            "--target" => {
+                options.target = Some(args.next().expect("--target needs a board argument"))
+            }
+            "--board" => options.board = Some(args.next().expect("--board needs a board argument")),
+            "--cert" => {
+                options.cert = Some(args.next().expect("--cert needs a certificate argument"))
+            }
+            "--compress" => {
+                let algorithm = args.next().expect("--compress needs an algorithm argument");
+                assert!(*algorithm == "lz4", "--compress only supports `lz4`");
+                options.compress = true;
+            }
+            "--version-string" => {
+                let version = args
+                    .next()
+                    .expect("--version-string needs a version argument");
+                assert!(
+                    version.len() <= HDR_VERSION_STRING_MAX_LEN,
+                    "--version-string can't be longer than {} bytes",
+                    HDR_VERSION_STRING_MAX_LEN
+                );
+                options.version_string = Some(version)
+            }
+            arg => positional.push(arg),
+        }
+    }
+    // rustBoot reads the signing certificate in place, at the end of the (decompressed) firmware
+    assert!(
+        !(options.compress && options.cert.is_some()),
+        "--compress can't be combined with --cert"
+    );
+    (positional, options)
+}
+
+/// Reads a board's profile from its manifest i.e. `boards/manifests/<board>.toml`.
+fn target_profile(board: &str) -> TargetProfile {
+    let path = format!("../boards/manifests/{}.toml", board);
+    let manifest = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("no board manifest for `{}` at {}", board, path));
+    match TargetProfile::from_manifest(&manifest) {
+        Ok(profile) => profile,
+        Err(e) => panic!("error: {:?}", e),
+    }
+}
+
+/// Pads a signed image for its target, if one was given.
+fn pad_image(mut image: Vec<u8>, profile: &Option<TargetProfile>) -> curve::Result<Vec<u8>> {
+    if let Some(profile) = profile {
+        profile.pad(&mut image)?;
+    }
+    Ok(image)
+}
+
+/// Parses a custom (i.e. vendor) TLV, given as `type:hexvalue`. The type is a decimal or
+/// `0x`-prefixed hex value.
+fn parse_custom_tlv(arg: &str) -> (u16, Vec<u8>) {
+    let (typ, value) = arg
+        .split_once(':')
+        .expect("custom TLVs are given as `type:hexvalue`");
+    let typ = match typ.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => typ.parse(),
+    }
+    .expect("a custom TLV's type must be a value between 0x8000 and 0xfffa");
+    // the version-string, compression, signing-certificate and board-ID TLVs' types are reserved,
+    // see `--version-string`, `--compress`, `--cert` and `--board`
+    assert!(
+        (HDR_VENDOR_TYPE_MIN..HDR_VERSION_STRING).contains(&typ),
+        "a custom TLV's type must be a value between 0x8000 and 0xfffa"
+    );
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    assert!(
+        value.len() % 2 == 0 && value.is_ascii(),
+        "a custom TLV's value must be an even number of hex digits"
+    );
+    let value = (0..value.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&value[idx..idx + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .expect("a custom TLV's value must be an even number of hex digits");
+    (typ, value)
+}
+
 use log::{Level, Metadata, Record};
 use log::{LevelFilter, SetLoggerError};
 
@@ -0,0 +1,165 @@
+//! Target profiles i.e. how a board programs its flash, as per its board manifest
+//! (`boards/manifests/<board>.toml`).
+//!
+//! Flash is programmed in whole units (ex: the stm32h7's 32-byte flash words). A signed image is
+//! padded with `0xFF` (i.e. erased flash) to a whole number of units, so its last unit is written
+//! whole rather than truncated. It must also end below the unit that holds the start of the
+//! partition trailer, as some parts program a unit only once per erase.
+//!
+//! ```text
+//! partition -> +-----------------+
+//!              | signed image    |
+//!              | (padding)       |
+//!              +-----------------+ <- unit aligned
+//!              | (gap)           |
+//!              +-----------------+ <- unit aligned, see `TargetProfile::trailer_offset`
+//!              | trial, flags    |
+//!              | state, magic    |
+//!              +-----------------+
+//! ```
+
+use crate::curve::{RbSignerError, Result};
+use serde::Deserialize;
+
+/// The tables of a board manifest needed for its profile. Other tables are ignored.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    flash: Flash,
+    partitions: Partitions,
+}
+
+#[derive(Debug, Deserialize)]
+struct Flash {
+    sector_size: usize,
+    #[serde(default = "default_write_size")]
+    write_size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Partitions {
+    size: usize,
+}
+
+fn default_write_size() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetProfile {
+    /// the programming unit, in bytes.
+    pub write_size: usize,
+    pub sector_size: usize,
+    pub partition_size: usize,
+}
+
+impl TargetProfile {
+    /// Parses a board manifest's `[flash]` and `[partitions]` tables. Without a `write_size`,
+    /// the board's flash is byte-programmable.
+    pub fn from_manifest(manifest: &str) -> Result<Self> {
+        let manifest =
+            toml::from_str::<Manifest>(manifest).map_err(|_| RbSignerError::InvalidManifest)?;
+        let profile = TargetProfile {
+            write_size: manifest.flash.write_size,
+            sector_size: manifest.flash.sector_size,
+            partition_size: manifest.partitions.size,
+        };
+        let valid = profile.write_size.is_power_of_two()
+            && profile.sector_size % profile.write_size == 0
+            && profile.partition_size % profile.sector_size == 0
+            && profile.partition_size > 0;
+        match valid {
+            true => Ok(profile),
+            false => Err(RbSignerError::InvalidManifest),
+        }
+    }
+
+    /// The offset (from the start of a partition) of the unit that holds the start of the
+    /// partition trailer. The update partition's trailer is the longest i.e. its magic, state,
+    /// sector flags and trial byte, see `rustBoot::image::image::PartDescriptor`.
+    pub fn trailer_offset(&self) -> usize {
+        let flags = (self.partition_size / self.sector_size).div_ceil(2);
+        let offset = self.partition_size - (4 + 1 + flags + 1);
+        offset - offset % self.write_size
+    }
+
+    /// Pads a signed image with `0xFF` to a whole number of units. Returns
+    /// [`RbSignerError::ImageTooLarge`] if it then runs into the partition trailer's unit.
+    pub fn pad(&self, image: &mut Vec<u8>) -> Result<()> {
+        let padded = image.len().div_ceil(self.write_size) * self.write_size;
+        if padded > self.trailer_offset() {
+            return Err(RbSignerError::ImageTooLarge(image.len()));
+        }
+        image.resize(padded, 0xFF);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STM32H723: TargetProfile = TargetProfile {
+        write_size: 32,
+        sector_size: 0x2_0000,
+        partition_size: 0x4_0000,
+    };
+
+    #[test]
+    fn manifest_profile() {
+        let manifest = r#"
+            [board]
+            name = "stm32h723"
+
+            [flash]
+            sector_size = 0x20000
+            write_size = 32
+
+            [partitions]
+            bootloader = 0x08000000
+            size = 0x40000
+            boot = 0x08020000
+            update = 0x08060000
+            swap = 0x080a0000
+        "#;
+        assert_eq!(TargetProfile::from_manifest(manifest).unwrap(), STM32H723);
+        // byte-programmable, by default
+        let manifest = manifest.replace("write_size = 32", "");
+        assert_eq!(
+            TargetProfile::from_manifest(&manifest).unwrap().write_size,
+            1
+        );
+        let manifest = manifest.replace("[flash]", "[flash]\nwrite_size = 24");
+        assert!(matches!(
+            TargetProfile::from_manifest(&manifest),
+            Err(RbSignerError::InvalidManifest)
+        ));
+    }
+
+    #[test]
+    fn trailer_offset() {
+        // 7 bytes of trailer i.e. magic, state, one byte of sector flags and the trial byte
+        assert_eq!(STM32H723.trailer_offset(), 0x4_0000 - 32);
+        let bytes = TargetProfile {
+            write_size: 1,
+            ..STM32H723
+        };
+        assert_eq!(bytes.trailer_offset(), 0x4_0000 - 7);
+    }
+
+    #[test]
+    fn padded_to_whole_units() {
+        let mut image = vec![0xaa; 0x1001];
+        STM32H723.pad(&mut image).unwrap();
+        assert_eq!(image.len(), 0x1020);
+        assert!(image[0x1001..].iter().all(|byte| *byte == 0xFF));
+        // already a whole number of units
+        STM32H723.pad(&mut image).unwrap();
+        assert_eq!(image.len(), 0x1020);
+
+        let mut image = vec![0xaa; 0x4_0000 - 31];
+        assert!(matches!(
+            STM32H723.pad(&mut image),
+            Err(RbSignerError::ImageTooLarge(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Release manifests i.e. the signed list of images a fleet's devices may install, see
+//! `rustBoot::release`.
+//!
+//! A release is described in toml, image paths are relative to the release's file
+//!
+//! ```toml
+//! sequence = 3
+//!
+//! [[images]]
+//! board = "nrf52840"
+//! image = "nrf52840_updtfw_v1236_signed.bin"
+//! ```
+//!
+//! An image's version is read from its header and its digest is computed over the whole signed
+//! image, as it's staged in the update partition.
+
+use crate::curve::*;
+use p256::ecdsa::signature::{digest::Digest, DigestSigner};
+use rustBoot::parser::{parse_header_tlv, Tags};
+use rustBoot::rbconstants::*;
+use rustBoot::release::{BOARD_ID_LEN, RELEASE_MAGIC};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use std::convert::TryInto;
+use std::path::PathBuf;
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    /// the manifest's sequence number, a newer release must have a higher one
+    pub sequence: u32,
+    pub images: Vec<ReleaseImage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseImage {
+    /// the board id, ascii and up to 16 bytes
+    pub board: String,
+    /// path to the signed image, relative to the release's file
+    pub image: PathBuf,
+}
+
+impl Release {
+    pub fn from_toml(release: &str) -> Result<Self> {
+        toml::from_str(release).map_err(|_| RbSignerError::InvalidRelease)
+    }
+}
+
+/// Returns a signed mcu-image's version, as rustBoot reads it from its header.
+pub fn image_version(image: &[u8]) -> Result<u32> {
+    let header = image
+        .get(..IMAGE_HEADER_SIZE)
+        .filter(|header| header[..4] == (RUSTBOOT_MAGIC as u32).to_le_bytes())
+        .ok_or(RbSignerError::InvalidRelease)?;
+    parse_header_tlv(header, Tags::Version)
+        .ok()
+        .and_then(|version| version.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(RbSignerError::InvalidRelease)
+}
+
+/// Returns a signed release manifest, listing each `(board id, signed image)`.
+pub fn sign_release(
+    sequence: u32,
+    images: &[(&str, &[u8])],
+    sk_type: SigningKeyType,
+) -> Result<Vec<u8>> {
+    let mut manifest = Vec::new();
+    manifest.extend_from_slice(&RELEASE_MAGIC.to_le_bytes());
+    manifest.extend_from_slice(&sequence.to_le_bytes());
+    manifest.extend_from_slice(&(images.len() as u32).to_le_bytes());
+    for (board, image) in images {
+        if board.is_empty() || board.len() > BOARD_ID_LEN || !board.is_ascii() {
+            return Err(RbSignerError::InvalidRelease);
+        }
+        let mut board_id = [0u8; BOARD_ID_LEN];
+        board_id[..board.len()].copy_from_slice(board.as_bytes());
+        manifest.extend_from_slice(&board_id);
+        manifest.extend_from_slice(&image_version(image)?.to_le_bytes());
+        manifest.extend_from_slice(&Sha256::digest(image));
+    }
+    match sk_type {
+        #[cfg(feature = "nistp256")]
+        SigningKeyType::NistP256(sk) => {
+            let signature = sk
+                .try_sign_digest(Sha256::new().chain(&manifest))
+                .map_err(RbSignerError::SignatureError)?;
+            manifest.extend_from_slice(signature.as_ref());
+            Ok(manifest)
+        }
+        _ => Err(RbSignerError::InvalidKeyType),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbsigner::sign::mcu_image_header;
+    use rustBoot::release::ReleaseManifest;
+
+    /// The signing key matching rustBoot's embedded public key.
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    fn signed_image(version: u32) -> Vec<u8> {
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let fw = [0x55u8; 300];
+        let header =
+            mcu_image_header(&fw[..], 300, version.to_le_bytes(), 0, 1, &[], &sk_type).unwrap();
+        [&header[..], &fw[..]].concat()
+    }
+
+    #[test]
+    fn signed_release() {
+        let (v1, v2) = (signed_image(1235), signed_image(1236));
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let manifest = sign_release(3, &[("nrf52840", &v1), ("stm32f411", &v2)], sk_type).unwrap();
+
+        let release = ReleaseManifest::parse(&manifest).unwrap();
+        assert_eq!(release.sequence(), 3);
+        let version = image_version(&v2).unwrap();
+        let entry = release.find(b"stm32f411", version).unwrap();
+        assert_eq!(entry.digest[..], Sha256::digest(&v2)[..]);
+        assert!(release.find(b"nrf52840", version).is_none());
+    }
+
+    #[test]
+    fn invalid_releases() {
+        let image = signed_image(1235);
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let res = sign_release(1, &[("a-board-id-that's-too-long", &image)], sk_type);
+        assert!(matches!(res, Err(RbSignerError::InvalidRelease)));
+        // not a signed mcu-image
+        assert!(matches!(
+            image_version(&image[IMAGE_HEADER_SIZE..]),
+            Err(RbSignerError::InvalidRelease)
+        ));
+        assert!(Release::from_toml("sequence = 1\n[[images]]\nboard = \"nrf52840\"").is_err());
+    }
+}
@@ -0,0 +1,488 @@
+//! A signing server i.e. `rbsigner serve <serve.toml>`, so build farms keep their signing keys on
+//! a single host and submit digests to it (ex: from xtask or CI), rather than copying the keys.
+//!
+//! The server is configured in toml, key paths (and the audit log's) are relative to the config's
+//! file
+//!
+//! ```toml
+//! listen = "unix:/run/rbsigner.sock"  # or "tcp:127.0.0.1:7878"
+//! audit_log = "audit.log"
+//!
+//! [keys.release]
+//! path = "keys/release.pem"           # a nistp256 key, in any of `keyfile`'s formats
+//!
+//! [clients.release-ci]
+//! token_sha256 = "9f86d0818..."       # the hex SHA-256 of the client's bearer token
+//!
+//! [policies.production]
+//! keys = ["release"]
+//! clients = ["release-ci"]
+//! ```
+//!
+//! A digest is signed with `POST /sign`, authenticated by a client's token and with a
+//! form-encoded body naming the key, the policy and the (hex) SHA-256 digest to sign
+//!
+//! ```text
+//! curl --unix-socket /run/rbsigner.sock -H "Authorization: Bearer $TOKEN" \
+//!     -d "key=release&policy=production&digest=<hex>" http://localhost/sign
+//! ```
+//!
+//! A request is only served if its policy lists both its key and its client. The response is the
+//! hex signature (i.e. `r || s`, as stored in an image's header, see
+//! [`rbsigner::sign::sign_digest`]).
+//!
+//! Every request that names a key and a policy is appended to the audit log, whether it's signed
+//! or denied. A signature isn't returned unless its audit entry was written.
+//!
+//! *Note: tokens aren't encrypted, a `tcp` server should only listen on localhost or behind a TLS
+//! proxy.*
+
+use crate::curve::{RbSignerError, Result, SigningKeyType};
+use crate::keyfile::load_signing_key;
+use rbsigner::sign::sign_digest;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The longest request line or header.
+const MAX_LINE: u64 = 1024;
+/// The largest request body.
+const MAX_BODY: usize = 1024;
+/// How long a client may take to send its request.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+pub struct ServeConfig {
+    /// `unix:<socket path>` or `tcp:<address>:<port>`
+    pub listen: String,
+    /// the audit log's path, entries are appended
+    pub audit_log: PathBuf,
+    pub keys: BTreeMap<String, KeyConfig>,
+    pub clients: BTreeMap<String, ClientConfig>,
+    pub policies: BTreeMap<String, Policy>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeyConfig {
+    /// path to the signing key, see `keyfile`
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientConfig {
+    /// the hex SHA-256 of the client's bearer token
+    pub token_sha256: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Policy {
+    /// the keys the policy signs with
+    pub keys: Vec<String>,
+    /// the clients the policy applies to
+    pub clients: Vec<String>,
+}
+
+impl ServeConfig {
+    pub fn from_toml(config: &str) -> Result<Self> {
+        let config: ServeConfig =
+            toml::from_str(config).map_err(|_| RbSignerError::InvalidServeConfig)?;
+        let known = config.policies.values().all(|policy| {
+            policy.keys.iter().all(|key| config.keys.contains_key(key))
+                && policy
+                    .clients
+                    .iter()
+                    .all(|client| config.clients.contains_key(client))
+        });
+        let tokens = config
+            .clients
+            .values()
+            .all(|client| token_digest(&client.token_sha256).is_some());
+        match known && tokens {
+            true => Ok(config),
+            false => Err(RbSignerError::InvalidServeConfig),
+        }
+    }
+}
+
+/// A response's status and body.
+type Response = (u16, String);
+
+pub struct Server<W: Write> {
+    config: ServeConfig,
+    keys: BTreeMap<String, SigningKeyType>,
+    audit_log: W,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(config: ServeConfig, keys: BTreeMap<String, SigningKeyType>, audit_log: W) -> Self {
+        Server {
+            config,
+            keys,
+            audit_log,
+        }
+    }
+
+    /// Serves a single request, read from `stream`, and writes the response to it.
+    fn respond(&mut self, mut stream: impl Read + Write) {
+        let (status, body) = self.handle(&mut BufReader::new(&mut stream));
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            _ => "Internal Server Error",
+        };
+        // the client may be gone, there's nobody to report the error to
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        );
+    }
+
+    /// Parses and serves a request.
+    fn handle(&mut self, request: &mut impl BufRead) -> Response {
+        let request = match Request::read(request) {
+            Some(request) => request,
+            None => return (400, "malformed request\n".into()),
+        };
+        if (request.method.as_str(), request.path.as_str()) != ("POST", "/sign") {
+            return (404, "only `POST /sign` is served\n".into());
+        }
+        let form = Form::parse(&request.body);
+        let (key, policy, digest) = match (form.get("key"), form.get("policy"), form.get("digest"))
+        {
+            // they're written to the audit log as is
+            (Some(key), Some(policy), Some(digest))
+                if [key, policy, digest]
+                    .iter()
+                    .all(|value| value.chars().all(|c| c.is_ascii_graphic())) =>
+            {
+                (key, policy, digest)
+            }
+            _ => {
+                return (
+                    400,
+                    "a request names a `key`, a `policy` and a `digest`\n".into(),
+                )
+            }
+        };
+        let client = request
+            .token
+            .as_deref()
+            .and_then(|token| self.client(token));
+
+        let result = match (client, from_hex(digest).and_then(|d| d.try_into().ok())) {
+            (None, _) => Err((401, "unknown or missing token")),
+            (_, None) => Err((400, "a digest is 32 hex-encoded bytes")),
+            (Some(client), Some(digest)) => self.sign(client, key, policy, &digest),
+        };
+        let outcome = match &result {
+            Ok(signature) => format!("signed signature={signature}"),
+            Err((_, reason)) => format!("denied ({reason})"),
+        };
+        let entry = format!(
+            "{} client={} key={key} policy={policy} digest={digest} {outcome}\n",
+            unix_time(),
+            client.unwrap_or("-"),
+        );
+        let audited = self
+            .audit_log
+            .write_all(entry.as_bytes())
+            .and_then(|_| self.audit_log.flush());
+        match (result, audited) {
+            (_, Err(_)) => (500, "can't write the audit log\n".into()),
+            (Ok(signature), Ok(_)) => (200, signature + "\n"),
+            (Err((status, reason)), Ok(_)) => (status, format!("{reason}\n")),
+        }
+    }
+
+    /// Returns the name of the client with `token`.
+    fn client(&self, token: &str) -> Option<&str> {
+        // tokens' digests are compared i.e. a comparison's timing doesn't depend on the token
+        let digest = Sha256::digest(token.as_bytes());
+        self.config
+            .clients
+            .iter()
+            .find(|(_, client)| token_digest(&client.token_sha256).as_deref() == Some(&digest[..]))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Signs `digest` with `key`, if `policy` allows `client` to use it. Returns the hex
+    /// signature.
+    fn sign(
+        &self,
+        client: &str,
+        key: &str,
+        policy: &str,
+        digest: &[u8; 32],
+    ) -> core::result::Result<String, (u16, &'static str)> {
+        let policy = self
+            .config
+            .policies
+            .get(policy)
+            .ok_or((403, "unknown policy"))?;
+        if !policy.clients.iter().any(|name| name == client) {
+            return Err((403, "the policy doesn't apply to the client"));
+        }
+        if !policy.keys.iter().any(|name| name == key) {
+            return Err((403, "the policy doesn't sign with the key"));
+        }
+        let sk = self.keys.get(key).ok_or((403, "unknown key"))?;
+        sign_digest(digest, sk)
+            .map(|signature| to_hex(&signature))
+            .map_err(|_| (500, "signing failed"))
+    }
+}
+
+/// `serve <serve.toml>` - serves signing requests until killed.
+pub fn serve(config_path: &Path) {
+    let config = fs::read_to_string(config_path).expect("Need path to the server's config");
+    let config = match ServeConfig::from_toml(&config) {
+        Ok(config) => config,
+        Err(e) => panic!("error: {:?}", e),
+    };
+    let dir = config_path.parent().unwrap_or_else(|| Path::new("."));
+    let keys = config
+        .keys
+        .iter()
+        .map(|(name, key)| {
+            let path = dir.join(&key.path);
+            (name.clone(), load_signing_key(&path.to_string_lossy()))
+        })
+        .collect();
+    let audit_log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(&config.audit_log))
+        .expect("can't open the audit log");
+    let listen = config.listen.clone();
+    let mut server = Server::new(config, keys, audit_log);
+
+    println!("\nSigning server:   {}", listen);
+    println!("Keys:             {}", server.keys.len());
+    println!("Policies:         {}", server.config.policies.len());
+    match listen.split_once(':') {
+        Some(("unix", path)) => {
+            // a previous server's socket
+            let _ = fs::remove_file(path);
+            let listener = UnixListener::bind(path).expect("can't bind the unix socket");
+            for stream in listener.incoming().flatten() {
+                if stream.set_read_timeout(Some(TIMEOUT)).is_ok() {
+                    server.respond(stream);
+                }
+            }
+        }
+        Some(("tcp", addr)) => {
+            let listener = TcpListener::bind(addr).expect("can't bind the tcp address");
+            for stream in listener.incoming().flatten() {
+                if stream.set_read_timeout(Some(TIMEOUT)).is_ok() {
+                    server.respond(stream);
+                }
+            }
+        }
+        _ => panic!("`listen` is either `unix:<socket path>` or `tcp:<address>:<port>`"),
+    }
+}
+
+/// An HTTP request, only what the server needs of it.
+struct Request {
+    method: String,
+    path: String,
+    /// the `Authorization: Bearer` token
+    token: Option<String>,
+    body: String,
+}
+
+impl Request {
+    fn read(stream: &mut impl BufRead) -> Option<Self> {
+        let line = read_line(stream)?;
+        let mut parts = line.split_whitespace();
+        let (method, path) = (parts.next()?.to_owned(), parts.next()?.to_owned());
+        let (mut token, mut len) = (None, 0);
+        loop {
+            let header = read_line(stream)?;
+            if header.is_empty() {
+                break;
+            }
+            let (name, value) = header.split_once(':')?;
+            match name.trim().to_ascii_lowercase().as_str() {
+                "authorization" => token = value.trim().strip_prefix("Bearer ").map(str::to_owned),
+                "content-length" => len = value.trim().parse().ok()?,
+                _ => {}
+            }
+        }
+        if len > MAX_BODY {
+            return None;
+        }
+        let mut body = vec![0u8; len];
+        stream.read_exact(&mut body).ok()?;
+        Some(Request {
+            method,
+            path,
+            token,
+            body: String::from_utf8(body).ok()?,
+        })
+    }
+}
+
+/// Reads a line of at most [`MAX_LINE`] bytes, without its line ending.
+fn read_line(stream: &mut impl BufRead) -> Option<String> {
+    let mut line = String::new();
+    stream.take(MAX_LINE).read_line(&mut line).ok()?;
+    match line.ends_with('\n') {
+        true => Some(line.trim_end().to_owned()),
+        false => None,
+    }
+}
+
+/// A form-encoded body i.e. `name=value` pairs joined by `&`. Values aren't percent-decoded, as
+/// names and hex values don't need it.
+struct Form<'a>(Vec<(&'a str, &'a str)>);
+
+impl<'a> Form<'a> {
+    fn parse(body: &'a str) -> Self {
+        Form(
+            body.trim()
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .collect(),
+        )
+    }
+
+    fn get(&self, name: &str) -> Option<&'a str> {
+        self.0
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Returns a client's token digest, given as hex.
+fn token_digest(hex: &str) -> Option<Vec<u8>> {
+    from_hex(hex).filter(|digest| digest.len() == 32)
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).ok())
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{import_signing_key, CurveType};
+
+    /// The signing key matching rustBoot's embedded public key.
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    fn config(policy_keys: &str) -> String {
+        let token = to_hex(&Sha256::digest(b"ci-token"));
+        let other = to_hex(&Sha256::digest(b"dev-token"));
+        format!(
+            "listen = \"unix:rbsigner.sock\"\naudit_log = \"audit.log\"\n\
+             [keys.release]\npath = \"release.der\"\n\
+             [clients.ci]\ntoken_sha256 = \"{token}\"\n\
+             [clients.dev]\ntoken_sha256 = \"{other}\"\n\
+             [policies.production]\nkeys = {policy_keys}\nclients = [\"ci\"]\n"
+        )
+    }
+
+    fn server() -> Server<Vec<u8>> {
+        let config = ServeConfig::from_toml(&config("[\"release\"]")).unwrap();
+        let sk = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let keys = BTreeMap::from([("release".to_owned(), sk)]);
+        Server::new(config, keys, Vec::new())
+    }
+
+    fn request(server: &mut Server<Vec<u8>>, token: &str, body: &str) -> Response {
+        let request = format!(
+            "POST /sign HTTP/1.1\r\nAuthorization: Bearer {token}\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        server.handle(&mut request.as_bytes())
+    }
+
+    #[test]
+    fn signs_digests() {
+        let mut server = server();
+        let digest = [0x42u8; 32];
+        let body = format!("key=release&policy=production&digest={}", to_hex(&digest));
+        let (status, signature) = request(&mut server, "ci-token", &body);
+        assert_eq!(status, 200);
+        let sk = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        assert_eq!(
+            signature.trim(),
+            to_hex(&sign_digest(&digest, &sk).unwrap())
+        );
+
+        let audit = String::from_utf8(server.audit_log.clone()).unwrap();
+        assert!(audit.contains("client=ci key=release policy=production"));
+        assert!(audit.contains(&format!("signed signature={}", signature.trim())));
+    }
+
+    #[test]
+    fn denies_requests() {
+        let mut server = server();
+        let body = format!("key=release&policy=production&digest={}", to_hex(&[1; 32]));
+        assert_eq!(request(&mut server, "wrong-token", &body).0, 401);
+        // a known client the policy doesn't apply to
+        assert_eq!(request(&mut server, "dev-token", &body).0, 403);
+        let other_policy = body.replace("production", "development");
+        assert_eq!(request(&mut server, "ci-token", &other_policy).0, 403);
+        let short_digest = body.replace(&to_hex(&[1; 32]), &to_hex(&[1; 31]));
+        assert_eq!(request(&mut server, "ci-token", &short_digest).0, 400);
+        assert_eq!(request(&mut server, "ci-token", "key=release").0, 400);
+        let forged_entry = body.replace("policy=production", "policy=production\ndenied");
+        assert_eq!(request(&mut server, "ci-token", &forged_entry).0, 400);
+        assert_eq!(
+            server.handle(&mut &b"GET /keys HTTP/1.1\r\n\r\n"[..]).0,
+            404
+        );
+        assert_eq!(server.handle(&mut &b"POST /sign HTTP/1.1\r\n"[..]).0, 400);
+
+        // every denial that names a key and a policy is audited
+        let audit = String::from_utf8(server.audit_log.clone()).unwrap();
+        assert_eq!(audit.lines().count(), 4);
+        assert!(audit.lines().all(|entry| entry.contains(" denied (")));
+        assert!(audit.lines().next().unwrap().contains("client=- "));
+    }
+
+    #[test]
+    fn invalid_configs() {
+        // a policy with an unknown key
+        assert!(ServeConfig::from_toml(&config("[\"debug\"]")).is_err());
+        let bad_token = config("[\"release\"]").replace("token_sha256 = \"", "token_sha256 = \"00");
+        assert!(ServeConfig::from_toml(&bad_token).is_err());
+        assert!(ServeConfig::from_toml("listen = \"tcp:127.0.0.1:7878\"").is_err());
+    }
+}
@@ -0,0 +1,441 @@
+//! Alloc-free mcu-image signing.
+//!
+//! The firmware is hashed as it's read from a [`Read`] source (a slice, a file or a flash
+//! device), one fixed-size chunk at a time, and the image header is assembled in a 256-byte
+//! buffer. Prepending the header to the firmware is up to the caller.
+
+#[cfg(feature = "nistp256")]
+use ecdsa::hazmat::{rfc6979_generate_k, SignPrimitive};
+#[cfg(feature = "nistp256")]
+use p256::{elliptic_curve::ops::Reduce, FieldBytes, NistP256, NonZeroScalar, Scalar, U256};
+use rustBoot::crc::Crc32;
+use rustBoot::parser::VendorTlv;
+use rustBoot::rbconstants::*;
+#[cfg(feature = "nistp256")]
+use sha2::Sha256;
+use signature::digest::Digest;
+#[cfg(feature = "nistp256")]
+use signature::DigestSigner;
+
+use crate::curve::*;
+use crate::header::{field::*, McuImageHeader};
+
+/// The size of the chunks the firmware is read (and hashed) in.
+const CHUNK_SIZE: usize = 512;
+
+/// A source of firmware bytes.
+///
+/// With the `std` feature, every [`std::io::Read`] is a source. Otherwise, byte slices are.
+pub trait Read {
+    type Error;
+
+    /// Reads up to `buf.len()` bytes into `buf`, returning the number of bytes read. `0` means
+    /// the source is exhausted.
+    fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error>;
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> Read for R {
+    type Error = std::io::Error;
+
+    fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+        std::io::Read::read(self, buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Read for &[u8] {
+    type Error = core::convert::Infallible;
+
+    fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+        let len = buf.len().min(self.len());
+        let (head, tail) = self.split_at(len);
+        buf[..len].copy_from_slice(head);
+        *self = tail;
+        Ok(len)
+    }
+}
+
+/// Returns a signed mcu-image's 256-byte header, given the firmware (`fw_size` bytes read from
+/// `fw`), its version, timestamp (in unix seconds), image id and vendor TLVs, and a signing key.
+/// Only supports `elliptic curve crypto`.
+///
+/// The firmware must hold at least `fw_size` bytes, only the first `fw_size` are signed. Vendor
+/// TLVs are signed along with the firmware.
+pub fn mcu_image_header(
+    fw: impl Read,
+    fw_size: u32,
+    version: [u8; 4],
+    timestamp: i64,
+    image_id: u8,
+    vendor_tlvs: &[VendorTlv],
+    sk_type: &SigningKeyType,
+) -> Result<[u8; IMAGE_HEADER_SIZE]> {
+    match sk_type {
+        #[cfg(feature = "nistp256")]
+        SigningKeyType::NistP256(sk) => {
+            let (mut header, prehashed_digest) = construct_img_header::<Sha256, 32>(
+                fw,
+                fw_size,
+                version,
+                timestamp,
+                image_id,
+                vendor_tlvs,
+            )?;
+            let derived_pk = sk.verifying_key().to_encoded_point(false);
+            let mut tag_len = [0u8; 4]; // tag and len each take up 2 bytes.
+
+            // set pubkey digest type, len and value
+            let pubkey_digest = Sha256::digest(&derived_pk.as_bytes()[1..]);
+            let hdr_pubkey_digest_len = (PUBKEY_DIGEST_SIZE as u16).to_be_bytes();
+            let pubkey_digest_tag = Tags::PubkeyDigest.get_id();
+            let pubkey_digest_len = hdr_pubkey_digest_len.as_ref();
+            pubkey_digest_tag
+                .iter()
+                .chain(pubkey_digest_len.iter())
+                .enumerate()
+                .for_each(|(idx, byte)| {
+                    tag_len[idx] = *byte;
+                });
+            header.set_pubkey_tag_len(u32::from_be_bytes(tag_len));
+            header.set_pubkey_digest_value(pubkey_digest.as_slice())?;
+
+            // set signature type, len and value
+            let signature = sk
+                .try_sign_digest(prehashed_digest)
+                .map_err(|v| RbSignerError::SignatureError(v))?;
+            let hdr_signature_len = (ECC_SIGNATURE_SIZE as u16).to_be_bytes();
+            let signature_tag = Tags::Signature.get_id();
+            let signature_len = hdr_signature_len.as_ref();
+            signature_tag
+                .iter()
+                .chain(signature_len.iter())
+                .enumerate()
+                .for_each(|(idx, byte)| {
+                    tag_len[idx] = *byte;
+                });
+            header.set_signature_tag_len(u32::from_be_bytes(tag_len));
+            header.set_signatue_value(signature.as_ref())?;
+            Ok(*header.inner_ref())
+        }
+        #[cfg(feature = "ed25519")]
+        SigningKeyType::Ed25519 => {
+            todo!()
+        }
+        _ => return Err(RbSignerError::InvalidKeyType),
+    }
+}
+
+/// Signs a SHA-256 `digest` (ex: a signed image's, computed by a client of `rbsigner serve`)
+/// i.e. returns the same signature as signing the digest's message, with [`mcu_image_header`].
+pub fn sign_digest(
+    digest: &[u8; 32],
+    sk_type: &SigningKeyType,
+) -> Result<[u8; ECC_SIGNATURE_SIZE]> {
+    match sk_type {
+        #[cfg(feature = "nistp256")]
+        SigningKeyType::NistP256(sk) => {
+            let x = NonZeroScalar::from_repr(sk.to_bytes());
+            let x = Option::<NonZeroScalar>::from(x).ok_or(RbSignerError::InvalidKeyType)?;
+            let z = <Scalar as Reduce<U256>>::from_be_bytes_reduced(FieldBytes::from(*digest));
+            // deterministic i.e. RFC6979 nonces, as `DigestSigner` uses
+            let k = rfc6979_generate_k::<NistP256, Sha256>(&x, &z, &[]);
+            let (signature, _) = x
+                .try_sign_prehashed(**k, z)
+                .map_err(RbSignerError::SignatureError)?;
+            let mut bytes = [0u8; ECC_SIGNATURE_SIZE];
+            bytes.copy_from_slice(signature.as_ref());
+            Ok(bytes)
+        }
+        _ => Err(RbSignerError::InvalidKeyType),
+    }
+}
+
+fn construct_img_header<D, const H: usize>(
+    mut fw: impl Read,
+    fw_size: u32,
+    version: [u8; 4],
+    timestamp: i64,
+    image_id: u8,
+    vendor_tlvs: &[VendorTlv],
+) -> Result<(McuImageHeader<[u8; 256]>, D)>
+where
+    D: Digest + Clone,
+{
+    // Construct an McuImageHeader
+    let mut header = McuImageHeader::new_checked([0; 256])?;
+    let mut tag_len = [0u8; 4]; // tag and len each take up 2 bytes.
+
+    // set magic value and firmware size
+    header.set_magic();
+    header.set_image_size(fw_size);
+
+    // set version type, len and value
+    let hdr_version_len = (HDR_VERSION_LEN as u16).to_be_bytes();
+    let version_tag = Tags::Version.get_id();
+    let version_len = hdr_version_len.as_ref();
+    version_tag
+        .iter()
+        .chain(version_len.iter())
+        .enumerate()
+        .for_each(|(idx, byte)| {
+            tag_len[idx] = *byte;
+        });
+    header.set_version_tag_len(u32::from_be_bytes(tag_len));
+    header.set_version_value(&version)?;
+
+    // set timestamp type, len and value
+    let hdr_timestamp_len = (HDR_TIMESTAMP_LEN as u16).to_be_bytes();
+    let timestamp_tag = Tags::TimeStamp.get_id();
+    let timestamp_len = hdr_timestamp_len.as_ref();
+    timestamp_tag
+        .iter()
+        .chain(timestamp_len.iter())
+        .enumerate()
+        .for_each(|(idx, byte)| {
+            tag_len[idx] = *byte;
+        });
+    header.set_timestamp_tag_len(u32::from_be_bytes(tag_len));
+    header.set_timestamp_value(&timestamp.to_le_bytes())?;
+
+    // set image type, len and value
+    let hdr_img_tag_len = (HDR_IMG_TYPE_LEN as u16).to_be_bytes();
+    let img_tag = Tags::ImgType.get_id();
+    let img_len = hdr_img_tag_len.as_ref();
+    img_tag
+        .iter()
+        .chain(img_len.iter())
+        .enumerate()
+        .for_each(|(idx, byte)| {
+            tag_len[idx] = *byte;
+        });
+    header.set_image_tag_len(u32::from_be_bytes(tag_len));
+    // the low byte identifies the image (the application or a companion image), the high byte
+    // the signature type i.e. nistp256
+    header.set_image_value(&[image_id, 0x02])?;
+
+    // set vendor TLVs and end of header
+    let vendor_end = header.set_vendor_tlvs(vendor_tlvs)?;
+    header.set_end_of_header(vendor_end);
+
+    let mut hasher = D::new();
+    hasher.update(&header.inner_ref()[..DIGEST_TYPE.start]);
+    // hash the firmware (and compute its crc) as it's read
+    let mut crc = Crc32::new();
+    let mut chunk = [0u8; CHUNK_SIZE];
+    let mut left = fw_size as usize;
+    while left > 0 {
+        let len = left.min(CHUNK_SIZE);
+        let read = fw
+            .read(&mut chunk[..len])
+            .map_err(|_| RbSignerError::ReadError)?;
+        if read == 0 {
+            // the firmware is shorter than `fw_size`
+            return Err(RbSignerError::ReadError);
+        }
+        hasher.update(&chunk[..read]);
+        crc.update(&chunk[..read]);
+        left -= read;
+    }
+    // the vendor TLVs are hashed after the firmware
+    hasher.update(&header.inner_ref()[VENDOR_TLVS.start..vendor_end]);
+    let digest = hasher.clone().finalize();
+
+    match H {
+        32 => {
+            // set digest type, len and value
+            let hdr_digest_len = (SHA256_DIGEST_SIZE as u16).to_le_bytes();
+            let digest_tag = Tags::Digest256.get_id();
+            let digest_len = hdr_digest_len.as_ref();
+            digest_tag
+                .iter()
+                .chain(digest_len.iter())
+                .enumerate()
+                .for_each(|(idx, byte)| {
+                    tag_len[idx] = *byte;
+                });
+            header.set_digest_tag_len(u32::from_be_bytes(tag_len));
+            header.set_sha256_digest_value(digest.as_slice())?;
+        }
+        _ => unimplemented!(),
+    }
+
+    // set crc32 type, len and value
+    let hdr_crc32_len = (HDR_CRC32_LEN as u16).to_be_bytes();
+    let crc32_tag = Tags::Crc32.get_id();
+    let crc32_len = hdr_crc32_len.as_ref();
+    crc32_tag
+        .iter()
+        .chain(crc32_len.iter())
+        .enumerate()
+        .for_each(|(idx, byte)| {
+            tag_len[idx] = *byte;
+        });
+    header.set_crc32_tag_len(u32::from_be_bytes(tag_len));
+    header.set_crc32_value(crc.finalize());
+
+    Ok((header, hasher))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use p256::ecdsa::{signature::DigestVerifier, Signature};
+
+    use super::*;
+
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    /// Reads at most 3 bytes at a time i.e. never fills a chunk.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> std::io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn mcu_image_header_test() {
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let fw = (0..1500u32).map(|i| i as u8).collect::<std::vec::Vec<_>>();
+        let header = mcu_image_header(
+            fw.as_slice(),
+            1500,
+            [1, 0, 0, 0],
+            1663342128,
+            1,
+            &[],
+            &sk_type,
+        )
+        .unwrap();
+
+        assert_eq!(&header[MAGIC], &[0x52, 0x55, 0x53, 0x54]);
+        assert_eq!(&header[IMAGE_SIZE], &1500u32.to_le_bytes());
+        assert_eq!(&header[TIMESTAMP_VALUE], &1663342128i64.to_le_bytes());
+        let mut hasher = Sha256::new();
+        hasher.update(&header[..DIGEST_TYPE.start]);
+        hasher.update(&fw);
+        assert_eq!(&header[SHA256_DIGEST], hasher.clone().finalize().as_slice());
+
+        assert_eq!(
+            &header[CRC32_TYPE.start..CRC32_LEN.end],
+            &[0x30, 0x00, 0x04, 0x00]
+        );
+        assert_eq!(
+            &header[CRC32_VALUE],
+            &rustBoot::crc::crc32(&fw).to_le_bytes()
+        );
+        assert_eq!(
+            rustBoot::crc::stored_crc32(&header),
+            Some(rustBoot::crc::crc32(&fw))
+        );
+
+        let signature = Signature::try_from(&header[SIGNATURE_VALUE]).unwrap();
+        match &sk_type {
+            SigningKeyType::NistP256(sk) => {
+                assert!(sk.verifying_key().verify_digest(hasher, &signature).is_ok())
+            }
+            _ => unreachable!(),
+        }
+
+        // the signature is randomized, everything else is the same however the firmware is read
+        let streamed = mcu_image_header(
+            Trickle(&fw),
+            1500,
+            [1, 0, 0, 0],
+            1663342128,
+            1,
+            &[],
+            &sk_type,
+        )
+        .unwrap();
+        assert_eq!(
+            &streamed[..SIGNATURE_VALUE.start],
+            &header[..SIGNATURE_VALUE.start]
+        );
+    }
+
+    #[test]
+    fn vendor_tlvs_test() {
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let fw = [0x55u8; 200];
+        let tlvs = [
+            VendorTlv {
+                typ: 0x8001,
+                value: b"build-42",
+            },
+            VendorTlv {
+                typ: 0x8002,
+                value: &[0xde, 0xad, 0xbe, 0xef],
+            },
+        ];
+        let header = mcu_image_header(&fw[..], 200, [1, 0, 0, 0], 0, 1, &tlvs, &sk_type).unwrap();
+        assert!(rustBoot::parser::vendor_tlvs(&header).unwrap().eq(tlvs));
+        // the vendor TLVs are signed along with the firmware
+        let image = [&header[..], &fw[..]].concat();
+        rustBoot::chain::SignedImage::parse(&image)
+            .unwrap()
+            .verify()
+            .unwrap();
+
+        let too_large = [VendorTlv {
+            typ: 0x8001,
+            value: &[0; 59],
+        }];
+        let res = mcu_image_header(&fw[..], 200, [1, 0, 0, 0], 0, 1, &too_large, &sk_type);
+        assert!(matches!(res, Err(RbSignerError::VendorTlvsTooLarge(63))));
+        let not_vendor = [VendorTlv {
+            typ: 0x0030,
+            value: &[],
+        }];
+        let res = mcu_image_header(&fw[..], 200, [1, 0, 0, 0], 0, 1, &not_vendor, &sk_type);
+        assert!(matches!(res, Err(RbSignerError::InvalidVendorTlv(0x0030))));
+    }
+
+    #[test]
+    fn board_tlv_test() {
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let fw = [0x55u8; 200];
+        let board_id = rustBoot::parser::board_id("stm32f446");
+        let tlvs = [VendorTlv {
+            typ: HDR_BOARD_ID,
+            value: &board_id,
+        }];
+        let header = mcu_image_header(&fw[..], 200, [1, 0, 0, 0], 0, 1, &tlvs, &sk_type).unwrap();
+        let tlvs = rustBoot::parser::vendor_tlvs(&header).unwrap();
+        assert_eq!(tlvs.board_id(), Ok(Some(board_id)));
+        assert_ne!(
+            tlvs.board_id(),
+            Ok(Some(rustBoot::parser::board_id("stm32f411")))
+        );
+    }
+
+    #[test]
+    fn sign_digest_test() {
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let fw = [0x5Au8; 300];
+        let header = mcu_image_header(&fw[..], 300, [1, 0, 0, 0], 0, 1, &[], &sk_type).unwrap();
+        // a digest's signature is the image's
+        let digest = header[SHA256_DIGEST].try_into().unwrap();
+        let signature = sign_digest(&digest, &sk_type).unwrap();
+        assert_eq!(&signature[..], &header[SIGNATURE_VALUE]);
+    }
+
+    #[test]
+    fn short_firmware_test() {
+        let sk_type = import_signing_key(CurveType::NistP256, &SK_BYTES).unwrap();
+        let fw = [0xAAu8; 100];
+        let res = mcu_image_header(&fw[..], 101, [1, 0, 0, 0], 0, 1, &[], &sk_type);
+        assert!(matches!(res, Err(RbSignerError::ReadError)));
+        // trailing bytes aren't signed
+        let header = mcu_image_header(&fw[..], 64, [1, 0, 0, 0], 0, 1, &[], &sk_type).unwrap();
+        assert_eq!(&header[IMAGE_SIZE], &64u32.to_le_bytes());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::curve::*;
+use minicbor::data::Tag;
+use minicbor::Encoder;
+use p256::ecdsa::signature::{digest::Digest, DigestSigner};
+use rustBoot::rbconstants::*;
+use rustBoot::suit::{APP_COMPONENT, SUIT_ENVELOPE_TAG};
+use sha2::Sha256;
+
+const COSE_SIGN1_TAG: u64 = 18;
+const COSE_ALG_SHA256: i64 = -16;
+const COSE_ALG_ES256: i64 = -7;
+
+/// Returns a signed mcu-image i.e. the firmware, prefixed with a SUIT envelope (instead of a rustBoot
+/// header). The manifest's sequence number is the image version.
+///
+/// NOTE:
+/// - the envelope is padded to `IMAGE_HEADER_SIZE` bytes, so the firmware's location is the
+///   same as for an image with a rustBoot header.
+///
+pub fn sign_suit_image(
+    mut fw_blob: Vec<u8>,
+    sk_type: SigningKeyType,
+    sequence_number: u32,
+) -> Result<Vec<u8>> {
+    match sk_type {
+        #[cfg(feature = "nistp256")]
+        SigningKeyType::NistP256(sk) => {
+            let manifest = encode_manifest(&fw_blob, sequence_number)?;
+            // the manifest's digest covers the bstr-wrapped manifest
+            let mut wrapped = Vec::new();
+            Encoder::new(&mut wrapped).bytes(&manifest)?;
+            let digest = encode_digest(&Sha256::digest(&wrapped))?;
+
+            // COSE_Sign1 - its (detached) payload is the bstr-wrapped digest
+            let mut protected = Vec::new();
+            Encoder::new(&mut protected)
+                .map(1)
+                .and_then(|e| e.i64(1))
+                .and_then(|e| e.i64(COSE_ALG_ES256))?;
+            let mut sig_structure = Sha256::new();
+            let mut to_be_signed = Vec::new();
+            Encoder::new(&mut to_be_signed)
+                .array(4)
+                .and_then(|e| e.str("Signature1"))
+                .and_then(|e| e.bytes(&protected))
+                .and_then(|e| e.bytes(&[]))
+                .and_then(|e| e.bytes(&digest))?;
+            sig_structure.update(&to_be_signed);
+            let signature = sk
+                .try_sign_digest(sig_structure)
+                .map_err(|v| RbSignerError::SignatureError(v))?;
+            println!("Signing the manifest...");
+            println!("Done.");
+            let mut sign1 = Vec::new();
+            Encoder::new(&mut sign1)
+                .tag(Tag::Unassigned(COSE_SIGN1_TAG))
+                .and_then(|e| e.array(4))
+                .and_then(|e| e.bytes(&protected))
+                .and_then(|e| e.map(0))
+                .and_then(|e| e.null())
+                .and_then(|e| e.bytes(signature.as_ref()))?;
+
+            let mut authentication = Vec::new();
+            Encoder::new(&mut authentication)
+                .array(2)
+                .and_then(|e| e.bytes(&digest))
+                .and_then(|e| e.bytes(&sign1))?;
+            let mut envelope = Vec::new();
+            Encoder::new(&mut envelope)
+                .tag(Tag::Unassigned(SUIT_ENVELOPE_TAG))
+                .and_then(|e| e.map(2))
+                .and_then(|e| e.u8(2))
+                .and_then(|e| e.bytes(&authentication))
+                .and_then(|e| e.u8(3))
+                .and_then(|e| e.bytes(&manifest))?;
+            if envelope.len() > IMAGE_HEADER_SIZE {
+                return Err(RbSignerError::EnvelopeTooLarge(envelope.len()));
+            }
+            envelope.resize(IMAGE_HEADER_SIZE, 0xFF);
+            // prepend envelope and return fw_blob
+            fw_blob.splice(0..0, envelope);
+            Ok(fw_blob)
+        }
+        SigningKeyType::Ed25519 => Err(RbSignerError::UnsupportedKey("ed25519")),
+        _ => return Err(RbSignerError::InvalidKeyType),
+    }
+}
+
+/// Encodes a `SUIT_Manifest` for an application image.
+fn encode_manifest(fw_blob: &[u8], sequence_number: u32) -> Result<Vec<u8>> {
+    let image_digest = encode_digest(&Sha256::digest(fw_blob))?;
+    // shared sequence - override the image digest and size, then check the image.
+    let mut shared_sequence = Vec::new();
+    Encoder::new(&mut shared_sequence)
+        .array(4)
+        .and_then(|e| e.u8(20)) // directive-override-parameters
+        .and_then(|e| e.map(2))
+        .and_then(|e| e.u8(3)) // image-digest
+        .and_then(|e| e.bytes(&image_digest))
+        .and_then(|e| e.u8(14)) // image-size
+        .and_then(|e| e.u64(fw_blob.len() as u64))
+        .and_then(|e| e.u8(3)) // condition-image-match
+        .and_then(|e| e.u8(15))?;
+    let mut common = Vec::new();
+    let mut e = Encoder::new(&mut common);
+    e.map(2)
+        .and_then(|e| e.u8(2)) // components
+        .and_then(|e| e.array(1))
+        .and_then(|e| e.array(APP_COMPONENT.len() as u64))?;
+    for id in APP_COMPONENT {
+        e.bytes(id)?;
+    }
+    e.u8(4) // shared-sequence
+        .and_then(|e| e.bytes(&shared_sequence))?;
+    let mut manifest = Vec::new();
+    Encoder::new(&mut manifest)
+        .map(3)
+        .and_then(|e| e.u8(1)) // manifest-version
+        .and_then(|e| e.u8(1))
+        .and_then(|e| e.u8(2)) // sequence-number
+        .and_then(|e| e.u32(sequence_number))
+        .and_then(|e| e.u8(3)) // common
+        .and_then(|e| e.bytes(&common))?;
+    Ok(manifest)
+}
+
+/// Encodes a (SHA-256) `SUIT_Digest`.
+fn encode_digest(digest: &[u8]) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    Encoder::new(&mut encoded)
+        .array(2)
+        .and_then(|e| e.i64(COSE_ALG_SHA256))
+        .and_then(|e| e.bytes(digest))?;
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use rustBoot::suit::SuitEnvelope;
+
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let key_file = include_bytes!("../../boards/sign_images/keygen/ecc256.der");
+        let sk = import_signing_key(CurveType::NistP256, &key_file[0x40..]).unwrap();
+        let firmware = vec![0xaa; 1000];
+        let image = sign_suit_image(firmware.clone(), sk, 3).unwrap();
+        assert_eq!(&image[IMAGE_HEADER_SIZE..], firmware.as_slice());
+
+        let envelope = SuitEnvelope::parse(&image[..IMAGE_HEADER_SIZE]).unwrap();
+        let manifest = envelope.verify().unwrap();
+        assert_eq!(manifest.get_firmware_version().unwrap(), 3);
+        assert!(manifest.has_component(APP_COMPONENT));
+        manifest.verify_payload(&firmware).unwrap();
+    }
+
+    #[test]
+    fn unsupported_key_type() {
+        assert!(matches!(
+            sign_suit_image(vec![0xaa; 16], SigningKeyType::Ed25519, 3),
+            Err(RbSignerError::UnsupportedKey("ed25519"))
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+//! Signing certificates i.e. a two-level chain of trust, from the public key embedded in the
+//! bootloader (the root key) to the key an image is signed with.
+//!
+//! A signing certificate carries a (nistp256) public key, the period it may sign images for and
+//! its role, signed with the root key. An image signed with a certified key carries its
+//! certificate, appended to its firmware and announced by its signing-certificate TLV (see
+//! [`HDR_SIGNING_CERT`](crate::rbconstants::HDR_SIGNING_CERT)). The certificate is checked with
+//! the root key and the image's signature with the certified key. Images without a certificate
+//! are signed with the root key, as before.
+//!
+//! So daily builds can be signed with a development key, which production bootloaders (i.e.
+//! built with `production-certs`) refuse, and signing keys can be replaced without reflashing
+//! any bootloader.
+//!
+//! Certificates are produced by `rbsigner cert`. All fields are little-endian
+//!
+//! ```text
+//! +---------+---------+------------+-----------+------------+-----------+
+//! | magic   | role    | not before | not after | public key | signature |
+//! | (RBSC)  |         |            |           | (x, y)     |           |
+//! | 4 bytes | 4 bytes | 8 bytes    | 8 bytes   | 64 bytes   | 64 bytes  |
+//! +---------+---------+------------+-----------+------------+-----------+
+//! ```
+//!
+//! - the role is `1` for a production and `2` for a development certificate.
+//! - the validity is in seconds since the unix epoch. Bootloaders generally don't know the time,
+//!   so it's checked against the image's (signed) timestamp i.e. a leaked key can still sign
+//!   images dated within its validity.
+//! - the signature is the root key's nistp256 signature over everything before it.
+//!
+//! *Note: the certificate is part of the image's firmware (i.e. it's counted in the header's
+//! `size` field and covered by the image's digest), so the image's layout doesn't change.*
+
+use core::convert::TryInto;
+use core::ops::Add;
+
+use crate::crypto::signatures::{verify_ecc256_signature, NistP256Signature, HDR_IMG_TYPE_AUTH};
+use crate::parser::{parse_header_tlv, vendor_tlvs, Tags};
+use crate::rbconstants::{ECC_SIGNATURE_SIZE, SIGNING_CERT_SIZE};
+use crate::{Result, RustbootError};
+
+use p256::ecdsa::signature::digest::Digest;
+use p256::ecdsa::VerifyingKey;
+use p256::elliptic_curve::consts::U32;
+use p256::elliptic_curve::{generic_array::GenericArray, FieldSize};
+use p256::{EncodedPoint, NistP256};
+use sha2::Sha256;
+
+pub const CERT_MAGIC: u32 = 0x43534252; // RBSC
+/// The number of bytes the root key signs i.e. everything but the signature.
+pub const CERT_SIGNED_LEN: usize = SIGNING_CERT_SIZE - ECC_SIGNATURE_SIZE;
+
+/// What a certified key may be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertRole {
+    /// release builds, accepted by all bootloaders.
+    Production,
+    /// development (ex: daily) builds, refused by `production-certs` bootloaders.
+    Development,
+}
+
+impl CertRole {
+    pub fn from_u32(role: u32) -> Option<Self> {
+        match role {
+            1 => Some(CertRole::Production),
+            2 => Some(CertRole::Development),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(self) -> u32 {
+        match self {
+            CertRole::Production => 1,
+            CertRole::Development => 2,
+        }
+    }
+}
+
+/// A signing certificate, see the [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct SigningCert<'a> {
+    bytes: &'a [u8],
+}
+
+// a certificate's length is checked by `parse`, so its fields are always in bounds
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    bytes
+        .get(offset..offset + 4)
+        .and_then(|field| field.try_into().ok())
+        .map_or(0, u32::from_le_bytes)
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    bytes
+        .get(offset..offset + 8)
+        .and_then(|field| field.try_into().ok())
+        .map_or(0, u64::from_le_bytes)
+}
+
+impl<'a> SigningCert<'a> {
+    /// Parses a signing certificate. Only its magic and role are checked, see [`Self::verify`].
+    /// Returns [`RustbootError::InvalidImage`] if it's malformed.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() != SIGNING_CERT_SIZE
+            || u32_at(bytes, 0) != CERT_MAGIC
+            || CertRole::from_u32(u32_at(bytes, 4)).is_none()
+        {
+            return Err(RustbootError::InvalidImage);
+        }
+        Ok(SigningCert { bytes })
+    }
+
+    /// Returns the certificate appended to an image's firmware, if its header announces one.
+    pub fn in_image(header: &[u8], firmware: &'a [u8]) -> Result<Option<Self>> {
+        if !vendor_tlvs(header)?.has_signing_cert()? {
+            return Ok(None);
+        }
+        let start = firmware
+            .len()
+            .checked_sub(SIGNING_CERT_SIZE)
+            .ok_or(RustbootError::InvalidFirmwareSize)?;
+        let cert = firmware
+            .get(start..)
+            .ok_or(RustbootError::InvalidFirmwareSize)?;
+        SigningCert::parse(cert).map(Some)
+    }
+
+    pub fn role(&self) -> CertRole {
+        // checked by `parse`, fall back to the less trusted role all the same
+        CertRole::from_u32(u32_at(self.bytes, 4)).unwrap_or(CertRole::Development)
+    }
+
+    pub fn not_before(&self) -> u64 {
+        u64_at(self.bytes, 8)
+    }
+
+    pub fn not_after(&self) -> u64 {
+        u64_at(self.bytes, 16)
+    }
+
+    /// The certified key i.e. an untagged, uncompressed nistp256 point.
+    pub fn public_key(&self) -> &'a [u8] {
+        self.bytes.get(24..CERT_SIGNED_LEN).unwrap_or_default()
+    }
+
+    /// Checks the certificate's signature, with the embedded (root) public key, and that it may
+    /// sign an image with `timestamp`. Returns
+    ///
+    /// - [`RustbootError::FwAuthFailed`] if the signature doesn't check out.
+    /// - [`RustbootError::CertRefused`] if `timestamp` is outside the certificate's validity or,
+    ///   in a `production-certs` build, if it's a development certificate.
+    pub fn verify(&self, timestamp: u64) -> Result<()> {
+        let (signed, signature) = self.bytes.split_at(CERT_SIGNED_LEN);
+        verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(
+            Sha256::new().chain(signed),
+            signature,
+        )?;
+        #[cfg(feature = "production-certs")]
+        if self.role() != CertRole::Production {
+            return Err(RustbootError::CertRefused);
+        }
+        if timestamp < self.not_before() || timestamp > self.not_after() {
+            return Err(RustbootError::CertRefused);
+        }
+        Ok(())
+    }
+
+    /// Verifies a signature over `digest` with the certified key, the same way as
+    /// [`verify_ecc256_signature`] does with the embedded one.
+    pub fn verify_signature<D>(&self, digest: D, signature: &[u8]) -> Result<bool>
+    where
+        D: Digest<OutputSize = U32>,
+    {
+        let untagged_bytes: &GenericArray<u8, <FieldSize<NistP256> as Add>::Output> =
+            GenericArray::from_slice(self.public_key());
+        let verify_key =
+            VerifyingKey::from_encoded_point(&EncodedPoint::from_untagged_bytes(untagged_bytes))
+                .map_err(|_| RustbootError::ECCError)?;
+        match (NistP256Signature { verify_key }).verify(digest, signature)? {
+            true => Ok(true),
+            false => Err(RustbootError::FwAuthFailed),
+        }
+    }
+}
+
+/// Verifies an image's signature over `digest` i.e. with the key certified by its signing
+/// certificate if it has one (once the certificate is checked, see [`SigningCert::verify`]) and
+/// with the embedded public key otherwise. `firmware` is the image's firmware, including the
+/// certificate.
+pub fn verify_image_signature(
+    header: &[u8],
+    firmware: &[u8],
+    digest: Sha256,
+    signature: &[u8],
+) -> Result<bool> {
+    match SigningCert::in_image(header, firmware)? {
+        Some(cert) => {
+            let timestamp = parse_header_tlv(header, Tags::TimeStamp)?
+                .try_into()
+                .map_err(|_| RustbootError::InvalidValue)?;
+            cert.verify(u64::from_le_bytes(timestamp))?;
+            cert.verify_signature(digest, signature)
+        }
+        None => verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(digest, signature),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+    use std::vec::Vec;
+
+    /// The signing key matching the embedded (root) public key.
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+    /// A certified (ex: development) signing key.
+    pub(crate) const CERTIFIED_KEY: [u8; 32] = [0x11; 32];
+
+    /// Returns a certificate for [`CERTIFIED_KEY`], signed with `root`.
+    pub(crate) fn signing_cert_by(root: &[u8; 32], role: u32, validity: (u64, u64)) -> Vec<u8> {
+        let key = SigningKey::from_bytes(&CERTIFIED_KEY).unwrap();
+        let mut cert = Vec::new();
+        cert.extend_from_slice(&CERT_MAGIC.to_le_bytes());
+        cert.extend_from_slice(&role.to_le_bytes());
+        cert.extend_from_slice(&validity.0.to_le_bytes());
+        cert.extend_from_slice(&validity.1.to_le_bytes());
+        cert.extend_from_slice(&key.verifying_key().to_encoded_point(false).as_bytes()[1..]);
+        let root = SigningKey::from_bytes(root).unwrap();
+        let signature: Signature = root.sign_digest(Sha256::new().chain(&cert));
+        cert.extend_from_slice(signature.as_ref());
+        cert
+    }
+
+    /// Returns a certificate for [`CERTIFIED_KEY`], signed with the root key.
+    pub(crate) fn signing_cert(role: u32, validity: (u64, u64)) -> Vec<u8> {
+        signing_cert_by(&SK_BYTES, role, validity)
+    }
+
+    #[test]
+    fn parse_certs() {
+        let cert = signing_cert(2, (1000, 2000));
+        let parsed = SigningCert::parse(&cert).unwrap();
+        assert_eq!(parsed.role(), CertRole::Development);
+        assert_eq!((parsed.not_before(), parsed.not_after()), (1000, 2000));
+        assert_eq!(parsed.public_key().len(), 64);
+
+        assert!(SigningCert::parse(&cert[..SIGNING_CERT_SIZE - 1]).is_err());
+        let mut bad_magic = cert.clone();
+        bad_magic[0] = 0x00;
+        assert!(SigningCert::parse(&bad_magic).is_err());
+        let mut bad_role = cert;
+        bad_role[4] = 3;
+        assert!(SigningCert::parse(&bad_role).is_err());
+    }
+
+    #[test]
+    fn verify_certs() {
+        let cert = signing_cert(1, (1000, 2000));
+        let parsed = SigningCert::parse(&cert).unwrap();
+        parsed.verify(1000).unwrap();
+        parsed.verify(2000).unwrap();
+        assert_eq!(parsed.verify(999), Err(RustbootError::CertRefused));
+        assert_eq!(parsed.verify(2001), Err(RustbootError::CertRefused));
+
+        // only the root key can certify a key
+        let forged = signing_cert_by(&CERTIFIED_KEY, 1, (1000, 2000));
+        assert_eq!(
+            SigningCert::parse(&forged).unwrap().verify(1500),
+            Err(RustbootError::FwAuthFailed)
+        );
+        let mut tampered = cert;
+        tampered[16] = 0xff; // extends its validity
+        assert_eq!(
+            SigningCert::parse(&tampered).unwrap().verify(1500),
+            Err(RustbootError::FwAuthFailed)
+        );
+
+        let cert = signing_cert(2, (1000, 2000));
+        let res = SigningCert::parse(&cert).unwrap().verify(1500);
+        #[cfg(feature = "production-certs")]
+        assert_eq!(res, Err(RustbootError::CertRefused));
+        #[cfg(not(feature = "production-certs"))]
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn certified_signatures() {
+        let cert = signing_cert(1, (1000, 2000));
+        let parsed = SigningCert::parse(&cert).unwrap();
+        let key = SigningKey::from_bytes(&CERTIFIED_KEY).unwrap();
+        let digest = Sha256::new().chain(b"rustBoot image");
+        let signature: Signature = key.sign_digest(digest.clone());
+        assert_eq!(
+            parsed.verify_signature(digest.clone(), signature.as_ref()),
+            Ok(true)
+        );
+        // the root key's signature isn't the certified key's
+        let root = SigningKey::from_bytes(&SK_BYTES).unwrap();
+        let signature: Signature = root.sign_digest(digest.clone());
+        assert_eq!(
+            parsed.verify_signature(digest, signature.as_ref()),
+            Err(RustbootError::FwAuthFailed)
+        );
+    }
+}
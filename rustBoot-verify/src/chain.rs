@@ -0,0 +1,422 @@
+//! Signed images held in memory i.e. a rustBoot header followed by the firmware, as produced by
+//! `rbsigner mcu-image`.
+//!
+//! Unlike the `image` module, nothing here depends on a board's flash layout. Boards that boot
+//! from RAM (ex: rpi4) use this to extend the chain of trust one level earlier - a small first
+//! stage verifies the signed second stage (i.e. rustBoot) before jumping to it.
+
+use core::convert::TryInto;
+
+use crate::crc::check_crc32;
+#[cfg(not(feature = "cert-chain"))]
+use crate::crypto::signatures::verify_ecc256_signature;
+use crate::crypto::signatures::HDR_IMG_TYPE_AUTH;
+use crate::parser::{get_header_tlv_offset, parse_header_tlv, vendor_tlvs, Tags};
+use crate::rbconstants::*;
+use crate::{Result, RustbootError};
+
+use p256::ecdsa::signature::digest::Digest;
+use sha2::Sha256;
+
+/// A rustBoot image i.e. a header and the firmware it describes.
+#[derive(Debug, Clone, Copy)]
+pub struct SignedImage<'a> {
+    header: &'a [u8],
+    firmware: &'a [u8],
+}
+
+impl<'a> SignedImage<'a> {
+    /// Parses the image at the start of `bytes`. Only the header's `magic` and `size` fields are
+    /// checked, see [`Self::verify`].
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let header = bytes
+            .get(..IMAGE_HEADER_SIZE)
+            .ok_or(RustbootError::InvalidImage)?;
+        if !has_magic(header) {
+            return Err(RustbootError::InvalidImage);
+        }
+        let fw_size = header
+            .get(4..8)
+            .and_then(|size| size.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or(RustbootError::InvalidImage)? as usize;
+        let firmware = bytes
+            .get(IMAGE_HEADER_SIZE..)
+            .and_then(|fw| fw.get(..fw_size))
+            .ok_or(RustbootError::InvalidFirmwareSize)?;
+        Ok(SignedImage { header, firmware })
+    }
+
+    /// The image's header.
+    pub fn header(&self) -> &'a [u8] {
+        self.header
+    }
+
+    /// The image's firmware (without its header).
+    pub fn firmware(&self) -> &'a [u8] {
+        self.firmware
+    }
+
+    pub fn get_firmware_version(&self) -> Result<u32> {
+        let val = parse_header_tlv(self.header, Tags::Version)?;
+        let fw_version =
+            u32::from_le_bytes(val.try_into().map_err(|_| RustbootError::InvalidValue)?);
+        Ok(fw_version)
+    }
+
+    pub fn get_image_type(&self) -> Result<u16> {
+        let img_type = parse_header_tlv(self.header, Tags::ImgType)?;
+        Ok(u16::from_le_bytes(
+            img_type
+                .try_into()
+                .map_err(|_| RustbootError::InvalidValue)?,
+        ))
+    }
+
+    /// Verifies the image's integrity and authenticity i.e. its digest and its signature,
+    /// against the embedded public key (or, with `cert-chain`, the key certified by the image's
+    /// signing certificate, see `cert`). If the header holds a CRC, it's checked first (see
+    /// [`crate::crc`]).
+    pub fn verify(&self) -> Result<()> {
+        if (self.get_image_type()? & HDR_MASK_HIGHBYTE) != HDR_IMG_TYPE_AUTH {
+            return Err(RustbootError::InvalidValue);
+        }
+        check_crc32(self.header, self.firmware)?;
+        // the digest covers all header fields preceding the `SHA_TLV` field, the firmware and
+        // the vendor TLVs.
+        let stored_hash = parse_header_tlv(self.header, Tags::Digest256)?;
+        let offset = get_header_tlv_offset(self.header, Tags::Digest256)?;
+        let mut hasher = Sha256::new();
+        hasher.update(
+            self.header
+                .get(..offset)
+                .ok_or(RustbootError::InvalidHdrFieldLength)?,
+        );
+        hasher.update(self.firmware);
+        hasher.update(vendor_tlvs(self.header)?.as_bytes());
+        if hasher.clone().finalize().as_slice() != stored_hash {
+            return Err(RustbootError::IntegrityCheckFailed);
+        }
+        let signature = parse_header_tlv(self.header, Tags::Signature)?;
+        // with a signing certificate, the image is signed by the key it certifies
+        #[cfg(feature = "cert-chain")]
+        let verified =
+            crate::cert::verify_image_signature(self.header, self.firmware, hasher, signature)?;
+        #[cfg(not(feature = "cert-chain"))]
+        let verified = verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(hasher, signature)?;
+        match verified {
+            true => Ok(()),
+            false => Err(RustbootError::FwAuthFailed),
+        }
+    }
+}
+
+/// Picks the image to boot out of several slots (ex: stage0's stage1 slots, see `boards/stage0`)
+/// i.e. the newest of the images that parse, [`SignedImage::verify`] and pass `check` (ex: a
+/// check of the image's vector table against its slot). Returns the slot's index along with its
+/// image, a tie in versions goes to the first slot.
+///
+/// Every slot's image is verified, so an interrupted or tampered update of one slot falls back
+/// to the other.
+pub fn newest_image<'a, F>(slots: &[&'a [u8]], check: F) -> Option<(usize, SignedImage<'a>)>
+where
+    F: Fn(usize, &SignedImage<'a>) -> Result<()>,
+{
+    let mut newest: Option<(usize, SignedImage<'a>, u32)> = None;
+    for (idx, slot) in slots.iter().enumerate() {
+        let image = match SignedImage::parse(slot) {
+            Ok(image) => image,
+            Err(_) => continue,
+        };
+        let version = match image.get_firmware_version() {
+            Ok(version) => version,
+            Err(_) => continue,
+        };
+        if matches!(newest, Some((_, _, newest)) if newest >= version) {
+            continue;
+        }
+        if image.verify().is_ok() && check(idx, &image).is_ok() {
+            newest = Some((idx, image, version));
+        }
+    }
+    newest.map(|(idx, image, _)| (idx, image))
+}
+
+/// Checks for rustBoot's `magic` at the start of `bytes`.
+pub fn has_magic(bytes: &[u8]) -> bool {
+    bytes.get(..4) == Some((RUSTBOOT_MAGIC as u32).to_le_bytes().as_slice())
+}
+
+/// Returns the offset of the first rustBoot header in `bytes`, looking for its `magic` at offsets
+/// `0`, `align`, `2 * align` ... up to (and including) `window`. Some toolchains pad an image to an
+/// alignment, so that its header doesn't start at the very first byte of a partition.
+pub fn find_header(bytes: &[u8], window: usize, align: usize) -> Option<usize> {
+    (0..=window)
+        .step_by(align.max(1))
+        .find(|offset| bytes.get(*offset..).is_some_and(has_magic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+    use std::vec::Vec;
+
+    /// the signing key matching the embedded public key i.e. `boards/sign_images/keygen/ecc256.der`
+    const SIGNING_KEY: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    #[rustfmt::skip]
+    const TLVS: &[u8] = &[
+        0x01, 0x00, 0x04, 0x00, // version type & len
+        0x02, 0x00, 0x00, 0x00, // version value
+        0xff, 0xff, 0xff, 0xff, // padding bytes
+        0x02, 0x00, 0x08, 0x00, // timestamp type & len
+        0x11, 0x11, 0x11, 0x11, // timestamp value
+        0x22, 0x22, 0x22, 0x22,
+        0x04, 0x00, 0x02, 0x00, // img type and len
+        0x01, 0x02,             // img value i.e. app, nistp256
+        0xff, 0xff, 0xff, 0xff, // padding bytes
+        0xff, 0xff,
+        0x03, 0x00, 0x20, 0x00, // digest type and len
+    ];
+
+    /// Returns a signed image for `fw`.
+    fn signed_image(fw: &[u8]) -> Vec<u8> {
+        signed_image_with_vendor_tlvs(fw, &[])
+    }
+
+    /// Returns a signed image for `fw`, with `vendor` TLVs.
+    fn signed_image_with_vendor_tlvs(fw: &[u8], vendor: &[u8]) -> Vec<u8> {
+        signed_image_by(&SIGNING_KEY, fw, vendor)
+    }
+
+    /// Returns an image for `fw`, with `vendor` TLVs, signed with `key`.
+    fn signed_image_by(key: &[u8; 32], fw: &[u8], vendor: &[u8]) -> Vec<u8> {
+        versioned_image_by(key, 2, fw, vendor)
+    }
+
+    /// As [`signed_image_by`], for an image of `version`.
+    fn versioned_image_by(key: &[u8; 32], version: u32, fw: &[u8], vendor: &[u8]) -> Vec<u8> {
+        let mut img = Vec::new();
+        img.extend_from_slice(&(RUSTBOOT_MAGIC as u32).to_le_bytes());
+        img.extend_from_slice(&(fw.len() as u32).to_le_bytes());
+        img.extend_from_slice(TLVS);
+        img[12..16].copy_from_slice(&version.to_le_bytes());
+        let hasher = Sha256::new()
+            .chain(&img[..img.len() - 4])
+            .chain(fw)
+            .chain(vendor);
+        img.extend_from_slice(hasher.clone().finalize().as_slice());
+        img.extend_from_slice(&[0x10, 0x00, 0x20, 0x00]);
+        img.extend_from_slice(&[0x55; 32]);
+        img.extend_from_slice(&[0x20, 0x00, 0x40, 0x00]);
+        let sk = SigningKey::from_bytes(key).unwrap();
+        let signature: Signature = sk.sign_digest(hasher);
+        img.extend_from_slice(signature.as_ref());
+        img.extend_from_slice(&[0x00, 0x00]);
+        img.resize(IMAGE_HEADER_SIZE, 0xff);
+        img[HDR_VENDOR_TLVS..HDR_VENDOR_TLVS + vendor.len()].copy_from_slice(vendor);
+        img.extend_from_slice(fw);
+        img
+    }
+
+    #[test]
+    fn verify_signed_image() {
+        let img = signed_image(&[0xaa; 100]);
+        let parsed = SignedImage::parse(&img).unwrap();
+        assert_eq!(parsed.firmware(), &[0xaa; 100][..]);
+        assert_eq!(parsed.get_firmware_version().unwrap(), 2);
+        assert_eq!(parsed.get_image_type().unwrap(), 0x0201);
+        parsed.verify().unwrap();
+    }
+
+    #[test]
+    fn tampered_image() {
+        let mut img = signed_image(&[0xaa; 100]);
+        img[IMAGE_HEADER_SIZE] = 0x00;
+        assert_eq!(
+            SignedImage::parse(&img).unwrap().verify().unwrap_err(),
+            RustbootError::IntegrityCheckFailed
+        );
+
+        // re-computing the digest doesn't help, the signature no longer matches
+        let mut img = signed_image(&[0xaa; 100]);
+        img[IMAGE_HEADER_SIZE] = 0x00;
+        let offset = 8 + TLVS.len() - 4;
+        let digest = Sha256::new()
+            .chain(&img[..offset])
+            .chain(&img[IMAGE_HEADER_SIZE..])
+            .finalize();
+        img[offset + 4..offset + 36].copy_from_slice(digest.as_slice());
+        assert!(SignedImage::parse(&img).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn vendor_tlvs_are_signed() {
+        let vendor = [0x01, 0x80, 0x02, 0x00, 0x12, 0x34];
+        let img = signed_image_with_vendor_tlvs(&[0xaa; 100], &vendor);
+        SignedImage::parse(&img).unwrap().verify().unwrap();
+        let tlv = vendor_tlvs(&img).unwrap().next().unwrap();
+        assert_eq!((tlv.typ, tlv.value), (0x8001, &[0x12, 0x34][..]));
+
+        // vendor TLVs can't be altered or stripped
+        let mut altered = img.clone();
+        altered[HDR_VENDOR_TLVS + 4] = 0x00;
+        assert_eq!(
+            SignedImage::parse(&altered).unwrap().verify().unwrap_err(),
+            RustbootError::IntegrityCheckFailed
+        );
+        let mut stripped = img;
+        stripped[HDR_VENDOR_TLVS..HDR_VENDOR_TLVS + 2].copy_from_slice(&[0x00, 0x00]);
+        assert_eq!(
+            SignedImage::parse(&stripped).unwrap().verify().unwrap_err(),
+            RustbootError::IntegrityCheckFailed
+        );
+    }
+
+    #[cfg(feature = "cert-chain")]
+    #[test]
+    fn certified_images() {
+        use crate::cert::tests::{signing_cert, CERTIFIED_KEY};
+
+        // the image's timestamp i.e. `0x2222222211111111`
+        let validity = (0x2222222200000000, 0x2222222300000000);
+        let marker = [0xfd, 0xff, 0x02, 0x00, SIGNING_CERT_SIZE as u8, 0x00];
+        let fw = [&[0xaa; 100][..], &signing_cert(1, validity)].concat();
+        let img = signed_image_by(&CERTIFIED_KEY, &fw, &marker);
+        SignedImage::parse(&img).unwrap().verify().unwrap();
+
+        // without the signing-certificate TLV, the image must be signed with the root key
+        let img = signed_image_by(&CERTIFIED_KEY, &fw, &[]);
+        assert_eq!(
+            SignedImage::parse(&img).unwrap().verify().unwrap_err(),
+            RustbootError::FwAuthFailed
+        );
+        // an expired certificate
+        let fw = [&[0xaa; 100][..], &signing_cert(1, (0, 0x2222222200000000))].concat();
+        let img = signed_image_by(&CERTIFIED_KEY, &fw, &marker);
+        assert_eq!(
+            SignedImage::parse(&img).unwrap().verify().unwrap_err(),
+            RustbootError::CertRefused
+        );
+        // no certificate (i.e. a firmware that's too short for one)
+        let img = signed_image_by(&CERTIFIED_KEY, &[0xaa; 100], &marker);
+        assert_eq!(
+            SignedImage::parse(&img).unwrap().verify().unwrap_err(),
+            RustbootError::InvalidFirmwareSize
+        );
+    }
+
+    /// Adds a `CRC32` TLV (after the signature) to a signed image.
+    fn with_crc(mut img: Vec<u8>, crc: u32) -> Vec<u8> {
+        let offset = 8 + TLVS.len() + 32 + 36 + 68;
+        img[offset..offset + 4].copy_from_slice(&[0x30, 0x00, 0x04, 0x00]);
+        img[offset + 4..offset + 8].copy_from_slice(&crc.to_le_bytes());
+        img[offset + 8..offset + 10].copy_from_slice(&[0x00, 0x00]);
+        img
+    }
+
+    #[test]
+    fn crc_precheck() {
+        let fw = [0xaa; 100];
+        let img = with_crc(signed_image(&fw), crate::crc::crc32(&fw));
+        SignedImage::parse(&img).unwrap().verify().unwrap();
+
+        // a corrupted image is rejected before its digest is checked
+        let mut corrupted = img.clone();
+        corrupted[IMAGE_HEADER_SIZE] = 0x00;
+        assert_eq!(
+            SignedImage::parse(&corrupted)
+                .unwrap()
+                .verify()
+                .unwrap_err(),
+            RustbootError::CrcCheckFailed
+        );
+        // the crc isn't signed, fixing it up doesn't help
+        let mut fw = fw;
+        fw[0] = 0x00;
+        let mut forged = with_crc(corrupted, crate::crc::crc32(&fw));
+        assert_eq!(
+            SignedImage::parse(&forged).unwrap().verify().unwrap_err(),
+            RustbootError::IntegrityCheckFailed
+        );
+        forged[IMAGE_HEADER_SIZE] = 0xaa;
+        assert_eq!(
+            SignedImage::parse(&forged).unwrap().verify().unwrap_err(),
+            RustbootError::CrcCheckFailed
+        );
+    }
+
+    #[test]
+    fn malformed_images() {
+        let img = signed_image(&[0xaa; 100]);
+        // truncated firmware
+        assert_eq!(
+            SignedImage::parse(&img[..img.len() - 1]).unwrap_err(),
+            RustbootError::InvalidFirmwareSize
+        );
+        // truncated header
+        assert_eq!(
+            SignedImage::parse(&img[..IMAGE_HEADER_SIZE - 1]).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+        // no magic
+        let mut img = img;
+        img[0] = 0x00;
+        assert_eq!(
+            SignedImage::parse(&img).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+    }
+
+    #[test]
+    fn header_search() {
+        let img = signed_image(&[0xaa; 100]);
+        assert_eq!(find_header(&img, 0, 4), Some(0));
+        assert_eq!(find_header(&img, 0x100, 0x20), Some(0));
+
+        let mut padded = vec![0xff; 0x40];
+        padded.extend_from_slice(&img);
+        assert_eq!(find_header(&padded, 0x100, 0x20), Some(0x40));
+        assert_eq!(find_header(&padded, 0x100, 0x10), Some(0x40));
+        // out of the window or off the alignment steps
+        assert_eq!(find_header(&padded, 0x20, 0x20), None);
+        assert_eq!(find_header(&padded, 0x100, 0x30), None);
+        assert_eq!(find_header(&[0xff; 0x40], 0x100, 0x20), None);
+    }
+
+    #[test]
+    fn newest_of_slots() {
+        let old = versioned_image_by(&SIGNING_KEY, 2, &[0xaa; 100], &[]);
+        let new = versioned_image_by(&SIGNING_KEY, 3, &[0xbb; 100], &[]);
+        let accept = |_: usize, _: &SignedImage| Ok(());
+        let newest = |slots: &[&[u8]]| newest_image(slots, accept).map(|(idx, _)| idx);
+        assert_eq!(newest(&[&old, &new]), Some(1));
+        assert_eq!(newest(&[&new, &old]), Some(0));
+        // a tie goes to the first slot
+        assert_eq!(newest(&[&old, &old]), Some(0));
+
+        // an interrupted (i.e. erased or partly written) or tampered slot is skipped
+        let mut tampered = new.clone();
+        tampered[IMAGE_HEADER_SIZE] = 0x00;
+        let erased = [0xff; IMAGE_HEADER_SIZE + 100];
+        assert_eq!(newest(&[&old, &tampered]), Some(0));
+        assert_eq!(newest(&[&erased, &old]), Some(1));
+        assert_eq!(newest(&[&new[..IMAGE_HEADER_SIZE + 50], &old]), Some(1));
+        assert_eq!(newest(&[&erased, &tampered]), None);
+
+        // as is a slot that `check` refuses (ex: an image linked for the other slot)
+        let only_first = |idx: usize, _: &SignedImage| match idx {
+            0 => Ok(()),
+            _ => Err(RustbootError::InvalidImage),
+        };
+        assert_eq!(
+            newest_image(&[&old, &new], only_first).map(|(idx, img)| (idx, img.firmware()[0])),
+            Some((0, 0xaa))
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! A fast integrity pre-check i.e. a CRC-32 of the firmware, stored in the image-header's `CRC32`
+//! TLV.
+//!
+//! Checking the CRC is much cheaper than hashing the image and checking its signature, so a
+//! corrupted image (ex: bit-rot in external flash) is rejected early and reported as
+//! [`RustbootError::CrcCheckFailed`] rather than as a failed signature. The CRC isn't covered by
+//! the signature - it only detects corruption, the image is still authenticated as before.
+//!
+//! Images signed without a CRC skip the pre-check.
+
+use core::convert::TryInto;
+
+use crate::parser::{parse_header_tlv, Tags};
+use crate::{Result, RustbootError};
+
+/// CRC-32 (IEEE 802.3) i.e. the reflected `0x04C11DB7` polynomial, as used by zlib.
+const POLY: u32 = 0xEDB8_8320;
+
+const TABLE: [u32; 256] = table();
+
+// evaluated at compile time i.e. its indexing can't panic at runtime
+#[allow(clippy::indexing_slicing)]
+const fn table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ POLY,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// A CRC-32, computed one chunk at a time.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Crc32(0xFFFF_FFFF)
+    }
+
+    /// Feeds `data` to the CRC.
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            // the index is masked to a byte i.e. it's always within the table
+            let entry = TABLE.get(((self.0 ^ *byte as u32) & 0xFF) as usize);
+            self.0 = entry.copied().unwrap_or_default() ^ (self.0 >> 8);
+        }
+    }
+
+    /// Returns the CRC of all data fed so far.
+    pub fn finalize(self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the CRC-32 of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finalize()
+}
+
+/// Returns the firmware's CRC, as stored in `header`. `None` if the image was signed without one.
+pub fn stored_crc32(header: &[u8]) -> Option<u32> {
+    let val = parse_header_tlv(header, Tags::Crc32).ok()?;
+    Some(u32::from_le_bytes(val.try_into().ok()?))
+}
+
+/// Checks `firmware` against the CRC stored in `header`, if any.
+pub fn check_crc32(header: &[u8], firmware: &[u8]) -> Result<()> {
+    match stored_crc32(header) {
+        Some(stored) if stored != crc32(firmware) => Err(RustbootError::CrcCheckFailed),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finalize(), 0xCBF4_3926);
+    }
+}
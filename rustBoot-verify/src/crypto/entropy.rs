@@ -0,0 +1,86 @@
+//! Entropy for fault and side-channel countermeasures (see the `entropy` feature), from a source
+//! registered by the board (ex: the MCU's hardware RNG, see `rustBoot_hal::entropy`).
+//!
+//! With a registered [`EntropySource`]
+//!
+//! - signature checks are preceded by a random delay (see [`random_delay`]), so a fault injected
+//!   at a fixed time after reset no longer lands on the same instruction on every attempt.
+//! - with `double-verify`, the second signature check is blinded i.e. its scalar multiplications
+//!   are split with a random scalar, so their intermediate values differ on every boot.
+//!
+//! Boards register their source with [`set_entropy_source`], before any image is verified. If none
+//! is registered, neither countermeasure is applied.
+
+/// A source of random bits.
+pub trait EntropySource {
+    /// Returns 32 random bits.
+    fn next_u32(&self) -> u32;
+
+    /// Fills `buf` with random bytes.
+    fn fill_bytes(&self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk
+                .iter_mut()
+                .zip(bytes)
+                .for_each(|(byte, random)| *byte = random);
+        }
+    }
+}
+
+/// The most iterations [`random_delay`] spins for.
+pub const MAX_DELAY: u32 = 0x3FF;
+
+static mut ENTROPY_SOURCE: Option<&'static dyn EntropySource> = None;
+
+/// Registers the source of the entropy used by rustBoot's countermeasures. Must be called before
+/// any image is verified.
+pub fn set_entropy_source(source: &'static dyn EntropySource) {
+    unsafe { ENTROPY_SOURCE = Some(source) }
+}
+
+/// Returns the registered entropy source, if any.
+pub fn entropy_source() -> Option<&'static dyn EntropySource> {
+    unsafe { ENTROPY_SOURCE }
+}
+
+/// Spins for a random number of iterations (up to [`MAX_DELAY`]), if an entropy source is
+/// registered. Returns the number of iterations.
+#[inline(never)]
+pub fn random_delay() -> u32 {
+    let iterations = entropy_source().map_or(0, |source| source.next_u32() & MAX_DELAY);
+    let mut count = 0u32;
+    while unsafe { core::ptr::read_volatile(&count) } < iterations {
+        unsafe { core::ptr::write_volatile(&mut count, count + 1) };
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    /// A counter, so registering it doesn't affect other tests' results.
+    struct Counter(AtomicU32);
+
+    impl EntropySource for Counter {
+        fn next_u32(&self) -> u32 {
+            self.0.fetch_add(0x101, Ordering::SeqCst)
+        }
+    }
+
+    static COUNTER: Counter = Counter(AtomicU32::new(0x1234_5600));
+
+    #[test]
+    fn random_delays() {
+        set_entropy_source(&COUNTER);
+        let delays = (0..4).map(|_| random_delay()).collect::<Vec<_>>();
+        assert!(delays.iter().all(|delay| *delay <= MAX_DELAY));
+        assert!(delays.windows(2).all(|pair| pair[0] != pair[1]));
+
+        let mut buf = [0u8; 7];
+        Counter(AtomicU32::new(0x0403_0201)).fill_bytes(&mut buf);
+        assert_eq!(buf, [0x01, 0x02, 0x03, 0x04, 0x02, 0x03, 0x03]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Device-unique key derivation, for encrypted updates (see the `device-keys` feature).
+//!
+//! Rather than sharing one image key across a fleet, each device's image (i.e. decryption) key is
+//! derived from a device-unique root. A leaked image key then only exposes the images built for
+//! that one device.
+//!
+//! The default root is [`UidRoot`] i.e. the mcu's factory-programmed unique ID, keyed with a
+//! secret salt that's embedded in the bootloader, via HKDF-SHA256. The same derivation runs on
+//! the host (see `cargo <board> provision`), to compute and register each device's key when it's
+//! provisioned. Boards with a hardware key-derivation root (ex: the nrf52840's CryptoCell KDR)
+//! can implement [`DeviceRoot`] instead.
+//!
+//! *Note: the salt is what keeps the derived keys secret (a UID isn't), it must live in the
+//! bootloader's write- and read-protected region.*
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::{Result, RustbootError};
+
+/// The length of a derived key, in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// The label (i.e. HKDF `info`) that image keys are derived with.
+pub const IMAGE_KEY_LABEL: &[u8] = b"rustBoot image key";
+
+/// A device-unique root, that keys are derived from.
+pub trait DeviceRoot {
+    /// Derives the key for `label` i.e. different labels yield unrelated keys.
+    fn derive_key(&self, label: &[u8]) -> Result<[u8; KEY_LEN]>;
+}
+
+/// The mcu's unique ID, keyed with a secret `salt` i.e. keys are derived as
+/// `HKDF-SHA256(salt, uid, label)`.
+pub struct UidRoot<'a> {
+    pub uid: &'a [u8],
+    pub salt: &'a [u8],
+}
+
+impl DeviceRoot for UidRoot<'_> {
+    fn derive_key(&self, label: &[u8]) -> Result<[u8; KEY_LEN]> {
+        let mut key = [0u8; KEY_LEN];
+        Hkdf::<Sha256>::new(Some(self.salt), self.uid)
+            .expand(label, &mut key)
+            .map_err(|_| RustbootError::InvalidValue)?;
+        Ok(key)
+    }
+}
+
+/// Derives the device's image (i.e. decryption) key from `root`.
+pub fn image_key(root: &impl DeviceRoot) -> Result<[u8; KEY_LEN]> {
+    root.derive_key(IMAGE_KEY_LABEL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uid_root() {
+        // RFC 5869, test case 1 (the first `KEY_LEN` bytes of its OKM)
+        let root = UidRoot {
+            uid: &[0x0b; 22],
+            salt: &[
+                0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+            ],
+        };
+        let info = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+        assert_eq!(
+            root.derive_key(&info),
+            Ok([
+                0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36,
+                0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56,
+                0xec, 0xc4, 0xc5, 0xbf,
+            ])
+        );
+
+        // keys are unique to a device and to a salt
+        let key = image_key(&root).unwrap();
+        let other_device = UidRoot {
+            uid: &[0x0c; 22],
+            ..root
+        };
+        let other_salt = UidRoot {
+            salt: &[0x01],
+            ..root
+        };
+        assert_ne!(image_key(&other_device).unwrap(), key);
+        assert_ne!(image_key(&other_salt).unwrap(), key);
+        assert_eq!(image_key(&root).unwrap(), key);
+    }
+}
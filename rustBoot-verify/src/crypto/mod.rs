@@ -0,0 +1,7 @@
+#[cfg(feature = "entropy")]
+pub mod entropy;
+#[cfg(feature = "device-keys")]
+pub mod kdf;
+#[cfg(feature = "secure-element")]
+pub mod secure_element;
+pub mod signatures;
@@ -0,0 +1,102 @@
+//! Public-key storage in an external secure element (ex: an ATECC608, over I2C), see the
+//! `secure-element` feature.
+//!
+//! By default, images are verified against the public key embedded in the bootloader. A
+//! registered [`SecureElement`] replaces it i.e. the key is read from the secure element's key
+//! slot, or the signature is verified by the secure element itself. Each device can then be
+//! provisioned with its own key, held outside of MCU flash, which makes devices harder to clone.
+//!
+//! Boards register their secure element with [`set_secure_element`], before any image is
+//! verified. If none is registered, the embedded key is used.
+
+use crate::{Result, RustbootError};
+
+/// A secure element holding the (NIST-P256) public key that images are signed against.
+pub trait SecureElement {
+    /// Reads the public key i.e. the untagged, uncompressed point `X || Y`, from the secure
+    /// element's key slot.
+    fn public_key(&self) -> Result<[u8; 64]>;
+
+    /// Returns `true` if the secure element verifies signatures itself (see
+    /// [`SecureElement::verify`]). Otherwise (the default), signatures are verified in software,
+    /// against [`SecureElement::public_key`].
+    fn verifies_on_chip(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if `signature` (i.e. `r || s`) is a valid signature of the SHA-256 `digest`,
+    /// made with the key in the secure element's slot. Only called if
+    /// [`SecureElement::verifies_on_chip`].
+    fn verify(&self, _digest: &[u8; 32], _signature: &[u8]) -> Result<bool> {
+        Err(RustbootError::SecureElementError)
+    }
+}
+
+static mut SECURE_ELEMENT: Option<&'static dyn SecureElement> = None;
+
+/// Registers the secure element that images are verified against, replacing the embedded public
+/// key. Must be called before any image is verified.
+pub fn set_secure_element(se: &'static dyn SecureElement) {
+    unsafe { SECURE_ELEMENT = Some(se) }
+}
+
+/// Returns the registered secure element, if any.
+pub fn secure_element() -> Option<&'static dyn SecureElement> {
+    unsafe { SECURE_ELEMENT }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::signatures::{verify_ecc256_signature, HDR_IMG_TYPE_AUTH};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use p256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    /// The signing key matching the embedded public key.
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    /// Holds the embedded key (so other tests are unaffected by its registration), counting reads.
+    struct KeySlot(AtomicUsize);
+
+    impl SecureElement for KeySlot {
+        fn public_key(&self) -> Result<[u8; 64]> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let vk = SigningKey::from_bytes(&SK_BYTES).unwrap().verifying_key();
+            let mut key = [0u8; 64];
+            key.copy_from_slice(&vk.to_encoded_point(false).as_bytes()[1..]);
+            Ok(key)
+        }
+    }
+
+    static SLOT: KeySlot = KeySlot(AtomicUsize::new(0));
+
+    #[test]
+    fn key_read_from_secure_element() {
+        set_secure_element(&SLOT);
+        let sk = SigningKey::from_bytes(&SK_BYTES).unwrap();
+        let mut hasher = Sha256::new();
+        Digest::update(&mut hasher, b"rustBoot image");
+        let signature: Signature = sk.sign_digest(hasher.clone());
+
+        let reads = SLOT.0.load(Ordering::SeqCst);
+        assert_eq!(
+            verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(
+                hasher.clone(),
+                signature.as_ref()
+            ),
+            Ok(true)
+        );
+        assert!(SLOT.0.load(Ordering::SeqCst) > reads);
+
+        Digest::update(&mut hasher, b"tampered");
+        assert_eq!(
+            verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(hasher, signature.as_ref()),
+            Err(RustbootError::FwAuthFailed)
+        );
+    }
+}
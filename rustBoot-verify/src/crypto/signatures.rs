@@ -0,0 +1,350 @@
+#![allow(warnings)]
+
+use crate::{Result, RustbootError};
+use core::convert::TryFrom;
+use core::ops::Add;
+
+#[cfg(feature = "entropy")]
+use super::entropy::{entropy_source, random_delay};
+#[cfg(feature = "secure-element")]
+use super::secure_element::secure_element;
+#[cfg(feature = "secp256k1")]
+use k256::{
+    ecdsa::{signature::DigestVerifier, Signature, VerifyingKey},
+    elliptic_curve::consts::U32,
+};
+#[cfg(feature = "nistp256")]
+use p256::{
+    ecdsa::signature::digest::Digest,
+    ecdsa::signature::digest::{FixedOutputDirty, Reset, Update},
+    ecdsa::{signature::DigestVerifier, Signature, VerifyingKey},
+    elliptic_curve::consts::U32,
+    elliptic_curve::{generic_array::GenericArray, FieldSize},
+    EncodedPoint, NistP256,
+};
+#[cfg(feature = "double-verify")]
+use p256::{
+    elliptic_curve::{ops::Reduce, AffineXCoordinate},
+    ProjectivePoint, PublicKey, Scalar, U256,
+};
+
+// NIST-P256 constants
+#[cfg(feature = "nistp256")]
+pub const HDR_IMG_TYPE_AUTH: u16 = 0x0200;
+// ECC-SECPK1 constants
+#[cfg(feature = "secp256k1")]
+pub const HDR_IMG_TYPE_AUTH: u16 = 0x0000;
+// ED25519 constants
+#[cfg(feature = "ed25519")]
+pub const HDR_IMG_TYPE_AUTH: u16 = 0x0100;
+
+/// A type to represent an ECDSA-SHA256 Signature
+#[cfg(feature = "nistp256")]
+pub struct NistP256Signature {
+    pub verify_key: VerifyingKey,
+}
+
+#[cfg(feature = "nistp256")]
+impl NistP256Signature {
+    /// Verifies an ECDSA signature. This method is intended to take as
+    /// argument, a pre-updated [`Digest`] instance thats needs to be finalized.
+    ///
+    /// Returns a `bool` if successful else an error.
+    pub fn verify<D: Digest<OutputSize = U32>>(self, digest: D, signature: &[u8]) -> Result<bool> {
+        let res = self
+            .verify_key
+            .verify_digest(
+                digest,
+                &Signature::try_from(signature).map_err(|_| RustbootError::BadSignature)?,
+            )
+            .is_ok();
+
+        Ok(res)
+    }
+}
+
+/// A type to represent an ECDSA-SHA256 Signature
+#[cfg(feature = "secp256k1")]
+pub struct Secp256k1Signature {
+    pub verify_key: VerifyingKey,
+}
+
+#[cfg(feature = "secp256k1")]
+impl Secp256k1Signature {
+    /// Verifies an ECDSA signature. This method is intended to take as
+    /// argument, a pre-updated [`Digest`] instance thats needs to be finalized.
+    ///
+    /// Returns a `bool` if successful else an error.
+    pub fn verify<D: Digest<OutputSize = U32>>(self, digest: D, signature: &[u8]) -> Result<bool> {
+        let res = self
+            .verify_key
+            .verify_digest(
+                digest,
+                &Signature::try_from(signature).map_err(|_| RustbootError::BadSignature)?,
+            )
+            .is_ok();
+        Ok(res)
+    }
+}
+
+/// Performs the signature verification; take as argument, a pre-updated
+/// [`Digest`] instance thats needs to be finalized and the associated signature
+/// to be verified.
+pub fn verify_ecc256_signature<D, const N: u16>(digest: D, signature: &[u8]) -> Result<bool>
+where
+    D: Digest<OutputSize = U32>,
+{
+    #[cfg(feature = "entropy")]
+    random_delay();
+    match N {
+        #[cfg(feature = "nistp256")]
+        HDR_IMG_TYPE_AUTH => {
+            #[cfg(feature = "secure-element")]
+            if let Some(se) = secure_element().filter(|se| se.verifies_on_chip()) {
+                let mut hash = [0u8; 32];
+                hash.copy_from_slice(&digest.finalize());
+                return match se.verify(&hash, signature)? {
+                    true => Ok(true),
+                    false => Err(RustbootError::FwAuthFailed),
+                };
+            }
+            if let VerifyingKeyTypes::VKeyNistP256(vk) = import_pubkey(PubkeyTypes::NistP256)? {
+                let ecc256_verifier = NistP256Signature { verify_key: vk };
+                let res = ecc256_verifier.verify(digest, signature)?;
+                match res {
+                    true => Ok(true),
+                    false => Err(RustbootError::FwAuthFailed),
+                }
+            } else {
+                Err(RustbootError::Unreachable)
+            }
+        }
+        #[cfg(feature = "secp256k1")]
+        HDR_IMG_TYPE_AUTH => {
+            let ecc256_verifier = Secp256k1Signature {
+                verify_key: import_pubkey(PubkeyTypes::Secp256k1)?,
+            };
+            let res = ecc256_verifier.verify(digest, signature)?;
+            match res {
+                true => Ok(true),
+                false => Err(RustbootError::FwAuthFailed),
+            }
+        }
+        // ed25519 signatures can't be checked yet
+        #[cfg(feature = "ed25519")]
+        HDR_IMG_TYPE_AUTH => Err(RustbootError::FwAuthFailed),
+        _ => Err(RustbootError::InvalidValue),
+    }
+}
+
+/// A second, independent signature check, see the `double-verify` feature.
+///
+/// With `double-verify`, an image's signature is checked twice - by [`verify_ecc256_signature`]
+/// and by a `SignatureCheck`, over a separately computed digest - and the image is only accepted
+/// if both checks pass. A single glitched (i.e. skipped) instruction can then no longer turn a
+/// failed verification into a passed one.
+///
+/// [`SoftwareCheck`] is the default. Boards with a crypto accelerator should register a check
+/// backed by it (see [`set_signature_check`]), so the two checks share no code at all.
+#[cfg(feature = "double-verify")]
+pub trait SignatureCheck {
+    /// Returns `true` if `signature` is a valid NIST-P256 signature of the SHA-256 `digest`, made
+    /// with the key matching the embedded public key.
+    fn check(&self, digest: &[u8; 32], signature: &[u8]) -> bool;
+}
+
+/// The default [`SignatureCheck`]. It evaluates the ECDSA verification equation with `p256`'s
+/// curve arithmetic, rather than going through its `ecdsa` verifier.
+///
+/// With the `entropy` feature and a registered entropy source, the evaluation is blinded i.e.
+/// `u1 * G` is computed as `(u1 - m) * G + m * G`, for a random `m`.
+#[cfg(feature = "double-verify")]
+pub struct SoftwareCheck;
+
+#[cfg(feature = "double-verify")]
+impl SignatureCheck for SoftwareCheck {
+    #[inline(never)]
+    fn check(&self, digest: &[u8; 32], signature: &[u8]) -> bool {
+        let vk = match import_pubkey(PubkeyTypes::NistP256) {
+            Ok(VerifyingKeyTypes::VKeyNistP256(vk)) => vk,
+            _ => return false,
+        };
+        let signature = match Signature::try_from(signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        let (r, s) = signature.split_scalars();
+        let s_inv = match Option::<Scalar>::from(s.invert()) {
+            Some(s_inv) => s_inv,
+            None => return false,
+        };
+        let z = <Scalar as Reduce<U256>>::from_be_bytes_reduced(*GenericArray::from_slice(digest));
+        let q = ProjectivePoint::from(*PublicKey::from(&vk).as_affine());
+        // R = (z * s^-1) * G + (r * s^-1) * Q, the signature is valid if `x(R) mod n == r`
+        let g = ProjectivePoint::generator();
+        let u1 = z * s_inv;
+        #[cfg(feature = "entropy")]
+        let u1_g = match entropy_source() {
+            Some(source) => {
+                let mut mask = GenericArray::default();
+                source.fill_bytes(&mut mask);
+                let mask = <Scalar as Reduce<U256>>::from_be_bytes_reduced(mask);
+                g * (u1 - mask) + g * mask
+            }
+            None => g * u1,
+        };
+        #[cfg(not(feature = "entropy"))]
+        let u1_g = g * u1;
+        let point = (u1_g + q * (*r * s_inv)).to_affine();
+        <Scalar as Reduce<U256>>::from_be_bytes_reduced(point.x()) == *r
+    }
+}
+
+#[cfg(feature = "double-verify")]
+static mut SIGNATURE_CHECK: &'static dyn SignatureCheck = &SoftwareCheck;
+
+/// Registers the second signature check (ex: one backed by a crypto accelerator), replacing
+/// [`SoftwareCheck`]. Must be called before any image is verified.
+#[cfg(feature = "double-verify")]
+pub fn set_signature_check(check: &'static dyn SignatureCheck) {
+    unsafe { SIGNATURE_CHECK = check }
+}
+
+/// Checks `signature` with the registered [`SignatureCheck`].
+#[cfg(feature = "double-verify")]
+pub fn second_signature_check(digest: &[u8; 32], signature: &[u8]) -> bool {
+    #[cfg(feature = "entropy")]
+    random_delay();
+    unsafe { SIGNATURE_CHECK }.check(digest, signature)
+}
+
+pub enum PubkeyTypes {
+    #[allow(dead_code)]
+    Secp256k1,
+    #[allow(dead_code)]
+    Ed25519,
+    NistP256,
+    #[allow(dead_code)]
+    NistP384,
+}
+
+pub enum VerifyingKeyTypes {
+    #[cfg(feature = "secp256k1")]
+    VKey256k1(VerifyingKey),
+    #[cfg(feature = "nistp256")]
+    VKeyNistP256(VerifyingKey),
+    #[allow(dead_code)]
+    VKeyEd25519,
+    #[allow(dead_code)]
+    VKeyNistP384,
+}
+
+/// Imports a raw public key embedded in the bootloader.
+///
+/// With the `secure-element` feature, the NIST-P256 key is read from the registered
+/// [`SecureElement`](super::secure_element::SecureElement) instead, if there is one.
+pub fn import_pubkey(pk: PubkeyTypes) -> Result<VerifyingKeyTypes> {
+    match pk {
+        #[cfg(feature = "secp256k1")]
+        PubkeyTypes::Secp256k1 => {
+            let embedded_pubkey = [0u8; 64];
+            let untagged_bytes: &GenericArray<u8, <FieldSize<Secp256k1> as Add>::Output> =
+                GenericArray::from_slice(&embedded_pubkey[..]);
+            let sec1_encoded_pubkey = EncodedPoint::from_untagged_bytes(untagged_bytes);
+            // `from_encoded_point` is fallible i.e. it will check to see if the point (i.e. pubkey) is on the curve.
+            let secp256k1_vk = VerifyingKey::from_encoded_point(&sec1_encoded_pubkey)
+                .map_err(|_| RustbootError::ECCError);
+            Ok(VerifyingKeyTypes::VKey256k1(secp256k1_vk?))
+        }
+        #[cfg(feature = "nistp256")]
+        PubkeyTypes::NistP256 => {
+            let embedded_pubkey = [
+                0x74, 0xBF, 0x5D, 0xE9, 0xF8, 0x69, 0x69, 0x44, 0x35, 0xAE, 0xB7, 0x39, 0x6F, 0xA1,
+                0x40, 0x11, 0xB6, 0xA1, 0x7F, 0x2D, 0x8A, 0x86, 0xB9, 0x58, 0xBC, 0x4A, 0x51, 0xF7,
+                0xF3, 0x0F, 0x23, 0x77, 0x78, 0x0E, 0x11, 0x46, 0x95, 0x3A, 0x1D, 0xDF, 0x69, 0xCD,
+                0x34, 0x23, 0xFE, 0x63, 0x05, 0x15, 0x30, 0x43, 0xBB, 0x9E, 0x75, 0x63, 0xE0, 0x41,
+                0x6A, 0x70, 0xCE, 0x16, 0x0A, 0x60, 0x2A, 0x38,
+            ];
+            #[cfg(feature = "secure-element")]
+            let embedded_pubkey = match secure_element() {
+                Some(se) => se.public_key()?,
+                None => embedded_pubkey,
+            };
+            let untagged_bytes: &GenericArray<u8, <FieldSize<NistP256> as Add>::Output> =
+                GenericArray::from_slice(&embedded_pubkey[..]);
+            let sec1_encoded_pubkey = EncodedPoint::from_untagged_bytes(untagged_bytes);
+            // `from_encoded_point` is fallible i.e. it will check to see if the point (i.e. pubkey) is on the curve.
+            let p256_vk = VerifyingKey::from_encoded_point(&sec1_encoded_pubkey)
+                .map_err(|_| RustbootError::ECCError);
+            Ok(VerifyingKeyTypes::VKeyNistP256(p256_vk?))
+        }
+        _ => Err(RustbootError::ECCError),
+    }
+}
+
+#[cfg(all(test, feature = "double-verify"))]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::DigestSigner, SigningKey};
+    use sha2::Sha256;
+
+    /// The signing key matching the embedded public key.
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    #[test]
+    fn software_check() {
+        let sk = SigningKey::from_bytes(&SK_BYTES).unwrap();
+        let mut hasher = Sha256::new();
+        Digest::update(&mut hasher, b"rustBoot image");
+        let signature: Signature = sk.sign_digest(hasher.clone());
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.clone().finalize());
+
+        // both checks agree on a valid signature
+        assert_eq!(
+            verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(hasher, signature.as_ref()),
+            Ok(true)
+        );
+        assert!(SoftwareCheck.check(&digest, signature.as_ref()));
+        assert!(second_signature_check(&digest, signature.as_ref()));
+
+        // and reject a tampered digest or signature
+        let mut tampered = digest;
+        tampered[0] ^= 1;
+        assert!(!SoftwareCheck.check(&tampered, signature.as_ref()));
+        let mut bad_signature = [0u8; 64];
+        bad_signature.copy_from_slice(signature.as_ref());
+        bad_signature[63] ^= 1;
+        assert!(!SoftwareCheck.check(&digest, &bad_signature));
+        assert!(!SoftwareCheck.check(&digest, &[0u8; 64]));
+    }
+
+    #[cfg(feature = "entropy")]
+    #[test]
+    fn blinded_software_check() {
+        use crate::crypto::entropy::{set_entropy_source, EntropySource};
+
+        struct Fixed;
+        impl EntropySource for Fixed {
+            fn next_u32(&self) -> u32 {
+                0xA5A5_5A5A
+            }
+        }
+        static FIXED: Fixed = Fixed;
+
+        set_entropy_source(&FIXED);
+        let sk = SigningKey::from_bytes(&SK_BYTES).unwrap();
+        let mut hasher = Sha256::new();
+        Digest::update(&mut hasher, b"rustBoot image");
+        let signature: Signature = sk.sign_digest(hasher.clone());
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.finalize());
+        assert!(SoftwareCheck.check(&digest, signature.as_ref()));
+        digest[31] ^= 1;
+        assert!(!SoftwareCheck.check(&digest, signature.as_ref()));
+    }
+}
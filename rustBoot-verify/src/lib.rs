@@ -0,0 +1,275 @@
+//! rustBoot's image verification i.e. parsing a rustBoot image's header (or a SUIT manifest)
+//! and verifying its digest and signature.
+//!
+//! Nothing here depends on flash, partitions or the update state machine, so an application can
+//! verify a rustBoot image (ex: before staging an update) with the same code as the bootloader,
+//! on the host or on a target. rustBoot re-exports these modules.
+//!
+//! With the `panic-free` feature, code that may panic (ex: `unwrap`, `expect`, `panic!` or
+//! indexing) is denied i.e. it doesn't compile (under `cargo clippy`), so every error is returned
+//! to the caller. This is a build profile for static analysis and MISRA-style reviews, CI checks
+//! it with `cargo clippy --features panic-free`.
+
+#![cfg_attr(not(test), no_std)]
+#![allow(non_snake_case)]
+#![cfg_attr(
+    all(feature = "panic-free", not(test)),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::todo,
+        clippy::unimplemented,
+        clippy::unreachable,
+        clippy::indexing_slicing
+    )
+)]
+
+#[cfg(all(feature = "cert-chain", feature = "double-verify"))]
+compile_error!("`double-verify` checks signatures with the embedded key only, it can't be combined with `cert-chain`");
+
+#[cfg(feature = "cert-chain")]
+pub mod cert;
+pub mod chain;
+pub mod crc;
+pub mod crypto;
+#[cfg(feature = "compression")]
+pub mod lz4;
+pub mod parser;
+pub mod rbconstants;
+#[cfg(feature = "release")]
+pub mod release;
+#[cfg(feature = "suit")]
+pub mod suit;
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+/// The RustbootError type.
+///
+/// Hardware errors (i.e. a HAL's or driver's own error type) convert into [`RustbootError::Hal`],
+/// keeping the driver's error code as its `source`. See [`RustbootError::recovery`] for what a
+/// bootloader can do about an error.
+pub enum RustbootError {
+    /// An operation is not permitted in the current state or an invalid state was reached.
+    InvalidState,
+    /// Firmware authentication failed
+    FwAuthFailed,
+    /// Image integrity verification failed.
+    IntegrityCheckFailed,
+    /// Image integrity verification failed i.e. the firmware's CRC doesn't match the one in its
+    /// header (checked before its digest), the image is corrupted.
+    CrcCheckFailed,
+    /// The val of the size field in an image header is not valid
+    InvalidFirmwareSize,
+    /// Type, length, value triple does not exist i.e. tried to parse the header
+    /// for a given a `field_type` but we reached the `end of header`.
+    TLVNotFound,
+    /// The hash output or length is invalid .
+    BadHashValue,
+    /// The value of a field in a param packet was not set
+    FieldNotSet,
+    /// Error while performing an `EC Crypto operation`
+    ECCError,
+    /// The image is malformed. Ex: for mcu(s) this could be an invalid
+    /// `magic` field or `trailer magic`
+    InvalidImage,
+    /// Something's wrong with the signature stored in the header.
+    BadSignature,
+    /// The version number of the img is invalid. For fit-images, this
+    /// could be a case where the timestamp in the supplied fit-image does
+    /// not match the `updt.txt` version.
+    BadVersion,
+    /// The value associated with the requested TLV is too large i.e. invalid.
+    InvalidHdrFieldLength,
+    /// Suppose to be unreachable
+    Unreachable,
+    /// Null value
+    NullValue,
+    /// The requested header field has an invalid value.
+    InvalidValue,
+    /// Attempt to reinitialize a global mutable static.  
+    StaticReinit,
+    /// The sector flag value is invalid
+    InvalidSectFlag,
+    /// A partition state or sector flag can't move to the requested state.
+    InvalidStateTransition,
+    /// A supplied buffer is too small to hold the result.
+    BufferTooSmall,
+    /// The kernel isn't a valid ARM64 `Image` i.e. its header is malformed.
+    InvalidKernelImage,
+    /// A flash write failed, or the flash doesn't hold the written data (ex: a worn sector).
+    FlashWriteFailed,
+    /// A flash erase failed, or the erased region isn't blank.
+    FlashEraseFailed,
+    /// The secure element didn't respond, or reported an error.
+    SecureElementError,
+    /// A HAL or driver reported an error, see [`HalError`].
+    Hal(HalError),
+    /// The image was built for another board i.e. its board-ID TLV doesn't match this board's.
+    BoardMismatch,
+    /// The image's signing certificate is refused i.e. the image's timestamp is outside its
+    /// validity or (in a `production-certs` build) it's a development certificate.
+    CertRefused,
+    /// The fit-image is outside its validity window i.e. the RTC's time is before its
+    /// `not-before` or after its `not-after`.
+    ImageExpired,
+    /// The fit-image carries a validity window but the board can't tell the time (it has no RTC
+    /// or its RTC is unset), see `rustBoot::version::ValidityPolicy`.
+    TimeUnavailable,
+    /// A fit-image's image is to be loaded outside the board's mapped memory i.e. its `load`
+    /// address (or its end) isn't in RAM that the bootloader can reach.
+    UnmappedLoadAddress,
+
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// The result type for rustboot.
+pub type Result<T> = core::result::Result<T, RustbootError>;
+
+#[rustfmt::skip]
+impl fmt::Display for RustbootError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &RustbootError::InvalidState             => write!(f, "Invalid State, operation not permitted"),
+            &RustbootError::FwAuthFailed             => write!(f, "Firmware authentication failed"),
+            &RustbootError::IntegrityCheckFailed     => write!(f, "Integrity check failed"),
+            &RustbootError::CrcCheckFailed           => write!(f, "Integrity check failed, bad CRC"),
+            &RustbootError::InvalidFirmwareSize      => write!(f, "Malformed Firmware"),
+            &RustbootError::TLVNotFound              => write!(f, "Reached end of header options"),
+            &RustbootError::BadHashValue             => write!(f, "Bad Hash"),
+            &RustbootError::FieldNotSet              => write!(f, "The field is not set"),
+            &RustbootError::ECCError                 => write!(f, "EC Crypto operation failed"),
+            &RustbootError::InvalidImage             => write!(f, "The image is not a valid RUSTBOOT image"),
+            &RustbootError::BadSignature             => write!(f, "Bad signature"),
+            &RustbootError::BadVersion               => write!(f, "Bad image version of fit-image version mismatch"),
+            &RustbootError::InvalidHdrFieldLength    => write!(f, "The length of the requested field is invalid"),
+            &RustbootError::Unreachable              => write!(f, "An unreachable state was reached."),
+            &RustbootError::NullValue                => write!(f, "got a NULL value"),
+            &RustbootError::InvalidValue             => write!(f, "Header field has an invalid value"),
+            &RustbootError::StaticReinit             => write!(f, "Cannot reinitialize global mutable static"),
+            &RustbootError::InvalidSectFlag          => write!(f, "The sector flag value is invalid"),
+            &RustbootError::InvalidStateTransition   => write!(f, "Invalid state transition"),
+            &RustbootError::BufferTooSmall           => write!(f, "The supplied buffer is too small"),
+            &RustbootError::InvalidKernelImage       => write!(f, "The kernel is not a valid ARM64 Image"),
+            &RustbootError::FlashWriteFailed         => write!(f, "Flash write failed"),
+            &RustbootError::FlashEraseFailed         => write!(f, "Flash erase failed"),
+            &RustbootError::SecureElementError       => write!(f, "Secure element error"),
+            &RustbootError::Hal(e)                   => write!(f, "Hardware error: {:?}, source: {:#x}", e.kind, e.source),
+            &RustbootError::BoardMismatch            => write!(f, "The image was built for another board"),
+            &RustbootError::CertRefused              => write!(f, "The image's signing certificate was refused"),
+            &RustbootError::ImageExpired             => write!(f, "The fit-image is outside its validity window"),
+            &RustbootError::TimeUnavailable          => write!(f, "The RTC is unset, the fit-image's validity can't be checked"),
+            &RustbootError::UnmappedLoadAddress      => write!(f, "An image's load address is outside mapped memory"),
+            &RustbootError::__Nonexhaustive          => write!(f, "An unreachable state was reached."),
+        }
+    }
+}
+
+impl RustbootError {
+    /// The error's code (i.e. its discriminant), ex: for an event log. Codes are stable, as
+    /// variants are only ever appended.
+    pub fn code(&self) -> u8 {
+        // SAFETY: `RustbootError` is `repr(u8)` i.e. its first byte is its discriminant.
+        unsafe { *(self as *const Self as *const u8) }
+    }
+
+    /// Classifies the error, so a bootloader can decide whether to retry an operation, fall back
+    /// to another image (or boot source) or halt.
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            RustbootError::Hal(HalError {
+                kind: HalErrorKind::Timeout | HalErrorKind::Busy | HalErrorKind::Io,
+                ..
+            })
+            | RustbootError::FlashWriteFailed
+            | RustbootError::FlashEraseFailed
+            | RustbootError::SecureElementError => Recovery::Retry,
+            RustbootError::InvalidState
+            | RustbootError::InvalidStateTransition
+            | RustbootError::StaticReinit
+            | RustbootError::Unreachable
+            | RustbootError::__Nonexhaustive => Recovery::Halt,
+            _ => Recovery::Fallback,
+        }
+    }
+}
+
+/// What a bootloader can do about an error, see [`RustbootError::recovery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The error is (likely) transient i.e. retry the operation.
+    Retry,
+    /// The image or boot source is bad i.e. try the next one (ex: the other partition).
+    Fallback,
+    /// Carrying on isn't safe i.e. halt.
+    Halt,
+}
+
+/// A hardware error i.e. one reported by a HAL or driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HalError {
+    pub kind: HalErrorKind,
+    /// The driver's own error code (ex: an SD card's status), for diagnostics.
+    pub source: u32,
+}
+
+impl HalError {
+    pub const fn new(kind: HalErrorKind, source: u32) -> Self {
+        HalError { kind, source }
+    }
+}
+
+impl From<HalError> for RustbootError {
+    fn from(e: HalError) -> Self {
+        RustbootError::Hal(e)
+    }
+}
+
+/// The kind of a [`HalError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HalErrorKind {
+    /// The device didn't respond in time.
+    Timeout,
+    /// The device is busy.
+    Busy,
+    /// A read or write failed.
+    Io,
+    /// The device is absent or couldn't be initialized.
+    NoDevice,
+    /// The device doesn't support the operation.
+    Unsupported,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes() {
+        assert_eq!(RustbootError::InvalidState.code(), 0);
+        assert_eq!(RustbootError::BufferTooSmall.code(), 19);
+        assert_eq!(RustbootError::SecureElementError.code(), 23);
+        let e = RustbootError::from(HalError::new(HalErrorKind::Timeout, 0x80));
+        assert_eq!(e.code(), 24);
+        assert_eq!(RustbootError::BoardMismatch.code(), 25);
+        assert_eq!(RustbootError::CertRefused.code(), 26);
+        assert_eq!(RustbootError::ImageExpired.code(), 27);
+        assert_eq!(RustbootError::TimeUnavailable.code(), 28);
+        assert_eq!(RustbootError::UnmappedLoadAddress.code(), 29);
+    }
+
+    #[test]
+    fn error_recovery() {
+        let hal = |kind| RustbootError::from(HalError::new(kind, 0));
+        assert_eq!(hal(HalErrorKind::Timeout).recovery(), Recovery::Retry);
+        assert_eq!(hal(HalErrorKind::Io).recovery(), Recovery::Retry);
+        assert_eq!(hal(HalErrorKind::NoDevice).recovery(), Recovery::Fallback);
+        assert_eq!(RustbootError::FlashWriteFailed.recovery(), Recovery::Retry);
+        assert_eq!(RustbootError::FwAuthFailed.recovery(), Recovery::Fallback);
+        assert_eq!(RustbootError::BadVersion.recovery(), Recovery::Fallback);
+        assert_eq!(RustbootError::StaticReinit.recovery(), Recovery::Halt);
+    }
+}
@@ -0,0 +1,233 @@
+//! Compressed images i.e. firmware stored in the `UPDATE` partition as an LZ4 block, and
+//! decompressed into `BOOT` when the update is installed.
+//!
+//! rbsigner's `--compress lz4` option compresses the firmware and records the algorithm and the
+//! compressed length in the image's compression TLV (see [`HDR_COMPRESSION`]). The image's
+//! header, digest, CRC and signature all describe the *decompressed* firmware i.e. an installed
+//! image is indistinguishable from an uncompressed one, and a compressed update is verified by
+//! decompressing it on the fly.
+//!
+//! The block is a raw LZ4 block (i.e. not an LZ4 frame) whose matches reach at most [`WINDOW`]
+//! bytes back, so it can be decompressed with a [`WINDOW`]-byte buffer rather than the whole
+//! firmware in RAM. rbsigner's compressor limits its matches accordingly.
+//!
+//! [`HDR_COMPRESSION`]: crate::rbconstants::HDR_COMPRESSION
+
+use crate::{Result, RustbootError};
+
+/// The decompression window i.e. how far back a match may reach.
+pub const WINDOW: usize = 4096;
+
+/// The smallest match, matches' lengths are stored less this.
+const MIN_MATCH: usize = 4;
+
+/// Decompresses an LZ4 block of `compressed_len` bytes into `decompressed_len` bytes. `next`
+/// returns the block's next byte, `sink` is passed the decompressed bytes in order, up to
+/// [`WINDOW`] bytes at a time. `window` holds the last [`WINDOW`] decompressed bytes.
+///
+/// A malformed block (ex: a match that reaches past the window, or one that doesn't decompress to
+/// `decompressed_len` bytes) is reported as [`RustbootError::IntegrityCheckFailed`], as the
+/// image's digest can't match it anyway. Errors returned by `next` or `sink` are passed on.
+pub fn decompress(
+    mut next: impl FnMut() -> Result<u8>,
+    compressed_len: usize,
+    decompressed_len: usize,
+    window: &mut [u8; WINDOW],
+    mut sink: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut input = Input {
+        next: &mut next,
+        consumed: 0,
+        len: compressed_len,
+    };
+    let mut pos = 0;
+    let mut put = |pos: &mut usize, window: &mut [u8; WINDOW], byte: u8| {
+        if *pos == decompressed_len {
+            return Err(RustbootError::IntegrityCheckFailed);
+        }
+        // `WINDOW`-aligned i.e. within the window
+        *window
+            .get_mut(*pos % WINDOW)
+            .ok_or(RustbootError::IntegrityCheckFailed)? = byte;
+        *pos += 1;
+        match *pos % WINDOW {
+            0 => sink(&window[..]),
+            _ => Ok(()),
+        }
+    };
+    while input.consumed < compressed_len {
+        let token = input.read()?;
+        let literals = length(token >> 4, &mut input)?;
+        for _ in 0..literals {
+            let byte = input.read()?;
+            put(&mut pos, window, byte)?;
+        }
+        // the last sequence is literals only
+        if input.consumed == compressed_len {
+            break;
+        }
+        let offset = u16::from_le_bytes([input.read()?, input.read()?]) as usize;
+        if offset == 0 || offset > pos.min(WINDOW) {
+            return Err(RustbootError::IntegrityCheckFailed);
+        }
+        let len = length(token & 0x0f, &mut input)? + MIN_MATCH;
+        for _ in 0..len {
+            let byte = *window
+                .get((pos - offset) % WINDOW)
+                .ok_or(RustbootError::IntegrityCheckFailed)?;
+            put(&mut pos, window, byte)?;
+        }
+    }
+    if pos != decompressed_len {
+        return Err(RustbootError::IntegrityCheckFailed);
+    }
+    match pos % WINDOW {
+        0 => Ok(()),
+        rem => sink(
+            window
+                .get(..rem)
+                .ok_or(RustbootError::IntegrityCheckFailed)?,
+        ),
+    }
+}
+
+/// The compressed block, read one byte at a time. Reading past its end is an error.
+struct Input<'a, F: FnMut() -> Result<u8>> {
+    next: &'a mut F,
+    consumed: usize,
+    len: usize,
+}
+
+impl<F: FnMut() -> Result<u8>> Input<'_, F> {
+    fn read(&mut self) -> Result<u8> {
+        if self.consumed == self.len {
+            return Err(RustbootError::IntegrityCheckFailed);
+        }
+        self.consumed += 1;
+        (self.next)()
+    }
+}
+
+/// Returns a literal run's or a match's length, given its token nibble. A nibble of 15 is followed
+/// by bytes that are added to it, up to (and including) the first byte that isn't 255.
+fn length<F: FnMut() -> Result<u8>>(nibble: u8, input: &mut Input<F>) -> Result<usize> {
+    let mut len = nibble as usize;
+    if nibble == 0x0f {
+        loop {
+            let byte = input.read()?;
+            len += byte as usize;
+            if byte != 0xff {
+                break;
+            }
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decompress_vec(block: &[u8], decompressed_len: usize) -> Result<Vec<u8>> {
+        let mut input = block.iter();
+        let mut window = [0u8; WINDOW];
+        let mut out = Vec::new();
+        decompress(
+            || input.next().copied().ok_or(RustbootError::Unreachable),
+            block.len(),
+            decompressed_len,
+            &mut window,
+            |bytes| {
+                assert!(bytes.len() <= WINDOW);
+                out.extend_from_slice(bytes);
+                Ok(())
+            },
+        )?;
+        Ok(out)
+    }
+
+    #[test]
+    fn decompresses_blocks() {
+        #[rustfmt::skip]
+        let block = [
+            0x35, b'a', b'b', b'c', 0x03, 0x00,         // "abc", then 9 bytes 3 bytes back
+            0x50, b'v', b'w', b'x', b'y', b'z',         // "vwxyz"
+        ];
+        assert_eq!(decompress_vec(&block, 17).unwrap(), b"abcabcabcabcvwxyz");
+        assert_eq!(decompress_vec(&[], 0).unwrap(), b"");
+        assert_eq!(decompress_vec(&[0x00], 0).unwrap(), b"");
+
+        // a long (i.e. overlapping) match, flushed a window at a time
+        let len = 3 * WINDOW + 100;
+        let mut block = vec![0x1f, b'a', 0x01, 0x00];
+        let mut rem = len - 1 - 5 - MIN_MATCH - 15;
+        while rem >= 0xff {
+            block.push(0xff);
+            rem -= 0xff;
+        }
+        block.push(rem as u8);
+        block.extend_from_slice(&[0x50, b'b', b'b', b'b', b'b', b'b']);
+        let out = decompress_vec(&block, len).unwrap();
+        assert_eq!(out.len(), len);
+        assert!(out[..len - 5].iter().all(|&b| b == b'a'));
+        assert!(out[len - 5..].iter().all(|&b| b == b'b'));
+    }
+
+    #[test]
+    fn malformed_blocks_are_errors() {
+        let block = [
+            0x35, b'a', b'b', b'c', 0x03, 0x00, 0x50, b'v', b'w', b'x', b'y', b'z',
+        ];
+        // the wrong length
+        assert_eq!(
+            decompress_vec(&block, 16),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+        assert_eq!(
+            decompress_vec(&block, 18),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+        // truncated
+        assert_eq!(
+            decompress_vec(&block[..5], 17),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+        // a match reaching before the start of the firmware
+        let mut bad_offset = block;
+        bad_offset[4] = 0x04;
+        assert_eq!(
+            decompress_vec(&bad_offset, 17),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+        // a zero offset
+        bad_offset[4] = 0x00;
+        assert_eq!(
+            decompress_vec(&bad_offset, 17),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+    }
+
+    #[test]
+    fn matches_stay_within_the_window() {
+        // 5000 literals, then a match 4097 bytes back
+        let mut block = vec![0xf0];
+        let mut rem = 5000 - 15;
+        while rem >= 0xff {
+            block.push(0xff);
+            rem -= 0xff;
+        }
+        block.push(rem as u8);
+        block.extend((0..5000).map(|i| i as u8));
+        block.extend_from_slice(&[0x01, 0x10]);
+        block.extend_from_slice(&[0x50, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            decompress_vec(&block, 5000 + 4 + 5),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+        // 4096 bytes back is fine
+        let at = block.len() - 8;
+        block[at] = 0x00;
+        let out = decompress_vec(&block, 5000 + 4 + 5).unwrap();
+        assert_eq!(&out[5000..5004], &out[5000 - WINDOW..5004 - WINDOW]);
+    }
+}
@@ -0,0 +1,776 @@
+use core::convert::TryInto;
+use core::usize;
+
+use crate::rbconstants::{
+    COMPRESSION_LZ4, ECC_SIGNATURE_SIZE, HDR_BOARD_ID, HDR_BOARD_ID_LEN, HDR_COMPRESSION,
+    HDR_COMPRESSION_LEN, HDR_CRC32_LEN, HDR_IMG_TYPE_LEN, HDR_SIGNING_CERT, HDR_TIMESTAMP_LEN,
+    HDR_VENDOR_TLVS, HDR_VENDOR_TYPE_MIN, HDR_VERSION_LEN, HDR_VERSION_STRING,
+    HDR_VERSION_STRING_MAX_LEN, IMAGE_HEADER_SIZE, SHA256_DIGEST_SIZE, SHA384_DIGEST_SIZE,
+    SIGNING_CERT_SIZE,
+};
+use crate::{Result, RustbootError};
+
+/// Parses an image-header i.e. the first [`IMAGE_HEADER_SIZE`] bytes of a partition, for a given `TLV`.
+///
+/// The header is attacker-controlled data (it is parsed before the image's signature is checked),
+/// so a malformed header is reported as an error and never results in a panic.
+///
+/// Returns a slice containing the value
+pub fn parse_header_tlv(header: &[u8], type_field: Tags) -> Result<&[u8]> {
+    let (value, _) = find_tlv(header, type_field)?;
+    Ok(value)
+}
+
+/// Same as [`parse_header_tlv`] but returns the offset of the `TLV` from the start of the image-header.
+pub fn get_header_tlv_offset(header: &[u8], type_field: Tags) -> Result<usize> {
+    let (_, offset) = find_tlv(header, type_field)?;
+    Ok(offset)
+}
+
+fn find_tlv(header: &[u8], type_field: Tags) -> Result<(&[u8], usize)> {
+    // we've checked `magic` and `size` fields of the header during init
+    // start parsing from the 8th byte of the header
+    let header_bytes = header
+        .get(8..IMAGE_HEADER_SIZE)
+        .ok_or(RustbootError::InvalidHdrFieldLength)?;
+    let extract: fn(&[u8]) -> IResult<&[u8], &[u8]> = match type_field {
+        Tags::Version => extract_version,
+        Tags::TimeStamp => extract_timestamp,
+        Tags::ImgType => extract_img_type,
+        Tags::Digest256 | Tags::Digest384 => extract_digest,
+        Tags::PubkeyDigest => extract_pubkey_digest,
+        Tags::Signature => extract_signature,
+        Tags::Crc32 => extract_crc32,
+        Tags::EndOfHeader => return Err(RustbootError::TLVNotFound),
+    };
+    let (remaining, value) = extract(header_bytes).map_err(|_| RustbootError::InvalidValue)?;
+    // `extract_digest` accepts either digest, make sure we found the one we were asked for.
+    match (type_field, value.len()) {
+        (Tags::Digest256, len) if len != SHA256_DIGEST_SIZE => {
+            return Err(RustbootError::TLVNotFound)
+        }
+        (Tags::Digest384, len) if len != SHA384_DIGEST_SIZE => {
+            return Err(RustbootError::TLVNotFound)
+        }
+        _ => {}
+    }
+    // a value is preceded by its 2-byte type and 2-byte length fields. `remaining` and `value`
+    // are sub-slices of `header_bytes`, so this cannot underflow.
+    let offset = IMAGE_HEADER_SIZE - remaining.len() - value.len() - 4;
+    Ok((value, offset))
+}
+
+/// Returns the vendor (i.e. OEM) TLVs in an image-header, see [`VendorTlvs`].
+///
+/// Vendor TLVs start at [`HDR_VENDOR_TLVS`] and end at the `end of header` (or an erased field).
+/// A vendor TLV whose type is below [`HDR_VENDOR_TYPE_MIN`] or whose value runs past the end of
+/// the header is an error.
+pub fn vendor_tlvs(header: &[u8]) -> Result<VendorTlvs<'_>> {
+    let tlvs = header
+        .get(HDR_VENDOR_TLVS..IMAGE_HEADER_SIZE)
+        .ok_or(RustbootError::InvalidHdrFieldLength)?;
+    let mut len = 0;
+    while let Some(typ) = tlvs.get(len..len + 2) {
+        let typ = le_u16(typ);
+        if typ == 0x0000 || typ == 0xffff {
+            break;
+        }
+        if typ < HDR_VENDOR_TYPE_MIN {
+            return Err(RustbootError::InvalidValue);
+        }
+        let value_len = tlvs
+            .get(len + 2..len + 4)
+            .map(|val| le_u16(val) as usize)
+            .ok_or(RustbootError::InvalidHdrFieldLength)?;
+        len += 4 + value_len;
+        if len > tlvs.len() {
+            return Err(RustbootError::InvalidHdrFieldLength);
+        }
+    }
+    tlvs.get(..len)
+        .map(VendorTlvs)
+        .ok_or(RustbootError::InvalidHdrFieldLength)
+}
+
+/// A vendor TLV i.e. authenticated metadata that an OEM embeds in an image (ex: a build id or a
+/// git hash), with rbsigner's `--custom-tlv` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VendorTlv<'a> {
+    /// the TLV's type, `HDR_VENDOR_TYPE_MIN` or above.
+    pub typ: u16,
+    pub value: &'a [u8],
+}
+
+/// An iterator over an image-header's vendor TLVs, see [`vendor_tlvs`].
+///
+/// *Note: vendor TLVs are covered by the image's digest (they're hashed after the firmware), so
+/// they're authenticated along with the image.*
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VendorTlvs<'a>(&'a [u8]);
+
+impl<'a> VendorTlvs<'a> {
+    /// The vendor TLVs, as they're hashed. Empty if there are none.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Returns the image's board id i.e. the value of its board-ID TLV, if it has one (see
+    /// [`board_id`]). Returns [`RustbootError::InvalidHdrFieldLength`] if the value isn't
+    /// [`HDR_BOARD_ID_LEN`] bytes long.
+    pub fn board_id(&self) -> Result<Option<[u8; HDR_BOARD_ID_LEN]>> {
+        let mut tlvs = *self;
+        match tlvs.find(|tlv| tlv.typ == HDR_BOARD_ID) {
+            Some(tlv) => match tlv.value.try_into() {
+                Ok(board_id) => Ok(Some(board_id)),
+                Err(_) => Err(RustbootError::InvalidHdrFieldLength),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `true` if a signing certificate is appended to the image's firmware i.e. if the
+    /// image has a signing-certificate TLV (see `cert`). Returns
+    /// [`RustbootError::InvalidHdrFieldLength`] if the TLV's value isn't [`SIGNING_CERT_SIZE`].
+    pub fn has_signing_cert(&self) -> Result<bool> {
+        let mut tlvs = *self;
+        match tlvs.find(|tlv| tlv.typ == HDR_SIGNING_CERT) {
+            Some(tlv) if tlv.value == (SIGNING_CERT_SIZE as u16).to_le_bytes() => Ok(true),
+            Some(_) => Err(RustbootError::InvalidHdrFieldLength),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the length of the image's compressed firmware if the image was compressed (with
+    /// rbsigner's `--compress` option) i.e. if it has a compression TLV, see `lz4`. LZ4 is the
+    /// only supported algorithm, any other is a [`RustbootError::InvalidValue`]. Returns
+    /// [`RustbootError::InvalidHdrFieldLength`] if the value isn't [`HDR_COMPRESSION_LEN`] bytes
+    /// long.
+    pub fn compressed_len(&self) -> Result<Option<usize>> {
+        let mut tlvs = *self;
+        match tlvs
+            .find(|tlv| tlv.typ == HDR_COMPRESSION)
+            .map(|tlv| tlv.value)
+        {
+            Some(value) if value.len() != HDR_COMPRESSION_LEN => {
+                Err(RustbootError::InvalidHdrFieldLength)
+            }
+            Some(&[COMPRESSION_LZ4, _, l0, l1, l2, l3]) => {
+                Ok(Some(u32::from_le_bytes([l0, l1, l2, l3]) as usize))
+            }
+            Some(_) => Err(RustbootError::InvalidValue),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the image's human-readable version i.e. the value of its version-string TLV, if it
+    /// has one (see rbsigner's `--version-string`). It's informational only, rustBoot compares
+    /// images by their (numeric) version. Returns [`RustbootError::InvalidHdrFieldLength`] if the
+    /// value is longer than [`HDR_VERSION_STRING_MAX_LEN`] and [`RustbootError::InvalidValue`] if
+    /// it isn't UTF-8.
+    pub fn version_string(&self) -> Result<Option<&'a str>> {
+        let mut tlvs = *self;
+        match tlvs.find(|tlv| tlv.typ == HDR_VERSION_STRING) {
+            Some(tlv) if tlv.value.len() > HDR_VERSION_STRING_MAX_LEN => {
+                Err(RustbootError::InvalidHdrFieldLength)
+            }
+            Some(tlv) => core::str::from_utf8(tlv.value)
+                .map(Some)
+                .map_err(|_| RustbootError::InvalidValue),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Returns the board id for a board's name (or a board variant's, ex: `stm32h723-revb`) i.e. the
+/// value of an image's board-ID TLV, see rbsigner's `--board` option. It's the board name's
+/// 32-bit FNV-1a hash, little-endian.
+///
+/// This is a `const fn`, so a board's id can be computed at compile time.
+pub const fn board_id(board: &str) -> [u8; HDR_BOARD_ID_LEN] {
+    let mut hash: u32 = 0x811c9dc5;
+    let mut bytes = board.as_bytes();
+    while let [byte, rest @ ..] = bytes {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+        bytes = rest;
+    }
+    hash.to_le_bytes()
+}
+
+impl<'a> Iterator for VendorTlvs<'a> {
+    type Item = VendorTlv<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (typ, rest) = (self.0.get(..2)?, self.0.get(2..)?);
+        let (value_len, rest) = (rest.get(..2)?, rest.get(2..)?);
+        let value = rest.get(..le_u16(value_len) as usize)?;
+        self.0 = rest.get(value.len()..)?;
+        Some(VendorTlv {
+            typ: le_u16(typ),
+            value,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+/// Each variant in [`Tags`] represents a field in the image-header.
+///
+/// *Note: [`EndOfHeader`] is a pseudo-Tag, i.e. doesnt come
+/// with an associated length-value pair*
+pub enum Tags {
+    Version,
+    TimeStamp,
+    ImgType,
+    Digest256,
+    Digest384,
+    PubkeyDigest,
+    Signature,
+    Crc32,
+    EndOfHeader,
+}
+
+impl Tags {
+    #[rustfmt::skip]
+    /// The ids are reversed to account for endianess
+    fn get_id(self) -> &'static [u8] {
+        match self {
+            Self::Version       => &[0x01, 0x00],
+            Self::TimeStamp     => &[0x02, 0x00],
+            Self::ImgType       => &[0x04, 0x00],
+            Self::Digest256     => &[0x03, 0x00],
+            Self::Digest384     => &[0x13, 0x00],
+            Self::PubkeyDigest  => &[0x10, 0x00],
+            Self::Signature     => &[0x20, 0x00],
+            Self::Crc32         => &[0x30, 0x00],
+            Self::EndOfHeader   => &[0x00, 0x00],
+        }
+    }
+}
+
+use nom::bytes::complete::take_while;
+use nom::bytes::complete::{tag, take};
+use nom::{
+    error::{Error, ErrorKind},
+    Err, IResult,
+};
+
+// use libc_print::libc_println;
+
+/// Returns the little-endian `u16` in the last two bytes of a (2-byte length, or 4-byte
+/// type-length) field, `0` if there are fewer.
+fn le_u16(field: &[u8]) -> u16 {
+    match *field {
+        [.., lo, hi] => u16::from_le_bytes([lo, hi]),
+        _ => 0,
+    }
+}
+
+fn check_for_eof(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    match tag::<_, _, Error<&[u8]>>(Tags::EndOfHeader.get_id())(input) {
+        Ok((_remainder, _eof)) => Err(Err::Error(Error::new(input, ErrorKind::Eof))),
+        Err(_e) => Ok((input, &[])),
+    }
+}
+
+fn check_for_padding(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let res = take_while::<_, _, Error<&[u8]>>(|pad_byte| pad_byte == 0xff)(input)?;
+    Ok(res)
+}
+
+fn extract_version<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let (input, _) = check_for_eof(input)?;
+    let (input, _) = check_for_padding(input)?;
+    let (remainder, version) = take(8u32)(input)?;
+    let (lengthvalue, version_check) = take(2u32)(version)?;
+    let (value, version_len) = take(2u32)(lengthvalue)?;
+    let len = le_u16(version_len) as usize;
+    if version_check == Tags::Version.get_id() && len == HDR_VERSION_LEN {
+        Ok((remainder, value))
+    } else {
+        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+    }
+}
+
+fn extract_timestamp<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let (remainder, _) = extract_version(input)?;
+    let (remainder, _) = check_for_eof(remainder)?;
+    let (remainder, _) = check_for_padding(remainder)?;
+    let (remainder, timestamp) = take(12u32)(remainder)?;
+    let (lengthvalue, timestamp_check) = take(2u32)(timestamp)?;
+    let (value, timestamp_len) = take(2u32)(lengthvalue)?;
+    let len = le_u16(timestamp_len) as usize;
+    if timestamp_check == Tags::TimeStamp.get_id() && len == HDR_TIMESTAMP_LEN {
+        Ok((remainder, value))
+    } else {
+        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+    }
+}
+
+fn extract_img_type<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let (remainder, _) = extract_timestamp(input)?;
+    let (remainder, _) = check_for_eof(remainder)?;
+    let (remainder, _) = check_for_padding(remainder)?;
+    let (remainder, img_type) = take(6u32)(remainder)?;
+    let (lengthvalue, img_type_check) = take(2u32)(img_type)?;
+    let (value, timestamp_len) = take(2u32)(lengthvalue)?;
+    let len = le_u16(timestamp_len) as usize;
+    if img_type_check == Tags::ImgType.get_id() && len == HDR_IMG_TYPE_LEN {
+        Ok((remainder, value))
+    } else {
+        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+    }
+}
+
+fn extract_digest<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let (remainder, _) = extract_img_type(input)?;
+    let (remainder, _) = check_for_eof(remainder)?;
+    let (remainder, _) = check_for_padding(remainder)?;
+    let (remainder, typelen) = take(4u32)(remainder)?;
+    let len = le_u16(typelen) as usize;
+    let (remainder, digest) = take(len)(remainder)?;
+    let (_, digest_check) = take(2u32)(typelen)?;
+    if (digest_check == Tags::Digest256.get_id() && len == SHA256_DIGEST_SIZE)
+        || (digest_check == Tags::Digest384.get_id() && len == SHA384_DIGEST_SIZE)
+    {
+        Ok((remainder, digest))
+    } else {
+        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+    }
+}
+
+fn extract_pubkey_digest<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let (remainder, _) = extract_digest(input)?;
+    let (remainder, _) = check_for_eof(remainder)?;
+    let (remainder, _) = check_for_padding(remainder)?;
+    let (remainder, typelen) = take(4u32)(remainder)?;
+    let len = le_u16(typelen) as usize;
+    let (remainder, digest) = take(len)(remainder)?;
+    let (_, digest_check) = take(2u32)(typelen)?;
+    if (digest_check == Tags::PubkeyDigest.get_id() && len == SHA256_DIGEST_SIZE)
+        || (digest_check == Tags::PubkeyDigest.get_id() && len == SHA384_DIGEST_SIZE)
+    {
+        Ok((remainder, digest))
+    } else {
+        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+    }
+}
+
+fn extract_signature<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let (remainder, _) = extract_pubkey_digest(input)?;
+    let (remainder, _) = check_for_eof(remainder)?;
+    let (remainder, _) = check_for_padding(remainder)?;
+    let (remainder, typelen) = take(4u32)(remainder)?;
+    let len = le_u16(typelen) as usize;
+    let (remainder, signature) = take(len)(remainder)?;
+    let (_, signature_check) = take(2u32)(typelen)?;
+    if signature_check == Tags::Signature.get_id() && len == ECC_SIGNATURE_SIZE {
+        Ok((remainder, signature))
+    } else {
+        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+    }
+}
+
+/// The (optional) `CRC32` TLV follows the signature.
+fn extract_crc32<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    let (remainder, _) = extract_signature(input)?;
+    let (remainder, _) = check_for_eof(remainder)?;
+    let (remainder, _) = check_for_padding(remainder)?;
+    let (remainder, crc) = take(8u32)(remainder)?;
+    let (lengthvalue, crc_check) = take(2u32)(crc)?;
+    let (value, crc_len) = take(2u32)(lengthvalue)?;
+    let len = le_u16(crc_len) as usize;
+    if crc_check == Tags::Crc32.get_id() && len == HDR_CRC32_LEN {
+        Ok((remainder, value))
+    } else {
+        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // use libc_print::libc_println;
+    use super::*;
+    use crate::rbconstants::PUBKEY_DIGEST_SIZE;
+
+    const PAD1: &[u8] = &[0x20, 0x01, 0xff, 0x02, 0x03];
+    const PAD2: &[u8] = &[0xff, 0xff, 0xff, 0x02, 0x03];
+
+    #[rustfmt::skip]
+    const DATA: &[u8] = &[
+        // 0x54, 0x53, 0x55, 0x52, // magic
+        // 0x65, 0x51, 0x48, 0x54, // size
+        0x01, 0x00, 0x04, 0x00, // version type & len
+        0x01, 0x02, 0x03, 0x04, // version value
+
+        0xff, 0xff, 0xff, 0xff, // padding bytes
+
+        0x02, 0x00, 0x08, 0x00, // timestamp type & len
+        0x11, 0x11, 0x11, 0x11, // timestamp value
+        0x22, 0x22, 0x22, 0x22, 
+
+        0x04, 0x00, 0x02, 0x00, // img type and len
+        0x02, 0x00,             // img value
+
+        0xff, 0xff, 0xff, 0xff, // padding bytes
+        0xff, 0xff,
+
+        // 32 byte digest type and len
+        0x03, 0x00, 0x20, 0x00, 
+        // digest value
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 
+        // 32-byte pubkey digest type and len
+        0x10, 0x00, 0x20, 0x00, 
+        // pubkey digest value
+        0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 
+        0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 
+        0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 
+        0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 
+        // signature type and len
+        0x20, 0x00, 0x40, 0x00, 
+        // signature value
+        0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+        0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+        0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+        0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+        0x44, 0x44, 0x44, 0x44, 
+        // crc32 type, len and value
+        0x30, 0x00, 0x04, 0x00,
+        0x78, 0x56, 0x34, 0x12,
+
+        // end of header
+        0x00, 0x00, 
+    ];
+
+    #[test]
+    fn padding_test() {
+        let val = match check_for_padding(PAD1) {
+            Ok((remainder, _val)) => {
+                // libc_println!("incorrect padding: {:?}", remainder);
+                remainder
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(val, &[0x20, 0x01, 0xff, 0x02, 0x03]);
+
+        let val = match check_for_padding(PAD2) {
+            Ok((_remainder, val)) => {
+                // libc_println!("padding: {:?}", val);
+                val
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(val, &[0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn parse_version() {
+        let val = match extract_version(DATA) {
+            Ok((_remainder, version)) => {
+                // libc_println!("version: {:?}", version);
+                version
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(val, &[0x01, 0x02, 0x03, 0x04])
+    }
+
+    #[test]
+    fn parse_timestamp() {
+        let val = match extract_timestamp(DATA) {
+            Ok((_remainder, timestamp)) => {
+                // libc_println!("timestamp: {:?}", timestamp);
+                timestamp
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(val, &[0x11, 0x11, 0x11, 0x11, 0x22, 0x22, 0x22, 0x22])
+    }
+
+    #[test]
+    fn parse_img_type() {
+        let val = match extract_img_type(DATA) {
+            Ok((_remainder, img_type)) => {
+                // libc_println!("img_type: {:?}", img_type);
+                img_type
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(val, &[0x02, 0x00])
+    }
+
+    #[test]
+    fn parse_digest() {
+        let val = match extract_digest(DATA) {
+            Ok((_remainder, digest)) => {
+                // libc_println!("digest: {:?}", digest);
+                digest
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(
+            val,
+            &[
+                0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33,
+                0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33,
+                0x33, 0x33, 0x33, 0x33,
+            ]
+        )
+    }
+
+    #[test]
+    fn parse_pubkey_digest() {
+        let val = match extract_pubkey_digest(DATA) {
+            Ok((_remainder, digest)) => {
+                // libc_println!("pubkey digest: {:?}", digest);
+                digest
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(
+            val,
+            &[
+                0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55,
+                0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55,
+                0x55, 0x55, 0x55, 0x55,
+            ]
+        )
+    }
+
+    #[test]
+    fn parse_signature() {
+        let val = match extract_signature(DATA) {
+            Ok((_remainder, signature)) => {
+                // libc_println!("signature: {:?}", signature);
+                signature
+            }
+            Err(_e) => &[],
+        };
+        assert_eq!(
+            val,
+            &[
+                0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+                0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+                0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+                0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+                0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x44,
+            ]
+        )
+    }
+
+    #[test]
+    fn parse_crc32() {
+        let val = match extract_crc32(DATA) {
+            Ok((_remainder, crc)) => crc,
+            Err(_e) => &[],
+        };
+        assert_eq!(val, &[0x78, 0x56, 0x34, 0x12])
+    }
+
+    #[test]
+    fn get_tlv_digest256() {
+        let remaining = match extract_digest(DATA) {
+            Ok((remainder, _digest)) => remainder,
+            Err(_e) => &[],
+        };
+        let offset = DATA.len() - remaining.len() - (4 + SHA256_DIGEST_SIZE);
+        assert_eq!(offset, 8 + 4 + 12 + 6 + 6)
+    }
+
+    #[test]
+    fn get_tlv_pubkey_digest() {
+        let remaining = match extract_pubkey_digest(DATA) {
+            Ok((remainder, _digest)) => remainder,
+            Err(_e) => &[],
+        };
+        let offset = DATA.len() - remaining.len() - (4 + PUBKEY_DIGEST_SIZE);
+        assert_eq!(offset, 8 + 4 + 12 + 6 + 6 + 36)
+    }
+
+    fn header() -> [u8; IMAGE_HEADER_SIZE] {
+        let mut header = [0xffu8; IMAGE_HEADER_SIZE];
+        header[..8].copy_from_slice(&[0x54, 0x53, 0x55, 0x52, 0x00, 0x10, 0x00, 0x00]);
+        header[8..8 + DATA.len()].copy_from_slice(DATA);
+        header
+    }
+
+    #[test]
+    fn parse_header_tlvs() {
+        let header = header();
+        assert_eq!(
+            parse_header_tlv(&header, Tags::Version).unwrap(),
+            &[0x01, 0x02, 0x03, 0x04]
+        );
+        assert_eq!(
+            parse_header_tlv(&header, Tags::ImgType).unwrap(),
+            &[0x02, 0x00]
+        );
+        assert_eq!(
+            get_header_tlv_offset(&header, Tags::Digest256).unwrap(),
+            8 + 8 + 4 + 12 + 6 + 6
+        );
+        assert_eq!(
+            get_header_tlv_offset(&header, Tags::Signature).unwrap(),
+            8 + 8 + 4 + 12 + 6 + 6 + 36 + 36
+        );
+    }
+
+    #[test]
+    fn parse_vendor_tlvs() {
+        let mut header = header();
+        assert_eq!(vendor_tlvs(&header).unwrap().count(), 0);
+
+        #[rustfmt::skip]
+        let tlvs = [
+            0x01, 0x80, 0x03, 0x00, 0xaa, 0xbb, 0xcc, // type 0x8001, 3-byte value
+            0x02, 0x80, 0x00, 0x00,                   // type 0x8002, no value
+        ];
+        header[HDR_VENDOR_TLVS..HDR_VENDOR_TLVS + tlvs.len()].copy_from_slice(&tlvs);
+        // an erased field ends the vendor TLVs, as does the `end of header`
+        let parsed = vendor_tlvs(&header).unwrap();
+        assert_eq!(parsed.as_bytes(), &tlvs);
+        assert!(parsed.eq([
+            VendorTlv {
+                typ: 0x8001,
+                value: &[0xaa, 0xbb, 0xcc],
+            },
+            VendorTlv {
+                typ: 0x8002,
+                value: &[],
+            },
+        ]));
+
+        // not a vendor type
+        let mut bad_type = header;
+        bad_type[HDR_VENDOR_TLVS + 1] = 0x00;
+        assert_eq!(vendor_tlvs(&bad_type), Err(RustbootError::InvalidValue));
+        // a value that runs past the end of the header
+        let mut bad_len = header;
+        bad_len[HDR_VENDOR_TLVS + 2] = 0x40;
+        assert_eq!(
+            vendor_tlvs(&bad_len),
+            Err(RustbootError::InvalidHdrFieldLength)
+        );
+    }
+
+    #[test]
+    fn board_ids() {
+        // FNV-1a test vectors
+        assert_eq!(board_id(""), 0x811c9dc5u32.to_le_bytes());
+        assert_eq!(board_id("a"), 0xe40c292cu32.to_le_bytes());
+        assert_ne!(board_id("stm32f411"), board_id("stm32f446"));
+
+        let mut header = header();
+        assert_eq!(vendor_tlvs(&header).unwrap().board_id(), Ok(None));
+        let id = board_id("stm32f411");
+        #[rustfmt::skip]
+        let tlvs = [
+            0x01, 0x80, 0x01, 0x00, 0xaa,                 // type 0x8001, 1-byte value
+            0xfe, 0xff, 0x04, 0x00, id[0], id[1], id[2], id[3], // board-ID
+        ];
+        header[HDR_VENDOR_TLVS..HDR_VENDOR_TLVS + tlvs.len()].copy_from_slice(&tlvs);
+        assert_eq!(vendor_tlvs(&header).unwrap().board_id(), Ok(Some(id)));
+        // a board id of the wrong length
+        header[HDR_VENDOR_TLVS + 5..HDR_VENDOR_TLVS + 11]
+            .copy_from_slice(&[0xfe, 0xff, 0x02, 0x00, 0xaa, 0xbb]);
+        header[HDR_VENDOR_TLVS + 11..HDR_VENDOR_TLVS + 13].fill(0xff);
+        assert_eq!(
+            vendor_tlvs(&header).unwrap().board_id(),
+            Err(RustbootError::InvalidHdrFieldLength)
+        );
+    }
+
+    #[test]
+    fn compressed_lens() {
+        let mut header = header();
+        assert_eq!(vendor_tlvs(&header).unwrap().compressed_len(), Ok(None));
+        #[rustfmt::skip]
+        let tlvs = [
+            0xfc, 0xff, 0x06, 0x00, 0x01, 0x00, 0x34, 0x12, 0x00, 0x00, // lz4, 0x1234 bytes
+        ];
+        header[HDR_VENDOR_TLVS..HDR_VENDOR_TLVS + tlvs.len()].copy_from_slice(&tlvs);
+        assert_eq!(
+            vendor_tlvs(&header).unwrap().compressed_len(),
+            Ok(Some(0x1234))
+        );
+        // an unknown algorithm
+        header[HDR_VENDOR_TLVS + 4] = 0x02;
+        assert_eq!(
+            vendor_tlvs(&header).unwrap().compressed_len(),
+            Err(RustbootError::InvalidValue)
+        );
+        // a value of the wrong length
+        header[HDR_VENDOR_TLVS + 2] = 0x02;
+        header[HDR_VENDOR_TLVS + 6..HDR_VENDOR_TLVS + 10].fill(0xff);
+        assert_eq!(
+            vendor_tlvs(&header).unwrap().compressed_len(),
+            Err(RustbootError::InvalidHdrFieldLength)
+        );
+    }
+
+    #[test]
+    fn version_strings() {
+        let mut header = header();
+        assert_eq!(vendor_tlvs(&header).unwrap().version_string(), Ok(None));
+        let tlvs = [&[0xfb, 0xff, 0x06, 0x00][..], b"v1.2.3"].concat();
+        header[HDR_VENDOR_TLVS..HDR_VENDOR_TLVS + tlvs.len()].copy_from_slice(&tlvs);
+        assert_eq!(
+            vendor_tlvs(&header).unwrap().version_string(),
+            Ok(Some("v1.2.3"))
+        );
+        // not UTF-8
+        header[HDR_VENDOR_TLVS + 4] = 0xff;
+        assert_eq!(
+            vendor_tlvs(&header).unwrap().version_string(),
+            Err(RustbootError::InvalidValue)
+        );
+        // too long
+        let tlvs = [&[0xfb, 0xff, 0x21, 0x00][..], &[b'v'; 0x21]].concat();
+        header[HDR_VENDOR_TLVS..HDR_VENDOR_TLVS + tlvs.len()].copy_from_slice(&tlvs);
+        assert_eq!(
+            vendor_tlvs(&header).unwrap().version_string(),
+            Err(RustbootError::InvalidHdrFieldLength)
+        );
+    }
+
+    #[test]
+    fn malformed_headers_are_errors() {
+        let header = header();
+        assert_eq!(
+            parse_header_tlv(&header[..IMAGE_HEADER_SIZE - 1], Tags::Version),
+            Err(RustbootError::InvalidHdrFieldLength)
+        );
+        assert_eq!(
+            parse_header_tlv(&header, Tags::EndOfHeader),
+            Err(RustbootError::TLVNotFound)
+        );
+        // only a sha256 digest is present
+        assert_eq!(
+            parse_header_tlv(&header, Tags::Digest384),
+            Err(RustbootError::TLVNotFound)
+        );
+        // a digest length that runs past the end of the header
+        let mut bad_len = header;
+        bad_len[8 + 36 + 2..8 + 36 + 4].copy_from_slice(&[0xff, 0xff]);
+        assert_eq!(
+            parse_header_tlv(&bad_len, Tags::Signature),
+            Err(RustbootError::InvalidValue)
+        );
+        // the crc is optional i.e. images signed without one end after the signature
+        let mut no_crc = header;
+        no_crc[8 + DATA.len() - 10..8 + DATA.len()].fill(0xff);
+        no_crc[8 + DATA.len() - 10..8 + DATA.len() - 8].copy_from_slice(&[0x00, 0x00]);
+        assert!(parse_header_tlv(&no_crc, Tags::Signature).is_ok());
+        assert_eq!(
+            parse_header_tlv(&no_crc, Tags::Crc32),
+            Err(RustbootError::InvalidValue)
+        );
+        // an all-padding header
+        assert_eq!(
+            parse_header_tlv(&[0xff; IMAGE_HEADER_SIZE], Tags::Signature),
+            Err(RustbootError::InvalidValue)
+        );
+    }
+}
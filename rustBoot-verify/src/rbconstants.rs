@@ -31,14 +31,35 @@ pub const PUBKEY_DIGEST_SIZE: usize = 32;
 #[cfg(feature = "sha384")]
 pub const PUBKEY_DIGEST_SIZE: usize = 48;
 
-// NVM_FLASH_WRITEONCE
-#[cfg(feature = "ext_flash")]
-pub const FLASHBUFFER_SIZE: usize = SECTOR_SIZE;
-pub const FLASHBUFFER_SIZE: usize = IMAGE_HEADER_SIZE;
-
 /* Signature Config */
 pub const ECC_SIGNATURE_SIZE: usize = 64;
 
+/* Integrity pre-check Config */
+pub const HDR_CRC32: u16 = 0x0030;
+pub const HDR_CRC32_LEN: usize = 0x4;
+
+/* Vendor TLVs Config */
+// vendor TLVs follow the `CRC32` TLV, up to the end of the header
+pub const HDR_VENDOR_TLVS: usize = 0xC0;
+pub const HDR_VENDOR_TYPE_MIN: u16 = 0x8000;
+// the board-ID TLV, a vendor TLV type reserved by rustBoot (see `parser::board_id`)
+pub const HDR_BOARD_ID: u16 = 0xFFFE;
+pub const HDR_BOARD_ID_LEN: usize = 0x4;
+// the signing-certificate TLV, a vendor TLV type reserved by rustBoot (see `cert`). Its value is
+// the length of the certificate appended to the firmware.
+pub const HDR_SIGNING_CERT: u16 = 0xFFFD;
+pub const SIGNING_CERT_SIZE: usize = 152;
+// the compression TLV, a vendor TLV type reserved by rustBoot (see `lz4`). Its value is the
+// compression algorithm, a reserved byte and the compressed firmware's length (LE).
+pub const HDR_COMPRESSION: u16 = 0xFFFC;
+pub const HDR_COMPRESSION_LEN: usize = 0x6;
+pub const COMPRESSION_LZ4: u8 = 0x01;
+// the version-string TLV, a vendor TLV type reserved by rustBoot (see
+// `parser::VendorTlvs::version_string`). Its value is the image's human-readable version (ex:
+// `v1.2.3-4-gdeadbee`), in UTF-8.
+pub const HDR_VERSION_STRING: u16 = 0xFFFB;
+pub const HDR_VERSION_STRING_MAX_LEN: usize = 0x20;
+
 #[derive(Clone, Copy)]
 /// Each variant in [`Tags`] represents a field in the image-header.
 ///
@@ -52,6 +73,7 @@ pub enum Tags {
     Digest384,
     PubkeyDigest,
     Signature,
+    Crc32,
     EndOfHeader,
 }
 
@@ -67,6 +89,7 @@ impl Tags {
             Self::Digest384     => &[0x13, 0x00],
             Self::PubkeyDigest  => &[0x10, 0x00],
             Self::Signature     => &[0x20, 0x00],
+            Self::Crc32         => &[0x30, 0x00],
             Self::EndOfHeader   => &[0x00, 0x00],
         }
     }
@@ -0,0 +1,188 @@
+//! Release manifests i.e. a signed list of the images (digest, version and target board) a
+//! fleet's devices may install.
+//!
+//! An image's signature only says who built it. A release manifest, signed with the same key,
+//! lets a fleet operator authorize specific image and board combinations centrally (ex: roll a
+//! release out to a single hardware revision). An OTA agent checks an offered update against
+//! the manifest before it stages the update and then checks the staged bytes against the
+//! manifest's digest.
+//!
+//! Manifests are produced by `rbsigner release-manifest`. All fields are little-endian
+//!
+//! ```text
+//! +---------+----------+---------+--------------------------------------+-----------+
+//! | magic   | sequence | count   | entries i.e. count x                 | signature |
+//! | (RBRM)  |          |         | board id (16), version (4), digest   |           |
+//! | 4 bytes | 4 bytes  | 4 bytes | (32)                                 | 64 bytes  |
+//! +---------+----------+---------+--------------------------------------+-----------+
+//! ```
+//!
+//! - a board id is any (ascii) identifier of up to 16 bytes, padded with `0x00`.
+//! - a digest is the SHA-256 digest of the whole signed image i.e. its header and firmware.
+//! - the signature is a nistp256 signature over everything before it.
+
+use core::convert::TryInto;
+
+use crate::crypto::signatures::{verify_ecc256_signature, HDR_IMG_TYPE_AUTH};
+use crate::rbconstants::{ECC_SIGNATURE_SIZE, SHA256_DIGEST_SIZE};
+use crate::{Result, RustbootError};
+
+use p256::ecdsa::signature::digest::Digest;
+use sha2::Sha256;
+
+pub const RELEASE_MAGIC: u32 = 0x4d524252; // RBRM
+pub const BOARD_ID_LEN: usize = 16;
+pub const RELEASE_HEADER_LEN: usize = 12;
+pub const RELEASE_ENTRY_LEN: usize = BOARD_ID_LEN + 4 + SHA256_DIGEST_SIZE;
+
+/// A release manifest, whose signature checked out.
+#[derive(Debug, Clone, Copy)]
+pub struct ReleaseManifest<'a> {
+    sequence: u32,
+    entries: &'a [u8],
+}
+
+/// An image authorized by a release manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReleaseEntry<'a> {
+    /// the board id, without its padding.
+    pub board_id: &'a [u8],
+    pub version: u32,
+    /// the SHA-256 digest of the signed image.
+    pub digest: &'a [u8; SHA256_DIGEST_SIZE],
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    bytes
+        .get(offset..offset + 4)
+        .and_then(|field| field.try_into().ok())
+        .map_or(0, u32::from_le_bytes)
+}
+
+impl<'a> ReleaseManifest<'a> {
+    /// Parses a release manifest and verifies its signature, with the embedded public key.
+    /// Returns [`RustbootError::InvalidImage`] if it's malformed and
+    /// [`RustbootError::FwAuthFailed`] if its signature doesn't check out.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < RELEASE_HEADER_LEN + ECC_SIGNATURE_SIZE {
+            return Err(RustbootError::InvalidImage);
+        }
+        let (signed, signature) = bytes.split_at(bytes.len() - ECC_SIGNATURE_SIZE);
+        let count = u32_at(signed, 8) as usize;
+        let entries = signed
+            .get(RELEASE_HEADER_LEN..)
+            .ok_or(RustbootError::InvalidImage)?;
+        if u32_at(signed, 0) != RELEASE_MAGIC
+            || count.checked_mul(RELEASE_ENTRY_LEN) != Some(entries.len())
+        {
+            return Err(RustbootError::InvalidImage);
+        }
+        verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(
+            Sha256::new().chain(signed),
+            signature,
+        )?;
+        Ok(ReleaseManifest {
+            sequence: u32_at(signed, 4),
+            entries,
+        })
+    }
+
+    /// The manifest's sequence number i.e. a newer manifest has a higher one.
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = ReleaseEntry<'a>> {
+        // entries are `RELEASE_ENTRY_LEN` bytes long, so none is skipped
+        self.entries
+            .chunks_exact(RELEASE_ENTRY_LEN)
+            .filter_map(|entry| {
+                let (board_id, rest) = entry.split_at(BOARD_ID_LEN);
+                let len = board_id
+                    .iter()
+                    .position(|byte| *byte == 0)
+                    .unwrap_or(BOARD_ID_LEN);
+                Some(ReleaseEntry {
+                    board_id: board_id.get(..len)?,
+                    version: u32_at(rest, 0),
+                    digest: rest.get(4..)?.try_into().ok()?,
+                })
+            })
+    }
+
+    /// Returns the entry authorizing `version` of the image for `board_id`, if there's one.
+    pub fn find(&self, board_id: &[u8], version: u32) -> Option<ReleaseEntry<'a>> {
+        self.entries()
+            .find(|entry| entry.board_id == board_id && entry.version == version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+
+    /// The signing key matching the embedded public key.
+    const SK_BYTES: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    fn release(entries: &[(&[u8], u32, [u8; 32])]) -> Vec<u8> {
+        let mut manifest = Vec::new();
+        manifest.extend_from_slice(&RELEASE_MAGIC.to_le_bytes());
+        manifest.extend_from_slice(&7u32.to_le_bytes());
+        manifest.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (board_id, version, digest) in entries {
+            let mut id = [0u8; BOARD_ID_LEN];
+            id[..board_id.len()].copy_from_slice(board_id);
+            manifest.extend_from_slice(&id);
+            manifest.extend_from_slice(&version.to_le_bytes());
+            manifest.extend_from_slice(digest);
+        }
+        let sk = SigningKey::from_bytes(&SK_BYTES).unwrap();
+        let signature: Signature = sk.sign_digest(Sha256::new().chain(&manifest));
+        manifest.extend_from_slice(signature.as_ref());
+        manifest
+    }
+
+    #[test]
+    fn authorized_images() {
+        let manifest = release(&[
+            (b"nrf52840", 1235, [0xaa; 32]),
+            (b"stm32f411", 1235, [0xbb; 32]),
+        ]);
+        let release = ReleaseManifest::parse(&manifest).unwrap();
+        assert_eq!(release.sequence(), 7);
+        assert_eq!(release.entries().count(), 2);
+        let entry = release.find(b"stm32f411", 1235).unwrap();
+        assert_eq!(entry.digest, &[0xbb; 32]);
+        // not released for that board or in that version
+        assert!(release.find(b"stm32f446", 1235).is_none());
+        assert!(release.find(b"nrf52840", 1236).is_none());
+        assert!(release.find(b"nrf5284", 1235).is_none());
+    }
+
+    #[test]
+    fn rejects_tampered_manifests() {
+        let manifest = release(&[(b"nrf52840", 1235, [0xaa; 32])]);
+        let mut tampered = manifest.clone();
+        tampered[RELEASE_HEADER_LEN + BOARD_ID_LEN] ^= 1;
+        assert_eq!(
+            ReleaseManifest::parse(&tampered).unwrap_err(),
+            RustbootError::FwAuthFailed
+        );
+        // truncated or miscounted entries
+        let mut miscounted = manifest.clone();
+        miscounted[8] = 2;
+        assert_eq!(
+            ReleaseManifest::parse(&miscounted).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+        assert_eq!(
+            ReleaseManifest::parse(&manifest[..40]).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+    }
+}
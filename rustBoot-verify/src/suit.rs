@@ -0,0 +1,618 @@
+//! SUIT (Software Updates for Internet of Things) manifests - an alternative to rustBoot's
+//! TLV image header.
+//!
+//! A SUIT envelope (`draft-ietf-suit-manifest`) carries a manifest (sequence number, component
+//! identifiers, the payload's digest and size etc.) and an authentication wrapper i.e. the
+//! manifest's digest and a `COSE_Sign1` signature over it. This module implements a `no_std`
+//! parser for the subset of SUIT used by rustBoot:
+//!
+//! - a single component, with its parameters set via `directive-override-parameters` (or
+//!   `directive-set-parameters`) in the shared sequence.
+//! - `SHA-256` digests and `ES256` (i.e. nistp256) signatures.
+//! - detached payloads i.e. the firmware isn't part of the envelope. Integrated payloads and
+//!   severable members are skipped.
+//!
+//! *Note: as per the SUIT spec, the manifest's digest is computed over the bstr-wrapped manifest
+//! and the signature's (detached) payload is the bstr-wrapped `SUIT_Digest`.*
+
+use core::convert::TryFrom;
+
+use crate::crypto::signatures::{verify_ecc256_signature, HDR_IMG_TYPE_AUTH};
+use crate::{Result, RustbootError};
+
+use minicbor::data::{Tag, Type};
+use minicbor::decode::{self, Decoder};
+use minicbor::encode::{Encoder, Write};
+use p256::ecdsa::signature::digest::Digest;
+use sha2::Sha256;
+
+/// CBOR tag of a `SUIT_Envelope_Tagged`.
+pub const SUIT_ENVELOPE_TAG: u64 = 107;
+/// CBOR tag of a `COSE_Sign1_Tagged`.
+const COSE_SIGN1_TAG: u64 = 18;
+
+// SUIT_Envelope
+const SUIT_AUTHENTICATION_WRAPPER: i64 = 2;
+const SUIT_MANIFEST: i64 = 3;
+// SUIT_Manifest
+const SUIT_MANIFEST_VERSION: i64 = 1;
+const SUIT_MANIFEST_SEQUENCE_NUMBER: i64 = 2;
+const SUIT_COMMON: i64 = 3;
+// SUIT_Common
+const SUIT_COMPONENTS: i64 = 2;
+const SUIT_SHARED_SEQUENCE: i64 = 4;
+// SUIT_Directive
+const SUIT_DIRECTIVE_SET_PARAMETERS: i64 = 19;
+const SUIT_DIRECTIVE_OVERRIDE_PARAMETERS: i64 = 20;
+// SUIT_Parameters
+const SUIT_PARAMETER_VENDOR_IDENTIFIER: i64 = 1;
+const SUIT_PARAMETER_CLASS_IDENTIFIER: i64 = 2;
+const SUIT_PARAMETER_IMAGE_DIGEST: i64 = 3;
+const SUIT_PARAMETER_IMAGE_SIZE: i64 = 14;
+
+/// `COSE` algorithm ids
+const COSE_ALG_SHA256: i64 = -16;
+const COSE_ALG_ES256: i64 = -7;
+const COSE_HEADER_ALG: i64 = 1;
+
+/// The component identifier of an application image i.e. `[h'00']`.
+pub const APP_COMPONENT: &[&[u8]] = &[&[0x00]];
+
+impl From<decode::Error> for RustbootError {
+    fn from(_: decode::Error) -> Self {
+        RustbootError::InvalidImage
+    }
+}
+
+/// A (parsed but not yet verified) SUIT envelope.
+#[derive(Debug, Clone, Copy)]
+pub struct SuitEnvelope<'a> {
+    authentication: &'a [u8],
+    /// the bstr-wrapped manifest
+    manifest: &'a [u8],
+}
+
+impl<'a> SuitEnvelope<'a> {
+    /// Returns true if `bytes` start with a tagged SUIT envelope.
+    pub fn is_envelope(bytes: &[u8]) -> bool {
+        let mut d = Decoder::new(bytes);
+        matches!(d.tag(), Ok(Tag::Unassigned(SUIT_ENVELOPE_TAG)))
+    }
+
+    /// Parses the SUIT envelope (tagged or untagged) at the start of `bytes`. Any bytes
+    /// following the envelope are ignored.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let mut d = Decoder::new(bytes);
+        if d.datatype()? == Type::Tag && d.tag()? != Tag::Unassigned(SUIT_ENVELOPE_TAG) {
+            return Err(RustbootError::InvalidImage);
+        }
+        let mut authentication = None;
+        let mut manifest = None;
+        for_each_entry(&mut d, |key, d| {
+            match key {
+                SUIT_AUTHENTICATION_WRAPPER => authentication = Some(d.bytes()?),
+                SUIT_MANIFEST => {
+                    let start = d.position();
+                    d.bytes()?;
+                    manifest = Some(
+                        bytes
+                            .get(start..d.position())
+                            .ok_or(RustbootError::InvalidImage)?,
+                    );
+                }
+                _ => d.skip()?,
+            }
+            Ok(())
+        })?;
+        match (authentication, manifest) {
+            (Some(authentication), Some(manifest)) => Ok(SuitEnvelope {
+                authentication,
+                manifest,
+            }),
+            _ => Err(RustbootError::InvalidImage),
+        }
+    }
+
+    /// Parses the envelope's manifest, **without** authenticating it. See [`Self::verify`].
+    pub fn manifest(&self) -> Result<SuitManifest<'a>> {
+        SuitManifest::parse(Decoder::new(self.manifest).bytes()?)
+    }
+
+    /// Authenticates the envelope's manifest i.e. checks the manifest's digest and the digest's
+    /// signature (against rustBoot's embedded public key). Returns the manifest if successful.
+    pub fn verify(&self) -> Result<SuitManifest<'a>> {
+        let mut d = Decoder::new(self.authentication);
+        let blocks = d.array()?;
+        // the first element is the manifest's digest, followed by (at least one) signature.
+        if blocks.is_some_and(|len| len < 2) {
+            return Err(RustbootError::InvalidImage);
+        }
+        let digest = d.bytes()?;
+        let stored_hash = parse_digest(digest)?;
+        if Sha256::digest(self.manifest).as_slice() != stored_hash {
+            return Err(RustbootError::IntegrityCheckFailed);
+        }
+        verify_cose_sign1(d.bytes()?, digest)?;
+        self.manifest()
+    }
+}
+
+/// A SUIT manifest.
+#[derive(Debug, Clone, Copy)]
+pub struct SuitManifest<'a> {
+    sequence_number: u64,
+    /// the encoded `SUIT_Component_Identifier`
+    component: &'a [u8],
+    vendor_id: Option<&'a [u8]>,
+    class_id: Option<&'a [u8]>,
+    image_digest: Option<&'a [u8]>,
+    image_size: Option<usize>,
+}
+
+impl<'a> SuitManifest<'a> {
+    fn parse(manifest: &'a [u8]) -> Result<Self> {
+        let mut d = Decoder::new(manifest);
+        let mut version = None;
+        let mut sequence_number = None;
+        let mut common = None;
+        for_each_entry(&mut d, |key, d| {
+            match key {
+                SUIT_MANIFEST_VERSION => version = Some(d.u32()?),
+                SUIT_MANIFEST_SEQUENCE_NUMBER => sequence_number = Some(d.u64()?),
+                SUIT_COMMON => common = Some(d.bytes()?),
+                _ => d.skip()?,
+            }
+            Ok(())
+        })?;
+        let (sequence_number, common) = match (version, sequence_number, common) {
+            (Some(1), Some(sequence_number), Some(common)) => (sequence_number, common),
+            _ => return Err(RustbootError::InvalidImage),
+        };
+        let mut manifest = SuitManifest {
+            sequence_number,
+            component: &[],
+            vendor_id: None,
+            class_id: None,
+            image_digest: None,
+            image_size: None,
+        };
+        let mut shared_sequence = None;
+        let mut d = Decoder::new(common);
+        for_each_entry(&mut d, |key, d| {
+            match key {
+                SUIT_COMPONENTS => {
+                    // only a single component is supported
+                    if d.array()? != Some(1) {
+                        return Err(RustbootError::InvalidImage);
+                    }
+                    let start = d.position();
+                    d.skip()?;
+                    manifest.component = common
+                        .get(start..d.position())
+                        .ok_or(RustbootError::InvalidImage)?;
+                }
+                SUIT_SHARED_SEQUENCE => shared_sequence = Some(d.bytes()?),
+                _ => d.skip()?,
+            }
+            Ok(())
+        })?;
+        if manifest.component.is_empty() {
+            return Err(RustbootError::InvalidImage);
+        }
+        if let Some(sequence) = shared_sequence {
+            manifest.parse_shared_sequence(sequence)?;
+        }
+        Ok(manifest)
+    }
+
+    /// Collects the parameters set by the shared sequence, all other commands are skipped.
+    fn parse_shared_sequence(&mut self, sequence: &'a [u8]) -> Result<()> {
+        let mut d = Decoder::new(sequence);
+        let len = d.array()?.ok_or(RustbootError::InvalidImage)?;
+        // a sequence of `command-id, command-argument` pairs
+        if len % 2 != 0 {
+            return Err(RustbootError::InvalidImage);
+        }
+        for _ in 0..len / 2 {
+            match d.i64()? {
+                SUIT_DIRECTIVE_SET_PARAMETERS | SUIT_DIRECTIVE_OVERRIDE_PARAMETERS => {
+                    for_each_entry(&mut d, |key, d| {
+                        match key {
+                            SUIT_PARAMETER_VENDOR_IDENTIFIER => self.vendor_id = Some(d.bytes()?),
+                            SUIT_PARAMETER_CLASS_IDENTIFIER => self.class_id = Some(d.bytes()?),
+                            SUIT_PARAMETER_IMAGE_DIGEST => self.image_digest = Some(d.bytes()?),
+                            SUIT_PARAMETER_IMAGE_SIZE => {
+                                let size = usize::try_from(d.u64()?)
+                                    .map_err(|_| RustbootError::InvalidFirmwareSize)?;
+                                self.image_size = Some(size)
+                            }
+                            _ => d.skip()?,
+                        }
+                        Ok(())
+                    })?;
+                }
+                _ => d.skip()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The manifest's sequence number i.e. its version.
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    /// Returns the sequence number as a rustBoot (4-byte) firmware version.
+    pub fn get_firmware_version(&self) -> Result<u32> {
+        u32::try_from(self.sequence_number).map_err(|_| RustbootError::BadVersion)
+    }
+
+    /// Returns true if the manifest's component identifier is `id`.
+    pub fn has_component(&self, id: &[&[u8]]) -> bool {
+        let mut d = Decoder::new(self.component);
+        if d.array().ok() != Some(Some(id.len() as u64)) {
+            return false;
+        }
+        id.iter().all(|part| d.bytes().ok() == Some(*part))
+    }
+
+    pub fn vendor_id(&self) -> Option<&'a [u8]> {
+        self.vendor_id
+    }
+
+    pub fn class_id(&self) -> Option<&'a [u8]> {
+        self.class_id
+    }
+
+    pub fn image_size(&self) -> Option<usize> {
+        self.image_size
+    }
+
+    /// Returns the payload's (SHA-256) digest.
+    pub fn image_digest(&self) -> Result<&'a [u8]> {
+        parse_digest(self.image_digest.ok_or(RustbootError::FieldNotSet)?)
+    }
+
+    /// Checks `payload` against the manifest's image size and digest.
+    pub fn verify_payload(&self, payload: &[u8]) -> Result<()> {
+        if self.image_size != Some(payload.len()) {
+            return Err(RustbootError::InvalidFirmwareSize);
+        }
+        if Sha256::digest(payload).as_slice() != self.image_digest()? {
+            return Err(RustbootError::IntegrityCheckFailed);
+        }
+        Ok(())
+    }
+}
+
+/// Parses an encoded `SUIT_Digest`, returns the digest bytes. Only `SHA-256` is supported.
+fn parse_digest(digest: &[u8]) -> Result<&[u8]> {
+    let mut d = Decoder::new(digest);
+    if d.array()? != Some(2) {
+        return Err(RustbootError::InvalidImage);
+    }
+    if d.i64()? != COSE_ALG_SHA256 {
+        return Err(RustbootError::InvalidValue);
+    }
+    let bytes = d.bytes()?;
+    if bytes.len() != 32 {
+        return Err(RustbootError::BadHashValue);
+    }
+    Ok(bytes)
+}
+
+/// Verifies an (`ES256`) `COSE_Sign1` authentication block, whose detached payload is `digest`
+/// i.e. the bstr-wrapped `SUIT_Digest`.
+fn verify_cose_sign1(block: &[u8], digest: &[u8]) -> Result<bool> {
+    let mut d = Decoder::new(block);
+    if d.datatype()? == Type::Tag && d.tag()? != Tag::Unassigned(COSE_SIGN1_TAG) {
+        return Err(RustbootError::InvalidImage);
+    }
+    if d.array()? != Some(4) {
+        return Err(RustbootError::InvalidImage);
+    }
+    let protected = d.bytes()?;
+    let mut alg = None;
+    for_each_entry(&mut Decoder::new(protected), |key, d| {
+        match key {
+            COSE_HEADER_ALG => alg = Some(d.i64()?),
+            _ => d.skip()?,
+        }
+        Ok(())
+    })?;
+    if alg != Some(COSE_ALG_ES256) {
+        return Err(RustbootError::InvalidValue);
+    }
+    // unprotected header
+    d.skip()?;
+    // the payload is detached
+    if d.datatype()? != Type::Null {
+        return Err(RustbootError::InvalidImage);
+    }
+    d.skip()?;
+    let signature = d.bytes()?;
+
+    // Sig_structure = ["Signature1", protected, external_aad, payload]
+    let mut hasher = Sha256::new();
+    Encoder::new(DigestWriter(&mut hasher))
+        .array(4)
+        .and_then(|e| e.str("Signature1"))
+        .and_then(|e| e.bytes(protected))
+        .and_then(|e| e.bytes(&[]))
+        .and_then(|e| e.bytes(digest))
+        .map_err(|_| RustbootError::Unreachable)?;
+    verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(hasher, signature)
+}
+
+/// Calls `f` with each integer key of the CBOR map at the decoder's position, `f` must consume
+/// the value. Entries with text keys (ex: integrated payloads) are skipped.
+fn for_each_entry<'b>(
+    d: &mut Decoder<'b>,
+    mut f: impl FnMut(i64, &mut Decoder<'b>) -> Result<()>,
+) -> Result<()> {
+    let len = d.map()?;
+    let mut entries = 0;
+    loop {
+        match len {
+            Some(len) if entries == len => break,
+            None if d.datatype()? == Type::Break => {
+                d.skip()?;
+                break;
+            }
+            _ => {}
+        }
+        match d.datatype()? {
+            Type::String | Type::StringIndef => {
+                d.skip()?;
+                d.skip()?;
+            }
+            _ => {
+                let key = d.i64()?;
+                f(key, d)?
+            }
+        }
+        entries += 1;
+    }
+    Ok(())
+}
+
+/// Feeds CBOR-encoded data to a hasher.
+struct DigestWriter<'d, D>(&'d mut D);
+
+impl<'d, D: Digest> Write for DigestWriter<'d, D> {
+    type Error = core::convert::Infallible;
+
+    fn write_all(&mut self, buf: &[u8]) -> core::result::Result<(), Self::Error> {
+        self.0.update(buf);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minicbor::encode::write::Cursor;
+    use p256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+
+    const FIRMWARE: &[u8] = &[0xaa; 100];
+    /// the signing key matching the embedded public key i.e. `boards/sign_images/keygen/ecc256.der`
+    const SIGNING_KEY: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    /// Encodes a manifest for `FIRMWARE`, returns its length.
+    fn manifest(buf: &mut [u8], sequence_number: u64) -> usize {
+        let mut digest = [0u8; 64];
+        let digest_len = suit_digest(&mut digest, &Sha256::digest(FIRMWARE));
+        let mut sequence = [0u8; 128];
+        let mut e = Encoder::new(Cursor::new(&mut sequence[..]));
+        e.array(4)
+            .unwrap()
+            .i64(SUIT_DIRECTIVE_OVERRIDE_PARAMETERS)
+            .unwrap()
+            .map(2)
+            .unwrap()
+            .i64(SUIT_PARAMETER_IMAGE_DIGEST)
+            .unwrap()
+            .bytes(&digest[..digest_len])
+            .unwrap()
+            .i64(SUIT_PARAMETER_IMAGE_SIZE)
+            .unwrap()
+            .u64(FIRMWARE.len() as u64)
+            .unwrap()
+            // condition-image-match
+            .u8(3)
+            .unwrap()
+            .u8(15)
+            .unwrap();
+        let sequence_len = e.writer().position();
+        let mut common = [0u8; 192];
+        let mut e = Encoder::new(Cursor::new(&mut common[..]));
+        e.map(2)
+            .unwrap()
+            .i64(SUIT_COMPONENTS)
+            .unwrap()
+            .array(1)
+            .unwrap()
+            .array(1)
+            .unwrap()
+            .bytes(&[0x00])
+            .unwrap()
+            .i64(SUIT_SHARED_SEQUENCE)
+            .unwrap()
+            .bytes(&sequence[..sequence_len])
+            .unwrap();
+        let common_len = e.writer().position();
+        let mut e = Encoder::new(Cursor::new(buf));
+        e.map(3)
+            .unwrap()
+            .i64(SUIT_MANIFEST_VERSION)
+            .unwrap()
+            .u8(1)
+            .unwrap()
+            .i64(SUIT_MANIFEST_SEQUENCE_NUMBER)
+            .unwrap()
+            .u64(sequence_number)
+            .unwrap()
+            .i64(SUIT_COMMON)
+            .unwrap()
+            .bytes(&common[..common_len])
+            .unwrap();
+        e.writer().position()
+    }
+
+    fn suit_digest(buf: &mut [u8], digest: &[u8]) -> usize {
+        let mut e = Encoder::new(Cursor::new(buf));
+        e.array(2)
+            .unwrap()
+            .i64(COSE_ALG_SHA256)
+            .unwrap()
+            .bytes(digest)
+            .unwrap();
+        e.writer().position()
+    }
+
+    /// Encodes a tagged envelope, returns its length. Unless `sign` is set, the envelope carries
+    /// a made-up signature.
+    fn envelope(buf: &mut [u8], manifest: &[u8], sign: bool) -> usize {
+        // the digest covers the bstr-wrapped manifest
+        let mut wrapped = [0u8; 256];
+        let mut e = Encoder::new(Cursor::new(&mut wrapped[..]));
+        e.bytes(manifest).unwrap();
+        let wrapped_len = e.writer().position();
+        let mut digest = [0u8; 64];
+        let digest_len = suit_digest(&mut digest, &Sha256::digest(&wrapped[..wrapped_len]));
+        let protected = [0xa1, 0x01, 0x26];
+        let mut signature = [0x55; 64];
+        if sign {
+            let mut hasher = Sha256::new();
+            Encoder::new(DigestWriter(&mut hasher))
+                .array(4)
+                .unwrap()
+                .str("Signature1")
+                .unwrap()
+                .bytes(&protected)
+                .unwrap()
+                .bytes(&[])
+                .unwrap()
+                .bytes(&digest[..digest_len])
+                .unwrap();
+            let sk = SigningKey::from_bytes(&SIGNING_KEY).unwrap();
+            let sig: Signature = sk.sign_digest(hasher);
+            signature.copy_from_slice(sig.as_ref());
+        }
+        let mut sign1 = [0u8; 128];
+        let mut e = Encoder::new(Cursor::new(&mut sign1[..]));
+        e.tag(Tag::Unassigned(COSE_SIGN1_TAG))
+            .unwrap()
+            .array(4)
+            .unwrap()
+            .bytes(&protected)
+            .unwrap()
+            .map(0)
+            .unwrap()
+            .null()
+            .unwrap()
+            .bytes(&signature)
+            .unwrap();
+        let sign1_len = e.writer().position();
+        let mut auth = [0u8; 256];
+        let mut e = Encoder::new(Cursor::new(&mut auth[..]));
+        e.array(2)
+            .unwrap()
+            .bytes(&digest[..digest_len])
+            .unwrap()
+            .bytes(&sign1[..sign1_len])
+            .unwrap();
+        let auth_len = e.writer().position();
+        let mut e = Encoder::new(Cursor::new(buf));
+        e.tag(Tag::Unassigned(SUIT_ENVELOPE_TAG))
+            .unwrap()
+            .map(2)
+            .unwrap()
+            .i64(SUIT_AUTHENTICATION_WRAPPER)
+            .unwrap()
+            .bytes(&auth[..auth_len])
+            .unwrap()
+            .i64(SUIT_MANIFEST)
+            .unwrap()
+            .bytes(manifest)
+            .unwrap();
+        e.writer().position()
+    }
+
+    #[test]
+    fn parse_envelope() {
+        let mut manifest_buf = [0u8; 256];
+        let manifest_len = manifest(&mut manifest_buf, 7);
+        let mut buf = [0xffu8; 512];
+        envelope(&mut buf, &manifest_buf[..manifest_len], false);
+
+        assert!(SuitEnvelope::is_envelope(&buf));
+        let manifest = SuitEnvelope::parse(&buf).unwrap().manifest().unwrap();
+        assert_eq!(manifest.sequence_number(), 7);
+        assert_eq!(manifest.get_firmware_version().unwrap(), 7);
+        assert!(manifest.has_component(APP_COMPONENT));
+        assert!(!manifest.has_component(&[b"radio"]));
+        assert_eq!(manifest.image_size(), Some(FIRMWARE.len()));
+        assert_eq!(manifest.vendor_id(), None);
+        manifest.verify_payload(FIRMWARE).unwrap();
+        assert_eq!(
+            manifest.verify_payload(&[0xab; 100]).unwrap_err(),
+            RustbootError::IntegrityCheckFailed
+        );
+        assert_eq!(
+            manifest.verify_payload(&FIRMWARE[1..]).unwrap_err(),
+            RustbootError::InvalidFirmwareSize
+        );
+    }
+
+    #[test]
+    fn verify_envelope() {
+        let mut manifest_buf = [0u8; 256];
+        let manifest_len = manifest(&mut manifest_buf, 7);
+        let mut buf = [0xffu8; 512];
+        let len = envelope(&mut buf, &manifest_buf[..manifest_len], false);
+        // the manifest digest matches, the made-up signature doesn't
+        let err = SuitEnvelope::parse(&buf).unwrap().verify().unwrap_err();
+        assert_ne!(err, RustbootError::IntegrityCheckFailed);
+        let mut signed = [0xffu8; 512];
+        envelope(&mut signed, &manifest_buf[..manifest_len], true);
+        let manifest = SuitEnvelope::parse(&signed).unwrap().verify().unwrap();
+        assert_eq!(manifest.sequence_number(), 7);
+
+        // tamper with the manifest's sequence number
+        let pos = buf[..len]
+            .windows(2)
+            .rposition(|w| w == [SUIT_MANIFEST_SEQUENCE_NUMBER as u8, 0x07])
+            .unwrap();
+        buf[pos + 1] = 0x08;
+        assert_eq!(
+            SuitEnvelope::parse(&buf).unwrap().verify().unwrap_err(),
+            RustbootError::IntegrityCheckFailed
+        );
+    }
+
+    #[test]
+    fn malformed_envelopes() {
+        assert!(!SuitEnvelope::is_envelope(b"RUST"));
+        assert_eq!(
+            SuitEnvelope::parse(&[0xd8, 0x6b, 0xa0]).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+        assert!(SuitEnvelope::parse(&[0xd8, 0x6b]).is_err());
+        assert!(SuitEnvelope::parse(&[0xff; 16]).is_err());
+
+        let mut manifest_buf = [0u8; 256];
+        let manifest_len = manifest(&mut manifest_buf, 7);
+        let mut buf = [0xffu8; 512];
+        let len = envelope(&mut buf, &manifest_buf[..manifest_len], false);
+        for end in 0..len {
+            assert!(SuitEnvelope::parse(&buf[..end])
+                .and_then(|env| env.manifest())
+                .is_err());
+        }
+    }
+}
@@ -84,7 +84,7 @@ fn main() {
     };
     // `patch_bytes_1_len` includes a `BEGIN_NODE`, we have to subtract it from the new length.
     // i.e. the `chosen` node takes up 12 bytes (0x00000001 + "chosen" + padding)
-    let padded_node_len = get_padded_node_len(&reader, "/chosen");
+    let padded_node_len = get_padded_node_len(&reader, "/chosen").unwrap();
     let new_node_len = patch_bytes_1_len + patch_bytes_2.as_slice().len() - padded_node_len;
     println!(
         "new_node_len: {:?}, padded_node_len: {:?}\n",
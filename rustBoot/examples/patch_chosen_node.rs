@@ -27,7 +27,7 @@ fn main() {
         PropertyValue::U32([0x07, 0x7f, 0x08, 0x4a]),
     ];
     let mut buf = [0; 40000];
-    let (res, len) = patch_chosen_node(reader, dtb_blob, &prop_val_list, &mut buf);
+    let (res, len) = patch_chosen_node(reader, dtb_blob, &prop_val_list, &mut buf).unwrap();
     println!("len: {}", len);
     let patched_dtb_blob = &res[..len];
 
@@ -0,0 +1,47 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustBoot::dt::{get_image_data, parse_algo, parse_fit, prepare_img_hash, Reader};
+use sha2::Sha256;
+
+fuzz_target!(|data: &[u8]| {
+    // `Reader` only accepts 8-byte aligned blobs
+    let mut aligned = vec![0u64; (data.len() + 7) / 8];
+    let blob =
+        unsafe { core::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, data.len()) };
+    blob.copy_from_slice(data);
+    let blob = &*blob;
+
+    if let Ok(reader) = Reader::read(blob) {
+        for _entry in reader.reserved_mem_entries() {}
+        for item in reader.struct_items() {
+            let _ = item.value_str();
+        }
+        let _ = reader
+            .struct_items()
+            .path_struct_items("/configurations")
+            .count();
+        if let Ok(node) = reader.find_node("/configurations") {
+            let _ = node
+                .get_prop_str("default")
+                .and_then(|config| node.find_child(config));
+        }
+        for image in reader
+            .find_node("/images")
+            .iter()
+            .flat_map(|node| node.iter_children())
+        {
+            let _ = image.and_then(|image| {
+                let _ = image.get_prop_u32("load");
+                let _ = image.get_prop_str("compression");
+                image.get_prop("data")
+            });
+        }
+        let _ = parse_fit::<Sha256, 32, 64, 4>(reader);
+    }
+    let _ = parse_algo(blob);
+    let _ = prepare_img_hash::<Sha256, 32, 64, 4>(blob, 0);
+    for img in ["kernel", "fdt", "ramdisk", "rbconfig"].iter() {
+        let _ = get_image_data(blob, img);
+    }
+});
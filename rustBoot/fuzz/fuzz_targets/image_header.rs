@@ -0,0 +1,27 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustBoot::parser::{get_header_tlv_offset, parse_header_tlv, Tags};
+use rustBoot::rbconstants::IMAGE_HEADER_SIZE;
+
+const TAGS: [Tags; 8] = [
+    Tags::Version,
+    Tags::TimeStamp,
+    Tags::ImgType,
+    Tags::Digest256,
+    Tags::Digest384,
+    Tags::PubkeyDigest,
+    Tags::Signature,
+    Tags::EndOfHeader,
+];
+
+fuzz_target!(|header: &[u8]| {
+    for tag in TAGS.iter() {
+        if let Ok(value) = parse_header_tlv(header, *tag) {
+            assert!(value.len() < IMAGE_HEADER_SIZE);
+        }
+        if let Ok(offset) = get_header_tlv_offset(header, *tag) {
+            assert!(offset < IMAGE_HEADER_SIZE);
+        }
+    }
+});
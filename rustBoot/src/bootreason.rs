@@ -0,0 +1,155 @@
+//! The boot reason's format.
+//!
+//! rustBoot records why the mcu booted (i.e. the last reset's cause) and what it did on the way
+//! (i.e. installed an update or rolled one back) in a register that survives the reset into
+//! firmware, see `rustBoot_hal::boot_reason`. Firmware reads it back to tell a watchdog reset from
+//! a power cycle or a first boot after an update, and clears it once it has acted on it.
+//!
+//! A reason fits a byte, as the nrf52840's retained registers are 8-bit:
+//!
+//! ```text
+//!  bits 7..5 | marker (0b101)
+//!  bits 4..3 | boot action (see [`BootAction`])
+//!  bits 2..0 | reset cause (see [`ResetCause`])
+//! ```
+
+const MARKER: u8 = 0b101 << 5;
+const MARKER_MASK: u8 = 0b111 << 5;
+
+/// The last reset's cause, per the mcu's reset flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetCause {
+    /// none of the flags rustBoot knows of were set (ex: a low-power reset).
+    Unknown = 0,
+    /// a power-on (or power-down) reset.
+    PowerOn = 1,
+    /// the reset pin.
+    Pin = 2,
+    /// a software reset i.e. `SYSRESETREQ`.
+    Software = 3,
+    /// an independent or window watchdog.
+    Watchdog = 4,
+    BrownOut = 5,
+    /// the core locked up (nrf52840 only).
+    Lockup = 6,
+}
+
+/// What rustBoot did before booting firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootAction {
+    /// booted the boot partition's image, as it was.
+    None = 0,
+    /// installed (i.e. swapped in) an update, which is booted for testing. It's rolled back on
+    /// the next boot unless it confirms itself.
+    UpdatePending = 1,
+    /// rolled back an update that didn't confirm itself i.e. booted the previous image.
+    RolledBack = 2,
+}
+
+/// Why the mcu booted and what rustBoot did on the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootReason {
+    pub reset: ResetCause,
+    pub action: BootAction,
+}
+
+impl ResetCause {
+    const ALL: [ResetCause; 7] = [
+        ResetCause::Unknown,
+        ResetCause::PowerOn,
+        ResetCause::Pin,
+        ResetCause::Software,
+        ResetCause::Watchdog,
+        ResetCause::BrownOut,
+        ResetCause::Lockup,
+    ];
+
+    /// Decodes an mcu's reset flags. `causes` maps a flag (or flags) to its cause, in order of
+    /// precedence - a reset usually sets more than one flag (ex: a power-on reset also sets the
+    /// pin flag on STM32 parts). Returns the cause of the first entry whose flags are set in
+    /// `flags`, [`ResetCause::Unknown`] if none are.
+    pub fn from_flags(flags: u32, causes: &[(u32, ResetCause)]) -> ResetCause {
+        causes
+            .iter()
+            .find(|(mask, _)| flags & mask != 0)
+            .map(|(_, cause)| *cause)
+            .unwrap_or(ResetCause::Unknown)
+    }
+}
+
+impl BootReason {
+    pub fn to_byte(&self) -> u8 {
+        MARKER | (self.action as u8) << 3 | self.reset as u8
+    }
+
+    /// Decodes a reason. Returns `None` if `byte` doesn't hold one (ex: a register that was
+    /// cleared, or never written).
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        if byte & MARKER_MASK != MARKER {
+            return None;
+        }
+        let action = match (byte >> 3) & 0b11 {
+            0 => BootAction::None,
+            1 => BootAction::UpdatePending,
+            2 => BootAction::RolledBack,
+            _ => return None,
+        };
+        let reset = *ResetCause::ALL.get((byte & 0b111) as usize)?;
+        Some(BootReason { reset, action })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reason_byte() {
+        let reason = BootReason {
+            reset: ResetCause::Watchdog,
+            action: BootAction::RolledBack,
+        };
+        assert_eq!(reason.to_byte(), 0b1011_0100);
+        for reset in ResetCause::ALL {
+            for action in [
+                BootAction::None,
+                BootAction::UpdatePending,
+                BootAction::RolledBack,
+            ] {
+                let reason = BootReason { reset, action };
+                assert_eq!(BootReason::from_byte(reason.to_byte()), Some(reason));
+            }
+        }
+    }
+
+    #[test]
+    fn no_reason() {
+        // cleared, erased, or out of range
+        assert_eq!(BootReason::from_byte(0x00), None);
+        assert_eq!(BootReason::from_byte(0xFF), None);
+        assert_eq!(BootReason::from_byte(0b1011_1000), None);
+        assert_eq!(BootReason::from_byte(0b1010_0111), None);
+    }
+
+    #[test]
+    fn reset_flags() {
+        let causes = [
+            (1 << 29 | 1 << 30, ResetCause::Watchdog),
+            (1 << 28, ResetCause::Software),
+            (1 << 27, ResetCause::PowerOn),
+            (1 << 26, ResetCause::Pin),
+        ];
+        // a power-on reset also sets the pin flag
+        let cause = ResetCause::from_flags(1 << 27 | 1 << 26, &causes);
+        assert_eq!(cause, ResetCause::PowerOn);
+        assert_eq!(
+            ResetCause::from_flags(1 << 30, &causes),
+            ResetCause::Watchdog
+        );
+        assert_eq!(ResetCause::from_flags(1 << 26, &causes), ResetCause::Pin);
+        assert_eq!(
+            ResetCause::from_flags(1 << 31, &causes),
+            ResetCause::Unknown
+        );
+    }
+}
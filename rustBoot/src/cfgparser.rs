@@ -4,7 +4,7 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{digit0, multispace0, multispace1},
-    combinator::opt,
+    combinator::{map_res, opt},
     error::ErrorKind,
     sequence::{preceded, separated_pair, tuple},
     AsChar, IResult, InputTakeAtPosition,
@@ -19,6 +19,7 @@ pub struct ActiveConf<'a> {
     active_config: ConfigKeys,
     pub image_name: ImageLabel<'a>,
     pub image_version: u32,
+    pub rootfs: Option<RootfsSlot>,
 }
 
 /// A struct to hold the passive-image configuration i.e. a newly downloaded fitimage
@@ -30,6 +31,7 @@ pub struct PassiveConf<'a> {
     pub image_name: Option<ImageLabel<'a>>,
     pub image_version: Option<u32>,
     pub update_status: Option<UpdateStatus>,
+    pub rootfs: Option<RootfsSlot>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -38,14 +40,49 @@ enum ConfigKeys {
     Passive,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UpdateStatus {
     Updating,
     Testing,
     Success,
 }
 
-/// A label consists of a `filename` and a file extension (ex: `.itb`)
+/// A root filesystem slot (`rootfs=a` or `rootfs=b`) i.e. the rootfs partition a fit-image is
+/// booted with, for full-system A/B updates. Each board maps its slots to partitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootfsSlot {
+    A,
+    B,
+}
+
+impl RootfsSlot {
+    /// The slot's index i.e. `0` for [`RootfsSlot::A`] and `1` for [`RootfsSlot::B`].
+    pub fn index(&self) -> usize {
+        match self {
+            RootfsSlot::A => 0,
+            RootfsSlot::B => 1,
+        }
+    }
+}
+
+/// A console's verbosity (`verbosity=quiet|error|normal|debug`, in an optional `[console]`
+/// section), from silent to most verbose. Boards cap it at build time i.e. a file can't make a
+/// quiet build print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Verbosity {
+    /// nothing is printed.
+    Quiet,
+    /// only warnings and errors (i.e. panics) are printed.
+    Error,
+    /// boot progress is printed too.
+    Normal,
+    /// so is the board's state i.e. its memory layout, drivers, etc.
+    Debug,
+}
+
+/// A label consists of a `filename` and a file extension i.e. `.itb` or, for a chunked fit-image
+/// (see [`crate::fs::chunks`]), `.cix`
 pub type ImageLabel<'a> = (&'a str, &'a str);
 
 impl From<&str> for ConfigKeys {
@@ -77,7 +114,7 @@ fn config_keys(input: &str) -> IResult<&str, ConfigKeys> {
 fn image_name(input: &str) -> IResult<&str, ImageLabel> {
     preceded(
         tag("image_name="),
-        tuple((alphanumericwithhypen, tag(".itb"))),
+        tuple((alphanumericwithhypen, alt((tag(".itb"), tag(".cix"))))),
     )(input)
     .map(|(next_input, res)| (next_input, res))
 }
@@ -85,14 +122,13 @@ fn image_name(input: &str) -> IResult<&str, ImageLabel> {
 fn image_version(input: &str) -> IResult<&str, u32> {
     preceded(
         tag("image_version="),
-        separated_pair(tag("ts"), tag("_"), tuple((digit0, multispace1))),
+        separated_pair(
+            tag("ts"),
+            tag("_"),
+            tuple((map_res(digit0, u32::from_str), multispace1)),
+        ),
     )(input)
-    .map(|(next_input, res)| {
-        (
-            next_input,
-            res.1 .0.parse::<u32>().expect("not a valid version number"),
-        )
-    })
+    .map(|(next_input, res)| (next_input, res.1 .0))
 }
 
 fn update_status(input: &str) -> IResult<&str, UpdateStatus> {
@@ -106,14 +142,8 @@ fn update_status(input: &str) -> IResult<&str, UpdateStatus> {
 fn ready_for_update(input: &str) -> IResult<&str, bool> {
     preceded(
         tag("ready_for_update_flag="),
-        alt((tag("true"), tag("false"))),
+        map_res(alt((tag("true"), tag("false"))), bool::from_str),
     )(input)
-    .map(|(next_input, res)| {
-        (
-            next_input,
-            bool::from_str(res).expect("not a boolean value"),
-        )
-    })
 }
 
 fn active_config(input: &str) -> IResult<&str, ActiveConf> {
@@ -134,6 +164,7 @@ fn active_config(input: &str) -> IResult<&str, ActiveConf> {
                 active_config,
                 image_name,
                 image_version,
+                rootfs: None,
             },
         )
     })
@@ -181,6 +212,7 @@ fn passive_config(input: &str) -> IResult<&str, PassiveConf> {
                 image_name,
                 image_version,
                 update_status,
+                rootfs: None,
             },
         )
     })
@@ -198,6 +230,305 @@ pub fn parse_config(input: &str) -> IResult<&str, (ActiveConf, PassiveConf)> {
     tuple((active_config, passive_config))(input)
 }
 
+/// A typed view of a complete `updt.txt` file, as returned by [`parse_update_config`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct UpdateConfig<'a> {
+    pub active: ActiveConf<'a>,
+    pub passive: PassiveConf<'a>,
+    /// the `[console]` section's verbosity, if it has one.
+    pub verbosity: Option<Verbosity>,
+}
+
+/// Errors reported by [`parse_update_config`]. All line numbers are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The line isn't a comment, a `[section]` header or a `key=value` pair.
+    Malformed { line: usize },
+    /// A quoted value is missing its closing `"`.
+    UnterminatedQuote { line: usize },
+    /// A `key=value` pair appears before the first `[section]` header.
+    KeyOutsideSection { line: usize },
+    /// The `[active]`, `[passive]` or `[console]` section appears more than once.
+    DuplicateSection { line: usize },
+    /// A known key is set more than once within a section.
+    DuplicateKey { line: usize, key: &'static str },
+    /// A known key holds a value that doesn't match its grammar.
+    InvalidValue { line: usize, key: &'static str },
+    /// A mandatory section is missing.
+    MissingSection(&'static str),
+    /// A mandatory key is missing from a section.
+    MissingKey {
+        section: &'static str,
+        key: &'static str,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    None,
+    Active,
+    Passive,
+    Console,
+    Unknown,
+}
+
+/// Parses a complete `updt.txt` file into an [`UpdateConfig`].
+///
+/// Unlike [`parse_config`], this parser is line-oriented and strict about what it accepts:
+/// - blank lines and `#` comments (full-line or trailing) are ignored.
+/// - whitespace around keys, `=` and values is ignored.
+/// - values may be double-quoted, in which case they may contain `#`.
+/// - unknown keys and unknown `[sections]` are tolerated (i.e. skipped), so newer files
+///   remain readable by older bootloaders.
+/// - known keys are validated and must not repeat. `[passive]` image fields may be left
+///   empty or set to `none`.
+/// - an optional `[console]` section sets the console's [`Verbosity`].
+///
+/// Any violation is reported as a [`ConfigError`] rather than a partial parse.
+pub fn parse_update_config(input: &str) -> Result<UpdateConfig<'_>, ConfigError> {
+    let mut section = Section::None;
+    let mut seen_active = false;
+    let mut seen_passive = false;
+    let mut seen_console = false;
+
+    let mut active_name = None;
+    let mut active_version = None;
+    let mut ready_flag = None;
+    let mut passive_name = None;
+    let mut passive_version = None;
+    let mut passive_status = None;
+    let mut active_rootfs = None;
+    let mut passive_rootfs = None;
+    let mut verbosity = None;
+
+    for (idx, raw_line) in input.split('\n').enumerate() {
+        let line = idx + 1;
+        let content = strip_comment(raw_line, line)?.trim();
+        if content.is_empty() {
+            continue;
+        }
+        if content.starts_with('[') {
+            section = match content {
+                "[active]" if seen_active => return Err(ConfigError::DuplicateSection { line }),
+                "[passive]" if seen_passive => return Err(ConfigError::DuplicateSection { line }),
+                "[console]" if seen_console => return Err(ConfigError::DuplicateSection { line }),
+                "[active]" => {
+                    seen_active = true;
+                    Section::Active
+                }
+                "[passive]" => {
+                    seen_passive = true;
+                    Section::Passive
+                }
+                "[console]" => {
+                    seen_console = true;
+                    Section::Console
+                }
+                _ if content.ends_with(']') => Section::Unknown,
+                _ => return Err(ConfigError::Malformed { line }),
+            };
+            continue;
+        }
+
+        let (key, value) = key_value(content, line)?;
+        match (section, key) {
+            (Section::None, _) => return Err(ConfigError::KeyOutsideSection { line }),
+            (Section::Active, "image_name") => {
+                let val = image_label(value).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "image_name",
+                })?;
+                set_once(&mut active_name, val, line, "image_name")?
+            }
+            (Section::Active, "image_version") => {
+                let val = version_number(value).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "image_version",
+                })?;
+                set_once(&mut active_version, val, line, "image_version")?
+            }
+            (Section::Active, "rootfs") => {
+                let val = rootfs_slot(value).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "rootfs",
+                })?;
+                set_once(&mut active_rootfs, val, line, "rootfs")?
+            }
+            (Section::Passive, "ready_for_update_flag") => {
+                let val = bool::from_str(value).map_err(|_| ConfigError::InvalidValue {
+                    line,
+                    key: "ready_for_update_flag",
+                })?;
+                set_once(&mut ready_flag, val, line, "ready_for_update_flag")?
+            }
+            (Section::Passive, "image_name") => {
+                let val = optional(value, image_label).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "image_name",
+                })?;
+                set_once(&mut passive_name, val, line, "image_name")?
+            }
+            (Section::Passive, "image_version") => {
+                let val = optional(value, version_number).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "image_version",
+                })?;
+                set_once(&mut passive_version, val, line, "image_version")?
+            }
+            (Section::Passive, "update_status") => {
+                let val = optional(value, status).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "update_status",
+                })?;
+                set_once(&mut passive_status, val, line, "update_status")?
+            }
+            (Section::Passive, "rootfs") => {
+                let val = optional(value, rootfs_slot).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "rootfs",
+                })?;
+                set_once(&mut passive_rootfs, val, line, "rootfs")?
+            }
+            (Section::Console, "verbosity") => {
+                let val = console_verbosity(value).ok_or(ConfigError::InvalidValue {
+                    line,
+                    key: "verbosity",
+                })?;
+                set_once(&mut verbosity, val, line, "verbosity")?
+            }
+            // unknown keys and sections are tolerated
+            (_, _) => {}
+        }
+    }
+
+    if !seen_active {
+        return Err(ConfigError::MissingSection("active"));
+    }
+    if !seen_passive {
+        return Err(ConfigError::MissingSection("passive"));
+    }
+    let missing = |section, key| ConfigError::MissingKey { section, key };
+    Ok(UpdateConfig {
+        active: ActiveConf {
+            active_config: ConfigKeys::Active,
+            image_name: active_name.ok_or(missing("active", "image_name"))?,
+            image_version: active_version.ok_or(missing("active", "image_version"))?,
+            rootfs: active_rootfs,
+        },
+        passive: PassiveConf {
+            passive_config: ConfigKeys::Passive,
+            ready_for_update_flag: ready_flag.ok_or(missing("passive", "ready_for_update_flag"))?,
+            image_name: passive_name.flatten(),
+            image_version: passive_version.flatten(),
+            update_status: passive_status.flatten(),
+            rootfs: passive_rootfs.flatten(),
+        },
+        verbosity,
+    })
+}
+
+/// Strips a trailing `#` comment (if any) that isn't part of a quoted value.
+fn strip_comment(line: &str, line_no: usize) -> Result<&str, ConfigError> {
+    let mut in_quotes = false;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return Ok(&line[..idx]),
+            _ => {}
+        }
+    }
+    match in_quotes {
+        true => Err(ConfigError::UnterminatedQuote { line: line_no }),
+        false => Ok(line),
+    }
+}
+
+/// Splits a `key=value` pair and unquotes its value.
+fn key_value(content: &str, line: usize) -> Result<(&str, &str), ConfigError> {
+    let (key, value) = content
+        .split_once('=')
+        .ok_or(ConfigError::Malformed { line })?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(ConfigError::Malformed { line });
+    }
+    let value = value.trim();
+    let value = match value.strip_prefix('"') {
+        Some(rest) => rest
+            .strip_suffix('"')
+            .filter(|inner| !inner.contains('"'))
+            .ok_or(ConfigError::Malformed { line })?,
+        None if value.contains('"') => return Err(ConfigError::Malformed { line }),
+        None => value,
+    };
+    Ok((key, value))
+}
+
+fn set_once<T>(
+    slot: &mut Option<T>,
+    val: T,
+    line: usize,
+    key: &'static str,
+) -> Result<(), ConfigError> {
+    match slot {
+        Some(_) => Err(ConfigError::DuplicateKey { line, key }),
+        None => {
+            *slot = Some(val);
+            Ok(())
+        }
+    }
+}
+
+/// An empty value or `none` leaves an optional field unset.
+fn optional<'a, T>(value: &'a str, parse: fn(&'a str) -> Option<T>) -> Option<Option<T>> {
+    match value {
+        "" | "none" => Some(None),
+        _ => parse(value).map(Some),
+    }
+}
+
+fn image_label(value: &str) -> Option<ImageLabel<'_>> {
+    match alphanumericwithhypen(value) {
+        Ok((".itb" | ".cix", name)) => Some((name, &value[name.len()..])),
+        _ => None,
+    }
+}
+
+fn version_number(value: &str) -> Option<u32> {
+    let digits = value.strip_prefix("ts_")?;
+    match digits.bytes().all(|b| b.is_ascii_digit()) {
+        true => digits.parse::<u32>().ok(),
+        false => None,
+    }
+}
+
+fn status(value: &str) -> Option<UpdateStatus> {
+    match value {
+        "updating" => Some(UpdateStatus::Updating),
+        "testing" => Some(UpdateStatus::Testing),
+        "success" => Some(UpdateStatus::Success),
+        _ => None,
+    }
+}
+
+fn rootfs_slot(value: &str) -> Option<RootfsSlot> {
+    match value {
+        "a" => Some(RootfsSlot::A),
+        "b" => Some(RootfsSlot::B),
+        _ => None,
+    }
+}
+
+fn console_verbosity(value: &str) -> Option<Verbosity> {
+    match value {
+        "quiet" => Some(Verbosity::Quiet),
+        "error" => Some(Verbosity::Error),
+        "normal" => Some(Verbosity::Normal),
+        "debug" => Some(Verbosity::Debug),
+        _ => None,
+    }
+}
+
 fn alphanumericwithhypen<T>(i: T) -> IResult<T, T>
 where
     T: InputTakeAtPosition,
@@ -288,6 +619,15 @@ mod tests {
             image_version("image_version=ts_111.222.345"),
             Err(Err::Error(Error::new(".222.345", ErrorKind::MultiSpace)))
         );
+        // an empty or out-of-range version is an error, not a panic
+        assert_eq!(
+            image_version("image_version=ts_ "),
+            Err(Err::Error(Error::new(" ", ErrorKind::MapRes)))
+        );
+        assert_eq!(
+            image_version("image_version=ts_4294967296 "),
+            Err(Err::Error(Error::new("4294967296 ", ErrorKind::MapRes)))
+        );
     }
 
     #[test]
@@ -331,7 +671,8 @@ mod tests {
                 ActiveConf {
                     active_config: ConfigKeys::Active,
                     image_name: ("xx", ".itb"),
-                    image_version: 123
+                    image_version: 123,
+                    rootfs: None
                 }
             ))
         );
@@ -355,7 +696,8 @@ mod tests {
                     ready_for_update_flag: true,
                     image_name: Some(("xx", ".itb")),
                     image_version: Some(123),
-                    update_status: Some(UpdateStatus::Updating)
+                    update_status: Some(UpdateStatus::Updating),
+                    rootfs: None
                 }
             ))
         );
@@ -377,7 +719,8 @@ mod tests {
                     ready_for_update_flag: false,
                     image_name: None,
                     image_version: None,
-                    update_status: None
+                    update_status: None,
+                    rootfs: None
                 }
             ))
         );
@@ -404,14 +747,16 @@ mod tests {
                     ActiveConf {
                         active_config: ConfigKeys::Active,
                         image_name: ("xx", ".itb"),
-                        image_version: 34488734
+                        image_version: 34488734,
+                        rootfs: None
                     },
                     PassiveConf {
                         passive_config: ConfigKeys::Passive,
                         ready_for_update_flag: true,
                         image_name: Some(("xx", ".itb")),
                         image_version: Some(34488735),
-                        update_status: Some(UpdateStatus::Updating)
+                        update_status: Some(UpdateStatus::Updating),
+                        rootfs: None
                     }
                 )
             ))
@@ -437,14 +782,16 @@ mod tests {
                     ActiveConf {
                         active_config: ConfigKeys::Active,
                         image_name: ("xx", ".itb"),
-                        image_version: 34488734
+                        image_version: 34488734,
+                        rootfs: None
                     },
                     PassiveConf {
                         passive_config: ConfigKeys::Passive,
                         ready_for_update_flag: false,
                         image_name: None,
                         image_version: None,
-                        update_status: None
+                        update_status: None,
+                        rootfs: None
                     }
                 )
             ))
@@ -467,17 +814,210 @@ mod tests {
                     ActiveConf {
                         active_config: ConfigKeys::Active,
                         image_name: ("xx", ".itb"),
-                        image_version: 34488734
+                        image_version: 34488734,
+                        rootfs: None
                     },
                     PassiveConf {
                         passive_config: ConfigKeys::Passive,
                         ready_for_update_flag: false,
                         image_name: None,
                         image_version: None,
-                        update_status: None
+                        update_status: None,
+                        rootfs: None
                     }
                 )
             ))
         );
     }
+
+    #[test]
+    fn test_parse_update_config() {
+        let cfg = parse_update_config(
+            "# rustBoot update state
+            [active]
+            image_name = \"signed-rpi4-apertis.itb\"
+            image_version=ts_1654328925 # first release
+
+            [passive]
+            ready_for_update_flag=true
+            image_name=signed-v1663342128.itb
+            image_version=ts_1663342128
+            update_status=updating
+            ",
+        );
+        assert_eq!(
+            cfg,
+            Ok(UpdateConfig {
+                active: ActiveConf {
+                    active_config: ConfigKeys::Active,
+                    image_name: ("signed-rpi4-apertis", ".itb"),
+                    image_version: 1654328925,
+                    rootfs: None
+                },
+                passive: PassiveConf {
+                    passive_config: ConfigKeys::Passive,
+                    ready_for_update_flag: true,
+                    image_name: Some(("signed-v1663342128", ".itb")),
+                    image_version: Some(1663342128),
+                    update_status: Some(UpdateStatus::Updating),
+                    rootfs: None
+                },
+                verbosity: None
+            })
+        );
+        // crlf line endings, unknown keys/sections and unset passive fields.
+        let cfg = parse_update_config(
+            "[active]\r\nimage_name=xx.itb\r\nimage_version=ts_1\r\nboot_count=3\r\n\
+             [passive]\r\nready_for_update_flag=false\r\nimage_name=\r\nimage_version=none\r\n\
+             [vendor]\r\nimage_name=not an image\r\n",
+        );
+        assert_eq!(
+            cfg,
+            Ok(UpdateConfig {
+                active: ActiveConf {
+                    active_config: ConfigKeys::Active,
+                    image_name: ("xx", ".itb"),
+                    image_version: 1,
+                    rootfs: None
+                },
+                passive: PassiveConf {
+                    passive_config: ConfigKeys::Passive,
+                    ready_for_update_flag: false,
+                    image_name: None,
+                    image_version: None,
+                    update_status: None,
+                    rootfs: None
+                },
+                verbosity: None
+            })
+        );
+        // A/B rootfs slots
+        let cfg = parse_update_config(
+            "[active]\nimage_name=xx.itb\nimage_version=ts_1\nrootfs=a\n\
+             [passive]\nready_for_update_flag=true\nimage_name=yy.itb\nimage_version=ts_2\n\
+             update_status=updating\nrootfs=b\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.active.rootfs, Some(RootfsSlot::A));
+        assert_eq!(cfg.passive.rootfs, Some(RootfsSlot::B));
+        assert_eq!(RootfsSlot::B.index(), 1);
+        let cfg = parse_update_config(
+            "[active]\nimage_name=xx.itb\nimage_version=ts_1\n\
+             [passive]\nready_for_update_flag=false\nrootfs=none\n",
+        )
+        .unwrap();
+        assert_eq!((cfg.active.rootfs, cfg.passive.rootfs), (None, None));
+        // the console's verbosity
+        let cfg = parse_update_config(
+            "[console]\nverbosity=error\n[active]\nimage_name=xx.itb\nimage_version=ts_1\n\
+             [passive]\nready_for_update_flag=false\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.verbosity, Some(Verbosity::Error));
+        assert!(Verbosity::Quiet < Verbosity::Error && Verbosity::Normal < Verbosity::Debug);
+        // chunked fit-images
+        assert_eq!(image_label("signed-v2.cix"), Some(("signed-v2", ".cix")));
+        assert_eq!(image_label("signed-v2.bin"), None);
+        // a quoted value may contain a `#`
+        assert_eq!(
+            key_value("image_name = \"a#b\"", 1),
+            Ok(("image_name", "a#b"))
+        );
+    }
+
+    #[test]
+    fn test_parse_update_config_errors() {
+        let passive = "[passive]\nready_for_update_flag=false\n";
+        let cases = [
+            ("[active\n", ConfigError::Malformed { line: 1 }),
+            ("[active]\nimage_name\n", ConfigError::Malformed { line: 2 }),
+            (
+                "[active]\nimage_name=\"xx.itb\n",
+                ConfigError::UnterminatedQuote { line: 2 },
+            ),
+            (
+                "image_name=xx.itb\n",
+                ConfigError::KeyOutsideSection { line: 1 },
+            ),
+            (
+                "[active]\nimage_name=xx.itb\nimage_name=yy.itb\n",
+                ConfigError::DuplicateKey {
+                    line: 3,
+                    key: "image_name",
+                },
+            ),
+            (
+                "[active]\nimage_name=xx.img\n",
+                ConfigError::InvalidValue {
+                    line: 2,
+                    key: "image_name",
+                },
+            ),
+            (
+                "[active]\nimage_version=ts_99999999999\n",
+                ConfigError::InvalidValue {
+                    line: 2,
+                    key: "image_version",
+                },
+            ),
+            (
+                "[active]\nimage_version=ts_+1\n",
+                ConfigError::InvalidValue {
+                    line: 2,
+                    key: "image_version",
+                },
+            ),
+            (
+                "[passive]\nupdate_status=done\n",
+                ConfigError::InvalidValue {
+                    line: 2,
+                    key: "update_status",
+                },
+            ),
+            (
+                "[passive]\nready_for_update_flag=yes\n",
+                ConfigError::InvalidValue {
+                    line: 2,
+                    key: "ready_for_update_flag",
+                },
+            ),
+            (
+                "[active]\nrootfs=c\n",
+                ConfigError::InvalidValue {
+                    line: 2,
+                    key: "rootfs",
+                },
+            ),
+            (
+                "[console]\nverbosity=loud\n",
+                ConfigError::InvalidValue {
+                    line: 2,
+                    key: "verbosity",
+                },
+            ),
+            (passive, ConfigError::MissingSection("active")),
+            (
+                "[active]\nimage_name=xx.itb\n[passive]\n",
+                ConfigError::MissingKey {
+                    section: "active",
+                    key: "image_version",
+                },
+            ),
+        ];
+        for (input, err) in cases.iter() {
+            assert_eq!(parse_update_config(input), Err(*err), "input: {:?}", input);
+        }
+        assert_eq!(
+            parse_update_config("[active]\nimage_name=xx.itb\nimage_version=ts_1\n"),
+            Err(ConfigError::MissingSection("passive"))
+        );
+        assert_eq!(
+            parse_update_config("[active]\n[passive]\n[active]\n"),
+            Err(ConfigError::DuplicateSection { line: 3 })
+        );
+        assert_eq!(
+            parse_update_config("[console]\n[console]\n"),
+            Err(ConfigError::DuplicateSection { line: 2 })
+        );
+    }
 }
@@ -2,93 +2,106 @@
 
 // **** TARGET PLATFORM - FLASH PARTIONINING ****
 
-#[cfg(feature = "nrf52840")]
-pub const SECTOR_SIZE: usize = 0x1000;
-#[cfg(feature = "nrf52840")]
-pub const PARTITION_SIZE: usize = 0x28000;
-#[cfg(feature = "nrf52840")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x2f000;
-#[cfg(feature = "nrf52840")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x57000;
-#[cfg(feature = "nrf52840")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x58000;
+#[cfg(feature = "state-journal")]
+use crate::image::image::StateRecord;
 
+// Partition layouts are generated from `boards/manifests/<board>.toml`, see `cargo <board> gen layout`.
+#[cfg(feature = "nrf52840")]
+include!("layouts/nrf52840.rs");
 #[cfg(feature = "stm32f411")]
-pub const SECTOR_SIZE: usize = 0x20000;
-#[cfg(feature = "stm32f411")]
-pub const PARTITION_SIZE: usize = 0x20000;
-#[cfg(feature = "stm32f411")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x08020000;
-#[cfg(feature = "stm32f411")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x08060000;
-#[cfg(feature = "stm32f411")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x08040000;
-
+include!("layouts/stm32f411.rs");
 #[cfg(feature = "stm32f446")]
-pub const SECTOR_SIZE: usize = 0x20000;
-#[cfg(feature = "stm32f446")]
-pub const PARTITION_SIZE: usize = 0x20000;
-#[cfg(feature = "stm32f446")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x08020000;
-#[cfg(feature = "stm32f446")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x08060000;
-#[cfg(feature = "stm32f446")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x08040000;
-
+include!("layouts/stm32f446.rs");
 #[cfg(feature = "stm32f469")]
-pub const SECTOR_SIZE: usize = 0x20000; // 128kb max sector size
-#[cfg(feature = "stm32f469")]
-pub const PARTITION_SIZE: usize = 0x60000; // 3 sectors per partition (boot or swap)
-#[cfg(feature = "stm32f469")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x08020000; // 3 sectors (128k) large, ends at 0x08080000-1
-#[cfg(feature = "stm32f469")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x08080000; // 3 sectors (128k) large, ends at 0x080e0000-1
-#[cfg(feature = "stm32f469")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x080e0000;
-
+include!("layouts/stm32f469.rs");
 #[cfg(feature = "stm32h723")]
-pub const SECTOR_SIZE: usize = 0x20000;
-#[cfg(feature = "stm32h723")]
-pub const PARTITION_SIZE: usize = 0x40000;
-#[cfg(feature = "stm32h723")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x08020000;
-#[cfg(feature = "stm32h723")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x080A0000;
-#[cfg(feature = "stm32h723")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x08060000;
-
-#[cfg(feature = "stm32f746")]
-pub const SECTOR_SIZE: usize = 0x40000; // 256kb
+include!("layouts/stm32h723.rs");
 #[cfg(feature = "stm32f746")]
-pub const PARTITION_SIZE: usize = 0x40000;
-#[cfg(feature = "stm32f746")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x08040000;
-#[cfg(feature = "stm32f746")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x080C0000;
-#[cfg(feature = "stm32f746")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x08080000;
-
-#[cfg(feature = "stm32f334")]
-pub const SECTOR_SIZE: usize = 0x1800;
+include!("layouts/stm32f746.rs");
 #[cfg(feature = "stm32f334")]
-pub const PARTITION_SIZE: usize = 0x1800;
-#[cfg(feature = "stm32f334")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x0800b800;
-#[cfg(feature = "stm32f334")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x0800e800;
-#[cfg(feature = "stm32f334")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x0800d000;
-
-#[cfg(feature = "rp2040")]
-pub const SECTOR_SIZE: usize = 0x1000;
+include!("layouts/stm32f334.rs");
+#[cfg(feature = "stm32f407")]
+include!("layouts/stm32f407.rs");
+#[cfg(feature = "stm32f769")]
+include!("layouts/stm32f769.rs");
 #[cfg(feature = "rp2040")]
-pub const PARTITION_SIZE: usize = 0x20000;
-#[cfg(feature = "rp2040")]
-pub const BOOT_PARTITION_ADDRESS: usize = 0x10020000;
-#[cfg(feature = "rp2040")]
-pub const UPDATE_PARTITION_ADDRESS: usize = 0x10040000;
-#[cfg(feature = "rp2040")]
-pub const SWAP_PARTITION_ADDRESS: usize = 0x10060000;
+include!("layouts/rp2040.rs");
+#[cfg(feature = "rp2350")]
+include!("layouts/rp2350.rs");
+#[cfg(feature = "esp32s3")]
+include!("layouts/esp32s3.rs");
+
+// Layout checks i.e. a layout that doesn't add up (ex: an edited one) fails to compile. The
+// bootloader's linker script checks that rustBoot itself fits below the boot partition.
+
+/// The boot, update and swap partitions (and the metadata sectors) i.e. `(address, length)`.
+#[cfg(not(feature = "metadata-sector"))]
+pub const PARTITIONS: [(usize, usize); 3] = [
+    (BOOT_PARTITION_ADDRESS, PARTITION_SIZE),
+    (UPDATE_PARTITION_ADDRESS, PARTITION_SIZE),
+    (SWAP_PARTITION_ADDRESS, SECTOR_SIZE),
+];
+#[cfg(feature = "metadata-sector")]
+pub const PARTITIONS: [(usize, usize); 5] = [
+    (BOOT_PARTITION_ADDRESS, PARTITION_SIZE),
+    (UPDATE_PARTITION_ADDRESS, PARTITION_SIZE),
+    (SWAP_PARTITION_ADDRESS, SECTOR_SIZE),
+    (BOOT_METADATA_ADDRESS, SECTOR_SIZE),
+    (UPDATE_METADATA_ADDRESS, SECTOR_SIZE),
+];
+
+// only evaluated at compile time i.e. its indexing can't panic at runtime
+#[allow(clippy::assertions_on_constants, clippy::indexing_slicing)]
+const fn check_layout() {
+    assert!(
+        SECTOR_SIZE > 0 && PARTITION_SIZE > 0 && PARTITION_SIZE % SECTOR_SIZE == 0,
+        "partition size must be a non-zero multiple of the sector size"
+    );
+    assert!(
+        WRITE_SIZE.is_power_of_two() && SECTOR_SIZE % WRITE_SIZE == 0,
+        "write size must be a power of two that divides the sector size"
+    );
+    assert!(
+        BOOTLOADER_ADDRESS < BOOT_PARTITION_ADDRESS,
+        "the bootloader must be located below the boot partition"
+    );
+    let flash_end = BOOTLOADER_ADDRESS + FLASH_SIZE;
+    let mut idx = 0;
+    while idx < PARTITIONS.len() {
+        let (addr, len) = PARTITIONS[idx];
+        assert!(addr % SECTOR_SIZE == 0, "partitions must be sector aligned");
+        assert!(
+            addr >= BOOT_PARTITION_ADDRESS && addr + len <= flash_end,
+            "partitions must fit in the device's flash, above the bootloader"
+        );
+        let mut other = idx + 1;
+        while other < PARTITIONS.len() {
+            let (other_addr, other_len) = PARTITIONS[other];
+            assert!(
+                addr >= other_addr + other_len || other_addr >= addr + len,
+                "partitions overlap"
+            );
+            other += 1;
+        }
+        idx += 1;
+    }
+    assert!(
+        HEADER_SEARCH_ALIGN.is_power_of_two()
+            && HEADER_SEARCH_ALIGN >= 4
+            && HEADER_SEARCH_WINDOW % HEADER_SEARCH_ALIGN == 0,
+        "the header scan's alignment must be a power of two (of at least a word) that divides its window"
+    );
+    assert!(
+        HEADER_SEARCH_WINDOW + IMAGE_HEADER_SIZE < PARTITION_SIZE,
+        "the header scan's window must leave room for an image in the partition"
+    );
+    assert!(
+        UPDATE_TRAILER_LEN <= SECTOR_SIZE,
+        "the update partition's trailer must fit a sector"
+    );
+}
+
+const _: () = check_layout();
 
 // **** RAM BOOT options for staged OS (update_ram only) ****
 pub const DTS_BOOT_ADDRESS: usize = 0xa0000;
@@ -113,14 +126,40 @@ pub const HDR_PADDING: u8 = 0xFF;
 
 pub const SECT_FLAG_NEW: u8 = 0x0F;
 
+/// Enumerated BOOTLOADER region i.e. rustBoot itself (including its embedded public key)
+pub const BOOTLOADER_SIZE: usize = BOOT_PARTITION_ADDRESS - BOOTLOADER_ADDRESS;
 /// Enumerated BOOT partition
+#[cfg(not(feature = "metadata-sector"))]
 pub const BOOT_TRAILER_ADDRESS: usize = BOOT_PARTITION_ADDRESS + PARTITION_SIZE;
+#[cfg(feature = "metadata-sector")]
+pub const BOOT_TRAILER_ADDRESS: usize = BOOT_METADATA_ADDRESS + SECTOR_SIZE;
 pub const BOOT_FWBASE: usize = BOOT_PARTITION_ADDRESS + IMAGE_HEADER_SIZE;
 /// Enumerated UPDATE partition
+#[cfg(not(feature = "metadata-sector"))]
 pub const UPDATE_TRAILER_ADDRESS: usize = UPDATE_PARTITION_ADDRESS + PARTITION_SIZE;
+#[cfg(feature = "metadata-sector")]
+pub const UPDATE_TRAILER_ADDRESS: usize = UPDATE_METADATA_ADDRESS + SECTOR_SIZE;
 pub const UPDATE_FWBASE: usize = UPDATE_PARTITION_ADDRESS + IMAGE_HEADER_SIZE;
+/// The number of a partition's (last) sectors that hold its trailer. With the `metadata-sector`
+/// feature, trailers are kept in a dedicated sector per partition (i.e. the board manifest's
+/// `boot_metadata` and `update_metadata`) and images may fill their partition.
+#[cfg(not(feature = "metadata-sector"))]
+pub const TRAILER_SECTORS: usize = 1;
+#[cfg(feature = "metadata-sector")]
+pub const TRAILER_SECTORS: usize = 0;
 /// Enumerated SWAP partition
 pub const SWAP_BASE: usize = SWAP_PARTITION_ADDRESS;
+/// How far past a partition's start rustBoot looks for an image's header (and in what steps),
+/// for images padded to an alignment by their toolchain. With the `header-scan` feature, it's the
+/// board manifest's `[header_scan]`, otherwise an image's header must be at its partition's start.
+#[cfg(feature = "header-scan")]
+pub const HEADER_SEARCH_WINDOW: usize = HEADER_SCAN_WINDOW;
+#[cfg(feature = "header-scan")]
+pub const HEADER_SEARCH_ALIGN: usize = HEADER_SCAN_ALIGN;
+#[cfg(not(feature = "header-scan"))]
+pub const HEADER_SEARCH_WINDOW: usize = 0;
+#[cfg(not(feature = "header-scan"))]
+pub const HEADER_SEARCH_ALIGN: usize = IMAGE_HEADER_SIZE;
 
 pub const RUSTBOOT_MAGIC: usize = 0x54535552; // RUST
 pub const RUSTBOOT_MAGIC_TRAIL: usize = 0x544F4F42; // BOOT
@@ -128,6 +167,33 @@ pub const RUSTBOOT_MAGIC_TRAIL: usize = 0x544F4F42; // BOOT
 pub const PART_STATUS_LEN: usize = 1;
 pub const MAGIC_TRAIL_LEN: usize = 4;
 
+/// The update partition's trailer i.e. its magic, state, sector flags, trial and retired bytes
+/// (and its state journal, if any). The boot partition's trailer has no sector flags (nor a
+/// retired byte).
+pub const UPDATE_TRAILER_LEN: usize =
+    journaled(MAGIC_TRAIL_LEN + PART_STATUS_LEN + (PARTITION_SIZE / SECTOR_SIZE + 1) / 2 + 2);
+pub const BOOT_TRAILER_LEN: usize = journaled(MAGIC_TRAIL_LEN + PART_STATUS_LEN + 1);
+
+/// With the `state-journal` feature, a partition's state is kept in the last `STATE_SLOTS` slots
+/// of its trailer (see [`StateRecord`]). A slot is a whole number of write units (and
+/// at least a record), so a torn write can't reach past its slot.
+#[cfg(feature = "state-journal")]
+pub const STATE_SLOTS: usize = 4;
+#[cfg(feature = "state-journal")]
+pub const STATE_SLOT_LEN: usize = match WRITE_SIZE > StateRecord::LEN {
+    true => WRITE_SIZE,
+    false => StateRecord::LEN,
+};
+
+/// The length of a trailer whose other bytes take up `len` bytes i.e. the state journal follows
+/// them, aligned to its slots.
+const fn journaled(len: usize) -> usize {
+    #[cfg(feature = "state-journal")]
+    return len.div_ceil(STATE_SLOT_LEN) * STATE_SLOT_LEN + STATE_SLOTS * STATE_SLOT_LEN;
+    #[cfg(not(feature = "state-journal"))]
+    len
+}
+
 /*  Hash Config */
 // SHA256 constants
 pub const HDR_SHA256: u16 = 0x0003;
@@ -150,3 +216,7 @@ pub const FLASHBUFFER_SIZE: usize = IMAGE_HEADER_SIZE;
 
 /* Signature Config */
 pub const ECC_SIGNATURE_SIZE: usize = 64;
+
+/* Integrity pre-check Config */
+pub const HDR_CRC32: u16 = 0x0030;
+pub const HDR_CRC32_LEN: usize = 0x4;
@@ -30,6 +30,12 @@ pub enum Error {
     BufferExhausted,
     /// Given buffer is too small to decode property value.
     BufferTooSmall,
+    /// An image's computed hash does not match the one stored in the fit-image.
+    IntegrityCheckFailed,
+    /// A node required by a rustBoot fit-image is missing.
+    MissingNode,
+    /// A property required by a rustBoot fit-image is missing.
+    MissingProperty,
     /// No more StructItem left in DTB structure.
     NoMoreStructItems,
     /// No zero entry found in reserved memory block.
@@ -58,6 +64,12 @@ pub enum Error {
     UnsupportedCompVersion,
     /// Unsupported
     Unsupported,
+    /// An image's `compression` isn't supported (or its decompressor isn't enabled).
+    UnsupportedCompression,
+    /// An image's compressed data is malformed.
+    DecompressionFailed,
+    /// A kernel command line that isn't covered by the fit-image's signature, in secure mode.
+    UnsignedBootargs,
 }
 
 /// DTB-related result.
@@ -0,0 +1,226 @@
+//! Compressed fit-image payloads i.e. images whose `compression` property isn't `none`.
+//!
+//! A fit-image's hashes (and its signature) cover an image's data as stored i.e. compressed.
+//! Images are decompressed to their load addresses only after the fit-image has been verified.
+//!
+//! Supported compressions:
+//! - `none`
+//! - `gzip`, with the `gzip` feature.
+//!
+//! `zstd` images are recognized but not supported - the available zstd decoders need an
+//! allocator, which rustBoot doesn't have.
+
+use super::{as_str, image_path, Error, Reader, Result};
+
+/// The `compression` of a fit-image's image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Parses a `compression` property's value.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(Error::UnsupportedCompression),
+        }
+    }
+}
+
+/// Returns the compression of the image `img` (i.e. `kernel`, `fdt`, `ramdisk` or `rbconfig`).
+/// An image without a `compression` property isn't compressed.
+pub fn get_image_compression(itb_blob: &[u8], img: &str) -> Result<Compression> {
+    let reader = Reader::read(itb_blob)?;
+    let root = reader.struct_items();
+    let (_, node_iter) = root
+        .path_struct_items(image_path(img))
+        .next()
+        .ok_or(Error::MissingNode)?;
+    match node_iter.get_node_property("compression") {
+        Some(val) => Compression::from_name(as_str(val)?.ok_or(Error::BadValueStr)?),
+        None => Ok(Compression::None),
+    }
+}
+
+/// Copies the image `img` from a (verified) fit-image to `dst`, decompressing it as required.
+/// Returns the image's (decompressed) size.
+pub fn load_image(itb_blob: &[u8], img: &str, dst: &mut [u8]) -> Result<usize> {
+    let data = super::get_image_data(itb_blob, img).ok_or(Error::MissingProperty)?;
+    copy_image(data, get_image_compression(itb_blob, img)?, dst)
+}
+
+/// Copies an image's `data` to `dst`, decompressing it as per its `compression`. Returns the
+/// image's (decompressed) size.
+pub(crate) fn copy_image(data: &[u8], compression: Compression, dst: &mut [u8]) -> Result<usize> {
+    match compression {
+        Compression::None => {
+            dst.get_mut(..data.len())
+                .ok_or(Error::BufferTooSmall)?
+                .copy_from_slice(data);
+            Ok(data.len())
+        }
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => gunzip(data, dst),
+        _ => Err(Error::UnsupportedCompression),
+    }
+}
+
+/// Returns the size of an image's `data` once it's decompressed as per its `compression` i.e. the
+/// size it takes up where it's loaded. A gzip member's size is the one its trailer records (which
+/// [`gunzip`] checks). A zstd image's size isn't known i.e. it's [`Error::UnsupportedCompression`].
+pub fn decompressed_size(data: &[u8], compression: Compression) -> Result<usize> {
+    use core::convert::TryInto;
+
+    match compression {
+        Compression::None => Ok(data.len()),
+        Compression::Gzip => {
+            // isize i.e. the trailer's last 4 bytes
+            let isize: [u8; 4] = data
+                .len()
+                .checked_sub(4)
+                .and_then(|start| data.get(start..))
+                .and_then(|isize| isize.try_into().ok())
+                .ok_or(Error::DecompressionFailed)?;
+            Ok(u32::from_le_bytes(isize) as usize)
+        }
+        Compression::Zstd => Err(Error::UnsupportedCompression),
+    }
+}
+
+/// gzip header flags, see RFC 1952
+#[cfg(feature = "gzip")]
+mod gzip {
+    pub const MAGIC: [u8; 2] = [0x1f, 0x8b];
+    pub const CM_DEFLATE: u8 = 8;
+    pub const HEADER_SIZE: usize = 10;
+    /// crc32 and isize i.e. the size of the uncompressed data.
+    pub const TRAILER_SIZE: usize = 8;
+    pub const FHCRC: u8 = 0x02;
+    pub const FEXTRA: u8 = 0x04;
+    pub const FNAME: u8 = 0x08;
+    pub const FCOMMENT: u8 = 0x10;
+}
+
+/// Decompresses a gzip member (i.e. a `.gz` file) into `dst`. Returns the decompressed size.
+///
+/// The trailer's crc32 isn't checked, the fit-image's hash already covers the compressed data.
+#[cfg(feature = "gzip")]
+pub fn gunzip(src: &[u8], dst: &mut [u8]) -> Result<usize> {
+    use core::convert::TryInto;
+    use gzip::*;
+    use miniz_oxide::inflate::{decompress_slice_iter_to_slice, TINFLStatus};
+
+    if src.len() < HEADER_SIZE + TRAILER_SIZE || src[..2] != MAGIC || src[2] != CM_DEFLATE {
+        return Err(Error::DecompressionFailed);
+    }
+    let flags = src[3];
+    let mut pos = HEADER_SIZE;
+    if flags & FEXTRA != 0 {
+        let xlen = src.get(pos..pos + 2).ok_or(Error::DecompressionFailed)?;
+        pos += 2 + u16::from_le_bytes([xlen[0], xlen[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            // zero-terminated
+            let len = src
+                .get(pos..)
+                .and_then(|s| s.iter().position(|b| *b == 0))
+                .ok_or(Error::DecompressionFailed)?;
+            pos += len + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    let trailer = src.len() - TRAILER_SIZE;
+    let deflate = src.get(pos..trailer).ok_or(Error::DecompressionFailed)?;
+    let uncompressed_size = u32::from_le_bytes(src[trailer + 4..].try_into().unwrap());
+
+    let len = decompress_slice_iter_to_slice(dst, core::iter::once(deflate), false, true).map_err(
+        |status| match status {
+            TINFLStatus::HasMoreOutput => Error::BufferTooSmall,
+            _ => Error::DecompressionFailed,
+        },
+    )?;
+    // isize is the uncompressed size, modulo 2^32
+    if len as u32 != uncompressed_size {
+        return Err(Error::DecompressionFailed);
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_names() {
+        assert_eq!(Compression::from_name("none"), Ok(Compression::None));
+        assert_eq!(Compression::from_name("gzip"), Ok(Compression::Gzip));
+        assert_eq!(Compression::from_name("zstd"), Ok(Compression::Zstd));
+        assert_eq!(
+            Compression::from_name("lzma"),
+            Err(Error::UnsupportedCompression)
+        );
+    }
+
+    /// `rustBoot ` x 64, gzipped with a file name (i.e. `FNAME`).
+    const GZIPPED: [u8; 42] = [
+        0x1f, 0x8b, 0x08, 0x08, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0x49, 0x6d, 0x61, 0x67, 0x65,
+        0x00, 0x2b, 0x2a, 0x2d, 0x2e, 0x71, 0xca, 0xcf, 0x2f, 0x51, 0x28, 0x1a, 0x65, 0x8c, 0x32,
+        0x48, 0x67, 0x00, 0x00, 0x47, 0x42, 0xc4, 0x3a, 0x40, 0x02, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn decompressed_sizes() {
+        assert_eq!(decompressed_size(&GZIPPED, Compression::None), Ok(42));
+        assert_eq!(decompressed_size(&GZIPPED, Compression::Gzip), Ok(9 * 64));
+        assert_eq!(
+            decompressed_size(&GZIPPED[..3], Compression::Gzip),
+            Err(Error::DecompressionFailed)
+        );
+        assert_eq!(
+            decompressed_size(&GZIPPED, Compression::Zstd),
+            Err(Error::UnsupportedCompression)
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gunzip_member() {
+        let mut dst = [0u8; 1024];
+        let len = gunzip(&GZIPPED, &mut dst).unwrap();
+        assert_eq!(len, 9 * 64);
+        assert!(dst[..len].chunks(9).all(|c| c == b"rustBoot "));
+
+        // too small a buffer
+        assert_eq!(
+            gunzip(&GZIPPED, &mut dst[..100]),
+            Err(Error::BufferTooSmall)
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gunzip_malformed() {
+        let mut dst = [0u8; 1024];
+        // not gzip
+        let mut src = GZIPPED;
+        src[0] = 0;
+        assert_eq!(gunzip(&src, &mut dst), Err(Error::DecompressionFailed));
+        // truncated
+        assert_eq!(
+            gunzip(&GZIPPED[..30], &mut dst),
+            Err(Error::DecompressionFailed)
+        );
+        // isize mismatch
+        let mut src = GZIPPED;
+        src[38] = 0x41;
+        assert_eq!(gunzip(&src, &mut dst), Err(Error::DecompressionFailed));
+    }
+}
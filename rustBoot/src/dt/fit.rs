@@ -1,8 +1,11 @@
 use core::cell::OnceCell;
-use core::convert::TryInto;
-use core::ops::Add;
+use core::convert::{TryFrom, TryInto};
+use core::ops::{Add, RangeInclusive};
 
-use super::{Concat, Error, Reader, Result};
+use super::reader::be_addr;
+use super::{
+    copy_image, decompressed_size, Compression, Concat, Error, ImageDigests, Reader, Result,
+};
 use log::info;
 use nom::AsBytes;
 use p256::ecdsa::signature::digest::Digest;
@@ -10,6 +13,7 @@ use p256::elliptic_curve::generic_array::ArrayLength;
 use sha2::Sha256;
 
 use crate::crypto::signatures::{verify_ecc256_signature, HDR_IMG_TYPE_AUTH};
+use crate::version::{TimestampPolicy, ValidityPolicy};
 
 pub static mut FALLBACK_TO_ACTIVE_IMG: OnceCell<bool> = OnceCell::new();
 pub static mut IS_PASSIVE_SELECTED: OnceCell<bool> = OnceCell::new();
@@ -18,9 +22,19 @@ pub static mut IS_PASSIVE_SELECTED: OnceCell<bool> = OnceCell::new();
 pub struct Config<'a, const S: usize> {
     description: &'a str,
     kernel: &'a str,
-    fdt: &'a str,
+    /// the config's fdt, if it carries one (see [`get_config_fdt`]).
+    fdt: Option<&'a str>,
     ramdisk: &'a str,
     rbconfig: &'a str,
+    /// the kernel command line, if the config carries one (see [`get_config_bootargs`]).
+    bootargs: Option<&'a str>,
+    /// the config's validity window (in seconds since the unix epoch), if it carries one (see
+    /// [`check_fit_validity`]).
+    not_before: Option<u32>,
+    not_after: Option<u32>,
+    /// the image that's chainloaded instead of booting the kernel, if the config carries one (see
+    /// [`get_config_loadable`]).
+    loadables: Option<&'a str>,
     signature: Signature<'a, S>,
 }
 
@@ -29,9 +43,13 @@ impl<'a, const S: usize> Default for Config<'a, S> {
         Config {
             description: "none",
             kernel: "none",
-            fdt: "none",
+            fdt: None,
             ramdisk: "none",
             rbconfig: "none",
+            bootargs: None,
+            not_before: None,
+            not_after: None,
+            loadables: None,
             signature: Signature {
                 value: [0; S],
                 algo: "none",
@@ -59,8 +77,8 @@ pub struct Image<'a, const H: usize> {
     arch: &'a str,
     os: Option<&'a str>,
     compression: &'a str,
-    load: Option<u32>,
-    entry: Option<u32>,
+    load: Option<u64>,
+    entry: Option<u64>,
     hash: Hash<'a, H>,
 }
 
@@ -93,6 +111,8 @@ pub struct Hash<'a, const H: usize> {
 #[repr(C)]
 pub struct Images<'a, const H: usize, const N: usize> {
     images: [Image<'a, H>; N],
+    /// the config's loadable, if it carries one.
+    loadable: Option<Image<'a, H>>,
 }
 
 #[derive(Debug)]
@@ -110,6 +130,21 @@ pub enum CurveType {
 pub fn parse_fit<D, const H: usize, const S: usize, const N: usize>(
     reader: Reader,
 ) -> Result<(Config<S>, Images<H, N>)>
+where
+    D: Digest,
+    <D as Digest>::OutputSize: Add,
+    <<D as Digest>::OutputSize as Add>::Output: ArrayLength<u8>,
+{
+    parse_fit_with::<D, H, S, N>(reader, None)
+}
+
+/// Same as [`parse_fit`] but re-uses image digests that were computed while the
+/// fit-image was being streamed in (see [`FitDigester`]). Images without a
+/// streamed digest (of the `data` value that's read here) are hashed here, as usual.
+pub fn parse_fit_with<'a, D, const H: usize, const S: usize, const N: usize>(
+    reader: Reader<'a>,
+    streamed: Option<&ImageDigests<H>>,
+) -> Result<(Config<'a, S>, Images<'a, H, N>)>
 where
     D: Digest,
     <D as Digest>::OutputSize: Add,
@@ -117,8 +152,12 @@ where
 {
     let mut configuration = Config::default();
     let mut images = [Image::default(); N];
+    let mut loadable = None;
     let root = reader.struct_items();
-    let (_, node_iter) = root.path_struct_items("/configurations").next().unwrap();
+    let (_, node_iter) = root
+        .path_struct_items("/configurations")
+        .next()
+        .ok_or(Error::MissingNode)?;
 
     // *** Find the default config ***
     if let Some(config) = node_iter.get_node_property("default") {
@@ -129,13 +168,20 @@ where
         defmt::info!("config: {:?}", config);
         // info!("config: {:?}", config);
 
-        let (_, node_iter) = root.path_struct_items(config).next().unwrap();
+        let (_, node_iter) = root
+            .path_struct_items(config)
+            .next()
+            .ok_or(Error::MissingNode)?;
         let config_properties = [
             "description",
             "kernel",
             "fdt",
             "ramdisk",
             "rbconfig",
+            "bootargs",
+            "not-before",
+            "not-after",
+            "loadables",
             "signature@1",
         ];
         let mut description = None;
@@ -143,6 +189,10 @@ where
         let mut fdt = None;
         let mut ramdisk = None;
         let mut rbconfig = None;
+        let mut bootargs = None;
+        let mut not_before = None;
+        let mut not_after = None;
+        let mut loadables = None;
         let mut signature_algo = None;
         let mut key_hint = None;
         let mut signed_images = None;
@@ -169,21 +219,37 @@ where
                 let rbconf = node_iter.get_node_property(prop);
                 rbconfig = rbconf
             }
+            "bootargs" => {
+                let args = node_iter.get_node_property(prop);
+                bootargs = args
+            }
+            "not-before" => {
+                let time = node_iter.get_node_property(prop);
+                not_before = time
+            }
+            "not-after" => {
+                let time = node_iter.get_node_property(prop);
+                not_after = time
+            }
+            "loadables" => {
+                let name = node_iter.get_node_property(prop);
+                loadables = name
+            }
             "signature@1" => {
                 for item in node_iter {
                     if item.is_property() {
                         match item.name() {
                             Ok(val) if val == "algo" => {
-                                signature_algo = Some(item.value().unwrap());
+                                signature_algo = item.value().ok();
                             }
                             Ok(val) if val == "key-name-hint" => {
-                                key_hint = Some(item.value().unwrap());
+                                key_hint = item.value().ok();
                             }
                             Ok(val) if val == "signed-images" => {
-                                signed_images = Some(item.value().unwrap());
+                                signed_images = item.value().ok();
                             }
                             Ok(val) if val == "value" => {
-                                signature = Some(item.value().unwrap());
+                                signature = item.value().ok();
                             }
                             _ => {}
                         }
@@ -220,23 +286,35 @@ where
                 }
             }
             None => {
-                return Err(Error::BadPropertyName);
+                return Err(Error::MissingProperty);
             }
         };
 
         let signature: Signature<S> = Signature {
             value: signature,
-            algo: as_str(signature_algo.unwrap())?.expect("algo not specified in itb"),
-            key_hint: as_str(key_hint.unwrap())?.expect("key_hint not specified in itb"),
-            signed_images: as_str(signed_images.unwrap())?
-                .expect("signed images list not specified in itb"),
+            algo: required_str(signature_algo)?,
+            key_hint: required_str(key_hint)?,
+            signed_images: required_str(signed_images)?,
         };
         let config = Config {
-            description: as_str(description.unwrap())?.expect("missing config description field"),
-            kernel: as_str(kernel.unwrap())?.expect("kernel not specified in itb"),
-            fdt: as_str(fdt.unwrap())?.expect("fdt not specified in itb"),
-            ramdisk: as_str(ramdisk.unwrap())?.expect("ramdisk not specified in itb"),
-            rbconfig: as_str(rbconfig.unwrap())?.expect("rbconfig not specified in itb"),
+            description: required_str(description)?,
+            kernel: required_str(kernel)?,
+            fdt: match fdt {
+                Some(val) => Some(as_str(val)?.ok_or(Error::BadValueStr)?),
+                None => None,
+            },
+            ramdisk: required_str(ramdisk)?,
+            rbconfig: required_str(rbconfig)?,
+            bootargs: match bootargs {
+                Some(val) => Some(as_str(val)?.ok_or(Error::BadValueStr)?),
+                None => None,
+            },
+            not_before: not_before.map(as_u32).transpose()?,
+            not_after: not_after.map(as_u32).transpose()?,
+            loadables: match loadables {
+                Some(val) => Some(as_str(val)?.ok_or(Error::BadValueStr)?),
+                None => None,
+            },
             signature,
         };
         configuration = config;
@@ -244,7 +322,7 @@ where
         defmt::info!("Config: {:?}\n", configuration);
         // info!("Config: {:?}\n", configuration);
 
-        let conf_properties = ["kernel", "fdt", "ramdisk", "rbconfig"];
+        let conf_properties = ["kernel", "fdt", "ramdisk", "rbconfig", "loadables"];
         for (idx, prop) in conf_properties.iter().enumerate() {
             match node_iter.get_node_property(prop) {
                 Some(val) => {
@@ -253,7 +331,10 @@ where
                     #[cfg(feature = "defmt")]
                     defmt::info!("img: {:?}", img);
 
-                    let (_, node_iter) = root.path_struct_items(img).next().unwrap();
+                    let (_, node_iter) = root
+                        .path_struct_items(img)
+                        .next()
+                        .ok_or(Error::MissingNode)?;
                     let img_properties = [
                         "description",
                         "data",
@@ -310,23 +391,45 @@ where
                     });
 
                     info!("computing {:?} hash", prop,);
-                    let computed_hash;
-                    match data {
-                        Some(data) => {
-                            computed_hash = D::digest(data);
-                            info!("computed {:?} hash: {:x}", prop, computed_hash);
+                    let computed_hash: [u8; H];
+                    // a streamed digest is only used if it's of the very `data` read here
+                    let streamed_digest = match (data, streamed) {
+                        (Some(data), Some(digests)) => digests.get(val, &reader, data),
+                        _ => None,
+                    };
+                    match (data, streamed_digest) {
+                        (Some(_), Some(digest)) => {
+                            computed_hash = *digest;
+                            info!("streamed {:?} hash: {:x?}", prop, computed_hash);
+                        }
+                        (Some(data), None) => {
+                            let digest = D::digest(data);
+                            info!("computed {:?} hash: {:x}", prop, digest);
+                            computed_hash = digest
+                                .as_slice()
+                                .try_into()
+                                .map_err(|_| Error::Unsupported)?;
                         }
-                        None => {
-                            panic!("invalid ITB supplied");
+                        (None, _) => {
+                            return Err(Error::MissingProperty);
                         }
                     }
 
-                    let (_, node_iter) = node_iter.path_struct_items("hash").next().unwrap();
+                    let (_, node_iter) = node_iter
+                        .path_struct_items("hash")
+                        .next()
+                        .ok_or(Error::MissingNode)?;
                     let hash_value = node_iter.get_node_property("value");
                     let hash_algo = node_iter.get_node_property("algo");
                     // println!("hash_value: {:x}", hash_value.unwrap());
-                    match computed_hash.as_slice().ne(hash_value.unwrap()) {
-                        true => panic!("{} intergity check failed...", prop),
+                    match computed_hash
+                        .as_slice()
+                        .ne(hash_value.ok_or(Error::MissingProperty)?)
+                    {
+                        true => {
+                            info!("{} integrity check failed...", prop);
+                            return Err(Error::IntegrityCheckFailed);
+                        }
                         false => {
                             info!(
                                 "\x1b[95m{} integrity consistent\x1b[0m with supplied itb...",
@@ -336,35 +439,36 @@ where
                     }
 
                     let hash: Hash<H> = Hash {
-                        value: computed_hash.as_slice().try_into().unwrap(),
-                        algo: as_str(hash_algo.unwrap())?.expect("hash_algo not specified in itb"),
+                        value: computed_hash,
+                        algo: required_str(hash_algo)?,
                     };
                     let os = match os {
                         Some(val) => as_str(val)?,
                         None => None,
                     };
                     let load = match load {
-                        Some(val) => Some(u32::from_be_bytes(val.try_into().unwrap())),
+                        Some(val) => Some(be_addr(val)?),
                         None => None,
                     };
                     let entry = match entry {
-                        Some(val) => Some(u32::from_be_bytes(val.try_into().unwrap())),
+                        Some(val) => Some(be_addr(val)?),
                         None => None,
                     };
 
                     let img = Image {
-                        description: as_str(description.unwrap())?
-                            .expect("image description not specified in itb"),
-                        typ: as_str(typ.unwrap())?.expect("image type not specified in itb"),
-                        arch: as_str(arch.unwrap())?.expect("image arch not specified in itb"),
+                        description: required_str(description)?,
+                        typ: required_str(typ)?,
+                        arch: required_str(arch)?,
                         os,
-                        compression: as_str(compression.unwrap())?
-                            .expect("image compression not specified in itb"),
+                        compression: required_str(compression)?,
                         load,
                         entry,
                         hash,
                     };
-                    images[idx] = img;
+                    match *prop {
+                        "loadables" => loadable = Some(img),
+                        _ => *images.get_mut(idx).ok_or(Error::BufferTooSmall)? = img,
+                    }
                     #[cfg(feature = "defmt")]
                     defmt::info!("Image: {:?}\n", img);
                 }
@@ -372,7 +476,7 @@ where
             }
         }
     }
-    let images = Images { images };
+    let images = Images { images, loadable };
     Ok((configuration, images))
 }
 
@@ -383,17 +487,33 @@ pub fn prepare_img_hash<'a, D, const H: usize, const S: usize, const N: usize>(
 where
     D: Digest,
 {
-    let reader = Reader::read(itb_blob).unwrap();
+    prepare_img_hash_with::<D, H, S, N>(itb_blob, itb_version, None, TimestampPolicy::Exact)
+}
+
+pub fn prepare_img_hash_with<'a, D, const H: usize, const S: usize, const N: usize>(
+    itb_blob: &'a [u8],
+    itb_version: u32,
+    streamed: Option<&ImageDigests<H>>,
+    timestamp_policy: TimestampPolicy,
+) -> Result<(D, [u8; S])>
+where
+    D: Digest,
+{
+    let reader = Reader::read(itb_blob)?;
     let root = &reader.struct_items();
-    let (_, node_iter) = root.path_struct_items("/").next().unwrap();
+    let (_, node_iter) = root
+        .path_struct_items("/")
+        .next()
+        .ok_or(Error::MissingNode)?;
 
     let mut hasher = D::new();
     let timestamp = node_iter.get_node_property("timestamp");
-    // check to see if the timestamp matches the supplied version (from updt.txt)
+    // check to see if the timestamp satisfies the supplied version (from updt.txt)
     match timestamp {
         Some(version) => {
-            let retrieved_version = u32::from_be_bytes(version.try_into().unwrap()); // mkimage always sets a 4-byte timestamp
-            if retrieved_version != itb_version {
+            // mkimage always sets a 4-byte timestamp
+            let retrieved_version = as_u32(version)?;
+            if !timestamp_policy.permits(itb_version, retrieved_version) {
                 info!(
                     "retrieved_version: {:?}, itb_version: {:?}",
                     retrieved_version, itb_version
@@ -412,16 +532,18 @@ where
         }
         None => {
             // mkimage always sets a timestamp
-            unreachable!()
+            return Err(Error::MissingProperty);
         }
     }
-    hasher.update(timestamp.unwrap());
+    hasher.update(timestamp.ok_or(Error::MissingProperty)?);
 
-    let (config, images) = parse_fit::<Sha256, H, S, N>(reader)?;
+    let (config, images) = parse_fit_with::<Sha256, H, S, N>(reader, streamed)?;
+    // a config without an fdt (i.e. the board's dtb is loaded separately) hashes as if its `fdt`
+    // were empty, so configs with one hash as they always have.
     let cfg_values = [
         config.description,
         config.kernel,
-        config.fdt,
+        config.fdt.unwrap_or(""),
         config.ramdisk,
         config.rbconfig,
         config.signature.algo,
@@ -430,12 +552,32 @@ where
     ];
     let mut buf = [0u8; 150];
     let mut offset = 0usize;
-    let _ = cfg_values.iter().for_each(|val| {
-        buf[offset..offset + val.len()].copy_from_slice(val.as_bytes());
+    for val in cfg_values.iter() {
+        buf.get_mut(offset..offset + val.len())
+            .ok_or(Error::BufferExhausted)?
+            .copy_from_slice(val.as_bytes());
         offset += val.len()
-    });
+    }
     let cfg_bytes = &buf[..offset];
     hasher.update(cfg_bytes);
+    // the kernel command line, the validity window and a loadable are optional i.e. configs
+    // without them hash as they always have. Each one that's present is hashed as a tagged,
+    // length-prefixed value (see [`hash_optional_prop`]), so no value can be moved into another
+    // property (ex: the end of `signed-images`) or passed off as a property that was dropped.
+    if let Some(bootargs) = config.bootargs {
+        hash_optional_prop(&mut hasher, "bootargs", bootargs.as_bytes());
+    }
+    if let Some(time) = config.not_before {
+        hash_optional_prop(&mut hasher, "not-before", &time.to_be_bytes());
+    }
+    if let Some(time) = config.not_after {
+        hash_optional_prop(&mut hasher, "not-after", &time.to_be_bytes());
+    }
+    // a loadable is covered along with its image's hash.
+    if let (Some(name), Some(img)) = (config.loadables, images.loadable) {
+        hash_optional_prop(&mut hasher, "loadables", name.as_bytes());
+        hasher.update(img.hash.value);
+    }
 
     let mut img_hashes = [[0u8; H]; N];
     let _ = for (idx, img) in images.images.iter().enumerate() {
@@ -450,6 +592,19 @@ where
     Ok((hasher, signature))
 }
 
+/// Marks the start of an optional config property in the config's hash. It isn't valid UTF-8, so
+/// it can't appear in any of the (string) values that are hashed ahead of it.
+const OPTIONAL_PROP_MARKER: u8 = 0xff;
+
+/// Hashes an optional config property as [`OPTIONAL_PROP_MARKER`], its name, its value's length (4
+/// bytes, big-endian) and its value.
+fn hash_optional_prop<D: Digest>(hasher: &mut D, name: &str, value: &[u8]) {
+    hasher.update([OPTIONAL_PROP_MARKER]);
+    hasher.update(name.as_bytes());
+    hasher.update((value.len() as u32).to_be_bytes());
+    hasher.update(value);
+}
+
 pub fn flatten<'a, const H: usize, const N: usize>(img_hash: [[u8; H]; N]) -> [u8; 32 * 4] {
     // we can replace this when generic parameters in const operations is stabilized
     let mut bytes = [0u8; 32 * 4];
@@ -469,6 +624,22 @@ pub fn flatten<'a, const H: usize, const N: usize>(img_hash: [[u8; H]; N]) -> [u
 pub fn verify_fit<const H: usize, const S: usize, const N: usize>(
     itb_blob: &[u8],
     itb_version: u32,
+) -> crate::Result<bool> {
+    verify_fit_with::<H, S, N>(itb_blob, itb_version, None, TimestampPolicy::Exact)
+}
+
+/// Verifies a signed fit-image, re-using image digests computed while the
+/// image tree blob was being read from storage. The fit-image's timestamp is
+/// checked against `itb_version` as per `timestamp_policy`.
+///
+/// NOTE:
+/// - digests must have been produced by a [`FitDigester`] fed with the very same `itb_blob`.
+///
+pub fn verify_fit_with<const H: usize, const S: usize, const N: usize>(
+    itb_blob: &[u8],
+    itb_version: u32,
+    streamed: Option<&ImageDigests<32>>,
+    timestamp_policy: TimestampPolicy,
 ) -> crate::Result<bool> {
     let algo = parse_algo(itb_blob);
     match algo {
@@ -477,33 +648,40 @@ pub fn verify_fit<const H: usize, const S: usize, const N: usize>(
         #[cfg(feature = "nistp256")]
         Ok(CurveType::NistP256) => {
             info!("test verify_fit");
-            let (prehashed_digest, signature) =
-                match prepare_img_hash::<Sha256, 32, 64, 4>(itb_blob, itb_version) {
-                    Ok((digest, signature)) => (digest, signature),
-                    Err(e) => match e {
-                        // `passive` fit-image version supplied does not match `cfg` version.
-                        Error::FitVersionMismatch => return Err(crate::RustbootError::BadVersion),
-                        _ => {
-                            info!("something went wrong while parsing supplied fit-image ");
-                            return Err(crate::RustbootError::__Nonexhaustive);
-                        }
-                    },
-                };
+            let (prehashed_digest, signature) = match prepare_img_hash_with::<Sha256, 32, 64, 4>(
+                itb_blob,
+                itb_version,
+                streamed,
+                timestamp_policy,
+            ) {
+                Ok((digest, signature)) => (digest, signature),
+                Err(e) => match e {
+                    // `passive` fit-image version supplied does not match `cfg` version.
+                    Error::FitVersionMismatch => return Err(crate::RustbootError::BadVersion),
+                    _ => {
+                        info!("something went wrong while parsing supplied fit-image ");
+                        return Err(crate::RustbootError::__Nonexhaustive);
+                    }
+                },
+            };
             let res = verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(
                 prehashed_digest,
                 signature.as_ref(),
             );
             res
         }
-        _ => todo!(),
+        _ => Err(crate::RustbootError::InvalidValue),
     }
 }
 
 pub fn parse_algo<'a>(itb_blob: &'a [u8]) -> Result<CurveType> {
     let mut curve_type = CurveType::None;
-    let reader = Reader::read(itb_blob).unwrap();
+    let reader = Reader::read(itb_blob)?;
     let root = reader.struct_items();
-    let (_, node_iter) = root.path_struct_items("/configurations").next().unwrap();
+    let (_, node_iter) = root
+        .path_struct_items("/configurations")
+        .next()
+        .ok_or(Error::MissingNode)?;
 
     if let Some(config) = node_iter.get_node_property("default") {
         // parse the default config's signature algo
@@ -512,7 +690,10 @@ pub fn parse_algo<'a>(itb_blob: &'a [u8]) -> Result<CurveType> {
         let sig_node = config.concat::<50>("/signature\0".as_bytes());
         let sig_node = sig_node.as_str()?;
 
-        let (_, node_iter) = root.path_struct_items(sig_node).next().unwrap();
+        let (_, node_iter) = root
+            .path_struct_items(sig_node)
+            .next()
+            .ok_or(Error::MissingNode)?;
         let algo_val = node_iter.get_node_property("algo");
 
         match algo_val {
@@ -520,11 +701,12 @@ pub fn parse_algo<'a>(itb_blob: &'a [u8]) -> Result<CurveType> {
                 let algo = as_str(val)?;
                 match algo {
                     Some("sha256,ecdsa256,nistp256") => curve_type = CurveType::NistP256,
-                    _ => unimplemented!(),
+                    _ => return Err(Error::Unsupported),
                 }
             }
             None => {
-                panic!("no signing algorithm specified in supplied itb")
+                // no signing algorithm specified in supplied itb
+                return Err(Error::MissingProperty);
             }
         }
     };
@@ -532,24 +714,963 @@ pub fn parse_algo<'a>(itb_blob: &'a [u8]) -> Result<CurveType> {
 }
 
 pub fn get_image_data<'a>(itb_blob: &'a [u8], img: &'a str) -> Option<&'a [u8]> {
-    let mut img_path = "";
-    match img {
-        "kernel" => img_path = "/images/kernel",
-        "fdt" => img_path = "/images/fdt",
-        "ramdisk" => img_path = "/images/initrd",
-        "rbconfig" => img_path = "/images/rbconfig",
-        _ => {}
-    }
-    let reader = Reader::read(itb_blob).unwrap();
+    let reader = Reader::read(itb_blob).ok()?;
     let root = reader.struct_items();
-    let (_, node_iter) = root.path_struct_items(img_path).next().unwrap();
+    let (_, node_iter) = root.path_struct_items(image_path(img)).next()?;
     let data = node_iter.get_node_property("data");
     data
 }
 
+/// Returns the kernel command line carried by a fit-image's default config (i.e. its `bootargs`
+/// property), if there's one.
+///
+/// A config's `bootargs` are covered by the config's signature, so they can't be tampered with
+/// (unlike a command line that's read from the SD card, see [`crate::dt::select_bootargs`]).
+pub fn get_config_bootargs(itb_blob: &[u8]) -> Option<&str> {
+    let reader = Reader::read(itb_blob).ok()?;
+    let root = reader.struct_items();
+    let (_, node_iter) = root.path_struct_items("/configurations").next()?;
+    let config = "/configurations/".concat::<50>(node_iter.get_node_property("default")?);
+    let (_, node_iter) = root.path_struct_items(config.as_str().ok()?).next()?;
+    as_str(node_iter.get_node_property("bootargs")?).ok()?
+}
+
+/// Checks a fit-image's validity window (i.e. its default config's `not-before` and `not-after`
+/// properties) against `now`, the time per the board's RTC (in seconds since the unix epoch) or
+/// `None` if the RTC is unset, see [`ValidityPolicy`]. A fit-image without a validity window is
+/// always valid.
+///
+/// The validity window is covered by the config's signature i.e. the fit-image must have been
+/// verified first (see [`verify_fit`]).
+pub fn check_fit_validity(
+    itb_blob: &[u8],
+    now: Option<u64>,
+    policy: ValidityPolicy,
+) -> crate::Result<()> {
+    let (not_before, not_after) =
+        get_config_validity(itb_blob).map_err(|_| crate::RustbootError::InvalidValue)?;
+    policy.check(not_before, not_after, now)
+}
+
+/// Returns a fit-image's validity window i.e. its default config's `not-before` and `not-after`
+/// properties, if it carries them.
+pub fn get_config_validity(itb_blob: &[u8]) -> Result<(Option<u32>, Option<u32>)> {
+    let reader = Reader::read(itb_blob)?;
+    let configs = reader.find_node("/configurations")?;
+    let config = configs.find_child(configs.get_prop_str("default")?)?;
+    Ok((
+        optional(config.get_prop_u32("not-before"))?,
+        optional(config.get_prop_u32("not-after"))?,
+    ))
+}
+
+/// A config's loadable i.e. an image (ex: U-Boot or a newer rustBoot) that a board chainloads
+/// instead of booting the kernel, see [`get_config_loadable`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loadable<'a> {
+    /// the image's name i.e. its node in `/images`.
+    pub name: &'a str,
+    pub data: &'a [u8],
+    pub compression: Compression,
+    /// the image's `load` and `entry` addresses, if it carries them.
+    pub load: Option<u64>,
+    pub entry: Option<u64>,
+}
+
+impl<'a> Loadable<'a> {
+    /// Copies the image to `dst`, decompressing it as required. Returns its (decompressed) size.
+    pub fn load_into(&self, dst: &mut [u8]) -> Result<usize> {
+        copy_image(self.data, self.compression, dst)
+    }
+
+    /// Returns the image's entry point, as an offset from where it's loaded i.e. `entry - load`
+    /// (or `0` if it doesn't carry both). An entry point before `load` is [`Error::Unsupported`].
+    pub fn entry_offset(&self) -> Result<usize> {
+        match (self.load, self.entry) {
+            (Some(load), Some(entry)) => entry
+                .checked_sub(load)
+                .and_then(|offset| usize::try_from(offset).ok())
+                .ok_or(Error::Unsupported),
+            _ => Ok(0),
+        }
+    }
+}
+
+/// Returns a fit-image's loadable i.e. the image named by its default config's `loadables`
+/// property, if it carries one. Only a single loadable is supported, a list of them is
+/// [`Error::Unsupported`].
+///
+/// A config's loadable is covered by the config's signature i.e. the fit-image must have been
+/// verified first (see [`verify_fit`]).
+pub fn get_config_loadable(itb_blob: &[u8]) -> Result<Option<Loadable<'_>>> {
+    let reader = Reader::read(itb_blob)?;
+    let configs = reader.find_node("/configurations")?;
+    let config = configs.find_child(configs.get_prop_str("default")?)?;
+    let name = match optional(config.get_prop_str("loadables"))? {
+        Some(name) if name.contains('\0') => return Err(Error::Unsupported),
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let image = reader.find_node("/images")?.find_child(name)?;
+    let compression = match optional(image.get_prop_str("compression"))? {
+        Some(compression) => Compression::from_name(compression)?,
+        None => Compression::None,
+    };
+    Ok(Some(Loadable {
+        name,
+        data: image.get_prop("data")?,
+        compression,
+        load: optional(image.get_prop_addr("load"))?,
+        entry: optional(image.get_prop_addr("entry"))?,
+    }))
+}
+
+/// An image that a fit-image's default config references, as it's to be loaded i.e. its `load`
+/// address and (decompressed) length, see [`find_unmapped_load`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadRange<'a> {
+    /// the image's name i.e. its node in `/images`.
+    pub name: &'a str,
+    pub load: u64,
+    pub len: usize,
+}
+
+impl<'a> LoadRange<'a> {
+    /// Returns true if the image (i.e. `load` up to `load + len`) lies within one of `ranges`.
+    pub fn is_within(&self, ranges: &[RangeInclusive<u64>]) -> bool {
+        let last = match self.load.checked_add((self.len as u64).saturating_sub(1)) {
+            Some(last) => last,
+            None => return false,
+        };
+        ranges
+            .iter()
+            .any(|range| range.contains(&self.load) && range.contains(&last))
+    }
+}
+
+/// Returns the first image that a fit-image's default config references (i.e. its `kernel`,
+/// `fdt`, `ramdisk` or `loadables`) whose `load` address falls outside `mapped` (the board's RAM,
+/// as inclusive ranges of physical addresses), or `None` if they're all within it. Images that
+/// don't carry a `load` address are skipped.
+///
+/// An image's length is its decompressed size (see [`decompressed_size`]) i.e. an image whose
+/// size isn't known can't be checked and is [`Error::UnsupportedCompression`].
+pub fn find_unmapped_load<'a>(
+    itb_blob: &'a [u8],
+    mapped: &[RangeInclusive<u64>],
+) -> Result<Option<LoadRange<'a>>> {
+    let reader = Reader::read(itb_blob)?;
+    let configs = reader.find_node("/configurations")?;
+    let config = configs.find_child(configs.get_prop_str("default")?)?;
+    let images = reader.find_node("/images")?;
+    for prop in ["kernel", "fdt", "ramdisk", "loadables"].iter() {
+        let names = match optional(config.get_prop_str(prop))? {
+            Some(names) => names,
+            None => continue,
+        };
+        for name in names.split('\0') {
+            let image = images.find_child(name)?;
+            let load = match optional(image.get_prop_addr("load"))? {
+                Some(load) => load,
+                None => continue,
+            };
+            let compression = match optional(image.get_prop_str("compression"))? {
+                Some(compression) => Compression::from_name(compression)?,
+                None => Compression::None,
+            };
+            let range = LoadRange {
+                name,
+                load,
+                len: decompressed_size(image.get_prop("data")?, compression)?,
+            };
+            if !range.is_within(mapped) {
+                return Ok(Some(range));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Checks that a fit-image's images are to be loaded into the board's `mapped` memory, see
+/// [`find_unmapped_load`]. An image outside of it is [`crate::RustbootError::UnmappedLoadAddress`].
+///
+/// **note:**
+/// - `mapped` must be bounded by the RAM the board actually has (ex: as its firmware reports it),
+///   not by what its address space could hold.
+/// - `load` addresses aren't covered by the fit-image's signature i.e. an image's hash covers
+///   only its `data`. This is a sanity check, for boards that load images at their `load`
+///   addresses. It doesn't make a `load` address trustworthy.
+pub fn check_load_addresses(itb_blob: &[u8], mapped: &[RangeInclusive<u64>]) -> crate::Result<()> {
+    match find_unmapped_load(itb_blob, mapped) {
+        Ok(None) => Ok(()),
+        Ok(Some(image)) => {
+            info!(
+                "{}: load address {:#x} (+{:#x} bytes) is outside mapped memory",
+                image.name, image.load, image.len
+            );
+            Err(crate::RustbootError::UnmappedLoadAddress)
+        }
+        Err(_) => Err(crate::RustbootError::InvalidValue),
+    }
+}
+
+/// Returns the fdt carried by a fit-image, if its default config references one i.e. only an fdt
+/// that's covered by the config's signature. An `/images/fdt` node that the config doesn't
+/// reference isn't returned.
+pub fn get_config_fdt(itb_blob: &[u8]) -> Option<&[u8]> {
+    let reader = Reader::read(itb_blob).ok()?;
+    let root = reader.struct_items();
+    let (_, node_iter) = root.path_struct_items("/configurations").next()?;
+    let config = "/configurations/".concat::<50>(node_iter.get_node_property("default")?);
+    let (_, node_iter) = root.path_struct_items(config.as_str().ok()?).next()?;
+    node_iter.get_node_property("fdt")?;
+    get_image_data(itb_blob, "fdt")
+}
+
+/// Returns the node path of a rustBoot fit-image's image i.e. `kernel`, `fdt`, `ramdisk` or
+/// `rbconfig`.
+pub(crate) fn image_path(img: &str) -> &'static str {
+    match img {
+        "kernel" => "/images/kernel",
+        "fdt" => "/images/fdt",
+        "ramdisk" => "/images/initrd",
+        "rbconfig" => "/images/rbconfig",
+        _ => "",
+    }
+}
+
 pub fn as_str(bytes: &[u8]) -> Result<Option<&str>> {
     let val = core::str::from_utf8(bytes)
         .map_err(|val| Error::BadStrEncoding(val))?
         .strip_suffix("\u{0}");
     Ok(val)
 }
+
+/// Decodes a 4-byte (big-endian) property i.e. a fit-image's `timestamp` or a config's
+/// `not-before` and `not-after`.
+fn as_u32(bytes: &[u8]) -> Result<u32> {
+    Ok(u32::from_be_bytes(
+        bytes.try_into().map_err(|_| Error::BadU32List)?,
+    ))
+}
+
+/// Maps a missing (optional) property to `None`.
+fn optional<T>(prop: Result<T>) -> Result<Option<T>> {
+    match prop {
+        Err(Error::MissingProperty) => Ok(None),
+        prop => prop.map(Some),
+    }
+}
+
+/// Same as [`as_str`] but for properties that a rustBoot fit-image must contain i.e. a missing
+/// property or one that isn't a zero-terminated string is an error.
+fn required_str(bytes: Option<&[u8]>) -> Result<&str> {
+    as_str(bytes.ok_or(Error::MissingProperty)?)?.ok_or(Error::BadValueStr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::Path;
+
+    fn read_dtb_vec(buf: &mut Vec<u8>, name: &str) {
+        let filename = Path::new(file!())
+            .parent()
+            .unwrap()
+            .strip_prefix("rustBoot/")
+            .unwrap()
+            .join("test_dtb")
+            .join(String::from(name) + ".dtb");
+        let mut file = File::open(filename).unwrap();
+        file.read_to_end(buf).unwrap();
+    }
+
+    #[test]
+    fn test_not_a_fit_image() {
+        let mut buf = Vec::new();
+        read_dtb_vec(&mut buf, "sample");
+        let reader = Reader::read(buf.as_slice()).unwrap();
+        assert_eq!(
+            parse_fit::<Sha256, 32, 64, 4>(reader).unwrap_err(),
+            Error::MissingNode
+        );
+        assert_eq!(parse_algo(buf.as_slice()).unwrap_err(), Error::MissingNode);
+        assert_eq!(get_image_data(buf.as_slice(), "kernel"), None);
+        assert_eq!(get_config_bootargs(buf.as_slice()), None);
+        assert_eq!(get_config_fdt(buf.as_slice()), None);
+    }
+
+    /// Builds a minimal fit-image like blob with an `/images/fdt` node and a default config,
+    /// which references the fdt if `config_fdt` is set and carries the `validity` window.
+    fn fit_blob(fdt: &[u8], config_fdt: bool, validity: (Option<u32>, Option<u32>)) -> Vec<u8> {
+        fit_blob_with(fdt, config_fdt, validity, None)
+    }
+
+    /// Same as [`fit_blob`], with an `/images/uboot` node (i.e. its `data`, a 2-cell `load` address
+    /// of `0x80000` and an `entry` of `0x80100`) if there's a `loadable`, which the config names
+    /// with the given `loadables` value.
+    fn fit_blob_with(
+        fdt: &[u8],
+        config_fdt: bool,
+        validity: (Option<u32>, Option<u32>),
+        loadable: Option<(&[u8], &[u8])>,
+    ) -> Vec<u8> {
+        use crate::dt::internal::{DTB_MAGIC, TOK_BEGIN_NODE, TOK_END, TOK_END_NODE, TOK_PROPERTY};
+        // name offsets into `strings`
+        const DEFAULT: u32 = 0;
+        const FDT: u32 = 8;
+        const DATA: u32 = 12;
+        const NOT_BEFORE: u32 = 17;
+        const NOT_AFTER: u32 = 28;
+        const LOADABLES: u32 = 38;
+        const LOAD: u32 = 48;
+        const ENTRY: u32 = 53;
+        let strings = b"default\0fdt\0data\0not-before\0not-after\0loadables\0load\0entry\0";
+        let mut st = Vec::new();
+        let push_u32 = |v: &mut Vec<u8>, val: u32| v.extend_from_slice(&val.to_be_bytes());
+        let pad = |v: &mut Vec<u8>| {
+            while v.len() % 4 != 0 {
+                v.push(0);
+            }
+        };
+        let begin_node = |v: &mut Vec<u8>, name: &[u8]| {
+            v.extend_from_slice(&TOK_BEGIN_NODE.to_be_bytes());
+            v.extend_from_slice(name);
+            v.push(0);
+            pad(v);
+        };
+        let push_prop = |v: &mut Vec<u8>, name_offset: u32, value: &[u8]| {
+            v.extend_from_slice(&TOK_PROPERTY.to_be_bytes());
+            v.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            v.extend_from_slice(&name_offset.to_be_bytes());
+            v.extend_from_slice(value);
+            pad(v);
+        };
+        begin_node(&mut st, b"");
+        begin_node(&mut st, b"images");
+        begin_node(&mut st, b"fdt");
+        push_prop(&mut st, DATA, fdt);
+        push_u32(&mut st, TOK_END_NODE);
+        if let Some((_, data)) = loadable {
+            begin_node(&mut st, b"uboot");
+            push_prop(&mut st, DATA, data);
+            push_prop(&mut st, LOAD, &0x80000u64.to_be_bytes());
+            push_prop(&mut st, ENTRY, &0x80100u32.to_be_bytes());
+            push_u32(&mut st, TOK_END_NODE);
+        }
+        push_u32(&mut st, TOK_END_NODE);
+        begin_node(&mut st, b"configurations");
+        push_prop(&mut st, DEFAULT, b"conf\0");
+        begin_node(&mut st, b"conf");
+        if config_fdt {
+            push_prop(&mut st, FDT, b"fdt\0");
+        }
+        if let Some(time) = validity.0 {
+            push_prop(&mut st, NOT_BEFORE, &time.to_be_bytes());
+        }
+        if let Some(time) = validity.1 {
+            push_prop(&mut st, NOT_AFTER, &time.to_be_bytes());
+        }
+        if let Some((name, _)) = loadable {
+            push_prop(&mut st, LOADABLES, name);
+        }
+        push_u32(&mut st, TOK_END_NODE);
+        push_u32(&mut st, TOK_END_NODE);
+        push_u32(&mut st, TOK_END_NODE);
+        push_u32(&mut st, TOK_END);
+
+        let rsvmap_offset = 40u32;
+        let struct_offset = rsvmap_offset + 16;
+        let strings_offset = struct_offset + st.len() as u32;
+        let total_size = strings_offset + strings.len() as u32;
+        let mut blob = Vec::new();
+        for val in [
+            DTB_MAGIC,
+            total_size,
+            struct_offset,
+            strings_offset,
+            rsvmap_offset,
+            17,
+            16,
+            0,
+            strings.len() as u32,
+            st.len() as u32,
+        ] {
+            push_u32(&mut blob, val);
+        }
+        blob.extend_from_slice(&[0; 16]);
+        blob.extend_from_slice(&st);
+        blob.extend_from_slice(strings);
+        blob
+    }
+
+    #[test]
+    fn test_config_fdt() {
+        let fdt = [0xAAu8; 32];
+        let blob = fit_blob(&fdt, true, (None, None));
+        assert_eq!(get_config_fdt(blob.as_slice()), Some(fdt.as_slice()));
+        // an fdt that the config doesn't reference isn't covered by its signature
+        let blob = fit_blob(&fdt, false, (None, None));
+        assert_eq!(get_image_data(blob.as_slice(), "fdt"), Some(fdt.as_slice()));
+        assert_eq!(get_config_fdt(blob.as_slice()), None);
+    }
+
+    #[test]
+    fn test_config_validity() {
+        let blob = fit_blob(&[0xAA; 32], true, (None, None));
+        assert_eq!(get_config_validity(blob.as_slice()), Ok((None, None)));
+        assert_eq!(
+            check_fit_validity(blob.as_slice(), None, ValidityPolicy::Refuse),
+            Ok(())
+        );
+
+        let blob = fit_blob(&[0xAA; 32], true, (Some(1000), Some(2000)));
+        assert_eq!(
+            get_config_validity(blob.as_slice()),
+            Ok((Some(1000), Some(2000)))
+        );
+        assert_eq!(
+            check_fit_validity(blob.as_slice(), Some(1500), ValidityPolicy::Refuse),
+            Ok(())
+        );
+        assert_eq!(
+            check_fit_validity(blob.as_slice(), Some(2001), ValidityPolicy::Allow),
+            Err(crate::RustbootError::ImageExpired)
+        );
+        assert_eq!(
+            check_fit_validity(blob.as_slice(), None, ValidityPolicy::Refuse),
+            Err(crate::RustbootError::TimeUnavailable)
+        );
+        assert_eq!(
+            check_fit_validity(blob.as_slice(), None, ValidityPolicy::Allow),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_config_loadable() {
+        let blob = fit_blob(&[0xAA; 32], true, (None, None));
+        assert_eq!(get_config_loadable(blob.as_slice()), Ok(None));
+
+        let uboot = [0x55u8; 40];
+        let blob = fit_blob_with(&[0xAA; 32], true, (None, None), Some((b"uboot\0", &uboot)));
+        let loadable = get_config_loadable(blob.as_slice()).unwrap().unwrap();
+        assert_eq!(loadable.name, "uboot");
+        assert_eq!(loadable.data, uboot.as_slice());
+        assert_eq!(loadable.compression, Compression::None);
+        assert_eq!(
+            (loadable.load, loadable.entry),
+            (Some(0x80000), Some(0x80100))
+        );
+        assert_eq!(loadable.entry_offset(), Ok(0x100));
+        let mut buf = [0u8; 64];
+        assert_eq!(loadable.load_into(&mut buf), Ok(uboot.len()));
+        assert_eq!(&buf[..uboot.len()], uboot.as_slice());
+        assert_eq!(
+            loadable.load_into(&mut [0u8; 32]),
+            Err(Error::BufferTooSmall)
+        );
+        let before_load = Loadable {
+            entry: Some(0x7f000),
+            ..loadable
+        };
+        assert_eq!(before_load.entry_offset(), Err(Error::Unsupported));
+
+        // only a single loadable is supported, and it must exist
+        let blob = fit_blob_with(
+            &[0xAA; 32],
+            true,
+            (None, None),
+            Some((b"uboot\0fdt\0", &uboot)),
+        );
+        assert_eq!(
+            get_config_loadable(blob.as_slice()),
+            Err(Error::Unsupported)
+        );
+        let blob = fit_blob_with(&[0xAA; 32], true, (None, None), Some((b"atf\0", &uboot)));
+        assert_eq!(
+            get_config_loadable(blob.as_slice()),
+            Err(Error::MissingNode)
+        );
+    }
+
+    #[test]
+    fn test_load_addresses() {
+        // the rpi4's (8GiB) RAM, around the low peripherals
+        let mapped = [0..=0xFBFF_FFFF, 0x1_0000_0000..=0x1_FFFF_FFFF];
+        let blob = fit_blob(&[0xAA; 32], true, (None, None));
+        assert_eq!(find_unmapped_load(blob.as_slice(), &mapped), Ok(None));
+
+        let blob = fit_blob_with(
+            &[0xAA; 32],
+            true,
+            (None, None),
+            Some((b"uboot\0", &[1; 40])),
+        );
+        assert_eq!(find_unmapped_load(blob.as_slice(), &mapped), Ok(None));
+        assert_eq!(check_load_addresses(blob.as_slice(), &mapped), Ok(()));
+        let uboot = LoadRange {
+            name: "uboot",
+            load: 0x80000,
+            len: 40,
+        };
+        // the image's end must be mapped too
+        for mapped in [0x1_0000_0000..=0x1_FFFF_FFFF, 0..=0x80026].iter() {
+            let mapped = [mapped.clone()];
+            assert_eq!(
+                find_unmapped_load(blob.as_slice(), &mapped),
+                Ok(Some(uboot))
+            );
+            assert_eq!(
+                check_load_addresses(blob.as_slice(), &mapped),
+                Err(crate::RustbootError::UnmappedLoadAddress)
+            );
+        }
+
+        let kernel = LoadRange {
+            name: "kernel",
+            load: 0x1_4000_0000,
+            len: 0x100_0000,
+        };
+        assert!(kernel.is_within(&mapped));
+        // across the low peripherals, or past the end of RAM
+        assert!(!LoadRange {
+            load: 0xFB80_0000,
+            ..kernel
+        }
+        .is_within(&mapped));
+        assert!(!LoadRange {
+            load: 0x1_FF80_0000,
+            ..kernel
+        }
+        .is_within(&mapped));
+        assert!(!LoadRange {
+            load: u64::MAX,
+            ..kernel
+        }
+        .is_within(&[0..=u64::MAX]));
+    }
+
+    #[test]
+    fn test_compressed_load_range() {
+        // a (truncated) gzip member, whose trailer records a 1MiB image
+        let mut gzipped = [0u8; 20];
+        gzipped[..2].copy_from_slice(&[0x1f, 0x8b]);
+        gzipped[16..].copy_from_slice(&0x10_0000u32.to_le_bytes());
+        let blob = FdtBuilder::default()
+            .begin_node("")
+            .begin_node("images")
+            .begin_node("kernel")
+            .prop("data", &gzipped)
+            .prop("compression", b"gzip\0")
+            .prop("load", &0x8_0000u64.to_be_bytes())
+            .end_node()
+            .end_node()
+            .begin_node("configurations")
+            .prop("default", b"conf\0")
+            .begin_node("conf")
+            .prop("kernel", b"kernel\0")
+            .end_node()
+            .end_node()
+            .end_node()
+            .finish();
+        // the image's data fits, but not once it's decompressed
+        let kernel = LoadRange {
+            name: "kernel",
+            load: 0x8_0000,
+            len: 0x10_0000,
+        };
+        assert_eq!(
+            find_unmapped_load(blob.as_slice(), &[0..=0xF_FFFF]),
+            Ok(Some(kernel))
+        );
+        assert_eq!(
+            find_unmapped_load(blob.as_slice(), &[0..=0x17_FFFF]),
+            Ok(None)
+        );
+    }
+
+    /// A minimal flattened device-tree writer, for blobs that are more than a few nodes deep.
+    #[derive(Default)]
+    struct FdtBuilder {
+        st: Vec<u8>,
+        strings: Vec<u8>,
+    }
+
+    impl FdtBuilder {
+        fn begin_node(&mut self, name: &str) -> &mut Self {
+            self.push_u32(crate::dt::internal::TOK_BEGIN_NODE);
+            self.st.extend_from_slice(name.as_bytes());
+            self.st.push(0);
+            self.pad();
+            self
+        }
+
+        fn end_node(&mut self) -> &mut Self {
+            self.push_u32(crate::dt::internal::TOK_END_NODE);
+            self
+        }
+
+        fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+            let name_offset = self.strings.len() as u32;
+            self.strings.extend_from_slice(name.as_bytes());
+            self.strings.push(0);
+            self.push_u32(crate::dt::internal::TOK_PROPERTY);
+            self.push_u32(value.len() as u32);
+            self.push_u32(name_offset);
+            self.st.extend_from_slice(value);
+            self.pad();
+            self
+        }
+
+        fn finish(&mut self) -> Vec<u8> {
+            use crate::dt::internal::{DTB_MAGIC, TOK_END};
+            self.push_u32(TOK_END);
+            let rsvmap_offset = 40u32;
+            let struct_offset = rsvmap_offset + 16;
+            let strings_offset = struct_offset + self.st.len() as u32;
+            let total_size = strings_offset + self.strings.len() as u32;
+            let mut blob = Vec::new();
+            for val in [
+                DTB_MAGIC,
+                total_size,
+                struct_offset,
+                strings_offset,
+                rsvmap_offset,
+                17,
+                16,
+                0,
+                self.strings.len() as u32,
+                self.st.len() as u32,
+            ] {
+                blob.extend_from_slice(&val.to_be_bytes());
+            }
+            blob.extend_from_slice(&[0; 16]);
+            blob.extend_from_slice(&self.st);
+            blob.extend_from_slice(&self.strings);
+            blob
+        }
+
+        fn push_u32(&mut self, val: u32) {
+            self.st.extend_from_slice(&val.to_be_bytes());
+        }
+
+        fn pad(&mut self) {
+            while self.st.len() % 4 != 0 {
+                self.st.push(0);
+            }
+        }
+    }
+
+    /// The parts of a rustBoot fit-image that tests tamper with, see [`rustboot_fit`].
+    #[derive(Clone, Copy)]
+    struct FitSpec<'a> {
+        kernel: &'a [u8],
+        /// a `data` property in a node nested in `/images/kernel`, ahead of the kernel's own.
+        nested_data: Option<&'a [u8]>,
+        bootargs: Option<&'a [u8]>,
+        not_after: Option<u32>,
+        signed_images: &'a [u8],
+        signature: [u8; 64],
+    }
+
+    const SPEC: FitSpec = FitSpec {
+        kernel: b"GOODGOOD",
+        nested_data: None,
+        bootargs: None,
+        not_after: None,
+        signed_images: b"kernel\0fdt\0ramdisk\0rbconfig\0",
+        signature: [0; 64],
+    };
+    const TIMESTAMP: u32 = 1_700_000_000;
+
+    /// Builds a rustBoot fit-image i.e. a `kernel`, `fdt`, `ramdisk` and `rbconfig` image (each
+    /// with its hash) and a default config with a signature node (see [`signed_fit`]).
+    fn rustboot_fit(spec: &FitSpec) -> Vec<u8> {
+        let mut fdt = FdtBuilder::default();
+        fdt.begin_node("")
+            .prop("timestamp", &TIMESTAMP.to_be_bytes())
+            .begin_node("images");
+        let images: [(&str, &[u8]); 4] = [
+            ("kernel", spec.kernel),
+            ("fdt", b"fdt"),
+            ("ramdisk", b"ramdisk"),
+            ("rbconfig", b"rbconfig"),
+        ];
+        for (name, data) in images.iter() {
+            fdt.begin_node(name);
+            if let (Some(nested), &"kernel") = (spec.nested_data, name) {
+                fdt.begin_node("x").prop("data", nested).end_node();
+            }
+            fdt.prop("description", b"test image\0")
+                .prop("data", data)
+                .prop("type", b"kernel\0")
+                .prop("arch", b"arm64\0")
+                .prop("os", b"linux\0")
+                .prop("compression", b"none\0")
+                .begin_node("hash")
+                .prop("value", Sha256::digest(data).as_slice())
+                .prop("algo", b"sha256\0")
+                .end_node()
+                .end_node();
+        }
+        fdt.end_node()
+            .begin_node("configurations")
+            .prop("default", b"conf\0")
+            .begin_node("conf")
+            .prop("description", b"test config\0")
+            .prop("kernel", b"kernel\0")
+            .prop("fdt", b"fdt\0")
+            .prop("ramdisk", b"ramdisk\0")
+            .prop("rbconfig", b"rbconfig\0");
+        if let Some(bootargs) = spec.bootargs {
+            fdt.prop("bootargs", bootargs);
+        }
+        if let Some(time) = spec.not_after {
+            fdt.prop("not-after", &time.to_be_bytes());
+        }
+        fdt.begin_node("signature@1")
+            .prop("algo", b"sha256,ecdsa256,nistp256\0")
+            .prop("key-name-hint", b"dev\0")
+            .prop("signed-images", spec.signed_images)
+            .prop("value", &spec.signature)
+            .end_node()
+            .end_node()
+            .end_node()
+            .end_node()
+            .finish()
+    }
+
+    #[test]
+    fn test_streamed_digest_of_nested_data() {
+        let digests = |blob: &[u8]| {
+            let mut digester = crate::dt::Sha256FitDigester::new();
+            digester.update(blob);
+            digester.finalize(blob).unwrap()
+        };
+        let blob = rustboot_fit(&SPEC);
+        let reader = Reader::read(blob.as_slice()).unwrap();
+        assert!(parse_fit_with::<Sha256, 32, 64, 4>(reader, Some(&digests(&blob))).is_ok());
+
+        // the streamed digest is the kernel's own `data`'s. It mustn't vouch for a nested node's
+        // `data`, which is read back ahead of it.
+        let blob = rustboot_fit(&FitSpec {
+            nested_data: Some(b"EVILEVIL"),
+            ..SPEC
+        });
+        let reader = Reader::read(blob.as_slice()).unwrap();
+        assert!(parse_fit_with::<Sha256, 32, 64, 4>(reader, Some(&digests(&blob))).is_ok());
+        assert_eq!(get_image_data(blob.as_slice(), "kernel"), Some(SPEC.kernel));
+    }
+
+    #[test]
+    fn test_nested_node_properties() {
+        let blob = FdtBuilder::default()
+            .begin_node("")
+            .begin_node("node")
+            .begin_node("a")
+            .begin_node("b")
+            .prop("value", b"nested")
+            .end_node()
+            .end_node()
+            .prop("value", b"own")
+            .end_node()
+            .end_node()
+            .finish();
+        let reader = Reader::read(blob.as_slice()).unwrap();
+        let root = reader.struct_items();
+        let (_, node_iter) = root.path_struct_items("/node").next().unwrap();
+        // a (doubly) nested node's property isn't the node's own
+        assert_eq!(
+            node_iter.clone().get_node_property("value"),
+            Some(&b"own"[..])
+        );
+        assert_eq!(node_iter.get_node_property("missing"), None);
+    }
+
+    /// the signing key matching the embedded public key i.e. `boards/sign_images/keygen/ecc256.der`
+    const SIGNING_KEY: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    /// Signs `spec`'s fit-image the way `rbsigner` does i.e. returns its config's signature.
+    fn sign(spec: &FitSpec) -> [u8; 64] {
+        let (digest, _) =
+            prepare_img_hash::<Sha256, 32, 64, 4>(&rustboot_fit(spec), TIMESTAMP).unwrap();
+        let signature: Signature = SigningKey::from_bytes(&SIGNING_KEY)
+            .unwrap()
+            .sign_digest(digest);
+        let mut value = [0u8; 64];
+        value.copy_from_slice(signature.as_ref());
+        value
+    }
+
+    #[test]
+    fn test_relocated_bootargs() {
+        let verify = |spec: &FitSpec| verify_fit::<32, 64, 4>(&rustboot_fit(spec), TIMESTAMP);
+        let original = FitSpec {
+            bootargs: Some(b"root=/dev/mmcblk0p2\0"),
+            ..SPEC
+        };
+        let original = FitSpec {
+            signature: sign(&original),
+            ..original
+        };
+        assert_eq!(verify(&original), Ok(true));
+        assert_eq!(
+            get_config_bootargs(&rustboot_fit(&original)),
+            Some("root=/dev/mmcblk0p2")
+        );
+        // the kernel command line moved into `signed-images` and `bootargs` dropped i.e. one that
+        // would have the bootloader fall back to an unsigned command line
+        let tampered = FitSpec {
+            bootargs: None,
+            signed_images: b"kernel\0fdt\0ramdisk\0rbconfigroot=/dev/mmcblk0p2\0",
+            ..original
+        };
+        assert_ne!(verify(&tampered), Ok(true));
+    }
+
+    #[test]
+    fn test_relocated_validity_window() {
+        // an ascii `not-after` i.e. one that can be passed off as (part of) a string
+        let not_after = 0x7000_0000u32;
+        let verify = |spec: &FitSpec| verify_fit::<32, 64, 4>(&rustboot_fit(spec), TIMESTAMP);
+
+        let original = FitSpec {
+            bootargs: Some(b"console=ttyS0\0"),
+            not_after: Some(not_after),
+            ..SPEC
+        };
+        let original = FitSpec {
+            signature: sign(&original),
+            ..original
+        };
+        assert_eq!(verify(&original), Ok(true));
+        // `not-after` dropped and appended to the kernel command line
+        let bootargs = [
+            &b"console=ttyS0"[..],
+            b"not-after",
+            &not_after.to_be_bytes(),
+            b"\0",
+        ]
+        .concat();
+        let tampered = FitSpec {
+            bootargs: Some(&bootargs),
+            not_after: None,
+            ..original
+        };
+        assert_ne!(verify(&tampered), Ok(true));
+
+        // without a kernel command line, `not-after` appended to `signed-images` instead
+        let original = FitSpec {
+            not_after: Some(not_after),
+            ..SPEC
+        };
+        let original = FitSpec {
+            signature: sign(&original),
+            ..original
+        };
+        assert_eq!(verify(&original), Ok(true));
+        let signed_images = [
+            &b"kernel\0fdt\0ramdisk\0rbconfig"[..],
+            b"not-after",
+            &not_after.to_be_bytes(),
+            b"\0",
+        ]
+        .concat();
+        let tampered = FitSpec {
+            signed_images: &signed_images,
+            not_after: None,
+            ..original
+        };
+        assert_ne!(verify(&tampered), Ok(true));
+    }
+
+    #[test]
+    fn test_corrupted_fit() {
+        let fdt = [0xAAu8; 8];
+        let buf = fit_blob_with(
+            &fdt,
+            true,
+            (Some(1000), Some(2000)),
+            Some((b"uboot\0", &[1; 8])),
+        );
+        let reader = Reader::read(buf.as_slice()).unwrap();
+        let conf = reader.find_node("/configurations/conf").unwrap();
+        assert_eq!(conf.get_prop_u32("not-after"), Ok(2000));
+        assert_eq!(
+            reader.find_node("/images/fdt").unwrap().get_prop("data"),
+            Ok(fdt.as_slice())
+        );
+
+        // a fit-image is parsed before it's verified i.e. corrupted blobs must be rejected (or
+        // read) without panicking
+        let check = |blob: &[u8]| {
+            if let Ok(reader) = Reader::read(blob) {
+                for child in reader
+                    .find_node("/images")
+                    .iter()
+                    .flat_map(crate::dt::Node::iter_children)
+                {
+                    let _ = child.and_then(|child| child.get_prop("data"));
+                }
+                let _ = parse_fit::<Sha256, 32, 64, 4>(reader);
+            }
+            let _ = parse_algo(blob);
+            let _ = get_image_data(blob, "fdt");
+            let _ = get_config_bootargs(blob);
+            let _ = get_config_fdt(blob);
+            let _ = get_config_validity(blob);
+            let _ = get_config_loadable(blob).map(|loadable| loadable.map(|l| l.entry_offset()));
+            let _ = find_unmapped_load(blob, &[0..=u64::MAX]);
+        };
+        let mut words = vec![0u64; buf.len() / 8 + 1];
+        let blob =
+            unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, buf.len()) };
+        for pos in 0..buf.len() {
+            for val in [0x00, 0x01, 0x7f, 0x80, 0xff] {
+                blob.copy_from_slice(&buf);
+                blob[pos] = val;
+                check(blob);
+            }
+            if pos % 4 == 0 && pos + 4 <= buf.len() {
+                for val in [0u32, 1, 2, 3, 9, 0x8000_0000, u32::MAX] {
+                    blob.copy_from_slice(&buf);
+                    blob[pos..pos + 4].copy_from_slice(&val.to_be_bytes());
+                    check(blob);
+                }
+            }
+        }
+        for len in 0..buf.len() {
+            blob.copy_from_slice(&buf);
+            if len >= 8 {
+                blob[4..8].copy_from_slice(&(len as u32).to_be_bytes());
+            }
+            check(&blob[..len]);
+        }
+    }
+
+    #[test]
+    fn test_malformed_blob() {
+        let mut buf = Vec::new();
+        read_dtb_vec(&mut buf, "bad_total_size");
+        assert_eq!(parse_algo(buf.as_slice()).unwrap_err(), Error::BadTotalSize);
+        assert_eq!(
+            prepare_img_hash::<Sha256, 32, 64, 4>(buf.as_slice(), 0).unwrap_err(),
+            Error::BadTotalSize
+        );
+        assert_eq!(get_image_data(buf.as_slice(), "kernel"), None);
+        assert_eq!(get_config_bootargs(buf.as_slice()), None);
+        assert_eq!(get_config_fdt(buf.as_slice()), None);
+        assert_eq!(
+            get_config_validity(buf.as_slice()),
+            Err(Error::BadTotalSize)
+        );
+    }
+}
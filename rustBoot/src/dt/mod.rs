@@ -1,16 +1,20 @@
 mod common;
+mod decompress;
 #[macro_use]
 mod fit;
 #[cfg_attr(test, macro_use)]
 mod internal;
 pub mod patch;
 mod reader;
+mod stream;
 mod struct_item;
 mod writer;
 
 pub use common::*;
+pub use decompress::*;
 pub use fit::*;
 pub use patch::*;
 pub use reader::*;
+pub use stream::*;
 pub use struct_item::*;
 pub use writer::*;
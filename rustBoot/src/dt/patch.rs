@@ -3,9 +3,46 @@ use super::{
     Error, PropertyValue, RawNodeConstructor, RawPropertyConstructor, Reader, Result,
     SerializedBuffer, StringsBlock, StructItem, TOKEN_SIZE,
 };
+use crate::RustbootError;
 use as_slice::AsSlice;
 use core::convert::TryInto;
 
+/// The `chosen` node's property an update's outcome is reported in, see [`UpdateReport`].
+pub const UPDATE_STATUS_PROP: &str = "rustboot,update-status";
+
+/// The outcome of an update (i.e. of booting a passive fit-image), reported to the kernel as the
+/// `chosen` node's `rustboot,update-status = <result version>` property, so userspace (ex: an OTA
+/// agent) can read it from `/proc/device-tree/chosen/rustboot,update-status`.
+///
+/// - `result` is `0` if the update was booted, else the [`RustbootError::code`] of the check that
+///   failed i.e. that made rustBoot fall back to the active fit-image.
+/// - `version` is the passive fit-image's version i.e. the version that was attempted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateReport {
+    pub result: u32,
+    pub version: u32,
+}
+
+impl UpdateReport {
+    /// The update (i.e. the passive fit-image) with `version` was booted.
+    pub fn booted(version: u32) -> Self {
+        UpdateReport { result: 0, version }
+    }
+
+    /// The update with `version` failed verification with `error`.
+    pub fn failed(error: RustbootError, version: u32) -> Self {
+        UpdateReport {
+            result: error.code() as u32,
+            version,
+        }
+    }
+
+    /// Returns the property's value i.e. `<result version>`.
+    pub fn property_value(&self) -> PropertyValue<'static> {
+        PropertyValue::U64(((self.result as u64) << 32 | self.version as u64).to_be_bytes())
+    }
+}
+
 pub fn make_new_strings_block_with<'a, const M: usize>(
     name_list: &'a [&str],
     new_strings_block: &'a mut StringsBlock<'a>,
@@ -60,7 +97,10 @@ pub fn parse_raw_node<'a, const N: usize>(
     dtb_blob: &[u8],
 ) -> Result<[(&'a str, NodeItems<'a>, usize); N]> {
     let root = &reader.struct_items();
-    let (_, node_iter) = root.path_struct_items(node_path).next().unwrap();
+    let (_, node_iter) = root
+        .path_struct_items(node_path)
+        .next()
+        .ok_or(Error::MissingNode)?;
     let mut prop_list = [("", NodeItems::None, 0usize); N];
 
     let header = Reader::get_header(dtb_blob)?;
@@ -140,6 +180,10 @@ pub fn check_chosen_node<'a, const N: usize, const M: usize>(
             "linux,initrd-end" => {
                 len_to_be_subtracted += len;
             }
+            // a stale report (ex: one left in a standalone dtb) is dropped
+            UPDATE_STATUS_PROP => {
+                len_to_be_subtracted += len;
+            }
             "" => {}
             _ => match item {
                 NodeItems::None => {}
@@ -183,13 +227,16 @@ pub fn update_dtb_header(
     header
 }
 
-pub fn get_padded_node_len<'a>(reader: &Reader<'a>, node_name: &str) -> usize {
+pub fn get_padded_node_len<'a>(reader: &Reader<'a>, node_name: &str) -> Result<usize> {
     let root = reader.struct_items();
-    let (node, _) = root.path_struct_items(node_name).next().unwrap();
+    let (node, _) = root
+        .path_struct_items(node_name)
+        .next()
+        .ok_or(Error::MissingNode)?;
 
-    let node_len = TOKEN_SIZE + node.node_name().unwrap().len();
+    let node_len = TOKEN_SIZE + node.node_name()?.len();
     let padded_node_len = node_len + (node_len % 4);
-    padded_node_len
+    Ok(padded_node_len)
 }
 
 pub fn get_node_start_and_end<'a>(
@@ -199,12 +246,15 @@ pub fn get_node_start_and_end<'a>(
     node_size: usize,
 ) -> Result<(usize, usize)> {
     let root = reader.struct_items();
-    let (node, node_iter) = root.path_struct_items(node_name).next().unwrap();
+    let (node, node_iter) = root
+        .path_struct_items(node_name)
+        .next()
+        .ok_or(Error::MissingNode)?;
 
     let header = Reader::get_header(dtb_blob)?;
     let struct_offset = header.struct_offset as usize;
 
-    let node_len = TOKEN_SIZE + node.node_name().unwrap().len();
+    let node_len = TOKEN_SIZE + node.node_name()?.len();
     let padded_node_len = node_len + (node_len % 4);
     let node_start = (node_iter.get_offset() + struct_offset as usize) - padded_node_len;
     let node_end = node_start + padded_node_len + node_size;
@@ -249,48 +299,68 @@ pub fn patch_dtb_node<'a, const N: usize>(
     patched_dtb_blob[slice_4].copy_from_slice(strings_block_patch);
 }
 
+/// Patches `dtb_blob`'s `chosen` node with `prop_val_list` (i.e. `bootargs`, `linux,initrd-start`
+/// and `linux,initrd-end`) and writes the patched dtb to `new_dtb_buffer`. Returns the buffer and
+/// the patched dtb's length.
+///
+/// Fails if the dtb has no `chosen` node or if the patched dtb doesn't fit in `new_dtb_buffer`.
 pub fn patch_chosen_node<'a, const N: usize>(
     reader: Reader<'a>,
     dtb_blob: &'a [u8],
     prop_val_list: &[PropertyValue],
     new_dtb_buffer: &'a mut [u8; N],
-) -> (&'a mut [u8; N], usize) {
+) -> Result<(&'a mut [u8; N], usize)> {
+    let name_list = ["bootargs", "linux,initrd-start", "linux,initrd-end"];
+    patch_chosen_node_with(reader, dtb_blob, name_list, prop_val_list, new_dtb_buffer)
+}
+
+/// Same as [`patch_chosen_node`] but also reports an update's outcome to the kernel i.e. adds a
+/// `rustboot,update-status` property (see [`UpdateReport`]) to the `chosen` node.
+pub fn patch_chosen_node_with_report<'a, const N: usize>(
+    reader: Reader<'a>,
+    dtb_blob: &'a [u8],
+    prop_val_list: &[PropertyValue; 3],
+    report: UpdateReport,
+    new_dtb_buffer: &'a mut [u8; N],
+) -> Result<(&'a mut [u8; N], usize)> {
+    let [bootargs, initrd_start, initrd_end] = *prop_val_list;
+    let name_list = [
+        "bootargs",
+        "linux,initrd-start",
+        "linux,initrd-end",
+        UPDATE_STATUS_PROP,
+    ];
+    let prop_val_list = [bootargs, initrd_start, initrd_end, report.property_value()];
+    patch_chosen_node_with(reader, dtb_blob, name_list, &prop_val_list, new_dtb_buffer)
+}
+
+fn patch_chosen_node_with<'a, const N: usize, const M: usize>(
+    reader: Reader<'a>,
+    dtb_blob: &'a [u8],
+    name_list: [&str; M],
+    prop_val_list: &[PropertyValue],
+    new_dtb_buffer: &'a mut [u8; N],
+) -> Result<(&'a mut [u8; N], usize)> {
     let mut buf = [0; 100];
-    let mut new_strings_block = StringsBlock::new(&mut buf[..]).unwrap();
+    let mut new_strings_block = StringsBlock::new(&mut buf[..])?;
 
-    let name_list = ["bootargs", "linux,initrd-start", "linux,initrd-end"];
-    let res = make_new_strings_block_with::<3>(&name_list, &mut new_strings_block, dtb_blob);
-    let (offset_list, strings_block_patch, strings_block_patch_len) = match res {
-        Ok((strings_block, offset_list)) => (offset_list, strings_block, strings_block.len()),
-        Err(e) => panic!("error: {:?}", e),
-    };
+    let (strings_block_patch, offset_list) =
+        make_new_strings_block_with::<M>(&name_list, &mut new_strings_block, dtb_blob)?;
+    let strings_block_patch_len = strings_block_patch.len();
 
     let node_name = "chosen";
-    let prop_val_list = prop_val_list;
-    let res = make_node_with_props::<200>(node_name, &prop_val_list, &offset_list);
-    let (patch_bytes_1_len, patch_bytes_1) = match res {
-        Ok((patch_bytes_1_len, patch_bytes_1)) => (patch_bytes_1_len, patch_bytes_1),
-        Err(e) => panic!("error: {:?}", e),
-    };
+    let (patch_bytes_1_len, patch_bytes_1) =
+        make_node_with_props::<256>(node_name, prop_val_list, &offset_list)?;
     let patch_bytes_1 = &patch_bytes_1[..patch_bytes_1_len];
 
-    let res = parse_raw_node::<10>(&reader, "/chosen", dtb_blob);
-    let parsed_node = match res {
-        Ok(val) => val,
-        Err(e) => panic!("error: {:?}", e),
-    };
-
-    let res = check_chosen_node::<10, 200>(parsed_node);
-    let (patch_bytes_2, len_to_be_subtracted) = match res {
-        Ok((buf, len_to_be_subtracted)) => (buf, len_to_be_subtracted),
-        Err(e) => panic!("error: {:?}", e),
-    };
+    let parsed_node = parse_raw_node::<10>(&reader, "/chosen", dtb_blob)?;
+    let (patch_bytes_2, len_to_be_subtracted) = check_chosen_node::<10, 200>(parsed_node)?;
     // `patch_bytes_1_len` includes a `BEGIN_NODE`, we have to subtract it from the new length.
     // i.e. the `chosen` node takes up 12 bytes (0x00000001 + "chosen" + padding)
-    let padded_node_len = get_padded_node_len(&reader, "/chosen");
+    let padded_node_len = get_padded_node_len(&reader, "/chosen")?;
     let new_node_len = patch_bytes_1_len + patch_bytes_2.as_slice().len() - padded_node_len;
 
-    let mut header = Reader::get_header(dtb_blob).unwrap();
+    let mut header = Reader::get_header(dtb_blob)?;
     {
         let _ = update_dtb_header(
             &mut header,
@@ -301,10 +371,19 @@ pub fn patch_chosen_node<'a, const N: usize>(
     }
 
     let (node_start, node_end) =
-        match get_node_start_and_end(&reader, "/chosen", dtb_blob, len_to_be_subtracted) {
-            Ok((node_start, node_end)) => (node_start, node_end),
-            Err(e) => panic!("error: {:?}", e),
-        };
+        get_node_start_and_end(&reader, "/chosen", dtb_blob, len_to_be_subtracted)?;
+    let remaining_bytes = dtb_blob
+        .len()
+        .checked_sub(node_end)
+        .ok_or(Error::UnexpectedEndOfBlob)?;
+    let patched_len = node_start
+        + patch_bytes_1.len()
+        + patch_bytes_2.as_slice().len()
+        + remaining_bytes
+        + strings_block_patch_len;
+    if patched_len > N {
+        return Err(Error::BufferExhausted);
+    }
 
     let _ = patch_dtb_node::<N>(
         &header,
@@ -318,7 +397,54 @@ pub fn patch_chosen_node<'a, const N: usize>(
     );
     let hdr_total_size = correct_endianess(header.total_size);
     // info!("len: {:?}", hdr_total_size);
-    (new_dtb_buffer, hdr_total_size as usize)
+    Ok((new_dtb_buffer, hdr_total_size as usize))
+}
+
+/// Selects the kernel command line (i.e. the `chosen` node's `bootargs`) to patch into the dtb.
+///
+/// - `signed` is the fit-image's command line i.e. one that's covered by its signature (see
+///   [`crate::dt::get_config_bootargs`]).
+/// - `external` is an override that comes from outside the fit-image (ex: a file on the SD card).
+///
+/// An override replaces the signed command line, unless `secure` is set. In secure mode, an
+/// override is refused (i.e. `root=`/`init=` can't be redirected by tampering with the SD card)
+/// and a fit-image must carry its own command line.
+pub fn select_bootargs<'a>(
+    signed: Option<&'a str>,
+    external: Option<&'a str>,
+    secure: bool,
+) -> Result<&'a str> {
+    match (signed, external) {
+        (_, Some(_)) if secure => Err(Error::UnsignedBootargs),
+        (_, Some(bootargs)) => Ok(bootargs),
+        (Some(bootargs), None) => Ok(bootargs),
+        (None, None) => Err(Error::MissingProperty),
+    }
+}
+
+/// Points a kernel command line at the root filesystem `root` (ex: an A/B update's rootfs
+/// partition, see [`crate::cfgparser::RootfsSlot`]) i.e. drops its `root=` argument (if any) and
+/// prepends `root=<root>`. The rewritten command line is written to `buf`.
+///
+/// Fails if the rewritten command line doesn't fit in `buf`.
+pub fn select_rootfs<'a>(bootargs: &str, root: &str, buf: &'a mut [u8]) -> Result<&'a str> {
+    let root_arg = ["root=", root];
+    let args = bootargs
+        .split_ascii_whitespace()
+        // any other `root=` would override ours
+        .filter(|arg| !arg.starts_with("root="))
+        .map(|arg| [arg, ""]);
+    let mut len = 0;
+    for (idx, arg) in core::iter::once(root_arg).chain(args).enumerate() {
+        let sep = if idx > 0 { " " } else { "" };
+        for part in [sep, arg[0], arg[1]] {
+            buf.get_mut(len..len + part.len())
+                .ok_or(Error::BufferTooSmall)?
+                .copy_from_slice(part.as_bytes());
+            len += part.len();
+        }
+    }
+    core::str::from_utf8(&buf[..len]).map_err(|e| Error::BadStrEncoding(e))
 }
 
 pub fn correct_endianess(val: u32) -> u32 {
@@ -330,3 +456,171 @@ pub fn correct_endianess(val: u32) -> u32 {
     let res = byte_1 | byte_2 | byte_3 | byte_4;
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn read_dtb(path: &str) -> Vec<u8> {
+        std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).unwrap()
+    }
+
+    #[test]
+    fn chosen_node_patching() {
+        let prop_val_list = [
+            PropertyValue::String("root=/dev/mmcblk1p2 rootwait rw"),
+            PropertyValue::U32([0x05, 0x89, 0x00, 0x00]),
+            PropertyValue::U32([0x07, 0x7f, 0x08, 0x4a]),
+        ];
+        let dtb_blob = read_dtb("examples/imx8mn-ddr4-evk.dtb");
+        let mut buf = [0u8; 60000];
+        let reader = Reader::read(&dtb_blob).unwrap();
+        let (patched, len) =
+            patch_chosen_node(reader, &dtb_blob, &prop_val_list, &mut buf).unwrap();
+        let reader = Reader::read(&patched[..len]).unwrap();
+        let bootargs = reader
+            .struct_items()
+            .path_struct_items("/chosen/bootargs")
+            .next()
+            .map(|(item, _)| item.value().unwrap());
+        assert!(bootargs
+            .unwrap()
+            .starts_with(b"root=/dev/mmcblk1p2 rootwait rw"));
+
+        // a buffer that can't hold the patched dtb
+        let mut buf = [0u8; 64];
+        let reader = Reader::read(&dtb_blob).unwrap();
+        assert_eq!(
+            patch_chosen_node(reader, &dtb_blob, &prop_val_list, &mut buf).unwrap_err(),
+            Error::BufferExhausted
+        );
+
+        // a dtb without a `chosen` node
+        let dtb_blob = read_dtb("src/dt/test_dtb/sample.dtb");
+        let mut buf = [0u8; 60000];
+        let reader = Reader::read(&dtb_blob).unwrap();
+        assert_eq!(
+            patch_chosen_node(reader, &dtb_blob, &prop_val_list, &mut buf).unwrap_err(),
+            Error::MissingNode
+        );
+    }
+
+    #[test]
+    fn update_status_reporting() {
+        let prop_val_list = [
+            PropertyValue::String("root=/dev/mmcblk1p2 rootwait rw"),
+            PropertyValue::U32([0x05, 0x89, 0x00, 0x00]),
+            PropertyValue::U32([0x07, 0x7f, 0x08, 0x4a]),
+        ];
+        let update_status = |dtb: &[u8]| {
+            let reader = Reader::read(dtb).unwrap();
+            reader
+                .struct_items()
+                .path_struct_items("/chosen/rustboot,update-status")
+                .map(|(item, _)| item.value().unwrap().to_vec())
+                .collect::<Vec<_>>()
+        };
+        let dtb_blob = read_dtb("examples/imx8mn-ddr4-evk.dtb");
+        let report = UpdateReport::failed(RustbootError::BadVersion, 0x6300_0000);
+        let mut buf = [0u8; 60000];
+        let reader = Reader::read(&dtb_blob).unwrap();
+        let (patched, len) =
+            patch_chosen_node_with_report(reader, &dtb_blob, &prop_val_list, report, &mut buf)
+                .unwrap();
+        let patched = patched[..len].to_vec();
+        let mut expected = (RustbootError::BadVersion.code() as u32)
+            .to_be_bytes()
+            .to_vec();
+        expected.extend_from_slice(&[0x63, 0x00, 0x00, 0x00]);
+        assert_eq!(update_status(&patched), [expected]);
+
+        // patching the dtb again replaces the report
+        let report = UpdateReport::booted(0x6400_0000);
+        let mut buf = [0u8; 60000];
+        let reader = Reader::read(&patched).unwrap();
+        let (repatched, len) =
+            patch_chosen_node_with_report(reader, &patched, &prop_val_list, report, &mut buf)
+                .unwrap();
+        assert_eq!(
+            update_status(&repatched[..len]),
+            [[0, 0, 0, 0, 0x64, 0, 0, 0].to_vec()]
+        );
+
+        // and is dropped by a plain patch
+        let mut buf = [0u8; 60000];
+        let reader = Reader::read(&patched).unwrap();
+        let (repatched, len) =
+            patch_chosen_node(reader, &patched, &prop_val_list, &mut buf).unwrap();
+        assert!(update_status(&repatched[..len]).is_empty());
+    }
+
+    #[test]
+    fn rootfs_selection() {
+        let mut buf = [0u8; 64];
+        assert_eq!(
+            select_rootfs(
+                "console=tty1 root=/dev/mmcblk0p2 rootwait",
+                "/dev/mmcblk0p3",
+                &mut buf
+            ),
+            Ok("root=/dev/mmcblk0p3 console=tty1 rootwait")
+        );
+        // a command line without (or with several) `root=` arguments
+        assert_eq!(
+            select_rootfs("rootwait  ro", "/dev/mmcblk0p2", &mut buf),
+            Ok("root=/dev/mmcblk0p2 rootwait ro")
+        );
+        assert_eq!(
+            select_rootfs(
+                "root=/dev/sda1 rootfstype=ext4 root=/dev/sda2",
+                "/dev/sdb1",
+                &mut buf
+            ),
+            Ok("root=/dev/sdb1 rootfstype=ext4")
+        );
+        assert_eq!(
+            select_rootfs("", "/dev/mmcblk0p2", &mut buf),
+            Ok("root=/dev/mmcblk0p2")
+        );
+        // a buffer that can't hold the rewritten command line
+        assert_eq!(
+            select_rootfs("rootwait", "/dev/mmcblk0p2", &mut buf[..20]),
+            Err(Error::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn bootargs_selection() {
+        let signed = Some("root=/dev/mmcblk0p2 rootwait ro");
+        let external = Some("root=/dev/sda1 init=/bin/sh");
+        assert_eq!(select_bootargs(signed, None, false), Ok(signed.unwrap()));
+        assert_eq!(
+            select_bootargs(signed, external, false),
+            Ok(external.unwrap())
+        );
+        assert_eq!(
+            select_bootargs(None, external, false),
+            Ok(external.unwrap())
+        );
+        assert_eq!(
+            select_bootargs(None, None, false),
+            Err(Error::MissingProperty)
+        );
+
+        // secure mode refuses overrides
+        assert_eq!(select_bootargs(signed, None, true), Ok(signed.unwrap()));
+        assert_eq!(
+            select_bootargs(signed, external, true),
+            Err(Error::UnsignedBootargs)
+        );
+        assert_eq!(
+            select_bootargs(None, external, true),
+            Err(Error::UnsignedBootargs)
+        );
+        assert_eq!(
+            select_bootargs(None, None, true),
+            Err(Error::MissingProperty)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use core::convert::TryFrom;
+use core::convert::{TryFrom, TryInto};
 use core::iter::FusedIterator;
 use core::mem::size_of;
 use core::slice::from_raw_parts;
@@ -152,31 +152,29 @@ impl<'a> StructItems<'a> {
     /// FIT Image, I will have to first parse the configurations node which will return a `self`. We can use
     /// this `self` to retrieve the property's value.
     /// - This methods return a `None` if you try to retrieve a `nested node as a property`.
+    /// - Only the node's own properties are returned i.e. properties of its sub-nodes (at any
+    ///   depth) are skipped.
     ///   
     pub fn get_node_property(self, name: &'a str) -> Option<&'a [u8]> {
-        let mut property_val = None;
-        let mut sub_node_end = false;
+        // the depth of the current item, relative to the node i.e. 0 for the node's own properties
+        let mut depth = 0usize;
         for item in self {
             if item.is_property() {
                 match item.name() {
-                    Ok(val) if val == name => {
-                        property_val = Some(item.value().unwrap());
-                        break;
-                    }
+                    Ok(val) if depth == 0 && val == name => return item.value().ok(),
                     _ => {}
                 }
             } else if item.is_begin_node() {
-                sub_node_end = true;
+                depth += 1;
             } else if item.is_end_node() {
-                if sub_node_end == true {
-                    sub_node_end = false;
-                    continue;
-                } else {
-                    break;
+                match depth.checked_sub(1) {
+                    Some(val) => depth = val,
+                    // the node's own end
+                    None => break,
                 }
             }
         }
-        property_val
+        None
     }
 
     /// Returns a structure path iterator for a given path.
@@ -411,7 +409,10 @@ impl<'a> Reader<'a> {
         }
 
         let offset = header.struct_offset as usize;
-        Ok(&blob[offset..offset + header.struct_size as usize])
+        let end = offset
+            .checked_add(header.struct_size as usize)
+            .ok_or(Error::UnexpectedEndOfBlob)?;
+        blob.get(offset..end).ok_or(Error::UnexpectedEndOfBlob)
     }
 
     pub fn get_strings_block(blob: &'a [u8], header: &Header) -> Result<&'a [u8]> {
@@ -420,7 +421,10 @@ impl<'a> Reader<'a> {
         }
 
         let offset = header.strings_offset as usize;
-        Ok(&blob[offset..offset + header.strings_size as usize])
+        let end = offset
+            .checked_add(header.strings_size as usize)
+            .ok_or(Error::UnexpectedEndOfBlob)?;
+        blob.get(offset..end).ok_or(Error::UnexpectedEndOfBlob)
     }
 
     /// Reads a given DTB blob and returns a corresponding reader.
@@ -478,6 +482,182 @@ impl<'a> Reader<'a> {
             offset: 0,
         }
     }
+
+    /// Returns the offset of `value` (ex: a property's value, as returned by a query) within the
+    /// structure block, or `None` if it isn't in the structure block.
+    pub(crate) fn struct_offset_of(&self, value: &[u8]) -> Option<usize> {
+        let offset = (value.as_ptr() as usize).checked_sub(self.struct_block.as_ptr() as usize)?;
+        match offset.checked_add(value.len()) {
+            Some(end) if end <= self.struct_block.len() => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Returns the node at `path` i.e. an absolute path whose components are node names, including
+    /// unit addresses ex: `/images/kernel@1`. A component without a unit address matches the first
+    /// node by that name, with or without one. Returns [`Error::MissingNode`] if there's no such
+    /// node.
+    pub fn find_node(&self, path: &str) -> Result<Node<'a>> {
+        let mut items = self.struct_items();
+        let root = match items.next_item()? {
+            StructItem::BeginNode { name } => Node { name, items },
+            _ => return Err(Error::BadStructToken),
+        };
+        path.strip_prefix('/')
+            .ok_or(Error::MissingNode)?
+            .split('/')
+            .filter(|comp| !comp.is_empty())
+            .try_fold(root, |node, comp| node.find_child(comp))
+    }
+}
+
+/// A device-tree node, see [`Reader::find_node`].
+///
+/// Queries walk the node's structure items as they go i.e. a malformed or truncated blob is
+/// reported as an error, whichever query runs into it.
+#[derive(Clone, Copy, Debug)]
+pub struct Node<'a> {
+    name: &'a str,
+    /// The node's items, right after its `BeginNode`.
+    items: StructItems<'a>,
+}
+
+impl<'a> Node<'a> {
+    /// Returns the node's name, including its unit address (if any).
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the value of the node's property `name`. Child nodes' properties aren't searched.
+    /// Returns [`Error::MissingProperty`] if there's no such property.
+    pub fn get_prop(&self, name: &str) -> Result<&'a [u8]> {
+        let mut items = self.items;
+        loop {
+            match items.next_item()? {
+                StructItem::Property { name: prop, value } if prop == name => return Ok(value),
+                StructItem::Property { .. } => {}
+                StructItem::BeginNode { .. } => skip_node(&mut items)?,
+                StructItem::EndNode => return Err(Error::MissingProperty),
+                StructItem::None => return Err(Error::BadStructToken),
+            }
+        }
+    }
+
+    /// Same as [`Node::get_prop`], for a single (big-endian) `u32` cell. Returns
+    /// [`Error::BadU32List`] if the property's value isn't 4 bytes long.
+    pub fn get_prop_u32(&self, name: &str) -> Result<u32> {
+        let value = self.get_prop(name)?;
+        Ok(u32::from_be_bytes(
+            value.try_into().map_err(|_| Error::BadU32List)?,
+        ))
+    }
+
+    /// Same as [`Node::get_prop`], for an address i.e. one or two (big-endian) `u32` cells (ex: a
+    /// fit-image's `load` address, which is 64-bit with `#address-cells = <2>`). Returns
+    /// [`Error::BadU32List`] if the property's value isn't 4 or 8 bytes long.
+    pub fn get_prop_addr(&self, name: &str) -> Result<u64> {
+        be_addr(self.get_prop(name)?)
+    }
+
+    /// Same as [`Node::get_prop`], for a zero-terminated string.
+    pub fn get_prop_str(&self, name: &str) -> Result<&'a str> {
+        let value = self.get_prop(name)?;
+        StructItem::Property { name: "", value }.value_str()
+    }
+
+    /// Returns an iterator over the node's (immediate) child nodes.
+    pub fn iter_children(&self) -> Children<'a> {
+        Children {
+            items: self.items,
+            done: false,
+        }
+    }
+
+    /// Returns the child node `name`, matched as a component of a path passed to
+    /// [`Reader::find_node`].
+    pub fn find_child(&self, name: &str) -> Result<Node<'a>> {
+        for child in self.iter_children() {
+            let child = child?;
+            let matches = match name.contains('@') {
+                true => child.name == name,
+                false => child.name.split('@').next() == Some(name),
+            };
+            if matches {
+                return Ok(child);
+            }
+        }
+        Err(Error::MissingNode)
+    }
+}
+
+/// Skips the rest of a node i.e. up to and including its `EndNode`, given its items.
+fn skip_node(items: &mut StructItems) -> Result<()> {
+    let mut depth = 0usize;
+    loop {
+        match items.next_item()? {
+            StructItem::BeginNode { .. } => depth += 1,
+            StructItem::EndNode if depth == 0 => return Ok(()),
+            StructItem::EndNode => depth -= 1,
+            StructItem::Property { .. } => {}
+            StructItem::None => return Err(Error::BadStructToken),
+        }
+    }
+}
+
+/// Iterator for a node's child nodes, see [`Node::iter_children`]. Stops after the first error.
+#[derive(Clone, Copy, Debug)]
+pub struct Children<'a> {
+    items: StructItems<'a>,
+    done: bool,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = Result<Node<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let item = match self.items.next_item() {
+                Ok(item) => item,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            };
+            match item {
+                StructItem::Property { .. } => {}
+                StructItem::BeginNode { name } => {
+                    let child = Node {
+                        name,
+                        items: self.items,
+                    };
+                    if let Err(err) = skip_node(&mut self.items) {
+                        self.done = true;
+                        return Some(Err(err));
+                    }
+                    return Some(Ok(child));
+                }
+                StructItem::EndNode => self.done = true,
+                StructItem::None => {
+                    self.done = true;
+                    return Some(Err(Error::BadStructToken));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a> FusedIterator for Children<'a> {}
+
+/// Decodes an address i.e. one or two (big-endian) `u32` cells, see [`Node::get_prop_addr`].
+pub(crate) fn be_addr(value: &[u8]) -> Result<u64> {
+    match value.len() {
+        4 => Ok(u32::from_be_bytes(value.try_into().map_err(|_| Error::BadU32List)?) as u64),
+        8 => Ok(u64::from_be_bytes(
+            value.try_into().map_err(|_| Error::BadU32List)?,
+        )),
+        _ => Err(Error::BadU32List),
+    }
 }
 
 #[cfg(test)]
@@ -765,6 +945,143 @@ mod tests {
         assert_properties_found(&root, "/foo/foo/bar", &["2", "3", "5", "6"]);
     }
 
+    #[test]
+    fn test_find_node() {
+        let mut buf = Vec::new();
+        let reader = read_dtb(&mut buf, "sample").unwrap();
+
+        assert_eq!(reader.find_node("/").unwrap().name(), "");
+        let node = reader.find_node("/node1/child-node1").unwrap();
+        assert_eq!(node.name(), "child-node1");
+        assert_eq!(node.get_prop_u32("second-child-property"), Ok(1));
+        assert_eq!(node.get_prop_str("a-string-property"), Ok("Hello, world"));
+        assert_eq!(node.get_prop("first-child-property"), Ok(&[][..]));
+
+        let node = reader.find_node("/node1/").unwrap();
+        assert_eq!(node.get_prop_str("a-string-property"), Ok("A string"));
+        // a child node's property isn't the node's
+        assert_eq!(
+            node.get_prop("second-child-property"),
+            Err(Error::MissingProperty)
+        );
+        assert_eq!(
+            node.get_prop_u32("a-string-property"),
+            Err(Error::BadU32List)
+        );
+        assert_eq!(
+            reader
+                .find_node("/node2")
+                .unwrap()
+                .get_prop_u32("a-cell-property"),
+            Err(Error::BadU32List)
+        );
+
+        assert_eq!(reader.find_node("node1").unwrap_err(), Error::MissingNode);
+        assert_eq!(reader.find_node("/node3").unwrap_err(), Error::MissingNode);
+        assert_eq!(
+            reader.find_node("/node1/child-node1/node1").unwrap_err(),
+            Error::MissingNode
+        );
+    }
+
+    #[test]
+    fn test_find_node_unit_address() {
+        let mut buf = Vec::new();
+        let reader = read_dtb(&mut buf, "sample2").unwrap();
+
+        assert_eq!(reader.find_node("/foo").unwrap().name(), "foo@1");
+        let node = reader.find_node("/foo@4/foo@6").unwrap();
+        assert_eq!(node.name(), "foo@6");
+        assert_eq!(node.get_prop_str("bar"), Ok("6"));
+        assert_eq!(reader.find_node("/foo@4/foo").unwrap().name(), "foo@5");
+        assert_eq!(reader.find_node("/foo@2").unwrap_err(), Error::MissingNode);
+    }
+
+    #[test]
+    fn test_iter_children() {
+        let mut buf = Vec::new();
+        let reader = read_dtb(&mut buf, "sample").unwrap();
+
+        let names = |path| {
+            reader
+                .find_node(path)
+                .unwrap()
+                .iter_children()
+                .map(|child| child.unwrap().name())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("/"), ["node1", "node2"]);
+        assert_eq!(names("/node1"), ["child-node1", "child-node2"]);
+        assert_eq!(names("/node1/child-node2"), Vec::<&str>::new());
+    }
+
+    /// Runs every query over a (possibly corrupted) blob, none of which may panic.
+    fn query_all(blob: &[u8]) {
+        let reader = match Reader::read(blob) {
+            Ok(reader) => reader,
+            Err(_) => return,
+        };
+        for path in [
+            "/",
+            "/node1/child-node1",
+            "/node2",
+            "/foo@4/foo@6",
+            "/foo/foo",
+        ] {
+            if let Ok(node) = reader.find_node(path) {
+                let _ = node.get_prop_u32("a-cell-property");
+                let _ = node.get_prop_str("bar");
+                for child in node.iter_children() {
+                    if let Ok(child) = child {
+                        let _ = child.get_prop("a-string-property");
+                    }
+                }
+            }
+        }
+        let _ = reader
+            .struct_items()
+            .path_struct_items("/foo/foo/bar")
+            .count();
+    }
+
+    #[test]
+    fn test_corrupted_blobs() {
+        for name in ["sample", "sample2"] {
+            let mut buf = Vec::new();
+            read_dtb_vec(&mut buf, name);
+            // stays 8-byte aligned, as the reader requires
+            let mut words = vec![0u64; buf.len() / 8 + 1];
+            let blob = unsafe {
+                core::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, buf.len())
+            };
+
+            for pos in 0..buf.len() {
+                for val in [0x00, 0x01, 0x03, 0x7f, 0x80, 0xff] {
+                    blob.copy_from_slice(&buf);
+                    blob[pos] = val;
+                    query_all(blob);
+                }
+                if pos % 4 == 0 && pos + 4 <= buf.len() {
+                    for val in [0u32, 1, 2, 3, 9, 0x7fff_ffff, 0x8000_0000, u32::MAX] {
+                        blob.copy_from_slice(&buf);
+                        blob[pos..pos + 4].copy_from_slice(&val.to_be_bytes());
+                        query_all(blob);
+                    }
+                }
+            }
+
+            // truncated blobs, with and without a matching header
+            for len in 0..buf.len() {
+                blob[..len].copy_from_slice(&buf[..len]);
+                query_all(&blob[..len]);
+                if len >= size_of::<Header>() {
+                    blob[4..8].copy_from_slice(&(len as u32).to_be_bytes());
+                    query_all(&blob[..len]);
+                }
+            }
+        }
+    }
+
     // Regression test for a prior unsafety issue: #5
     test_read_dtb!(test_bad_reserved_mem_offset, BadTotalSize);
 
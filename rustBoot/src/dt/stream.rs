@@ -0,0 +1,529 @@
+//! Incremental hashing of fit-image `data` properties.
+//!
+//! Loading a large fit-image and then hashing it in a second pass means we walk
+//! the entire blob twice. A [`FitDigester`] is fed the image-tree blob in arbitrary
+//! chunks (i.e. as each cluster lands in RAM) and hashes every property value of
+//! the `/images/*` sub-nodes on the fly. Once the blob is completely loaded,
+//! [`FitDigester::finalize`] resolves property names via the strings block and
+//! returns the digests of all `data` properties.
+//!
+//! A digest is only ever used for the very `data` value it was computed over i.e. each digest
+//! records where its value is (within the structure block), see [`ImageDigests::get`].
+
+use p256::ecdsa::signature::digest::Digest;
+use sha2::Sha256;
+
+use super::internal::{DTB_MAGIC, TOK_BEGIN_NODE, TOK_END, TOK_END_NODE, TOK_NOP, TOK_PROPERTY};
+use super::{Error, Reader, Result};
+
+/// A property value's offset (within the structure block) that's never read back i.e. an empty
+/// value's, whose digest isn't worth keeping.
+const NO_OFFSET: usize = usize::MAX;
+
+/// Max number of image sub-nodes (i.e. `/images/*`) tracked while streaming.
+pub const MAX_STREAMED_IMAGES: usize = 4;
+/// Max number of properties tracked per image sub-node.
+pub const MAX_STREAMED_PROPS: usize = 10;
+/// The digester used by [`super::verify_fit_with`], i.e. rustBoot fit-images are hashed with sha256.
+pub type Sha256FitDigester = FitDigester<Sha256, 32>;
+
+/// Max length of an image sub-node name.
+const MAX_NAME_LEN: usize = 32;
+/// DT spec says all compliant device-trees include a 40-byte header
+const HEADER_LEN: usize = 0x28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Header,
+    Skip,
+    Token,
+    NodeName,
+    PropDesc,
+    PropValue,
+    Done,
+}
+
+/// A streamed property i.e. its name's offset (in the strings block), its value's offset (in the
+/// structure block) and length, and the value's hasher.
+struct StreamedProp<D> {
+    name_offset: u32,
+    value_offset: usize,
+    value_len: usize,
+    hasher: D,
+}
+
+struct ImageNode<D> {
+    name: [u8; MAX_NAME_LEN],
+    name_len: usize,
+    props: [Option<StreamedProp<D>>; MAX_STREAMED_PROPS],
+    prop_count: usize,
+}
+
+impl<D> ImageNode<D> {
+    fn new() -> Self {
+        ImageNode {
+            name: [0; MAX_NAME_LEN],
+            name_len: 0,
+            props: [(); MAX_STREAMED_PROPS].map(|_| None),
+            prop_count: 0,
+        }
+    }
+}
+
+/// The digest of an image sub-node's `data` property, and where its value is.
+#[derive(Debug, Clone, Copy)]
+struct ImageDigest<const H: usize> {
+    name: [u8; MAX_NAME_LEN],
+    name_len: usize,
+    /// the value's offset, within the structure block.
+    offset: usize,
+    len: usize,
+    digest: [u8; H],
+}
+
+/// Digests of the `data` property of each image sub-node, as computed by a [`FitDigester`].
+#[derive(Debug, Clone, Copy)]
+pub struct ImageDigests<const H: usize> {
+    entries: [ImageDigest<H>; MAX_STREAMED_IMAGES],
+    count: usize,
+}
+
+impl<const H: usize> ImageDigests<H> {
+    /// Returns the digest of an image sub-node's `data` property, given the sub-node's name and
+    /// the value that `reader` (i.e. the fully loaded fit-image's) returns for it. `None` unless
+    /// `data` is the very value that was hashed i.e. a value read from elsewhere (ex: a nested
+    /// node's `data`) must be hashed as is.
+    ///
+    /// **note:** the name may include a trailing null byte, as is the case with property
+    /// values referenced in the `/configurations` node.
+    pub fn get(&self, name: &[u8], reader: &Reader, data: &[u8]) -> Option<&[u8; H]> {
+        let name = match name.split_last() {
+            Some((0, rest)) => rest,
+            _ => name,
+        };
+        let offset = reader.struct_offset_of(data)?;
+        self.entries
+            .get(..self.count)?
+            .iter()
+            .find(|entry| entry.name.get(..entry.name_len) == Some(name))
+            .filter(|entry| entry.offset == offset && entry.len == data.len())
+            .map(|entry| &entry.digest)
+    }
+}
+
+/// A streaming digester for fit-images (i.e. image-tree blobs).
+///
+/// The digester is fed contiguous chunks of a fit-image, in order, via [`FitDigester::update`].
+/// Chunks can be of arbitrary size and do not need to be aligned to device-tree tokens.
+pub struct FitDigester<D: Digest, const H: usize> {
+    header: [u8; HEADER_LEN],
+    pos: usize,
+    skip_to: usize,
+    struct_start: usize,
+    struct_end: usize,
+    step: Step,
+    scratch: [u8; MAX_NAME_LEN],
+    scratch_len: usize,
+    value_len: usize,
+    value_left: usize,
+    depth: usize,
+    in_images: bool,
+    current: Option<usize>,
+    active: Option<(usize, usize)>,
+    images: [ImageNode<D>; MAX_STREAMED_IMAGES],
+    image_count: usize,
+    error: Option<Error>,
+}
+
+impl<D: Digest, const H: usize> Default for FitDigester<D, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Digest, const H: usize> FitDigester<D, H> {
+    /// Creates a new [`FitDigester`].
+    pub fn new() -> Self {
+        FitDigester {
+            header: [0; HEADER_LEN],
+            pos: 0,
+            skip_to: 0,
+            struct_start: 0,
+            struct_end: 0,
+            step: Step::Header,
+            scratch: [0; MAX_NAME_LEN],
+            scratch_len: 0,
+            value_len: 0,
+            value_left: 0,
+            depth: 0,
+            in_images: false,
+            current: None,
+            active: None,
+            images: [(); MAX_STREAMED_IMAGES].map(|_| ImageNode::new()),
+            image_count: 0,
+            error: None,
+        }
+    }
+
+    /// Feeds the next chunk of the fit-image to the digester.
+    pub fn update(&mut self, chunk: &[u8]) {
+        let mut idx = 0;
+        while idx < chunk.len() && self.error.is_none() {
+            let avail = &chunk[idx..];
+            let consumed = match self.step {
+                Step::Header => self.fill_header(avail),
+                Step::Skip => {
+                    let n = (self.skip_to - self.pos).min(avail.len());
+                    if self.pos + n == self.skip_to {
+                        self.step = Step::Token;
+                    }
+                    n
+                }
+                Step::Token => self.fill_scratch(avail, 4, Self::handle_token),
+                Step::PropDesc => self.fill_scratch(avail, 8, Self::handle_prop_desc),
+                Step::NodeName => self.fill_node_name(avail),
+                Step::PropValue => {
+                    let n = self.value_left.min(avail.len());
+                    if let Some((img, prop)) = self.active {
+                        if let Some(prop) = self.images[img].props[prop].as_mut() {
+                            if self.value_left == self.value_len {
+                                prop.value_offset = self.pos - self.struct_start;
+                            }
+                            prop.hasher.update(&avail[..n]);
+                        }
+                    }
+                    self.value_left -= n;
+                    if self.value_left == 0 {
+                        self.active = None;
+                        self.skip_padding(self.pos + n, self.value_len);
+                    }
+                    n
+                }
+                // we're past the structure block, nothing left to hash.
+                Step::Done => avail.len(),
+            };
+            self.pos += consumed;
+            idx += consumed;
+            if self.step != Step::Done && self.struct_end != 0 && self.pos > self.struct_end {
+                self.error = Some(Error::UnexpectedEndOfStruct);
+            }
+        }
+    }
+
+    /// Completes the digest computation. Takes the fully loaded fit-image as input, which is
+    /// used to resolve property names (via the strings block).
+    pub fn finalize(self, itb_blob: &[u8]) -> Result<ImageDigests<H>> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if self.step != Step::Done {
+            return Err(Error::UnexpectedEndOfStruct);
+        }
+        let header = Reader::get_header(itb_blob)?;
+        let strings_block = Reader::get_strings_block(itb_blob, &header)?;
+
+        let mut digests = ImageDigests {
+            entries: [ImageDigest {
+                name: [0; MAX_NAME_LEN],
+                name_len: 0,
+                offset: NO_OFFSET,
+                len: 0,
+                digest: [0; H],
+            }; MAX_STREAMED_IMAGES],
+            count: 0,
+        };
+        for node in IntoIterator::into_iter(self.images).take(self.image_count) {
+            for prop in IntoIterator::into_iter(node.props).flatten() {
+                let name = strings_block
+                    .get(prop.name_offset as usize..)
+                    .and_then(|s| s.split(|byte| *byte == 0).next())
+                    .ok_or(Error::BadPropertyName)?;
+                if name == b"data" {
+                    let digest = prop.hasher.finalize();
+                    if digest.len() != H {
+                        return Err(Error::BufferTooSmall);
+                    }
+                    let entry = &mut digests.entries[digests.count];
+                    entry.name = node.name;
+                    entry.name_len = node.name_len;
+                    entry.offset = prop.value_offset;
+                    entry.len = prop.value_len;
+                    entry.digest.copy_from_slice(digest.as_slice());
+                    digests.count += 1;
+                    break;
+                }
+            }
+        }
+        Ok(digests)
+    }
+
+    fn fill_header(&mut self, avail: &[u8]) -> usize {
+        let n = (HEADER_LEN - self.pos).min(avail.len());
+        self.header[self.pos..self.pos + n].copy_from_slice(&avail[..n]);
+        if self.pos + n == HEADER_LEN {
+            let be_u32 = |off: usize| {
+                u32::from_be_bytes([
+                    self.header[off],
+                    self.header[off + 1],
+                    self.header[off + 2],
+                    self.header[off + 3],
+                ]) as usize
+            };
+            if be_u32(0) != DTB_MAGIC as usize {
+                self.error = Some(Error::BadMagic);
+                return n;
+            }
+            let struct_offset = be_u32(8);
+            let struct_size = be_u32(36);
+            if struct_offset < HEADER_LEN || struct_offset % 4 != 0 || struct_size % 4 != 0 {
+                self.error = Some(Error::UnalignedStruct);
+                return n;
+            }
+            self.struct_start = struct_offset;
+            self.struct_end = struct_offset + struct_size;
+            self.skip_to = struct_offset;
+            self.step = if struct_offset == HEADER_LEN {
+                Step::Token
+            } else {
+                Step::Skip
+            };
+        }
+        n
+    }
+
+    /// Collects `len` bytes into the scratch buffer and invokes `handler` once complete.
+    fn fill_scratch(&mut self, avail: &[u8], len: usize, handler: fn(&mut Self)) -> usize {
+        let n = (len - self.scratch_len).min(avail.len());
+        self.scratch[self.scratch_len..self.scratch_len + n].copy_from_slice(&avail[..n]);
+        self.scratch_len += n;
+        if self.scratch_len == len {
+            self.scratch_len = 0;
+            handler(self);
+        }
+        n
+    }
+
+    fn fill_node_name(&mut self, avail: &[u8]) -> usize {
+        match avail.iter().position(|byte| *byte == 0) {
+            Some(nul) => {
+                if !self.push_name(&avail[..nul]) {
+                    return nul;
+                }
+                let name_len = self.scratch_len;
+                self.scratch_len = 0;
+                self.begin_node(name_len);
+                // node names are null terminated and padded to a 4-byte boundary
+                self.skip_padding(self.pos + nul + 1, name_len + 1);
+                nul + 1
+            }
+            None => {
+                self.push_name(avail);
+                avail.len()
+            }
+        }
+    }
+
+    fn push_name(&mut self, bytes: &[u8]) -> bool {
+        if self.scratch_len + bytes.len() > MAX_NAME_LEN {
+            // only image sub-node names need to fit, everything else is just skipped.
+            if self.in_images && self.depth == 2 {
+                self.error = Some(Error::BadNodeName);
+                return false;
+            }
+            self.scratch_len = MAX_NAME_LEN;
+            return true;
+        }
+        self.scratch[self.scratch_len..self.scratch_len + bytes.len()].copy_from_slice(bytes);
+        self.scratch_len += bytes.len();
+        true
+    }
+
+    fn handle_token(&mut self) {
+        let token = u32::from_be_bytes([
+            self.scratch[0],
+            self.scratch[1],
+            self.scratch[2],
+            self.scratch[3],
+        ]);
+        match token {
+            TOK_BEGIN_NODE => self.step = Step::NodeName,
+            TOK_END_NODE => {
+                if self.depth == 3 {
+                    self.current = None;
+                } else if self.depth == 2 {
+                    self.in_images = false;
+                }
+                self.depth = self.depth.saturating_sub(1);
+            }
+            TOK_PROPERTY => self.step = Step::PropDesc,
+            TOK_NOP => {}
+            TOK_END => self.step = Step::Done,
+            _ => self.error = Some(Error::BadStructToken),
+        }
+    }
+
+    fn begin_node(&mut self, name_len: usize) {
+        self.depth += 1;
+        let name = &self.scratch[..name_len];
+        if self.depth == 2 && name == b"images" {
+            self.in_images = true;
+        } else if self.depth == 3 && self.in_images && self.image_count < MAX_STREAMED_IMAGES {
+            let node = &mut self.images[self.image_count];
+            node.name[..name_len].copy_from_slice(name);
+            node.name_len = name_len;
+            self.current = Some(self.image_count);
+            self.image_count += 1;
+        }
+    }
+
+    fn handle_prop_desc(&mut self) {
+        let value_len = u32::from_be_bytes([
+            self.scratch[0],
+            self.scratch[1],
+            self.scratch[2],
+            self.scratch[3],
+        ]) as usize;
+        let name_offset = u32::from_be_bytes([
+            self.scratch[4],
+            self.scratch[5],
+            self.scratch[6],
+            self.scratch[7],
+        ]);
+        self.active = None;
+        if let (Some(img), 3) = (self.current, self.depth) {
+            let node = &mut self.images[img];
+            if node.prop_count < MAX_STREAMED_PROPS {
+                node.props[node.prop_count] = Some(StreamedProp {
+                    name_offset,
+                    value_offset: NO_OFFSET,
+                    value_len,
+                    hasher: D::new(),
+                });
+                self.active = Some((img, node.prop_count));
+                node.prop_count += 1;
+            }
+        }
+        self.value_len = value_len;
+        self.value_left = value_len;
+        if value_len == 0 {
+            self.active = None;
+            self.step = Step::Token;
+        } else {
+            self.step = Step::PropValue;
+        }
+    }
+
+    /// Skips padding bytes (if any) following a field of `len` bytes, ending at `next_pos`.
+    fn skip_padding(&mut self, next_pos: usize, len: usize) {
+        let padding = (4 - len % 4) % 4;
+        if padding == 0 {
+            self.step = Step::Token;
+        } else {
+            self.skip_to = next_pos + padding;
+            self.step = Step::Skip;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Sha256;
+
+    /// Builds a minimal fit-image like blob with a single `/images/kernel` node.
+    fn fit_blob(data: &[u8]) -> Vec<u8> {
+        let strings = b"description\0data\0";
+        let mut st = Vec::new();
+        let push_u32 = |v: &mut Vec<u8>, val: u32| v.extend_from_slice(&val.to_be_bytes());
+        let push_name = |v: &mut Vec<u8>, name: &[u8]| {
+            v.extend_from_slice(name);
+            v.push(0);
+            while v.len() % 4 != 0 {
+                v.push(0);
+            }
+        };
+        let push_prop = |v: &mut Vec<u8>, name_offset: u32, value: &[u8]| {
+            v.extend_from_slice(&TOK_PROPERTY.to_be_bytes());
+            v.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            v.extend_from_slice(&name_offset.to_be_bytes());
+            v.extend_from_slice(value);
+            while v.len() % 4 != 0 {
+                v.push(0);
+            }
+        };
+        push_u32(&mut st, TOK_BEGIN_NODE);
+        push_name(&mut st, b"");
+        push_u32(&mut st, TOK_BEGIN_NODE);
+        push_name(&mut st, b"images");
+        push_u32(&mut st, TOK_BEGIN_NODE);
+        push_name(&mut st, b"kernel");
+        push_prop(&mut st, 0, b"test kernel\0");
+        push_prop(&mut st, 12, data);
+        push_u32(&mut st, TOK_END_NODE);
+        push_u32(&mut st, TOK_END_NODE);
+        push_u32(&mut st, TOK_END_NODE);
+        push_u32(&mut st, TOK_END);
+
+        let rsvmap_offset = HEADER_LEN as u32;
+        let struct_offset = rsvmap_offset + 16;
+        let strings_offset = struct_offset + st.len() as u32;
+        let total_size = strings_offset + strings.len() as u32;
+
+        let mut blob = Vec::new();
+        for val in [
+            DTB_MAGIC,
+            total_size,
+            struct_offset,
+            strings_offset,
+            rsvmap_offset,
+            17,
+            16,
+            0,
+            strings.len() as u32,
+            st.len() as u32,
+        ] {
+            push_u32(&mut blob, val);
+        }
+        blob.extend_from_slice(&[0; 16]);
+        blob.extend_from_slice(&st);
+        blob.extend_from_slice(strings);
+        blob
+    }
+
+    #[test]
+    fn test_streamed_digest_matches_one_shot_digest() {
+        let data = (0..1021u32).map(|i| i as u8).collect::<Vec<_>>();
+        let blob = fit_blob(&data);
+        let expected = Sha256::digest(&data);
+
+        for chunk_size in [1, 3, 7, 64, 512, blob.len()] {
+            let mut digester = FitDigester::<Sha256, 32>::new();
+            blob.chunks(chunk_size)
+                .for_each(|chunk| digester.update(chunk));
+            let digests = digester.finalize(&blob).unwrap();
+            let reader = Reader::read(&blob).unwrap();
+            let kernel = reader.find_node("/images/kernel").unwrap();
+            let data = kernel.get_prop("data").unwrap();
+            assert_eq!(
+                digests.get(b"kernel\0", &reader, data).unwrap().as_slice(),
+                expected.as_slice()
+            );
+            assert!(digests.get(b"fdt", &reader, data).is_none());
+            // the digest is only good for the value it was computed over
+            let description = kernel.get_prop("description").unwrap();
+            assert!(digests.get(b"kernel", &reader, description).is_none());
+            assert!(digests.get(b"kernel", &reader, &data[1..]).is_none());
+        }
+    }
+
+    #[test]
+    fn test_truncated_blob() {
+        let blob = fit_blob(&[0xAA; 100]);
+        let mut digester = FitDigester::<Sha256, 32>::new();
+        digester.update(&blob[..blob.len() / 2]);
+        assert_eq!(
+            digester.finalize(&blob).unwrap_err(),
+            Error::UnexpectedEndOfStruct
+        );
+    }
+}
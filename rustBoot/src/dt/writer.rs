@@ -32,7 +32,7 @@ impl<const M: usize> SerializedBuffer<M> {
         let val = core::str::from_utf8(self.as_slice())
             .map_err(|val| Error::BadStrEncoding(val))?
             .strip_suffix("\u{0}");
-        Ok(val.unwrap())
+        val.ok_or(Error::BadValueStr)
     }
 
     pub fn as_str_no_suffix<'a>(&'a self) -> Result<&'a str> {
@@ -72,6 +72,8 @@ impl<'a> Concat for &'a str {
     /// Note:
     /// - The resultant buffer contains the concatenated string-literal
     /// - The length of concatenated string-literal has to be `< 50`
+    /// - Anything beyond `N` bytes is truncated, i.e. a truncated zero-terminated string
+    /// will no longer be zero-terminated.
     ///
     fn concat<const N: usize>(self, slice_2: &[u8]) -> SerializedBuffer<N> {
         let mut buffer = [0u8; N];
@@ -80,9 +82,9 @@ impl<'a> Concat for &'a str {
         let _ = slice_1
             .iter()
             .chain(slice_2.iter())
-            .enumerate()
-            .for_each(|(idx, byte)| buffer[idx] = *byte);
-        let len = slice_1.len() + slice_2.len();
+            .zip(buffer.iter_mut())
+            .for_each(|(byte, slot)| *slot = *byte);
+        let len = core::cmp::min(slice_1.len() + slice_2.len(), N);
         SerializedBuffer { buffer, len }
     }
 }
@@ -189,23 +191,26 @@ impl<'a> RawNodeConstructor<'a> {
         if name_offset_list.len() != prop_val_list.len() {
             return Err(Error::NonExhaustive);
         }
-        let raw_chosen_node = RawNodeConstructor::make_raw_node(&mut buf[..], node_name).unwrap();
+        let raw_chosen_node = RawNodeConstructor::make_raw_node(&mut buf[..], node_name)?;
         let serialized_node_buffer = raw_chosen_node.serialize()?;
         let raw_node = serialized_node_buffer.as_slice();
         let node_len = raw_chosen_node.serialize()?.len;
-        buf[..node_len].copy_from_slice(raw_node);
+        buf.get_mut(..node_len)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(raw_node);
         let mut buffer_offset = node_len;
-        for (idx, property_val) in prop_val_list.iter().enumerate() {
+        for (name_offset, property_val) in name_offset_list.iter().zip(prop_val_list) {
             let raw_prop_node = RawPropertyConstructor::make_raw_property(
-                &mut buf[buffer_offset..],
-                name_offset_list[idx],
+                buf.get_mut(buffer_offset..).ok_or(Error::BufferTooSmall)?,
+                *name_offset,
                 property_val,
-            )
-            .unwrap();
+            )?;
             let serialized_prop_buffer = raw_prop_node.serialize()?;
             let raw_prop = serialized_prop_buffer.as_slice();
             let prop_len = raw_prop_node.serialize()?.len;
-            buf[buffer_offset..buffer_offset + prop_len].copy_from_slice(raw_prop);
+            buf.get_mut(buffer_offset..buffer_offset + prop_len)
+                .ok_or(Error::BufferTooSmall)?
+                .copy_from_slice(raw_prop);
             buffer_offset += prop_len
         }
         Ok(buffer_offset)
@@ -217,9 +222,12 @@ impl<'a> RawNodeConstructor<'a> {
 /// describes the set of basic value types.
 ///
 /// Note:  This impl doesnt account for all property value types.
+#[derive(Debug, Clone, Copy)]
 pub enum PropertyValue<'a> {
     String(&'a str),
     U32([u8; 4]),
+    /// A `<u64>` i.e. two big-endian 32-bit cells.
+    U64([u8; 8]),
     Empty,
 }
 
@@ -229,6 +237,7 @@ impl<'a> AsRef<[u8]> for PropertyValue<'a> {
             Self::Empty => &[],
             Self::String(val) => val.as_ref(),
             Self::U32(val) => val.as_ref(),
+            Self::U64(val) => val.as_ref(),
         }
     }
 }
@@ -316,16 +325,15 @@ impl<'a> RawPropertyConstructor<'a> {
                         prop_val: &buf[..prop_val_len + 4],
                     })
                 }
-                PropertyValue::U32(val) => {
+                PropertyValue::U32(_) | PropertyValue::U64(_) | PropertyValue::Empty => {
                     buf[..prop_val_len].copy_from_slice(prop_val.as_ref());
                     Ok(RawPropertyConstructor {
                         fdt_prop: TOK_PROPERTY,
-                        prop_len: val.len() as u32,
+                        prop_len: prop_val_len as u32,
                         name_off: prop_name_offset as u32,
                         prop_val: &buf[..prop_val_len],
                     })
                 }
-                _ => unimplemented!(),
             }
         } else {
             let padding = count % 4;
@@ -577,4 +585,38 @@ mod tests {
             Error::BufferTooSmall
         );
     }
+
+    #[test]
+    fn test_concat_truncates() {
+        let path = "/images/".concat::<10>("kernel\0".as_bytes());
+        assert_eq!(path.as_slice(), "/images/ke".as_bytes());
+        assert_eq!(path.as_str().unwrap_err(), Error::BadValueStr);
+
+        let path = "/images/".concat::<50>("kernel\0".as_bytes());
+        assert_eq!(path.as_str().unwrap(), "/images/kernel");
+    }
+
+    #[test]
+    fn test_make_node_with_props() {
+        let props = [PropertyValue::Empty, PropertyValue::U32(1u32.to_be_bytes())];
+        let mut buf = [0u8; 64];
+        let len =
+            RawNodeConstructor::make_node_with_props(&mut buf, "chosen", &[0, 8], &props).unwrap();
+        assert_eq!(len, 12 + 12 + 16);
+        // the empty property, then the `u32`
+        assert_eq!(&buf[12..24], &[0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            &buf[24..40],
+            &[0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 8, 0, 0, 0, 1]
+        );
+
+        // a buffer that's too small for the node or its properties is an error, not a panic
+        for size in 0..len {
+            let mut buf = vec![0u8; size];
+            assert_eq!(
+                RawNodeConstructor::make_node_with_props(&mut buf, "chosen", &[0, 8], &props),
+                Err(Error::BufferTooSmall)
+            );
+        }
+    }
 }
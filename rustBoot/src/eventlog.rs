@@ -0,0 +1,162 @@
+//! The boot/update event log's record format.
+//!
+//! The event log is an append-only sequence of fixed-size records, in a reserved flash sector
+//! (see the `log` partition in a board's manifest). A record is written to the first free (i.e.
+//! erased) slot. Once the sector is full, it's erased and only the newest records are kept.
+//!
+//! ```text
+//!  0       1      2          4                 8
+//!  | event | code | seq (le) | version (le)    |
+//! ```
+
+use core::convert::TryInto;
+
+/// The length of a record, in bytes.
+pub const RECORD_LEN: usize = 8;
+
+const ERASED: u8 = 0xFF;
+
+/// A logged event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Event {
+    /// Firmware marked an update for installation.
+    UpdateTriggered = 0x01,
+    /// Firmware marked an update for a trial boot.
+    UpdateTested = 0x02,
+    /// rustBoot swapped an update in.
+    UpdateInstalled = 0x03,
+    /// Firmware confirmed the running image.
+    UpdateConfirmed = 0x04,
+    /// An image failed verification.
+    VerifyFailed = 0x05,
+    /// rustBoot swapped the previous image back in.
+    RollbackPerformed = 0x06,
+    /// An update (or rollback) couldn't be performed.
+    UpdateFailed = 0x07,
+    /// rustBoot booted the image a boot-redirect request selected, see [`crate::redirect`].
+    Redirected = 0x08,
+}
+
+impl Event {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x01 => Some(Event::UpdateTriggered),
+            0x02 => Some(Event::UpdateTested),
+            0x03 => Some(Event::UpdateInstalled),
+            0x04 => Some(Event::UpdateConfirmed),
+            0x05 => Some(Event::VerifyFailed),
+            0x06 => Some(Event::RollbackPerformed),
+            0x07 => Some(Event::UpdateFailed),
+            0x08 => Some(Event::Redirected),
+            _ => None,
+        }
+    }
+}
+
+/// An entry in the event log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    pub event: Event,
+    /// the error (i.e. a [`crate::RustbootError::code`]) that caused a failure, if it's
+    /// known. `0` otherwise.
+    pub code: u8,
+    /// the record's sequence number i.e. a count of the events logged so far, which wraps.
+    pub seq: u16,
+    /// the version of the image the event concerns, `0` if it's unknown.
+    pub version: u32,
+}
+
+impl Record {
+    pub fn to_bytes(&self) -> [u8; RECORD_LEN] {
+        let mut bytes = [0u8; RECORD_LEN];
+        bytes[0] = self.event as u8;
+        bytes[1] = self.code;
+        bytes[2..4].copy_from_slice(&self.seq.to_le_bytes());
+        bytes[4..].copy_from_slice(&self.version.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a record. Returns `None` if `bytes` don't hold one (ex: a write that was cut short
+    /// by a reset).
+    pub fn from_bytes(bytes: &[u8; RECORD_LEN]) -> Option<Self> {
+        let [event, code, s0, s1, v0, v1, v2, v3] = *bytes;
+        Some(Record {
+            event: Event::from_byte(event)?,
+            code,
+            seq: u16::from_le_bytes([s0, s1]),
+            version: u32::from_le_bytes([v0, v1, v2, v3]),
+        })
+    }
+}
+
+/// Returns the number of used slots in `log` i.e. its first free slot is at `used * RECORD_LEN`.
+pub fn used(log: &[u8]) -> usize {
+    log.chunks_exact(RECORD_LEN)
+        .take_while(|slot| slot.iter().any(|byte| *byte != ERASED))
+        .count()
+}
+
+/// Returns `log`'s records, oldest first. Slots that don't hold a record are skipped.
+pub fn records(log: &[u8]) -> impl Iterator<Item = Record> + '_ {
+    log.chunks_exact(RECORD_LEN)
+        .take(used(log))
+        .filter_map(|slot| Record::from_bytes(slot.try_into().ok()?))
+}
+
+/// Returns the sequence number of the next record appended to `log`.
+pub fn next_seq(log: &[u8]) -> u16 {
+    records(log)
+        .last()
+        .map_or(0, |record| record.seq.wrapping_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(event: Event, seq: u16) -> Record {
+        Record {
+            event,
+            code: 0,
+            seq,
+            version: 0x0102_0304,
+        }
+    }
+
+    #[test]
+    fn record_bytes() {
+        let rec = Record {
+            code: 5,
+            ..record(Event::VerifyFailed, 0x1234)
+        };
+        assert_eq!(
+            rec.to_bytes(),
+            [0x05, 5, 0x34, 0x12, 0x04, 0x03, 0x02, 0x01]
+        );
+        assert_eq!(Record::from_bytes(&rec.to_bytes()), Some(rec));
+        assert_eq!(Record::from_bytes(&[ERASED; RECORD_LEN]), None);
+        assert_eq!(Record::from_bytes(&[0x00; RECORD_LEN]), None);
+    }
+
+    #[test]
+    fn appended_records() {
+        let mut log = [ERASED; 4 * RECORD_LEN];
+        assert_eq!((used(&log), next_seq(&log)), (0, 0));
+
+        log[..RECORD_LEN].copy_from_slice(&record(Event::UpdateTriggered, 7).to_bytes());
+        // a slot that doesn't hold a record (ex: a torn write)
+        log[RECORD_LEN] = 0x00;
+        log[2 * RECORD_LEN..3 * RECORD_LEN]
+            .copy_from_slice(&record(Event::UpdateInstalled, 8).to_bytes());
+        assert_eq!(used(&log), 3);
+        let events = [Event::UpdateTriggered, Event::UpdateInstalled];
+        assert!(records(&log).map(|rec| rec.event).eq(events));
+        assert_eq!(next_seq(&log), 9);
+
+        let last = record(Event::UpdateConfirmed, u16::MAX);
+        log[3 * RECORD_LEN..].copy_from_slice(&last.to_bytes());
+        assert_eq!(used(&log), 4);
+        assert_eq!(next_seq(&log), 0);
+    }
+}
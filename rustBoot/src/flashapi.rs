@@ -1,20 +1,49 @@
 use crate::image::image::{PartDescriptor, Swappable, ValidPart};
+use crate::{Result, RustbootError};
+
+/// Flash access, for partitions.
+///
+/// Writes and erases return [`RustbootError::FlashWriteFailed`](crate::RustbootError::FlashWriteFailed)
+/// or [`RustbootError::FlashEraseFailed`](crate::RustbootError::FlashEraseFailed) if they fail
+/// (or, where writes are verified, don't take), so callers can retry or abort before relying on
+/// the data.
+///
+/// It's the update engine's interface to flash. Applications should stage updates with
+/// `rustBoot_update`'s `FlashUpdater::write_update_chunk` (and `finalize_update`) rather than
+/// write partitions through it.
 pub trait FlashApi: Copy {
     fn flash_trailer_write<Part: ValidPart + Swappable>(
         self,
         part: &PartDescriptor<Part>,
         offset: usize,
-        data: *const u8,
-        len: usize,
-    );
+        data: &[u8],
+    ) -> Result<()>;
     fn flash_write<Part: ValidPart>(
         self,
         part: &PartDescriptor<Part>,
         offset: usize,
-        data: *const u8,
+        data: &[u8],
+    ) -> Result<()>;
+    fn flash_erase<Part: ValidPart>(
+        self,
+        part: &PartDescriptor<Part>,
+        offset: usize,
         len: usize,
-    );
-    fn flash_erase<Part: ValidPart>(self, part: &PartDescriptor<Part>, offset: usize, len: usize);
+    ) -> Result<()>;
+    /// Fills `data` with bytes read from a partition, starting at `offset` from the start of the partition.
+    ///
+    /// The default impl assumes a memory-mapped partition. Returns
+    /// [`RustbootError::FieldNotSet`] if the partition's start isn't set.
+    fn flash_read<Part: ValidPart>(
+        self,
+        part: &PartDescriptor<Part>,
+        offset: usize,
+        data: &mut [u8],
+    ) -> Result<()> {
+        let hdr = part.hdr.ok_or(RustbootError::FieldNotSet)?;
+        unsafe { core::ptr::copy_nonoverlapping(hdr.add(offset), data.as_mut_ptr(), data.len()) }
+        Ok(())
+    }
     fn flash_init();
     fn flash_lock();
     fn flash_unlock();
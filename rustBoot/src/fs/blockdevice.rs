@@ -37,19 +37,36 @@ pub trait BlockDevice {
     /// The errors that the `BlockDevice` can return. Must be debug formattable.
     type Error: core::fmt::Debug;
     /// Read one or more blocks, starting at the given block index.
-    fn read(
-        &self,
-        blocks: &mut [Block],
-        start_block_idx: BlockIdx,
-        reason: &str,
-    ) -> Result<(), Self::Error>;
+    fn read(&self, blocks: &mut [Block], start_block_idx: BlockIdx) -> Result<(), Self::Error>;
     /// Write one or more blocks, starting at the given block index.
     fn write(&self, blocks: &[Block], start_block_idx: BlockIdx) -> Result<(), Self::Error>;
     /// Determine how many blocks this device can hold.
     fn num_blocks(&self) -> Result<BlockCount, Self::Error>;
 }
 
-use super::fat::MAX_FAT_ENTRIES;
+/// Block device statistics - lets a caller see how hard a device had to work to serve its reads
+/// i.e. on marginal media.
+pub trait Statistics {
+    /// Return the number of reads that failed and were retried.
+    fn read_retries(&self) -> usize {
+        0
+    }
+
+    /// Return the number of reads that still failed after exhausting all retries.
+    fn read_failures(&self) -> usize {
+        0
+    }
+
+    /// Return the number of times the device was re-initialized to recover from a failed read.
+    fn reinits(&self) -> usize {
+        0
+    }
+
+    /// Return the number of times the device's clock was lowered to recover from failed reads.
+    fn clock_downshifts(&self) -> usize {
+        0
+    }
+}
 
 impl Block {
     /// All our blocks are a fixed length of 512 bytes. We do not support
@@ -77,39 +94,6 @@ impl Block {
         // - `N != 0`
         unsafe { core::slice::from_raw_parts_mut(blocks.as_mut_ptr().cast::<Block>(), len) }
     }
-
-    /// Converts a `&[[u8; 4]]` slice into a slice of `Block`s,
-    /// assuming that there's no remainder.
-    pub fn from_fat_entries(blocks: &mut [[u8; 4]]) -> &mut [Block] {
-        let len = (blocks.len() * 4) / Block::LEN;
-        // # Safety
-        //
-        // - The slice splits exactly into `N`-element chunks (aka `self.len() % N == 0`).
-        // - `N != 0`
-        unsafe { core::slice::from_raw_parts_mut(blocks.as_mut_ptr().cast::<Block>(), len) }
-    }
-
-    pub fn to_fat_entries(blocks: &mut [Block]) -> &mut [[u8; 4]] {
-        let len = MAX_FAT_ENTRIES as usize;
-        let ptr = (&mut blocks[0].contents).as_mut_ptr() as *mut [u8; 4];
-        let buff;
-        unsafe {
-            // there is no way to turn a slice of arrays into a slice of bytes i.e.
-            // turn a &mut [[u8; 512]] to a &mut [[u8; 4]] safely.
-            // at least, we cannot do this without prior allocation. In a no_std environment, where
-            // a heap doesnt exist, this becomes a pain. We'd either have to use something like `heapless`
-            // (and predict the amount of space we'll use for allocation) or take the last resort.
-            //
-            // use `from_raw_parts_mut` to construct a mutable slice of bytes from a slice of arrays.
-            //
-            // # Safety
-            // - This is still safe as it satifies `of from_raw_parts_mut` usage conditions.
-            // - `Block's` are always multiples of 4.
-            buff = core::slice::from_raw_parts_mut(ptr, len);
-        }
-        assert_eq!(blocks.len() * Block::LEN, buff.len() * 4);
-        buff
-    }
 }
 
 impl Default for Block {
@@ -0,0 +1,104 @@
+//! Boot sources i.e. where a bootloader loads its fit-image from, tried in priority order.
+//!
+//! A device whose primary partition is corrupted (ex: a torn write or a worn-out SD card) can
+//! still boot from a recovery partition, rather than being bricked. Sources are tried in order
+//! (see [`first_bootable`]) until one of them boots.
+
+use core::fmt;
+
+use super::controller::VolumeIdx;
+
+/// A source to load (and boot) a fit-image from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootSource {
+    /// a FAT partition on the boot device (i.e. the SD card), by its MBR index.
+    Partition(usize),
+    /// a USB mass-storage device.
+    Usb,
+    /// a TFTP server, over the network.
+    Tftp,
+}
+
+/// The default boot order - the primary partition, then a recovery partition, then USB and TFTP
+/// (where the board supports them).
+pub const DEFAULT_BOOT_ORDER: [BootSource; 4] = [
+    BootSource::Partition(0),
+    BootSource::Partition(1),
+    BootSource::Usb,
+    BootSource::Tftp,
+];
+
+impl BootSource {
+    /// Returns the boot device's volume, for partitions.
+    pub fn volume(&self) -> Option<VolumeIdx> {
+        match self {
+            BootSource::Partition(idx) => Some(VolumeIdx(*idx)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BootSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootSource::Partition(idx) => write!(f, "partition {}", idx),
+            BootSource::Usb => write!(f, "usb"),
+            BootSource::Tftp => write!(f, "tftp"),
+        }
+    }
+}
+
+/// Tries to boot from each of `sources`, in order, with `boot`. Returns the first source that
+/// boots along with `boot`'s result or `None` if all of them fail. Failed sources are reported to
+/// `on_failure` (ex: to log them).
+pub fn first_bootable<T, E>(
+    sources: &[BootSource],
+    mut boot: impl FnMut(BootSource) -> Result<T, E>,
+    mut on_failure: impl FnMut(BootSource, E),
+) -> Option<(BootSource, T)> {
+    sources.iter().find_map(|&source| match boot(source) {
+        Ok(val) => Some((source, val)),
+        Err(e) => {
+            on_failure(source, e);
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_are_tried_in_order() {
+        let mut failed = Vec::new();
+        // the primary partition is corrupted, the recovery partition boots
+        let booted = first_bootable(
+            &DEFAULT_BOOT_ORDER,
+            |source| match source {
+                BootSource::Partition(1) => Ok(0x8_0000),
+                _ => Err("corrupted"),
+            },
+            |source, _| failed.push(source),
+        );
+        assert_eq!(booted, Some((BootSource::Partition(1), 0x8_0000)));
+        assert_eq!(failed, [BootSource::Partition(0)]);
+
+        failed.clear();
+        let booted = first_bootable(
+            &DEFAULT_BOOT_ORDER,
+            |_| Err::<usize, _>("unavailable"),
+            |source, _| failed.push(source),
+        );
+        assert_eq!(booted, None);
+        assert_eq!(failed, DEFAULT_BOOT_ORDER);
+    }
+
+    #[test]
+    fn source_names() {
+        assert_eq!(format!("{}", BootSource::Partition(1)), "partition 1");
+        assert_eq!(format!("{}", BootSource::Tftp), "tftp");
+        assert_eq!(BootSource::Partition(1).volume(), Some(VolumeIdx(1)));
+        assert_eq!(BootSource::Usb.volume(), None);
+    }
+}
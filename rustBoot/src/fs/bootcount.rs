@@ -0,0 +1,268 @@
+//! A boot-attempt counter for updates i.e. the health-check contract between rustBoot and the
+//! booted system's userspace.
+//!
+//! A passive fit-image (i.e. an update) is booted at most `max_attempts` times before it's
+//! confirmed. The counter is kept in [`BOOT_COUNT_FILE`], in the FAT partition's root directory
+//!
+//! ```text
+//! image_version=ts_1663342128
+//! attempts_left=2
+//! confirmed=false
+//! ```
+//!
+//! - rustBoot arms the counter on the first boot of an update (i.e. of a new `image_version`)
+//!   and decrements it on every boot of that update, see [`next_attempt`].
+//! - userspace confirms the update (ex: once its health checks pass) by rewriting the file with
+//!   `confirmed=true`. It's the only way to stop the count, rustBoot never confirms an update.
+//! - once an unconfirmed update has used up its attempts, rustBoot reverts to the active
+//!   fit-image (i.e. the previous one).
+//!
+//! A confirmation only stops the count, promoting the update to the active fit-image is still
+//! done through `updt.txt`. Boards without a FAT partition they can write (ex: ones that keep the
+//! counter in an I2C EEPROM) can reuse [`BootCount`]'s encoding and [`next_attempt`].
+
+use super::blockdevice::BlockDevice;
+use super::controller::{Controller, Error, Volume};
+use super::filesystem::{Directory, Mode, TimeSource};
+
+/// The boot-attempt counter's file, in the FAT partition's root directory.
+pub const BOOT_COUNT_FILE: &str = "BOOTCNT.TXT";
+
+/// The largest encoded [`BootCount`].
+pub const BOOT_COUNT_MAX_LEN: usize = 64;
+
+/// The boot-attempt counter of an update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootCount {
+    /// The update's version i.e. its `updt.txt` `image_version`.
+    pub version: u32,
+    /// The boots left before rustBoot reverts to the active fit-image.
+    pub attempts_left: u8,
+    /// Set by userspace, once the update is healthy.
+    pub confirmed: bool,
+}
+
+/// What to do with an update, given its boot-attempt counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootAttempt {
+    /// The update's been confirmed, boot it.
+    Confirmed,
+    /// Boot the update, after storing its (decremented) counter.
+    Trial(BootCount),
+    /// The update wasn't confirmed in time, revert to the active fit-image.
+    Exhausted,
+}
+
+/// Decides whether the update with `version` may be booted, given its `stored` counter. A counter
+/// that belongs to another version (or none at all) is re-armed with `max_attempts`.
+pub fn next_attempt(stored: Option<BootCount>, version: u32, max_attempts: u8) -> BootAttempt {
+    let attempts_left = match stored {
+        Some(count) if count.version == version && count.confirmed => {
+            return BootAttempt::Confirmed
+        }
+        Some(count) if count.version == version => count.attempts_left,
+        _ => max_attempts,
+    };
+    match attempts_left.checked_sub(1) {
+        Some(attempts_left) => BootAttempt::Trial(BootCount {
+            version,
+            attempts_left,
+            confirmed: false,
+        }),
+        None => BootAttempt::Exhausted,
+    }
+}
+
+impl BootCount {
+    /// Encodes the counter into `out` (see the module's docs for its format). Returns the number
+    /// of bytes written, `None` if it doesn't fit.
+    pub fn encode(&self, out: &mut [u8]) -> Option<usize> {
+        let (mut version, mut attempts_left) = ([0u8; 10], [0u8; 10]);
+        let parts: [&[u8]; 6] = [
+            b"image_version=ts_",
+            decimal(self.version, &mut version),
+            b"\nattempts_left=",
+            decimal(self.attempts_left as u32, &mut attempts_left),
+            b"\nconfirmed=",
+            match self.confirmed {
+                true => b"true\n",
+                false => b"false\n",
+            },
+        ];
+        let mut len = 0;
+        for part in parts {
+            out.get_mut(len..len + part.len())?.copy_from_slice(part);
+            len += part.len();
+        }
+        Some(len)
+    }
+
+    /// Decodes a counter. Unknown keys are skipped, a missing `confirmed` is `false`. Returns
+    /// `None` if the counter is malformed (ex: a torn write).
+    pub fn decode(input: &[u8]) -> Option<Self> {
+        let input = core::str::from_utf8(input).ok()?;
+        let (mut version, mut attempts_left, mut confirmed) = (None, None, false);
+        for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.split_once('=')? {
+                ("image_version", val) => {
+                    version = Some(val.strip_prefix("ts_")?.parse::<u32>().ok()?)
+                }
+                ("attempts_left", val) => attempts_left = Some(val.parse::<u8>().ok()?),
+                ("confirmed", val) => confirmed = val.parse::<bool>().ok()?,
+                (_, _) => {}
+            }
+        }
+        Some(BootCount {
+            version: version?,
+            attempts_left: attempts_left?,
+            confirmed,
+        })
+    }
+}
+
+/// Writes `val`'s decimal digits to the end of `buf`, returning them.
+fn decimal(mut val: u32, buf: &mut [u8; 10]) -> &[u8] {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (val % 10) as u8;
+        val /= 10;
+        if val == 0 {
+            break &buf[start..];
+        }
+    }
+}
+
+impl<D, T> Controller<D, T>
+where
+    D: BlockDevice,
+    T: TimeSource,
+    <D as BlockDevice>::Error: core::fmt::Debug,
+{
+    /// Counts a boot of the update with `version` (see [`next_attempt`]) i.e. reads the
+    /// boot-attempt counter from `dir` and, if the update is to be booted on trial, stores the
+    /// decremented counter.
+    ///
+    /// A counter that can't be decoded counts as exhausted, so a corrupted file never grants an
+    /// update more attempts.
+    pub fn count_boot_attempt(
+        &mut self,
+        volume: &mut Volume,
+        dir: &Directory,
+        version: u32,
+        max_attempts: u8,
+    ) -> Result<BootAttempt, Error<D::Error>> {
+        let mut buf = [0u8; BOOT_COUNT_MAX_LEN];
+        let stored = match self.open_file_in_dir(volume, dir, BOOT_COUNT_FILE, Mode::ReadOnly) {
+            Ok(mut file) => {
+                let fits = file.length() as usize <= buf.len();
+                let read = self.read(volume, &mut file, &mut buf);
+                self.close_file(volume, file)?;
+                match (fits, BootCount::decode(&buf[..read?])) {
+                    (true, Some(count)) => Some(count),
+                    _ => return Ok(BootAttempt::Exhausted),
+                }
+            }
+            Err(Error::FileNotFound) => None,
+            Err(e) => return Err(e),
+        };
+        let attempt = next_attempt(stored, version, max_attempts);
+        if let BootAttempt::Trial(count) = attempt {
+            let len = count
+                .encode(&mut buf)
+                .ok_or(Error::FormatError("boot count does not fit in its buffer"))?;
+            let mut file = self.open_file_in_dir(
+                volume,
+                dir,
+                BOOT_COUNT_FILE,
+                Mode::ReadWriteCreateOrTruncate,
+            )?;
+            let written = self.write(volume, &mut file, &buf[..len]);
+            self.close_file(volume, file)?;
+            if written? != len {
+                return Err(Error::NotEnoughSpace);
+            }
+        }
+        Ok(attempt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_attempt() {
+        let count = |version, attempts_left, confirmed| BootCount {
+            version,
+            attempts_left,
+            confirmed,
+        };
+        // a new update arms the counter
+        assert_eq!(
+            next_attempt(None, 7, 3),
+            BootAttempt::Trial(count(7, 2, false))
+        );
+        assert_eq!(
+            next_attempt(Some(count(6, 0, false)), 7, 3),
+            BootAttempt::Trial(count(7, 2, false))
+        );
+        // and every boot decrements it
+        assert_eq!(
+            next_attempt(Some(count(7, 1, false)), 7, 3),
+            BootAttempt::Trial(count(7, 0, false))
+        );
+        assert_eq!(
+            next_attempt(Some(count(7, 0, false)), 7, 3),
+            BootAttempt::Exhausted
+        );
+        assert_eq!(next_attempt(None, 7, 0), BootAttempt::Exhausted);
+        // until userspace confirms it
+        assert_eq!(
+            next_attempt(Some(count(7, 0, true)), 7, 3),
+            BootAttempt::Confirmed
+        );
+        // a confirmation of another version doesn't count
+        assert_eq!(
+            next_attempt(Some(count(6, 2, true)), 7, 3),
+            BootAttempt::Trial(count(7, 2, false))
+        );
+    }
+
+    #[test]
+    fn test_boot_count_encoding() {
+        let count = BootCount {
+            version: 1663342128,
+            attempts_left: 2,
+            confirmed: false,
+        };
+        let mut buf = [0u8; BOOT_COUNT_MAX_LEN];
+        let len = count.encode(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            b"image_version=ts_1663342128\nattempts_left=2\nconfirmed=false\n"
+        );
+        assert_eq!(BootCount::decode(&buf[..len]), Some(count));
+        assert_eq!(count.encode(&mut buf[..len - 1]), None);
+
+        // as written by a userspace confirm tool
+        assert_eq!(
+            BootCount::decode(b"image_version=ts_0\r\nattempts_left=0\r\nconfirmed=true\r\n"),
+            Some(BootCount {
+                version: 0,
+                attempts_left: 0,
+                confirmed: true,
+            })
+        );
+        // torn or malformed counters
+        assert_eq!(BootCount::decode(&buf[..20]), None);
+        assert_eq!(
+            BootCount::decode(b"image_version=ts_1\nattempts_left=x\n"),
+            None
+        );
+        assert_eq!(
+            BootCount::decode(b"image_version=1\nattempts_left=1\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Chunked (i.e. content-addressed) fit-images, for differential updates.
+//!
+//! A full fit-image is hundreds of MB, but consecutive releases share most of their content.
+//! `rbsigner chunk-index` splits a signed fit-image into content-defined chunks and publishes a
+//! *chunk index* (a `.cix` file) along with the chunks. Chunks are stored in the FAT partition's
+//! [`CHUNK_DIR`] directory, named after their digest (see [`chunk_file_name`]), so a chunk that's
+//! shared by two releases is only stored (and downloaded) once. An updater only fetches the chunks
+//! that aren't in the store yet.
+//!
+//! rustBoot boots a chunk index (i.e. an `image_name=xx.cix` in `updt.txt`) by reassembling the
+//! fit-image from the store, see [`Controller::read_chunked_with`]. Every chunk is checked against
+//! its digest as it's loaded and the reassembled fit-image is authenticated as usual.
+//!
+//! A chunk index is a header followed by an entry per chunk, in file order
+//!
+//! ```text
+//!  0       4             8            12           16
+//!  | RBCI  | version (le) | count (le) | size (le)  |
+//!  | sha256 digest (32 bytes)          | len (le)   |  x count
+//! ```
+//!
+//! - `size` is the size of the fit-image i.e. the sum of the chunks' lengths.
+//! - chunk names are truncated digests, a (very unlikely) name collision is caught by the chunk's
+//!   digest check.
+
+use core::convert::TryInto;
+
+use sha2::{Digest, Sha256};
+
+use super::blockdevice::{Block, BlockDevice};
+use super::controller::{Controller, Error, FatCache, Volume, VolumeType};
+use super::filesystem::{Directory, File, Mode, TimeSource};
+
+/// The chunk store, in the FAT partition's root directory.
+pub const CHUNK_DIR: &str = "CHUNKS";
+/// The extension of a chunk index, in `updt.txt`.
+pub const INDEX_EXTENSION: &str = ".cix";
+/// The length of a chunk index's header, in bytes.
+pub const INDEX_HEADER_SIZE: usize = 16;
+/// The length of a chunk index's entry, in bytes.
+pub const INDEX_ENTRY_SIZE: usize = 36;
+
+const MAGIC: &[u8; 4] = b"RBCI";
+const VERSION: u32 = 1;
+
+/// Reasons a chunk index can't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkError {
+    /// Not a chunk index i.e. a missing or malformed header.
+    BadHeader,
+    /// An unsupported chunk index version.
+    BadVersion,
+    /// A chunk index (or an entry) that's truncated.
+    BadLength,
+}
+
+/// A chunk index's header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexHeader {
+    /// the number of chunks.
+    pub count: u32,
+    /// the size of the fit-image, in bytes.
+    pub size: u32,
+}
+
+impl IndexHeader {
+    pub fn to_bytes(&self) -> [u8; INDEX_HEADER_SIZE] {
+        let mut bytes = [0u8; INDEX_HEADER_SIZE];
+        bytes[..4].copy_from_slice(MAGIC);
+        bytes[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.count.to_le_bytes());
+        bytes[12..].copy_from_slice(&self.size.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChunkError> {
+        let bytes = bytes
+            .get(..INDEX_HEADER_SIZE)
+            .ok_or(ChunkError::BadLength)?;
+        if &bytes[..4] != MAGIC {
+            return Err(ChunkError::BadHeader);
+        }
+        let field = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        if field(4) != VERSION {
+            return Err(ChunkError::BadVersion);
+        }
+        Ok(IndexHeader {
+            count: field(8),
+            size: field(12),
+        })
+    }
+}
+
+/// A chunk index's entry i.e. a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkEntry {
+    /// the chunk's SHA-256 digest.
+    pub digest: [u8; 32],
+    /// the chunk's length, in bytes.
+    pub len: u32,
+}
+
+impl ChunkEntry {
+    pub fn to_bytes(&self) -> [u8; INDEX_ENTRY_SIZE] {
+        let mut bytes = [0u8; INDEX_ENTRY_SIZE];
+        bytes[..32].copy_from_slice(&self.digest);
+        bytes[32..].copy_from_slice(&self.len.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChunkError> {
+        let bytes = bytes.get(..INDEX_ENTRY_SIZE).ok_or(ChunkError::BadLength)?;
+        Ok(ChunkEntry {
+            digest: bytes[..32].try_into().unwrap(),
+            len: u32::from_le_bytes(bytes[32..].try_into().unwrap()),
+        })
+    }
+
+    /// The name of the chunk's file, in [`CHUNK_DIR`].
+    pub fn file_name(&self) -> [u8; 12] {
+        chunk_file_name(&self.digest)
+    }
+}
+
+/// Returns the (8.3) name of a chunk's file, given its digest i.e. the digest's first 11 hex
+/// digits, with a `.` after the 8th. Ex: `1A2B3C4D.5E6`.
+pub fn chunk_file_name(digest: &[u8; 32]) -> [u8; 12] {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let mut name = [b'.'; 12];
+    let digits = digest
+        .iter()
+        .flat_map(|byte| [HEX[(byte >> 4) as usize], HEX[(byte & 0xf) as usize]]);
+    (0..8)
+        .chain(9..12)
+        .zip(digits)
+        .for_each(|(idx, digit)| name[idx] = digit);
+    name
+}
+
+/// Returns true if `name` is a chunk index i.e. has the [`INDEX_EXTENSION`].
+pub fn is_chunk_index(name: &str) -> bool {
+    name.len() > INDEX_EXTENSION.len()
+        && name[name.len() - INDEX_EXTENSION.len()..].eq_ignore_ascii_case(INDEX_EXTENSION)
+}
+
+impl<D, T> Controller<D, T>
+where
+    D: BlockDevice,
+    T: TimeSource,
+    <D as BlockDevice>::Error: core::fmt::Debug,
+{
+    /// Reassembles the fit-image described by the chunk index `index_name` (in `dir`) into
+    /// `buffer`, from the chunks in [`CHUNK_DIR`]. Returns the fit-image's size.
+    ///
+    /// Every chunk is checked against its digest before it's handed to `on_chunk` (ex: to hash the
+    /// fit-image while it's reassembled), in file order. A missing or corrupted chunk is an error.
+    ///
+    /// Chunks are read a cluster-run at a time (see [`Self::read_multi`]) i.e. `buffer` must have
+    /// room for a cluster's worth of bytes past the fit-image.
+    pub fn read_chunked_with<F: FnMut(&[u8]), const SECTORS: usize>(
+        &mut self,
+        volume: &mut Volume,
+        dir: &Directory,
+        index_name: &str,
+        buffer: &mut [u8],
+        cache: &FatCache<SECTORS>,
+        on_chunk: F,
+    ) -> Result<usize, Error<D::Error>> {
+        let mut index = self.open_file_in_dir(volume, dir, index_name, Mode::ReadOnly)?;
+        let chunk_dir = match self.open_dir(volume, dir, CHUNK_DIR) {
+            Ok(chunk_dir) => chunk_dir,
+            Err(e) => {
+                self.close_file(volume, index)?;
+                return Err(e);
+            }
+        };
+        let res = self.read_chunks(volume, &mut index, &chunk_dir, buffer, cache, on_chunk);
+        self.close_dir(volume, chunk_dir);
+        self.close_file(volume, index)?;
+        res
+    }
+
+    fn read_chunks<F: FnMut(&[u8]), const SECTORS: usize>(
+        &mut self,
+        volume: &mut Volume,
+        index: &mut File,
+        chunk_dir: &Directory,
+        buffer: &mut [u8],
+        cache: &FatCache<SECTORS>,
+        mut on_chunk: F,
+    ) -> Result<usize, Error<D::Error>> {
+        let cluster_len = match &volume.volume_type {
+            VolumeType::Fat(fat) => fat.blocks_per_cluster as usize * Block::LEN,
+        };
+        let mut bytes = [0u8; INDEX_HEADER_SIZE];
+        self.read_exact(volume, index, &mut bytes)?;
+        let header = IndexHeader::from_bytes(&bytes).map_err(index_error)?;
+        if header.size as usize + cluster_len > buffer.len() {
+            return Err(Error::FormatError(
+                "chunked fit-image does not fit in buffer",
+            ));
+        }
+
+        let mut offset = 0;
+        for _ in 0..header.count {
+            let mut bytes = [0u8; INDEX_ENTRY_SIZE];
+            self.read_exact(volume, index, &mut bytes)?;
+            let entry = ChunkEntry::from_bytes(&bytes).map_err(index_error)?;
+            let len = entry.len as usize;
+            if offset + len > header.size as usize {
+                return Err(Error::FormatError("chunk index size mismatch"));
+            }
+
+            let name = entry.file_name();
+            let name = core::str::from_utf8(&name).unwrap();
+            let mut chunk = self.open_file_in_dir(volume, chunk_dir, name, Mode::ReadOnly)?;
+            let mut hasher = Sha256::new();
+            let res = match chunk.length() as usize == len {
+                true => {
+                    self.read_multi_with(volume, &mut chunk, &mut buffer[offset..], cache, |data| {
+                        hasher.update(data)
+                    })
+                }
+                false => Err(Error::FormatError("chunk length mismatch")),
+            };
+            self.close_file(volume, chunk)?;
+            if res? != len || hasher.finalize().as_slice() != entry.digest {
+                return Err(Error::FormatError("chunk digest mismatch"));
+            }
+            on_chunk(&buffer[offset..offset + len]);
+            offset += len;
+        }
+        match offset == header.size as usize {
+            true => Ok(offset),
+            false => Err(Error::FormatError("chunk index size mismatch")),
+        }
+    }
+
+    /// Fills `buf` from `file`, a short read is an error.
+    fn read_exact(
+        &mut self,
+        volume: &Volume,
+        file: &mut File,
+        buf: &mut [u8],
+    ) -> Result<(), Error<D::Error>> {
+        match self.read(volume, file, buf)? == buf.len() {
+            true => Ok(()),
+            false => Err(Error::EndOfFile),
+        }
+    }
+}
+
+fn index_error<E: core::fmt::Debug>(e: ChunkError) -> Error<E> {
+    match e {
+        ChunkError::BadHeader => Error::FormatError("not a chunk index"),
+        ChunkError::BadVersion => Error::FormatError("unsupported chunk index version"),
+        ChunkError::BadLength => Error::FormatError("truncated chunk index"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_roundtrip() {
+        let header = IndexHeader {
+            count: 3,
+            size: 0x0012_3456,
+        };
+        let bytes = header.to_bytes();
+        assert_eq!(&bytes[..8], b"RBCI\x01\x00\x00\x00");
+        assert_eq!(IndexHeader::from_bytes(&bytes), Ok(header));
+        assert_eq!(
+            IndexHeader::from_bytes(&bytes[..15]),
+            Err(ChunkError::BadLength)
+        );
+        let mut bad = bytes;
+        bad[0] = b'X';
+        assert_eq!(IndexHeader::from_bytes(&bad), Err(ChunkError::BadHeader));
+        bad = bytes;
+        bad[4] = 2;
+        assert_eq!(IndexHeader::from_bytes(&bad), Err(ChunkError::BadVersion));
+
+        let entry = ChunkEntry {
+            digest: [0xa5; 32],
+            len: 65536,
+        };
+        assert_eq!(ChunkEntry::from_bytes(&entry.to_bytes()), Ok(entry));
+    }
+
+    #[test]
+    fn chunk_names() {
+        let mut digest = [0u8; 32];
+        digest[..6].copy_from_slice(&[0x1a, 0x2b, 0x3c, 0x4d, 0x5e, 0x6f]);
+        assert_eq!(&chunk_file_name(&digest), b"1A2B3C4D.5E6");
+
+        assert!(is_chunk_index("signed-v1663342128.cix"));
+        assert!(is_chunk_index("SIGNED~1.CIX"));
+        assert!(!is_chunk_index("signed-v1663342128.itb"));
+        assert!(!is_chunk_index(".cix"));
+    }
+}
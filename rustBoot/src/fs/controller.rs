@@ -28,7 +28,9 @@ use super::filesystem::{
     Timestamp, MAX_FILE_SIZE,
 };
 
-pub use super::fat::FAT_CACHE;
+use crate::RustbootError;
+
+pub use super::fat::{FatCache, MAX_FAT_SECTORS};
 
 // ****************************************************************************
 //
@@ -90,6 +92,22 @@ where
     NotInBlock,
 }
 
+/// A filesystem error converts into a [`RustbootError`]. A block device's errors keep their own
+/// conversion (ex: into a [`RustbootError::Hal`] error), any other error means the filesystem (or
+/// a file) can't be read i.e. it's an [`RustbootError::InvalidImage`].
+impl<E> From<Error<E>> for RustbootError
+where
+    E: core::fmt::Debug + Into<RustbootError>,
+{
+    fn from(e: Error<E>) -> Self {
+        match e {
+            Error::DeviceError(e) => e.into(),
+            Error::TooManyOpenDirs | Error::TooManyOpenFiles => RustbootError::BufferTooSmall,
+            _ => RustbootError::InvalidImage,
+        }
+    }
+}
+
 /// We have to track what directories are open to prevent users from modifying
 /// open directories (like creating a file when we have an open iterator).
 pub const MAX_OPEN_DIRS: usize = 4;
@@ -98,6 +116,8 @@ pub const MAX_OPEN_DIRS: usize = 4;
 /// deleting open files (like Windows does).
 pub const MAX_OPEN_FILES: usize = 4;
 
+/// A `TimeSource` for boards without a real-time clock - every timestamp it hands out is the
+/// epoch.
 pub struct TestClock;
 
 impl TimeSource for TestClock {
@@ -125,7 +145,7 @@ where
     pub block_device: D,
     pub timesource: T,
     open_dirs: [(VolumeIdx, Cluster); MAX_OPEN_DIRS],
-    open_files: [(VolumeIdx, Cluster); MAX_OPEN_DIRS],
+    open_files: [(VolumeIdx, Cluster); MAX_OPEN_FILES],
 }
 
 /// Represents a partition with a filesystem within it.
@@ -195,16 +215,16 @@ where
     T: TimeSource,
     <D as BlockDevice>::Error: core::fmt::Debug,
 {
-    /// Create a new Disk Controller using a generic `BlockDevice`. From this
-    /// controller we can open volumes (partitions) and with those we can open
-    /// files.
+    /// Create a new Disk Controller using a generic `BlockDevice` i.e. an sd-card, SPI-SD or
+    /// QSPI flash. From this controller we can open volumes (partitions) and with those we can
+    /// open files.
     pub fn new(block_device: D, timesource: T) -> Controller<D, T> {
-        info!("create new emmc-fat controller...");
+        info!("create new fat controller...");
         Controller {
             block_device,
             timesource,
-            open_dirs: [(VolumeIdx(0), Cluster::INVALID); 4],
-            open_files: [(VolumeIdx(0), Cluster::INVALID); 4],
+            open_dirs: [(VolumeIdx(0), Cluster::INVALID); MAX_OPEN_DIRS],
+            open_files: [(VolumeIdx(0), Cluster::INVALID); MAX_OPEN_FILES],
         }
     }
 
@@ -233,7 +253,7 @@ where
         let (part_type, lba_start, num_blocks) = {
             let mut blocks = [Block::new()];
             self.block_device
-                .read(&mut blocks, BlockIdx(0), "read_mbr")
+                .read(&mut blocks, BlockIdx(0))
                 .map_err(Error::DeviceError)?;
             let block = &blocks[0];
             // We only support Master Boot Record (MBR) partitioned cards, not
@@ -548,7 +568,7 @@ where
                 open_files_row = Some(i);
             }
         }
-        open_files_row.ok_or(Error::TooManyOpenDirs)
+        open_files_row.ok_or(Error::TooManyOpenFiles)
     }
 
     /// Delete a closed file with the given full path, if exists.
@@ -582,50 +602,55 @@ where
         };
     }
 
-    /// Populates a static cache with the `file allocation table` contents (of the supplied volume).
+    /// Populates `cache` with the `file allocation table` contents (of the supplied volume).
     /// We use the cache to walk the FAT table. This greatly improves performance when loading large
     /// files (such as fit-images).
     ///
+    /// The cache is provided by the caller and sized with `SECTORS` i.e. a board picks how much
+    /// RAM to spend on it. If the FAT is larger, only its first `SECTORS` sectors are cached.
+    ///
     /// Note:
     /// - Only `FAT32` volumes are supported
     ///
-    pub fn populate_fat_cache(
+    pub fn populate_fat_cache<const SECTORS: usize>(
         &self,
         volume: &Volume,
+        cache: &mut FatCache<SECTORS>,
     ) -> Result<(), Error<<D as BlockDevice>::Error>> {
         match &volume.volume_type {
-            VolumeType::Fat(vol) => vol.populate_static_fat_cache(self)?,
+            VolumeType::Fat(vol) => vol.populate_fat_cache(self, cache)?,
         }
         Ok(())
     }
 
     /// Returns the number of contiguous clusters. If the next cluster in the sequence isn't contiguous
     /// (i.e. is fragmented), it returns a 0
-    fn check_contiguous_cluster_count(
+    fn check_contiguous_cluster_count<const SECTORS: usize>(
         &self,
         volume: &Volume,
+        cache: &FatCache<SECTORS>,
         mut cluster: Cluster,
         blocks_per_cluster: u8,
     ) -> Result<u32, Error<D::Error>> {
         let mut contiguous_cluster_count = 0u32;
         let mut next_cluster = match &volume.volume_type {
-            VolumeType::Fat(fat) => match fat.next_cluster_in_fat_cache(cluster) {
+            VolumeType::Fat(fat) => match fat.next_cluster_in_fat_cache(self, cache, cluster) {
                 Ok(cluster) => cluster,
                 Err(e) => match e {
                     // If this is the last cluster for the file, simply return the same cluster.
                     Error::EndOfFile => cluster,
-                    _ => panic!("Error: traversing the FAT table, {:?}", e),
+                    _ => return Err(e),
                 },
             },
         };
         while next_cluster.0.wrapping_sub(cluster.0) == 1 {
             cluster = next_cluster;
             next_cluster = match &volume.volume_type {
-                VolumeType::Fat(fat) => match fat.next_cluster_in_fat_cache(cluster) {
+                VolumeType::Fat(fat) => match fat.next_cluster_in_fat_cache(self, cache, cluster) {
                     Ok(cluster) => cluster,
                     Err(e) => match e {
                         Error::EndOfFile => break,
-                        _ => panic!("Error: traversing the FAT table, {:?}", e),
+                        _ => return Err(e),
                     },
                 },
             };
@@ -654,12 +679,29 @@ where
     /// - Providing a buffer that isn't a multiple of `block-size` bytes and is less-than file-length will result
     /// in an `out of bounds` error. In other words, for files that aren't exact multiples of `block-size` bytes,
     /// a buffer of length (block-size * (file length/ block size)) + 1 must be provided.
+    /// - The FAT is walked via `cache`, see [`Self::populate_fat_cache`].
+    ///
+    pub fn read_multi<const SECTORS: usize>(
+        &mut self,
+        volume: &Volume,
+        file: &mut File,
+        buffer: &mut [u8],
+        cache: &FatCache<SECTORS>,
+    ) -> Result<usize, Error<D::Error>> {
+        self.read_multi_with(volume, file, buffer, cache, |_| {})
+    }
+
+    /// Same as [`Self::read_multi`] but hands every chunk of file-data to `on_chunk` as soon as it
+    /// lands in `buffer`, so callers can process (e.g. hash) a file while the rest of it is still being read.
     ///
-    pub fn read_multi(
+    /// Chunks are passed in file order and exclude any trailing block padding.
+    pub fn read_multi_with<F: FnMut(&[u8]), const SECTORS: usize>(
         &mut self,
         volume: &Volume,
         file: &mut File,
         buffer: &mut [u8],
+        cache: &FatCache<SECTORS>,
+        mut on_chunk: F,
     ) -> Result<usize, Error<D::Error>> {
         let blocks_per_cluster = match &volume.volume_type {
             VolumeType::Fat(fat) => fat.blocks_per_cluster,
@@ -677,8 +719,12 @@ where
 
         while file_blocks > 0 {
             // Walk the FAT to see if we have contiguos clusters
-            let contiguous_cluster_count =
-                self.check_contiguous_cluster_count(volume, starting_cluster, blocks_per_cluster)?;
+            let contiguous_cluster_count = self.check_contiguous_cluster_count(
+                volume,
+                cache,
+                starting_cluster,
+                blocks_per_cluster,
+            )?;
 
             let blocks_to_read = (contiguous_cluster_count + 1) * blocks_per_cluster as u32;
             let bytes_to_read = Block::LEN * blocks_to_read as usize;
@@ -690,8 +736,10 @@ where
             };
 
             self.block_device
-                .read(Block::from_array_slice(blocks), block_idx, "read_multi")
+                .read(Block::from_array_slice(blocks), block_idx)
                 .map_err(Error::DeviceError)?;
+            let bytes = bytes_to_read.min(file.left() as usize);
+            on_chunk(&buffer[block_read_counter..block_read_counter + bytes]);
 
             file_blocks = match file_blocks.checked_sub(blocks_to_read) {
                 // checked integer subtraction
@@ -700,24 +748,25 @@ where
             };
             let next_cluster = match &volume.volume_type {
                 VolumeType::Fat(fat) => {
-                    match fat.next_cluster_in_fat_cache(starting_cluster + contiguous_cluster_count)
-                    {
+                    match fat.next_cluster_in_fat_cache(
+                        self,
+                        cache,
+                        starting_cluster + contiguous_cluster_count,
+                    ) {
                         Ok(cluster) => cluster,
                         Err(e) => match e {
                             Error::EndOfFile => {
-                                let bytes = bytes_to_read.min(file.left() as usize);
                                 bytes_read += bytes;
                                 file.seek_from_current(bytes as i32).unwrap();
                                 break;
                             }
-                            _ => panic!("Error: traversing the FAT table, {:?}", e),
+                            _ => return Err(e),
                         },
                     }
                 }
             };
             starting_cluster = next_cluster;
 
-            let bytes = bytes_to_read.min(file.left() as usize);
             bytes_read += bytes;
             file.seek_from_current(bytes as i32).unwrap();
             block_read_counter += Block::LEN * blocks_to_read as usize;
@@ -742,7 +791,7 @@ where
                 self.find_data_on_disk(volume, &mut file.current_cluster, file.current_offset)?;
             let mut blocks = [Block::new()];
             self.block_device
-                .read(&mut blocks, block_idx, "read")
+                .read(&mut blocks, block_idx)
                 .map_err(Error::DeviceError)?;
             let block = &blocks[0];
             let to_copy = block_avail.min(space).min(file.left() as usize);
@@ -832,7 +881,7 @@ where
             if block_offset != 0 {
                 info!("Partial block write");
                 self.block_device
-                    .read(&mut blocks, block_idx, "read")
+                    .read(&mut blocks, block_idx)
                     .map_err(Error::DeviceError)?;
             }
             let block = &mut blocks[0];
@@ -930,7 +979,7 @@ where
     ) -> Result<(), Error<D::Error>> {
         let mut blocks = [Block::new()];
         self.block_device
-            .read(&mut blocks, entry.entry_block, "read")
+            .read(&mut blocks, entry.entry_block)
             .map_err(Error::DeviceError)?;
         let block = &mut blocks[0];
 
@@ -970,6 +1019,91 @@ fn solve_mode_variant(mode: Mode, dir_entry_is_some: bool) -> Mode {
     mode
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A RAM-backed block device, standing in for an SPI-SD card or QSPI flash.
+    struct RamDisk([Block; 1]);
+
+    impl BlockDevice for RamDisk {
+        type Error = ();
+
+        fn read(&self, blocks: &mut [Block], start_block_idx: BlockIdx) -> Result<(), ()> {
+            let start = start_block_idx.0 as usize;
+            let src = self.0.get(start..start + blocks.len()).ok_or(())?;
+            blocks.copy_from_slice(src);
+            Ok(())
+        }
+
+        fn write(&self, _blocks: &[Block], _start_block_idx: BlockIdx) -> Result<(), ()> {
+            Err(())
+        }
+
+        fn num_blocks(&self) -> Result<BlockCount, ()> {
+            Ok(BlockCount(self.0.len() as u32))
+        }
+    }
+
+    fn mbr(part_type: u8) -> RamDisk {
+        let mut mbr = Block::new();
+        mbr[446 + 4] = part_type;
+        // the partition starts right after the mbr
+        mbr[446 + 8] = 1;
+        mbr[510] = 0x55;
+        mbr[511] = 0xAA;
+        RamDisk([mbr])
+    }
+
+    #[test]
+    fn test_get_volume_over_any_block_device() {
+        let mut ctrlr = Controller::new(RamDisk([Block::new()]), TestClock);
+        assert!(matches!(
+            ctrlr.get_volume(VolumeIdx(0)),
+            Err(Error::FormatError("Invalid MBR signature"))
+        ));
+
+        let mut ctrlr = Controller::new(mbr(0x83), TestClock);
+        assert!(matches!(
+            ctrlr.get_volume(VolumeIdx(0)),
+            Err(Error::FormatError("Partition type not supported"))
+        ));
+        assert!(matches!(
+            ctrlr.get_volume(VolumeIdx(4)),
+            Err(Error::NoSuchVolume)
+        ));
+
+        // a FAT partition's BPB is read off the device, which fails here as the disk ends at the mbr
+        let mut ctrlr = Controller::new(mbr(PARTITION_ID_FAT32_LBA), TestClock);
+        assert!(matches!(
+            ctrlr.get_volume(VolumeIdx(0)),
+            Err(Error::DeviceError(()))
+        ));
+        assert!(!ctrlr.has_open_handles());
+        let (disk, _) = ctrlr.free();
+        assert_eq!(disk.num_blocks().unwrap(), BlockCount(1));
+    }
+
+    #[test]
+    fn test_error_conversion() {
+        use crate::{HalError, HalErrorKind};
+
+        let timeout = HalError::new(HalErrorKind::Timeout, 2);
+        assert_eq!(
+            RustbootError::from(Error::DeviceError(timeout)),
+            RustbootError::Hal(timeout)
+        );
+        assert_eq!(
+            RustbootError::from(Error::<HalError>::TooManyOpenFiles),
+            RustbootError::BufferTooSmall
+        );
+        assert_eq!(
+            RustbootError::from(Error::<HalError>::FileNotFound),
+            RustbootError::InvalidImage
+        );
+    }
+}
+
 // ****************************************************************************
 //
 // End Of File
@@ -16,16 +16,48 @@ use log::{info, warn};
 /// Number of entries reserved at the start of a File Allocation Table
 pub const RESERVED_ENTRIES: u32 = 2;
 
-const MAX_FAT_SECTORS: u32 = 5000;
-pub(crate) const MAX_FAT_ENTRIES: u32 = (MAX_FAT_SECTORS * Block::LEN as u32) / 4;
-pub struct FatCache(pub [[u8; 4]; MAX_FAT_ENTRIES as usize]);
-impl FatCache {
-    /// Creates an initialized FAT_CACHE.
+/// The number of FAT sectors cached by the application-class boards (i.e. a 2.5MB cache), enough
+/// for the whole FAT of a ~20GB FAT32 volume with 32KB clusters.
+pub const MAX_FAT_SECTORS: usize = 5000;
+/// The number of FAT32 entries in a sector.
+const FAT32_ENTRIES_PER_BLOCK: usize = Block::LEN / 4;
+
+/// A cache of (the first `SECTORS` sectors of) a FAT32 volume's file allocation table, see
+/// [`Controller::populate_fat_cache`].
+///
+/// The cache is provided by the caller, so a board decides how much RAM it spends on it - the
+/// cache takes up [`FatCache::SIZE`] bytes, known at compile time. Clusters whose entries lie
+/// past the end of the cache are looked up on the block device instead.
+pub struct FatCache<const SECTORS: usize> {
+    blocks: [Block; SECTORS],
+    /// the number of sectors populated.
+    cached: usize,
+}
+
+impl<const SECTORS: usize> FatCache<SECTORS> {
+    /// The size of the cache, in bytes.
+    pub const SIZE: usize = SECTORS * Block::LEN;
+    const EMPTY: Block = Block {
+        contents: [0u8; Block::LEN],
+    };
+
+    /// Creates an empty cache.
     pub const fn new() -> Self {
-        Self([[0u8; 4]; MAX_FAT_ENTRIES as usize])
+        FatCache {
+            blocks: [Self::EMPTY; SECTORS],
+            cached: 0,
+        }
+    }
+
+    /// Returns `cluster`'s FAT32 entry, if it's cached.
+    fn entry(&self, cluster: Cluster) -> Option<u32> {
+        let idx = cluster.0 as usize;
+        let offset = (idx % FAT32_ENTRIES_PER_BLOCK) * 4;
+        self.blocks[..self.cached]
+            .get(idx / FAT32_ENTRIES_PER_BLOCK)
+            .map(|block| LittleEndian::read_u32(&block.contents[offset..offset + 4]))
     }
 }
-pub static mut FAT_CACHE: FatCache = FatCache::new();
 
 /// Indentifies the supported types of FAT format
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -490,7 +522,7 @@ impl FatVolume {
                 let mut blocks = [Block::new()];
                 controller
                     .block_device
-                    .read(&mut blocks, fat32_info.info_location, "read_info_sector")
+                    .read(&mut blocks, fat32_info.info_location)
                     .map_err(Error::DeviceError)?;
                 let block = &mut blocks[0];
                 if let Some(count) = self.free_clusters_count {
@@ -536,7 +568,7 @@ impl FatVolume {
                 let this_fat_ent_offset = (fat_offset % Block::LEN_U32) as usize;
                 controller
                     .block_device
-                    .read(&mut blocks, this_fat_block_num, "read_fat")
+                    .read(&mut blocks, this_fat_block_num)
                     .map_err(Error::DeviceError)?;
                 let entry = match new_value {
                     Cluster::INVALID => 0xFFF6,
@@ -557,7 +589,7 @@ impl FatVolume {
                 let this_fat_ent_offset = (fat_offset % Block::LEN_U32) as usize;
                 controller
                     .block_device
-                    .read(&mut blocks, this_fat_block_num, "read_fat")
+                    .read(&mut blocks, this_fat_block_num)
                     .map_err(Error::DeviceError)?;
                 let entry = match new_value {
                     Cluster::INVALID => 0x0FFF_FFF6,
@@ -584,18 +616,16 @@ impl FatVolume {
 
     /// Walking the FAT table for large files can be really slow.
     ///
-    /// This method allows us to cache the `file allocation table` contents.
+    /// This method allows us to cache the `file allocation table` contents, in a caller-provided
+    /// `cache`. If the FAT is larger than the cache, only its first `SECTORS` sectors are cached.
     ///
-    /// TODO:
-    /// - need to ensure that the cache is only ever populated once i.e. the cache is static
+    /// Note:
     /// - `rustBoot` has no need to update the `fat` as it does NOT support file-system writes to a `block-device`.
     /// This is a security design-goal.
-    ///
-    /// Note:
-    /// - the maximum `cache-size` is fixed at 5000 sectors/blocks
-    pub(crate) fn populate_static_fat_cache<D, T>(
+    pub(crate) fn populate_fat_cache<D, T, const SECTORS: usize>(
         &self,
         controller: &Controller<D, T>,
+        cache: &mut FatCache<SECTORS>,
     ) -> Result<(), Error<D::Error>>
     where
         D: BlockDevice,
@@ -605,7 +635,7 @@ impl FatVolume {
         let mut blocks = [Block::new()];
         controller
             .block_device
-            .read(&mut blocks, self.lba_start, "read_bpb")
+            .read(&mut blocks, self.lba_start)
             .map_err(Error::DeviceError)?;
         let block = &blocks[0];
         let bpb = Bpb::create_from_bytes(&block).map_err(Error::FormatError)?;
@@ -613,57 +643,51 @@ impl FatVolume {
         // retrieve the block idx where the `fat` starts
         let fat_start_blockidx = self.lba_start + self.fat_start;
         info!("fat_start_blockidx: {:?}", fat_start_blockidx);
-        let fat_size = bpb.fat_size();
+        let fat_size = bpb.fat_size() as usize;
         info!("fat_size: {:?}", fat_size);
-        assert!(fat_size <= MAX_FAT_SECTORS);
+        if fat_size > SECTORS {
+            warn!(
+                "fat is larger than the fat_cache, caching {} of {} sectors",
+                SECTORS, fat_size
+            );
+        }
 
         // populate fat cache
-        let fat_buffer = Block::from_fat_entries(unsafe { &mut FAT_CACHE.0 });
+        cache.cached = 0;
+        let cached = fat_size.min(SECTORS);
         controller
             .block_device
-            .read(fat_buffer, fat_start_blockidx, "fat_read")
+            .read(&mut cache.blocks[..cached], fat_start_blockidx)
             .map_err(Error::DeviceError)?;
-        let fat_entries: [[u8; 4]; MAX_FAT_ENTRIES as usize] = Block::to_fat_entries(fat_buffer)
-            .try_into()
-            .map_err(|_| Error::ConversionError)?;
-        info!("4 entries of fat_cache: {:?}", &fat_entries[..4]);
-        info!("number of fat_cache entries: {:?}", (fat_size * 512) / 4);
+        cache.cached = cached;
+        info!(
+            "number of fat_cache entries: {:?}",
+            cached * FAT32_ENTRIES_PER_BLOCK
+        );
 
         Ok(())
     }
 
-    /// Look in the FAT_CACHE to see which cluster comes next.
-    pub(crate) fn next_cluster_in_fat_cache(
+    /// Look in `cache` to see which cluster comes next, falling back to the FAT (on the block
+    /// device) for clusters that aren't cached.
+    pub(crate) fn next_cluster_in_fat_cache<D, T, const SECTORS: usize>(
         &self,
+        controller: &Controller<D, T>,
+        cache: &FatCache<SECTORS>,
         cluster: Cluster,
-    ) -> Result<Cluster, Error<&'static str>> {
+    ) -> Result<Cluster, Error<D::Error>>
+    where
+        D: BlockDevice,
+        T: TimeSource,
+    {
         match &self.fat_specific_info {
             FatSpecificInfo::Fat16(_fat16_info) => {
                 unimplemented!()
             }
-            FatSpecificInfo::Fat32(_fat32_info) => {
-                let fat_entry_idx = cluster.0 as usize;
-                let fat_entry =
-                    LittleEndian::read_u32(unsafe { &FAT_CACHE.0[fat_entry_idx] }) & 0x0FFF_FFFF;
-                match fat_entry {
-                    0x0000_0000 => {
-                        // Jumped to free space
-                        Err(Error::JumpedFree)
-                    }
-                    0x0FFF_FFF7 => {
-                        // Bad cluster
-                        Err(Error::BadCluster)
-                    }
-                    0x0000_0001 | 0x0FFF_FFF8..=0x0FFF_FFFF => {
-                        // There is no next cluster
-                        Err(Error::EndOfFile)
-                    }
-                    f => {
-                        // Seems legit
-                        Ok(Cluster(f))
-                    }
-                }
-            }
+            FatSpecificInfo::Fat32(_fat32_info) => match cache.entry(cluster) {
+                Some(fat_entry) => fat32_next_cluster(fat_entry),
+                None => self.next_cluster(controller, cluster),
+            },
         }
     }
 
@@ -685,7 +709,7 @@ impl FatVolume {
                 let this_fat_ent_offset = (fat_offset % Block::LEN_U32) as usize;
                 controller
                     .block_device
-                    .read(&mut blocks, this_fat_block_num, "next_cluster")
+                    .read(&mut blocks, this_fat_block_num)
                     .map_err(Error::DeviceError)?;
                 let fat_entry = LittleEndian::read_u16(
                     &blocks[0][this_fat_ent_offset..=this_fat_ent_offset + 1],
@@ -711,29 +735,11 @@ impl FatVolume {
                 let this_fat_ent_offset = (fat_offset % Block::LEN_U32) as usize;
                 controller
                     .block_device
-                    .read(&mut blocks, this_fat_block_num, "next_cluster")
+                    .read(&mut blocks, this_fat_block_num)
                     .map_err(Error::DeviceError)?;
-                let fat_entry = LittleEndian::read_u32(
+                fat32_next_cluster(LittleEndian::read_u32(
                     &blocks[0][this_fat_ent_offset..=this_fat_ent_offset + 3],
-                ) & 0x0FFF_FFFF;
-                match fat_entry {
-                    0x0000_0000 => {
-                        // Jumped to free space
-                        Err(Error::JumpedFree)
-                    }
-                    0x0FFF_FFF7 => {
-                        // Bad cluster
-                        Err(Error::BadCluster)
-                    }
-                    0x0000_0001 | 0x0FFF_FFF8..=0x0FFF_FFFF => {
-                        // There is no next cluster
-                        Err(Error::EndOfFile)
-                    }
-                    f => {
-                        // Seems legit
-                        Ok(Cluster(f))
-                    }
-                }
+                ))
             }
         }
     }
@@ -806,7 +812,7 @@ impl FatVolume {
                     for block in first_dir_block_num.range(dir_size) {
                         controller
                             .block_device
-                            .read(&mut blocks, block, "read_dir")
+                            .read(&mut blocks, block)
                             .map_err(Error::DeviceError)?;
                         for entry in 0..Block::LEN / OnDiskDirEntry::LEN {
                             let start = entry * OnDiskDirEntry::LEN;
@@ -865,7 +871,7 @@ impl FatVolume {
                     for block in first_dir_block_num.range(dir_size) {
                         controller
                             .block_device
-                            .read(&mut blocks, block, "read_dir")
+                            .read(&mut blocks, block)
                             .map_err(Error::DeviceError)?;
                         for entry in 0..Block::LEN / OnDiskDirEntry::LEN {
                             let start = entry * OnDiskDirEntry::LEN;
@@ -942,7 +948,7 @@ impl FatVolume {
                     for block in first_dir_block_num.range(dir_size) {
                         controller
                             .block_device
-                            .read(&mut blocks, block, "read_dir")
+                            .read(&mut blocks, block)
                             .map_err(Error::DeviceError)?;
                         for entry in 0..Block::LEN / OnDiskDirEntry::LEN {
                             let start = entry * OnDiskDirEntry::LEN;
@@ -984,7 +990,7 @@ impl FatVolume {
                     for block in block_idx.range(BlockCount(u32::from(self.blocks_per_cluster))) {
                         controller
                             .block_device
-                            .read(&mut blocks, block, "read_dir")
+                            .read(&mut blocks, block)
                             .map_err(Error::DeviceError)?;
                         for entry in 0..Block::LEN / OnDiskDirEntry::LEN {
                             let start = entry * OnDiskDirEntry::LEN;
@@ -1141,7 +1147,7 @@ impl FatVolume {
         let mut blocks = [Block::new()];
         controller
             .block_device
-            .read(&mut blocks, block, "read_dir")
+            .read(&mut blocks, block)
             .map_err(Error::DeviceError)?;
         for entry in 0..Block::LEN / OnDiskDirEntry::LEN {
             let start = entry * OnDiskDirEntry::LEN;
@@ -1195,7 +1201,7 @@ impl FatVolume {
         let mut blocks = [Block::new()];
         controller
             .block_device
-            .read(&mut blocks, block, "read_dir")
+            .read(&mut blocks, block)
             .map_err(Error::DeviceError)?;
         for entry in 0..Block::LEN / OnDiskDirEntry::LEN {
             let start = entry * OnDiskDirEntry::LEN;
@@ -1299,7 +1305,7 @@ impl FatVolume {
         let mut blocks = [Block::new()];
         controller
             .block_device
-            .read(&mut blocks, block, "read_dir")
+            .read(&mut blocks, block)
             .map_err(Error::DeviceError)?;
         for entry in 0..Block::LEN / OnDiskDirEntry::LEN {
             let start = entry * OnDiskDirEntry::LEN;
@@ -1351,7 +1357,7 @@ impl FatVolume {
                     // info!("Reading block {:?}", this_fat_block_num);
                     controller
                         .block_device
-                        .read(&mut blocks, this_fat_block_num, "next_cluster")
+                        .read(&mut blocks, this_fat_block_num)
                         .map_err(Error::DeviceError)?;
 
                     while this_fat_ent_offset <= Block::LEN - 2 {
@@ -1382,7 +1388,7 @@ impl FatVolume {
                     info!("Reading block {:?}", this_fat_block_num);
                     controller
                         .block_device
-                        .read(&mut blocks, this_fat_block_num, "next_cluster")
+                        .read(&mut blocks, this_fat_block_num)
                         .map_err(Error::DeviceError)?;
 
                     while this_fat_ent_offset <= Block::LEN - 4 {
@@ -1529,6 +1535,28 @@ impl FatVolume {
     }
 }
 
+/// Decodes a FAT32 entry i.e. the cluster that follows the entry's cluster.
+fn fat32_next_cluster<E: core::fmt::Debug>(fat_entry: u32) -> Result<Cluster, Error<E>> {
+    match fat_entry & 0x0FFF_FFFF {
+        0x0000_0000 => {
+            // Jumped to free space
+            Err(Error::JumpedFree)
+        }
+        0x0FFF_FFF7 => {
+            // Bad cluster
+            Err(Error::BadCluster)
+        }
+        0x0000_0001 | 0x0FFF_FFF8..=0x0FFF_FFFF => {
+            // There is no next cluster
+            Err(Error::EndOfFile)
+        }
+        f => {
+            // Seems legit
+            Ok(Cluster(f))
+        }
+    }
+}
+
 /// Load the boot parameter block from the start of the given partition and
 /// determine if the partition contains a valid FAT16 or FAT32 file system.
 pub fn parse_volume<D, T>(
@@ -1544,7 +1572,7 @@ where
     let mut blocks = [Block::new()];
     controller
         .block_device
-        .read(&mut blocks, lba_start, "read_bpb")
+        .read(&mut blocks, lba_start)
         .map_err(Error::DeviceError)?;
     let block = &blocks[0];
     let bpb = Bpb::create_from_bytes(&block).map_err(Error::FormatError)?;
@@ -1589,11 +1617,7 @@ where
             let mut info_blocks = [Block::new()];
             controller
                 .block_device
-                .read(
-                    &mut info_blocks,
-                    lba_start + info_location,
-                    "read_info_sector",
-                )
+                .read(&mut info_blocks, lba_start + info_location)
                 .map_err(Error::DeviceError)?;
             let info_block = &info_blocks[0];
             let info_sector =
@@ -1822,4 +1846,40 @@ mod tests {
                 return;
             })
     }
+
+    #[test]
+    fn test_fat_cache_entries() {
+        let mut cache = FatCache::<2>::new();
+        assert_eq!(FatCache::<2>::SIZE, 1024);
+        assert_eq!(cache.entry(Cluster(2)), None);
+
+        // cluster `n` is followed by `n + 1`, in both sectors
+        for (i, block) in cache.blocks.iter_mut().enumerate() {
+            for entry in 0..FAT32_ENTRIES_PER_BLOCK {
+                let cluster = (i * FAT32_ENTRIES_PER_BLOCK + entry) as u32;
+                LittleEndian::write_u32(&mut block.contents[entry * 4..], cluster + 1);
+            }
+        }
+        cache.cached = 1;
+        assert_eq!(cache.entry(Cluster(5)), Some(6));
+        assert_eq!(cache.entry(Cluster(127)), Some(128));
+        // only the first sector is populated
+        assert_eq!(cache.entry(Cluster(128)), None);
+        cache.cached = 2;
+        assert_eq!(cache.entry(Cluster(128)), Some(129));
+        assert_eq!(cache.entry(Cluster(256)), None);
+
+        assert!(matches!(
+            fat32_next_cluster::<()>(0xF000_0006),
+            Ok(Cluster(6))
+        ));
+        assert!(matches!(
+            fat32_next_cluster::<()>(0x0FFF_FFFF),
+            Err(Error::EndOfFile)
+        ));
+        assert!(matches!(
+            fat32_next_cluster::<()>(0),
+            Err(Error::JumpedFree)
+        ));
+    }
 }
@@ -574,8 +574,60 @@ impl Timestamp {
             },
         })
     }
+
+    /// Create a `Timestamp` from POSIX time i.e. seconds since 1970-Jan-01, 00:00:00. Years past
+    /// 2225 (the last one a `Timestamp` can hold) saturate.
+    pub fn from_unix_secs(secs: u64) -> Timestamp {
+        // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = secs / SECS_PER_DAY + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let month = if mp < 10 { mp + 2 } else { mp - 10 };
+        let year = yoe + era * 400 + (month < 2) as u64;
+        let secs_of_day = secs % SECS_PER_DAY;
+        match year - 1970 > u8::MAX as u64 {
+            true => Timestamp {
+                year_since_1970: u8::MAX,
+                zero_indexed_month: 11,
+                zero_indexed_day: 30,
+                hours: 23,
+                minutes: 59,
+                seconds: 59,
+            },
+            false => Timestamp {
+                year_since_1970: (year - 1970) as u8,
+                zero_indexed_month: month as u8,
+                zero_indexed_day: (doy - (153 * mp + 2) / 5) as u8,
+                hours: (secs_of_day / 3600) as u8,
+                minutes: (secs_of_day % 3600 / 60) as u8,
+                seconds: (secs_of_day % 60) as u8,
+            },
+        }
+    }
+
+    /// Convert a `Timestamp` to POSIX time i.e. seconds since 1970-Jan-01, 00:00:00.
+    pub fn to_unix_secs(&self) -> u64 {
+        // days-from-civil, see http://howardhinnant.github.io/date_algorithms.html
+        let month = u64::from(self.zero_indexed_month);
+        let year = 1970 + u64::from(self.year_since_1970) - (month < 2) as u64;
+        let era = year / 400;
+        let yoe = year - era * 400;
+        let mp = (month + 10) % 12;
+        let doy = (153 * mp + 2) / 5 + u64::from(self.zero_indexed_day);
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        days * SECS_PER_DAY
+            + u64::from(self.hours) * 3600
+            + u64::from(self.minutes) * 60
+            + u64::from(self.seconds)
+    }
 }
 
+const SECS_PER_DAY: u64 = 86_400;
+
 impl core::fmt::Debug for Timestamp {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(f, "Timestamp({})", self)
@@ -822,4 +874,31 @@ mod tests {
         assert_eq!(res1, false);
         assert_eq!(res2, true)
     }
+
+    #[test]
+    fn test_timestamp_unix_secs() {
+        let cases = [
+            (0, Timestamp::from_calendar(1970, 1, 1, 0, 0, 0)),
+            (315_532_800, Timestamp::from_calendar(1980, 1, 1, 0, 0, 0)),
+            (951_782_400, Timestamp::from_calendar(2000, 2, 29, 0, 0, 0)),
+            (
+                1_663_342_128,
+                Timestamp::from_calendar(2022, 9, 16, 15, 28, 48),
+            ),
+            (
+                1_735_689_599,
+                Timestamp::from_calendar(2024, 12, 31, 23, 59, 59),
+            ),
+        ];
+        for (secs, timestamp) in cases {
+            let timestamp = timestamp.unwrap();
+            assert_eq!(Timestamp::from_unix_secs(secs), timestamp);
+            assert_eq!(timestamp.to_unix_secs(), secs);
+        }
+        // saturates past 2225
+        assert_eq!(
+            Timestamp::from_unix_secs(u64::MAX),
+            Timestamp::from_calendar(2225, 12, 31, 23, 59, 59).unwrap()
+        );
+    }
 }
@@ -1,7 +1,19 @@
+//! A `no_std` FAT reader (and writer), generic over any [`BlockDevice`] - the rpi4's sd-card
+//! driver is just one of them. Application firmware can reuse it over SPI-SD or QSPI flash by
+//! implementing [`BlockDevice`] (and [`TimeSource`]) and handing both to a [`Controller`].
+
 #![allow(dead_code)]
 
 pub mod blockdevice;
+pub mod boot_source;
+pub mod bootcount;
+pub mod chunks;
 pub mod controller;
 mod fat;
 pub mod filesystem;
+pub mod state;
 mod structure;
+
+pub use blockdevice::{Block, BlockCount, BlockDevice, BlockIdx};
+pub use controller::{Controller, Error, TestClock, Volume, VolumeIdx, VolumeType};
+pub use filesystem::{DirEntry, Directory, File, Mode, TimeSource, Timestamp};
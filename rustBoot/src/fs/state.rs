@@ -0,0 +1,487 @@
+//! Power-fail safe storage for rustBoot's update state (i.e. the contents of `updt.txt`).
+//!
+//! Rewriting `updt.txt` in place isn't safe - a power cut half-way through the write leaves
+//! us with a truncated or garbled config. Instead, the update state is kept in two copies
+//! ([`STATE_FILES`]) and every write goes to the *inactive* copy, i.e. the one that wasn't
+//! used to boot. The active copy is never touched, so an interrupted write at worst
+//! loses the new state and we keep booting with the old one.
+//!
+//! Each copy is a regular `updt.txt` prefixed with a fixed-size header line
+//!
+//! ```text
+//! #rbstate seq=00000002 len=000000a5 crc=1c291ca3
+//! [active]
+//! image_name=...
+//! ```
+//!
+//! - `seq` is incremented (wrapping) on every write, the newest copy wins.
+//! - `len` is the length of the config that follows the header.
+//! - `crc` is a CRC-32 (IEEE, see [`crate::crc`]) over `seq`, `len` (little-endian) and the config.
+//!
+//! As the header starts with a `#`, it is just a comment to the `updt.txt` parser.
+
+use super::blockdevice::BlockDevice;
+use super::controller::{Controller, Error, Volume};
+use super::filesystem::{Directory, Mode, TimeSource};
+use crate::crc::Crc32;
+
+/// The two copies of the update state, in the FAT partition's root directory.
+pub const STATE_FILES: [&str; 2] = ["UPDT_A.TXT", "UPDT_B.TXT"];
+
+const MAGIC: &[u8] = b"#rbstate seq=";
+const LEN_TAG: &[u8] = b" len=";
+const CRC_TAG: &[u8] = b" crc=";
+
+/// Size of the header line (including the trailing newline) that precedes the config.
+pub const STATE_HEADER_SIZE: usize = MAGIC.len() + 8 + LEN_TAG.len() + 8 + CRC_TAG.len() + 8 + 1;
+
+/// One of the two copies of the update state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateSlot {
+    A,
+    B,
+}
+
+impl StateSlot {
+    /// The file backing this slot.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            StateSlot::A => STATE_FILES[0],
+            StateSlot::B => STATE_FILES[1],
+        }
+    }
+
+    /// The other slot i.e. the one that gets written next.
+    pub fn other(self) -> Self {
+        match self {
+            StateSlot::A => StateSlot::B,
+            StateSlot::B => StateSlot::A,
+        }
+    }
+}
+
+/// Ways in which a stored copy of the update state can be invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// The output buffer is too small to hold the header and config.
+    BufferTooSmall,
+    /// The header line is missing or malformed.
+    BadHeader,
+    /// The file is shorter or longer than the header says (e.g. a torn write).
+    BadLength,
+    /// The config doesn't match its checksum.
+    BadChecksum,
+    /// The config doesn't have the key that's to be set, see [`set_config_value`].
+    MissingKey,
+}
+
+/// A decoded (and checksum verified) copy of the update state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateRecord<'a> {
+    pub seq: u32,
+    /// the `updt.txt` config, without the header line.
+    pub config: &'a [u8],
+}
+
+/// The update state that rustBoot should act on, as returned by
+/// [`Controller::read_update_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateState<'a> {
+    /// the slot this state was read from.
+    pub slot: StateSlot,
+    pub record: StateRecord<'a>,
+}
+
+fn record_crc(seq: u32, config: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(&seq.to_le_bytes());
+    crc.update(&(config.len() as u32).to_le_bytes());
+    crc.update(config);
+    crc.finalize()
+}
+
+fn write_hex(out: &mut [u8], val: u32) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    for (idx, byte) in out.iter_mut().enumerate() {
+        *byte = DIGITS[((val >> (28 - 4 * idx)) & 0xf) as usize];
+    }
+}
+
+fn read_hex(input: &[u8]) -> Option<u32> {
+    input.iter().try_fold(0u32, |acc, byte| {
+        let digit = (*byte as char).to_digit(16)?;
+        Some(acc << 4 | digit)
+    })
+}
+
+/// Encodes `config` with sequence number `seq` into `out`. Returns the number of bytes
+/// written.
+pub fn encode_record(seq: u32, config: &[u8], out: &mut [u8]) -> Result<usize, StateError> {
+    let total = STATE_HEADER_SIZE + config.len();
+    if out.len() < total || config.len() > u32::MAX as usize {
+        return Err(StateError::BufferTooSmall);
+    }
+    let mut idx = 0;
+    for (tag, val) in [
+        (MAGIC, seq),
+        (LEN_TAG, config.len() as u32),
+        (CRC_TAG, record_crc(seq, config)),
+    ] {
+        out[idx..idx + tag.len()].copy_from_slice(tag);
+        idx += tag.len();
+        write_hex(&mut out[idx..idx + 8], val);
+        idx += 8;
+    }
+    out[idx] = b'\n';
+    out[STATE_HEADER_SIZE..total].copy_from_slice(config);
+    Ok(total)
+}
+
+/// Decodes and verifies a copy of the update state.
+pub fn decode_record(input: &[u8]) -> Result<StateRecord<'_>, StateError> {
+    if input.len() < STATE_HEADER_SIZE {
+        return Err(StateError::BadHeader);
+    }
+    let (header, config) = input.split_at(STATE_HEADER_SIZE);
+    let mut vals = [0u32; 3];
+    let mut rest = header;
+    for (tag, val) in [MAGIC, LEN_TAG, CRC_TAG].iter().zip(vals.iter_mut()) {
+        rest = rest.strip_prefix(*tag).ok_or(StateError::BadHeader)?;
+        *val = read_hex(&rest[..8]).ok_or(StateError::BadHeader)?;
+        rest = &rest[8..];
+    }
+    if rest != b"\n" {
+        return Err(StateError::BadHeader);
+    }
+    let [seq, len, crc] = vals;
+    if config.len() != len as usize {
+        return Err(StateError::BadLength);
+    }
+    if record_crc(seq, config) != crc {
+        return Err(StateError::BadChecksum);
+    }
+    Ok(StateRecord { seq, config })
+}
+
+/// Returns true if sequence number `a` is newer than `b`, allowing for wrap-around.
+pub fn is_newer(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+/// Picks the newest of two (decoded) copies of the update state.
+pub fn select_state<'a>(
+    a: Option<StateRecord<'a>>,
+    b: Option<StateRecord<'a>>,
+) -> Option<UpdateState<'a>> {
+    match (a, b) {
+        (Some(a), Some(b)) if is_newer(b.seq, a.seq) => Some(UpdateState {
+            slot: StateSlot::B,
+            record: b,
+        }),
+        (Some(a), _) => Some(UpdateState {
+            slot: StateSlot::A,
+            record: a,
+        }),
+        (None, Some(b)) => Some(UpdateState {
+            slot: StateSlot::B,
+            record: b,
+        }),
+        (None, None) => None,
+    }
+}
+
+/// Encodes `config` as the state that follows `current` into `out` i.e. for the *other* slot,
+/// with the next sequence number. Returns the slot it's for and the record's length.
+pub fn next_record(
+    current: Option<&UpdateState<'_>>,
+    config: &[u8],
+    out: &mut [u8],
+) -> Result<(StateSlot, usize), StateError> {
+    let (slot, seq) = match current {
+        Some(state) => (state.slot.other(), state.record.seq.wrapping_add(1)),
+        None => (StateSlot::A, 1),
+    };
+    Ok((slot, encode_record(seq, config, out)?))
+}
+
+/// Copies an `updt.txt` config into `out`, with the first `key` in `section` (ex: `[passive]`)
+/// set to `value` i.e. its line is replaced by `key=value`. Returns the new config's length.
+pub fn set_config_value(
+    config: &str,
+    section: &str,
+    key: &str,
+    value: &str,
+    out: &mut [u8],
+) -> Result<usize, StateError> {
+    let mut len = 0;
+    let mut in_section = false;
+    let mut found = false;
+    for line in config.split_inclusive('\n') {
+        let content = line.trim();
+        if content.starts_with('[') {
+            in_section = content == section;
+        }
+        let is_key = in_section
+            && !found
+            && content
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='));
+        let parts = match is_key {
+            true => {
+                found = true;
+                let newline = if line.ends_with('\n') { "\n" } else { "" };
+                [key, "=", value, newline]
+            }
+            false => [line, "", "", ""],
+        };
+        for part in parts.iter() {
+            out.get_mut(len..len + part.len())
+                .ok_or(StateError::BufferTooSmall)?
+                .copy_from_slice(part.as_bytes());
+            len += part.len();
+        }
+    }
+    match found {
+        true => Ok(len),
+        false => Err(StateError::MissingKey),
+    }
+}
+
+impl<D, T> Controller<D, T>
+where
+    D: BlockDevice,
+    T: TimeSource,
+    <D as BlockDevice>::Error: core::fmt::Debug,
+{
+    /// Reads both copies of the update state from `dir` and returns the newest valid one.
+    ///
+    /// `buf` is split in half, one half per copy - so it must be at least twice the size of
+    /// the largest state file. Missing, torn or corrupted copies are ignored. Returns `None`
+    /// if neither copy is valid.
+    pub fn read_update_state<'b>(
+        &mut self,
+        volume: &mut Volume,
+        dir: &Directory,
+        buf: &'b mut [u8],
+    ) -> Result<Option<UpdateState<'b>>, Error<D::Error>> {
+        let half = buf.len() / 2;
+        let (buf_a, buf_b) = buf.split_at_mut(half);
+        let len_a = self.read_state_file(volume, dir, StateSlot::A, buf_a)?;
+        let len_b = self.read_state_file(volume, dir, StateSlot::B, buf_b)?;
+
+        let (buf_a, buf_b): (&'b [u8], &'b [u8]) = (buf_a, buf_b);
+        let a = len_a.and_then(|len| decode_record(&buf_a[..len]).ok());
+        let b = len_b.and_then(|len| decode_record(&buf_b[..len]).ok());
+        Ok(select_state(a, b))
+    }
+
+    /// Writes `config` as the new update state.
+    ///
+    /// `current` is the state we booted with (as returned by [`Self::read_update_state`]).
+    /// The new state is written to the *other* slot, with the next sequence number, so the
+    /// current state stays intact should the write be interrupted. `scratch` must be able to
+    /// hold the config plus [`STATE_HEADER_SIZE`] bytes.
+    ///
+    /// Returns the slot that was written.
+    pub fn write_update_state(
+        &mut self,
+        volume: &mut Volume,
+        dir: &Directory,
+        current: Option<&UpdateState<'_>>,
+        config: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<StateSlot, Error<D::Error>> {
+        let (slot, len) = next_record(current, config, scratch)
+            .map_err(|_| Error::FormatError("update state does not fit in scratch buffer"))?;
+
+        let mut file = self.open_file_in_dir(
+            volume,
+            dir,
+            slot.file_name(),
+            Mode::ReadWriteCreateOrTruncate,
+        )?;
+        let written = self.write(volume, &mut file, &scratch[..len]);
+        self.close_file(volume, file)?;
+        if written? != len {
+            return Err(Error::NotEnoughSpace);
+        }
+        Ok(slot)
+    }
+
+    /// Reads a state file into `buf`, returning `None` if it doesn't exist or doesn't fit.
+    fn read_state_file(
+        &mut self,
+        volume: &mut Volume,
+        dir: &Directory,
+        slot: StateSlot,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, Error<D::Error>> {
+        let mut file = match self.open_file_in_dir(volume, dir, slot.file_name(), Mode::ReadOnly) {
+            Ok(file) => file,
+            Err(Error::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if file.length() as usize > buf.len() {
+            self.close_file(volume, file)?;
+            return Ok(None);
+        }
+        let mut num_read = 0;
+        let res = loop {
+            if file.eof() {
+                break Ok(Some(num_read));
+            }
+            match self.read(volume, &mut file, &mut buf[num_read..]) {
+                Ok(0) => break Ok(Some(num_read)),
+                Ok(n) => num_read += n,
+                Err(e) => break Err(e),
+            }
+        };
+        self.close_file(volume, file)?;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &[u8] = b"[active]\nimage_name=apertis\nimage_version=1\n";
+
+    #[test]
+    fn test_record_roundtrip() {
+        let mut buf = [0u8; 128];
+        let len = encode_record(7, CONFIG, &mut buf).unwrap();
+        assert_eq!(len, STATE_HEADER_SIZE + CONFIG.len());
+        assert!(buf.starts_with(b"#rbstate seq=00000007 len=0000002c crc="));
+        assert_eq!(buf[STATE_HEADER_SIZE - 1], b'\n');
+
+        let record = decode_record(&buf[..len]).unwrap();
+        assert_eq!(record.seq, 7);
+        assert_eq!(record.config, CONFIG);
+
+        let mut small = [0u8; 16];
+        assert_eq!(
+            encode_record(7, CONFIG, &mut small),
+            Err(StateError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_record_corruption() {
+        let mut buf = [0u8; 128];
+        let len = encode_record(1, CONFIG, &mut buf).unwrap();
+
+        // torn write
+        assert_eq!(decode_record(&buf[..len - 1]), Err(StateError::BadLength));
+        assert_eq!(decode_record(&buf[..10]), Err(StateError::BadHeader));
+        // flipped bit in the config
+        let mut corrupt = buf;
+        corrupt[len - 2] ^= 0x01;
+        assert_eq!(decode_record(&corrupt[..len]), Err(StateError::BadChecksum));
+        // flipped sequence number
+        let mut corrupt = buf;
+        corrupt[MAGIC.len() + 7] = b'2';
+        assert_eq!(decode_record(&corrupt[..len]), Err(StateError::BadChecksum));
+        // legacy `updt.txt` without a header
+        assert_eq!(decode_record(CONFIG), Err(StateError::BadHeader));
+    }
+
+    #[test]
+    fn test_torn_write() {
+        let (mut buf_a, mut buf_b) = ([0u8; 128], [0u8; 128]);
+        let len = encode_record(3, CONFIG, &mut buf_a).unwrap();
+        let current = select_state(decode_record(&buf_a[..len]).ok(), None).unwrap();
+
+        // the next state goes to the inactive copy, with the next sequence number
+        let next = b"[active]\nimage_name=apertis\nimage_version=2\n";
+        let (slot, next_len) = next_record(Some(&current), next, &mut buf_b).unwrap();
+        assert_eq!(slot, StateSlot::B);
+        let written = select_state(
+            decode_record(&buf_a[..len]).ok(),
+            decode_record(&buf_b[..next_len]).ok(),
+        )
+        .unwrap();
+        assert_eq!((written.slot, written.record.seq), (StateSlot::B, 4));
+        assert_eq!(written.record.config, &next[..]);
+
+        // a write that's torn anywhere keeps the previous state
+        for torn in 0..next_len {
+            let state = select_state(
+                decode_record(&buf_a[..len]).ok(),
+                decode_record(&buf_b[..torn]).ok(),
+            );
+            assert_eq!(state, Some(current));
+        }
+        let mut garbled = buf_b;
+        garbled[next_len - 2] ^= 0x01;
+        let state = select_state(
+            decode_record(&buf_a[..len]).ok(),
+            decode_record(&garbled[..next_len]).ok(),
+        );
+        assert_eq!(state, Some(current));
+        // as is the first state, which goes to slot A
+        assert_eq!(
+            next_record(None, CONFIG, &mut buf_b),
+            Ok((StateSlot::A, len))
+        );
+    }
+
+    #[test]
+    fn test_set_config_value() {
+        let config = "[active]\nready_for_update_flag=true\n[passive]\nready_for_update_flag = true\nimage_name=x.itb";
+        let mut out = [0u8; 128];
+        let len = set_config_value(
+            config,
+            "[passive]",
+            "ready_for_update_flag",
+            "false",
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            &out[..len],
+            &b"[active]\nready_for_update_flag=true\n[passive]\nready_for_update_flag=false\nimage_name=x.itb"[..]
+        );
+        assert_eq!(
+            set_config_value(config, "[passive]", "update_status", "testing", &mut out),
+            Err(StateError::MissingKey)
+        );
+        assert_eq!(
+            set_config_value(config, "[passive]", "image_name", "y.itb", &mut out[..16]),
+            Err(StateError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_select_state() {
+        let record = |seq| StateRecord {
+            seq,
+            config: CONFIG,
+        };
+        assert_eq!(select_state(None, None), None);
+        assert_eq!(
+            select_state(Some(record(3)), None).unwrap().slot,
+            StateSlot::A
+        );
+        assert_eq!(
+            select_state(None, Some(record(3))).unwrap().slot,
+            StateSlot::B
+        );
+        assert_eq!(
+            select_state(Some(record(3)), Some(record(4))).unwrap().slot,
+            StateSlot::B
+        );
+        assert_eq!(
+            select_state(Some(record(5)), Some(record(4))).unwrap().slot,
+            StateSlot::A
+        );
+        // sequence numbers wrap around
+        assert_eq!(
+            select_state(Some(record(u32::MAX)), Some(record(0)))
+                .unwrap()
+                .slot,
+            StateSlot::B
+        );
+        assert_eq!(StateSlot::A.other(), StateSlot::B);
+    }
+}
@@ -0,0 +1,242 @@
+//! The chainload handoff's format.
+//!
+//! A board that chainloads another bootloader (ex: U-Boot or a newer rustBoot, carried by a
+//! verified fit-image as its config's loadable, see [`crate::dt::get_config_loadable`]) passes it
+//! a handoff, which describes the components rustBoot already loaded into RAM i.e. the verified
+//! fit-image, the dtb and the loadable itself. The next stage can re-use them (after checking
+//! their digests, see [`Component::matches`]) rather than reading them again.
+//!
+//! ```text
+//!  0       4         8       12         16
+//!  | magic | version | count | reserved |
+//!  | components, MAX_COMPONENTS * 56 bytes (unused ones are zeroed)
+//!  | crc32
+//! ```
+//!
+//! All fields are little-endian. A component is
+//!
+//! ```text
+//!  0      4          8              16          24               56
+//!  | kind | reserved | address (u64) | size (u64) | sha256 digest |
+//! ```
+//!
+//! and the `crc32` (IEEE, see [`crate::crc`]) covers everything before it. The crc
+//! only catches a handoff that's corrupt (or was never written), the digests are what tie a
+//! component to the bytes that were verified.
+
+use core::convert::TryInto;
+
+use sha2::{Digest, Sha256};
+
+use crate::crc::crc32;
+use crate::{Result, RustbootError};
+
+/// The most components a handoff describes.
+pub const MAX_COMPONENTS: usize = 4;
+/// The length of a handoff, in bytes.
+pub const HANDOFF_LEN: usize = HEADER_LEN + MAX_COMPONENTS * COMPONENT_LEN + 4;
+
+const MAGIC: u32 = 0x4F48_4252; // "RBHO"
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 16;
+const COMPONENT_LEN: usize = 56;
+
+/// What a handed-off component is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Kind {
+    /// The (verified) fit-image, as it was read.
+    FitImage = 1,
+    /// The dtb passed to the next stage.
+    Fdt = 2,
+    /// The next stage itself i.e. the fit-image's loadable, as it was loaded.
+    Loadable = 3,
+    /// A relocated kernel or ramdisk, for a next stage that's handed one.
+    Kernel = 4,
+    Ramdisk = 5,
+}
+
+impl Kind {
+    fn from_u32(val: u32) -> Option<Self> {
+        match val {
+            1 => Some(Kind::FitImage),
+            2 => Some(Kind::Fdt),
+            3 => Some(Kind::Loadable),
+            4 => Some(Kind::Kernel),
+            5 => Some(Kind::Ramdisk),
+            _ => None,
+        }
+    }
+}
+
+/// A component that's in RAM, at `address`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Component {
+    pub kind: Kind,
+    pub address: u64,
+    pub size: u64,
+    /// the sha256 digest of the component's `size` bytes.
+    pub digest: [u8; 32],
+}
+
+impl Component {
+    /// Describes `bytes` (i.e. a component that's been loaded) as a component of `kind`.
+    pub fn new(kind: Kind, bytes: &[u8]) -> Self {
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(Sha256::digest(bytes).as_slice());
+        Component {
+            kind,
+            address: bytes.as_ptr() as u64,
+            size: bytes.len() as u64,
+            digest,
+        }
+    }
+
+    /// Returns true if `bytes` (i.e. the component, as found at its address) match its digest.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() as u64 == self.size && Sha256::digest(bytes).as_slice() == self.digest
+    }
+}
+
+/// The components a bootloader hands off to the next stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Handoff {
+    components: [Option<Component>; MAX_COMPONENTS],
+}
+
+impl Handoff {
+    pub fn new() -> Self {
+        Handoff::default()
+    }
+
+    /// Adds a component. Returns `BufferTooSmall` if the handoff already describes
+    /// [`MAX_COMPONENTS`].
+    pub fn push(&mut self, component: Component) -> Result<()> {
+        let slot = self
+            .components
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(RustbootError::BufferTooSmall)?;
+        *slot = Some(component);
+        Ok(())
+    }
+
+    /// Returns the components, in the order they were added.
+    pub fn components(&self) -> impl Iterator<Item = &Component> {
+        self.components.iter().flatten()
+    }
+
+    /// Returns the (first) component of `kind`, if there's one.
+    pub fn get(&self, kind: Kind) -> Option<&Component> {
+        self.components().find(|component| component.kind == kind)
+    }
+
+    pub fn to_bytes(&self) -> [u8; HANDOFF_LEN] {
+        let mut bytes = [0u8; HANDOFF_LEN];
+        let count = self.components().count() as u32;
+        for (field, val) in bytes.chunks_exact_mut(4).zip([MAGIC, VERSION, count]) {
+            field.copy_from_slice(&val.to_le_bytes());
+        }
+        let entries = bytes[HEADER_LEN..].chunks_exact_mut(COMPONENT_LEN);
+        for (entry, component) in entries.zip(self.components()) {
+            entry[..4].copy_from_slice(&(component.kind as u32).to_le_bytes());
+            entry[8..16].copy_from_slice(&component.address.to_le_bytes());
+            entry[16..24].copy_from_slice(&component.size.to_le_bytes());
+            entry[24..].copy_from_slice(&component.digest);
+        }
+        let crc = crc32(&bytes[..HANDOFF_LEN - 4]);
+        bytes[HANDOFF_LEN - 4..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a handoff. Returns `None` if `bytes` don't hold one (ex: a previous stage that
+    /// didn't pass one, or a corrupt one) or if it's from an unknown version.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..HANDOFF_LEN)?;
+        let word = |offset: usize| {
+            let field = bytes.get(offset..offset + 4)?;
+            Some(u32::from_le_bytes(field.try_into().ok()?))
+        };
+        let crc = word(HANDOFF_LEN - 4)?;
+        if word(0)? != MAGIC || word(4)? != VERSION || crc32(&bytes[..HANDOFF_LEN - 4]) != crc {
+            return None;
+        }
+        let count = word(8)? as usize;
+        if count > MAX_COMPONENTS {
+            return None;
+        }
+
+        let mut handoff = Handoff::new();
+        let entries = bytes[HEADER_LEN..].chunks_exact(COMPONENT_LEN);
+        for (slot, entry) in handoff.components.iter_mut().zip(entries).take(count) {
+            *slot = Some(Component {
+                kind: Kind::from_u32(u32::from_le_bytes(entry.get(..4)?.try_into().ok()?))?,
+                address: u64::from_le_bytes(entry.get(8..16)?.try_into().ok()?),
+                size: u64::from_le_bytes(entry.get(16..24)?.try_into().ok()?),
+                digest: entry.get(24..)?.try_into().ok()?,
+            });
+        }
+        Some(handoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handoff_bytes() {
+        let fit = [0xAAu8; 64];
+        let fdt = [0x55u8; 16];
+        let mut handoff = Handoff::new();
+        handoff.push(Component::new(Kind::FitImage, &fit)).unwrap();
+        handoff.push(Component::new(Kind::Fdt, &fdt)).unwrap();
+
+        let bytes = handoff.to_bytes();
+        assert_eq!(
+            &bytes[..12],
+            &[0x52, 0x42, 0x48, 0x4F, 1, 0, 0, 0, 2, 0, 0, 0]
+        );
+        let decoded = Handoff::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, handoff);
+        assert_eq!(decoded.components().count(), 2);
+
+        let component = decoded.get(Kind::Fdt).unwrap();
+        assert_eq!(component.address, fdt.as_ptr() as u64);
+        assert!(component.matches(&fdt));
+        assert!(!component.matches(&fdt[1..]));
+        assert!(!decoded.get(Kind::FitImage).unwrap().matches(&[0xABu8; 64]));
+        assert_eq!(decoded.get(Kind::Loadable), None);
+    }
+
+    #[test]
+    fn corrupt_handoffs() {
+        let mut handoff = Handoff::new();
+        handoff
+            .push(Component::new(Kind::Loadable, &[1, 2, 3]))
+            .unwrap();
+        let bytes = handoff.to_bytes();
+
+        // cleared memory, a truncated handoff and any flipped bit
+        assert_eq!(Handoff::from_bytes(&[0; HANDOFF_LEN]), None);
+        assert_eq!(Handoff::from_bytes(&bytes[..HANDOFF_LEN - 1]), None);
+        for bit in 0..HANDOFF_LEN * 8 {
+            let mut corrupt = bytes;
+            corrupt[bit / 8] ^= 1 << (bit % 8);
+            assert_eq!(Handoff::from_bytes(&corrupt), None);
+        }
+    }
+
+    #[test]
+    fn full_handoff() {
+        let mut handoff = Handoff::new();
+        for _ in 0..MAX_COMPONENTS {
+            handoff.push(Component::new(Kind::Kernel, &[0])).unwrap();
+        }
+        assert_eq!(
+            handoff.push(Component::new(Kind::Ramdisk, &[0])),
+            Err(RustbootError::BufferTooSmall)
+        );
+        assert_eq!(Handoff::from_bytes(&handoff.to_bytes()), Some(handoff));
+    }
+}
@@ -0,0 +1,221 @@
+//! Companion images i.e. additional payloads (such as radio or coprocessor firmware) that
+//! are delivered as part of an application update.
+//!
+//! Each companion image is a complete rustBoot image - it has its own header (version, digest,
+//! signature etc.). The image-type's low byte identifies the companion (any value other than
+//! `HDR_IMG_TYPE_APP`, `0x00` or `0xFF`), its destination is up to the board's HAL.
+//!
+//! Companion images are staged in the update partition, right after the application image.
+//! Each one starts on a sector boundary and the list ends at the first sector without a
+//! rustBoot header (or at the partition's last sector, if it holds the trailer).
+
+use core::iter::FusedIterator;
+
+use crate::chain::{has_magic, SignedImage};
+use crate::constants::*;
+use crate::Result;
+use crate::RustbootError;
+
+/// A companion image, staged alongside an application update.
+#[derive(Debug, Clone, Copy)]
+pub struct CompanionImage<'a> {
+    id: u8,
+    image: SignedImage<'a>,
+}
+
+impl<'a> CompanionImage<'a> {
+    /// Parses the companion image at the start of `bytes`. Only the header is checked, see
+    /// [`Self::verify`].
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let image = SignedImage::parse(bytes)?;
+        let id = (image.get_image_type()? & HDR_MASK_LOWBYTE) as u8;
+        if id as u16 == HDR_IMG_TYPE_APP || id == 0x00 || id == 0xFF {
+            return Err(RustbootError::InvalidImage);
+        }
+        Ok(CompanionImage { id, image })
+    }
+
+    /// The companion's id i.e. the low byte of its image-type.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// The companion's firmware (without its header).
+    pub fn firmware(&self) -> &'a [u8] {
+        self.image.firmware()
+    }
+
+    pub fn get_firmware_version(&self) -> Result<u32> {
+        self.image.get_firmware_version()
+    }
+
+    /// Number of bytes the image occupies in the update partition, i.e. rounded up to the next sector.
+    pub fn staged_size(&self) -> usize {
+        staged_size(self.firmware().len())
+    }
+
+    /// Verifies the image's integrity and authenticity, the same way as an application image's.
+    pub fn verify(&self) -> Result<()> {
+        self.image.verify()
+    }
+}
+
+/// Iterator over the companion images staged in an update partition.
+#[derive(Debug, Clone)]
+pub struct CompanionImages<'a> {
+    staged: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> CompanionImages<'a> {
+    /// Iterates over companion images in `staged`, i.e. the part of the update partition that
+    /// follows the application image.
+    pub fn new(staged: &'a [u8]) -> Self {
+        CompanionImages { staged, offset: 0 }
+    }
+
+    /// Iterates over the companion images staged in the update partition, after an application
+    /// image that ends `app_end` bytes into it, see
+    /// [`PartDescriptor::image_end`](super::image::PartDescriptor::image_end).
+    pub fn in_update_partition(app_end: usize) -> CompanionImages<'static> {
+        let start = app_end.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+        let end = PARTITION_SIZE - TRAILER_SECTORS * SECTOR_SIZE;
+        let staged = match start < end {
+            true => unsafe {
+                core::slice::from_raw_parts(
+                    (UPDATE_PARTITION_ADDRESS + start) as *const u8,
+                    end - start,
+                )
+            },
+            false => &[],
+        };
+        CompanionImages::new(staged)
+    }
+}
+
+impl<'a> Iterator for CompanionImages<'a> {
+    type Item = Result<CompanionImage<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.staged.get(self.offset..)?;
+        if remaining.len() < IMAGE_HEADER_SIZE || !has_magic(remaining) {
+            self.offset = self.staged.len();
+            return None;
+        }
+        match CompanionImage::parse(remaining) {
+            Ok(img) => {
+                self.offset += img.staged_size();
+                Some(Ok(img))
+            }
+            Err(e) => {
+                // a malformed image ends the list
+                self.offset = self.staged.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a> FusedIterator for CompanionImages<'a> {}
+
+fn staged_size(fw_size: usize) -> usize {
+    (IMAGE_HEADER_SIZE + fw_size).div_ceil(SECTOR_SIZE) * SECTOR_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::digest::Digest;
+    use sha2::Sha256;
+    use std::vec::Vec;
+
+    #[rustfmt::skip]
+    const TLVS: &[u8] = &[
+        0x01, 0x00, 0x04, 0x00, // version type & len
+        0x07, 0x00, 0x00, 0x00, // version value
+        0xff, 0xff, 0xff, 0xff, // padding bytes
+        0x02, 0x00, 0x08, 0x00, // timestamp type & len
+        0x11, 0x11, 0x11, 0x11, // timestamp value
+        0x22, 0x22, 0x22, 0x22,
+        0x04, 0x00, 0x02, 0x00, // img type and len
+        0x05, 0x02,             // img value i.e. companion `5`, nistp256
+        0xff, 0xff, 0xff, 0xff, // padding bytes
+        0xff, 0xff,
+        0x03, 0x00, 0x20, 0x00, // digest type and len
+    ];
+
+    fn companion(fw: &[u8]) -> Vec<u8> {
+        let mut img = Vec::new();
+        img.extend_from_slice(&(RUSTBOOT_MAGIC as u32).to_le_bytes());
+        img.extend_from_slice(&(fw.len() as u32).to_le_bytes());
+        img.extend_from_slice(TLVS);
+        let digest = Sha256::new()
+            .chain(&img[..img.len() - 4])
+            .chain(fw)
+            .finalize();
+        img.extend_from_slice(digest.as_slice());
+        img.extend_from_slice(&[0x10, 0x00, 0x20, 0x00]);
+        img.extend_from_slice(&[0x55; 32]);
+        img.extend_from_slice(&[0x20, 0x00, 0x40, 0x00]);
+        img.extend_from_slice(&[0x44; 64]);
+        img.extend_from_slice(&[0x00, 0x00]);
+        img.resize(IMAGE_HEADER_SIZE, 0xff);
+        img.extend_from_slice(fw);
+        img.resize(staged_size(fw.len()), 0xff);
+        img
+    }
+
+    #[test]
+    fn parse_staged_companions() {
+        let mut staged = companion(&[0xaa; 10]);
+        staged.extend_from_slice(&companion(&[0xbb; SECTOR_SIZE]));
+        staged.resize(staged.len() + SECTOR_SIZE, 0xff);
+
+        let images = CompanionImages::new(&staged)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].id(), 5);
+        assert_eq!(images[0].firmware(), &[0xaa; 10]);
+        assert_eq!(images[0].get_firmware_version().unwrap(), 7);
+        assert_eq!(images[1].firmware(), &[0xbb; SECTOR_SIZE][..]);
+        assert_eq!(images[1].staged_size(), 2 * SECTOR_SIZE);
+    }
+
+    #[test]
+    fn malformed_companions() {
+        let mut staged = companion(&[0xaa; 10]);
+        // firmware size runs past the staged region
+        staged[4..8].copy_from_slice(&(SECTOR_SIZE as u32).to_le_bytes());
+        let mut images = CompanionImages::new(&staged);
+        assert_eq!(
+            images.next().unwrap().unwrap_err(),
+            RustbootError::InvalidFirmwareSize
+        );
+        assert!(images.next().is_none());
+
+        // the application's image-type isn't a companion
+        let mut staged = companion(&[0xaa; 10]);
+        staged[8 + 28] = HDR_IMG_TYPE_APP as u8;
+        assert_eq!(
+            CompanionImage::parse(&staged).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+    }
+
+    #[test]
+    fn verify_companion() {
+        let mut staged = companion(&[0xaa; 10]);
+        let img = CompanionImage::parse(&staged).unwrap();
+        // the integrity check passes, the made-up signature doesn't
+        let err = img.verify().unwrap_err();
+        assert_ne!(err, RustbootError::IntegrityCheckFailed);
+
+        staged[IMAGE_HEADER_SIZE] = 0x00;
+        let img = CompanionImage::parse(&staged).unwrap();
+        assert_eq!(
+            img.verify().unwrap_err(),
+            RustbootError::IntegrityCheckFailed
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! ESP app images i.e. the firmware format booted on Espressif parts (ex: the esp32s3).
+//!
+//! Unlike a Cortex-M image, an ESP app image isn't executed in place from its first byte. It's a
+//! list of segments, each loaded to RAM (i.e. IRAM/DRAM) or mapped into the flash cache's
+//! instruction/data buses (i.e. IROM/DROM) by the bootloader, which then jumps to its entry
+//! point.
+//!
+//! ```text
+//! | magic (0xE9) | segment count | spi mode | spi speed/size | entry (u32) | extended header (16 bytes) |
+//! | load address (u32) | length (u32) | data ... | (repeated for every segment)
+//! ```
+//!
+//! Mapped segments are mapped a 64K MMU page at a time, so a segment's offset in flash must be
+//! congruent to its (virtual) load address, modulo the page size. Images are built (i.e. by
+//! `espflash`) for a page-aligned flash offset, which is why rustBoot's example firmware is padded
+//! to the next page boundary after the rustBoot header, see [`app_image_start`].
+
+use core::ops::Range;
+
+use crate::{Result, RustbootError};
+
+/// The first byte of an app image.
+pub const ESP_IMAGE_MAGIC: u8 = 0xE9;
+/// The size of an app image's header, including its extended header.
+pub const ESP_IMAGE_HEADER_SIZE: usize = 24;
+/// The size of a segment's header i.e. its load address and length.
+pub const ESP_SEGMENT_HEADER_SIZE: usize = 8;
+/// The most segments an image may have (i.e. the ESP-IDF bootloader's limit).
+pub const ESP_IMAGE_MAX_SEGMENTS: usize = 16;
+/// The flash cache's MMU page size.
+pub const MMU_PAGE_SIZE: usize = 0x10000;
+
+/// Returns the address of the app image that follows the rustBoot header at `fw_base` i.e. the
+/// next MMU page boundary. The bytes in between are padding.
+pub const fn app_image_start(fw_base: usize) -> usize {
+    (fw_base + MMU_PAGE_SIZE - 1) & !(MMU_PAGE_SIZE - 1)
+}
+
+/// A chip's memory map, as far as loading an app image goes.
+#[derive(Debug, Clone)]
+pub struct MemoryMap<'a> {
+    /// the chip id in the image's extended header (ex: `9` for the esp32s3).
+    pub chip_id: u16,
+    /// the flash cache's (virtual) address ranges i.e. the IROM and DROM buses. Segments that lie
+    /// in them are mapped rather than loaded.
+    pub mapped: &'a [Range<usize>],
+    /// the RAM segments may be loaded to i.e. IRAM and DRAM, minus the bootloader's own RAM.
+    pub ram: &'a [Range<usize>],
+}
+
+/// A segment of an app image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    /// the (virtual) address the segment is loaded or mapped to.
+    pub load_addr: usize,
+    /// the offset of the segment's data, from the start of the image.
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Segment {
+    fn range(&self) -> Range<usize> {
+        self.load_addr..self.load_addr.saturating_add(self.len)
+    }
+}
+
+/// A parsed app image, see [`EspImage::parse`].
+#[derive(Debug, Clone, Copy)]
+pub struct EspImage<'a> {
+    bytes: &'a [u8],
+    entry: usize,
+    chip_id: u16,
+    segments: usize,
+}
+
+impl<'a> EspImage<'a> {
+    /// Parses the app image at the start of `bytes`, checking that every segment lies within
+    /// `bytes`. Returns `InvalidImage` otherwise.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let header = bytes
+            .get(..ESP_IMAGE_HEADER_SIZE)
+            .ok_or(RustbootError::InvalidImage)?;
+        let (magic, segments, entry, chip_id) = match *header {
+            [magic, segments, _, _, e0, e1, e2, e3, _, _, _, _, c0, c1, ..] => (
+                magic,
+                segments as usize,
+                u32::from_le_bytes([e0, e1, e2, e3]) as usize,
+                u16::from_le_bytes([c0, c1]),
+            ),
+            _ => return Err(RustbootError::InvalidImage),
+        };
+        if magic != ESP_IMAGE_MAGIC || segments == 0 || segments > ESP_IMAGE_MAX_SEGMENTS {
+            return Err(RustbootError::InvalidImage);
+        }
+        let image = EspImage {
+            bytes,
+            entry,
+            chip_id,
+            segments,
+        };
+        let mut offset = ESP_IMAGE_HEADER_SIZE;
+        for _ in 0..segments {
+            let segment = image.segment_at(offset)?;
+            offset = segment.offset + segment.len;
+        }
+        Ok(image)
+    }
+
+    /// The image's entry point.
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// The image's segments, in the order they're stored.
+    pub fn segments(&self) -> impl Iterator<Item = Segment> + 'a {
+        let image = *self;
+        let mut offset = ESP_IMAGE_HEADER_SIZE;
+        (0..self.segments).map_while(move |_| {
+            // segments were checked by `parse`
+            let segment = image.segment_at(offset).ok()?;
+            offset = segment.offset + segment.len;
+            Some(segment)
+        })
+    }
+
+    /// Checks that the image can be booted from `image_addr` (i.e. the address it's read from,
+    /// which is a page-aligned flash address) on a chip with the given memory map i.e.
+    ///
+    /// - it's built for the chip.
+    /// - every segment either lies in a mapped region and is congruent to its flash address, or
+    ///   lies in RAM that may be loaded to. A segment that would overwrite the bootloader while
+    ///   it's loading the image is refused.
+    /// - the entry point lies in one of its segments.
+    ///
+    /// Returns `InvalidImage` otherwise.
+    pub fn check(&self, image_addr: usize, map: &MemoryMap) -> Result<()> {
+        let within = |regions: &[Range<usize>], range: &Range<usize>| {
+            regions
+                .iter()
+                .any(|region| region.start <= range.start && range.end <= region.end)
+        };
+        if self.chip_id != map.chip_id {
+            return Err(RustbootError::InvalidImage);
+        }
+        for segment in self.segments() {
+            let range = segment.range();
+            let mapped = within(map.mapped, &range)
+                && (image_addr + segment.offset) % MMU_PAGE_SIZE
+                    == segment.load_addr % MMU_PAGE_SIZE;
+            if !mapped && !within(map.ram, &range) {
+                return Err(RustbootError::InvalidImage);
+            }
+        }
+        match self
+            .segments()
+            .any(|segment| segment.range().contains(&self.entry))
+        {
+            true => Ok(()),
+            false => Err(RustbootError::InvalidImage),
+        }
+    }
+
+    fn segment_at(&self, offset: usize) -> Result<Segment> {
+        let header = self
+            .bytes
+            .get(offset..offset + ESP_SEGMENT_HEADER_SIZE)
+            .ok_or(RustbootError::InvalidImage)?;
+        let segment = match *header {
+            [a0, a1, a2, a3, l0, l1, l2, l3] => Segment {
+                load_addr: u32::from_le_bytes([a0, a1, a2, a3]) as usize,
+                offset: offset + ESP_SEGMENT_HEADER_SIZE,
+                len: u32::from_le_bytes([l0, l1, l2, l3]) as usize,
+            },
+            _ => return Err(RustbootError::InvalidImage),
+        };
+        match segment.offset.checked_add(segment.len) {
+            Some(end) if end <= self.bytes.len() => Ok(segment),
+            _ => Err(RustbootError::InvalidImage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IROM: Range<usize> = 0x4200_0000..0x4400_0000;
+    const DROM: Range<usize> = 0x3C00_0000..0x3E00_0000;
+    const MAP: MemoryMap = MemoryMap {
+        chip_id: 9,
+        mapped: &[IROM, DROM],
+        ram: &[0x4037_0000..0x403B_0000, 0x3FC8_8000..0x3FCC_0000],
+    };
+
+    /// An app image for chip `9`, with the given `(load address, data)` segments.
+    fn image(entry: u32, segments: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut image = vec![ESP_IMAGE_MAGIC, segments.len() as u8, 2, 0x20];
+        image.extend_from_slice(&entry.to_le_bytes());
+        image.extend_from_slice(&[0xEE, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        for (load_addr, data) in segments {
+            image.extend_from_slice(&load_addr.to_le_bytes());
+            image.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            image.extend_from_slice(data);
+        }
+        image
+    }
+
+    #[test]
+    fn parses_segments() {
+        let image = image(
+            0x4037_8000,
+            &[(0x3C00_0020, &[1; 32]), (0x4037_8000, &[2; 16])],
+        );
+        let esp = EspImage::parse(&image).unwrap();
+        assert_eq!(esp.entry(), 0x4037_8000);
+        let segments = esp.segments().collect::<Vec<_>>();
+        assert_eq!(
+            segments,
+            [
+                Segment {
+                    load_addr: 0x3C00_0020,
+                    offset: 32,
+                    len: 32,
+                },
+                Segment {
+                    load_addr: 0x4037_8000,
+                    offset: 72,
+                    len: 16,
+                },
+            ]
+        );
+        assert_eq!(&image[72..88], &[2; 16]);
+        // padding follows the image
+        let padded = [&image[..], &[0xFF; 64]].concat();
+        assert_eq!(EspImage::parse(&padded).unwrap().segments().count(), 2);
+    }
+
+    #[test]
+    fn invalid_images() {
+        let image = image(0x4037_8000, &[(0x4037_8000, &[2; 16])]);
+        let invalid = |bytes: &[u8]| EspImage::parse(bytes).err();
+        assert_eq!(invalid(&image), None);
+        // truncated header or segment
+        assert_eq!(invalid(&image[..20]), Some(RustbootError::InvalidImage));
+        assert_eq!(
+            invalid(&image[..image.len() - 1]),
+            Some(RustbootError::InvalidImage)
+        );
+        // erased flash
+        assert_eq!(invalid(&[0xFF; 64]), Some(RustbootError::InvalidImage));
+        // no segments, or too many
+        let mut bytes = image.clone();
+        bytes[1] = 0;
+        assert_eq!(invalid(&bytes), Some(RustbootError::InvalidImage));
+        bytes[1] = 17;
+        assert_eq!(invalid(&bytes), Some(RustbootError::InvalidImage));
+        // a segment length that overflows
+        let mut bytes = image.clone();
+        bytes[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(invalid(&bytes), Some(RustbootError::InvalidImage));
+    }
+
+    #[test]
+    fn memory_map_checks() {
+        let check = |entry, segments: &[(u32, &[u8])]| {
+            EspImage::parse(&image(entry, segments))
+                .unwrap()
+                .check(0x3D03_0000, &MAP)
+        };
+        // a mapped segment's data starts 32 bytes into the image
+        let irom = 0x4201_0020;
+        assert_eq!(check(irom, &[(irom, &[0; 16])]), Ok(()));
+        assert_eq!(check(0x4037_8000, &[(0x4037_8000, &[0; 16])]), Ok(()));
+
+        let invalid = Err(RustbootError::InvalidImage);
+        // a mapped segment that isn't congruent to its flash address
+        assert_eq!(check(irom, &[(0x4201_0000, &[0; 48])]), invalid);
+        let esp = image(irom, &[(irom, &[0; 16])]);
+        let esp = EspImage::parse(&esp).unwrap();
+        assert_eq!(esp.check(0x3D03_0100, &MAP), invalid);
+        // a RAM segment that overlaps the bootloader's RAM, or lies outside RAM
+        assert_eq!(check(0x4037_8000, &[(0x403A_FFF0, &[0; 32])]), invalid);
+        assert_eq!(check(0x6000_0000, &[(0x6000_0000, &[0; 16])]), invalid);
+        // the entry point isn't in a segment
+        assert_eq!(check(0x4037_9000, &[(0x4037_8000, &[0; 16])]), invalid);
+
+        // built for another chip
+        let mut bytes = image(0x4037_8000, &[(0x4037_8000, &[0; 16])]);
+        bytes[12] = 5;
+        let esp = EspImage::parse(&bytes).unwrap();
+        assert_eq!(esp.check(0x3D03_0000, &MAP), invalid);
+    }
+
+    #[test]
+    fn app_image_starts() {
+        assert_eq!(app_image_start(0x3D02_0100), 0x3D03_0000);
+        assert_eq!(app_image_start(0x3D03_0000), 0x3D03_0000);
+    }
+}
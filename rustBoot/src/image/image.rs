@@ -1,10 +1,26 @@
+#[cfg(feature = "mcuboot")]
+use super::mcuboot::McubootImage;
 use super::sealed::Sealed;
+pub use super::state::{PartitionState, Retired, SectorFlag, StateRecord, TRIAL_MARKER};
+use crate::chain::find_header;
 use crate::constants::*;
-use crate::crypto::signatures::{verify_ecc256_signature, HDR_IMG_TYPE_AUTH};
+use crate::crc::{stored_crc32, Crc32};
+#[cfg(feature = "double-verify")]
+use crate::crypto::signatures::second_signature_check;
+#[cfg(not(feature = "cert-chain"))]
+use crate::crypto::signatures::verify_ecc256_signature;
+use crate::crypto::signatures::HDR_IMG_TYPE_AUTH;
 use crate::parser::*;
+pub use crate::parser::{VendorTlv, VendorTlvs};
+use crate::progress::{Phase, Progress};
+use crate::rbconstants::HDR_BOARD_ID_LEN;
+#[cfg(feature = "suit")]
+use crate::suit::{SuitEnvelope, APP_COMPONENT};
 use crate::{Result, RustbootError};
 
 use crate::flashapi::FlashApi;
+#[cfg(feature = "compression")]
+use crate::lz4;
 
 #[cfg(feature = "secp256k1")]
 use k256::{
@@ -161,7 +177,11 @@ impl ValidPart for Swap {
 
 #[derive(Debug)]
 pub struct PartDescriptor<Part: ValidPart> {
+    /// the partition's start, which flash operations (i.e. [`FlashApi`]) are relative to
     pub hdr: Option<*const u8>,
+    /// the offset of the image's header from the partition's start i.e. the alignment padding
+    /// that precedes it, see [`HEADER_SEARCH_WINDOW`]
+    pub hdr_offset: usize,
     pub fw_base: *const u8,
     sha_hash: Option<*const u8>,
     pub trailer: Option<*const u8>,
@@ -180,17 +200,11 @@ impl<Part: ValidPart> PartDescriptor<Part> {
     pub fn open_partition(part: Part, updater: impl FlashApi) -> Result<ImageType<'static>> {
         match part.part_id() {
             PartId::PartBoot => {
-                let size;
-                unsafe {
-                    let magic = *(BOOT_PARTITION_ADDRESS as *const usize);
-                    size = *((BOOT_PARTITION_ADDRESS + 4) as *const usize);
-                    if (magic != RUSTBOOT_MAGIC) || (size > PARTITION_SIZE - IMAGE_HEADER_SIZE) {
-                        return Err(RustbootError::InvalidImage);
-                    }
-                }
+                let (hdr_offset, size) = find_image(BOOT_PARTITION_ADDRESS)?;
                 let part_desc = PartDescriptor {
                     hdr: Some(BOOT_PARTITION_ADDRESS as *const u8),
-                    fw_base: (BOOT_FWBASE) as *const u8,
+                    hdr_offset,
+                    fw_base: (BOOT_FWBASE + hdr_offset) as *const u8,
                     sha_hash: None,
                     trailer: Some(BOOT_TRAILER_ADDRESS as *const u8),
                     fw_size: size,
@@ -221,21 +235,15 @@ impl<Part: ValidPart> PartDescriptor<Part> {
                         },
                         state: Some(state),
                     })),
-                    _ => todo!(),
+                    _ => Err(RustbootError::InvalidState),
                 }
             }
             PartId::PartUpdate => {
-                let size;
-                unsafe {
-                    let magic = *(UPDATE_PARTITION_ADDRESS as *const usize);
-                    size = *((UPDATE_PARTITION_ADDRESS + 4) as *const usize);
-                    if (magic != RUSTBOOT_MAGIC) || (size > PARTITION_SIZE - IMAGE_HEADER_SIZE) {
-                        return Err(RustbootError::InvalidImage);
-                    }
-                }
+                let (hdr_offset, size) = find_image(UPDATE_PARTITION_ADDRESS)?;
                 let part_desc = PartDescriptor {
                     hdr: Some(UPDATE_PARTITION_ADDRESS as *const u8),
-                    fw_base: (UPDATE_FWBASE) as *const u8,
+                    hdr_offset,
+                    fw_base: (UPDATE_FWBASE + hdr_offset) as *const u8,
                     sha_hash: None,
                     trailer: Some(UPDATE_TRAILER_ADDRESS as *const u8),
                     fw_size: size,
@@ -261,7 +269,7 @@ impl<Part: ValidPart> PartDescriptor<Part> {
                             state: Some(state),
                         }))
                     }
-                    _ => todo!(),
+                    _ => Err(RustbootError::InvalidState),
                 }
             }
             PartId::PartSwap => {
@@ -269,6 +277,7 @@ impl<Part: ValidPart> PartDescriptor<Part> {
                 // This is an exclusive constructor for the `swap` partition.
                 let part_desc = PartDescriptor {
                     hdr: Some(SWAP_PARTITION_ADDRESS as *const u8),
+                    hdr_offset: 0,
                     fw_base: SWAP_BASE as *const u8,
                     sha_hash: None,
                     trailer: None,
@@ -288,26 +297,115 @@ impl<Part: ValidPart> PartDescriptor<Part> {
             }
         }
     }
+
+    /// Returns the image's header i.e. past any padding that precedes it.
+    pub fn header(&self) -> Option<*const u8> {
+        self.hdr.map(|hdr| hdr.wrapping_add(self.hdr_offset))
+    }
+
+    /// Returns the offset (from the partition's start) at which the image ends i.e. the length of
+    /// its padding, header and firmware.
+    pub fn image_end(&self) -> usize {
+        self.hdr_offset + IMAGE_HEADER_SIZE + self.fw_size
+    }
+}
+
+/// Returns the offset of the image in the partition at `addr` (i.e. of its header) and the size
+/// of its firmware. An image is expected at the partition's start, unless the board allows for
+/// padding (see [`HEADER_SEARCH_WINDOW`]) in which case the first header found within the window
+/// is used.
+fn find_image(addr: usize) -> Result<(usize, usize)> {
+    match image_size(addr, 0) {
+        Err(RustbootError::InvalidImage) if HEADER_SEARCH_WINDOW > 0 => {
+            let window =
+                unsafe { core::slice::from_raw_parts(addr as *const u8, HEADER_SEARCH_WINDOW + 4) };
+            let offset = find_header(window, HEADER_SEARCH_WINDOW, HEADER_SEARCH_ALIGN)
+                .ok_or(RustbootError::InvalidImage)?;
+            let size = image_size(addr, offset)?;
+            match offset + IMAGE_HEADER_SIZE + size <= PARTITION_SIZE {
+                true => Ok((offset, size)),
+                false => Err(RustbootError::InvalidImage),
+            }
+        }
+        res => res.map(|size| (0, size)),
+    }
+}
+
+/// Returns the size of the firmware of the image `offset` bytes into the partition at `addr`, as
+/// recorded in the image's header (or SUIT manifest). For MCUboot images, this includes the TLVs
+/// following the firmware.
+fn image_size(addr: usize, offset: usize) -> Result<usize> {
+    #[cfg(feature = "mcuboot")]
+    if let Some(image) = mcuboot_image_at(addr as *const u8, offset) {
+        return Ok(image?.size());
+    }
+    let addr = addr + offset;
+    #[cfg(feature = "suit")]
+    if let Some(envelope) = envelope_at(addr as *const u8) {
+        let size = envelope
+            .manifest()?
+            .image_size()
+            .ok_or(RustbootError::InvalidFirmwareSize)?;
+        if size > PARTITION_SIZE - IMAGE_HEADER_SIZE {
+            return Err(RustbootError::InvalidImage);
+        }
+        return Ok(size);
+    }
+    unsafe {
+        let magic = *(addr as *const usize);
+        let size = *((addr + 4) as *const usize);
+        if (magic != RUSTBOOT_MAGIC) || (size > PARTITION_SIZE - IMAGE_HEADER_SIZE) {
+            return Err(RustbootError::InvalidImage);
+        }
+        Ok(size)
+    }
+}
+
+/// Returns the SUIT envelope at `hdr`, if the image carries one instead of a rustBoot header.
+/// The envelope occupies the header's [`IMAGE_HEADER_SIZE`] bytes.
+#[cfg(feature = "suit")]
+fn envelope_at(hdr: *const u8) -> Option<SuitEnvelope<'static>> {
+    let header = unsafe { core::slice::from_raw_parts(hdr, IMAGE_HEADER_SIZE) };
+    match SuitEnvelope::is_envelope(header) {
+        true => SuitEnvelope::parse(header).ok(),
+        false => None,
+    }
+}
+
+/// Returns the MCUboot image `offset` bytes into the partition at `part`, if the partition holds
+/// one instead of a rustBoot image. The image (i.e. its header, firmware and TLVs) must fit in
+/// what's left of the partition, or it's rejected.
+#[cfg(feature = "mcuboot")]
+fn mcuboot_image_at(part: *const u8, offset: usize) -> Option<Result<McubootImage<'static>>> {
+    let image = unsafe {
+        core::slice::from_raw_parts(
+            part.wrapping_add(offset),
+            PARTITION_SIZE.saturating_sub(offset),
+        )
+    };
+    match McubootImage::is_mcuboot(image) {
+        true => Some(McubootImage::parse(image)),
+        false => None,
+    }
 }
 
 impl<Part: ValidPart + Swappable> PartDescriptor<Part> {
     pub fn get_part_status(&self, updater: impl FlashApi) -> Result<States> {
         let magic_trailer = unsafe { *self.get_partition_trailer_magic()? };
         if magic_trailer != RUSTBOOT_MAGIC_TRAIL as u32 {
-            self.set_partition_trailer_magic(updater)
-                .expect("failed to set partition status");
-        }
-        let state = unsafe { *self.get_partition_state()? };
-        let state = match state {
-            0xFF => Ok(States::New(StateNew)),
-            0x70 => Ok(States::Updating(StateUpdating)),
-            0x10 => Ok(States::Testing(StateTesting)),
-            0x00 => Ok(States::Success(StateSuccess)),
-            _ => Err(RustbootError::InvalidState),
-        };
-        state
+            self.set_partition_trailer_magic(updater)?;
+        }
+        Ok(match self.partition_state()? {
+            PartitionState::New => States::New(StateNew),
+            PartitionState::Updating => States::Updating(StateUpdating),
+            PartitionState::Testing => States::Testing(StateTesting),
+            PartitionState::Success => States::Success(StateSuccess),
+        })
     }
 
+    /// Moves the partition to `state`. Returns [`RustbootError::InvalidStateTransition`] if
+    /// the partition may not move from its current state to `state`, see
+    /// [`PartitionState::transition`].
     pub fn set_state<State: TypeState + Updateable>(
         &self,
         updater: impl FlashApi,
@@ -315,14 +413,13 @@ impl<Part: ValidPart + Swappable> PartDescriptor<Part> {
     ) -> Result<bool> {
         let magic_trailer = unsafe { *self.get_partition_trailer_magic()? };
         if magic_trailer != RUSTBOOT_MAGIC_TRAIL as u32 {
-            self.set_partition_trailer_magic(updater)
-                .expect("failed to set partition status");
+            self.set_partition_trailer_magic(updater)?;
         }
-        let current_state = unsafe { *self.get_partition_state()? };
-        let new_state = state.from().unwrap();
-        if current_state != new_state {
-            self.set_partition_state(updater, new_state)
-                .expect("failed to set partition status");
+        let current_state = self.partition_state()?;
+        let new_state =
+            PartitionState::from_byte(state.from().ok_or(RustbootError::InvalidState)?)?;
+        if current_state.transition(self.part.part_id(), new_state)? != current_state {
+            self.set_partition_state(updater, new_state.as_byte())?;
         }
         Ok(true)
     }
@@ -332,17 +429,87 @@ impl<Part: ValidPart + Swappable> PartDescriptor<Part> {
     }
 
     fn set_partition_trailer_magic(&self, updater: impl FlashApi) -> Result<()> {
-        let trailer_magic = (&RUSTBOOT_MAGIC_TRAIL as *const usize) as *const u8;
-        Ok(updater.flash_trailer_write(self, 0, trailer_magic, MAGIC_TRAIL_LEN))
+        let trailer_magic = RUSTBOOT_MAGIC_TRAIL.to_le_bytes();
+        updater.flash_trailer_write(self, 0, &trailer_magic[..MAGIC_TRAIL_LEN])
     }
 
+    /// Reads the partition's state, from its state byte or (with the `state-journal` feature)
+    /// its newest [`StateRecord`].
+    fn partition_state(&self) -> Result<PartitionState> {
+        #[cfg(feature = "state-journal")]
+        return Ok(StateRecord::newest(self.state_slots()?)
+            .map_or(PartitionState::New, |record| record.state));
+        #[cfg(not(feature = "state-journal"))]
+        PartitionState::from_byte(unsafe { *self.get_partition_state()? })
+    }
+
+    #[cfg(not(feature = "state-journal"))]
     fn get_partition_state(&self) -> Result<*const u8> {
         self.get_trailer_at_offset(1)
     }
 
+    /// Writes the partition's state. With the `state-journal` feature, it's recorded in the
+    /// journal's next slot, see [`StateRecord`]. Returns [`RustbootError::InvalidState`] if the
+    /// journal is full i.e. the trailer must be erased first.
     pub fn set_partition_state(&self, updater: impl FlashApi, state: u8) -> Result<()> {
-        let state = &state as *const u8;
-        Ok(updater.flash_trailer_write(self, 1, state, PART_STATUS_LEN))
+        #[cfg(feature = "state-journal")]
+        {
+            let slots = self.state_slots()?;
+            let record = StateRecord {
+                state: PartitionState::from_byte(state)?,
+                seq: StateRecord::newest(slots).map_or(0, |newest| newest.seq.wrapping_add(1)),
+            };
+            let slot = StateRecord::next_slot(slots).ok_or(RustbootError::InvalidState)?;
+            return updater.flash_trailer_write(
+                self,
+                self.state_slot_offset(slot),
+                &record.to_bytes(),
+            );
+        }
+        #[cfg(not(feature = "state-journal"))]
+        updater.flash_trailer_write(self, 1, &[state])
+    }
+
+    /// The state journal's slots, in the order they're written in.
+    #[cfg(feature = "state-journal")]
+    fn state_slots(&self) -> Result<[&'static [u8]; STATE_SLOTS]> {
+        let mut slots: [&[u8]; STATE_SLOTS] = [&[]; STATE_SLOTS];
+        for (idx, slot) in slots.iter_mut().enumerate() {
+            let addr = self.get_trailer_at_offset(self.state_slot_offset(idx))?;
+            *slot = unsafe { core::slice::from_raw_parts(addr, STATE_SLOT_LEN) };
+        }
+        Ok(slots)
+    }
+
+    /// The offset of the state journal's `slot` (i.e. of its first byte). The journal is the
+    /// trailer's first (and lowest) `STATE_SLOTS` slots, see [`UPDATE_TRAILER_LEN`].
+    #[cfg(feature = "state-journal")]
+    fn state_slot_offset(&self, slot: usize) -> usize {
+        let len = match self.part.part_id() {
+            PartId::PartUpdate => UPDATE_TRAILER_LEN,
+            _ => BOOT_TRAILER_LEN,
+        };
+        len - slot * STATE_SLOT_LEN - MAGIC_TRAIL_LEN
+    }
+
+    /// Returns `true` if the partition's image is marked as a trial boot, see [`TRIAL_MARKER`].
+    pub fn is_trial(&self) -> Result<bool> {
+        let marker = unsafe { *self.get_trailer_at_offset(self.trial_offset())? };
+        Ok(marker == TRIAL_MARKER)
+    }
+
+    /// Marks the partition's image as a trial boot, see [`TRIAL_MARKER`]. The mark is cleared
+    /// along with the rest of the trailer i.e. by a swap.
+    pub fn set_trial(&self, updater: impl FlashApi) -> Result<()> {
+        self.set_trailer_at(updater, self.trial_offset(), TRIAL_MARKER)
+    }
+
+    /// The trial byte follows the state or, in the update partition, the sector flags.
+    fn trial_offset(&self) -> usize {
+        match self.part.part_id() {
+            PartId::PartUpdate => 2 + (PARTITION_SIZE / SECTOR_SIZE + 1) / 2,
+            _ => 2,
+        }
     }
 
     fn get_trailer_at_offset(&self, offset: usize) -> Result<*const u8> {
@@ -353,13 +520,13 @@ impl<Part: ValidPart + Swappable> PartDescriptor<Part> {
     }
 
     fn set_trailer_at(&self, updater: impl FlashApi, offset: usize, flag: u8) -> Result<()> {
-        let newflag = &flag as *const u8;
-        Ok(updater.flash_trailer_write(self, offset, newflag, 1))
+        updater.flash_trailer_write(self, offset, &[flag])
     }
 }
 
 impl PartDescriptor<Update> {
-    pub fn get_flags(&self, sector: usize) -> Result<SectFlags> {
+    /// Returns a sector's flag i.e. the sector's progress through the swap.
+    pub fn get_flags(&self, sector: usize) -> Result<SectorFlag> {
         let sector_position = sector >> 1;
         let magic_trailer = unsafe { *self.get_partition_trailer_magic()? };
         if magic_trailer != RUSTBOOT_MAGIC_TRAIL as u32 {
@@ -372,20 +539,16 @@ impl PartDescriptor<Update> {
         } else {
             flags = (res & 0xF0) >> 4;
         }
-        match flags {
-            0x0F => Ok(SectFlags::NewFlag),
-            0x07 => Ok(SectFlags::SwappingFlag),
-            0x03 => Ok(SectFlags::BackupFlag),
-            0x00 => Ok(SectFlags::UpdatedFlag),
-            _ => return Err(RustbootError::InvalidSectFlag),
-        }
+        SectorFlag::from_nibble(flags)
     }
 
     pub fn get_update_sector_flags(&self, offset: usize) -> Result<*const u8> {
         self.get_trailer_at_offset(2 + offset)
     }
-    pub fn set_flags(&self, updater: impl FlashApi, sector: usize, flag: SectFlags) -> Result<()> {
-        let newflag = flag.from().ok_or(RustbootError::InvalidSectFlag)?;
+    /// Sets a sector's flag. Returns [`RustbootError::InvalidStateTransition`] if `flag`
+    /// isn't the sector's current flag or the next one, see [`SectorFlag::transition`]. A
+    /// sector with an invalid flag is treated as `New`.
+    pub fn set_flags(&self, updater: impl FlashApi, sector: usize, flag: SectorFlag) -> Result<()> {
         let sector_position = sector >> 1;
         let magic_trailer = unsafe { *self.get_partition_trailer_magic()? };
         if magic_trailer != RUSTBOOT_MAGIC_TRAIL as u32 {
@@ -393,6 +556,14 @@ impl PartDescriptor<Update> {
         }
         let flags;
         let res = unsafe { *self.get_update_sector_flags(sector_position)? };
+        let current = match sector == (sector_position << 1) {
+            true => res,
+            false => res >> 4,
+        };
+        let newflag = SectorFlag::from_nibble(current)
+            .unwrap_or(SectorFlag::New)
+            .transition(flag)?
+            .as_nibble();
         if sector == (sector_position << 1) {
             flags = (res & 0xF0) | (newflag & 0x0F);
         } else {
@@ -409,56 +580,91 @@ impl PartDescriptor<Update> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-#[cfg_attr(feature = "defmt", derive(Format))]
-pub enum SectFlags {
-    NewFlag,
-    SwappingFlag,
-    BackupFlag,
-    UpdatedFlag,
-    None,
-}
-
-impl SectFlags {
-    pub fn has_new_flag(&self) -> bool {
-        self == &SectFlags::NewFlag
-    }
-
-    pub fn has_swapping_flag(&self) -> bool {
-        self == &SectFlags::SwappingFlag
-    }
-
-    pub fn has_backup_flag(&self) -> bool {
-        self == &SectFlags::BackupFlag
-    }
+/// The buffer a compressed image is read through, as it's decompressed.
+#[cfg(feature = "compression")]
+const COMPRESSED_CHUNK: usize = 64;
 
-    pub fn has_updated_flag(&self) -> bool {
-        self == &SectFlags::UpdatedFlag
-    }
-
-    pub fn set_swapping_flag(&mut self) -> Self {
-        *self = SectFlags::SwappingFlag;
-        *self
-    }
-
-    pub fn set_backup_flag(&mut self) -> Self {
-        *self = SectFlags::BackupFlag;
-        *self
+#[cfg(feature = "compression")]
+impl<Part: ValidPart> PartDescriptor<Part> {
+    /// Returns the length of the partition's compressed firmware, if it holds a compressed image
+    /// i.e. if it's the `UPDATE` partition and its image has a compression TLV (see [`lz4`]).
+    ///
+    /// An installed image keeps its header (and so its compression TLV), but `BOOT` holds its
+    /// decompressed firmware.
+    pub fn compressed_len(&self) -> Result<Option<usize>> {
+        let hdr = match (self.part.part_id(), self.header()) {
+            (PartId::PartUpdate, Some(hdr)) => hdr,
+            _ => return Ok(None),
+        };
+        #[cfg(feature = "suit")]
+        if envelope_at(hdr).is_some() {
+            return Ok(None);
+        }
+        #[cfg(feature = "mcuboot")]
+        if self
+            .hdr
+            .and_then(|part| mcuboot_image_at(part, self.hdr_offset))
+            .is_some()
+        {
+            return Ok(None);
+        }
+        let header = unsafe { core::slice::from_raw_parts(hdr, IMAGE_HEADER_SIZE) };
+        match vendor_tlvs(header)?.compressed_len()? {
+            Some(len) if self.hdr_offset + IMAGE_HEADER_SIZE + len > PARTITION_SIZE => {
+                Err(RustbootError::InvalidImage)
+            }
+            len => Ok(len),
+        }
     }
 
-    pub fn set_updated_flag(&mut self) -> Self {
-        *self = SectFlags::UpdatedFlag;
-        *self
+    /// Decompresses the partition's compressed firmware (see [`Self::compressed_len`]), read via
+    /// [`FlashApi::flash_read`]. `sink` is passed the decompressed firmware in order, up to
+    /// [`lz4::WINDOW`] bytes at a time.
+    pub fn decompress(
+        &self,
+        updater: impl FlashApi,
+        sink: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        self.decompress_with(
+            |offset, buf| updater.flash_read(self, self.hdr_offset + offset, buf),
+            sink,
+        )
     }
 
-    pub fn from(&self) -> Option<u8> {
-        match self {
-            SectFlags::NewFlag => Some(0x0F),
-            SectFlags::SwappingFlag => Some(0x07),
-            SectFlags::BackupFlag => Some(0x03),
-            SectFlags::UpdatedFlag => Some(0x00),
-            _ => None,
-        }
+    /// Same as [`Self::decompress`], `read` fills a buffer with the image's bytes at an offset
+    /// (from its header).
+    fn decompress_with(
+        &self,
+        mut read: impl FnMut(usize, &mut [u8]) -> Result<()>,
+        sink: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let compressed_len = self.compressed_len()?.ok_or(RustbootError::InvalidImage)?;
+        let end = IMAGE_HEADER_SIZE + compressed_len;
+        let mut buf = [0u8; COMPRESSED_CHUNK];
+        let (mut offset, mut pos, mut len) = (IMAGE_HEADER_SIZE, 0, 0);
+        let mut window = [0u8; lz4::WINDOW];
+        lz4::decompress(
+            || {
+                // the block is never read past its end i.e. `offset < end`
+                if pos == len {
+                    len = (end - offset).min(COMPRESSED_CHUNK);
+                    read(
+                        offset,
+                        buf.get_mut(..len).ok_or(RustbootError::BufferTooSmall)?,
+                    )?;
+                    offset += len;
+                    pos = 0;
+                }
+                pos += 1;
+                buf.get(pos - 1)
+                    .copied()
+                    .ok_or(RustbootError::IntegrityCheckFailed)
+            },
+            compressed_len,
+            self.fw_size,
+            &mut window,
+            sink,
+        )
     }
 }
 
@@ -529,19 +735,69 @@ impl<'a> RustbootImage<'a, Update, StateNew> {
 
 impl<'a, Part: ValidPart + Swappable, State: TypeState> RustbootImage<'a, Part, State> {
     pub fn get_firmware_version(&self) -> Result<u32> {
+        #[cfg(feature = "suit")]
+        if let Some(envelope) = self.suit_envelope() {
+            return envelope.manifest()?.get_firmware_version();
+        }
+        #[cfg(feature = "mcuboot")]
+        if let Some(image) = self.mcuboot_image() {
+            return Ok(image?.get_firmware_version());
+        }
         let val = parse_tlv(self, Tags::Version)?;
         let fw_version =
-            u32::from_be_bytes(val.try_into().map_err(|_| RustbootError::InvalidValue)?);
+            u32::from_le_bytes(val.try_into().map_err(|_| RustbootError::InvalidValue)?);
         Ok(fw_version)
     }
+
+    /// Returns the image's vendor (i.e. OEM) TLVs. SUIT and MCUboot images have none.
+    pub fn vendor_tlvs(&self) -> Result<VendorTlvs<'a>> {
+        #[cfg(feature = "suit")]
+        if self.suit_envelope().is_some() {
+            return Ok(VendorTlvs::default());
+        }
+        #[cfg(feature = "mcuboot")]
+        if self.mcuboot_image().is_some() {
+            return Ok(VendorTlvs::default());
+        }
+        get_vendor_tlvs(self)
+    }
+
+    /// Checks that the image was built for this board i.e. that its board-ID TLV (if it has one)
+    /// matches `board_id`, see [`board_id`]. Returns [`RustbootError::BoardMismatch`] if it
+    /// doesn't.
+    ///
+    /// Images without a board-ID TLV (i.e. signed without rbsigner's `--board` option) aren't
+    /// bound to a board and pass the check.
+    ///
+    /// *Note: the TLV is covered by the image's digest, so this is only meaningful for an image
+    /// whose authenticity has been verified.*
+    pub fn check_board(&self, board_id: &[u8; HDR_BOARD_ID_LEN]) -> Result<()> {
+        match self.vendor_tlvs()?.board_id()? {
+            Some(id) if &id != board_id => Err(RustbootError::BoardMismatch),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<'a, Part: ValidPart + Swappable, State: Updateable> RustbootImage<'a, Part, State> {
-    pub fn get_state(&self) -> &State {
-        let state = self.state.as_ref().unwrap();
-        state
+    pub fn get_state(&self) -> Result<&State> {
+        self.state.as_ref().ok_or(RustbootError::FieldNotSet)
     }
     pub fn get_image_type(&self) -> Result<u16> {
+        // SUIT envelopes are always signed (`ES256` i.e. nistp256), the manifest's component
+        // identifies the image.
+        #[cfg(feature = "suit")]
+        if let Some(envelope) = self.suit_envelope() {
+            return match envelope.manifest()?.has_component(APP_COMPONENT) {
+                true => Ok(HDR_IMG_TYPE_APP | HDR_IMG_TYPE_AUTH),
+                false => Err(RustbootError::InvalidImage),
+            };
+        }
+        // MCUboot images are application images, only signed (nistp256) ones are supported.
+        #[cfg(feature = "mcuboot")]
+        if let Some(image) = self.mcuboot_image() {
+            return image.map(|_| HDR_IMG_TYPE_APP | HDR_IMG_TYPE_AUTH);
+        }
         let val = parse_tlv(self, Tags::ImgType)?;
         let image_type =
             u16::from_le_bytes(val.try_into().map_err(|_| RustbootError::InvalidValue)?);
@@ -553,45 +809,94 @@ impl<'a, Part: ValidPart + Swappable, State: TypeState> RustbootImage<'a, Part,
     /// Used to verify the integrity of an image. Note - integrity checking includes
     /// `version` and `timestamp` fields.
     pub fn verify_integrity<const N: usize>(&mut self) -> Result<bool> {
+        self.verify_integrity_with_progress::<N>(&())
+    }
+
+    /// Same as [`Self::verify_integrity`], `progress` is called as the image is hashed.
+    pub fn verify_integrity_with_progress<const N: usize>(
+        &mut self,
+        progress: &impl Progress,
+    ) -> Result<bool> {
         match N {
             #[cfg(feature = "sha256")]
-            SHA256_DIGEST_SIZE => {
-                let integrity_check;
-                let _hash_type = HDR_SHA256;
-                let fw_size = self
-                    .part_desc
-                    .get()
-                    .ok_or(RustbootError::FieldNotSet)?
-                    .fw_size;
-                let res = parse_tlv(self, Tags::Digest256);
-                let stored_hash = match res {
-                    Ok(stored_hash) => {
-                        let hasher = compute_img_hash::<Part, State, Sha256, N>(self, fw_size)?;
-                        let computed_hash = hasher.finalize();
-                        if computed_hash.as_slice() != stored_hash {
-                            panic!("..integrity check failed");
-                        }
-                        integrity_check = true;
-                        Some(stored_hash.as_ptr())
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
-                };
-                if integrity_check.eq(&true) {
-                    match self.part_desc.get_mut() {
-                        Some(val) => {
-                            val.sha_ok = true;
-                            val.sha_hash = stored_hash;
-                        }
-                        None => return Err(RustbootError::__Nonexhaustive),
-                    }
-                    Ok(true)
-                } else {
-                    Err(RustbootError::Unreachable) // technically should be unreachable
+            SHA256_DIGEST_SIZE => self.check_integrity(compute_img_crc, |img, fw_size| {
+                compute_img_hash::<Part, State, Sha256, N>(img, fw_size, progress)
+            }),
+            _ => Err(RustbootError::BadHashValue),
+        }
+    }
+
+    /// Same as [`Self::verify_integrity`] but the hashed bytes (i.e. the header fields preceding
+    /// the digest and the firmware) are read via [`FlashApi::flash_read`], `C` bytes at a time,
+    /// instead of being addressed as one contiguous slice. This bounds the RAM hashing needs to a
+    /// single `C`-byte buffer (e.g. 1-4KB) and lets a board read its flash its own way (ex: reads
+    /// that survive an ECC error).
+    ///
+    /// *Note: the header's TLVs (and, with `cert-chain`, the signing certificate) are still read
+    /// in place i.e. the partition must be memory-mapped.*
+    pub fn verify_integrity_chunked<const N: usize, const C: usize>(
+        &mut self,
+        updater: impl FlashApi,
+    ) -> Result<bool> {
+        match N {
+            #[cfg(feature = "sha256")]
+            SHA256_DIGEST_SIZE => self.check_integrity(
+                |img, fw_size| compute_img_crc_chunked::<Part, State, C>(img, fw_size, updater),
+                |img, fw_size| {
+                    compute_img_hash_chunked::<Part, State, Sha256, N, C>(img, fw_size, updater)
+                },
+            ),
+            _ => Err(RustbootError::BadHashValue),
+        }
+    }
+
+    fn check_integrity<D: Digest>(
+        &mut self,
+        compute_crc: impl FnOnce(&Self, usize) -> Result<u32>,
+        compute_hash: impl FnOnce(&Self, usize) -> Result<D>,
+    ) -> Result<bool> {
+        #[cfg(feature = "suit")]
+        if let Some(envelope) = self.suit_envelope() {
+            return self.check_suit(envelope, false);
+        }
+        #[cfg(feature = "mcuboot")]
+        if let Some(image) = self.mcuboot_image() {
+            return self.check_mcuboot(image?, false);
+        }
+        let integrity_check;
+        let _hash_type = HDR_SHA256;
+        let fw_size = self
+            .part_desc
+            .get()
+            .ok_or(RustbootError::FieldNotSet)?
+            .fw_size;
+        self.check_crc(fw_size, compute_crc)?;
+        let res = parse_tlv(self, Tags::Digest256);
+        let stored_hash = match res {
+            Ok(stored_hash) => {
+                let hasher = compute_hash(self, fw_size)?;
+                let computed_hash = hasher.finalize();
+                if computed_hash.as_slice() != stored_hash {
+                    return Err(RustbootError::IntegrityCheckFailed);
+                }
+                integrity_check = true;
+                Some(stored_hash.as_ptr())
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        if integrity_check.eq(&true) {
+            match self.part_desc.get_mut() {
+                Some(val) => {
+                    val.sha_ok = true;
+                    val.sha_hash = stored_hash;
                 }
+                None => return Err(RustbootError::__Nonexhaustive),
             }
-            _ => todo!(),
+            Ok(true)
+        } else {
+            Err(RustbootError::Unreachable) // technically should be unreachable
         }
     }
 
@@ -601,58 +906,212 @@ impl<'a, Part: ValidPart + Swappable, State: TypeState> RustbootImage<'a, Part,
     ///
     /// - `IMG_TYPE_AUTH_ECC256` (secp256k1)
     /// - `IMG_TYPE_AUTH_ED25519` (ed25519)
+    ///
+    /// With the `double-verify` feature, the image is hashed twice and the signature is also checked
+    /// with the registered [`SignatureCheck`](crate::crypto::signatures::SignatureCheck), both checks
+    /// must pass. SUIT and MCUboot images are checked once.
+    ///
+    /// With the `cert-chain` feature, an image that carries a signing certificate is checked with
+    /// the key it certifies, see `rustBoot::cert`.
     pub fn verify_authenticity<const N: u16>(&mut self) -> Result<bool> {
+        self.verify_authenticity_with_progress::<N>(&())
+    }
+
+    /// Same as [`Self::verify_authenticity`], `progress` is called as the image is hashed.
+    pub fn verify_authenticity_with_progress<const N: u16>(
+        &mut self,
+        progress: &impl Progress,
+    ) -> Result<bool> {
         match N {
             #[cfg(feature = "nistp256")]
-            HDR_IMG_TYPE_AUTH => {
-                let auth_check;
-                let _signature_type = HDR_SIGNATURE;
-                let fw_size = self
-                    .part_desc
-                    .get()
-                    .ok_or(RustbootError::FieldNotSet)?
-                    .fw_size;
-                let res = parse_tlv(self, Tags::Signature);
-                let computed_hash = match res {
-                    Ok(stored_signature) => {
-                        let img_type_val = parse_tlv(self, Tags::ImgType)?;
-                        let val = img_type_val[0] as u16 + ((img_type_val[1] as u16) << 8);
-                        if (val & 0xFF00) != N {
-                            return Err(RustbootError::InvalidValue);
-                        }
-                        // verify signature
-                        let hasher2 = compute_img_hash::<Part, State, Sha256, SHA256_DIGEST_SIZE>(
-                            self, fw_size,
-                        )?;
-                        let computed_hash = Some(hasher2.clone().finalize().as_ptr());
-                        auth_check = verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(
-                            hasher2,
-                            &stored_signature,
-                        )?;
-                        computed_hash
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
-                };
-                if auth_check.eq(&true) {
-                    match self.part_desc.get_mut() {
-                        Some(val) => {
-                            val.sha_hash = computed_hash;
-                            val.signature_ok = true;
-                        }
-                        None => return Err(RustbootError::__Nonexhaustive),
+            HDR_IMG_TYPE_AUTH => self.check_authenticity::<N>(compute_img_crc, |img, fw_size| {
+                compute_img_hash::<Part, State, Sha256, SHA256_DIGEST_SIZE>(img, fw_size, progress)
+            }),
+            // ed25519 signatures can't be checked yet
+            #[cfg(feature = "ed25519")]
+            HDR_IMG_TYPE_AUTH => Err(RustbootError::FwAuthFailed),
+            _ => Err(RustbootError::InvalidValue),
+        }
+    }
+
+    /// Same as [`Self::verify_authenticity`] but the image is read via the [`FlashApi`], `C` bytes
+    /// at a time. See [`Self::verify_integrity_chunked`].
+    pub fn verify_authenticity_chunked<const N: u16, const C: usize>(
+        &mut self,
+        updater: impl FlashApi,
+    ) -> Result<bool> {
+        match N {
+            #[cfg(feature = "nistp256")]
+            HDR_IMG_TYPE_AUTH => self.check_authenticity::<N>(
+                |img, fw_size| compute_img_crc_chunked::<Part, State, C>(img, fw_size, updater),
+                |img, fw_size| {
+                    compute_img_hash_chunked::<Part, State, Sha256, SHA256_DIGEST_SIZE, C>(
+                        img, fw_size, updater,
+                    )
+                },
+            ),
+            // ed25519 signatures can't be checked yet
+            #[cfg(feature = "ed25519")]
+            HDR_IMG_TYPE_AUTH => Err(RustbootError::FwAuthFailed),
+            _ => Err(RustbootError::InvalidValue),
+        }
+    }
+
+    #[cfg(feature = "nistp256")]
+    fn check_authenticity<const N: u16>(
+        &mut self,
+        compute_crc: impl FnOnce(&Self, usize) -> Result<u32>,
+        compute_hash: impl Fn(&Self, usize) -> Result<Sha256>,
+    ) -> Result<bool> {
+        #[cfg(feature = "suit")]
+        if let Some(envelope) = self.suit_envelope() {
+            return self.check_suit(envelope, true);
+        }
+        #[cfg(feature = "mcuboot")]
+        if let Some(image) = self.mcuboot_image() {
+            return self.check_mcuboot(image?, true);
+        }
+        let auth_check;
+        let _signature_type = HDR_SIGNATURE;
+        let fw_size = self
+            .part_desc
+            .get()
+            .ok_or(RustbootError::FieldNotSet)?
+            .fw_size;
+        self.check_crc(fw_size, compute_crc)?;
+        let res = parse_tlv(self, Tags::Signature);
+        let computed_hash = match res {
+            Ok(stored_signature) => {
+                let img_type_val = parse_tlv(self, Tags::ImgType)?;
+                let val = u16::from_le_bytes(
+                    img_type_val
+                        .try_into()
+                        .map_err(|_| RustbootError::InvalidValue)?,
+                );
+                if (val & 0xFF00) != N {
+                    return Err(RustbootError::InvalidValue);
+                }
+                // verify signature
+                let hasher2 = compute_hash(self, fw_size)?;
+                let computed_hash = Some(hasher2.clone().finalize().as_ptr());
+                // with a signing certificate, the image is signed by the key it certifies. The
+                // certificate is read in place, at the end of the (memory-mapped) firmware.
+                #[cfg(feature = "cert-chain")]
+                {
+                    let fw_base = self
+                        .part_desc
+                        .get()
+                        .ok_or(RustbootError::FieldNotSet)?
+                        .fw_base;
+                    let firmware = unsafe { core::slice::from_raw_parts(fw_base, fw_size) };
+                    auth_check = crate::cert::verify_image_signature(
+                        image_header(self)?,
+                        firmware,
+                        hasher2,
+                        &stored_signature,
+                    )?;
+                }
+                #[cfg(not(feature = "cert-chain"))]
+                {
+                    auth_check = verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(
+                        hasher2,
+                        &stored_signature,
+                    )?;
+                }
+                // check the signature again, over a freshly computed digest and with an
+                // independent routine
+                #[cfg(feature = "double-verify")]
+                {
+                    let mut digest = [0u8; SHA256_DIGEST_SIZE];
+                    digest.copy_from_slice(&compute_hash(self, fw_size)?.finalize());
+                    if !(auth_check && second_signature_check(&digest, &stored_signature)) {
+                        return Err(RustbootError::FwAuthFailed);
                     }
-                    Ok(true)
-                } else {
-                    Err(RustbootError::Unreachable) // technically should be unreachable
                 }
+                computed_hash
             }
-            #[cfg(feature = "ed25519")]
-            HDR_IMG_TYPE_AUTH => todo!(),
-            _ => todo!(),
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        if auth_check.eq(&true) {
+            match self.part_desc.get_mut() {
+                Some(val) => {
+                    val.sha_hash = computed_hash;
+                    val.signature_ok = true;
+                }
+                None => return Err(RustbootError::__Nonexhaustive),
+            }
+            Ok(true)
+        } else {
+            Err(RustbootError::Unreachable) // technically should be unreachable
+        }
+    }
+
+    /// Checks the firmware's CRC against the one in its header (see [`crate::crc`]) i.e. a
+    /// corrupted image is rejected before it's hashed. Skipped if the image's digest has already
+    /// been verified or if its header holds no CRC.
+    fn check_crc(
+        &self,
+        fw_size: usize,
+        compute_crc: impl FnOnce(&Self, usize) -> Result<u32>,
+    ) -> Result<()> {
+        let part_desc = self.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+        if part_desc.sha_ok {
+            return Ok(());
+        }
+        match stored_crc32(image_header(self)?) {
+            Some(stored) if stored != compute_crc(self, fw_size)? => {
+                Err(RustbootError::CrcCheckFailed)
+            }
+            _ => Ok(()),
         }
     }
+
+    #[cfg(feature = "suit")]
+    fn suit_envelope(&self) -> Option<SuitEnvelope<'static>> {
+        self.part_desc.get()?.header().and_then(envelope_at)
+    }
+
+    /// Checks the firmware's size and digest against the SUIT manifest and, if `authenticate` is
+    /// set, the manifest's signature.
+    ///
+    /// *Note: the firmware is addressed as one contiguous slice i.e. SUIT images must be stored in
+    /// memory-mapped flash.*
+    #[cfg(feature = "suit")]
+    fn check_suit(&mut self, envelope: SuitEnvelope, authenticate: bool) -> Result<bool> {
+        let part_desc = self.part_desc.get_mut().ok_or(RustbootError::FieldNotSet)?;
+        let manifest = match authenticate {
+            true => envelope.verify()?,
+            false => envelope.manifest()?,
+        };
+        let firmware = unsafe { core::slice::from_raw_parts(part_desc.fw_base, part_desc.fw_size) };
+        manifest.verify_payload(firmware)?;
+        part_desc.sha_hash = Some(manifest.image_digest()?.as_ptr());
+        part_desc.sha_ok = true;
+        part_desc.signature_ok |= authenticate;
+        Ok(true)
+    }
+
+    #[cfg(feature = "mcuboot")]
+    fn mcuboot_image(&self) -> Option<Result<McubootImage<'static>>> {
+        let part_desc = self.part_desc.get()?;
+        mcuboot_image_at(part_desc.hdr?, part_desc.hdr_offset)
+    }
+
+    /// Checks an MCUboot image's digest and, if `authenticate` is set, its signature.
+    ///
+    /// *Note: as with SUIT images, the image is addressed as one contiguous slice i.e. MCUboot
+    /// images must be stored in memory-mapped flash.*
+    #[cfg(feature = "mcuboot")]
+    fn check_mcuboot(&mut self, image: McubootImage, authenticate: bool) -> Result<bool> {
+        let part_desc = self.part_desc.get_mut().ok_or(RustbootError::FieldNotSet)?;
+        image.verify(authenticate)?;
+        part_desc.sha_hash = Some(image.digest()?.as_ptr());
+        part_desc.sha_ok = true;
+        part_desc.signature_ok |= authenticate;
+        Ok(true)
+    }
 }
 
 /// Computes the hash of an image contained in a partition. This function returns
@@ -660,11 +1119,17 @@ impl<'a, Part: ValidPart + Swappable, State: TypeState> RustbootImage<'a, Part,
 ///
 /// To get the actual hash output, we call the hasher's finalize mthod.
 ///
+/// The digest covers the header fields preceding the `SHA_TLV` field, the firmware and the
+/// header's vendor TLVs (see [`vendor_tlvs`]), in that order.
+///
 /// *Note - `offset` represents an offset (the `SHA_TLV` field) from the start of header
 /// (includes type and length fields).*
+///
+/// `progress` is called after every sector's worth of firmware is hashed.
 fn compute_img_hash<Part, State, D, const N: usize>(
     img: &RustbootImage<Part, State>,
     fw_size: usize,
+    progress: &impl Progress,
 ) -> Result<D>
 where
     Part: ValidPart + Swappable,
@@ -672,10 +1137,11 @@ where
     D: Digest,
 {
     let mut size = fw_size;
-    let part_desc = img.part_desc.get().unwrap();
-    if let Some(val) = part_desc.hdr {
-        let part = (unsafe { (val as *const [u8; PARTITION_SIZE]).as_ref() })
-            .ok_or(RustbootError::NullValue)?;
+    let part_desc = img.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+    if let Some(val) = part_desc.header() {
+        // the image i.e. from its header to the partition's end
+        let part =
+            unsafe { core::slice::from_raw_parts(val, PARTITION_SIZE - part_desc.hdr_offset) };
         match N {
             #[cfg(feature = "sha256")]
             SHA256_DIGEST_SIZE => {
@@ -686,31 +1152,364 @@ where
                 while offset > 0 {
                     if offset < block_size {
                         block_size = offset;
-                        hasher.update(&part[..block_size]);
+                        hasher.update(part.get(..block_size).ok_or(RustbootError::InvalidValue)?);
                         break;
                     }
-                    hasher.update(&part[..block_size]);
+                    hasher.update(part.get(..block_size).ok_or(RustbootError::InvalidValue)?);
                     offset -= block_size;
                 }
+                // a compressed image is hashed as it's decompressed
+                #[cfg(feature = "compression")]
+                if part_desc.compressed_len()?.is_some() {
+                    let mut hashed = 0;
+                    part_desc.decompress_with(
+                        |offset, buf| {
+                            let src = part
+                                .get(offset..offset + buf.len())
+                                .ok_or(RustbootError::InvalidFirmwareSize)?;
+                            buf.copy_from_slice(src);
+                            Ok(())
+                        },
+                        |firmware| {
+                            hasher.update(firmware);
+                            hashed += firmware.len();
+                            progress.on_progress(Phase::Verify, hashed, fw_size);
+                            Ok(())
+                        },
+                    )?;
+                    hasher.update(get_vendor_tlvs(img)?.as_bytes());
+                    return Ok(hasher);
+                }
                 offset = 0x0; // reset offset to use as `fw_base`.
                 block_size = 0x40; // reset block_size
                 while size > 0 {
                     if size < block_size {
                         block_size = size;
                     }
+                    let start = IMAGE_HEADER_SIZE + offset;
                     hasher.update(
-                        &part[IMAGE_HEADER_SIZE + offset..IMAGE_HEADER_SIZE + offset + block_size],
+                        part.get(start..start + block_size)
+                            .ok_or(RustbootError::InvalidFirmwareSize)?,
                     );
                     offset += block_size;
                     size -= block_size;
+                    if offset % SECTOR_SIZE == 0 || size == 0 {
+                        progress.on_progress(Phase::Verify, offset, fw_size);
+                    }
                 }
+                hasher.update(get_vendor_tlvs(img)?.as_bytes());
                 Ok(hasher)
             }
-            #[cfg(feature = "sha384")]
-            SHA384_DIGEST_SIZE => todo!(),
-            _ => todo!(),
+            _ => Err(RustbootError::BadHashValue),
         }
     } else {
         return Err(RustbootError::InvalidValue);
     }
 }
+
+/// Computes the CRC of the firmware contained in a partition.
+fn compute_img_crc<Part, State>(img: &RustbootImage<Part, State>, fw_size: usize) -> Result<u32>
+where
+    Part: ValidPart + Swappable,
+    State: TypeState,
+{
+    let part_desc = img.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+    let mut crc = Crc32::new();
+    #[cfg(feature = "compression")]
+    if part_desc.compressed_len()?.is_some() {
+        let hdr = part_desc.header().ok_or(RustbootError::InvalidValue)?;
+        part_desc.decompress_with(
+            |offset, buf| {
+                let src = unsafe { core::slice::from_raw_parts(hdr.add(offset), buf.len()) };
+                buf.copy_from_slice(src);
+                Ok(())
+            },
+            |firmware| {
+                crc.update(firmware);
+                Ok(())
+            },
+        )?;
+        return Ok(crc.finalize());
+    }
+    let firmware = unsafe { core::slice::from_raw_parts(part_desc.fw_base, fw_size) };
+    crc.update(firmware);
+    Ok(crc.finalize())
+}
+
+/// Same as [`compute_img_crc`], except that the firmware is read via [`FlashApi::flash_read`] into a
+/// `C`-byte buffer, one chunk at a time.
+fn compute_img_crc_chunked<Part, State, const C: usize>(
+    img: &RustbootImage<Part, State>,
+    fw_size: usize,
+    updater: impl FlashApi,
+) -> Result<u32>
+where
+    Part: ValidPart + Swappable,
+    State: TypeState,
+{
+    if C == 0 {
+        return Err(RustbootError::InvalidValue);
+    }
+    let part_desc = img.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+    let mut buf = [0u8; C];
+    let mut crc = Crc32::new();
+    #[cfg(feature = "compression")]
+    if part_desc.compressed_len()?.is_some() {
+        part_desc.decompress(updater, |firmware| {
+            crc.update(firmware);
+            Ok(())
+        })?;
+        return Ok(crc.finalize());
+    }
+    let (mut offset, mut len) = (part_desc.hdr_offset + IMAGE_HEADER_SIZE, fw_size);
+    while len > 0 {
+        let chunk = buf
+            .get_mut(..len.min(C))
+            .ok_or(RustbootError::BufferTooSmall)?;
+        updater.flash_read(part_desc, offset, chunk)?;
+        crc.update(chunk);
+        offset += chunk.len();
+        len -= chunk.len();
+    }
+    Ok(crc.finalize())
+}
+
+/// Computes the hash of an image contained in a partition, same as [`compute_img_hash`], except
+/// that the image is read via [`FlashApi::flash_read`] into a `C`-byte buffer, one chunk at a time.
+fn compute_img_hash_chunked<Part, State, D, const N: usize, const C: usize>(
+    img: &RustbootImage<Part, State>,
+    fw_size: usize,
+    updater: impl FlashApi,
+) -> Result<D>
+where
+    Part: ValidPart + Swappable,
+    State: TypeState,
+    D: Digest,
+{
+    if C == 0 {
+        return Err(RustbootError::InvalidValue);
+    }
+    let part_desc = img.part_desc.get().ok_or(RustbootError::FieldNotSet)?;
+    match N {
+        #[cfg(feature = "sha256")]
+        SHA256_DIGEST_SIZE => {
+            let mut buf = [0u8; C];
+            let mut hasher = D::new();
+            // header fields preceding the `SHA_TLV` field
+            let hdr_len = get_tlv_offset(img, Tags::Digest256)?;
+            let start = part_desc.hdr_offset;
+            hash_flash_range(updater, part_desc, start, hdr_len, &mut buf, &mut hasher)?;
+            #[cfg(feature = "compression")]
+            if part_desc.compressed_len()?.is_some() {
+                part_desc.decompress(updater, |firmware| {
+                    hasher.update(firmware);
+                    Ok(())
+                })?;
+                hasher.update(get_vendor_tlvs(img)?.as_bytes());
+                return Ok(hasher);
+            }
+            hash_flash_range(
+                updater,
+                part_desc,
+                start + IMAGE_HEADER_SIZE,
+                fw_size,
+                &mut buf,
+                &mut hasher,
+            )?;
+            hasher.update(get_vendor_tlvs(img)?.as_bytes());
+            Ok(hasher)
+        }
+        _ => Err(RustbootError::BadHashValue),
+    }
+}
+
+/// Feeds `len` bytes of a partition, starting at `offset`, to `hasher` - one `buf`-sized chunk at a time.
+fn hash_flash_range<Part: ValidPart, D: Digest>(
+    updater: impl FlashApi,
+    part_desc: &PartDescriptor<Part>,
+    mut offset: usize,
+    mut len: usize,
+    buf: &mut [u8],
+    hasher: &mut D,
+) -> Result<()> {
+    while len > 0 {
+        let chunk = buf
+            .get_mut(..len.min(buf.len()))
+            .ok_or(RustbootError::BufferTooSmall)?;
+        updater.flash_read(part_desc, offset, chunk)?;
+        hasher.update(&*chunk);
+        offset += chunk.len();
+        len -= chunk.len();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::boxed::Box;
+    use std::vec;
+
+    /// a golden image i.e. `rustBoot/test-vectors`, see `crate::image`
+    const VALID: &[u8] = include_bytes!("../../test-vectors/valid.bin");
+    const FW_SIZE: usize = 256;
+    /// the image's alignment padding, see [`HEADER_SEARCH_WINDOW`]
+    const PADDING: usize = 0x80;
+
+    /// Memory-mapped flash i.e. the default [`FlashApi::flash_read`].
+    #[derive(Clone, Copy)]
+    struct Mapped;
+
+    impl FlashApi for Mapped {
+        fn flash_trailer_write<Part: ValidPart + Swappable>(
+            self,
+            _: &PartDescriptor<Part>,
+            _: usize,
+            _: &[u8],
+        ) -> Result<()> {
+            Err(RustbootError::FlashWriteFailed)
+        }
+        fn flash_write<Part: ValidPart>(
+            self,
+            _: &PartDescriptor<Part>,
+            _: usize,
+            _: &[u8],
+        ) -> Result<()> {
+            Err(RustbootError::FlashWriteFailed)
+        }
+        fn flash_erase<Part: ValidPart>(
+            self,
+            _: &PartDescriptor<Part>,
+            _: usize,
+            _: usize,
+        ) -> Result<()> {
+            Err(RustbootError::FlashEraseFailed)
+        }
+        fn flash_init() {}
+        fn flash_lock() {}
+        fn flash_unlock() {}
+    }
+
+    /// Returns a descriptor of a partition holding `image`, `padding` bytes past its start.
+    fn partition(image: &[u8], padding: usize) -> OnceCell<PartDescriptor<Boot>> {
+        let mut part = vec![0xff; PARTITION_SIZE];
+        part[padding..padding + image.len()].copy_from_slice(image);
+        let part: &'static [u8] = Box::leak(part.into_boxed_slice());
+        descriptor(Some(part.as_ptr()), padding)
+    }
+
+    fn descriptor(hdr: Option<*const u8>, padding: usize) -> OnceCell<PartDescriptor<Boot>> {
+        OnceCell::from(PartDescriptor {
+            hdr,
+            hdr_offset: padding,
+            fw_base: hdr.map_or(core::ptr::null(), |hdr| {
+                hdr.wrapping_add(padding + IMAGE_HEADER_SIZE)
+            }),
+            sha_hash: None,
+            trailer: None,
+            fw_size: FW_SIZE,
+            hdr_ok: true,
+            signature_ok: false,
+            sha_ok: false,
+            part: Boot,
+        })
+    }
+
+    /// Checks that hashing (and CRC-ing) the image `C` bytes at a time matches the one-shot
+    /// digest (and CRC).
+    fn check_chunked<const C: usize>(img: &RustbootImage<Boot, StateNew>) {
+        let digest = compute_img_hash::<_, _, Sha256, SHA256_DIGEST_SIZE>(img, FW_SIZE, &())
+            .unwrap()
+            .finalize();
+        let chunked =
+            compute_img_hash_chunked::<_, _, Sha256, SHA256_DIGEST_SIZE, C>(img, FW_SIZE, Mapped)
+                .unwrap()
+                .finalize();
+        assert_eq!(chunked, digest, "{}-byte chunks", C);
+        assert_eq!(
+            compute_img_crc_chunked::<_, _, C>(img, FW_SIZE, Mapped),
+            compute_img_crc(img, FW_SIZE),
+            "{}-byte chunks",
+            C
+        );
+    }
+
+    #[test]
+    fn chunked_digests() {
+        for padding in [0, PADDING] {
+            let mut desc = partition(VALID, padding);
+            let mut img = RustbootImage {
+                part_desc: &mut desc,
+                state: Some(StateNew),
+            };
+            // chunks that divide the image's header fields and firmware, and ones that don't
+            check_chunked::<1>(&img);
+            check_chunked::<7>(&img);
+            check_chunked::<64>(&img);
+            check_chunked::<100>(&img);
+            check_chunked::<FW_SIZE>(&img);
+            check_chunked::<4096>(&img);
+            assert_eq!(
+                img.verify_integrity_chunked::<SHA256_DIGEST_SIZE, 100>(Mapped),
+                Ok(true)
+            );
+        }
+    }
+
+    #[test]
+    fn chunked_digest_of_corrupted_image() {
+        let mut image = VALID.to_vec();
+        image[IMAGE_HEADER_SIZE + 10] ^= 0x01;
+        let mut desc = partition(&image, 0);
+        let mut img = RustbootImage {
+            part_desc: &mut desc,
+            state: Some(StateNew),
+        };
+        assert!(img
+            .verify_integrity_chunked::<SHA256_DIGEST_SIZE, 100>(Mapped)
+            .is_err());
+    }
+
+    #[test]
+    fn partition_without_header() {
+        let mut desc = descriptor(None, 0);
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            Mapped.flash_read(desc.get().unwrap(), 0, &mut buf),
+            Err(RustbootError::FieldNotSet)
+        );
+        let mut img = RustbootImage {
+            part_desc: &mut desc,
+            state: Some(StateNew),
+        };
+        assert_eq!(
+            compute_img_crc_chunked::<_, _, 64>(&img, FW_SIZE, Mapped),
+            Err(RustbootError::FieldNotSet)
+        );
+        assert!(img
+            .verify_integrity_chunked::<SHA256_DIGEST_SIZE, 64>(Mapped)
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mcuboot")]
+    fn mcuboot_image_past_partition_end() {
+        // a padded image whose TLV info starts 2 bytes before the partition's end
+        let mut header = vec![0u8; IMAGE_HEADER_SIZE];
+        header[..4].copy_from_slice(&crate::image::mcuboot::MCUBOOT_IMAGE_MAGIC.to_le_bytes());
+        header[8..10].copy_from_slice(&(IMAGE_HEADER_SIZE as u16).to_le_bytes());
+        let size = PARTITION_SIZE - PADDING - IMAGE_HEADER_SIZE - 2;
+        header[12..16].copy_from_slice(&(size as u32).to_le_bytes());
+        let desc = partition(&header, PADDING);
+        let part = desc.get().unwrap().hdr.unwrap();
+
+        assert_eq!(
+            mcuboot_image_at(part, PADDING).map(|image| image.map(|image| image.size())),
+            Some(Err(RustbootError::InvalidFirmwareSize))
+        );
+        assert_eq!(
+            image_size(part as usize, PADDING),
+            Err(RustbootError::InvalidFirmwareSize)
+        );
+    }
+}
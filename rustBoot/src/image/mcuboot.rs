@@ -0,0 +1,403 @@
+//! MCUboot-format images i.e. images signed by MCUboot's `imgtool`, accepted in place of
+//! rustBoot images to allow a gradual migration from MCUboot.
+//!
+//! ```text
+//! +----------------------+ <- partition start
+//! | image_header         |    32 bytes, padded to `ih_hdr_size`
+//! +----------------------+
+//! | firmware             |    `ih_img_size` bytes
+//! +----------------------+
+//! | protected TLVs       |    optional, `ih_protect_tlv_size` bytes
+//! +----------------------+
+//! | TLVs                 |    SHA256 digest, ECDSA signature etc.
+//! +----------------------+
+//! ```
+//!
+//! The digest covers the header, the firmware and the protected TLVs. Only `SHA-256` digests and
+//! `ECDSA-P256` signatures are supported and the signature is checked against rustBoot's
+//! embedded public key (the key whose digest is in the `KEYHASH` TLV isn't looked up).
+//!
+//! rustBoot boots the firmware at [`IMAGE_HEADER_SIZE`] from the start of a partition, so the
+//! header must be padded to exactly that size i.e. `imgtool sign --header-size 0x100
+//! --pad-header`. Encrypted, RAM-load and non-bootable images aren't supported. Partition states
+//! (and swap progress) are kept in rustBoot's partition trailers, MCUboot's image trailer isn't
+//! used.
+
+use core::convert::TryInto;
+
+use crate::crypto::signatures::{verify_ecc256_signature, HDR_IMG_TYPE_AUTH};
+use crate::rbconstants::{ECC_SIGNATURE_SIZE, IMAGE_HEADER_SIZE, SHA256_DIGEST_SIZE};
+use crate::{Result, RustbootError};
+
+use p256::ecdsa::signature::digest::Digest;
+use sha2::Sha256;
+
+/// `ih_magic`
+pub const MCUBOOT_IMAGE_MAGIC: u32 = 0x96f3_b83d;
+/// Size of MCUboot's `image_header` (without padding).
+const HEADER_LEN: usize = 32;
+const TLV_INFO_MAGIC: u16 = 0x6907;
+const TLV_PROT_INFO_MAGIC: u16 = 0x6908;
+/// Size of a TLV area's info header and of a TLV's type and length fields.
+const TLV_INFO_LEN: usize = 4;
+const TLV_SHA256: u16 = 0x10;
+const TLV_ECDSA_SIG: u16 = 0x22;
+
+/// `ih_flags` of images rustBoot can't boot i.e. `IMAGE_F_ENCRYPTED_AES128`,
+/// `IMAGE_F_ENCRYPTED_AES256`, `IMAGE_F_NON_BOOTABLE` and `IMAGE_F_RAM_LOAD`.
+const UNSUPPORTED_FLAGS: u32 = 0x04 | 0x08 | 0x10 | 0x20;
+
+/// An MCUboot image, with a parsed header and TLV area. See [`Self::verify`].
+#[derive(Debug, Clone, Copy)]
+pub struct McubootImage<'a> {
+    /// the header, firmware and protected TLVs i.e. what the digest covers
+    hashed: &'a [u8],
+    /// the (unprotected) TLVs, without the TLV area's info header
+    tlvs: &'a [u8],
+    size: usize,
+    version: u32,
+}
+
+impl<'a> McubootImage<'a> {
+    /// Checks for MCUboot's `ih_magic` at the start of `bytes`.
+    pub fn is_mcuboot(bytes: &[u8]) -> bool {
+        bytes.get(..4) == Some(MCUBOOT_IMAGE_MAGIC.to_le_bytes().as_slice())
+    }
+
+    /// Parses the image at the start of `bytes` i.e. its header and TLV areas. The digest and
+    /// signature aren't checked, see [`Self::verify`].
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let header = bytes.get(..HEADER_LEN).ok_or(RustbootError::InvalidImage)?;
+        // `header` is `HEADER_LEN` bytes long i.e. its fields are always in bounds
+        let u16_at = |offset: usize| {
+            header
+                .get(offset..offset + 2)
+                .and_then(|field| field.try_into().ok())
+                .map_or(0, u16::from_le_bytes)
+        };
+        let u32_at = |offset: usize| {
+            header
+                .get(offset..offset + 4)
+                .and_then(|field| field.try_into().ok())
+                .map_or(0, u32::from_le_bytes)
+        };
+
+        let (hdr_size, protect_tlv_size) = (u16_at(8) as usize, u16_at(10) as usize);
+        let (img_size, flags) = (u32_at(12) as usize, u32_at(16));
+        if !Self::is_mcuboot(header)
+            || hdr_size != IMAGE_HEADER_SIZE
+            || flags & UNSUPPORTED_FLAGS != 0
+        {
+            return Err(RustbootError::InvalidImage);
+        }
+        let tlv_area = hdr_size
+            .checked_add(img_size)
+            .ok_or(RustbootError::InvalidFirmwareSize)?;
+        if protect_tlv_size != 0 {
+            let (magic, len) = tlv_info(bytes, tlv_area)?;
+            if magic != TLV_PROT_INFO_MAGIC || len != protect_tlv_size {
+                return Err(RustbootError::InvalidImage);
+            }
+        }
+        let hashed = tlv_area
+            .checked_add(protect_tlv_size)
+            .and_then(|len| bytes.get(..len))
+            .ok_or(RustbootError::InvalidFirmwareSize)?;
+        let (magic, len) = tlv_info(bytes, hashed.len())?;
+        if magic != TLV_INFO_MAGIC || len < TLV_INFO_LEN {
+            return Err(RustbootError::InvalidImage);
+        }
+        let tlvs = bytes
+            .get(hashed.len() + TLV_INFO_LEN..hashed.len() + len)
+            .ok_or(RustbootError::InvalidFirmwareSize)?;
+
+        // `ih_ver` i.e. major.minor.revision, the build number is ignored
+        let [major, minor] = u16_at(20).to_le_bytes();
+        let version = (major as u32) << 24 | (minor as u32) << 16 | u16_at(22) as u32;
+        Ok(McubootImage {
+            hashed,
+            tlvs,
+            size: hashed.len() + len - hdr_size,
+            version,
+        })
+    }
+
+    /// The firmware's version i.e. `major << 24 | minor << 16 | revision`. The build number isn't
+    /// part of it, the same as MCUboot's downgrade prevention.
+    pub fn get_firmware_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Number of bytes following the (padded) header i.e. the firmware and its TLV areas. This is
+    /// what has to be copied, to move the image to another partition.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The image's digest, as stored in its `SHA256` TLV.
+    pub fn digest(&self) -> Result<&'a [u8]> {
+        match self.find_tlv(TLV_SHA256)? {
+            digest if digest.len() == SHA256_DIGEST_SIZE => Ok(digest),
+            _ => Err(RustbootError::BadHashValue),
+        }
+    }
+
+    /// Checks the image's digest. If `authenticate` is set, checks its signature too, against the
+    /// embedded public key.
+    pub fn verify(&self, authenticate: bool) -> Result<()> {
+        let stored_hash = self.digest()?;
+        let mut hasher = Sha256::new();
+        hasher.update(self.hashed);
+        if hasher.clone().finalize().as_slice() != stored_hash {
+            return Err(RustbootError::IntegrityCheckFailed);
+        }
+        if authenticate {
+            let mut signature = [0u8; ECC_SIGNATURE_SIZE];
+            der_to_fixed(self.find_tlv(TLV_ECDSA_SIG)?, &mut signature)?;
+            if !verify_ecc256_signature::<Sha256, HDR_IMG_TYPE_AUTH>(hasher, &signature)? {
+                return Err(RustbootError::FwAuthFailed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the first (unprotected) TLV of type `tlv_type`.
+    fn find_tlv(&self, tlv_type: u16) -> Result<&'a [u8]> {
+        let mut tlvs = self.tlvs;
+        while let Some((typ, len)) = type_len(tlvs) {
+            let value = tlvs
+                .get(TLV_INFO_LEN..TLV_INFO_LEN + len)
+                .ok_or(RustbootError::InvalidHdrFieldLength)?;
+            if typ == tlv_type {
+                return Ok(value);
+            }
+            tlvs = tlvs
+                .get(TLV_INFO_LEN + len..)
+                .ok_or(RustbootError::InvalidHdrFieldLength)?;
+        }
+        Err(RustbootError::TLVNotFound)
+    }
+}
+
+/// Returns the magic and total length of the TLV area at `offset` in `bytes`.
+fn tlv_info(bytes: &[u8], offset: usize) -> Result<(u16, usize)> {
+    bytes
+        .get(offset..)
+        .and_then(type_len)
+        .ok_or(RustbootError::InvalidFirmwareSize)
+}
+
+/// Returns the type (or magic) and length at the start of a TLV (or TLV area), `None` if there
+/// are fewer than [`TLV_INFO_LEN`] bytes.
+fn type_len(tlv: &[u8]) -> Option<(u16, usize)> {
+    match *tlv {
+        [t0, t1, l0, l1, ..] => Some((
+            u16::from_le_bytes([t0, t1]),
+            u16::from_le_bytes([l0, l1]) as usize,
+        )),
+        _ => None,
+    }
+}
+
+/// Converts a DER-encoded ECDSA signature (i.e. `SEQUENCE { r INTEGER, s INTEGER }`, as stored
+/// by `imgtool`) to its fixed-size encoding i.e. `r || s`.
+fn der_to_fixed(der: &[u8], signature: &mut [u8; ECC_SIGNATURE_SIZE]) -> Result<()> {
+    let (sequence, rest) = der_element(der, 0x30)?;
+    let (r, sequence) = der_element(sequence, 0x02)?;
+    let (s, sequence) = der_element(sequence, 0x02)?;
+    if !rest.is_empty() || !sequence.is_empty() {
+        return Err(RustbootError::BadSignature);
+    }
+    for (int, dst) in [r, s]
+        .iter()
+        .zip(signature.chunks_mut(ECC_SIGNATURE_SIZE / 2))
+    {
+        // integers are signed i.e. may have a leading zero byte
+        let start = int.iter().position(|b| *b != 0).unwrap_or(int.len());
+        let int = int.get(start..).unwrap_or_default();
+        if int.len() > dst.len() {
+            return Err(RustbootError::BadSignature);
+        }
+        let (pad, value) = dst.split_at_mut(dst.len() - int.len());
+        pad.fill(0);
+        value.copy_from_slice(int);
+    }
+    Ok(())
+}
+
+/// Splits a DER element with the given `tag` (and a short-form length) off the start of `der`.
+fn der_element(der: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    match der {
+        [t, len, rest @ ..] if *t == tag && *len < 0x80 && *len as usize <= rest.len() => {
+            Ok(rest.split_at(*len as usize))
+        }
+        _ => Err(RustbootError::BadSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::DigestSigner, Signature, SigningKey};
+    use std::vec::Vec;
+
+    /// the signing key matching the embedded public key i.e. `boards/sign_images/keygen/ecc256.der`
+    const SIGNING_KEY: [u8; 32] = [
+        0x53, 0xce, 0x7e, 0x5d, 0x40, 0xa8, 0xbe, 0xca, 0xe3, 0xdf, 0x7f, 0x9f, 0xb3, 0x07, 0x1a,
+        0x93, 0xf9, 0x52, 0x47, 0x30, 0xcc, 0x30, 0xe6, 0x07, 0x1c, 0xe7, 0xfc, 0x90, 0x7d, 0x5e,
+        0x58, 0xa0,
+    ];
+
+    /// DER-encodes a fixed-size signature, the way `imgtool` stores it.
+    fn der(signature: &[u8]) -> Vec<u8> {
+        let mut seq = Vec::new();
+        for int in signature.chunks(32) {
+            let start = int.iter().position(|b| *b != 0).unwrap_or(31);
+            let mut int = int[start..].to_vec();
+            if int[0] & 0x80 != 0 {
+                int.insert(0, 0);
+            }
+            seq.extend_from_slice(&[0x02, int.len() as u8]);
+            seq.extend_from_slice(&int);
+        }
+        let mut der = std::vec![0x30, seq.len() as u8];
+        der.extend_from_slice(&seq);
+        der
+    }
+
+    fn tlv(area: &mut Vec<u8>, tlv_type: u16, value: &[u8]) {
+        area.extend_from_slice(&tlv_type.to_le_bytes());
+        area.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        area.extend_from_slice(value);
+    }
+
+    /// Returns an image for `fw`, as signed by `imgtool sign --header-size 0x100 --pad-header
+    /// --version 1.2.3+4`, with a protected TLV.
+    fn mcuboot_image(fw: &[u8]) -> Vec<u8> {
+        let protected = [0x50, 0x00, 0x04, 0x00, 0xaa, 0xbb, 0xcc, 0xdd];
+        let mut img = Vec::new();
+        img.extend_from_slice(&MCUBOOT_IMAGE_MAGIC.to_le_bytes());
+        img.extend_from_slice(&0u32.to_le_bytes()); // ih_load_addr
+        img.extend_from_slice(&(IMAGE_HEADER_SIZE as u16).to_le_bytes());
+        img.extend_from_slice(&((TLV_INFO_LEN + protected.len()) as u16).to_le_bytes());
+        img.extend_from_slice(&(fw.len() as u32).to_le_bytes());
+        img.extend_from_slice(&0u32.to_le_bytes()); // ih_flags
+        img.extend_from_slice(&[1, 2, 3, 0, 4, 0, 0, 0]); // ih_ver
+        img.resize(IMAGE_HEADER_SIZE, 0);
+        img.extend_from_slice(fw);
+        img.extend_from_slice(&TLV_PROT_INFO_MAGIC.to_le_bytes());
+        img.extend_from_slice(&((TLV_INFO_LEN + protected.len()) as u16).to_le_bytes());
+        img.extend_from_slice(&protected);
+
+        let hasher = Sha256::new().chain(&img);
+        let sk = SigningKey::from_bytes(&SIGNING_KEY).unwrap();
+        let signature: Signature = sk.sign_digest(hasher.clone());
+        let mut tlvs = Vec::new();
+        tlv(&mut tlvs, 0x01, &[0x55; 32]); // KEYHASH
+        tlv(&mut tlvs, TLV_SHA256, hasher.finalize().as_slice());
+        tlv(&mut tlvs, TLV_ECDSA_SIG, &der(signature.as_ref()));
+        img.extend_from_slice(&TLV_INFO_MAGIC.to_le_bytes());
+        img.extend_from_slice(&((TLV_INFO_LEN + tlvs.len()) as u16).to_le_bytes());
+        img.extend_from_slice(&tlvs);
+        img
+    }
+
+    #[test]
+    fn verify_mcuboot_image() {
+        let mut img = mcuboot_image(&[0xaa; 100]);
+        let staged = img.len();
+        // the rest of the partition is erased
+        img.resize(staged + 0x100, 0xff);
+
+        assert!(McubootImage::is_mcuboot(&img));
+        let parsed = McubootImage::parse(&img).unwrap();
+        assert_eq!(parsed.get_firmware_version(), 0x0102_0003);
+        assert_eq!(parsed.size(), staged - IMAGE_HEADER_SIZE);
+        parsed.verify(false).unwrap();
+        parsed.verify(true).unwrap();
+    }
+
+    #[test]
+    fn tampered_mcuboot_image() {
+        let mut img = mcuboot_image(&[0xaa; 100]);
+        img[IMAGE_HEADER_SIZE] = 0x00;
+        let parsed = McubootImage::parse(&img).unwrap();
+        assert_eq!(
+            parsed.verify(false),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+
+        // the protected TLVs are covered by the digest
+        let mut img = mcuboot_image(&[0xaa; 100]);
+        img[IMAGE_HEADER_SIZE + 100 + 8] = 0x00;
+        let parsed = McubootImage::parse(&img).unwrap();
+        assert_eq!(
+            parsed.verify(false),
+            Err(RustbootError::IntegrityCheckFailed)
+        );
+
+        // a bad signature
+        let mut img = mcuboot_image(&[0xaa; 100]);
+        let last = img.len() - 1;
+        img[last] ^= 0x01;
+        let parsed = McubootImage::parse(&img).unwrap();
+        parsed.verify(false).unwrap();
+        assert!(parsed.verify(true).is_err());
+    }
+
+    #[test]
+    fn malformed_mcuboot_images() {
+        let img = mcuboot_image(&[0xaa; 100]);
+        // not an MCUboot image
+        let mut bad = img.clone();
+        bad[0] = 0x00;
+        assert_eq!(
+            McubootImage::parse(&bad).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+        // the header isn't padded to rustBoot's header size
+        let mut bad = img.clone();
+        bad[8..10].copy_from_slice(&0x200u16.to_le_bytes());
+        assert_eq!(
+            McubootImage::parse(&bad).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+        // encrypted
+        let mut bad = img.clone();
+        bad[16] = 0x04;
+        assert_eq!(
+            McubootImage::parse(&bad).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+        // truncated TLV area
+        assert_eq!(
+            McubootImage::parse(&img[..img.len() - 1]).unwrap_err(),
+            RustbootError::InvalidFirmwareSize
+        );
+        // firmware size runs past the image
+        let mut bad = img;
+        bad[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(McubootImage::parse(&bad).is_err());
+    }
+
+    #[test]
+    fn der_signatures() {
+        let mut fixed = [0u8; ECC_SIGNATURE_SIZE];
+        let mut signature = [0x11u8; ECC_SIGNATURE_SIZE];
+        signature[0] = 0x80; // r needs a leading zero
+        signature[32] = 0x00; // s is shorter than 32 bytes
+        der_to_fixed(&der(&signature), &mut fixed).unwrap();
+        assert_eq!(fixed, signature);
+
+        let mut bad = der(&signature);
+        bad.push(0x00);
+        assert_eq!(
+            der_to_fixed(&bad, &mut fixed),
+            Err(RustbootError::BadSignature)
+        );
+        assert_eq!(
+            der_to_fixed(&[0x30, 0x02, 0x02, 0x21], &mut fixed),
+            Err(RustbootError::BadSignature)
+        );
+    }
+}
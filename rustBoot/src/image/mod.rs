@@ -1,2 +1,86 @@
+pub mod companion;
+pub mod esp;
 pub mod image;
+#[cfg(feature = "mcuboot")]
+pub mod mcuboot;
 mod sealed;
+pub mod state;
+pub mod vectors;
+
+/// The golden images i.e. `rustBoot/test-vectors`, as generated by `cargo xtask gen-vectors`.
+/// They lock the image format - rustBoot must keep parsing and verifying (or rejecting) them.
+#[cfg(test)]
+mod tests {
+    use crate::chain::SignedImage;
+    use crate::parser::{board_id, vendor_tlvs};
+    use crate::rbconstants::IMAGE_HEADER_SIZE;
+    use crate::version::VersionPolicy;
+    use crate::RustbootError;
+
+    const VALID: &[u8] = include_bytes!("../../test-vectors/valid.bin");
+    const BAD_SIGNATURE: &[u8] = include_bytes!("../../test-vectors/bad-signature.bin");
+    const BAD_VERSION: &[u8] = include_bytes!("../../test-vectors/bad-version.bin");
+    const TRUNCATED_HEADER: &[u8] = include_bytes!("../../test-vectors/truncated-header.bin");
+    const WRONG_BOARD: &[u8] = include_bytes!("../../test-vectors/wrong-board.bin");
+
+    /// the golden images' board, version and firmware (a byte ramp)
+    const BOARD: [u8; 4] = board_id("golden");
+    const VERSION: u32 = 2;
+    const FW_SIZE: usize = 256;
+
+    fn board_of(image: &SignedImage) -> Option<[u8; 4]> {
+        vendor_tlvs(image.header()).unwrap().board_id().unwrap()
+    }
+
+    #[test]
+    fn golden_valid_image() {
+        // the fixed fields i.e. `magic` and the firmware's size
+        assert_eq!(&VALID[..4], b"RUST");
+        assert_eq!(&VALID[4..8], &(FW_SIZE as u32).to_le_bytes());
+        assert_eq!(VALID.len(), IMAGE_HEADER_SIZE + FW_SIZE);
+
+        let image = SignedImage::parse(VALID).unwrap();
+        assert_eq!(image.get_firmware_version(), Ok(VERSION));
+        assert_eq!(image.get_image_type(), Ok(0x0201));
+        assert!(image
+            .firmware()
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b == i as u8));
+        assert_eq!(board_of(&image), Some(BOARD));
+        assert_eq!(image.verify(), Ok(()));
+    }
+
+    #[test]
+    fn golden_bad_signature() {
+        let image = SignedImage::parse(BAD_SIGNATURE).unwrap();
+        assert_eq!(image.get_firmware_version(), Ok(VERSION));
+        assert_eq!(image.verify(), Err(RustbootError::FwAuthFailed));
+    }
+
+    #[test]
+    fn golden_bad_version() {
+        // validly signed, but a rollback from the valid image
+        let image = SignedImage::parse(BAD_VERSION).unwrap();
+        assert_eq!(image.verify(), Ok(()));
+        let version = image.get_firmware_version().unwrap();
+        assert_eq!(version, VERSION - 1);
+        assert!(!VersionPolicy::STRICT.permits(VERSION, version));
+    }
+
+    #[test]
+    fn golden_truncated_header() {
+        assert_eq!(
+            SignedImage::parse(TRUNCATED_HEADER).unwrap_err(),
+            RustbootError::InvalidImage
+        );
+    }
+
+    #[test]
+    fn golden_wrong_board() {
+        let image = SignedImage::parse(WRONG_BOARD).unwrap();
+        assert_eq!(image.verify(), Ok(()));
+        assert_eq!(board_of(&image), Some(board_id("golden-other")));
+        assert_ne!(board_of(&image), Some(BOARD));
+    }
+}
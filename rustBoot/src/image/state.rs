@@ -0,0 +1,558 @@
+//! Partition states and sector flags i.e. the update state machine, as stored in the boot and
+//! update partitions' trailers.
+//!
+//! ```text
+//!                                  end of partition ->  +
+//!  ... | retired | trial | sector flags | state | trailer magic   |
+//! ```
+//!
+//! With the `metadata-sector` feature, the trailer ends with the partition's metadata sector
+//! instead (see `constants::BOOT_TRAILER_ADDRESS`).
+//!
+//! A partition's state is a single byte. The update partition's sector flags are nibbles (two
+//! sectors per byte) that record the progress of an interruptible swap, so an interrupted
+//! update is resumed on the next boot. The boot partition has no sector flags.
+//!
+//! The trial byte marks an update (and, once it's swapped in, the boot image) as a trial boot
+//! i.e. [`TRIAL_MARKER`], see `update_test`. The update partition's retired byte follows it,
+//! see [`Retired`].
+//!
+//! With the `state-journal` feature, the state byte is left blank and a partition's state is
+//! journaled instead i.e. kept in [`StateRecord`]s, in slots that follow the retired (or trial)
+//! byte.
+
+use super::image::PartId;
+use crate::crc::crc32;
+use crate::{Result, RustbootError};
+
+/// Marks a partition's image as a trial boot. An unconfirmed trial is reverted for good i.e. the
+/// restored image is confirmed rather than tested again.
+pub const TRIAL_MARKER: u8 = 0x54; // T
+
+/// The state of the boot or update partition. The swap partition has no state.
+///
+/// Legal transitions (see [`PartitionState::transition`]) are
+///
+/// | partition | from              | to         | when                                    |
+/// |-----------|-------------------|------------|-----------------------------------------|
+/// | update    | `New`             | `Updating` | an update is triggered                  |
+/// | boot      | `New` / `Success` | `Testing`  | an image has been swapped into boot     |
+/// | boot      | `New` / `Testing` | `Success`  | the booted image confirms the update    |
+///
+/// Setting a partition to its current state is a no-op. A swap erases both partitions'
+/// trailers, which puts them back in `New`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(Format))]
+pub enum PartitionState {
+    /// No update has been triggered and the boot image has not been staged.
+    New,
+    /// The update partition holds an update, which should replace the image in boot.
+    Updating,
+    /// The boot partition holds a freshly swapped image. If still present after a reboot,
+    /// the image failed to confirm itself and is rolled back.
+    Testing,
+    /// The boot image has been confirmed i.e. the update is complete.
+    Success,
+}
+
+impl PartitionState {
+    /// All partition states.
+    pub const ALL: [PartitionState; 4] = [
+        PartitionState::New,
+        PartitionState::Updating,
+        PartitionState::Testing,
+        PartitionState::Success,
+    ];
+
+    /// Decodes a trailer's state byte.
+    pub fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0xFF => Ok(PartitionState::New),
+            0x70 => Ok(PartitionState::Updating),
+            0x10 => Ok(PartitionState::Testing),
+            0x00 => Ok(PartitionState::Success),
+            _ => Err(RustbootError::InvalidState),
+        }
+    }
+
+    /// The state, as stored in the trailer.
+    pub const fn as_byte(self) -> u8 {
+        match self {
+            PartitionState::New => 0xFF,
+            PartitionState::Updating => 0x70,
+            PartitionState::Testing => 0x10,
+            PartitionState::Success => 0x00,
+        }
+    }
+
+    /// Checks that `part` may move from this state to `to`, returning the new state.
+    pub fn transition(self, part: PartId, to: PartitionState) -> Result<PartitionState> {
+        use PartitionState::*;
+        let legal = match (part, self, to) {
+            (PartId::PartSwap, _, _) => false,
+            (_, from, to) if from == to => true,
+            (PartId::PartUpdate, New, Updating) => true,
+            (PartId::PartBoot, New | Success, Testing) => true,
+            (PartId::PartBoot, New | Testing, Success) => true,
+            _ => false,
+        };
+        match legal {
+            true => Ok(to),
+            false => Err(RustbootError::InvalidStateTransition),
+        }
+    }
+}
+
+/// The progress of a sector through the swap. A sector moves through the flags in order and
+/// each step copies one sector (see [`SectorFlag::pending_copy`]).
+///
+/// | flag       | nibble | pending copy      |
+/// |------------|--------|-------------------|
+/// | `New`      | `0xF`  | update -> swap    |
+/// | `Swapping` | `0x7`  | boot -> update    |
+/// | `Backup`   | `0x3`  | swap -> boot      |
+/// | `Updated`  | `0x0`  | -                 |
+///
+/// Each step only clears bits, so a flag can be updated without erasing the trailer. The copies
+/// are those of the (default) sector swap, other swap policies assign their own steps to the
+/// flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(Format))]
+pub enum SectorFlag {
+    /// The sector has not been swapped yet.
+    New,
+    /// The update's sector has been copied to the swap partition.
+    Swapping,
+    /// The boot sector has been backed up in the update partition.
+    Backup,
+    /// The update's sector has been copied into the boot partition.
+    Updated,
+}
+
+impl SectorFlag {
+    /// All sector flags, in swap order.
+    pub const ALL: [SectorFlag; 4] = [
+        SectorFlag::New,
+        SectorFlag::Swapping,
+        SectorFlag::Backup,
+        SectorFlag::Updated,
+    ];
+
+    /// Decodes a sector's nibble. Only the low nibble of `nibble` is used.
+    pub fn from_nibble(nibble: u8) -> Result<Self> {
+        match nibble & 0x0F {
+            0x0F => Ok(SectorFlag::New),
+            0x07 => Ok(SectorFlag::Swapping),
+            0x03 => Ok(SectorFlag::Backup),
+            0x00 => Ok(SectorFlag::Updated),
+            _ => Err(RustbootError::InvalidSectFlag),
+        }
+    }
+
+    /// The flag, as stored in the trailer.
+    pub const fn as_nibble(self) -> u8 {
+        match self {
+            SectorFlag::New => 0x0F,
+            SectorFlag::Swapping => 0x07,
+            SectorFlag::Backup => 0x03,
+            SectorFlag::Updated => 0x00,
+        }
+    }
+
+    /// The flag that follows this one, once its pending copy is done.
+    pub const fn next(self) -> Option<SectorFlag> {
+        match self {
+            SectorFlag::New => Some(SectorFlag::Swapping),
+            SectorFlag::Swapping => Some(SectorFlag::Backup),
+            SectorFlag::Backup => Some(SectorFlag::Updated),
+            SectorFlag::Updated => None,
+        }
+    }
+
+    /// The sector copy (i.e. `(source, destination)`) that moves a sector on from this flag,
+    /// `None` once the sector has been swapped.
+    pub const fn pending_copy(self) -> Option<(PartId, PartId)> {
+        match self {
+            SectorFlag::New => Some((PartId::PartUpdate, PartId::PartSwap)),
+            SectorFlag::Swapping => Some((PartId::PartBoot, PartId::PartUpdate)),
+            SectorFlag::Backup => Some((PartId::PartSwap, PartId::PartBoot)),
+            SectorFlag::Updated => None,
+        }
+    }
+
+    /// Checks that a sector may move from this flag to `to` i.e. `to` is this flag or the next
+    /// one, returning the new flag.
+    pub fn transition(self, to: SectorFlag) -> Result<SectorFlag> {
+        match to == self || Some(to) == self.next() {
+            true => Ok(to),
+            false => Err(RustbootError::InvalidStateTransition),
+        }
+    }
+}
+
+/// The update partition's retired byte i.e. whether its image (the one an update replaced) has
+/// been retired, once the update was confirmed, and the number of boots counted since.
+///
+/// An erased byte isn't retired. Retiring the image clears the byte's top bit, each boot counted
+/// clears the next one, up to [`Retired::MAX_BOOTS`] boots. Bits are only ever cleared, so the
+/// byte is counted without erasing the trailer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(Format))]
+pub struct Retired(u8);
+
+impl Retired {
+    /// A freshly retired image i.e. no boots have been counted.
+    pub const MARKED: Retired = Retired(0x7F);
+    /// The most boots that can be counted.
+    pub const MAX_BOOTS: u8 = 7;
+
+    /// Decodes the retired byte, `None` if the image isn't retired.
+    pub const fn from_byte(byte: u8) -> Option<Self> {
+        match byte & 0x80 {
+            0 => Some(Retired(byte)),
+            _ => None,
+        }
+    }
+
+    /// The byte, as stored in the trailer.
+    pub const fn as_byte(self) -> u8 {
+        self.0
+    }
+
+    /// The number of boots counted since the image was retired.
+    pub const fn boots(self) -> u8 {
+        self.0.leading_zeros() as u8 - 1
+    }
+
+    /// Counts a boot i.e. clears the byte's highest set bit. Saturates at [`Retired::MAX_BOOTS`].
+    pub const fn counted(self) -> Retired {
+        match self.0 {
+            0 => self,
+            byte => Retired(byte & !(0x80 >> byte.leading_zeros())),
+        }
+    }
+}
+
+/// A partition's state, as journaled (see the `state-journal` feature) i.e. written to the next
+/// of a few slots along with a sequence number and a CRC, rather than over a single byte. A write
+/// that's torn (ex: by a reset) leaves a record that fails its CRC, so the partition keeps the
+/// state of the newest valid record i.e. the one it had before the write.
+///
+/// ```text
+///  | state | seq | 0x00 | 0x00 | crc32 of the first 4 bytes (LE) |
+/// ```
+///
+/// A slot can't be written again until the trailer is erased, so records are written to the
+/// journal's slots in turn, see [`StateRecord::next_slot`]. A blank journal is `New`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(Format))]
+pub struct StateRecord {
+    pub state: PartitionState,
+    /// the newest record has the highest sequence number
+    pub seq: u8,
+}
+
+impl StateRecord {
+    /// A record's length, in bytes.
+    pub const LEN: usize = 8;
+
+    /// The record, as stored in a slot.
+    pub fn to_bytes(&self) -> [u8; StateRecord::LEN] {
+        let (state, seq) = (self.state.as_byte(), self.seq);
+        let [c0, c1, c2, c3] = crc32(&[state, seq, 0, 0]).to_le_bytes();
+        [state, seq, 0, 0, c0, c1, c2, c3]
+    }
+
+    /// Decodes the record at the start of `slot`, `None` if there's none i.e. the slot is blank
+    /// or its write was torn.
+    pub fn from_bytes(slot: &[u8]) -> Option<Self> {
+        let (head, crc) = slot.get(..StateRecord::LEN)?.split_at(4);
+        // the zeroed bytes keep a blank slot (whose CRC happens to match) from passing for one
+        if head.get(2..) != Some(&[0, 0][..]) || crc != crc32(head).to_le_bytes() {
+            return None;
+        }
+        Some(StateRecord {
+            state: PartitionState::from_byte(*head.first()?).ok()?,
+            seq: *head.get(1)?,
+        })
+    }
+
+    /// Returns the newest valid record in a journal's `slots`, `None` if there's none.
+    pub fn newest<'a>(slots: impl IntoIterator<Item = &'a [u8]>) -> Option<StateRecord> {
+        slots
+            .into_iter()
+            .filter_map(StateRecord::from_bytes)
+            .max_by_key(|record| record.seq)
+    }
+
+    /// Returns the slot the next record goes in i.e. the one that follows the last slot written
+    /// to (torn or not). `None` if the journal is full.
+    pub fn next_slot<'a>(slots: impl IntoIterator<Item = &'a [u8]>) -> Option<usize> {
+        let (mut next, mut len) = (0, 0);
+        for (idx, slot) in slots.into_iter().enumerate() {
+            if slot.iter().any(|byte| *byte != 0xFF) {
+                next = idx + 1;
+            }
+            len = idx + 1;
+        }
+        (next < len).then_some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARTS: [PartId; 3] = [PartId::PartBoot, PartId::PartUpdate, PartId::PartSwap];
+
+    #[test]
+    fn state_bytes() {
+        for byte in 0..=u8::MAX {
+            match PartitionState::from_byte(byte) {
+                Ok(state) => assert_eq!(state.as_byte(), byte),
+                Err(e) => assert_eq!(e, RustbootError::InvalidState),
+            }
+        }
+        for state in PartitionState::ALL {
+            assert_eq!(PartitionState::from_byte(state.as_byte()), Ok(state));
+        }
+    }
+
+    #[test]
+    fn sector_flag_nibbles() {
+        let valid = (0..=0x0F)
+            .filter(|nibble| SectorFlag::from_nibble(*nibble).is_ok())
+            .count();
+        assert_eq!(valid, SectorFlag::ALL.len());
+        for byte in 0..=u8::MAX {
+            match SectorFlag::from_nibble(byte) {
+                Ok(flag) => assert_eq!(flag.as_nibble(), byte & 0x0F),
+                Err(e) => assert_eq!(e, RustbootError::InvalidSectFlag),
+            }
+        }
+    }
+
+    #[test]
+    fn partition_transitions() {
+        use PartitionState::*;
+        let legal = [
+            (PartId::PartUpdate, New, Updating),
+            (PartId::PartBoot, New, Testing),
+            (PartId::PartBoot, Success, Testing),
+            (PartId::PartBoot, New, Success),
+            (PartId::PartBoot, Testing, Success),
+        ];
+        for part in PARTS {
+            for from in PartitionState::ALL {
+                for to in PartitionState::ALL {
+                    let res = from.transition(part, to);
+                    if part == PartId::PartSwap {
+                        assert_eq!(res, Err(RustbootError::InvalidStateTransition));
+                    } else if from == to || legal.contains(&(part, from, to)) {
+                        assert_eq!(res, Ok(to));
+                    } else {
+                        assert_eq!(res, Err(RustbootError::InvalidStateTransition));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The states reachable from `New` (i.e. an erased trailer).
+    fn reachable(part: PartId) -> Vec<PartitionState> {
+        let mut seen = vec![PartitionState::New];
+        let mut i = 0;
+        while i < seen.len() {
+            for to in PartitionState::ALL {
+                if seen[i].transition(part, to).is_ok() && !seen.contains(&to) {
+                    seen.push(to);
+                }
+            }
+            i += 1;
+        }
+        seen
+    }
+
+    #[test]
+    fn reachable_states() {
+        use PartitionState::*;
+        assert_eq!(reachable(PartId::PartUpdate), [New, Updating]);
+        assert_eq!(reachable(PartId::PartBoot), [New, Testing, Success]);
+        assert_eq!(reachable(PartId::PartSwap), [New]);
+    }
+
+    #[test]
+    fn sector_flag_transitions() {
+        for (i, from) in SectorFlag::ALL.iter().enumerate() {
+            for (j, to) in SectorFlag::ALL.iter().enumerate() {
+                match j == i || j == i + 1 {
+                    true => assert_eq!(from.transition(*to), Ok(*to)),
+                    false => assert_eq!(
+                        from.transition(*to),
+                        Err(RustbootError::InvalidStateTransition)
+                    ),
+                }
+            }
+            // a flag only ever advances by clearing bits
+            if let Some(next) = from.next() {
+                assert_eq!(next.as_nibble() & !from.as_nibble(), 0);
+                assert!(from.pending_copy().is_some());
+            } else {
+                assert_eq!(from.pending_copy(), None);
+            }
+        }
+    }
+
+    /// Boot, update and swap partitions of `SECTORS` one-byte sectors, with the update's
+    /// sector flags. Sector `i` holds `0xB0 + i` in boot and `0xC0 + i` in update.
+    struct Flash {
+        parts: [Vec<u8>; 3],
+        flags: Vec<SectorFlag>,
+    }
+
+    const SECTORS: usize = 3;
+
+    impl Flash {
+        fn new() -> Self {
+            Flash {
+                parts: [
+                    (0..SECTORS as u8).map(|i| 0xB0 + i).collect(),
+                    (0..SECTORS as u8).map(|i| 0xC0 + i).collect(),
+                    vec![0xFF],
+                ],
+                flags: vec![SectorFlag::New; SECTORS],
+            }
+        }
+
+        fn part(&mut self, id: PartId) -> &mut Vec<u8> {
+            &mut self.parts[PARTS.iter().position(|p| *p == id).unwrap()]
+        }
+
+        /// Swaps the partitions, as the update does, stopping after `budget` sector copies
+        /// (i.e. power is lost). Returns the remaining budget.
+        fn swap(&mut self, mut budget: usize) -> usize {
+            for sector in 0..SECTORS {
+                let mut flag = self.flags[sector];
+                while let Some((from, to)) = flag.pending_copy() {
+                    if budget == 0 {
+                        return 0;
+                    }
+                    budget -= 1;
+                    // the swap partition holds a single sector
+                    let src = if from == PartId::PartSwap { 0 } else { sector };
+                    let dst = if to == PartId::PartSwap { 0 } else { sector };
+                    let byte = self.part(from)[src];
+                    self.part(to)[dst] = byte;
+                    flag = flag.transition(flag.next().unwrap()).unwrap();
+                    self.flags[sector] = flag;
+                }
+            }
+            budget
+        }
+    }
+
+    #[test]
+    fn interrupted_swap_resumes() {
+        let copies = SECTORS * 3;
+        for first in 0..=copies {
+            for second in 0..=copies {
+                let mut flash = Flash::new();
+                flash.swap(first);
+                flash.swap(second);
+                // the final boot always completes the swap
+                flash.swap(copies);
+                let expected = Flash::new();
+                assert_eq!(flash.parts[0], expected.parts[1]);
+                assert_eq!(flash.parts[1], expected.parts[0]);
+                assert!(flash.flags.iter().all(|f| *f == SectorFlag::Updated));
+            }
+        }
+    }
+
+    #[test]
+    fn retired_boots() {
+        assert_eq!(Retired::from_byte(0xFF), None);
+        let mut retired = Retired::from_byte(Retired::MARKED.as_byte()).unwrap();
+        for boots in 0..=Retired::MAX_BOOTS {
+            assert_eq!(retired.boots(), boots);
+            let next = retired.counted();
+            // counting only clears bits
+            assert_eq!(next.as_byte() & retired.as_byte(), next.as_byte());
+            retired = next;
+        }
+        assert_eq!(retired.boots(), Retired::MAX_BOOTS);
+        for byte in 0..0x80 {
+            let retired = Retired::from_byte(byte).unwrap();
+            assert!(retired.boots() <= Retired::MAX_BOOTS);
+            if retired.boots() < Retired::MAX_BOOTS {
+                assert!(retired.counted().boots() > retired.boots());
+            }
+        }
+    }
+
+    /// A journal of 3 slots, as the trailer holds it.
+    fn journal(records: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut slots = vec![vec![0xFF; 16]; 3];
+        for (slot, record) in slots.iter_mut().zip(records) {
+            slot[..record.len()].copy_from_slice(record);
+        }
+        slots
+    }
+
+    fn slots(journal: &[Vec<u8>]) -> Vec<&[u8]> {
+        journal.iter().map(Vec::as_slice).collect()
+    }
+
+    #[test]
+    fn state_records() {
+        for state in PartitionState::ALL {
+            let record = StateRecord { state, seq: 7 };
+            assert_eq!(StateRecord::from_bytes(&record.to_bytes()), Some(record));
+        }
+        // a blank slot and (any) torn write
+        assert_eq!(StateRecord::from_bytes(&[0xFF; StateRecord::LEN]), None);
+        let bytes = StateRecord {
+            state: PartitionState::Testing,
+            seq: 1,
+        }
+        .to_bytes();
+        for len in 0..StateRecord::LEN {
+            let mut torn = [0xFF; StateRecord::LEN];
+            torn[..len].copy_from_slice(&bytes[..len]);
+            assert_eq!(StateRecord::from_bytes(&torn), None);
+        }
+    }
+
+    #[test]
+    fn state_journal() {
+        use PartitionState::*;
+        let testing = StateRecord {
+            state: Testing,
+            seq: 0,
+        }
+        .to_bytes();
+        let success = StateRecord {
+            state: Success,
+            seq: 1,
+        }
+        .to_bytes();
+
+        let blank = journal(&[]);
+        assert_eq!(StateRecord::newest(slots(&blank)), None);
+        assert_eq!(StateRecord::next_slot(slots(&blank)), Some(0));
+
+        let confirmed = journal(&[&testing, &success]);
+        let newest = StateRecord::newest(slots(&confirmed)).unwrap();
+        assert_eq!(newest.state, Success);
+        assert_eq!(StateRecord::next_slot(slots(&confirmed)), Some(2));
+
+        // a torn write keeps the previous state and uses up its slot
+        let torn = journal(&[&testing, &success[..5]]);
+        let newest = StateRecord::newest(slots(&torn)).unwrap();
+        assert_eq!(newest.state, Testing);
+        assert_eq!(StateRecord::next_slot(slots(&torn)), Some(2));
+
+        let full = journal(&[&testing, &success, &success]);
+        assert_eq!(StateRecord::next_slot(slots(&full)), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! A sanity check of a Cortex-M image's vector table, before it's booted.
+//!
+//! A verified image is booted as-is. This catches images that are clearly corrupt (ex: a build
+//! linked for the wrong address) but pass the header's length checks, for instance in development
+//! builds that don't check signatures. A firmware image starts with its vector table i.e. its
+//! initial stack pointer, followed by its reset vector.
+
+use core::convert::TryInto;
+use core::ops::Range;
+
+use crate::{Result, RustbootError};
+
+/// Checks the vector table at the start of `firmware` i.e. that
+///
+/// - the initial stack pointer is word-aligned and lies in `ram`. It may also point right past
+///   `ram`, as stacks grow down.
+/// - the reset vector points into `fw_region` (i.e. the boot partition's firmware) and has its
+///   thumb bit set.
+///
+/// Returns `InvalidImage` otherwise.
+pub fn check_vector_table(
+    firmware: &[u8],
+    ram: Range<usize>,
+    fw_region: Range<usize>,
+) -> Result<()> {
+    let word = |idx: usize| -> Result<usize> {
+        firmware
+            .get(idx * 4..idx * 4 + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(|bytes| u32::from_le_bytes(bytes) as usize)
+            .ok_or(RustbootError::InvalidImage)
+    };
+    let (sp, reset) = (word(0)?, word(1)?);
+    let sp_ok = sp % 4 == 0 && sp > ram.start && sp <= ram.end;
+    let reset_ok = reset & 1 == 1 && fw_region.contains(&(reset & !1));
+    match sp_ok && reset_ok {
+        true => Ok(()),
+        false => Err(RustbootError::InvalidImage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAM: Range<usize> = 0x2000_0000..0x2004_0000;
+    const FW: Range<usize> = 0x2f100..0x57000;
+
+    fn vectors(sp: u32, reset: u32) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&sp.to_le_bytes());
+        bytes[4..].copy_from_slice(&reset.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn vector_table_checks() {
+        let check = |sp, reset| check_vector_table(&vectors(sp, reset), RAM, FW);
+        assert_eq!(check(0x2004_0000, 0x2f201), Ok(()));
+        assert_eq!(check(0x2000_8000, 0x56fff), Ok(()));
+
+        let invalid = Err(RustbootError::InvalidImage);
+        // the stack pointer isn't in RAM or isn't aligned
+        assert_eq!(check(0x2000_0000, 0x2f201), invalid);
+        assert_eq!(check(0x2004_0004, 0x2f201), invalid);
+        assert_eq!(check(0x2000_8002, 0x2f201), invalid);
+        assert_eq!(check(0xFFFF_FFFF, 0x2f201), invalid);
+        // the reset vector isn't in the boot partition or isn't a thumb address
+        assert_eq!(check(0x2004_0000, 0x2f200), invalid);
+        assert_eq!(check(0x2004_0000, 0x2f001), invalid);
+        assert_eq!(check(0x2004_0000, 0x57001), invalid);
+        assert_eq!(check(0x2004_0000, 0xFFFF_FFFF), invalid);
+        // erased or truncated firmware
+        assert_eq!(check_vector_table(&[0xFF; 8], RAM, FW), invalid);
+        assert_eq!(check_vector_table(&[0; 4], RAM, FW), invalid);
+    }
+}
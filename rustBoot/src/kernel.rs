@@ -0,0 +1,378 @@
+//! Linux kernel `Image` header validation, for ARM64 and RISC-V 64 kernels.
+//!
+//! A fit-image's kernel is an ARM64 or RISC-V `Image` (optionally with an EFI stub i.e. also a
+//! PE/COFF image). Its 64-byte header tells a bootloader where to place the kernel, relative to a
+//! 2MiB aligned base and how much memory it needs. See the kernel's
+//! `Documentation/arm64/booting.rst` and `Documentation/riscv/boot-image-header.rst`.
+//!
+//! An ARM64 `Image` header:
+//!
+//! ```text
+//! u32 code0;          /* Executable code, "MZ" for EFI-stub kernels */
+//! u32 code1;          /* Executable code */
+//! u64 text_offset;    /* Image load offset, little endian */
+//! u64 image_size;     /* Effective Image size, little endian */
+//! u64 flags;          /* kernel flags, little endian */
+//! u64 res2;           /* reserved */
+//! u64 res3;           /* reserved */
+//! u64 res4;           /* reserved */
+//! u32 magic;          /* Magic number, little endian, "ARM\x64" */
+//! u32 res5;           /* reserved (used for PE COFF offset) */
+//! ```
+//!
+//! A RISC-V `Image` header:
+//!
+//! ```text
+//! u32 code0;          /* Executable code, "MZ" for EFI-stub kernels */
+//! u32 code1;          /* Executable code */
+//! u64 text_offset;    /* Image load offset, little endian */
+//! u64 image_size;     /* Effective Image size, little endian */
+//! u64 flags;          /* kernel flags, little endian */
+//! u32 version;        /* Version of this header */
+//! u32 res1;           /* reserved */
+//! u64 res2;           /* reserved */
+//! u64 magic;          /* Magic number, little endian, "RISCV" (deprecated) */
+//! u32 magic2;         /* Magic number 2, little endian, "RSC\x05" */
+//! u32 res3;           /* reserved (used for PE COFF offset) */
+//! ```
+
+use core::convert::TryInto;
+
+use crate::{Result, RustbootError};
+
+/// Size of the `Image` header.
+pub const ARM64_IMAGE_HEADER_SIZE: usize = 64;
+/// `ARM\x64`
+pub const ARM64_IMAGE_MAGIC: u32 = 0x644d_5241;
+/// `text_offset` of kernels older than v3.17 i.e. kernels with an `image_size` of zero.
+pub const ARM64_LEGACY_TEXT_OFFSET: u64 = 0x8_0000;
+/// The kernel is placed at `text_offset` from a base with this alignment.
+pub const ARM64_IMAGE_BASE_ALIGN: usize = 0x20_0000;
+
+/// Size of the RISC-V `Image` header.
+pub const RISCV_IMAGE_HEADER_SIZE: usize = 64;
+/// `RISCV\0\0\0`, the (deprecated) magic of kernels older than v5.5.
+pub const RISCV_IMAGE_MAGIC: u64 = 0x0056_4353_4952;
+/// `RSC\x05`
+pub const RISCV_IMAGE_MAGIC2: u32 = 0x0543_5352;
+/// A RV64 kernel is placed at `text_offset` from a base with this alignment (i.e. a PMD).
+pub const RISCV_IMAGE_BASE_ALIGN: usize = 0x20_0000;
+
+/// `MZ`, the start of a PE/COFF (i.e. EFI-stub) image.
+const PE_DOS_MAGIC: &[u8] = b"MZ";
+const PE_MAGIC: &[u8] = b"PE\0\0";
+/// flags, bit 0: kernel endianness. 1 if BE, 0 if LE.
+const FLAG_BE: u64 = 1;
+
+/// An ARM64 kernel `Image`, with a validated header.
+#[derive(Debug, Clone, Copy)]
+pub struct Arm64Image<'a> {
+    data: &'a [u8],
+    text_offset: u64,
+    image_size: u64,
+    flags: u64,
+}
+
+impl<'a> Arm64Image<'a> {
+    /// Validates the `Image` header at the start of `data` i.e. its magic, the kernel's
+    /// endianness, its size fields and for EFI-stub kernels, the PE header's offset.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let header = data
+            .get(..ARM64_IMAGE_HEADER_SIZE)
+            .ok_or(RustbootError::InvalidKernelImage)?;
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+
+        if u32_at(56) != ARM64_IMAGE_MAGIC {
+            return Err(RustbootError::InvalidKernelImage);
+        }
+        let (mut text_offset, image_size, flags) = (u64_at(8), u64_at(16), u64_at(24));
+        if image_size == 0 {
+            text_offset = ARM64_LEGACY_TEXT_OFFSET;
+        } else if image_size < data.len() as u64 {
+            return Err(RustbootError::InvalidKernelImage);
+        }
+        if flags & FLAG_BE != 0 || text_offset % 4096 != 0 {
+            return Err(RustbootError::InvalidKernelImage);
+        }
+        check_pe_header(data)?;
+        Ok(Arm64Image {
+            data,
+            text_offset,
+            image_size,
+            flags,
+        })
+    }
+
+    /// Offset of the kernel (i.e. its entry point) from a 2MiB aligned base.
+    pub fn text_offset(&self) -> usize {
+        self.text_offset as usize
+    }
+
+    /// Memory used by the kernel, from its load address. The kernel's `bss` follows the loaded
+    /// `Image`, so this can be larger than the `Image` itself.
+    pub fn image_size(&self) -> usize {
+        match self.image_size {
+            0 => self.data.len(),
+            size => size as usize,
+        }
+    }
+
+    /// The kernel's `flags` field.
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    /// Returns true if this is an EFI-stub kernel (i.e. also a PE/COFF image).
+    pub fn is_efi_stub(&self) -> bool {
+        &self.data[..2] == PE_DOS_MAGIC
+    }
+
+    /// Copies the kernel to `text_offset` in `dst`. `dst` must start at a 2MiB aligned address.
+    ///
+    /// Returns the kernel's offset in `dst` i.e. its entry point or `BufferTooSmall` if the
+    /// kernel's `image_size` doesn't fit.
+    pub fn load_into(&self, dst: &mut [u8]) -> Result<usize> {
+        load_at(self.data, self.text_offset(), self.image_size(), dst)
+    }
+}
+
+/// A RISC-V 64 kernel `Image`, with a validated header.
+///
+/// The RISC-V boot protocol only asks for the kernel to be placed at a 2MiB aligned address.
+/// Like [`Arm64Image`], it's placed at `text_offset` (i.e. its offset from the start of RAM) from
+/// a 2MiB aligned base, which satisfies that.
+#[derive(Debug, Clone, Copy)]
+pub struct Riscv64Image<'a> {
+    data: &'a [u8],
+    text_offset: u64,
+    image_size: u64,
+    flags: u64,
+    version: u32,
+}
+
+impl<'a> Riscv64Image<'a> {
+    /// Validates the `Image` header at the start of `data` i.e. its magic (either one), the
+    /// kernel's endianness, its size fields and for EFI-stub kernels, the PE header's offset.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let header = data
+            .get(..RISCV_IMAGE_HEADER_SIZE)
+            .ok_or(RustbootError::InvalidKernelImage)?;
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+
+        if u32_at(56) != RISCV_IMAGE_MAGIC2 && u64_at(48) != RISCV_IMAGE_MAGIC {
+            return Err(RustbootError::InvalidKernelImage);
+        }
+        let (text_offset, image_size, flags) = (u64_at(8), u64_at(16), u64_at(24));
+        if image_size < data.len() as u64 {
+            return Err(RustbootError::InvalidKernelImage);
+        }
+        if flags & FLAG_BE != 0 || text_offset % 4096 != 0 {
+            return Err(RustbootError::InvalidKernelImage);
+        }
+        check_pe_header(data)?;
+        Ok(Riscv64Image {
+            data,
+            text_offset,
+            image_size,
+            flags,
+            version: u32_at(32),
+        })
+    }
+
+    /// Offset of the kernel (i.e. its entry point) from a 2MiB aligned base.
+    pub fn text_offset(&self) -> usize {
+        self.text_offset as usize
+    }
+
+    /// Memory used by the kernel, from its load address. The kernel's `bss` follows the loaded
+    /// `Image`, so this can be larger than the `Image` itself.
+    pub fn image_size(&self) -> usize {
+        self.image_size as usize
+    }
+
+    /// The kernel's `flags` field.
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    /// The header's version i.e. `(major, minor)`.
+    pub fn version(&self) -> (u16, u16) {
+        ((self.version >> 16) as u16, self.version as u16)
+    }
+
+    /// Returns true if this is an EFI-stub kernel (i.e. also a PE/COFF image).
+    pub fn is_efi_stub(&self) -> bool {
+        &self.data[..2] == PE_DOS_MAGIC
+    }
+
+    /// Copies the kernel to `text_offset` in `dst`. `dst` must start at a 2MiB aligned address.
+    ///
+    /// Returns the kernel's offset in `dst` i.e. its entry point or `BufferTooSmall` if the
+    /// kernel's `image_size` doesn't fit.
+    pub fn load_into(&self, dst: &mut [u8]) -> Result<usize> {
+        load_at(self.data, self.text_offset(), self.image_size(), dst)
+    }
+}
+
+/// An EFI-stub kernel's header points to its PE header i.e. checks that it's actually there.
+fn check_pe_header(data: &[u8]) -> Result<()> {
+    if &data[..2] == PE_DOS_MAGIC {
+        let pe_offset = u32::from_le_bytes(data[60..64].try_into().unwrap()) as usize;
+        if data.get(pe_offset..pe_offset + 4) != Some(PE_MAGIC) {
+            return Err(RustbootError::InvalidKernelImage);
+        }
+    }
+    Ok(())
+}
+
+fn load_at(data: &[u8], offset: usize, image_size: usize, dst: &mut [u8]) -> Result<usize> {
+    if offset + image_size > dst.len() {
+        return Err(RustbootError::BufferTooSmall);
+    }
+    dst[offset..offset + data.len()].copy_from_slice(data);
+    Ok(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    fn image(text_offset: u64, image_size: u64, len: usize) -> Vec<u8> {
+        let mut img = std::vec![0u8; len];
+        img[..4].copy_from_slice(&[0x4d, 0x5a, 0x00, 0x91]); // "MZ", add x13, x18, #0x16
+        img[8..16].copy_from_slice(&text_offset.to_le_bytes());
+        img[16..24].copy_from_slice(&image_size.to_le_bytes());
+        img[24..32].copy_from_slice(&0x0au64.to_le_bytes());
+        img[56..60].copy_from_slice(&ARM64_IMAGE_MAGIC.to_le_bytes());
+        img[60..64].copy_from_slice(&0x40u32.to_le_bytes());
+        img[0x40..0x44].copy_from_slice(PE_MAGIC);
+        img
+    }
+
+    #[test]
+    fn valid_image() {
+        let img = image(0, 0x2000, 0x1000);
+        let kernel = Arm64Image::parse(&img).unwrap();
+        assert!(kernel.is_efi_stub());
+        assert_eq!(kernel.text_offset(), 0);
+        assert_eq!(kernel.image_size(), 0x2000);
+
+        let mut dst = std::vec![0xffu8; 0x2000];
+        assert_eq!(kernel.load_into(&mut dst).unwrap(), 0);
+        assert_eq!(&dst[..0x1000], &img[..]);
+        assert!(kernel.load_into(&mut dst[..0x1fff]).is_err());
+    }
+
+    #[test]
+    fn honors_text_offset() {
+        let img = image(0x8_0000, 0x2000, 0x1000);
+        let kernel = Arm64Image::parse(&img).unwrap();
+        let mut dst = std::vec![0u8; 0x8_2000];
+        assert_eq!(kernel.load_into(&mut dst).unwrap(), 0x8_0000);
+        assert_eq!(&dst[0x8_0000..0x8_1000], &img[..]);
+
+        // pre v3.17 kernels have no image_size, text_offset is assumed to be 0x80000.
+        let img = image(0x1234, 0, 0x1000);
+        let kernel = Arm64Image::parse(&img).unwrap();
+        assert_eq!(kernel.text_offset(), 0x8_0000);
+        assert_eq!(kernel.image_size(), 0x1000);
+    }
+
+    #[test]
+    fn invalid_images() {
+        let err = Err(RustbootError::InvalidKernelImage);
+        // truncated
+        assert_eq!(
+            Arm64Image::parse(&image(0, 0x2000, 0x1000)[..63]).map(|_| ()),
+            err
+        );
+        // bad magic
+        let mut img = image(0, 0x2000, 0x1000);
+        img[56] = 0;
+        assert_eq!(Arm64Image::parse(&img).map(|_| ()), err);
+        // image_size smaller than the Image
+        assert_eq!(Arm64Image::parse(&image(0, 0x800, 0x1000)).map(|_| ()), err);
+        // big-endian kernel
+        let mut img = image(0, 0x2000, 0x1000);
+        img[24] |= 1;
+        assert_eq!(Arm64Image::parse(&img).map(|_| ()), err);
+        // EFI-stub with a bad PE header offset
+        let mut img = image(0, 0x2000, 0x1000);
+        img[60..64].copy_from_slice(&0x2000u32.to_le_bytes());
+        assert_eq!(Arm64Image::parse(&img).map(|_| ()), err);
+    }
+
+    fn riscv_image(text_offset: u64, image_size: u64, len: usize) -> Vec<u8> {
+        let mut img = std::vec![0u8; len];
+        img[..4].copy_from_slice(&[0x4d, 0x5a, 0x6f, 0x10]); // "MZ", c.li s4, -13
+        img[8..16].copy_from_slice(&text_offset.to_le_bytes());
+        img[16..24].copy_from_slice(&image_size.to_le_bytes());
+        img[32..36].copy_from_slice(&0x2u32.to_le_bytes());
+        img[48..56].copy_from_slice(&RISCV_IMAGE_MAGIC.to_le_bytes());
+        img[56..60].copy_from_slice(&RISCV_IMAGE_MAGIC2.to_le_bytes());
+        img[60..64].copy_from_slice(&0x40u32.to_le_bytes());
+        img[0x40..0x44].copy_from_slice(PE_MAGIC);
+        img
+    }
+
+    #[test]
+    fn valid_riscv_image() {
+        let img = riscv_image(0x20_0000, 0x2000, 0x1000);
+        let kernel = Riscv64Image::parse(&img).unwrap();
+        assert!(kernel.is_efi_stub());
+        assert_eq!(kernel.version(), (0, 2));
+        assert_eq!(kernel.text_offset(), 0x20_0000);
+        assert_eq!(kernel.image_size(), 0x2000);
+
+        let mut dst = std::vec![0xffu8; 0x20_2000];
+        assert_eq!(kernel.load_into(&mut dst).unwrap(), 0x20_0000);
+        assert_eq!(&dst[0x20_0000..0x20_1000], &img[..]);
+        assert!(kernel.load_into(&mut dst[..0x20_1fff]).is_err());
+
+        // kernels older than v5.5 only carry the deprecated magic
+        let mut img = riscv_image(0x20_0000, 0x2000, 0x1000);
+        img[56..60].copy_from_slice(&[0; 4]);
+        assert!(Riscv64Image::parse(&img).is_ok());
+    }
+
+    #[test]
+    fn invalid_riscv_images() {
+        let err = Err(RustbootError::InvalidKernelImage);
+        // truncated
+        let img = riscv_image(0x20_0000, 0x2000, 0x1000);
+        assert_eq!(Riscv64Image::parse(&img[..63]).map(|_| ()), err);
+        // an ARM64 Image
+        assert_eq!(
+            Riscv64Image::parse(&image(0, 0x2000, 0x1000)).map(|_| ()),
+            err
+        );
+        // no magic
+        let mut img = riscv_image(0x20_0000, 0x2000, 0x1000);
+        img[48..60].copy_from_slice(&[0; 12]);
+        assert_eq!(Riscv64Image::parse(&img).map(|_| ()), err);
+        // image_size smaller than the Image
+        assert_eq!(
+            Riscv64Image::parse(&riscv_image(0x20_0000, 0x800, 0x1000)).map(|_| ()),
+            err
+        );
+        // big-endian kernel or an unaligned text_offset
+        let mut img = riscv_image(0x20_0000, 0x2000, 0x1000);
+        img[24] |= 1;
+        assert_eq!(Riscv64Image::parse(&img).map(|_| ()), err);
+        assert_eq!(
+            Riscv64Image::parse(&riscv_image(0x20_0100, 0x2000, 0x1000)).map(|_| ()),
+            err
+        );
+        // EFI-stub with a bad PE header offset
+        let mut img = riscv_image(0x20_0000, 0x2000, 0x1000);
+        img[60..64].copy_from_slice(&0x2000u32.to_le_bytes());
+        assert_eq!(Riscv64Image::parse(&img).map(|_| ()), err);
+    }
+}
@@ -0,0 +1,11 @@
+// @generated by `cargo esp32s3 gen layout` from `boards/manifests/esp32s3.toml`.
+// Do not edit by hand.
+
+pub const SECTOR_SIZE: usize = 0x1000;
+pub const WRITE_SIZE: usize = 0x10;
+pub const FLASH_SIZE: usize = 0x800000;
+pub const PARTITION_SIZE: usize = 0x100000;
+pub const BOOTLOADER_ADDRESS: usize = 0x3d000000;
+pub const BOOT_PARTITION_ADDRESS: usize = 0x3d020000;
+pub const SWAP_PARTITION_ADDRESS: usize = 0x3d220000;
+pub const UPDATE_PARTITION_ADDRESS: usize = 0x3d120000;
@@ -0,0 +1,25 @@
+// @generated by `cargo nrf52840 gen layout` from `boards/manifests/nrf52840.toml`.
+// Do not edit by hand.
+
+pub const SECTOR_SIZE: usize = 0x1000;
+pub const WRITE_SIZE: usize = 0x4;
+pub const FLASH_SIZE: usize = 0x100000;
+pub const PARTITION_SIZE: usize = 0x28000;
+pub const BOOTLOADER_ADDRESS: usize = 0x0;
+pub const BOOT_PARTITION_ADDRESS: usize = 0x2f000;
+pub const SWAP_PARTITION_ADDRESS: usize = 0x57000;
+pub const UPDATE_PARTITION_ADDRESS: usize = 0x58000;
+pub const EVENT_LOG_ADDRESS: usize = 0x80000;
+pub const BOOT_METADATA_ADDRESS: usize = 0x81000;
+pub const UPDATE_METADATA_ADDRESS: usize = 0x82000;
+pub const PROVISION_FLAG_ADDRESS: usize = 0x83000;
+pub const STAGE0_SIZE: usize = 0x8000;
+pub const STAGE1_A_ADDRESS: usize = 0x8000;
+pub const STAGE1_B_ADDRESS: usize = 0x1b000;
+pub const STAGE1_SIZE: usize = 0x13000;
+pub const BOOT_PIN_PORT: u8 = 1;
+pub const BOOT_PIN: u8 = 0;
+pub const BOOT_PIN_ACTIVE_LOW: bool = true;
+pub const BOOT_PIN_HOLD_MS: u32 = 100;
+pub const HEADER_SCAN_WINDOW: usize = 0x400;
+pub const HEADER_SCAN_ALIGN: usize = 0x100;
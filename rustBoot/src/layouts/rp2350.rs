@@ -0,0 +1,11 @@
+// @generated by `cargo rp2350 gen layout` from `boards/manifests/rp2350.toml`.
+// Do not edit by hand.
+
+pub const SECTOR_SIZE: usize = 0x1000;
+pub const WRITE_SIZE: usize = 0x100;
+pub const FLASH_SIZE: usize = 0x400000;
+pub const PARTITION_SIZE: usize = 0x20000;
+pub const BOOTLOADER_ADDRESS: usize = 0x10000000;
+pub const BOOT_PARTITION_ADDRESS: usize = 0x10020000;
+pub const SWAP_PARTITION_ADDRESS: usize = 0x10060000;
+pub const UPDATE_PARTITION_ADDRESS: usize = 0x10040000;
@@ -0,0 +1,11 @@
+// @generated by `cargo stm32f334 gen layout` from `boards/manifests/stm32f334.toml`.
+// Do not edit by hand.
+
+pub const SECTOR_SIZE: usize = 0x1800;
+pub const WRITE_SIZE: usize = 0x2;
+pub const FLASH_SIZE: usize = 0x10000;
+pub const PARTITION_SIZE: usize = 0x1800;
+pub const BOOTLOADER_ADDRESS: usize = 0x8000000;
+pub const BOOT_PARTITION_ADDRESS: usize = 0x800b800;
+pub const SWAP_PARTITION_ADDRESS: usize = 0x800e800;
+pub const UPDATE_PARTITION_ADDRESS: usize = 0x800d000;
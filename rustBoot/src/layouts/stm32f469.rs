@@ -0,0 +1,11 @@
+// @generated by `cargo stm32f469 gen layout` from `boards/manifests/stm32f469.toml`.
+// Do not edit by hand.
+
+pub const SECTOR_SIZE: usize = 0x20000;
+pub const WRITE_SIZE: usize = 0x4;
+pub const FLASH_SIZE: usize = 0x200000;
+pub const PARTITION_SIZE: usize = 0x60000;
+pub const BOOTLOADER_ADDRESS: usize = 0x8000000;
+pub const BOOT_PARTITION_ADDRESS: usize = 0x8020000;
+pub const SWAP_PARTITION_ADDRESS: usize = 0x80e0000;
+pub const UPDATE_PARTITION_ADDRESS: usize = 0x8080000;
@@ -0,0 +1,11 @@
+// @generated by `cargo stm32f746 gen layout` from `boards/manifests/stm32f746.toml`.
+// Do not edit by hand.
+
+pub const SECTOR_SIZE: usize = 0x40000;
+pub const WRITE_SIZE: usize = 0x1;
+pub const FLASH_SIZE: usize = 0x100000;
+pub const PARTITION_SIZE: usize = 0x40000;
+pub const BOOTLOADER_ADDRESS: usize = 0x8000000;
+pub const BOOT_PARTITION_ADDRESS: usize = 0x8040000;
+pub const SWAP_PARTITION_ADDRESS: usize = 0x80c0000;
+pub const UPDATE_PARTITION_ADDRESS: usize = 0x8080000;
@@ -0,0 +1,92 @@
+//! The bootloader panic record's format.
+//!
+//! A bootloader that panics records where it panicked (see `rustBoot_hal::panic_record`) in
+//! registers that survive the reset that follows i.e. a board's backup registers. A field unit
+//! that hits a bootloader panic leaves evidence that the application (or rustBoot's console) can
+//! read, rather than spinning silently.
+//!
+//! ```text
+//!  word 0  | magic
+//!  word 1  | file hash (see [`file_hash`])
+//!  word 2  | line
+//!  word 3  | timestamp (seconds since the unix epoch, `0` if it's unknown)
+//! ```
+
+/// The length of a record, in (32-bit) words.
+pub const RECORD_WORDS: usize = 4;
+
+const MAGIC: u32 = 0x5242_5021; // "RBP!"
+
+/// A bootloader panic's location and time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanicRecord {
+    /// the hash of the source file that panicked, see [`file_hash`].
+    pub file_hash: u32,
+    pub line: u32,
+    /// the time (in seconds since the unix epoch) of the panic, per the board's RTC. `0` if the
+    /// board has no calendar RTC or if it was never set.
+    pub timestamp: u32,
+}
+
+impl PanicRecord {
+    pub fn new(file: &str, line: u32, timestamp: u32) -> Self {
+        PanicRecord {
+            file_hash: file_hash(file),
+            line,
+            timestamp,
+        }
+    }
+
+    pub fn to_words(&self) -> [u32; RECORD_WORDS] {
+        [MAGIC, self.file_hash, self.line, self.timestamp]
+    }
+
+    /// Decodes a record. Returns `None` if `words` don't hold one (ex: backup registers that were
+    /// cleared, or never written).
+    pub fn from_words(words: &[u32; RECORD_WORDS]) -> Option<Self> {
+        match words {
+            [MAGIC, file_hash, line, timestamp] => Some(PanicRecord {
+                file_hash: *file_hash,
+                line: *line,
+                timestamp: *timestamp,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Hashes a source file's path (i.e. a panic location's `file()`) with 32-bit FNV-1a. A record
+/// only holds the hash, match it against `file_hash` of the bootloader's source files to find the
+/// file that panicked.
+pub const fn file_hash(file: &str) -> u32 {
+    let mut bytes = file.as_bytes();
+    let mut hash = 0x811C_9DC5u32;
+    while let [byte, rest @ ..] = bytes {
+        hash = (hash ^ *byte as u32).wrapping_mul(0x0100_0193);
+        bytes = rest;
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_words() {
+        let rec = PanicRecord::new("src/update/update_flash.rs", 42, 1_700_000_000);
+        assert_eq!(rec.file_hash, file_hash("src/update/update_flash.rs"));
+        assert_eq!(rec.to_words(), [MAGIC, rec.file_hash, 42, 1_700_000_000]);
+        assert_eq!(PanicRecord::from_words(&rec.to_words()), Some(rec));
+        // cleared (or never written) backup registers
+        assert_eq!(PanicRecord::from_words(&[0; RECORD_WORDS]), None);
+    }
+
+    #[test]
+    fn fnv1a_file_hash() {
+        // reference values for 32-bit FNV-1a
+        assert_eq!(file_hash(""), 0x811C_9DC5);
+        assert_eq!(file_hash("a"), 0xE40C_292C);
+        assert_eq!(file_hash("foobar"), 0xBF9C_F968);
+    }
+}
@@ -0,0 +1,36 @@
+//! Progress reporting during long-running operations.
+//!
+//! Verifying or swapping a large image can take seconds. A [`Progress`] hook is called as these
+//! operations advance, so board code can blink an LED or feed a watchdog in the meantime. `()`
+//! is the no-op hook.
+
+/// A long-running operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Hashing an image, to check its integrity or signature. Counted in bytes.
+    Verify,
+    /// Exchanging the boot and update images. Counted in sectors.
+    Swap,
+    /// Erasing the rest of the boot and update partitions after a swap. Counted in sectors.
+    Erase,
+}
+
+/// A hook called as a long-running operation advances.
+pub trait Progress {
+    /// `done` out of `total` units of `phase` are complete. Called at least once with
+    /// `done == total` as a phase completes.
+    ///
+    /// *Note: this is called from the bootloader's update path, it must not touch the flash
+    /// partitions and should return quickly.*
+    fn on_progress(&self, phase: Phase, done: usize, total: usize);
+}
+
+impl Progress for () {
+    fn on_progress(&self, _phase: Phase, _done: usize, _total: usize) {}
+}
+
+impl<P: Progress> Progress for &P {
+    fn on_progress(&self, phase: Phase, done: usize, total: usize) {
+        (*self).on_progress(phase, done, total)
+    }
+}
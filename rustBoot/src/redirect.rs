@@ -0,0 +1,191 @@
+//! The boot-redirect request's format.
+//!
+//! Firmware asks rustBoot to boot a signed image at another address once (ex: diagnostic
+//! firmware, shipped alongside the application) by writing a request to the board's backup
+//! registers and resetting, see `rustBoot_update::update::redirect`. rustBoot clears the request
+//! as it reads it, so the boot after the redirected one is a normal one.
+//!
+//! ```text
+//!  word 0  | magic
+//!  word 1  | address (of the image's rustBoot header)
+//!  word 2  | !address
+//!  word 3  | 0
+//! ```
+//!
+//! The request itself isn't trusted i.e. anything that can write the backup registers can write
+//! one. It only selects an image, which is verified (and must lie outside the bootloader and the
+//! partitions, see [`check_target`]) before it's booted. The image is held to the version policy
+//! updates are (see [`check_image`]) i.e. a redirect can't boot an old, signed image that an
+//! update couldn't install.
+//!
+//! *Note: a request shares the backup registers with the bootloader's panic record (see
+//! [`crate::panicrecord`]), writing one replaces the other.*
+
+use core::ops::Range;
+
+use crate::chain::SignedImage;
+use crate::rbconstants::{HDR_IMG_TYPE_APP, HDR_MASK_LOWBYTE};
+use crate::version::VersionPolicy;
+use crate::{Result, RustbootError};
+
+/// The length of a request, in (32-bit) words.
+pub const REQUEST_WORDS: usize = 4;
+
+const MAGIC: u32 = 0x5242_523E; // "RBR>"
+
+/// A request to boot the image at `address` once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootRedirect {
+    /// the address of the image's rustBoot header, its firmware follows the header.
+    pub address: u32,
+}
+
+impl BootRedirect {
+    pub fn to_words(&self) -> [u32; REQUEST_WORDS] {
+        [MAGIC, self.address, !self.address, 0]
+    }
+
+    /// Decodes a request. Returns `None` if `words` don't hold one (ex: backup registers that were
+    /// cleared, hold a panic record or were partly overwritten).
+    pub fn from_words(words: &[u32; REQUEST_WORDS]) -> Option<Self> {
+        match words {
+            [MAGIC, address, check, 0] if *check == !*address => {
+                Some(BootRedirect { address: *address })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Checks that an image of `len` bytes at `address` (i.e. a redirect's target) lies in `flash`
+/// and doesn't overlap any of the `reserved` regions (i.e. the bootloader, the partitions and
+/// other sectors with a role, as `(address, length)`). Returns `InvalidValue` otherwise.
+pub fn check_target(
+    address: usize,
+    len: usize,
+    flash: Range<usize>,
+    reserved: &[(usize, usize)],
+) -> Result<()> {
+    let end = address
+        .checked_add(len)
+        .ok_or(RustbootError::InvalidValue)?;
+    let in_flash = address >= flash.start && end <= flash.end;
+    let overlaps = reserved
+        .iter()
+        .any(|(start, size)| address < start.saturating_add(*size) && *start < end);
+    match in_flash && !overlaps {
+        true => Ok(()),
+        false => Err(RustbootError::InvalidValue),
+    }
+}
+
+/// Checks a redirect's image i.e. that it's an application image, that `policy` (i.e. the
+/// policy updates are held to) permits it to replace the boot partition's image (of version
+/// `installed`) and its digest and signature. An image of the installed version (ex: diagnostic
+/// firmware released with it) is accepted too.
+///
+/// Returns `InvalidImage`, `BadVersion` or the verification's error otherwise.
+pub fn check_image(image: &SignedImage, installed: u32, policy: &VersionPolicy) -> Result<()> {
+    if (image.get_image_type()? & HDR_MASK_LOWBYTE) != HDR_IMG_TYPE_APP {
+        return Err(RustbootError::InvalidImage);
+    }
+    if !policy
+        .allow_equal(true)
+        .permits(installed, image.get_firmware_version()?)
+    {
+        return Err(RustbootError::BadVersion);
+    }
+    image.verify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::VersionEncoding;
+
+    /// validly signed images (see `crate::image`'s golden images), of version 2 and 1
+    const VALID: &[u8] = include_bytes!("../test-vectors/valid.bin");
+    const BAD_VERSION: &[u8] = include_bytes!("../test-vectors/bad-version.bin");
+
+    #[test]
+    fn request_words() {
+        let req = BootRedirect {
+            address: 0x0807_0000,
+        };
+        assert_eq!(req.to_words(), [MAGIC, 0x0807_0000, 0xF7F8_FFFF, 0]);
+        assert_eq!(BootRedirect::from_words(&req.to_words()), Some(req));
+        // cleared (or never written) backup registers and a panic record
+        assert_eq!(BootRedirect::from_words(&[0; REQUEST_WORDS]), None);
+        assert_eq!(
+            BootRedirect::from_words(&[0x5242_5021, 0x1234, 42, 0]),
+            None
+        );
+        // a corrupted address
+        let mut words = req.to_words();
+        words[1] ^= 0x100;
+        assert_eq!(BootRedirect::from_words(&words), None);
+    }
+
+    #[test]
+    fn redirect_targets() {
+        const FLASH: Range<usize> = 0x0800_0000..0x0808_0000;
+        let reserved = [
+            (0x0800_0000, 0x2_0000), // the bootloader
+            (0x0802_0000, 0x2_0000), // boot
+            (0x0804_0000, 0x2_0000), // update
+            (0x0806_0000, 0x1_0000), // swap
+        ];
+        assert_eq!(
+            check_target(0x0807_0000, 0x1_0000, FLASH, &reserved),
+            Ok(())
+        );
+        // overlaps the swap partition, or runs past the end of flash
+        assert_eq!(
+            check_target(0x0806_F000, 0x2000, FLASH, &reserved),
+            Err(RustbootError::InvalidValue)
+        );
+        assert_eq!(
+            check_target(0x0807_0000, 0x1_0001, FLASH, &reserved),
+            Err(RustbootError::InvalidValue)
+        );
+        // the boot partition's image
+        assert_eq!(
+            check_target(0x0802_0000, 0x100, FLASH, &reserved),
+            Err(RustbootError::InvalidValue)
+        );
+        assert_eq!(
+            check_target(usize::MAX, 2, FLASH, &reserved),
+            Err(RustbootError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn redirect_images() {
+        let policy = VersionPolicy::STRICT;
+        let image = SignedImage::parse(VALID).unwrap();
+        assert_eq!(check_image(&image, 1, &policy), Ok(()));
+        // the installed image's version
+        assert_eq!(check_image(&image, 2, &policy), Ok(()));
+        assert_eq!(
+            check_image(&image, 3, &policy),
+            Err(RustbootError::BadVersion)
+        );
+
+        // a correctly signed image, older than the installed one
+        let old = SignedImage::parse(BAD_VERSION).unwrap();
+        assert_eq!(old.verify(), Ok(()));
+        assert_eq!(
+            check_image(&old, 2, &policy),
+            Err(RustbootError::BadVersion)
+        );
+        assert_eq!(
+            check_image(&old, 2, &VersionPolicy::new(VersionEncoding::SemVer)),
+            Err(RustbootError::BadVersion)
+        );
+        // unless the policy accepts downgrades
+        assert_eq!(
+            check_image(&old, 2, &policy.allow_downgrade_within_epoch(true)),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Firmware version policies.
+//!
+//! rustBoot images carry a 4-byte version field (mcu images) or a timestamp (fit-images). By
+//! default an update is only accepted if its version is strictly greater than the version
+//! currently installed. A [`VersionPolicy`] describes how a version is encoded and which
+//! (if any) non-increasing updates are acceptable, a [`TimestampPolicy`] how a fit-image's
+//! timestamp must relate to the version recorded in `updt.txt` and a [`ValidityPolicy`] what to
+//! do with a fit-image's validity window when the board can't tell the time.
+
+use crate::{Result, RustbootError};
+
+/// Describes how a 4-byte version field is laid out.
+///
+/// All encodings order the same way as the raw `u32` i.e. a higher epoch always
+/// compares greater, regardless of the fields that follow it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionEncoding {
+    /// A plain counter.
+    Counter,
+    /// A semantic version - `major[31:24] minor[23:16] patch[15:0]`.
+    SemVer,
+    /// An epoch followed by a counter - `epoch[31:24] counter[23:0]`.
+    EpochCounter,
+    /// An epoch followed by a semantic version - `epoch[31:24] major[23:16] minor[15:8] patch[7:0]`.
+    EpochSemVer,
+}
+
+impl VersionEncoding {
+    /// Returns the epoch of a version. Encodings without an epoch field place every
+    /// version in epoch `0`.
+    pub const fn epoch(self, version: u32) -> u32 {
+        match self {
+            VersionEncoding::Counter | VersionEncoding::SemVer => 0,
+            VersionEncoding::EpochCounter | VersionEncoding::EpochSemVer => version >> 24,
+        }
+    }
+}
+
+/// Encodes a [`VersionEncoding::SemVer`] version.
+pub const fn semver(major: u8, minor: u8, patch: u16) -> u32 {
+    (major as u32) << 24 | (minor as u32) << 16 | patch as u32
+}
+
+/// Encodes a [`VersionEncoding::EpochCounter`] version. Only the lower 24 bits of `counter` are used.
+pub const fn epoch_counter(epoch: u8, counter: u32) -> u32 {
+    (epoch as u32) << 24 | (counter & 0x00FF_FFFF)
+}
+
+/// Encodes a [`VersionEncoding::EpochSemVer`] version.
+pub const fn epoch_semver(epoch: u8, major: u8, minor: u8, patch: u8) -> u32 {
+    (epoch as u32) << 24 | (major as u32) << 16 | (minor as u32) << 8 | patch as u32
+}
+
+/// Rules for accepting an update, given the installed and the candidate image's versions.
+///
+/// The default policy (and [`VersionPolicy::STRICT`]) only accepts strictly increasing versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionPolicy {
+    encoding: VersionEncoding,
+    allow_equal: bool,
+    allow_downgrade_within_epoch: bool,
+}
+
+impl VersionPolicy {
+    /// Strictly increasing plain counters.
+    pub const STRICT: Self = VersionPolicy::new(VersionEncoding::Counter);
+
+    /// Returns a policy that only accepts strictly increasing versions of the given encoding.
+    pub const fn new(encoding: VersionEncoding) -> Self {
+        VersionPolicy {
+            encoding,
+            allow_equal: false,
+            allow_downgrade_within_epoch: false,
+        }
+    }
+
+    /// Also accept an update with the same version as the installed image i.e. a re-install.
+    pub const fn allow_equal(mut self, allow: bool) -> Self {
+        self.allow_equal = allow;
+        self
+    }
+
+    /// Also accept an older version, as long as it belongs to the installed image's epoch.
+    /// Downgrading to an earlier epoch is never accepted.
+    ///
+    /// *Note: with an encoding that has no epoch field, this accepts any downgrade.*
+    pub const fn allow_downgrade_within_epoch(mut self, allow: bool) -> Self {
+        self.allow_downgrade_within_epoch = allow;
+        self
+    }
+
+    /// Returns the version encoding this policy applies to.
+    pub const fn encoding(&self) -> VersionEncoding {
+        self.encoding
+    }
+
+    /// Returns true if an image with version `candidate` may replace one with version `installed`.
+    pub fn permits(&self, installed: u32, candidate: u32) -> bool {
+        if candidate > installed {
+            true
+        } else if candidate == installed {
+            self.allow_equal
+        } else {
+            self.allow_downgrade_within_epoch
+                && self.encoding.epoch(candidate) == self.encoding.epoch(installed)
+        }
+    }
+}
+
+impl Default for VersionPolicy {
+    fn default() -> Self {
+        VersionPolicy::STRICT
+    }
+}
+
+/// Rules for checking a fit-image's timestamp against the version recorded in `updt.txt`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampPolicy {
+    /// The timestamp must match the recorded version (the default).
+    #[default]
+    Exact,
+    /// The timestamp must not be older than the recorded version i.e. an image may be rebuilt
+    /// without updating `updt.txt`.
+    NotOlder,
+}
+
+impl TimestampPolicy {
+    /// Returns true if a fit-image with the given `timestamp` satisfies the `recorded` version.
+    pub fn permits(&self, recorded: u32, timestamp: u32) -> bool {
+        match self {
+            TimestampPolicy::Exact => timestamp == recorded,
+            TimestampPolicy::NotOlder => timestamp >= recorded,
+        }
+    }
+}
+
+/// Rules for checking a fit-image's validity window (i.e. its config's `not-before` and
+/// `not-after`) when there's no time to check it against, as the board has no RTC or its RTC is
+/// unset. A fit-image without a validity window is always valid.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ValidityPolicy {
+    /// Refuse the fit-image (the default) i.e. an old image can't be booted by resetting the RTC.
+    #[default]
+    Refuse,
+    /// Boot the fit-image anyway i.e. only enforce its validity window when the time is known.
+    Allow,
+}
+
+impl ValidityPolicy {
+    /// Checks a fit-image's validity window against `now` (in seconds since the unix epoch),
+    /// either bound may be open. `now` is `None` if the RTC is unset.
+    pub fn check(
+        &self,
+        not_before: Option<u32>,
+        not_after: Option<u32>,
+        now: Option<u64>,
+    ) -> Result<()> {
+        if not_before.is_none() && not_after.is_none() {
+            return Ok(());
+        }
+        match now {
+            Some(now)
+                if not_before.is_some_and(|t| now < t as u64)
+                    || not_after.is_some_and(|t| now > t as u64) =>
+            {
+                Err(RustbootError::ImageExpired)
+            }
+            Some(_) => Ok(()),
+            None => match self {
+                ValidityPolicy::Refuse => Err(RustbootError::TimeUnavailable),
+                ValidityPolicy::Allow => Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strict_policy() {
+        let policy = VersionPolicy::default();
+        assert!(policy.permits(1, 2));
+        assert!(!policy.permits(2, 2));
+        assert!(!policy.permits(2, 1));
+        assert!(VersionPolicy::STRICT.allow_equal(true).permits(2, 2));
+    }
+
+    #[test]
+    fn semver_ordering() {
+        let policy = VersionPolicy::new(VersionEncoding::SemVer);
+        assert!(policy.permits(semver(1, 2, 3), semver(1, 2, 4)));
+        assert!(policy.permits(semver(1, 2, 0xffff), semver(1, 3, 0)));
+        assert!(policy.permits(semver(1, 0xff, 0xffff), semver(2, 0, 0)));
+        assert!(!policy.permits(semver(2, 0, 0), semver(1, 9, 9)));
+    }
+
+    #[test]
+    fn downgrade_within_epoch() {
+        let policy =
+            VersionPolicy::new(VersionEncoding::EpochSemVer).allow_downgrade_within_epoch(true);
+        assert!(policy.permits(epoch_semver(1, 2, 0, 0), epoch_semver(1, 1, 9, 9)));
+        assert!(!policy.permits(epoch_semver(2, 0, 0, 0), epoch_semver(1, 9, 9, 9)));
+        assert!(policy.permits(epoch_semver(1, 9, 9, 9), epoch_semver(2, 0, 0, 0)));
+        assert!(!policy.permits(epoch_semver(1, 2, 0, 0), epoch_semver(1, 2, 0, 0)));
+
+        let policy =
+            VersionPolicy::new(VersionEncoding::EpochCounter).allow_downgrade_within_epoch(true);
+        assert!(policy.permits(epoch_counter(3, 10), epoch_counter(3, 1)));
+        assert!(!policy.permits(epoch_counter(3, 10), epoch_counter(2, 0x00ff_ffff)));
+        assert_eq!(epoch_counter(1, 0x0100_0001), epoch_counter(1, 1));
+    }
+
+    #[test]
+    fn timestamp_policies() {
+        assert!(TimestampPolicy::default().permits(100, 100));
+        assert!(!TimestampPolicy::Exact.permits(100, 101));
+        assert!(TimestampPolicy::NotOlder.permits(100, 101));
+        assert!(!TimestampPolicy::NotOlder.permits(100, 99));
+    }
+
+    #[test]
+    fn validity_windows() {
+        let policy = ValidityPolicy::default();
+        assert_eq!(policy.check(None, None, None), Ok(()));
+        assert_eq!(policy.check(Some(100), Some(200), Some(100)), Ok(()));
+        assert_eq!(policy.check(Some(100), Some(200), Some(200)), Ok(()));
+        assert_eq!(
+            policy.check(Some(100), Some(200), Some(201)),
+            Err(RustbootError::ImageExpired)
+        );
+        assert_eq!(
+            policy.check(Some(100), None, Some(99)),
+            Err(RustbootError::ImageExpired)
+        );
+        assert_eq!(policy.check(None, Some(200), Some(0)), Ok(()));
+        // the RTC is unset
+        assert_eq!(
+            policy.check(None, Some(200), None),
+            Err(RustbootError::TimeUnavailable)
+        );
+        assert_eq!(ValidityPolicy::Allow.check(None, Some(200), None), Ok(()));
+        assert_eq!(
+            ValidityPolicy::Allow.check(None, Some(200), Some(201)),
+            Err(RustbootError::ImageExpired)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! `cargo xtask build-all --boards <board>,<board>.. [-j <jobs>] <boot-ver> <updt-ver>`
+//!
+//! Builds and signs the example firmware of several mcu boards (i.e. runs `cargo <board> build
+//! pkgs-for` and `cargo <board> sign pkgs-for` for each board) and collects each board's
+//! artifacts into `<out>/v<updt_ver>/<board>`, ex: for a release. The directory also gets an
+//! `artifacts.json`, mapping each board to its collected artifacts. With a derived update version
+//! (i.e. `git` or `cargo`, see `version.rs`), the directory is named after the first board's
+//! version string instead, ex: `<out>/v1.2.3-4-gdeadbee`.
+//!
+//! A board's tasks are compiled with the board's feature (see `.cargo/config.toml`), so each board
+//! is built by its own `xtask` process, with its own target directory (i.e.
+//! `target/build-all/<board>`). Up to `--jobs` boards are built at once and each board's output
+//! goes to the `build.log` in its directory, rather than the terminal. A board that fails doesn't
+//! stop the others, failures are reported once all boards are done.
+//!
+//! *Note: the boards' firmware shares cargo's target directory, so their compiles are partly
+//! serialized by its lock.*
+
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    cli::{BuildAllArgs, Versions},
+    manifest::BoardManifest,
+    root_dir, Artifacts,
+};
+
+pub fn build_all(args: &BuildAllArgs) -> Result<Vec<PathBuf>, anyhow::Error> {
+    if args.jobs == 0 {
+        bail!("`--jobs` must be at least 1");
+    }
+    for (i, board) in args.boards.iter().enumerate() {
+        if args.boards[..i].contains(board) {
+            bail!("{} is listed more than once", board);
+        }
+        BoardManifest::load(board)
+            .with_context(|| format!("can't build {}, only mcu boards are supported", board))?;
+    }
+    let (_, updt) = args.versions.resolve(&args.boards[0])?;
+    let out = root_dir().join(&args.out).join(match updt.label {
+        Some(label) => label,
+        None => format!("v{}", updt.number),
+    });
+
+    // boards are taken from `args.boards` in order, by `jobs` workers
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(BTreeMap::new());
+    thread::scope(|scope| {
+        for _ in 0..args.jobs.min(args.boards.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let board = match args.boards.get(i) {
+                    Some(board) => board,
+                    None => break,
+                };
+                println!("building {}", board);
+                let result = build_board(board, &args.versions, &out.join(board));
+                match &result {
+                    Ok(_) => println!("built {}", board),
+                    Err(e) => println!("{} failed: {:#}", board, e),
+                }
+                results.lock().unwrap().insert(i, result);
+            });
+        }
+    });
+
+    let mut collected = BTreeMap::new();
+    let mut failed = Vec::new();
+    for (i, result) in results.into_inner().unwrap() {
+        let board = &args.boards[i];
+        match result {
+            Ok(artifacts) => {
+                collected.insert(board, artifacts);
+            }
+            Err(_) => failed.push(board.as_str()),
+        }
+    }
+    fs::create_dir_all(&out)?;
+    let index = out.join("artifacts.json");
+    fs::write(&index, serde_json::to_string_pretty(&collected)?)?;
+    println!("collected artifacts into {}", out.display());
+    if !failed.is_empty() {
+        bail!(
+            "{} failed, see the `build.log` in {}/<board>",
+            failed.join(", "),
+            out.display()
+        );
+    }
+    let mut artifacts = collected.into_values().flatten().collect::<Vec<_>>();
+    artifacts.push(index);
+    Ok(artifacts)
+}
+
+/// Builds and signs `board`'s example firmware, and copies the artifacts into `dir`. Returns the
+/// copies.
+fn build_board(
+    board: &str,
+    versions: &Versions,
+    dir: &Path,
+) -> Result<Vec<PathBuf>, anyhow::Error> {
+    // an earlier build's artifacts (of the same version) are replaced
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    fs::create_dir_all(dir)?;
+    let mut log = File::create(dir.join("build.log"))?;
+    let (boot_ver, updt_ver) = (versions.boot_ver.to_string(), versions.updt_ver.to_string());
+    let mut artifacts = board_task(board, &["build", "pkgs-for"], &mut log)?;
+    artifacts.extend(board_task(
+        board,
+        &["sign", "pkgs-for", &boot_ver, &updt_ver],
+        &mut log,
+    )?);
+
+    let mut copies = Vec::new();
+    for artifact in artifacts {
+        let name = artifact
+            .file_name()
+            .with_context(|| format!("{} isn't a file", artifact.display()))?;
+        let copy = dir.join(name);
+        fs::copy(&artifact, &copy).with_context(|| format!("can't copy {}", artifact.display()))?;
+        copies.push(copy);
+    }
+    Ok(copies)
+}
+
+/// Runs `cargo <board> <task> --json` in a child process, logging its output to `log`. Returns
+/// the paths of the artifacts it produced.
+fn board_task(board: &str, task: &[&str], log: &mut File) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo"));
+    let target_dir = root_dir().join("target/build-all").join(board);
+    writeln!(log, "$ cargo {} {} --json", board, task.join(" "))?;
+    let output = Command::new(cargo)
+        .current_dir(root_dir())
+        .args(["run", "-p", "xtask", "--features", board, "--target-dir"])
+        .arg(&target_dir)
+        .args(["--", board])
+        .args(task)
+        .arg("--json")
+        .stderr(log.try_clone()?)
+        .output()?;
+    log.write_all(&output.stdout)?;
+    if !output.status.success() {
+        bail!(
+            "`cargo {} {}` failed ({})",
+            board,
+            task.join(" "),
+            output.status
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json = stdout.lines().last().unwrap_or_default();
+    let artifacts: Artifacts = serde_json::from_str(json)
+        .with_context(|| format!("`cargo {} {}` printed no artifacts", board, task.join(" ")))?;
+    Ok(artifacts.artifacts)
+}
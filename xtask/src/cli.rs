@@ -0,0 +1,287 @@
+//! xtask's command line i.e. `cargo xtask <command>` and `cargo <board> <task>`.
+//!
+//! Board aliases (see `.cargo/config.toml`) prepend the board's name, so a board's tasks are
+//! parsed as an external subcommand and then as a [`BoardCli`], with the board as its name.
+
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    new_board::NewBoardArgs,
+    version::{parse_version, VersionArg},
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "xtask",
+    about = "Builds, signs and flashes rustBoot and its example firmware"
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub output: OutputArgs,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Print the paths of built and signed artifacts as JSON, on the last line of stdout
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run tests
+    Test {
+        #[command(subcommand)]
+        what: TestTarget,
+    },
+    /// Scaffold a new mcu board
+    NewBoard(NewBoardArgs),
+    /// Flash a self-test in place of rustBoot and run it, to check a board's flash driver. It
+    /// erases the update and swap partitions and re-flashes rustBoot once it's done
+    Selftest {
+        /// The (mcu) board to test
+        board: String,
+    },
+    /// Run the hardware-in-the-loop test of the update flow i.e. flash instrumented firmware and
+    /// check (over RTT) that updates are swapped in, confirmed and rolled back. It erases the board
+    /// and leaves it running the instrumented firmware
+    Hil {
+        /// The (mcu) board to test
+        board: String,
+    },
+    /// Build and sign the example firmware of several (mcu) boards in parallel, and collect the
+    /// artifacts into a versioned directory
+    BuildAll(BuildAllArgs),
+    /// Generate the golden signed images (in `rustBoot/test-vectors`) that rustBoot's tests parse
+    /// and verify
+    GenVectors {
+        /// Don't write the images, fail if the committed ones differ from freshly signed ones
+        #[arg(long)]
+        check: bool,
+    },
+    /// A board's tasks i.e. `cargo <board> <task>`, see `cargo <board> --help`
+    #[command(external_subcommand)]
+    Board(Vec<String>),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TestTarget {
+    /// Run the workspace's tests
+    #[command(name = "rustBoot")]
+    RustBoot,
+}
+
+/// A board's tasks, the board's name is the first argument.
+#[derive(Debug, Parser)]
+pub struct BoardCli {
+    #[command(flatten)]
+    pub output: OutputArgs,
+    #[command(subcommand)]
+    pub task: Task,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Task {
+    /// Build rustBoot and/or the board's example firmware
+    Build {
+        #[command(subcommand)]
+        what: BuildTarget,
+    },
+    /// Sign the example firmware, a fit-image or rustBoot
+    Sign {
+        #[command(subcommand)]
+        what: SignTarget,
+    },
+    /// Flash the signed example firmware or rustBoot
+    Flash {
+        #[command(subcommand)]
+        what: FlashTarget,
+    },
+    /// Build, sign and flash rustBoot and the example firmware
+    BuildSignFlash {
+        #[command(subcommand)]
+        what: BuildSignFlashTarget,
+    },
+    /// Erase the partitions' trailers and flash the trailer magic, to be used ONLY for testing
+    EraseAndFlashTrailerMagic {
+        /// The trailers are kept in the manifest's metadata sectors i.e. rustBoot is built with
+        /// the `metadata-sector` feature
+        #[arg(long)]
+        metadata_sector: bool,
+    },
+    /// Generate files from the board's manifest
+    Gen {
+        #[command(subcommand)]
+        what: GenTarget,
+    },
+    /// Derive a device's image key from its unique ID and register it, for encrypted updates
+    Provision(ProvisionArgs),
+    /// Write the UICR registers rustBoot expects i.e. the bootloader's start address, the NFC
+    /// pins and (optionally) APPROTECT, nRF parts only
+    ProvisionUicr(UicrArgs),
+    /// Program the OTP for secure boot i.e. the boot key's hash, secure boot and (optionally) the
+    /// debug port's lock, rp2350 only. OTP can't be erased
+    ProvisionOtp(OtpArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BuildTarget {
+    /// Build rustBoot and the example firmware
+    PkgsFor,
+    /// Build rustBoot
+    #[command(name = "rustBoot-only")]
+    RustBootOnly,
+    /// Build the C bindings to rustBoot's update API i.e. `librustboot.a` and `rustboot.h`
+    Ffi,
+    /// Build stage0 of a two-stage bootloader i.e. boards whose manifest has a `[stage0]`
+    Stage0,
+    /// Build rustBoot as stage1 of a two-stage bootloader, to run from a stage1 slot
+    Stage1 {
+        /// The stage1 slot
+        #[arg(value_parser = ["a", "b"])]
+        slot: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SignTarget {
+    /// Sign the example firmware
+    PkgsFor(Versions),
+    /// Sign a fit-image, in the board's `apertis` directory
+    FitImage {
+        /// The fit-image's `.its` file
+        its_name: String,
+    },
+    /// Sign rustBoot, as a second stage (rpi4) or for the bootrom's secure boot (rp2350)
+    #[command(name = "rustBoot")]
+    RustBoot {
+        /// rustBoot's version
+        version: u32,
+    },
+    /// Combine rustBoot and the signed example firmware into a single Intel HEX file, for
+    /// production programming
+    FactoryImage(Versions),
+    /// Wrap rustBoot in an i.MX HAB image or insert its CSF (imx8mn)
+    HabImage {
+        /// The CSF generated by NXP's CST, to insert into the HAB image
+        csf: Option<PathBuf>,
+    },
+    /// Build and sign rustBoot as stage1 of a two-stage bootloader, for a stage1 slot
+    Stage1 {
+        /// The stage1 slot
+        #[arg(value_parser = ["a", "b"])]
+        slot: String,
+        /// stage1's version, stage0 boots the newest valid stage1
+        version: u32,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FlashTarget {
+    /// Flash the signed example firmware
+    SignedPkg {
+        #[command(flatten)]
+        versions: Versions,
+        #[command(flatten)]
+        verify: VerifyArgs,
+    },
+    /// Flash rustBoot
+    #[command(name = "rustBoot")]
+    RustBoot,
+    /// Flash stage0 of a two-stage bootloader
+    Stage0,
+    /// Flash a signed stage1 to its slot
+    Stage1 {
+        /// The stage1 slot
+        #[arg(value_parser = ["a", "b"])]
+        slot: String,
+        /// The signed stage1's version
+        version: u32,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BuildSignFlashTarget {
+    /// Build, sign and flash rustBoot and the example firmware
+    #[command(name = "rustBoot")]
+    RustBoot {
+        #[command(flatten)]
+        versions: Versions,
+        #[command(flatten)]
+        verify: VerifyArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GenTarget {
+    /// Generate the partition layout i.e. `rustBoot/src/layouts/<board>.rs` and the
+    /// bootloader's and firmware's `partitions.x` (and, for a two-stage bootloader, the
+    /// bootloader's `stage0.x` and `stage1.x`)
+    Layout,
+}
+
+/// Versions of the example firmware, each a number or `git`/`cargo` i.e. derived from `git
+/// describe` or the firmware crate's `Cargo.toml` (see `version.rs`).
+#[derive(Debug, Args)]
+pub struct Versions {
+    /// Version of the boot firmware
+    #[arg(value_parser = parse_version)]
+    pub boot_ver: VersionArg,
+    /// Version of the update firmware
+    #[arg(value_parser = parse_version)]
+    pub updt_ver: VersionArg,
+}
+
+#[derive(Debug, Args)]
+pub struct BuildAllArgs {
+    /// The (mcu) boards to build, comma-separated ex: `nrf52840,stm32f411`
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub boards: Vec<String>,
+    /// The number of boards built at once
+    #[arg(short, long, default_value_t = 2)]
+    pub jobs: usize,
+    /// The artifacts are collected into `<out>/v<updt_ver>/<board>` (or, with a derived update
+    /// version, `<out>/<version string>/<board>`)
+    #[arg(long, default_value = "dist")]
+    pub out: PathBuf,
+    #[command(flatten)]
+    pub versions: Versions,
+}
+
+#[derive(Debug, Args)]
+pub struct ProvisionArgs {
+    /// The device's unique ID, in hex (ex: as read from its FICR or UID registers)
+    #[arg(long)]
+    pub uid: String,
+}
+
+#[derive(Debug, Args)]
+pub struct UicrArgs {
+    /// Lock the debug port (APPROTECT), for production parts. It can only be unlocked with a
+    /// full chip erase
+    #[arg(long)]
+    pub approtect: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct OtpArgs {
+    /// Disable the debug port, for production parts
+    #[arg(long)]
+    pub debug_disable: bool,
+    /// Confirm that the OTP is to be programmed. Once secure boot is enabled, the part only boots
+    /// a rustBoot sealed with the boot key, for good
+    #[arg(long)]
+    pub irreversible: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Read back the boot and update partitions after flashing and compare them against the
+    /// signed images
+    #[arg(long)]
+    pub verify: bool,
+}
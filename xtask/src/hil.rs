@@ -0,0 +1,246 @@
+//! `cargo xtask hil <board>`
+//!
+//! A hardware-in-the-loop test of the update flow, on an mcu board attached to a debug probe. The
+//! board is erased and flashed with rustBoot and the instrumented firmware (see `boards/hil`),
+//! signed as the boot (v1) and update (v2) images. Each step then resets the board through the
+//! probe (i.e. `probe-run --no-flash`) and checks what the firmware reports over RTT:
+//!
+//! 1. v1 boots and triggers the update.
+//! 2. v2 is swapped in, booted for testing and confirms itself.
+//! 3. v2 stays.
+//! 4. a v3 that doesn't confirm itself is staged, v2 triggers it.
+//! 5. v3 is swapped in, booted for testing and left unconfirmed.
+//! 6. v3 is rolled back i.e. v2 is booted for testing (again) and confirms itself.
+//! 7. the rejected v3 is erased, v2 stays.
+//!
+//! The test stops at the first step that fails, with the firmware's output. Steps wait for the
+//! firmware's breakpoint i.e. a board that doesn't boot hangs the test, CI jobs should have a
+//! timeout.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use xshell::cmd;
+
+use crate::manifest::BoardManifest;
+use crate::{build_rustBoot_only, flash_rustBoot, mcu_elf, root_dir, signed_image};
+
+/// The instrumented firmware's command TLV and its flags, see `boards/hil`.
+const HIL_COMMAND: u16 = 0x8F00;
+const CONFIRM: u8 = 1 << 0;
+const TRIGGER: u8 = 1 << 1;
+
+/// An image of the instrumented firmware i.e. its version and what it does.
+#[derive(Clone, Copy)]
+struct Image {
+    version: u32,
+    command: u8,
+}
+
+const V1: Image = Image {
+    version: 1,
+    command: TRIGGER,
+};
+const V2: Image = Image {
+    version: 2,
+    command: CONFIRM | TRIGGER,
+};
+/// An update that doesn't confirm itself, it's rolled back.
+const V3: Image = Image {
+    version: 3,
+    command: 0,
+};
+
+/// What's done before the board is reset, for a step.
+enum Stage {
+    Nothing,
+    /// flash an image to the update partition
+    Update(Image),
+    /// erase the update partition's first sector i.e. the staged image's header
+    EraseUpdate,
+}
+
+/// A firmware report, see `boards/hil`.
+enum Event {
+    /// the boot image's version and the boot partition's state
+    Booted(u32, &'static str),
+    Confirmed,
+    Unconfirmed,
+    /// the update's version
+    Triggered(u32),
+    Idle,
+}
+
+struct Step {
+    name: &'static str,
+    stage: Stage,
+    /// the firmware's reports, in order
+    expect: &'static [Event],
+}
+
+const STEPS: &[Step] = &[
+    Step {
+        name: "v1 boots and triggers the update",
+        stage: Stage::Nothing,
+        expect: &[
+            Event::Booted(V1.version, "new"),
+            Event::Triggered(V2.version),
+        ],
+    },
+    Step {
+        name: "v2 is swapped in and confirms itself",
+        stage: Stage::Nothing,
+        expect: &[Event::Booted(V2.version, "testing"), Event::Confirmed],
+    },
+    Step {
+        name: "v2 stays",
+        stage: Stage::Nothing,
+        expect: &[Event::Booted(V2.version, "success"), Event::Idle],
+    },
+    Step {
+        name: "v3 is staged and triggered",
+        stage: Stage::Update(V3),
+        expect: &[
+            Event::Booted(V2.version, "success"),
+            Event::Triggered(V3.version),
+        ],
+    },
+    Step {
+        name: "v3 is swapped in and left unconfirmed",
+        stage: Stage::Nothing,
+        expect: &[Event::Booted(V3.version, "testing"), Event::Unconfirmed],
+    },
+    Step {
+        name: "v3 is rolled back, v2 confirms itself",
+        stage: Stage::Nothing,
+        expect: &[Event::Booted(V2.version, "testing"), Event::Confirmed],
+    },
+    Step {
+        name: "v3 is erased, v2 stays",
+        stage: Stage::EraseUpdate,
+        expect: &[Event::Booted(V2.version, "success"), Event::Idle],
+    },
+];
+
+impl fmt::Display for Event {
+    /// Formats the event as the firmware reports it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Booted(version, state) => {
+                write!(f, "hil: booted v{} {}", version, state)
+            }
+            Event::Confirmed => write!(f, "hil: confirmed"),
+            Event::Unconfirmed => write!(f, "hil: left unconfirmed"),
+            Event::Triggered(version) => write!(f, "hil: triggered v{}", version),
+            Event::Idle => write!(f, "hil: idle"),
+        }
+    }
+}
+
+pub fn hil(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    if manifest.esp.is_some() {
+        bail!(
+            "the hardware-in-the-loop test runs over a debug probe (and RTT), ESP boards aren't \
+             supported"
+        );
+    }
+    let chip = &manifest.board.chip;
+    let triple = &manifest.board.target;
+    let elf = mcu_elf(target, "hil")?;
+
+    build_rustBoot_only(target)?;
+    {
+        let _p = xshell::pushd(root_dir().join("boards/hil"))?;
+        cmd!("cargo build --release --features {target} --target {triple}").run()?;
+    }
+    let bin = signed_image(&format!("{}_hil.bin", target));
+    cmd!("rust-objcopy -O binary {elf} {bin}").run()?;
+    let mut artifacts = vec![elf.clone()];
+    for image in [V1, V2, V3].iter() {
+        artifacts.push(sign(&manifest, target, image)?);
+    }
+
+    cmd!("probe-rs-cli erase --chip {chip}").run()?;
+    flash_rustBoot(target)?;
+    download(chip, manifest.partitions.boot, &hil_image(target, &V1))?;
+    download(chip, manifest.partitions.update, &hil_image(target, &V2))?;
+
+    for (idx, step) in STEPS.iter().enumerate() {
+        match &step.stage {
+            Stage::Nothing => {}
+            Stage::Update(image) => {
+                download(chip, manifest.partitions.update, &hil_image(target, image))?
+            }
+            Stage::EraseUpdate => {
+                let pyocd_target = &manifest.board.pyocd_target;
+                let update = format!("0x{:x}", manifest.partitions.update);
+                cmd!("pyocd erase -t {pyocd_target} -s {update}").run()?;
+            }
+        }
+        let output = cmd!("probe-run --no-flash --chip {chip} {elf}")
+            .ignore_status()
+            .output()?;
+        let log = [output.stdout, output.stderr].concat();
+        let log = String::from_utf8_lossy(&log);
+        let res = match output.status.success() {
+            true => check_step(step, &log),
+            false => Err(String::from("the firmware panicked or couldn't be run")),
+        };
+        match res {
+            Ok(()) => println!("hil: {}/{} {}: ok", idx + 1, STEPS.len(), step.name),
+            Err(e) => {
+                println!("{}", log);
+                bail!(
+                    "{}'s hardware-in-the-loop test failed at step {}/{} ({}): {}",
+                    target,
+                    idx + 1,
+                    STEPS.len(),
+                    step.name,
+                    e
+                );
+            }
+        }
+    }
+    println!("hil: all {} steps passed", STEPS.len());
+    Ok(artifacts)
+}
+
+/// Checks that the firmware reported the step's events, in order.
+fn check_step(step: &Step, log: &str) -> Result<(), String> {
+    let mut rest = log;
+    for event in step.expect {
+        let report = event.to_string();
+        match rest.find(&report) {
+            Some(pos) => rest = &rest[pos + report.len()..],
+            None => return Err(format!("expected `{}`", report)),
+        }
+    }
+    Ok(())
+}
+
+/// Signs the instrumented firmware as an image, bound to the board.
+fn sign(manifest: &BoardManifest, target: &str, image: &Image) -> Result<PathBuf, anyhow::Error> {
+    let key = manifest.signing_key();
+    let version = image.version.to_string();
+    let command = format!("0x{:x}:{:02x}", HIL_COMMAND, image.command);
+
+    let _p = xshell::pushd(root_dir().join("rbsigner"))?;
+    cmd!("cargo run mcu-image ../boards/sign_images/signed_images/{target}_hil.bin nistp256 {key} {version} --target {target} --board {target} --custom-tlv {command}").run()?;
+    Ok(hil_image(target, image))
+}
+
+/// Returns the path of a signed image of the instrumented firmware.
+fn hil_image(target: &str, image: &Image) -> PathBuf {
+    signed_image(&format!("{}_hil_v{}_signed.bin", target, image.version))
+}
+
+/// Flashes an image to `addr`.
+fn download(chip: &str, addr: usize, image: &Path) -> Result<(), anyhow::Error> {
+    let addr = format!("0x{:x}", addr);
+    cmd!("probe-rs-cli download --format Bin --base-address {addr} --chip {chip} {image}").run()?;
+    Ok(())
+}
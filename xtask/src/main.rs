@@ -2,57 +2,146 @@
 #![allow(non_snake_case)]
 #![deny(unused_must_use)]
 
-#[cfg(feature = "mcu")]
-use rustBoot::constants::{BOOT_PARTITION_ADDRESS, PARTITION_SIZE, UPDATE_PARTITION_ADDRESS};
-use std::{env, path::PathBuf};
+use std::{fs, path::PathBuf};
 // use std::path::Path;
 
+use anyhow::{bail, Context};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
 use xshell::cmd;
 
+mod build_all;
+mod cli;
+mod hil;
+mod manifest;
+mod new_board;
+mod otp;
+mod provision;
+mod stage0;
+mod uicr;
+mod vectors;
+mod version;
+use cli::*;
+use manifest::{BoardManifest, ESP_MMU_PAGE_SIZE};
+use rustBoot::rbconstants::IMAGE_HEADER_SIZE;
+use version::Version;
+
+/// `--json` output i.e. the paths of built and signed artifacts.
+#[derive(Serialize, Deserialize)]
+struct Artifacts {
+    artifacts: Vec<PathBuf>,
+}
+
 fn main() -> Result<(), anyhow::Error> {
-    let args = env::args().skip(1).collect::<Vec<_>>();
-    let args = args.iter().map(|s| &**s).collect::<Vec<_>>();
-
-    match &args[..] {
-        ["test", "rustBoot"] => test_rustBoot(),
-        [board, "build", "pkgs-for"] => build_rustBoot(board),
-        [board, "sign", "pkgs-for", boot_ver, updt_ver] => sign_packages(board, boot_ver, updt_ver),
-        [board, "sign", "fit-image", its_name] => sign_fit_image(board, its_name),
-        #[cfg(feature = "mcu")]
-        [board, "flash", "signed-pkg", boot_ver, updt_ver] => {
-            flash_signed_fwimages(board, boot_ver, updt_ver)
-        }
-        [board, "flash", "rustBoot"] => flash_rustBoot(board),
-        [board, "build", "rustBoot-only"] => build_rustBoot_only(board),
-        #[cfg(feature = "mcu")]
-        [board, "build-sign-flash", "rustBoot", boot_ver, updt_ver] => {
-            full_image_flash(board, boot_ver, updt_ver)
-        }
-        #[cfg(feature = "mcu")]
-        [board, "erase-and-flash-trailer-magic"] => erase_and_flash_trailer_magic(board),
-        _ => {
-            println!("USAGE: cargo [board] test rustBoot");
-            println!("OR");
-            println!("USAGE: cargo [board] [build|sign|flash] [pkgs-for|signed-pkg] [boot-ver] [updt-ver]");
-            println!("OR");
-            println!("USAGE: cargo [board] [sign] [fit-image]");
-            println!("OR");
-            println!("USAGE: cargo [board] [build-sign-flash] [rustBoot] [boot-ver] [updt-ver]");
-            Ok(())
-        }
+    let cli = Cli::parse();
+    let (json, artifacts) = match cli.command {
+        Command::Test {
+            what: TestTarget::RustBoot,
+        } => (cli.output.json, test_rustBoot()?),
+        Command::NewBoard(args) => (cli.output.json, new_board::new_board(&args)?),
+        Command::Selftest { board } => (cli.output.json, selftest(&board)?),
+        Command::Hil { board } => (cli.output.json, hil::hil(&board)?),
+        Command::BuildAll(args) => (cli.output.json, build_all::build_all(&args)?),
+        Command::GenVectors { check } => (cli.output.json, vectors::gen_vectors(check)?),
+        Command::Board(args) => {
+            let board_cli = BoardCli::parse_from(&args);
+            let artifacts = run_task(&args[0], board_cli.task)?;
+            (cli.output.json || board_cli.output.json, artifacts)
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string(&Artifacts { artifacts })?);
+    }
+    Ok(())
+}
+
+/// Runs a board's task. Returns the paths of the artifacts it produced.
+fn run_task(target: &str, task: Task) -> Result<Vec<PathBuf>, anyhow::Error> {
+    match task {
+        Task::Build { what } => match what {
+            BuildTarget::PkgsFor => build_rustBoot(target),
+            BuildTarget::RustBootOnly => build_rustBoot_only(target),
+            BuildTarget::Ffi => build_ffi(target),
+            BuildTarget::Stage0 => stage0::build_stage0(target),
+            BuildTarget::Stage1 { slot } => stage0::build_stage1(target, &slot),
+        },
+        Task::Sign { what } => match what {
+            SignTarget::PkgsFor(versions) => {
+                let (boot, updt) = versions.resolve(target)?;
+                sign_packages(target, &boot, &updt)
+            }
+            SignTarget::FitImage { its_name } => sign_fit_image(target, &its_name),
+            SignTarget::RustBoot { version } => sign_rustBoot(target, version),
+            SignTarget::HabImage { csf } => sign_hab_image(target, csf),
+            SignTarget::Stage1 { slot, version } => stage0::sign_stage1(target, &slot, version),
+            SignTarget::FactoryImage(versions) => {
+                let (boot, updt) = versions.resolve(target)?;
+                factory_image(target, boot.number, updt.number)
+            }
+        },
+        Task::Flash { what } => match what {
+            FlashTarget::SignedPkg { versions, verify } => {
+                let (boot, updt) = versions.resolve(target)?;
+                let artifacts = flash_signed_fwimages(target, boot.number, updt.number)?;
+                if verify.verify {
+                    verify_signed_fwimages(target, boot.number, updt.number)?;
+                }
+                Ok(artifacts)
+            }
+            FlashTarget::RustBoot => flash_rustBoot(target),
+            FlashTarget::Stage0 => stage0::flash_stage0(target),
+            FlashTarget::Stage1 { slot, version } => stage0::flash_stage1(target, &slot, version),
+        },
+        Task::BuildSignFlash {
+            what: BuildSignFlashTarget::RustBoot { versions, verify },
+        } => {
+            let (boot, updt) = versions.resolve(target)?;
+            full_image_flash(target, &boot, &updt, verify.verify)
+        }
+        Task::EraseAndFlashTrailerMagic { metadata_sector } => {
+            erase_and_flash_trailer_magic(target, metadata_sector)
+        }
+        Task::Gen {
+            what: GenTarget::Layout,
+        } => gen_layout(target),
+        Task::Provision(args) => provision::provision(target, &args),
+        Task::ProvisionUicr(args) => uicr::provision_uicr(target, &args),
+        Task::ProvisionOtp(args) => otp::provision_otp(target, &args),
     }
 }
 
-fn test_rustBoot() -> Result<(), anyhow::Error> {
+fn test_rustBoot() -> Result<Vec<PathBuf>, anyhow::Error> {
     let _p = xshell::pushd(root_dir())?;
     cmd!("cargo test --workspace").run()?;
-    Ok(())
+    Ok(Vec::new())
 }
 
-fn build_rustBoot_only(target: &&str) -> Result<(), anyhow::Error> {
-    let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
+/// Builds the flash self-test (see `boards/selftest`) for an mcu board and runs it with
+/// `probe-run`, in place of rustBoot. rustBoot is re-flashed afterwards, whether the self-test
+/// passed or not.
+fn selftest(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let triple = &manifest.board.target;
+    let chip = manifest.bootloader_chip();
+    let elf = mcu_elf(target, "selftest")?;
+
+    let _p = xshell::pushd(root_dir().join("boards/selftest"))?;
+    cmd!("cargo build --release --features {target} --target {triple}").run()?;
+    let res = cmd!("probe-run --chip {chip} {elf}").run();
+    flash_rustBoot(target)?;
+    res.map_err(|e| anyhow::anyhow!("{}'s flash self-test failed: {}", target, e))?;
+    Ok(vec![elf])
+}
+
+fn build_rustBoot_only(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let board_dir = root_dir().join("boards/bootloaders").join(target);
+    if dev_unsigned(target)? {
+        println!("WARNING: `dev-unsigned` is enabled, this rustBoot boots UNSIGNED images.");
+        println!("WARNING: it's for development only and can't be used in factory images.");
+    }
+    let _p = xshell::pushd(&board_dir)?;
     match target {
-        &"rpi4" => {
+        "rpi4" => {
             cmd!("cargo build --release").run()?; // `
                                                   // if Path::new("kernel8.img").exists() {
                                                   //     cmd!("powershell -command \"del kernel8.img\"").run()?;
@@ -62,39 +151,106 @@ fn build_rustBoot_only(target: &&str) -> Result<(), anyhow::Error> {
             #[cfg(not(feature = "windows"))]
             cmd!("rust-objcopy --strip-all -O binary ../../target/aarch64-unknown-none-softfloat/release/kernel rustBoot.bin").run()?;
         }
-        &"nrf52840" => {
+        "rpi5" => {
             cmd!("cargo build --release").run()?;
+            #[cfg(feature = "windows")]
+            cmd!("rust-objcopy --strip-all -O binary ..\\..\\target\\aarch64-unknown-none-softfloat\\release\\kernel_2712 kernel_2712.img").run()?;
+            #[cfg(not(feature = "windows"))]
+            cmd!("rust-objcopy --strip-all -O binary ../../target/aarch64-unknown-none-softfloat/release/kernel_2712 kernel_2712.img").run()?;
         }
-        &"stm32f411" => {
+        // OpenSBI's payload i.e. a raw binary, packaged into the SPL's FIT (see the board's README)
+        "visionfive2" => {
             cmd!("cargo build --release").run()?;
+            cmd!("rust-objcopy --strip-all -O binary ../../target/riscv64gc-unknown-none-elf/release/rustBoot rustBoot.bin").run()?;
         }
-        &"stm32f446" => {
+        "nrf52840" => {
             cmd!("cargo build --release").run()?;
         }
-        &"stm32f469" => {
+        "stm32f411" => {
             cmd!("cargo build --release").run()?;
         }
-        &"stm32h723" => {
+        "stm32f446" => {
             cmd!("cargo build --release").run()?;
         }
-        &"stm32f746" => {
+        "stm32f469" => {
             cmd!("cargo build --release").run()?;
         }
-        &"stm32f334" => {
+        "stm32h723" => {
             cmd!("cargo build --release").run()?;
         }
-        &"rp2040" => {
+        "stm32f746" => {
+            cmd!("cargo build --release").run()?;
+        }
+        "stm32f334" => {
+            cmd!("cargo build --release").run()?;
+        }
+        "stm32f407" => {
+            cmd!("cargo build --release").run()?;
+        }
+        "stm32f769" => {
+            cmd!("cargo build --release").run()?;
+        }
+        "rp2040" => {
             cmd!("cargo build --release").run()?;
         }
+        "rp2350" => {
+            cmd!("cargo build --release").run()?;
+        }
+        // the ROM loads rustBoot as its second-stage bootloader i.e. an ESP image
+        "esp32s3" => {
+            cmd!("cargo build --release").run()?;
+            let elf = mcu_elf(target, target)?;
+            cmd!("esptool.py --chip esp32s3 elf2image --flash_mode dio --flash_size 8MB -o rustBoot.bin {elf}").run()?;
+        }
+        "imx8mn" => {
+            cmd!("cargo build --release").run()?;
+            cmd!("rust-objcopy --strip-all -O binary ../../target/aarch64-unknown-none-softfloat/release/imx8mn-rs imx8mn.bin").run()?;
+        }
         _ => {
             println!("board not supported");
+            return Ok(Vec::new());
         }
     }
 
-    Ok(())
+    let artifact = match target {
+        "rpi4" => board_dir.join("rustBoot.bin"),
+        "rpi5" => board_dir.join("kernel_2712.img"),
+        "imx8mn" => board_dir.join("imx8mn.bin"),
+        "esp32s3" => board_dir.join("rustBoot.bin"),
+        "visionfive2" => board_dir.join("rustBoot.bin"),
+        _ => mcu_elf(target, target)?,
+    };
+    Ok(vec![artifact])
+}
+
+/// Builds the C bindings (see `boards/ffi`) for an mcu board's firmware. The header is
+/// regenerated along with the static library.
+fn build_ffi(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let triple = &manifest.board.target;
+
+    let _p = xshell::pushd(root_dir().join("boards/ffi"))?;
+    cmd!("cargo build --release --features {target} --target {triple}").run()?;
+    Ok(vec![
+        root_dir()
+            .join("boards/target")
+            .join(triple)
+            .join("release/librustboot.a"),
+        root_dir().join("boards/ffi/include/rustboot.h"),
+    ])
 }
 
-fn build_rustBoot(target: &&str) -> Result<(), anyhow::Error> {
+/// Returns the path of an mcu board's (bootloader or firmware) ELF, built for its target.
+fn mcu_elf(target: &str, bin: &str) -> Result<PathBuf, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    Ok(root_dir()
+        .join("boards/target")
+        .join(&manifest.board.target)
+        .join("release")
+        .join(bin))
+}
+
+fn build_rustBoot(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
     let _p = xshell::pushd(
         root_dir()
             .join("boards/firmware")
@@ -109,318 +265,440 @@ fn build_rustBoot(target: &&str) -> Result<(), anyhow::Error> {
             .join("updt_fw_blinky_red"),
     )?;
     cmd!("cargo build --release").run()?;
-    build_rustBoot_only(target)?;
-    Ok(())
+    let mut artifacts = vec![
+        mcu_elf(target, &format!("{}_bootfw", target))?,
+        mcu_elf(target, &format!("{}_updtfw", target))?,
+    ];
+    artifacts.extend(build_rustBoot_only(target)?);
+    Ok(artifacts)
 }
 
-fn sign_fit_image(target: &&str, its_filename: &str) -> Result<(), anyhow::Error> {
-    match *target {
-        "rpi4" => {
-            let tmp_itb_filename = "unsigned-rpi4-apertis.itb";
+fn sign_fit_image(target: &str, its_filename: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    match target {
+        "rpi4" | "rpi5" | "visionfive2" => {
+            let tmp_itb_filename = format!("unsigned-{}-apertis.itb", target);
             let kf_path = "../boards/sign_images/keygen/ecc256.der";
 
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders/rpi4/apertis"))?;
+            let apertis_dir = root_dir()
+                .join("boards/bootloaders")
+                .join(target)
+                .join("apertis");
+            let _p = xshell::pushd(&apertis_dir)?;
             cmd!("mkimage -f {its_filename} {tmp_itb_filename}").run()?;
             let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run fit-image ../boards/bootloaders/rpi4/apertis/{tmp_itb_filename} nistp256 {kf_path}").run()?;
+            cmd!("cargo run fit-image ../boards/bootloaders/{target}/apertis/{tmp_itb_filename} nistp256 {kf_path}").run()?;
 
             // cleanup
             #[cfg(feature = "windows")]
             cmd!("powershell -command \"del kernel8.img\"").run()?;
             #[cfg(not(feature = "windows"))]
-            cmd!("rm -rf ../boards/bootloaders/rpi4/apertis/{tmp_itb_filename}").run()?;
-
-            Ok(())
+            cmd!("rm -rf ../boards/bootloaders/{target}/apertis/{tmp_itb_filename}").run()?;
+
+            // the signed fit-image is named after its timestamp i.e. `signed-v<timestamp>.itb`
+            let mut signed = Vec::new();
+            for entry in fs::read_dir(&apertis_dir)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if name.starts_with("signed-v") && name.ends_with(".itb") {
+                    signed.push((entry.metadata()?.modified()?, entry.path()));
+                }
+            }
+            Ok(signed
+                .into_iter()
+                .max()
+                .map(|(_, path)| path)
+                .into_iter()
+                .collect())
         }
         _ => unimplemented!(),
     }
 }
 
-fn sign_packages(target: &&str, boot_ver: &&str, updt_ver: &&str) -> Result<(), anyhow::Error> {
-    // let boot_ver = target[3].to_string();
-    // let updt_ver = target[4].to_string();
+/// Builds rpi4's first stage (`kernel8.img`) and signs rustBoot, which becomes the second stage.
+fn sign_rustBoot(target: &str, version: u32) -> Result<Vec<PathBuf>, anyhow::Error> {
+    match target {
+        "rpi4" => {
+            let kf_path = "../boards/sign_images/keygen/ecc256.der";
+            let version = version.to_string();
+            build_rustBoot_only(target)?;
 
-    match *target {
-        "nrf52840" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/nrf52840_bootfw -O binary nrf52840_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/nrf52840_updtfw -O binary nrf52840_updtfw.bin").run()?;
+            let _p = xshell::pushd(root_dir().join("boards/bootloaders/rpi4-stage1"))?;
+            cmd!("cargo build --release").run()?;
+            #[cfg(feature = "windows")]
+            cmd!("rust-objcopy --strip-all -O binary ..\\..\\target\\aarch64-unknown-none-softfloat\\release\\stage1 kernel8.img").run()?;
+            #[cfg(not(feature = "windows"))]
+            cmd!("rust-objcopy --strip-all -O binary ../../target/aarch64-unknown-none-softfloat/release/stage1 kernel8.img").run()?;
 
             let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/nrf52840_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/nrf52840_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
+            cmd!("cargo run mcu-image ../boards/bootloaders/rpi4/rustBoot.bin nistp256 {kf_path} {version}").run()?;
+            Ok(vec![
+                root_dir().join("boards/bootloaders/rpi4-stage1/kernel8.img"),
+                signed_image(&format!("rustBoot_v{}_signed.bin", version)),
+            ])
+        }
+        // sealed for the bootrom i.e. rustBoot is signed with the boot key, `picotool seal` also
+        // writes the OTP settings (see `provision-otp`) that make the bootrom check it
+        "rp2350" => {
+            let manifest = BoardManifest::load(target)?;
+            let key = manifest
+                .boot_key()
+                .with_context(|| format!("{}'s manifest has no `boot_key`", target))?;
+            let major = version.to_string();
+            build_rustBoot_only(target)?;
+            let elf = mcu_elf(target, target)?;
+            let (sealed, otp) = sealed_rustBoot(target);
+            cmd!("picotool seal --sign --major {major} {elf} {sealed} {key} {otp}").run()?;
+            Ok(vec![sealed, otp])
         }
-        "stm32f411" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f411_bootfw -O binary stm32f411_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f411_updtfw -O binary stm32f411_updtfw.bin").run()?;
+        _ => unimplemented!(),
+    }
+}
 
-            let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f411_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f411_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
-        }
-        "stm32f446" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f446_bootfw -O binary stm32f446_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f446_updtfw -O binary stm32f446_updtfw.bin").run()?;
+/// Returns the paths of the rp2350's sealed rustBoot (see `sign_rustBoot`) and of the OTP
+/// settings written along with it.
+fn sealed_rustBoot(target: &str) -> (PathBuf, PathBuf) {
+    let board_dir = root_dir().join("boards/bootloaders").join(target);
+    (
+        board_dir.join("rustBoot_sealed.elf"),
+        board_dir.join("otp.json"),
+    )
+}
 
-            let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f446_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f446_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
-        }
-        "stm32f469" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f469_bootfw -O binary stm32f469_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f469_updtfw -O binary stm32f469_updtfw.bin").run()?;
+/// Wraps rustBoot in an i.MX HAB image, along with a CSF description for NXP's CST. Given the
+/// CSF generated by CST, inserts it into the HAB image instead.
+fn sign_hab_image(target: &str, csf: Option<PathBuf>) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let board_dir = root_dir().join("boards/bootloaders").join(target);
+    match target {
+        "imx8mn" => match csf {
+            None => {
+                build_rustBoot_only(target)?;
+                let _p = xshell::pushd(root_dir().join("rbsigner"))?;
+                cmd!("cargo run imx-image ../boards/bootloaders/imx8mn/imx8mn.bin 0x912000")
+                    .run()?;
+                Ok(vec![
+                    board_dir.join("imx8mn_hab.bin"),
+                    board_dir.join("imx8mn_hab.csf"),
+                ])
+            }
+            Some(csf) => {
+                let csf = fs::canonicalize(csf)?;
+                let _p = xshell::pushd(root_dir().join("rbsigner"))?;
+                cmd!("cargo run imx-csf ../boards/bootloaders/imx8mn/imx8mn_hab.bin {csf}")
+                    .run()?;
+                Ok(vec![board_dir.join("imx8mn_hab_signed.bin")])
+            }
+        },
+        _ => unimplemented!(),
+    }
+}
 
-            let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f469_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f469_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
+fn sign_packages(
+    target: &str,
+    boot: &Version,
+    updt: &Version,
+) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let (boot_ver, updt_ver) = (boot.number.to_string(), updt.number.to_string());
+    // a derived version is also embedded as a string, see `version.rs`
+    let (boot_label, updt_label) = (version_string_arg(boot), version_string_arg(updt));
+    let triple = &manifest.board.target;
+    let key = manifest.signing_key();
+
+    let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
+    match manifest.esp {
+        None => {
+            cmd!("rust-objcopy -I elf32-littlearm ../../target/{triple}/release/{target}_bootfw -O binary {target}_bootfw.bin").run()?;
+            cmd!("rust-objcopy -I elf32-littlearm ../../target/{triple}/release/{target}_updtfw -O binary {target}_updtfw.bin").run()?;
+        }
+        Some(_) => {
+            esp_app_image(&manifest, &format!("{}_bootfw", target))?;
+            esp_app_image(&manifest, &format!("{}_updtfw", target))?;
         }
-        "stm32h723" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32h723_bootfw -O binary stm32h723_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32h723_updtfw -O binary stm32h723_updtfw.bin").run()?;
+    }
 
-            let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32h723_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32h723_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
-        }
-        "stm32f746" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f746_bootfw -O binary stm32f746_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f746_updtfw -O binary stm32f746_updtfw.bin").run()?;
+    // padded to the board's flash write unit (see `rbsigner::profile`) and bound to the board
+    let _p = xshell::pushd(root_dir().join("rbsigner"))?;
+    cmd!("cargo run mcu-image ../boards/sign_images/signed_images/{target}_bootfw.bin nistp256 {key} {boot_ver} --target {target} --board {target} {boot_label...}").run()?;
+    cmd!("cargo run mcu-image ../boards/sign_images/signed_images/{target}_updtfw.bin nistp256 {key} {updt_ver} --target {target} --board {target} {updt_label...}").run()?;
+    Ok(vec![
+        signed_image(&format!("{}_bootfw_v{}_signed.bin", target, boot_ver)),
+        signed_image(&format!("{}_updtfw_v{}_signed.bin", target, updt_ver)),
+    ])
+}
 
-            let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f746_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f746_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
-        }
-        "stm32f334" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f334_bootfw -O binary stm32f334_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv7em-none-eabihf/release/stm32f334_updtfw -O binary stm32f334_updtfw.bin").run()?;
+/// Returns rbsigner's `--version-string` option for a derived version, nothing for a number.
+fn version_string_arg(version: &Version) -> Vec<&str> {
+    match &version.label {
+        Some(label) => vec!["--version-string", label.as_str()],
+        None => Vec::new(),
+    }
+}
 
-            let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f334_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/stm32f334_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
-        }
-        "rp2040" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv6m-none-eabi/release/rp2040_bootfw -O binary rp2040_bootfw.bin").run()?;
-            cmd!("rust-objcopy -I elf32-littlearm ../../target/thumbv6m-none-eabi/release/rp2040_updtfw -O binary rp2040_updtfw.bin").run()?;
+/// Converts an ESP board's firmware (i.e. `boards/target/<triple>/release/<bin>`) to an ESP app
+/// image, `<bin>.bin` in the current directory. The image is padded with `0xFF` so it starts on
+/// an MMU page once it follows the rustBoot header, see `rustBoot::image::esp::app_image_start`.
+fn esp_app_image(manifest: &BoardManifest, bin: &str) -> Result<(), anyhow::Error> {
+    let triple = &manifest.board.target;
+    let chip = &manifest.board.name;
+    let image = format!("{}.bin", bin);
+    cmd!("espflash save-image --chip {chip} ../../target/{triple}/release/{bin} {image}").run()?;
+
+    let fw_base = manifest.partitions.boot + IMAGE_HEADER_SIZE;
+    let padding = (ESP_MMU_PAGE_SIZE - fw_base % ESP_MMU_PAGE_SIZE) % ESP_MMU_PAGE_SIZE;
+    let padded = [vec![0xFF; padding], fs::read(&image)?].concat();
+    fs::write(&image, padded)?;
+    Ok(())
+}
 
-            let _p = xshell::pushd(root_dir().join("rbsigner"))?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/rp2040_bootfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {boot_ver}").run()?;
-            cmd!("cargo run mcu-image ../boards/sign_images/signed_images/rp2040_updtfw.bin nistp256 ../boards/sign_images/keygen/ecc256.der {updt_ver}").run()?;
-            Ok(())
-        }
-        _ => todo!(),
+/// Combines rustBoot (its ELF) and the signed example firmware into a factory image i.e.
+/// `<board>_factory_v<boot-ver>_v<updt-ver>.hex`, so a board can be programmed with a single file.
+fn factory_image(
+    target: &str,
+    boot_ver: u32,
+    updt_ver: u32,
+) -> Result<Vec<PathBuf>, anyhow::Error> {
+    if dev_unsigned(target)? {
+        bail!(
+            "{}'s rustBoot is built with `dev-unsigned`, refusing to assemble a factory image",
+            target
+        );
     }
+    if BoardManifest::load(target)?.esp.is_some() {
+        bail!("factory images aren't supported for ESP boards, as rustBoot isn't an ELF there");
+    }
+    let manifest = root_dir()
+        .join("boards/manifests")
+        .join(format!("{}.toml", target));
+    let bootloader = mcu_elf(target, target)?;
+    let boot = signed_image(&format!("{}_bootfw_v{}_signed.bin", target, boot_ver));
+    let updt = signed_image(&format!("{}_updtfw_v{}_signed.bin", target, updt_ver));
+    let output = signed_image(&format!(
+        "{}_factory_v{}_v{}.hex",
+        target, boot_ver, updt_ver
+    ));
+
+    let _p = xshell::pushd(root_dir().join("rbsigner"))?;
+    cmd!("cargo run assemble {manifest} {bootloader} {boot} {updt} {output}").run()?;
+    Ok(vec![output])
 }
 
-#[cfg(feature = "mcu")]
-#[rustfmt::skip]
-fn flash_signed_fwimages(target: &&str, boot_ver: &&str, updt_ver: &&str) -> Result<(), anyhow::Error> {
-    match *target {
-        "nrf52840" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip nRF52840_xxAA nrf52840_bootfw_v{boot_ver}_signed.bin").run()?;
-
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip nRF52840_xxAA nrf52840_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        "stm32f411" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip stm32f411vetx stm32f411_bootfw_v{boot_ver}_signed.bin").run()?;
+/// Returns true if the board's rustBoot is built with the `dev-unsigned` feature i.e. it's one of
+/// its bootloader's default features, see `rustBoot-update`.
+fn dev_unsigned(target: &str) -> Result<bool, anyhow::Error> {
+    let path = root_dir()
+        .join("boards/bootloaders")
+        .join(target)
+        .join("Cargo.toml");
+    let cargo_toml: toml::Value = toml::from_str(&fs::read_to_string(&path)?)?;
+    let default = cargo_toml
+        .get("features")
+        .and_then(|features| features.get("default"))
+        .and_then(|default| default.as_array());
+    Ok(default.map_or(false, |features| {
+        features
+            .iter()
+            .any(|feature| feature.as_str() == Some("dev-unsigned"))
+    }))
+}
 
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip stm32f411vetx stm32f411_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        "stm32f446" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip stm32f446retx stm32f446_bootfw_v{boot_ver}_signed.bin").run()?;
+/// Returns the path of an image signed by `rbsigner` i.e. in `boards/sign_images/signed_images`.
+fn signed_image(name: &str) -> PathBuf {
+    root_dir()
+        .join("boards/sign_images/signed_images")
+        .join(name)
+}
 
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip stm32f446retx stm32f446_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        "stm32f469" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip STM32F469NIHx stm32f469_bootfw_v{boot_ver}_signed.bin").run()?;
+#[rustfmt::skip]
+fn flash_signed_fwimages(target: &str, boot_ver: u32, updt_ver: u32) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let (boot_ver, updt_ver) = (boot_ver.to_string(), updt_ver.to_string());
+    let chip = &manifest.board.chip;
+
+    let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
+    // ESP parts are flashed by flash offset i.e. relative to the bootloader
+    if manifest.esp.is_some() {
+        let boot_offset = format!("0x{:x}", manifest.partitions.boot - manifest.partitions.bootloader);
+        cmd!("espflash write-bin {boot_offset} {target}_bootfw_v{boot_ver}_signed.bin").run()?;
+        let updt_offset = format!("0x{:x}", manifest.partitions.update - manifest.partitions.bootloader);
+        cmd!("espflash write-bin {updt_offset} {target}_updtfw_v{updt_ver}_signed.bin").run()?;
+        return Ok(Vec::new());
+    }
+    let boot_part_addr = format!("0x{:x}", manifest.partitions.boot);
+    cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip {chip} {target}_bootfw_v{boot_ver}_signed.bin").run()?;
 
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip STM32F469NIHx stm32f469_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        "stm32h723" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip STM32H723ZGTx stm32h723_bootfw_v{boot_ver}_signed.bin").run()?;
+    let updt_part_addr = format!("0x{:x}", manifest.partitions.update);
+    cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip {chip} {target}_updtfw_v{updt_ver}_signed.bin").run()?;
+    Ok(Vec::new())
+}
 
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip STM32H723ZGTx stm32h723_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        "stm32f746" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip stm32f746zgtx stm32f746_bootfw_v{boot_ver}_signed.bin").run()?;
+fn flash_rustBoot(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let chip = manifest.bootloader_chip();
+
+    // a board with a boot key is flashed with its sealed rustBoot, as the bootrom refuses an
+    // unsigned one once secure boot is enabled
+    if manifest.boot_key().is_some() {
+        let (sealed, _) = sealed_rustBoot(target);
+        let elf = mcu_elf(target, target)?;
+        let stale = match (fs::metadata(&sealed), fs::metadata(&elf)) {
+            (Ok(sealed), Ok(elf)) => sealed.modified()? < elf.modified()?,
+            _ => true,
+        };
+        if stale {
+            bail!(
+                "rustBoot isn't sealed or was rebuilt since, run `cargo {} sign rustBoot <version>`",
+                target
+            );
+        }
+        cmd!("probe-rs-cli download --chip {chip} {sealed}").run()?;
+        return Ok(Vec::new());
+    }
 
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip stm32f746zgtx stm32f746_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        "stm32f334" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip stm32f334r8tx stm32f334_bootfw_v{boot_ver}_signed.bin").run()?;
+    let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
+    // rustBoot goes to flash offset `0x0`, followed by the ESP partition table
+    if let Some(esp) = &manifest.esp {
+        let table = format!("0x{:x}", esp.partition_table);
+        cmd!("espflash partition-table --to-binary -o partitions.bin partitions.csv").run()?;
+        cmd!("espflash write-bin 0x0 rustBoot.bin").run()?;
+        cmd!("espflash write-bin {table} partitions.bin").run()?;
+        return Ok(Vec::new());
+    }
+    cmd!("cargo flash --chip {chip} --release").run()?;
+    Ok(Vec::new())
+}
 
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip stm32f334r8tx stm32f334_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        "rp2040" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            let boot_part_addr = format!("0x{:x}", BOOT_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {boot_part_addr} --chip RP2040 rp2040_bootfw_v{boot_ver}_signed.bin").run()?;
+fn full_image_flash(
+    target: &str,
+    boot: &Version,
+    updt: &Version,
+    verify: bool,
+) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let chip = &manifest.board.chip;
+
+    let mut artifacts = build_rustBoot(target)?;
+    artifacts.extend(sign_packages(target, boot, updt)?);
+    if manifest.board.mass_erase {
+        cmd!("probe-rs-cli erase --chip {chip}").run()?;
+    }
+    flash_signed_fwimages(target, boot.number, updt.number)?;
+    flash_rustBoot(target)?;
+    if verify {
+        verify_signed_fwimages(target, boot.number, updt.number)?;
+    }
+    Ok(artifacts)
+}
 
-            let updt_part_addr = format!("0x{:x}", UPDATE_PARTITION_ADDRESS);
-            cmd!("probe-rs-cli download --format Bin --base-address {updt_part_addr} --chip RP2040 rp2040_updtfw_v{updt_ver}_signed.bin").run()?;
-            Ok(())
-        }
-        _ => todo!(),
+/// Reads back the boot and update partitions (with pyocd) and compares them against the signed
+/// images, to catch flash-programming failures that `probe-rs-cli download` doesn't report.
+fn verify_signed_fwimages(target: &str, boot_ver: u32, updt_ver: u32) -> Result<(), anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    if manifest.esp.is_some() {
+        bail!("readback verification (with pyocd) isn't supported for ESP boards");
+    }
+    let images = [
+        (
+            manifest.partitions.boot,
+            signed_image(&format!("{}_bootfw_v{}_signed.bin", target, boot_ver)),
+        ),
+        (
+            manifest.partitions.update,
+            signed_image(&format!("{}_updtfw_v{}_signed.bin", target, updt_ver)),
+        ),
+    ];
+    for (addr, image) in images.iter() {
+        let expected = fs::read(image)?;
+        let readback = image.with_extension("readback");
+        let pyocd_target = &manifest.board.pyocd_target;
+        let savemem = format!(
+            "savemem 0x{:x} 0x{:x} {}",
+            addr,
+            expected.len(),
+            readback.display()
+        );
+        cmd!("pyocd cmd -t {pyocd_target} -c {savemem}").run()?;
+        let actual = fs::read(&readback)?;
+        fs::remove_file(&readback)?;
+
+        compare_readback(&expected, &actual)
+            .map_err(|e| anyhow::anyhow!("{} @ 0x{:x}: {}", image.display(), addr, e))?;
+        println!("verified {} @ 0x{:x}", image.display(), addr);
     }
+    Ok(())
 }
 
-fn flash_rustBoot(target: &&str) -> Result<(), anyhow::Error> {
-    match *target {
-        "nrf52840" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip nRF52840_xxAA --release").run()?;
-            Ok(())
-        }
-        "stm32f411" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip stm32f411vetx --release").run()?;
-            Ok(())
-        }
-        "stm32f446" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip stm32f446vetx --release").run()?;
-            Ok(())
-        }
-        "stm32f469" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip STM32F469NIHx --release").run()?;
-            Ok(())
-        }
-        "stm32h723" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip STM32H723ZGTx --release").run()?;
-            Ok(())
-        }
-        "stm32f746" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip stm32f746zgtx --release").run()?;
-            Ok(())
-        }
-        "stm32f334" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip stm32f334r8tx --release").run()?;
-            Ok(())
-        }
-        "rp2040" => {
-            let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
-            cmd!("cargo flash --chip RP2040 --release").run()?;
-            Ok(())
-        }
-        _ => todo!(),
+/// Compares a partition's readback against the image flashed to it.
+fn compare_readback(expected: &[u8], actual: &[u8]) -> Result<(), String> {
+    if actual.len() < expected.len() {
+        return Err(format!(
+            "short readback, {} of {} bytes",
+            actual.len(),
+            expected.len()
+        ));
+    }
+    match expected.iter().zip(actual).position(|(e, a)| e != a) {
+        Some(offset) => Err(format!(
+            "flash doesn't match the image, first difference at offset 0x{:x} ({} bytes differ)",
+            offset,
+            expected.iter().zip(actual).filter(|(e, a)| e != a).count()
+        )),
+        None => Ok(()),
     }
 }
 
-#[cfg(feature = "mcu")]
-fn full_image_flash(target: &&str, boot_ver: &&str, updt_ver: &&str) -> Result<(), anyhow::Error> {
-    match *target {
-        "nrf52840" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            cmd!("probe-rs-cli erase --chip nRF52840_xxAA").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
-        "stm32f411" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            cmd!("probe-rs-cli erase --chip stm32f411vetx").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
-        "stm32f446" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            cmd!("probe-rs-cli erase --chip stm32f446retx").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
-        "stm32f469" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            cmd!("probe-rs-cli erase --chip STM32F469NIHx").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
-        "stm32h723" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            cmd!("probe-rs-cli erase --chip STM32H723ZGTx").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
-        "stm32f746" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            cmd!("probe-rs-cli erase --chip stm32f746zgtx").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
-        "stm32f334" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            cmd!("probe-rs-cli erase --chip stm32f334r8tx").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
-        "rp2040" => {
-            build_rustBoot(target)?;
-            sign_packages(target, boot_ver, updt_ver)?;
-            //cmd!("probe-rs-cli erase --chip RP2040").run()?;
-            flash_signed_fwimages(target, boot_ver, updt_ver)?;
-            flash_rustBoot(target)?;
-            Ok(())
-        }
+/// Regenerates a board's partition layout (i.e. `rustBoot/src/layouts/<board>.rs` and the
+/// bootloader's and firmware's `partitions.x` linker fragments) from its manifest.
+fn gen_layout(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+
+    let layout = root_dir()
+        .join("rustBoot/src/layouts")
+        .join(format!("{}.rs", target));
+    fs::write(&layout, manifest.to_rust())?;
+    println!("generated {}", layout.display());
+
+    // ESP firmware are app images and rustBoot runs from RAM i.e. neither is linked against the
+    // partitions, the ESP partition table is generated instead
+    if manifest.esp.is_some() {
+        let table = root_dir()
+            .join("boards/bootloaders")
+            .join(target)
+            .join("partitions.csv");
+        fs::write(&table, manifest.to_esp_partition_table())?;
+        println!("generated {}", table.display());
+        return Ok(vec![layout, table]);
+    }
 
-        _ => todo!(),
+    let fragment = root_dir()
+        .join("boards/firmware")
+        .join(target)
+        .join("partitions.x");
+    fs::write(&fragment, manifest.to_linker_fragment())?;
+    println!("generated {}", fragment.display());
+
+    let bootloader_fragment = root_dir()
+        .join("boards/bootloaders")
+        .join(target)
+        .join("partitions.x");
+    fs::write(&bootloader_fragment, manifest.to_bootloader_fragment())?;
+    println!("generated {}", bootloader_fragment.display());
+    let mut generated = vec![layout, fragment, bootloader_fragment];
+
+    // a two-stage bootloader's fragments, see `manifest::Stage0`
+    let stage_fragments = manifest
+        .to_stage0_fragment()
+        .zip(manifest.to_stage1_fragment());
+    if let Some((stage0, stage1)) = stage_fragments {
+        let bootloader_dir = root_dir().join("boards/bootloaders").join(target);
+        for (name, contents) in [("stage0.x", stage0), ("stage1.x", stage1)] {
+            let path = bootloader_dir.join(name);
+            fs::write(&path, contents)?;
+            println!("generated {}", path.display());
+            generated.push(path);
+        }
     }
+    Ok(generated)
 }
 
 fn root_dir() -> PathBuf {
@@ -429,138 +707,44 @@ fn root_dir() -> PathBuf {
     xtask_dir
 }
 
-#[cfg(feature = "mcu")]
 /// to be used ONLY for testing.
-fn erase_and_flash_trailer_magic(target: &&str) -> Result<(), anyhow::Error> {
-    match *target {
-        "nrf52840" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t nrf52840 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t nrf52840 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t nrf52840 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t nrf52840 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t nrf52840 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        "stm32f411" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t stm32f411 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f411 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f411 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f411 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f411 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        "stm32f446" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t stm32f446 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f446 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f446 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f446 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f446 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        "stm32f4696" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t stm32f469 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f469 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f469 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f469 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f469 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        "stm32h723" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t stm32h723 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32h723 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32h723 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32h723 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32h723 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        "stm32f746" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t stm32f746 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f746 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f746 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f746 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f746 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        "stm32f334" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t stm32f334 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f334 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f334 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t stm32f334 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t stm32f334 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        "rp2040" => {
-            let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
-            // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
-            cmd!("pyocd erase -t rp2040 -s 0x0").run()?;
-            let boot_trailer_magic = format!("0x{:x}", BOOT_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t rp2040 -s {boot_trailer_magic}").run()?;
-            cmd!("pyocd flash -t rp2040 --base-address {boot_trailer_magic} trailer_magic.bin")
-                .run()?;
-
-            let updt_trailer_magic =
-                format!("0x{:x}", UPDATE_PARTITION_ADDRESS + PARTITION_SIZE - 4);
-            cmd!("pyocd erase -t rp2040 -s {updt_trailer_magic}").run()?;
-            cmd!("pyocd flash -t rp2040 --base-address {updt_trailer_magic} trailer_magic.bin")
-                .run()?;
-            Ok(())
-        }
-        _ => todo!(),
-    }
+///
+/// A trailer ends with its partition or, with `metadata_sector`, with the partition's metadata
+/// sector.
+fn erase_and_flash_trailer_magic(
+    target: &str,
+    metadata_sector: bool,
+) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let pyocd_target = &manifest.board.pyocd_target;
+    let (boot_trailer, updt_trailer) = match metadata_sector {
+        true => {
+            let (boot, update) = manifest
+                .metadata_sectors()
+                .with_context(|| format!("{}'s manifest reserves no metadata sectors", target))?;
+            let sector_size = manifest.flash.sector_size;
+            (boot + sector_size, update + sector_size)
+        }
+        false => {
+            let partition_size = manifest.partitions.size;
+            (
+                manifest.partitions.boot + partition_size,
+                manifest.partitions.update + partition_size,
+            )
+        }
+    };
+
+    let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
+    // just to ensure that an existing bootloader doesnt start to boot automatically - during a test
+    cmd!("pyocd erase -t {pyocd_target} -s 0x0").run()?;
+    let boot_trailer_magic = format!("0x{:x}", boot_trailer - 4);
+    cmd!("pyocd erase -t {pyocd_target} -s {boot_trailer_magic}").run()?;
+    cmd!("pyocd flash -t {pyocd_target} --base-address {boot_trailer_magic} trailer_magic.bin")
+        .run()?;
+
+    let updt_trailer_magic = format!("0x{:x}", updt_trailer - 4);
+    cmd!("pyocd erase -t {pyocd_target} -s {updt_trailer_magic}").run()?;
+    cmd!("pyocd flash -t {pyocd_target} --base-address {updt_trailer_magic} trailer_magic.bin")
+        .run()?;
+    Ok(Vec::new())
 }
@@ -0,0 +1,554 @@
+//! Board manifests i.e. `boards/manifests/<board>.toml`.
+//!
+//! A manifest describes a board's flash layout (partition addresses/sizes, sector size),
+//! the names used by our probe tools and the signing key. `xtask` reads it to flash and sign
+//! images and can emit the corresponding rust module (consumed by `rustBoot::constants`) and
+//! linker script fragments for the board's bootloader and firmware.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{bail, Context};
+use rustBoot::rbconstants::IMAGE_HEADER_SIZE;
+use serde::Deserialize;
+
+use crate::root_dir;
+
+/// The flash cache's MMU page size on Espressif parts, see `rustBoot::image::esp`.
+pub const ESP_MMU_PAGE_SIZE: usize = 0x10000;
+
+#[derive(Debug, Deserialize)]
+pub struct BoardManifest {
+    pub board: Board,
+    pub flash: Flash,
+    pub partitions: Partitions,
+    pub keys: Keys,
+    /// nRF parts only, see [`Uicr`]
+    pub uicr: Option<Uicr>,
+    /// see [`BootPin`]
+    pub boot_pin: Option<BootPin>,
+    /// Espressif parts only, see [`Esp`]
+    pub esp: Option<Esp>,
+    /// a two-stage bootloader, see [`Stage0`]
+    pub stage0: Option<Stage0>,
+    /// see [`HeaderScan`]
+    pub header_scan: Option<HeaderScan>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Board {
+    pub name: String,
+    /// rust target triple
+    pub target: String,
+    /// probe-rs chip name
+    pub chip: String,
+    /// chip name passed to `cargo flash`, if it differs from `chip`
+    pub bootloader_chip: Option<String>,
+    pub pyocd_target: String,
+    /// erase the entire chip before flashing, in `build-sign-flash`
+    #[serde(default = "default_true")]
+    pub mass_erase: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Flash {
+    /// the device's flash size, flash starts at the bootloader (i.e. `partitions.bootloader`)
+    pub size: usize,
+    pub sector_size: usize,
+    /// the programming unit (ex: the stm32h7's 32-byte flash words), writes are widened to whole
+    /// units. A power of two, at most 256 bytes.
+    #[serde(default = "default_write_size")]
+    pub write_size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Partitions {
+    /// start of flash, the bootloader occupies everything up to the boot partition
+    pub bootloader: usize,
+    pub size: usize,
+    pub boot: usize,
+    pub update: usize,
+    pub swap: usize,
+    /// the (single-sector) boot/update event log, if the board reserves one
+    pub log: Option<usize>,
+    /// the (single-sector) boot and update partitions' trailers, if the board reserves them i.e.
+    /// for rustBoot built with the `metadata-sector` feature
+    pub boot_metadata: Option<usize>,
+    pub update_metadata: Option<usize>,
+    /// the (single-sector) first-boot provisioning flag, if the board reserves one i.e. for
+    /// rustBoot built with the `provisioning` feature
+    pub provision: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Keys {
+    /// path to the signing key, relative to the repository root
+    pub signing_key: PathBuf,
+    /// path to the (secret) salt that device-unique image keys are derived with, relative to
+    /// the repository root
+    pub key_salt: Option<PathBuf>,
+    /// path to the device-key registry, relative to the repository root. Defaults to
+    /// `target/device-keys/<board>.json`.
+    pub device_keys: Option<PathBuf>,
+    /// path to the key the chip's bootrom verifies rustBoot with (i.e. the rp2350's secp256k1
+    /// boot key, in PEM), relative to the repository root
+    pub boot_key: Option<PathBuf>,
+}
+
+/// The nRF UICR (i.e. user information configuration registers) settings written by
+/// `cargo <board> provision-uicr`, along with the bootloader's start address.
+#[derive(Debug, Deserialize)]
+pub struct Uicr {
+    /// configure the NFC antenna pins (`NFCPINS`) as GPIOs
+    #[serde(default)]
+    pub nfc_pins_as_gpio: bool,
+}
+
+/// The ESP partition table (i.e. the one `espflash` and ESP-IDF's tools read), generated from
+/// the manifest's partitions by `cargo <board> gen layout`. Partition addresses are then flash
+/// addresses as read through the flash cache i.e. `partitions.bootloader` is flash offset `0x0`.
+#[derive(Debug, Deserialize)]
+pub struct Esp {
+    /// the partition table's flash offset, within the bootloader's region
+    pub partition_table: usize,
+}
+
+/// A GPIO (ex: a button) that, held at reset, keeps rustBoot from booting firmware, see
+/// `rustBoot_hal::boot_pin`.
+#[derive(Debug, Deserialize)]
+pub struct BootPin {
+    /// the GPIO port i.e. `0` for `P0` (nRF) or `GPIOA` (STM32), `1` for `P1` or `GPIOB` etc.
+    pub port: u8,
+    pub pin: u8,
+    /// the pin reads low while it's held (ex: a button to ground)
+    #[serde(default = "default_true")]
+    pub active_low: bool,
+    /// how long the pin must be held, in milliseconds
+    pub hold_ms: u32,
+}
+
+/// A two-stage bootloader i.e. a small, immutable stage0 (see `boards/stage0`) at the start of
+/// flash, that boots the newest valid stage1 (i.e. rustBoot, as a signed image) out of two slots.
+/// The stage0 and the stage1 slots split the bootloader's region.
+#[derive(Debug, Deserialize)]
+pub struct Stage0 {
+    /// stage0's size, from the start of flash
+    pub size: usize,
+    /// the stage1 slots, stage1 is linked right after the slot's header
+    pub stage1_a: usize,
+    pub stage1_b: usize,
+    /// the size of each stage1 slot
+    pub stage1_size: usize,
+}
+
+/// Where rustBoot looks for an image's header, for images padded to an alignment by their
+/// toolchain (i.e. rustBoot built with `header-scan`). The header is looked for at the
+/// partition's start and then every `align` bytes, up to `window` bytes into the partition.
+#[derive(Debug, Deserialize)]
+pub struct HeaderScan {
+    pub window: usize,
+    pub align: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_write_size() -> usize {
+    1
+}
+
+impl BoardManifest {
+    /// Loads and validates `boards/manifests/<board>.toml`.
+    pub fn load(board: &str) -> Result<Self, anyhow::Error> {
+        let path = root_dir()
+            .join("boards/manifests")
+            .join(format!("{}.toml", board));
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("no manifest at {}", path.display()))?;
+        let manifest: BoardManifest = toml::from_str(&contents)
+            .with_context(|| format!("invalid manifest {}", path.display()))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        let Partitions {
+            bootloader,
+            size,
+            boot,
+            update,
+            swap,
+            log,
+            boot_metadata,
+            update_metadata,
+            provision,
+        } = self.partitions;
+        let sector_size = self.flash.sector_size;
+        if sector_size == 0 || size % sector_size != 0 {
+            bail!("partition size must be a non-zero multiple of the sector size");
+        }
+        let write_size = self.flash.write_size;
+        if !write_size.is_power_of_two() || write_size > 256 || sector_size % write_size != 0 {
+            bail!("write size must be a power of two, up to 256, that divides the sector size");
+        }
+        if size <= IMAGE_HEADER_SIZE {
+            bail!("partition size must be larger than the image header");
+        }
+        if boot_metadata.is_some() != update_metadata.is_some() {
+            bail!("metadata sectors must be reserved for both the boot and update partitions");
+        }
+        let sectors = [log, boot_metadata, update_metadata, provision];
+        let sectors_aligned = sectors.iter().flatten().all(|addr| addr % sector_size == 0);
+        if boot % sector_size != 0
+            || update % sector_size != 0
+            || swap % sector_size != 0
+            || !sectors_aligned
+        {
+            bail!("partitions must be sector aligned");
+        }
+        if bootloader >= boot {
+            bail!("the bootloader must be located below the boot partition");
+        }
+        let flash_end = bootloader + self.flash.size;
+        // the swap partition, the event log, the metadata sectors and the provisioning flag are a
+        // single sector
+        let mut parts = vec![
+            (bootloader, boot - bootloader),
+            (boot, size),
+            (update, size),
+            (swap, sector_size),
+        ];
+        parts.extend(sectors.iter().flatten().map(|addr| (*addr, sector_size)));
+        if parts.iter().any(|(start, len)| start + len > flash_end) {
+            bail!("partitions must fit in the device's flash");
+        }
+        for (idx, (start, len)) in parts.iter().enumerate() {
+            for (other_start, other_len) in parts.iter().skip(idx + 1) {
+                if start < &(other_start + other_len) && other_start < &(start + len) {
+                    bail!("partitions overlap");
+                }
+            }
+        }
+        if let Some(esp) = &self.esp {
+            // app partitions are mapped a 64K MMU page at a time
+            if (boot - bootloader) % ESP_MMU_PAGE_SIZE != 0
+                || (update - bootloader) % ESP_MMU_PAGE_SIZE != 0
+            {
+                bail!("the boot and update partitions must be aligned to 64K, for ESP parts");
+            }
+            let table = esp.partition_table;
+            if table % sector_size != 0 || table + sector_size > boot - bootloader {
+                bail!("the ESP partition table must be a sector within the bootloader's region");
+            }
+        }
+        if let Some(stage0) = &self.stage0 {
+            // rustBoot runs from RAM on ESP parts, see `Esp`
+            if self.esp.is_some() {
+                bail!("ESP parts can't have a two-stage bootloader");
+            }
+            let slots = [
+                (bootloader, stage0.size),
+                (stage0.stage1_a, stage0.stage1_size),
+                (stage0.stage1_b, stage0.stage1_size),
+            ];
+            if slots
+                .iter()
+                .any(|(start, len)| start % sector_size != 0 || len % sector_size != 0)
+            {
+                bail!("stage0 and the stage1 slots must be sector aligned");
+            }
+            if stage0.size == 0 || stage0.stage1_size <= IMAGE_HEADER_SIZE {
+                bail!("stage0 can't be empty and the stage1 slots must be larger than the image header");
+            }
+            if slots
+                .iter()
+                .any(|(start, len)| *start < bootloader || start + len > boot)
+            {
+                bail!("stage0 and the stage1 slots must lie within the bootloader's region");
+            }
+            for (idx, (start, len)) in slots.iter().enumerate() {
+                for (other_start, other_len) in slots.iter().skip(idx + 1) {
+                    if start < &(other_start + other_len) && other_start < &(start + len) {
+                        bail!("stage0 and the stage1 slots overlap");
+                    }
+                }
+            }
+        }
+        if let Some(boot_pin) = &self.boot_pin {
+            if boot_pin.pin > 31 || boot_pin.hold_ms == 0 {
+                bail!("the boot pin must be a pin (0-31) of its port, held for at least 1ms");
+            }
+        }
+        if let Some(scan) = &self.header_scan {
+            if !scan.align.is_power_of_two() || scan.align < 4 || scan.window % scan.align != 0 {
+                bail!("the header scan's alignment must be a power of two (of at least 4) that divides its window");
+            }
+            if scan.window + IMAGE_HEADER_SIZE >= size {
+                bail!("the header scan's window must leave room for an image in the partitions");
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the boot and update partitions' metadata sectors, if the board reserves them.
+    pub fn metadata_sectors(&self) -> Option<(usize, usize)> {
+        self.partitions
+            .boot_metadata
+            .zip(self.partitions.update_metadata)
+    }
+
+    pub fn bootloader_chip(&self) -> &str {
+        self.board
+            .bootloader_chip
+            .as_deref()
+            .unwrap_or(&self.board.chip)
+    }
+
+    /// Returns the signing key path, relative to `rbsigner`'s crate directory.
+    pub fn signing_key(&self) -> PathBuf {
+        PathBuf::from("..").join(&self.keys.signing_key)
+    }
+
+    /// Returns the key salt's path, if the board has one.
+    pub fn key_salt(&self) -> Option<PathBuf> {
+        self.keys
+            .key_salt
+            .as_ref()
+            .map(|path| root_dir().join(path))
+    }
+
+    /// Returns the boot key's path, if the board has one.
+    pub fn boot_key(&self) -> Option<PathBuf> {
+        self.keys
+            .boot_key
+            .as_ref()
+            .map(|path| root_dir().join(path))
+    }
+
+    /// Returns the device-key registry's path.
+    pub fn device_keys(&self, board: &str) -> PathBuf {
+        match &self.keys.device_keys {
+            Some(path) => root_dir().join(path),
+            None => root_dir()
+                .join("target/device-keys")
+                .join(format!("{}.json", board)),
+        }
+    }
+
+    /// Renders the partitioning constants included by `rustBoot::constants`.
+    pub fn to_rust(&self) -> String {
+        let mut layout = format!(
+            "// @generated by `cargo {name} gen layout` from `boards/manifests/{name}.toml`.\n\
+             // Do not edit by hand.\n\
+             \n\
+             pub const SECTOR_SIZE: usize = {sector:#x};\n\
+             pub const WRITE_SIZE: usize = {write:#x};\n\
+             pub const FLASH_SIZE: usize = {flash:#x};\n\
+             pub const PARTITION_SIZE: usize = {size:#x};\n\
+             pub const BOOTLOADER_ADDRESS: usize = {bootloader:#x};\n\
+             pub const BOOT_PARTITION_ADDRESS: usize = {boot:#x};\n\
+             pub const SWAP_PARTITION_ADDRESS: usize = {swap:#x};\n\
+             pub const UPDATE_PARTITION_ADDRESS: usize = {update:#x};\n",
+            name = self.board.name,
+            sector = self.flash.sector_size,
+            write = self.flash.write_size,
+            flash = self.flash.size,
+            size = self.partitions.size,
+            bootloader = self.partitions.bootloader,
+            boot = self.partitions.boot,
+            swap = self.partitions.swap,
+            update = self.partitions.update,
+        );
+        if let Some(log) = self.partitions.log {
+            layout += &format!("pub const EVENT_LOG_ADDRESS: usize = {:#x};\n", log);
+        }
+        if let Some((boot, update)) = self.metadata_sectors() {
+            layout += &format!(
+                "pub const BOOT_METADATA_ADDRESS: usize = {:#x};\n\
+                 pub const UPDATE_METADATA_ADDRESS: usize = {:#x};\n",
+                boot, update
+            );
+        }
+        if let Some(provision) = self.partitions.provision {
+            layout += &format!(
+                "pub const PROVISION_FLAG_ADDRESS: usize = {:#x};\n",
+                provision
+            );
+        }
+        if let Some(stage0) = &self.stage0 {
+            layout += &format!(
+                "pub const STAGE0_SIZE: usize = {:#x};\n\
+                 pub const STAGE1_A_ADDRESS: usize = {:#x};\n\
+                 pub const STAGE1_B_ADDRESS: usize = {:#x};\n\
+                 pub const STAGE1_SIZE: usize = {:#x};\n",
+                stage0.size, stage0.stage1_a, stage0.stage1_b, stage0.stage1_size
+            );
+        }
+        if let Some(boot_pin) = &self.boot_pin {
+            layout += &format!(
+                "pub const BOOT_PIN_PORT: u8 = {};\n\
+                 pub const BOOT_PIN: u8 = {};\n\
+                 pub const BOOT_PIN_ACTIVE_LOW: bool = {};\n\
+                 pub const BOOT_PIN_HOLD_MS: u32 = {};\n",
+                boot_pin.port, boot_pin.pin, boot_pin.active_low, boot_pin.hold_ms
+            );
+        }
+        if let Some(scan) = &self.header_scan {
+            layout += &format!(
+                "pub const HEADER_SCAN_WINDOW: usize = {:#x};\n\
+                 pub const HEADER_SCAN_ALIGN: usize = {:#x};\n",
+                scan.window, scan.align
+            );
+        }
+        layout
+    }
+
+    /// Renders a linker script fragment, to be `INCLUDE`d by the board's firmware.
+    pub fn to_linker_fragment(&self) -> String {
+        format!(
+            "/* @generated by `cargo {name} gen layout` from `boards/manifests/{name}.toml`. */\n\
+             /* Do not edit by hand. */\n\
+             \n\
+             __rustboot_sector_size = {sector:#x};\n\
+             __rustboot_partition_size = {size:#x};\n\
+             __rustboot_boot_partition = {boot:#x};\n\
+             __rustboot_update_partition = {update:#x};\n\
+             __rustboot_swap_partition = {swap:#x};\n\
+             /* firmware is linked right after the 256-byte rustBoot header */\n\
+             __rustboot_fw_origin = {origin:#x};\n\
+             __rustboot_fw_max_len = {max_len:#x};\n",
+            name = self.board.name,
+            sector = self.flash.sector_size,
+            size = self.partitions.size,
+            boot = self.partitions.boot,
+            update = self.partitions.update,
+            swap = self.partitions.swap,
+            origin = self.partitions.boot + IMAGE_HEADER_SIZE,
+            max_len = self.partitions.size - IMAGE_HEADER_SIZE,
+        )
+    }
+    /// Renders the bootloader's linker script fragment, `INCLUDE`d by its `memory.x`. The link
+    /// fails if rustBoot (i.e. its `.text`, `.rodata` and `.data`) doesn't end below the boot
+    /// partition.
+    pub fn to_bootloader_fragment(&self) -> String {
+        format!(
+            "/* @generated by `cargo {name} gen layout` from `boards/manifests/{name}.toml`. */\n\
+             /* Do not edit by hand. */\n\
+             \n\
+             __rustboot_boot_partition = {boot:#x};\n\
+             ASSERT(LOADADDR(.data) + SIZEOF(.data) <= __rustboot_boot_partition,\n       \
+             \"rustBoot runs into the boot partition, see boards/manifests/{name}.toml\");\n",
+            name = self.board.name,
+            boot = self.partitions.boot,
+        )
+    }
+
+    /// Renders stage0's linker script fragment (see [`Stage0`]), `INCLUDE`d by the bootloader's
+    /// `memory.x` in place of `partitions.x` when `boards/stage0` is linked with it. The link
+    /// fails if stage0 doesn't end below the first stage1 slot.
+    pub fn to_stage0_fragment(&self) -> Option<String> {
+        let stage0 = self.stage0.as_ref()?;
+        Some(format!(
+            "/* @generated by `cargo {name} gen layout` from `boards/manifests/{name}.toml`. */\n\
+             /* Do not edit by hand. */\n\
+             \n\
+             __rustboot_stage0_end = {end:#x};\n\
+             ASSERT(LOADADDR(.data) + SIZEOF(.data) <= __rustboot_stage0_end,\n       \
+             \"stage0 runs into the stage1 slots, see boards/manifests/{name}.toml\");\n",
+            name = self.board.name,
+            end = self.partitions.bootloader + stage0.size,
+        ))
+    }
+
+    /// Renders the linker script fragment of rustBoot built as stage1 (see [`Stage0`]),
+    /// `INCLUDE`d by the bootloader's `memory.x` in place of `partitions.x`. The link fails if
+    /// rustBoot isn't linked right after a stage1 slot's header or doesn't fit the slot.
+    pub fn to_stage1_fragment(&self) -> Option<String> {
+        let stage0 = self.stage0.as_ref()?;
+        Some(format!(
+            "/* @generated by `cargo {name} gen layout` from `boards/manifests/{name}.toml`. */\n\
+             /* Do not edit by hand. */\n\
+             \n\
+             __rustboot_stage1_a = {a:#x};\n\
+             __rustboot_stage1_b = {b:#x};\n\
+             __rustboot_stage1_size = {size:#x};\n\
+             /* stage1 is linked right after the 256-byte rustBoot header */\n\
+             ASSERT(ORIGIN(FLASH) == __rustboot_stage1_a + {header:#x} || ORIGIN(FLASH) == __rustboot_stage1_b + {header:#x},\n       \
+             \"stage1 isn't linked for a stage1 slot, see boards/manifests/{name}.toml\");\n\
+             ASSERT(LOADADDR(.data) + SIZEOF(.data) <= ORIGIN(FLASH) - {header:#x} + __rustboot_stage1_size,\n       \
+             \"stage1 runs past its slot, see boards/manifests/{name}.toml\");\n",
+            name = self.board.name,
+            a = stage0.stage1_a,
+            b = stage0.stage1_b,
+            size = stage0.stage1_size,
+            header = IMAGE_HEADER_SIZE,
+        ))
+    }
+
+    /// Returns the flash region (i.e. origin and length) rustBoot is linked to as stage1 in
+    /// `slot` (`a` or `b`), if the board has a two-stage bootloader.
+    pub fn stage1_region(&self, slot: &str) -> Result<(usize, usize), anyhow::Error> {
+        let stage0 = self
+            .stage0
+            .as_ref()
+            .with_context(|| format!("{}'s manifest has no `[stage0]`", self.board.name))?;
+        let start = match slot {
+            "a" => stage0.stage1_a,
+            "b" => stage0.stage1_b,
+            _ => bail!("unknown stage1 slot `{}`, expected `a` or `b`", slot),
+        };
+        Ok((
+            start + IMAGE_HEADER_SIZE,
+            stage0.stage1_size - IMAGE_HEADER_SIZE,
+        ))
+    }
+
+    /// Renders the ESP partition table (see [`Esp`]) as CSV, for `espflash partition-table`.
+    /// rustBoot's partitions are app (i.e. always encrypted) or encrypted data partitions, with
+    /// custom subtypes.
+    pub fn to_esp_partition_table(&self) -> String {
+        let base = self.partitions.bootloader;
+        let size = self.partitions.size;
+        let sector_size = self.flash.sector_size;
+        let mut table = format!(
+            "# @generated by `cargo {name} gen layout` from `boards/manifests/{name}.toml`.\n\
+             # Do not edit by hand.\n\
+             \n\
+             # Name,      Type, SubType, Offset, Size, Flags\n\
+             rb_boot,     app,  ota_0,   {boot:#x}, {size:#x},\n\
+             rb_update,   app,  ota_1,   {update:#x}, {size:#x},\n\
+             rb_swap,     data, 0x80,    {swap:#x}, {sector_size:#x}, encrypted\n",
+            name = self.board.name,
+            boot = self.partitions.boot - base,
+            update = self.partitions.update - base,
+            swap = self.partitions.swap - base,
+            size = size,
+            sector_size = sector_size,
+        );
+        if let Some(log) = self.partitions.log {
+            table += &format!(
+                "rb_log,      data, 0x81,    {:#x}, {:#x}, encrypted\n",
+                log - base,
+                sector_size
+            );
+        }
+        if let Some((boot, update)) = self.metadata_sectors() {
+            table += &format!(
+                "rb_boot_md,  data, 0x82,    {:#x}, {:#x}, encrypted\n\
+                 rb_updt_md,  data, 0x83,    {:#x}, {:#x}, encrypted\n",
+                boot - base,
+                sector_size,
+                update - base,
+                sector_size
+            );
+        }
+        if let Some(provision) = self.partitions.provision {
+            table += &format!(
+                "rb_prov,     data, 0x84,    {:#x}, {:#x}, encrypted\n",
+                provision - base,
+                sector_size
+            );
+        }
+        table
+    }
+}
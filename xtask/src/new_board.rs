@@ -0,0 +1,631 @@
+//! `cargo xtask new-board <name> --family <family> --flash-size <size>`
+//!
+//! Scaffolds a new mcu board i.e.
+//! - its manifest (`boards/manifests/<name>.toml`), with a partition layout derived from the
+//!   flash size and the generated layout files (see `cargo <board> gen layout`),
+//! - a bootloader crate (`boards/bootloaders/<name>`), linked below the boot partition,
+//! - a HAL stub (`boards/hal/src/<family-module>/<name>.rs`) implementing `FlashInterface`,
+//! - the board's feature in `rustBoot`, `rustBoot-hal`, `rustBoot-update` and `xtask`, its
+//!   cargo alias and its arm in `build rustBoot-only`.
+//!
+//! The HAL stub's flash operations panic until they're implemented for the board.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use clap::Args;
+
+use crate::{gen_layout, manifest::BoardManifest, root_dir};
+
+/// A family of mcus, sharing a HAL crate and a memory map.
+#[derive(Debug)]
+struct Family {
+    name: &'static str,
+    /// `rustBoot-hal` module i.e. `boards/hal/src/<module>`
+    module: &'static str,
+    /// `rustBoot-hal` dependency for the family, `{name}` is replaced with the board's name.
+    hal_dependency: &'static str,
+    target: &'static str,
+    flash_base: usize,
+    ram_base: usize,
+    sector_size: usize,
+    /// flash programming unit, see the manifest's `write_size`
+    write_size: usize,
+    ram_size: usize,
+}
+
+const FAMILIES: &[Family] = &[
+    Family {
+        name: "stm32f4",
+        module: "stm",
+        // the board's name must be a `stm32f4xx-hal` device feature i.e. `stm32f401`
+        hal_dependency: "stm32f4xx-hal/{name}",
+        target: "thumbv7em-none-eabihf",
+        flash_base: 0x0800_0000,
+        ram_base: 0x2000_0000,
+        sector_size: 0x20000,
+        write_size: 4,
+        ram_size: 0x10000,
+    },
+    Family {
+        name: "stm32f7",
+        module: "stm",
+        // the board's name must be a `stm32f7xx-hal` device feature i.e. `stm32f767`
+        hal_dependency: "stm32f7xx-hal/{name}",
+        target: "thumbv7em-none-eabihf",
+        flash_base: 0x0800_0000,
+        ram_base: 0x2000_0000,
+        sector_size: 0x40000,
+        write_size: 1,
+        ram_size: 0x20000,
+    },
+    Family {
+        name: "stm32h7",
+        module: "stm",
+        hal_dependency: "stm32h7xx-hal",
+        target: "thumbv7em-none-eabihf",
+        flash_base: 0x0800_0000,
+        ram_base: 0x2000_0000,
+        sector_size: 0x20000,
+        write_size: 32,
+        ram_size: 0x20000,
+    },
+    Family {
+        name: "nrf52",
+        module: "nrf",
+        hal_dependency: "nrf52840-hal",
+        target: "thumbv7em-none-eabihf",
+        flash_base: 0x0,
+        ram_base: 0x2000_0000,
+        sector_size: 0x1000,
+        write_size: 4,
+        ram_size: 0x40000,
+    },
+];
+
+#[derive(Debug, Args)]
+pub struct NewBoardArgs {
+    /// The board's name, a lowercase identifier ex: `stm32f401`
+    #[arg(value_parser = parse_name)]
+    name: String,
+    /// The board's mcu family
+    #[arg(long, value_parser = parse_family)]
+    family: &'static Family,
+    /// probe-rs chip name [default: the board's name]
+    #[arg(long)]
+    chip: Option<String>,
+    /// Flash size ex: `512K`, `1M` or `0x80000`
+    #[arg(long, value_parser = parse_size)]
+    flash_size: usize,
+    /// RAM size [default: the family's]
+    #[arg(long, value_parser = parse_size)]
+    ram_size: Option<usize>,
+    /// Flash sector (i.e. erase unit) size [default: the family's]
+    #[arg(long, value_parser = parse_size)]
+    sector_size: Option<usize>,
+    /// Space reserved for the bootloader (i.e. rustBoot and its public key), rounded up to a
+    /// sector
+    #[arg(long, value_parser = parse_size, default_value = "128K")]
+    bootloader_size: usize,
+}
+
+struct NewBoard<'a> {
+    name: &'a str,
+    family: &'a Family,
+    chip: &'a str,
+    flash_size: usize,
+    ram_size: usize,
+    sector_size: usize,
+    bootloader_size: usize,
+}
+
+/// Partition layout of a new board, in the manifest's terms.
+struct Layout {
+    bootloader: usize,
+    size: usize,
+    boot: usize,
+    update: usize,
+    swap: usize,
+}
+
+/// Scaffolds a new board. Returns the paths of the generated files.
+pub fn new_board(args: &NewBoardArgs) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let name = args.name.as_str();
+    let board = NewBoard {
+        name,
+        family: args.family,
+        chip: args.chip.as_deref().unwrap_or(name),
+        flash_size: args.flash_size,
+        ram_size: args.ram_size.unwrap_or(args.family.ram_size),
+        sector_size: args.sector_size.unwrap_or(args.family.sector_size),
+        bootloader_size: args.bootloader_size,
+    };
+    let root = root_dir();
+    let bootloader_dir = root.join("boards/bootloaders").join(name);
+    let manifest_path = root.join("boards/manifests").join(format!("{}.toml", name));
+    if bootloader_dir.exists() || manifest_path.exists() {
+        bail!("board `{}` already exists", name);
+    }
+    let layout = board.layout()?;
+
+    // manifest and the layouts generated from it
+    fs::write(&manifest_path, board.manifest(&layout))?;
+    println!("generated {}", manifest_path.display());
+    BoardManifest::load(name)?;
+    fs::create_dir_all(root.join("boards/firmware").join(name))?;
+    fs::create_dir_all(&bootloader_dir)?;
+    let mut generated = vec![manifest_path];
+    generated.extend(gen_layout(name)?);
+
+    // bootloader crate
+    fs::create_dir_all(bootloader_dir.join("src"))?;
+    fs::create_dir_all(bootloader_dir.join(".cargo"))?;
+    let files = [
+        ("Cargo.toml", board.bootloader_cargo_toml()),
+        ("build.rs", BOOTLOADER_BUILD_RS.to_string()),
+        ("memory.x", board.bootloader_memory_x(&layout)),
+        ("src/main.rs", board.bootloader_main_rs()),
+        (".cargo/config.toml", board.bootloader_cargo_config()),
+    ];
+    for (file, contents) in files.iter() {
+        let path = bootloader_dir.join(file);
+        fs::write(&path, contents)?;
+        println!("generated {}", path.display());
+        generated.push(path);
+    }
+
+    // HAL stub
+    let hal_dir = root.join("boards/hal/src").join(board.family.module);
+    let hal_stub = hal_dir.join(format!("{}.rs", name));
+    fs::write(&hal_stub, board.hal_stub())?;
+    println!("generated {}", hal_stub.display());
+
+    board.register(&root)?;
+
+    println!(
+        "\n`{}` is set up. Implement the `FlashInterface` stubs in {} and check {}.",
+        name,
+        hal_stub.display(),
+        bootloader_dir.join("memory.x").display()
+    );
+    println!("Then build it with `cargo {} build rustBoot-only`.", name);
+    generated.push(hal_stub);
+    Ok(generated)
+}
+
+fn parse_name(name: &str) -> Result<String, anyhow::Error> {
+    if !name.starts_with(|c: char| c.is_ascii_lowercase())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        bail!("board names must be lowercase identifiers, ex: `stm32f401`");
+    }
+    Ok(name.to_string())
+}
+
+fn parse_family(name: &str) -> Result<&'static Family, anyhow::Error> {
+    FAMILIES
+        .iter()
+        .find(|family| family.name == name)
+        .with_context(|| {
+            let families = FAMILIES.iter().map(|f| f.name).collect::<Vec<_>>();
+            format!("unknown family `{}`, expected one of {:?}", name, families)
+        })
+}
+
+/// Parses a size i.e. `512K`, `1M`, `0x80000` or `524288`.
+fn parse_size(size: &str) -> Result<usize, anyhow::Error> {
+    let (digits, unit) = match size.as_bytes().last() {
+        Some(b'K') | Some(b'k') => (&size[..size.len() - 1], 1024),
+        Some(b'M') | Some(b'm') => (&size[..size.len() - 1], 1024 * 1024),
+        _ => (size, 1),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .with_context(|| format!("invalid size `{}`", size))?;
+    Ok(value * unit)
+}
+
+impl<'a> NewBoard<'a> {
+    /// The bootloader at the start of flash, followed by the boot and update partitions (which
+    /// share what's left of flash) and a single-sector swap partition.
+    fn layout(&self) -> Result<Layout, anyhow::Error> {
+        let sector = self.sector_size;
+        if sector == 0 {
+            bail!("the sector size can't be zero");
+        }
+        let bootloader_len = self.bootloader_size.div_ceil(sector) * sector;
+        let size = self
+            .flash_size
+            .checked_sub(bootloader_len + sector)
+            .map(|free| free / 2 / sector * sector)
+            .unwrap_or(0);
+        if size == 0 {
+            bail!(
+                "{:#x} bytes of flash can't hold a {:#x} byte bootloader and two partitions",
+                self.flash_size,
+                bootloader_len
+            );
+        }
+        let bootloader = self.family.flash_base;
+        let boot = bootloader + bootloader_len;
+        Ok(Layout {
+            bootloader,
+            size,
+            boot,
+            update: boot + size,
+            swap: boot + 2 * size,
+        })
+    }
+
+    fn manifest(&self, layout: &Layout) -> String {
+        format!(
+            r#"# rustBoot board manifest for `{name}`.
+#
+# Run `cargo {name} gen layout` after editing this file, to regenerate
+# `rustBoot/src/layouts/{name}.rs` and the `partitions.x` of `boards/bootloaders/{name}`
+# and `boards/firmware/{name}`.
+
+[board]
+name = "{name}"
+target = "{target}"
+# probe-rs chip name
+chip = "{chip}"
+# pyocd target name
+pyocd_target = "{name}"
+
+[flash]
+# the device's flash, in bytes from its start (i.e. the bootloader)
+size = {flash:#x}
+sector_size = {sector:#x}
+# programming unit in bytes, writes are widened to whole units
+write_size = {write_size}
+
+[partitions]
+# start of flash i.e. the bootloader, which runs up to the boot partition
+bootloader = {bootloader:#010x}
+size = {size:#x}
+boot = {boot:#010x}
+update = {update:#010x}
+swap = {swap:#010x}
+
+[keys]
+# relative to the repository root
+signing_key = "boards/sign_images/keygen/ecc256.der"
+"#,
+            name = self.name,
+            target = self.family.target,
+            chip = self.chip,
+            flash = self.flash_size,
+            sector = self.sector_size,
+            write_size = self.family.write_size,
+            bootloader = layout.bootloader,
+            size = layout.size,
+            boot = layout.boot,
+            update = layout.update,
+            swap = layout.swap,
+        )
+    }
+
+    fn bootloader_cargo_toml(&self) -> String {
+        format!(
+            r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[[bin]]
+bench = false
+doctest = false
+name = "{name}"
+test = false
+
+[dependencies]
+cortex-m = {{ version = "0.7", features = ["critical-section-single-core"] }}
+cortex-m-rt = "0.7"
+rustBoot-hal = {{path = "../../hal", default-features = false, features = ["{name}"]}}
+rustBoot-update = {{path = "../../update", features = ["{name}"]}}
+
+[features]
+default = []
+# opt-in hardening: lock the debug port on first boot, see `rustBoot-update`
+production = ["rustBoot-update/production"]
+production-permanent = ["rustBoot-update/production-permanent"]
+# development only: boot images that aren't (validly) signed, see `rustBoot-update`
+dev-unsigned = ["rustBoot-update/dev-unsigned"]
+"#,
+            name = self.name
+        )
+    }
+
+    /// rustBoot is linked in the bootloader region i.e. below the boot partition.
+    fn bootloader_memory_x(&self, layout: &Layout) -> String {
+        format!(
+            r#"/* generated by `cargo xtask new-board`, from `boards/manifests/{name}.toml` */
+/* rustBoot occupies everything up to the boot partition */
+MEMORY
+{{
+  FLASH    (rx)  : ORIGIN = {flash:#010x}, LENGTH = {flash_len}K
+  RAM      (rwx) : ORIGIN = {ram:#010x}, LENGTH = {ram_len}K
+}}
+
+/* checks that rustBoot ends below the boot partition, see `cargo {name} gen layout` */
+INCLUDE partitions.x
+"#,
+            name = self.name,
+            flash = layout.bootloader,
+            flash_len = (layout.boot - layout.bootloader) / 1024,
+            ram = self.family.ram_base,
+            ram_len = self.ram_size / 1024,
+        )
+    }
+
+    fn bootloader_main_rs(&self) -> String {
+        format!(
+            r#"#![no_std]
+#![no_main]
+
+use rustBoot_hal::{module}::{name}::FlashWriterEraser;
+use rustBoot_update::update::{{update_flash::FlashUpdater, UpdateInterface}};
+
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {{
+    let updater = FlashUpdater::new(FlashWriterEraser::new());
+    updater.rustboot_start()
+}}
+
+#[panic_handler] // panicking behavior
+fn panic(_: &core::panic::PanicInfo) -> ! {{
+    loop {{
+        cortex_m::asm::bkpt();
+    }}
+}}
+"#,
+            module = self.family.module,
+            name = self.name
+        )
+    }
+
+    fn bootloader_cargo_config(&self) -> String {
+        format!(
+            r#"[build]
+target = "{target}"
+
+[target.'cfg(all(target_arch = "arm", target_os = "none"))']
+runner = "probe-run --chip {chip}"
+rustflags = [
+  "-C", "linker=flip-link",
+  "-C", "link-arg=-Tlink.x",
+  # This is needed if your flash or ram addresses are not aligned to 0x10000 in memory.x
+  # See https://github.com/rust-embedded/cortex-m-quickstart/pull/95
+  "-C", "link-arg=--nmagic",
+]
+"#,
+            target = self.family.target,
+            chip = self.chip
+        )
+    }
+
+    fn hal_stub(&self) -> String {
+        format!(
+            r#"//! rustBoot's HAL for the `{name}`, generated by `cargo xtask new-board`.
+//!
+//! **note:** the flash operations are stubs i.e. they panic until they're implemented for this
+//! board. See `stm32f411.rs` for a complete implementation.
+
+use crate::{{DebugProtection, FlashError, FlashInterface}};
+
+pub struct FlashWriterEraser {{}}
+
+impl FlashWriterEraser {{
+    pub fn new() -> Self {{
+        FlashWriterEraser {{}}
+    }}
+}}
+
+impl FlashInterface for FlashWriterEraser {{
+    fn hal_init() {{}}
+
+    /// This method is used to unlock the flash
+    fn hal_flash_unlock(&self) {{
+        todo!("unlock the {name}'s flash")
+    }}
+
+    /// This method is used to lock the flash
+    fn hal_flash_lock(&self) {{
+        todo!("lock the {name}'s flash")
+    }}
+
+    /// This method is used to write `data` to flash, at `address`
+    fn hal_flash_write(&self, address: usize, data: &[u8]) -> Result<(), FlashError> {{
+        todo!("write to the {name}'s flash")
+    }}
+
+    /// This method is used to erase the sectors covering `addr..addr + len`
+    fn hal_flash_erase(&self, addr: usize, len: usize) -> Result<(), FlashError> {{
+        todo!("erase the {name}'s flash")
+    }}
+
+    /// This method is used to write-protect the sectors within `addr..addr + len`
+    fn hal_flash_protect(&self, addr: usize, len: usize) {{
+        todo!("write-protect the {name}'s flash")
+    }}
+
+    /// This method returns the current debug-access protection level
+    fn hal_debug_protection(&self) -> DebugProtection {{
+        todo!("read the {name}'s debug-access protection")
+    }}
+
+    /// This method is used to raise the debug-access protection level
+    fn hal_set_debug_protection(&self, level: DebugProtection) {{
+        todo!("set the {name}'s debug-access protection")
+    }}
+}}
+
+pub fn preboot() {{}}
+
+/// This method is used to boot the firmware from a particular address
+///
+/// Method arguments:
+/// -   fw_base_address  : address of the firmware i.e. its vector table
+/// Returns:
+/// -  NONE
+pub fn boot_from(fw_base_address: usize) -> ! {{
+    unsafe {{
+        (*cortex_m::peripheral::SCB::PTR)
+            .vtor
+            .write(fw_base_address as u32);
+        cortex_m::asm::bootload(fw_base_address as *const u32)
+    }}
+}}
+"#,
+            name = self.name
+        )
+    }
+
+    /// Adds the board to the feature lists, `cfg`s and dispatch tables that every board has an
+    /// entry in.
+    fn register(&self, root: &Path) -> Result<(), anyhow::Error> {
+        let name = self.name;
+        let module = self.family.module;
+        let hal_dependency = self.family.hal_dependency.replace("{name}", name);
+
+        insert_after_last(
+            &root.join("rustBoot/Cargo.toml"),
+            |line| line.ends_with(r#"= ["mcu"]"#),
+            &format!(r#"{} = ["mcu"]"#, name),
+        )?;
+        insert_after_last(
+            &root.join("rustBoot/src/constants.rs"),
+            |line| line.starts_with(r#"include!("layouts/"#),
+            &format!(
+                "#[cfg(feature = \"{name}\")]\ninclude!(\"layouts/{name}.rs\");",
+                name = name
+            ),
+        )?;
+        insert_after_last(
+            &root.join("boards/update/Cargo.toml"),
+            |line| line.contains(r#"= ["rustBoot/"#),
+            &format!(r#"{name} = ["rustBoot/{name}"]"#, name = name),
+        )?;
+        insert_after_last(
+            &root.join("boards/hal/Cargo.toml"),
+            |line| line.contains(&format!(r#"= ["{}""#, module)),
+            &format!(r#"{} = ["{}", "{}"]"#, name, module, hal_dependency),
+        )?;
+        let hal_mod = root.join("boards/hal/src").join(module).join("mod.rs");
+        let mut contents = fs::read_to_string(&hal_mod)?;
+        contents.push_str(&format!(
+            "\n#[cfg(feature = \"{name}\")]\npub mod {name};\n",
+            name = name
+        ));
+        fs::write(&hal_mod, contents)?;
+        insert_after_last(
+            &root.join("boards/hal/src/lib.rs"),
+            |line| line.ends_with("::boot_from(fw_base_address);"),
+            &format!(
+                "\n    #[cfg(feature = \"{name}\")]\n    crate::{module}::{name}::boot_from(fw_base_address);",
+                name = name,
+                module = module
+            ),
+        )?;
+        insert_after_last(
+            &root.join("xtask/Cargo.toml"),
+            |line| line.contains(r#"= ["mcu", "rustBoot/"#),
+            &format!(r#"{name} = ["mcu", "rustBoot/{name}"]"#, name = name),
+        )?;
+        insert_after_last(
+            &root.join(".cargo/config.toml"),
+            |line| line.contains("'run -p xtask --features "),
+            &format!(
+                "{name} = 'run -p xtask --features {name} -- {name}'",
+                name = name
+            ),
+        )?;
+        // `build rustBoot-only`, mcu boards are followed by `imx8mn`
+        insert_before_first(
+            &root.join("xtask/src/main.rs"),
+            |line| line == r#"        "imx8mn" => {"#,
+            &format!(
+                "        \"{}\" => {{\n            cmd!(\"cargo build --release\").run()?;\n        }}",
+                name
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+/// Inserts `text` as a line of its own, after the last line of `path` matching `anchor`.
+fn insert_after_last(
+    path: &Path,
+    anchor: impl Fn(&str) -> bool,
+    text: &str,
+) -> Result<(), anyhow::Error> {
+    insert_line(path, text, |lines| {
+        lines
+            .iter()
+            .rposition(|line| anchor(line))
+            .map(|idx| idx + 1)
+    })
+}
+
+/// Inserts `text` as a line of its own, before the first line of `path` matching `anchor`.
+fn insert_before_first(
+    path: &Path,
+    anchor: impl Fn(&str) -> bool,
+    text: &str,
+) -> Result<(), anyhow::Error> {
+    insert_line(path, text, |lines| {
+        lines.iter().position(|line| anchor(line))
+    })
+}
+
+fn insert_line(
+    path: &Path,
+    text: &str,
+    position: impl Fn(&[&str]) -> Option<usize>,
+) -> Result<(), anyhow::Error> {
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents.lines().collect::<Vec<_>>();
+    let idx = position(&lines)
+        .with_context(|| format!("couldn't register the board in {}", path.display()))?;
+    lines.insert(idx, text);
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    fs::write(path, contents)?;
+    println!("updated {}", path.display());
+    Ok(())
+}
+
+const BOOTLOADER_BUILD_RS: &str = r#"use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn main() {
+    // Put the linker script somewhere the linker can find it
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    File::create(out.join("memory.x"))
+        .unwrap()
+        .write_all(include_bytes!("memory.x"))
+        .unwrap();
+    // generated from the board's manifest, see `cargo <board> gen layout`
+    File::create(out.join("partitions.x"))
+        .unwrap()
+        .write_all(include_bytes!("partitions.x"))
+        .unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=partitions.x");
+}
+"#;
@@ -0,0 +1,59 @@
+//! `cargo <board> provision-otp [--debug-disable] --irreversible`
+//!
+//! Programs the rp2350's OTP for secure boot (see `rustBoot_hal::pico::rp2350::otp_state`), with
+//! picotool. The part must be in BOOTSEL mode.
+//!
+//! - `BOOTKEY0` and `BOOT_FLAGS1.KEY_VALID` i.e. the boot key's hash, as written by `picotool
+//!   seal` along with the sealed rustBoot (see `cargo rp2350 sign rustBoot`).
+//! - `CRIT1.SECURE_BOOT_ENABLE` i.e. the bootrom only boots images signed with the boot key.
+//! - `CRIT1.DEBUG_DISABLE`, with `--debug-disable`. Production builds of rustBoot refuse to boot
+//!   a part without it.
+//!
+//! *Note: OTP bits can't be cleared. Without `--irreversible`, the settings are only printed.*
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use serde_json::{json, Value};
+use xshell::cmd;
+
+use crate::{cli::OtpArgs, manifest::BoardManifest, sealed_rustBoot};
+
+pub fn provision_otp(board: &str, args: &OtpArgs) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(board)?;
+    if manifest.boot_key().is_none() {
+        bail!(
+            "{}'s manifest has no `boot_key` i.e. it isn't an rp2350",
+            board
+        );
+    }
+    let (_, otp_path) = sealed_rustBoot(board);
+    let contents = fs::read_to_string(&otp_path).with_context(|| {
+        format!(
+            "no OTP settings at {}, run `cargo {} sign rustBoot <version>` first",
+            otp_path.display(),
+            board
+        )
+    })?;
+    let mut otp: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("invalid OTP settings {}", otp_path.display()))?;
+    let crit1 = otp
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("invalid OTP settings {}", otp_path.display()))?
+        .entry("crit1")
+        .or_insert_with(|| json!({}));
+    crit1["secure_boot_enable"] = json!(1);
+    if args.debug_disable {
+        crit1["debug_disable"] = json!(1);
+    }
+
+    let otp = serde_json::to_string_pretty(&otp)?;
+    println!("{}", otp);
+    if !args.irreversible {
+        bail!("OTP can't be erased, pass `--irreversible` to program the above");
+    }
+    let path = otp_path.with_file_name("otp_provision.json");
+    fs::write(&path, otp)?;
+    cmd!("picotool otp load {path}").run()?;
+    Ok(vec![path])
+}
@@ -0,0 +1,69 @@
+//! `cargo <board> provision --uid <hex>`
+//!
+//! Derives a device's image key (see `rustBoot::crypto::kdf`) from its unique ID and the board's
+//! secret key salt (the `key_salt` in its manifest), the same way the bootloader does, and
+//! registers it in the board's device-key registry. The registry is a JSON object mapping each
+//! device's UID to its image key (both hex-encoded), encrypted updates for a device are built
+//! with its registered key.
+//!
+//! *Note: the salt and the registry are secrets, keep them out of version control.*
+
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use rustBoot::crypto::kdf::{image_key, UidRoot};
+
+use crate::{cli::ProvisionArgs, manifest::BoardManifest};
+
+pub fn provision(board: &str, args: &ProvisionArgs) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(board)?;
+    let salt_path = manifest
+        .key_salt()
+        .ok_or_else(|| anyhow!("{}'s manifest has no `key_salt`", board))?;
+    let salt = fs::read(&salt_path)
+        .with_context(|| format!("can't read the key salt at {}", salt_path.display()))?;
+    if salt.len() < 16 {
+        bail!("the key salt must be at least 16 (random) bytes");
+    }
+    let uid = from_hex(&args.uid)?;
+    let key = image_key(&UidRoot {
+        uid: &uid,
+        salt: &salt,
+    })
+    .map_err(|e| anyhow!("key derivation failed: {}", e))?;
+
+    let registry = manifest.device_keys(board);
+    let mut keys: BTreeMap<String, String> = match fs::read_to_string(&registry) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .with_context(|| format!("invalid device-key registry {}", registry.display()))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let uid = to_hex(&uid);
+    if keys.insert(uid.clone(), to_hex(&key)).is_some() {
+        println!("re-registered device {}", uid);
+    } else {
+        println!("registered device {}", uid);
+    }
+    if let Some(dir) = registry.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&registry, serde_json::to_string_pretty(&keys)?)?;
+    Ok(vec![registry])
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let hex = hex.trim_start_matches("0x");
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        bail!("the UID must be an even number of hex digits");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16))
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("invalid UID {}", hex))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
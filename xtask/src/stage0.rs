@@ -0,0 +1,104 @@
+//! Two-stage bootloaders i.e. boards whose manifest has a `[stage0]` (see `manifest::Stage0`).
+//!
+//! - `cargo <board> build stage0` builds `boards/stage0`, linked to the start of flash. stage0 is
+//!   flashed once (`cargo <board> flash stage0`) and never updated.
+//! - `cargo <board> build stage1 <a|b>` builds the board's rustBoot (with its `two-stage`
+//!   feature) linked to run from a stage1 slot, right after its header. Each slot gets its own
+//!   build (in `boards/target/stage1_<slot>`), as stage1 runs in place.
+//! - `cargo <board> sign stage1 <a|b> <version>` signs a slot's build i.e.
+//!   `<board>_stage1_<slot>_v<version>_signed.bin`, and `cargo <board> flash stage1 <a|b>
+//!   <version>` flashes it to its slot.
+//!
+//! stage0 boots the newest valid stage1, so a stage1 update is signed with a higher version and
+//! written to the slot that isn't running, see `rustBoot_update::update::stage1`.
+
+use std::path::PathBuf;
+
+use rustBoot::rbconstants::IMAGE_HEADER_SIZE;
+use xshell::cmd;
+
+use crate::{manifest::BoardManifest, root_dir, signed_image};
+
+pub fn build_stage0(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    // checks that the board has a `[stage0]`
+    manifest.stage1_region("a")?;
+    let triple = &manifest.board.target;
+
+    let _p = xshell::pushd(root_dir().join("boards/stage0"))?;
+    cmd!("cargo build --release --features {target} --target {triple}").run()?;
+    Ok(vec![stage0_elf(&manifest)])
+}
+
+pub fn build_stage1(target: &str, slot: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let (origin, length) = manifest.stage1_region(slot)?;
+    let target_dir = stage1_target_dir(slot);
+
+    // read by the bootloader's `build.rs`, in place of its `memory.x`'s flash region
+    let _p = xshell::pushd(root_dir().join("boards/bootloaders").join(target))?;
+    cmd!("cargo build --release --features two-stage --target-dir {target_dir}")
+        .env("RUSTBOOT_STAGE1_ORIGIN", format!("{:#x}", origin))
+        .env("RUSTBOOT_STAGE1_LENGTH", format!("{:#x}", length))
+        .run()?;
+    Ok(vec![stage1_elf(&manifest, slot)])
+}
+
+pub fn sign_stage1(target: &str, slot: &str, version: u32) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    build_stage1(target, slot)?;
+    let elf = stage1_elf(&manifest, slot);
+    let key = manifest.signing_key();
+    let version = version.to_string();
+
+    let _p = xshell::pushd(root_dir().join("boards/sign_images/signed_images"))?;
+    cmd!("rust-objcopy -I elf32-littlearm {elf} -O binary {target}_stage1_{slot}.bin").run()?;
+    let _p = xshell::pushd(root_dir().join("rbsigner"))?;
+    cmd!("cargo run mcu-image ../boards/sign_images/signed_images/{target}_stage1_{slot}.bin nistp256 {key} {version} --target {target} --board {target}").run()?;
+    Ok(vec![signed_image(&format!(
+        "{}_stage1_{}_v{}_signed.bin",
+        target, slot, version
+    ))])
+}
+
+pub fn flash_stage0(target: &str) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let chip = manifest.bootloader_chip();
+    let elf = stage0_elf(&manifest);
+    cmd!("probe-rs-cli download --chip {chip} {elf}").run()?;
+    Ok(Vec::new())
+}
+
+pub fn flash_stage1(target: &str, slot: &str, version: u32) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(target)?;
+    let (origin, _) = manifest.stage1_region(slot)?;
+    let chip = manifest.bootloader_chip();
+    let slot_addr = format!("{:#x}", origin - IMAGE_HEADER_SIZE);
+    let image = signed_image(&format!(
+        "{}_stage1_{}_v{}_signed.bin",
+        target, slot, version
+    ));
+    cmd!("probe-rs-cli download --format Bin --base-address {slot_addr} --chip {chip} {image}")
+        .run()?;
+    Ok(Vec::new())
+}
+
+fn stage0_elf(manifest: &BoardManifest) -> PathBuf {
+    root_dir()
+        .join("boards/target")
+        .join(&manifest.board.target)
+        .join("release/stage0")
+}
+
+fn stage1_target_dir(slot: &str) -> PathBuf {
+    root_dir()
+        .join("boards/target")
+        .join(format!("stage1_{}", slot))
+}
+
+fn stage1_elf(manifest: &BoardManifest, slot: &str) -> PathBuf {
+    stage1_target_dir(slot)
+        .join(&manifest.board.target)
+        .join("release")
+        .join(&manifest.board.name)
+}
@@ -0,0 +1,78 @@
+//! `cargo <board> provision-uicr [--approtect]`
+//!
+//! Writes the nRF UICR registers that rustBoot checks at boot (see
+//! `rustBoot_hal::nrf::nrf52840::uicr_state`), with pyocd:
+//!
+//! - `NRFFW[0]` i.e. the bootloader's start address (the manifest's `bootloader` partition).
+//! - `NFCPINS`, if the manifest's `[uicr]` sets `nfc_pins_as_gpio`.
+//! - `APPROTECT`, with `--approtect`. This locks the debug port after the next reset, production
+//!   builds of rustBoot refuse to boot a part without it.
+//!
+//! A UICR register can't be re-written without erasing the UICR, so the UICR is erased first.
+//!
+//! *Note: a part whose debug port is already locked has to be unlocked (i.e. `pyocd erase
+//! --chip`) before it can be re-provisioned.*
+
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use xshell::cmd;
+
+use crate::{cli::UicrArgs, manifest::BoardManifest};
+
+// the NVMC's `CONFIG` and `ERASEUICR` registers
+const NVMC_CONFIG: u32 = 0x4001_E504;
+const NVMC_ERASEUICR: u32 = 0x4001_E514;
+const CONFIG_REN: u32 = 0;
+const CONFIG_WEN: u32 = 1;
+const CONFIG_EEN: u32 = 2;
+
+const UICR_NRFFW0: u32 = 0x1000_1014;
+const UICR_APPROTECT: u32 = 0x1000_1208;
+const UICR_NFCPINS: u32 = 0x1000_120C;
+// `NFCPINS.PROTECT` cleared i.e. the pins are GPIOs
+const NFCPINS_DISABLED: u32 = 0xFFFF_FFFE;
+const APPROTECT_ENABLED: u32 = 0x0000_0000;
+
+pub fn provision_uicr(board: &str, args: &UicrArgs) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let manifest = BoardManifest::load(board)?;
+    let uicr = manifest.uicr.as_ref().ok_or_else(|| {
+        anyhow!(
+            "{}'s manifest has no `[uicr]` i.e. it isn't an nRF part",
+            board
+        )
+    })?;
+
+    let mut writes = vec![(UICR_NRFFW0, manifest.partitions.bootloader as u32)];
+    if uicr.nfc_pins_as_gpio {
+        writes.push((UICR_NFCPINS, NFCPINS_DISABLED));
+    }
+    // last, it locks the debug port (after a reset)
+    if args.approtect {
+        writes.push((UICR_APPROTECT, APPROTECT_ENABLED));
+    }
+
+    let mut commands = vec![
+        write32(NVMC_CONFIG, CONFIG_EEN),
+        write32(NVMC_ERASEUICR, 1),
+        // a UICR erase takes at most ~300ms
+        "sleep 500".to_string(),
+        write32(NVMC_CONFIG, CONFIG_WEN),
+    ];
+    commands.extend(writes.iter().map(|(addr, val)| write32(*addr, *val)));
+    commands.push(write32(NVMC_CONFIG, CONFIG_REN));
+    // UICR registers are only applied on reset
+    commands.push("reset".to_string());
+
+    let pyocd_target = &manifest.board.pyocd_target;
+    let commands = commands.iter().flat_map(|command| ["-c", command.as_str()]);
+    cmd!("pyocd cmd -t {pyocd_target} {commands...}").run()?;
+    for (addr, val) in writes.iter() {
+        println!("UICR 0x{:08x} = 0x{:08x}", addr, val);
+    }
+    Ok(Vec::new())
+}
+
+fn write32(addr: u32, val: u32) -> String {
+    format!("write32 0x{:08x} 0x{:08x}", addr, val)
+}
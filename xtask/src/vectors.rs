@@ -0,0 +1,141 @@
+//! `cargo xtask gen-vectors [--check]`
+//!
+//! Generates the golden signed images in `rustBoot/test-vectors` i.e. small mcu-images signed
+//! with the development key (`boards/sign_images/keygen/ecc256.der`), which rustBoot's tests
+//! parse and verify (see `rustBoot::image`). They lock the on-flash image format - a change to
+//! rbsigner's output or to rustBoot's parser shows up as a diff or a failing test.
+//!
+//! The images are signed with rbsigner's signing core (rather than the `rbsigner` tool), with a
+//! fixed timestamp. ECDSA signatures are deterministic (RFC 6979), so generating the images again
+//! yields the same bytes. With `--check`, nothing is written and the command fails if the
+//! committed images don't match.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{bail, Context};
+use rbsigner::curve::{import_signing_key, CurveType};
+use rbsigner::sign::mcu_image_header;
+use rustBoot::parser::{board_id, get_header_tlv_offset, Tags, VendorTlv};
+use rustBoot::rbconstants::{HDR_BOARD_ID, HDR_IMG_TYPE_APP, IMAGE_HEADER_SIZE};
+
+use crate::root_dir;
+
+/// The board the golden images are bound to, see `rustBoot::parser::board_id`.
+const BOARD: &str = "golden";
+/// The board of the `wrong-board` image.
+const OTHER_BOARD: &str = "golden-other";
+/// The version of the golden images, the `bad-version` image is a downgrade from it.
+const VERSION: u32 = 2;
+/// The images' timestamp i.e. 2023-11-14T22:13:20Z.
+const TIMESTAMP: i64 = 1_700_000_000;
+/// The size of the images' firmware.
+const FW_SIZE: usize = 256;
+
+/// A golden image's file name and how it's made.
+struct Vector {
+    name: &'static str,
+    image: fn(&[u8]) -> Result<Vec<u8>, anyhow::Error>,
+}
+
+const VECTORS: &[Vector] = &[
+    Vector {
+        name: "valid.bin",
+        image: |key| signed_image(key, VERSION, BOARD),
+    },
+    // the signature's last byte is flipped
+    Vector {
+        name: "bad-signature.bin",
+        image: |key| {
+            let mut image = signed_image(key, VERSION, BOARD)?;
+            let offset = signature_offset(&image)?;
+            image[offset + 63] ^= 0x01;
+            Ok(image)
+        },
+    },
+    // validly signed, but older than `valid.bin` i.e. a rollback
+    Vector {
+        name: "bad-version.bin",
+        image: |key| signed_image(key, VERSION - 1, BOARD),
+    },
+    // the header is cut short
+    Vector {
+        name: "truncated-header.bin",
+        image: |key| {
+            let mut image = signed_image(key, VERSION, BOARD)?;
+            image.truncate(IMAGE_HEADER_SIZE / 2);
+            Ok(image)
+        },
+    },
+    // validly signed, for another board
+    Vector {
+        name: "wrong-board.bin",
+        image: |key| signed_image(key, VERSION, OTHER_BOARD),
+    },
+];
+
+pub fn gen_vectors(check: bool) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let key_file = root_dir().join("boards/sign_images/keygen/ecc256.der");
+    let key = fs::read(&key_file).with_context(|| format!("can't read {}", key_file.display()))?;
+    // the development key file holds the public key, followed by the secret scalar
+    let key = key
+        .get(64..96)
+        .with_context(|| format!("{} isn't a raw nistp256 key pair", key_file.display()))?;
+
+    let dir = root_dir().join("rustBoot/test-vectors");
+    if !check {
+        fs::create_dir_all(&dir)?;
+    }
+    let mut stale = Vec::new();
+    let mut artifacts = Vec::new();
+    for vector in VECTORS {
+        let path = dir.join(vector.name);
+        let image = (vector.image)(key)?;
+        match check {
+            true if fs::read(&path).ok().as_ref() != Some(&image) => stale.push(vector.name),
+            true => {}
+            false => {
+                fs::write(&path, &image)?;
+                println!("{} ({} bytes)", path.display(), image.len());
+            }
+        }
+        artifacts.push(path);
+    }
+    if !stale.is_empty() {
+        bail!(
+            "golden images don't match what rbsigner signs, run `cargo xtask gen-vectors` if the \
+             image format changed on purpose: {}",
+            stale.join(", ")
+        );
+    }
+    Ok(artifacts)
+}
+
+/// Returns a golden image i.e. its firmware (a byte ramp), signed with `key`, bound to `board`.
+fn signed_image(key: &[u8], version: u32, board: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let sk = import_signing_key(CurveType::NistP256, key)
+        .map_err(|e| anyhow::anyhow!("invalid signing key: {:?}", e))?;
+    let fw = (0..FW_SIZE).map(|i| i as u8).collect::<Vec<_>>();
+    let board_id = board_id(board);
+    let vendor_tlvs = [VendorTlv {
+        typ: HDR_BOARD_ID,
+        value: &board_id,
+    }];
+    let header = mcu_image_header(
+        fw.as_slice(),
+        FW_SIZE as u32,
+        version.to_le_bytes(),
+        TIMESTAMP,
+        HDR_IMG_TYPE_APP as u8,
+        &vendor_tlvs,
+        &sk,
+    )
+    .map_err(|e| anyhow::anyhow!("can't sign a golden image: {:?}", e))?;
+    Ok([&header[..], &fw].concat())
+}
+
+/// Returns the offset of an image's signature (i.e. its signature TLV's value).
+fn signature_offset(image: &[u8]) -> Result<usize, anyhow::Error> {
+    get_header_tlv_offset(&image[..IMAGE_HEADER_SIZE], Tags::Signature)
+        .map(|offset| offset + 4)
+        .map_err(|e| anyhow::anyhow!("a golden image without a signature: {:?}", e))
+}
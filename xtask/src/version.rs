@@ -0,0 +1,237 @@
+//! Firmware versions, given as a number or derived from `git describe` or the firmware crate's
+//! `Cargo.toml`.
+//!
+//! rustBoot compares images by their 32-bit version, so a derived `MAJOR.MINOR.PATCH` version is
+//! encoded as rustBoot's `VersionEncoding::SemVer` (see `rustBoot::version::semver`) i.e.
+//!
+//! ```text
+//! MAJOR << 24 | MINOR << 16 | PATCH
+//! ```
+//!
+//! where `MAJOR` and `MINOR` must fit in a byte and `PATCH` in 16 bits, so a later release always
+//! gets a larger version. Pre-release versions (ex: `1.2.3-rc.1`) have no such ordering and are
+//! refused. A derived version is also embedded in the signed image as a human-readable string
+//! (ex: `v1.2.3-4-gdeadbee`), see rbsigner's `--version-string`.
+//!
+//! - `git` is the nearest `v<MAJOR>.<MINOR>.<PATCH>` tag (from `git describe --tags`). The
+//!   commits since (and a dirty tree) only show in the string i.e. they don't make a newer
+//!   version, tag a release for that.
+//! - `cargo` is the firmware crate's `package.version` (ex: `boards/firmware/<board>/
+//!   updt_fw_blinky_red/Cargo.toml`), with no commits.
+
+use std::{fmt, fs, path::Path};
+
+use anyhow::{bail, Context};
+use rustBoot::{rbconstants::HDR_VERSION_STRING_MAX_LEN, version};
+use xshell::cmd;
+
+use crate::{cli::Versions, root_dir};
+
+/// A firmware version, as given on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionArg {
+    /// A version number
+    Number(u32),
+    /// Derived from `git describe --tags`
+    Git,
+    /// Derived from the firmware crate's `Cargo.toml`
+    Cargo,
+}
+
+/// A resolved firmware version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// The image's version, see the module's docs for how a derived version is encoded
+    pub number: u32,
+    /// A derived version's human-readable string
+    pub label: Option<String>,
+}
+
+/// Parses a version i.e. a number, `git` or `cargo`.
+pub fn parse_version(arg: &str) -> Result<VersionArg, anyhow::Error> {
+    match arg {
+        "git" => Ok(VersionArg::Git),
+        "cargo" => Ok(VersionArg::Cargo),
+        number => number.parse().map(VersionArg::Number).with_context(|| {
+            format!(
+                "invalid version `{}`, expected a number, `git` or `cargo`",
+                number
+            )
+        }),
+    }
+}
+
+impl fmt::Display for VersionArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionArg::Number(number) => write!(f, "{}", number),
+            VersionArg::Git => f.write_str("git"),
+            VersionArg::Cargo => f.write_str("cargo"),
+        }
+    }
+}
+
+impl VersionArg {
+    /// Resolves the version of the firmware crate in `crate_dir`.
+    pub fn resolve(&self, crate_dir: &Path) -> Result<Version, anyhow::Error> {
+        let (semver, label) = match self {
+            VersionArg::Number(number) => {
+                return Ok(Version {
+                    number: *number,
+                    label: None,
+                })
+            }
+            VersionArg::Git => {
+                let _p = xshell::pushd(crate_dir)?;
+                let describe = cmd!("git describe --tags --long --dirty --match v[0-9]*").read()?;
+                let semver = parse_describe(&describe)?;
+                (semver, describe)
+            }
+            VersionArg::Cargo => {
+                let path = crate_dir.join("Cargo.toml");
+                let cargo_toml: toml::Value = toml::from_str(&fs::read_to_string(&path)?)?;
+                let semver = cargo_toml
+                    .get("package")
+                    .and_then(|package| package.get("version"))
+                    .and_then(|version| version.as_str())
+                    .with_context(|| format!("{} has no package version", path.display()))?;
+                (semver.to_string(), format!("v{}", semver))
+            }
+        };
+        if label.len() > HDR_VERSION_STRING_MAX_LEN {
+            bail!(
+                "version `{}` is longer than {} bytes",
+                label,
+                HDR_VERSION_STRING_MAX_LEN
+            );
+        }
+        Ok(Version {
+            number: encode(&semver)?,
+            label: Some(label),
+        })
+    }
+}
+
+impl Versions {
+    /// Resolves the versions of `target`'s example firmware i.e. of its boot and update
+    /// firmware crates.
+    pub fn resolve(&self, target: &str) -> Result<(Version, Version), anyhow::Error> {
+        let firmware = root_dir().join("boards/firmware").join(target);
+        Ok((
+            self.boot_ver
+                .resolve(&firmware.join("boot_fw_blinky_green"))?,
+            self.updt_ver
+                .resolve(&firmware.join("updt_fw_blinky_red"))?,
+        ))
+    }
+}
+
+/// Returns the tag's version from `git describe --long` output
+/// (`v<semver>-<commits>-g<hash>[-dirty]`).
+fn parse_describe(describe: &str) -> Result<String, anyhow::Error> {
+    let invalid = || format!("can't parse `git describe` output `{}`", describe);
+    let describe = describe.strip_suffix("-dirty").unwrap_or(describe);
+    let mut parts = describe.rsplitn(3, '-');
+    let _hash = parts.next().with_context(invalid)?;
+    let commits = parts.next().with_context(invalid)?;
+    let tag = parts.next().with_context(invalid)?;
+    let semver = tag.strip_prefix('v').with_context(invalid)?;
+    commits.parse::<u32>().with_context(invalid)?;
+    Ok(semver.to_string())
+}
+
+/// Encodes a `MAJOR.MINOR.PATCH` version, see the module's docs.
+fn encode(semver: &str) -> Result<u32, anyhow::Error> {
+    if semver.contains('-') {
+        bail!(
+            "pre-release version `{}` can't be encoded as an image version",
+            semver
+        );
+    }
+    let invalid = || {
+        format!(
+            "version `{}` isn't MAJOR.MINOR.PATCH, with MAJOR and MINOR at most 255 and PATCH at \
+             most 65535",
+            semver
+        )
+    };
+    let mut parts = semver.split('.');
+    let (major, minor, patch) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(major), Some(minor), Some(patch), None) => (major, minor, patch),
+        _ => bail!(invalid()),
+    };
+    Ok(version::semver(
+        major.parse().with_context(invalid)?,
+        minor.parse().with_context(invalid)?,
+        patch.parse().with_context(invalid)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbsigner::curve::{import_signing_key, CurveType};
+    use rbsigner::sign::mcu_image_header;
+    use rustBoot::chain::SignedImage;
+    use rustBoot::rbconstants::HDR_IMG_TYPE_APP;
+    use rustBoot::version::{VersionEncoding, VersionPolicy};
+
+    /// Returns the version rustBoot reads from an image signed (as `rbsigner mcu-image` does)
+    /// with `version`.
+    fn signed_version(version: u32) -> u32 {
+        let sk = import_signing_key(CurveType::NistP256, &[0x11; 32]).unwrap();
+        let fw = [0xaa; 16];
+        let header = mcu_image_header(
+            &fw[..],
+            fw.len() as u32,
+            version.to_le_bytes(),
+            0,
+            HDR_IMG_TYPE_APP as u8,
+            &[],
+            &sk,
+        )
+        .unwrap();
+        let image = [&header[..], &fw].concat();
+        SignedImage::parse(&image)
+            .unwrap()
+            .get_firmware_version()
+            .unwrap()
+    }
+
+    #[test]
+    fn versions_round_trip() {
+        let releases = ["0.9.9", "1.2.3", "1.2.256", "1.3.0", "1.255.0", "2.0.0"];
+        let policies = [
+            VersionPolicy::STRICT,
+            VersionPolicy::new(VersionEncoding::SemVer),
+        ];
+        let versions = releases
+            .iter()
+            .map(|release| encode(release).unwrap())
+            .collect::<Vec<_>>();
+        for (release, version) in releases.iter().zip(&versions) {
+            assert_eq!(signed_version(*version), *version, "v{}", release);
+        }
+        for pair in versions.windows(2) {
+            let (older, newer) = (signed_version(pair[0]), signed_version(pair[1]));
+            for policy in &policies {
+                assert!(policy.permits(older, newer));
+                assert!(!policy.permits(newer, older));
+            }
+        }
+    }
+
+    #[test]
+    fn commits_since_tag() {
+        // commits since a tag don't outrank the next release
+        let describe = parse_describe("v1.2.3-5-gdeadbee-dirty").unwrap();
+        assert_eq!(encode(&describe).unwrap(), version::semver(1, 2, 3));
+        assert!(VersionPolicy::STRICT.permits(
+            signed_version(encode(&describe).unwrap()),
+            signed_version(encode("1.3.0").unwrap())
+        ));
+        assert!(encode("1.2.3-rc.1").is_err());
+        assert!(encode("1.256.0").is_err());
+        assert!(encode("1.2").is_err());
+    }
+}